@@ -0,0 +1,16 @@
+// Renders with D3D12, so there's nothing to compare elsewhere.
+#![cfg(windows)]
+
+use common::golden::GoldenTest;
+
+#[test]
+fn matches_golden_image() {
+    let comparison = GoldenTest::new(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/golden"),
+        "hello_window_clear",
+    )
+    .output_dir(env!("CARGO_TARGET_TMPDIR"))
+    .run(env!("CARGO_BIN_EXE_hello_window_clear").as_ref())
+    .unwrap_or_else(|e| panic!("{e}"));
+    println!("{comparison}");
+}
@@ -0,0 +1,447 @@
+// The frame loop every sample shares. A sample implements `Sample`, and
+// `run_sample` creates the window and the D3D12 device and swapchain (see
+// `d3d12`), then drives the sample through `run`.
+//
+// `run` only talks to the window through `Platform` and to the GPU through
+// `Backend`, so the order of calls can be checked against mocks. With
+// `--headless` there is no window: `HeadlessPlatform` stands in for it and
+// the frames are drawn to offscreen textures for a fixed number of frames.
+// F12 saves a screenshot, and `--screenshot=path` saves the last frame.
+//
+// Only `d3d12` needs Windows. Elsewhere there's no `run_sample`, but `run`
+// with `HeadlessPlatform` and `NullBackend` works the same.
+
+#[cfg(windows)]
+mod d3d12;
+
+#[cfg(windows)]
+pub use d3d12::{
+    report_live_objects, run_sample, Gpu, WindowPlatform, BACK_BUFFER_FORMAT, FRAME_COUNT,
+};
+
+use std::{error::Error, fmt, path::PathBuf, time::Duration};
+
+use crate::{
+    gfx::{
+        backend::{Null, NullDevice},
+        CommandQueue, DeferredDeleter, QueueType,
+    },
+    input::{InputEvent, InputState, Key},
+    screenshot::Screenshot,
+    time::{Clock, FrameStats, FrameTimer},
+    util::print_debug_string,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    /// Use the WARP software rasterizer instead of a hardware adapter.
+    pub warp: bool,
+    /// Render offscreen without opening a window.
+    pub headless: bool,
+    /// Exit after this many frames.
+    pub frames: Option<u64>,
+    /// Save the last frame here before exiting.
+    pub screenshot: Option<PathBuf>,
+}
+
+impl Config {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            width: 800,
+            height: 600,
+            warp: false,
+            headless: false,
+            frames: None,
+            screenshot: None,
+        }
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Applies command line flags: `-warp` or `/warp` selects WARP,
+    /// `--headless` renders without a window, `--frames=N` exits after N
+    /// frames and `--screenshot=path` saves the last one. Unknown arguments
+    /// are left for the sample.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for arg in args {
+            let arg = arg.as_ref();
+            if arg.eq_ignore_ascii_case("-warp") || arg.eq_ignore_ascii_case("/warp") {
+                self.warp = true;
+            } else if arg == "--headless" {
+                self.headless = true;
+            } else if let Some(frames) = arg.strip_prefix("--frames=") {
+                if let Ok(frames) = frames.parse() {
+                    self.frames = Some(frames);
+                }
+            } else if let Some(path) = arg.strip_prefix("--screenshot=") {
+                self.screenshot = Some(path.into());
+            }
+        }
+        self
+    }
+
+    /// How many frames to run before exiting. Headless runs have nobody to
+    /// close them, and screenshot runs are done once they have a frame, so
+    /// both stop after `DEFAULT_HEADLESS_FRAMES` unless told otherwise.
+    pub fn frame_limit(&self) -> Option<u64> {
+        match self.frames {
+            None if self.headless || self.screenshot.is_some() => Some(DEFAULT_HEADLESS_FRAMES),
+            frames => frames,
+        }
+    }
+
+    /// Where F12 saves frame `index`: the title in snake case and the frame,
+    /// in the current directory.
+    pub fn screenshot_file_name(&self, index: u64) -> PathBuf {
+        let name: String = self
+            .title
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{name}_{index:06}.png").into()
+    }
+
+    /// The title with the adapter noted, for the window.
+    pub fn window_title(&self) -> String {
+        if self.warp {
+            format!("{} (WARP)", self.title)
+        } else {
+            self.title.clone()
+        }
+    }
+}
+
+/// Enough to cycle through every back buffer.
+pub const DEFAULT_HEADLESS_FRAMES: u64 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    Input(InputEvent),
+    /// The client area changed size. Zero means minimized.
+    Resized {
+        width: u32,
+        height: u32,
+    },
+}
+
+/// What `update` gets to see each frame.
+pub struct Frame<'a> {
+    pub input: &'a InputState,
+    /// Seconds since the last frame, clamped.
+    pub dt: f32,
+    pub elapsed: Duration,
+    pub index: u64,
+}
+
+pub trait Sample<B: Backend>: Sized {
+    const TITLE: &'static str;
+
+    /// The window to open, before command line flags are applied.
+    fn config() -> Config {
+        Config::new(Self::TITLE)
+    }
+
+    fn init(config: &Config, backend: &mut B) -> Result<Self, Box<dyn Error>>;
+
+    fn update(&mut self, frame: &Frame) {
+        let _ = frame;
+    }
+
+    /// Records and submits the frame. The runner presents afterwards.
+    fn render(&mut self, backend: &mut B) -> Result<(), Box<dyn Error>>;
+
+    /// Called after the backend has resized its back buffers.
+    fn on_resize(
+        &mut self,
+        backend: &mut B,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        let _ = (backend, width, height);
+        Ok(())
+    }
+
+    /// Every event, after the runner has handled it.
+    fn on_event(&mut self, event: &Event) {
+        let _ = event;
+    }
+
+    /// Called once the GPU is idle, whether the loop ended normally or not.
+    fn shutdown(&mut self, backend: &mut B) {
+        let _ = backend;
+    }
+}
+
+pub trait Platform {
+    /// Appends what happened since the last call. Returns false once the
+    /// window has been closed.
+    fn poll_events(&mut self, events: &mut Vec<Event>) -> bool;
+
+    fn set_title(&mut self, title: &str);
+}
+
+/// The device and swapchain side of the loop.
+pub trait Backend {
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>>;
+
+    fn present(&mut self) -> Result<(), Box<dyn Error>>;
+
+    /// Blocks until the GPU has finished all submitted work.
+    fn wait_idle(&mut self);
+
+    /// Reads back the frame that was just rendered, before it's presented.
+    fn capture(&mut self) -> Result<Screenshot, Box<dyn Error>> {
+        Err("this backend can't take screenshots".into())
+    }
+}
+
+/// No window: never any events, and the loop only ends on the frame limit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeadlessPlatform;
+
+impl Platform for HeadlessPlatform {
+    fn poll_events(&mut self, _events: &mut Vec<Event>) -> bool {
+        true
+    }
+
+    fn set_title(&mut self, _title: &str) {}
+}
+
+/// The null graphics backend behind the sample loop. Frames are fenced on a
+/// direct queue and retired objects collected as with `Gpu`, but nothing is
+/// drawn.
+pub struct NullBackend {
+    pub size: (u32, u32),
+    pub frames_presented: u64,
+    device: NullDevice,
+    command_queue: CommandQueue<Null>,
+    deleter: DeferredDeleter<Null>,
+}
+
+impl NullBackend {
+    pub fn new(config: &Config) -> Self {
+        let device = NullDevice::new();
+        let command_queue = CommandQueue::new(&device, QueueType::Direct)
+            .expect("the null device always creates queues");
+        Self {
+            size: (config.width, config.height),
+            frames_presented: 0,
+            device,
+            command_queue,
+            deleter: DeferredDeleter::new(),
+        }
+    }
+
+    pub fn device(&self) -> &NullDevice {
+        &self.device
+    }
+
+    pub fn command_queue(&mut self) -> &mut CommandQueue<Null> {
+        &mut self.command_queue
+    }
+
+    pub fn deleter(&mut self) -> &mut DeferredDeleter<Null> {
+        &mut self.deleter
+    }
+}
+
+impl Backend for NullBackend {
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        self.size = (width, height);
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), Box<dyn Error>> {
+        let sync_point = self.command_queue.signal()?;
+        self.command_queue.wait_cpu(&sync_point)?;
+        self.deleter.collect(self.command_queue.fence());
+        self.frames_presented += 1;
+        Ok(())
+    }
+
+    fn wait_idle(&mut self) {
+        if let Err(e) = self.command_queue.flush() {
+            print_debug_string(&format!("failed to flush the null queue {e}"));
+        }
+        self.deleter.collect(self.command_queue.fence());
+    }
+
+    /// Black, since nothing was drawn.
+    fn capture(&mut self) -> Result<Screenshot, Box<dyn Error>> {
+        let (width, height) = self.size;
+        Ok(Screenshot::new(
+            width,
+            height,
+            vec![0; width as usize * height as usize * 4],
+        ))
+    }
+}
+
+impl Drop for NullBackend {
+    fn drop(&mut self) {
+        self.wait_idle();
+        self.deleter.flush_all();
+    }
+}
+
+/// Which step of the loop failed, for the message and the log.
+#[derive(Debug)]
+pub struct SampleError {
+    pub stage: &'static str,
+    pub source: Box<dyn Error>,
+}
+
+impl fmt::Display for SampleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.stage, self.source)
+    }
+}
+
+impl Error for SampleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+fn stage<T>(stage: &'static str, result: Result<T, Box<dyn Error>>) -> Result<T, SampleError> {
+    result.map_err(|source| SampleError { stage, source })
+}
+
+/// How often the frame statistics in the title are refreshed.
+const TITLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Runs `S` until the window closes, Escape is pressed, the frame limit is
+/// reached or a step fails.
+/// `shutdown` runs after a successful `init` no matter how the loop ends.
+pub fn run<S, P, B, C>(
+    config: &Config,
+    platform: &mut P,
+    backend: &mut B,
+    clock: C,
+) -> Result<(), SampleError>
+where
+    S: Sample<B>,
+    P: Platform,
+    B: Backend,
+    C: Clock,
+{
+    let mut sample = stage("init", S::init(config, backend))?;
+
+    let result = frame_loop(config, &mut sample, platform, backend, clock);
+
+    backend.wait_idle();
+    sample.shutdown(backend);
+    result
+}
+
+fn frame_loop<S, P, B, C>(
+    config: &Config,
+    sample: &mut S,
+    platform: &mut P,
+    backend: &mut B,
+    clock: C,
+) -> Result<(), SampleError>
+where
+    S: Sample<B>,
+    P: Platform,
+    B: Backend,
+    C: Clock,
+{
+    let mut timer = FrameTimer::with_clock(clock);
+    let mut stats = FrameStats::new(120);
+    let mut next_title = timer.clock().now() + TITLE_INTERVAL;
+    let mut input = InputState::new();
+    let mut events = Vec::new();
+    let mut buffer_size = (config.width, config.height);
+    let mut minimized = false;
+    let frame_limit = config.frame_limit();
+
+    loop {
+        events.clear();
+        input.begin_frame();
+        let open = platform.poll_events(&mut events);
+
+        for event in &events {
+            match *event {
+                Event::Input(input_event) => input.handle(input_event),
+                Event::Resized { width, height } => {
+                    minimized = width == 0 || height == 0;
+                    if !minimized && (width, height) != buffer_size {
+                        backend.wait_idle();
+                        stage("resize", backend.resize(width, height))?;
+                        stage("on_resize", sample.on_resize(backend, width, height))?;
+                        buffer_size = (width, height);
+                    }
+                }
+            }
+            sample.on_event(event);
+        }
+
+        if !open || input.was_key_pressed(Key::Escape) {
+            return Ok(());
+        }
+
+        timer.tick();
+        stats.push(timer.raw_delta());
+        let index = timer.frame_count() - 1;
+        let last_frame = frame_limit.is_some_and(|limit| timer.frame_count() >= limit);
+        sample.update(&Frame {
+            input: &input,
+            dt: timer.delta_seconds(),
+            elapsed: timer.elapsed(),
+            index,
+        });
+
+        // Minimized windows have no back buffer to draw to.
+        if !minimized {
+            stage("render", sample.render(backend))?;
+
+            // A screenshot that was asked for interactively isn't worth
+            // stopping for.
+            if input.was_key_pressed(Key::F12) {
+                let path = config.screenshot_file_name(index);
+                match backend.capture().and_then(|shot| shot.save(&path)) {
+                    Ok(()) => print_debug_string(&format!("saved {}", path.display())),
+                    Err(e) => {
+                        print_debug_string(&format!("failed to save {}: {e}", path.display()))
+                    }
+                }
+            }
+            if let (true, Some(path)) = (last_frame, &config.screenshot) {
+                stage(
+                    "screenshot",
+                    backend.capture().and_then(|shot| shot.save(path)),
+                )?;
+            }
+
+            stage("present", backend.present())?;
+        }
+
+        if timer.clock().now() >= next_title {
+            platform.set_title(&format!("{} - {stats}", config.window_title()));
+            next_title = timer.clock().now() + TITLE_INTERVAL;
+        }
+
+        if last_frame {
+            return Ok(());
+        }
+    }
+}
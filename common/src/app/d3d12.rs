@@ -0,0 +1,532 @@
+use std::{error::Error, process::ExitCode};
+
+use windows::{
+    core::Interface,
+    Win32::Graphics::{
+        Direct3D::D3D_FEATURE_LEVEL_11_0,
+        Direct3D12::{
+            D3D12CreateDevice, D3D12GetDebugInterface, ID3D12Debug, ID3D12DescriptorHeap,
+            ID3D12Device, ID3D12InfoQueue, ID3D12Resource, D3D12_CPU_DESCRIPTOR_HANDLE,
+            D3D12_DESCRIPTOR_HEAP_DESC, D3D12_DESCRIPTOR_HEAP_TYPE_RTV, D3D12_HEAP_FLAG_NONE,
+            D3D12_HEAP_TYPE_DEFAULT, D3D12_INFO_QUEUE_FILTER, D3D12_INFO_QUEUE_FILTER_DESC,
+            D3D12_MESSAGE_ID_CLEARRENDERTARGETVIEW_MISMATCHINGCLEARVALUE,
+            D3D12_MESSAGE_ID_MAP_INVALID_NULLRANGE, D3D12_MESSAGE_ID_UNMAP_INVALID_NULLRANGE,
+            D3D12_MESSAGE_SEVERITY_CORRUPTION, D3D12_MESSAGE_SEVERITY_ERROR,
+            D3D12_MESSAGE_SEVERITY_INFO, D3D12_MESSAGE_SEVERITY_WARNING, D3D12_RESOURCE_DESC,
+            D3D12_RESOURCE_DIMENSION_TEXTURE2D, D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET,
+            D3D12_RESOURCE_STATE_COMMON, D3D12_RESOURCE_STATE_PRESENT,
+        },
+        Dxgi::{
+            Common::{DXGI_FORMAT, DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_SAMPLE_DESC},
+            CreateDXGIFactory2, DXGIGetDebugInterface1, IDXGIAdapter1, IDXGIDebug1, IDXGIFactory4,
+            IDXGISwapChain3, DXGI_ADAPTER_FLAG, DXGI_ADAPTER_FLAG_NONE, DXGI_ADAPTER_FLAG_SOFTWARE,
+            DXGI_CREATE_FACTORY_DEBUG, DXGI_CREATE_FACTORY_FLAGS, DXGI_DEBUG_ALL,
+            DXGI_DEBUG_RLO_DETAIL, DXGI_DEBUG_RLO_IGNORE_INTERNAL, DXGI_MWA_NO_ALT_ENTER,
+            DXGI_PRESENT, DXGI_SWAP_CHAIN_DESC1, DXGI_SWAP_CHAIN_FLAG,
+            DXGI_SWAP_EFFECT_FLIP_DISCARD, DXGI_USAGE_RENDER_TARGET_OUTPUT,
+        },
+    },
+};
+
+use super::{run, Backend, Config, Event, HeadlessPlatform, Platform, Sample};
+use crate::{
+    gfx::{
+        backend::{BackendError, CommandList, D3d12, D3d12CommandList, D3d12Device},
+        heap_properties,
+        readback::{to_rgba8, Readback, ReadbackLayout},
+        CommandListPool, CommandQueue, DeferredDeleter, Format, QueueType, SyncPoint,
+    },
+    os::{App, Window},
+    screenshot::Screenshot,
+    time::SystemClock,
+    util::print_debug_string,
+};
+
+pub const FRAME_COUNT: u32 = 2;
+pub const BACK_BUFFER_FORMAT: DXGI_FORMAT = DXGI_FORMAT_R8G8B8A8_UNORM;
+
+/// Opens the window, creates the device and runs `S` until it exits,
+/// reporting any error and leaked D3D objects on the way out. With
+/// `--headless` no window is opened.
+pub fn run_sample<S: Sample<Gpu>>() -> ExitCode {
+    let config = S::config().args(std::env::args().skip(1));
+
+    let result = if config.headless {
+        run_headless::<S>(&config)
+    } else {
+        run_windowed::<S>(&config)
+    };
+    report_live_objects();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            print_debug_string(&format!("{}: {e}", config.title));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_windowed<S: Sample<Gpu>>(config: &Config) -> Result<(), Box<dyn Error>> {
+    let (app, window) = App::init(
+        config.window_title(),
+        (config.width as i32, config.height as i32),
+    )?;
+    let mut gpu = Gpu::new(config, &window)?;
+    let mut platform = WindowPlatform::new(app, window);
+
+    run::<S, _, _, _>(config, &mut platform, &mut gpu, SystemClock::new())?;
+    Ok(())
+}
+
+fn run_headless<S: Sample<Gpu>>(config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut gpu = Gpu::headless(config)?;
+    run::<S, _, _, _>(config, &mut HeadlessPlatform, &mut gpu, SystemClock::new())?;
+    Ok(())
+}
+
+/// The Win32 window, reporting input and client area size changes.
+pub struct WindowPlatform {
+    app: App,
+    window: Window,
+    size: (u32, u32),
+}
+
+impl WindowPlatform {
+    pub fn new(app: App, window: Window) -> Self {
+        let (width, height) = window.get_physical_size();
+        Self {
+            app,
+            window,
+            size: (width as u32, height as u32),
+        }
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+}
+
+impl Platform for WindowPlatform {
+    fn poll_events(&mut self, events: &mut Vec<Event>) -> bool {
+        let mut input = Vec::new();
+        let open = self.app.pump_messages(&mut input);
+        events.extend(input.into_iter().map(Event::Input));
+
+        // WM_SIZE is sent straight to the window procedure rather than
+        // queued, so it's simpler to look for changes here.
+        let (width, height) = self.window.get_physical_size();
+        let size = (width.max(0) as u32, height.max(0) as u32);
+        if size != self.size {
+            self.size = size;
+            events.push(Event::Resized {
+                width: size.0,
+                height: size.1,
+            });
+        }
+
+        open
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.window.set_title(title);
+    }
+}
+
+/// The device, direct queue and swapchain a sample renders with. Headless,
+/// there is no swapchain and the back buffers are plain textures.
+pub struct Gpu {
+    factory: IDXGIFactory4,
+    device: ID3D12Device,
+    command_queue: CommandQueue<D3d12>,
+    swapchain: Option<IDXGISwapChain3>,
+    rtv_heap: ID3D12DescriptorHeap,
+    rtv_descriptor_size: usize,
+    back_buffers: Vec<ID3D12Resource>,
+    frame_index: u32,
+    size: (u32, u32),
+    command_list_pool: CommandListPool,
+    deleter: DeferredDeleter<D3d12>,
+    last_submission: Option<SyncPoint<D3d12>>,
+}
+
+impl Gpu {
+    pub fn new(config: &Config, window: &Window) -> Result<Self, Box<dyn Error>> {
+        let (width, height) = window.get_physical_size();
+        let mut gpu = Self::create(config, (width as u32, height as u32))?;
+
+        let swapchain_desc = DXGI_SWAP_CHAIN_DESC1 {
+            BufferCount: FRAME_COUNT,
+            Width: gpu.size.0,
+            Height: gpu.size.1,
+            Format: BACK_BUFFER_FORMAT,
+            BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+            SwapEffect: DXGI_SWAP_EFFECT_FLIP_DISCARD,
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let swapchain: IDXGISwapChain3 = unsafe {
+            gpu.factory.CreateSwapChainForHwnd(
+                gpu.command_queue.queue().queue(),
+                window.get_handle(),
+                &swapchain_desc,
+                None,
+                None,
+            )
+        }?
+        .cast()?;
+
+        // todo: support fullscreen transitions.
+        unsafe {
+            gpu.factory
+                .MakeWindowAssociation(window.get_handle(), DXGI_MWA_NO_ALT_ENTER)
+        }?;
+
+        gpu.swapchain = Some(swapchain);
+        gpu.create_back_buffers()?;
+        Ok(gpu)
+    }
+
+    /// Renders to `config.width` by `config.height` textures instead of a
+    /// window.
+    pub fn headless(config: &Config) -> Result<Self, Box<dyn Error>> {
+        let mut gpu = Self::create(config, (config.width, config.height))?;
+        gpu.create_back_buffers()?;
+        Ok(gpu)
+    }
+
+    fn create(config: &Config, size: (u32, u32)) -> Result<Self, Box<dyn Error>> {
+        let (factory, device) = create_device(config.warp)?;
+        let command_queue = CommandQueue::new(&D3d12Device::new(&device), QueueType::Direct)?;
+
+        let rtv_heap: ID3D12DescriptorHeap = unsafe {
+            device.CreateDescriptorHeap(&D3D12_DESCRIPTOR_HEAP_DESC {
+                NumDescriptors: FRAME_COUNT,
+                Type: D3D12_DESCRIPTOR_HEAP_TYPE_RTV,
+                ..Default::default()
+            })
+        }?;
+        let rtv_descriptor_size =
+            unsafe { device.GetDescriptorHandleIncrementSize(D3D12_DESCRIPTOR_HEAP_TYPE_RTV) }
+                as usize;
+
+        let command_list_pool = CommandListPool::new(&device);
+        Ok(Self {
+            factory,
+            device,
+            command_queue,
+            swapchain: None,
+            rtv_heap,
+            rtv_descriptor_size,
+            back_buffers: Vec::new(),
+            frame_index: 0,
+            size,
+            command_list_pool,
+            deleter: DeferredDeleter::new(),
+            last_submission: None,
+        })
+    }
+
+    fn create_back_buffers(&mut self) -> windows::core::Result<()> {
+        self.back_buffers = (0..FRAME_COUNT)
+            .map(|i| match &self.swapchain {
+                Some(swapchain) => unsafe { swapchain.GetBuffer(i) },
+                None => create_offscreen_target(&self.device, self.size),
+            })
+            .collect::<windows::core::Result<_>>()?;
+
+        for (i, back_buffer) in self.back_buffers.iter().enumerate() {
+            unsafe {
+                self.device
+                    .CreateRenderTargetView(back_buffer, None, self.rtv(i as u32))
+            };
+        }
+
+        self.frame_index = match &self.swapchain {
+            Some(swapchain) => unsafe { swapchain.GetCurrentBackBufferIndex() },
+            None => 0,
+        };
+        Ok(())
+    }
+
+    fn rtv(&self, index: u32) -> D3D12_CPU_DESCRIPTOR_HANDLE {
+        D3D12_CPU_DESCRIPTOR_HANDLE {
+            ptr: unsafe { self.rtv_heap.GetCPUDescriptorHandleForHeapStart() }.ptr
+                + index as usize * self.rtv_descriptor_size,
+        }
+    }
+
+    pub fn factory(&self) -> &IDXGIFactory4 {
+        &self.factory
+    }
+
+    pub fn device(&self) -> &ID3D12Device {
+        &self.device
+    }
+
+    pub fn command_queue(&mut self) -> &mut CommandQueue<D3d12> {
+        &mut self.command_queue
+    }
+
+    pub fn deleter(&mut self) -> &mut DeferredDeleter<D3d12> {
+        &mut self.deleter
+    }
+
+    /// A reset command list for the direct queue.
+    pub fn command_list(&mut self) -> windows::core::Result<D3d12CommandList> {
+        self.command_list_pool.acquire(&self.command_queue)
+    }
+
+    /// Executes closed command lists in order and returns them to the pool.
+    pub fn submit(
+        &mut self,
+        command_lists: Vec<D3d12CommandList>,
+    ) -> Result<SyncPoint<D3d12>, BackendError> {
+        let lists: Vec<&D3d12CommandList> = command_lists.iter().collect();
+        let sync_point = self.command_queue.execute(&lists)?;
+        self.command_list_pool.retire(command_lists, &sync_point);
+        self.last_submission = Some(sync_point.clone());
+        Ok(sync_point)
+    }
+
+    pub fn back_buffer(&self) -> &ID3D12Resource {
+        &self.back_buffers[self.frame_index as usize]
+    }
+
+    pub fn back_buffer_rtv(&self) -> D3D12_CPU_DESCRIPTOR_HANDLE {
+        self.rtv(self.frame_index)
+    }
+
+    pub fn frame_index(&self) -> u32 {
+        self.frame_index
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.swapchain.is_none()
+    }
+
+    // todo: THIS IS NOT BEST PRACTICE BUT IT IS EXPEDIENT FOR NOW!
+    fn wait_for_previous_frame(&mut self) {
+        if let Some(sync_point) = &self.last_submission {
+            if let Err(e) = self.command_queue.wait_cpu(sync_point) {
+                print_debug_string(&format!("failed to wait for the previous frame {e}"));
+            }
+        }
+
+        self.deleter.collect(self.command_queue.fence());
+        if let Some(swapchain) = &self.swapchain {
+            self.frame_index = unsafe { swapchain.GetCurrentBackBufferIndex() };
+        }
+    }
+}
+
+impl Backend for Gpu {
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        // Every reference to the old buffers has to go before resizing.
+        self.back_buffers.clear();
+        if let Some(swapchain) = &self.swapchain {
+            unsafe {
+                swapchain.ResizeBuffers(
+                    FRAME_COUNT,
+                    width,
+                    height,
+                    BACK_BUFFER_FORMAT,
+                    DXGI_SWAP_CHAIN_FLAG(0),
+                )
+            }?;
+        }
+        self.size = (width, height);
+        self.create_back_buffers()?;
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), Box<dyn Error>> {
+        match &self.swapchain {
+            Some(swapchain) => unsafe { swapchain.Present(1, DXGI_PRESENT(0)) }.ok()?,
+            None => self.frame_index = (self.frame_index + 1) % FRAME_COUNT,
+        }
+        self.wait_for_previous_frame();
+        Ok(())
+    }
+
+    fn wait_idle(&mut self) {
+        self.wait_for_previous_frame();
+    }
+
+    fn capture(&mut self) -> Result<Screenshot, Box<dyn Error>> {
+        let (width, height) = self.size;
+        let layout = ReadbackLayout::new(width, height, Format(BACK_BUFFER_FORMAT.0 as u32))?;
+        let readback = Readback::new(&self.device, layout)?;
+
+        // Samples leave the back buffer ready to present.
+        let mut command_list = self.command_list()?;
+        readback.record_copy(
+            command_list.list(),
+            self.back_buffer(),
+            D3D12_RESOURCE_STATE_PRESENT,
+        );
+        command_list.close()?;
+        let sync_point = self.submit(vec![command_list])?;
+        self.command_queue.wait_cpu(&sync_point)?;
+
+        let pixels = to_rgba8(layout.format, &readback.read()?)?;
+        Ok(Screenshot::new(width, height, pixels))
+    }
+}
+
+impl Drop for Gpu {
+    fn drop(&mut self) {
+        // Make sure the GPU is done with everything before releasing it.
+        self.wait_for_previous_frame();
+        self.deleter.flush_all();
+    }
+}
+
+/// A texture standing in for a swapchain buffer. It starts in COMMON, which
+/// is the same state as PRESENT, so samples transition it the same way.
+fn create_offscreen_target(
+    device: &ID3D12Device,
+    (width, height): (u32, u32),
+) -> windows::core::Result<ID3D12Resource> {
+    let desc = D3D12_RESOURCE_DESC {
+        Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+        Width: width as u64,
+        Height: height,
+        DepthOrArraySize: 1,
+        MipLevels: 1,
+        Format: BACK_BUFFER_FORMAT,
+        SampleDesc: DXGI_SAMPLE_DESC {
+            Count: 1,
+            Quality: 0,
+        },
+        Flags: D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET,
+        ..Default::default()
+    };
+
+    let mut resource: Option<ID3D12Resource> = None;
+    unsafe {
+        device.CreateCommittedResource(
+            &heap_properties(D3D12_HEAP_TYPE_DEFAULT),
+            D3D12_HEAP_FLAG_NONE,
+            &desc,
+            D3D12_RESOURCE_STATE_COMMON,
+            None,
+            &mut resource,
+        )
+    }?;
+    resource.ok_or_else(windows::core::Error::empty)
+}
+
+fn get_hardware_adapter(factory: &IDXGIFactory4) -> windows::core::Result<IDXGIAdapter1> {
+    for i in 0.. {
+        let adapter = unsafe { factory.EnumAdapters1(i) }?;
+        let desc = unsafe { adapter.GetDesc1() }?;
+
+        if (DXGI_ADAPTER_FLAG(desc.Flags as _) & DXGI_ADAPTER_FLAG_SOFTWARE)
+            != DXGI_ADAPTER_FLAG_NONE
+        {
+            // Don't select the Basic Render Driver adapter.
+            // Pass in "/warp" on the command line if you want a software adapter.
+            continue;
+        }
+
+        // Check to see whether the adapter supports D3D12 but don't create the device yet.
+        if unsafe {
+            D3D12CreateDevice(
+                &adapter,
+                D3D_FEATURE_LEVEL_11_0,
+                std::ptr::null_mut::<Option<ID3D12Device>>(),
+            )
+        }
+        .is_ok()
+        {
+            return Ok(adapter);
+        }
+    }
+
+    unreachable!()
+}
+
+fn create_device(warp: bool) -> Result<(IDXGIFactory4, ID3D12Device), Box<dyn Error>> {
+    if cfg!(debug_assertions) {
+        unsafe {
+            let mut debug: Option<ID3D12Debug> = None;
+            if let Some(debug) = D3D12GetDebugInterface(&mut debug).ok().and(debug) {
+                debug.EnableDebugLayer();
+
+                if let Ok(dxgi_debug) = DXGIGetDebugInterface1::<IDXGIDebug1>(0) {
+                    dxgi_debug.EnableLeakTrackingForThread();
+                }
+            }
+        }
+    }
+
+    let dxgi_factory_flags = if cfg!(debug_assertions) {
+        DXGI_CREATE_FACTORY_DEBUG
+    } else {
+        DXGI_CREATE_FACTORY_FLAGS(0)
+    };
+
+    let dxgi_factory: IDXGIFactory4 = unsafe { CreateDXGIFactory2(dxgi_factory_flags) }?;
+
+    let adapter = if warp {
+        unsafe { dxgi_factory.EnumWarpAdapter() }
+    } else {
+        get_hardware_adapter(&dxgi_factory)
+    }?;
+
+    let mut device: Option<ID3D12Device> = None;
+    unsafe { D3D12CreateDevice(&adapter, D3D_FEATURE_LEVEL_11_0, &mut device) }?;
+    let device: ID3D12Device = device.ok_or("failed to create device")?;
+
+    if cfg!(debug_assertions) {
+        unsafe {
+            let info_queue = device.cast::<ID3D12InfoQueue>()?;
+            info_queue.SetBreakOnSeverity(D3D12_MESSAGE_SEVERITY_CORRUPTION, true)?;
+            info_queue.SetBreakOnSeverity(D3D12_MESSAGE_SEVERITY_ERROR, true)?;
+            info_queue.SetBreakOnSeverity(D3D12_MESSAGE_SEVERITY_WARNING, true)?;
+
+            let mut severities = [D3D12_MESSAGE_SEVERITY_INFO];
+            let mut deny_ids = [
+                D3D12_MESSAGE_ID_CLEARRENDERTARGETVIEW_MISMATCHINGCLEARVALUE,
+                D3D12_MESSAGE_ID_MAP_INVALID_NULLRANGE,
+                D3D12_MESSAGE_ID_UNMAP_INVALID_NULLRANGE,
+            ];
+
+            let filter = D3D12_INFO_QUEUE_FILTER {
+                DenyList: D3D12_INFO_QUEUE_FILTER_DESC {
+                    NumSeverities: severities.len() as u32,
+                    pSeverityList: severities.as_mut_ptr(),
+                    NumIDs: deny_ids.len() as u32,
+                    pIDList: deny_ids.as_mut_ptr(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            info_queue.PushStorageFilter(&filter)?;
+        }
+    }
+
+    Ok((dxgi_factory, device))
+}
+
+pub fn report_live_objects() {
+    unsafe {
+        if cfg!(debug_assertions) {
+            if let Ok(dxgi_debug) = DXGIGetDebugInterface1::<IDXGIDebug1>(0) {
+                let _ = dxgi_debug.ReportLiveObjects(
+                    DXGI_DEBUG_ALL,
+                    DXGI_DEBUG_RLO_DETAIL | DXGI_DEBUG_RLO_IGNORE_INTERNAL,
+                );
+            }
+        }
+    }
+}
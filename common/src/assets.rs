@@ -0,0 +1,47 @@
+// Textures from files. Every file format parses into a `Texture`: one
+// tightly packed buffer of texels and a `TextureLayout` saying where each
+// subresource sits in it. Getting that onto the GPU is the same whatever the
+// file was (see `texture`).
+
+pub mod dds;
+pub mod image;
+pub mod ktx2;
+
+mod texture;
+pub(crate) mod zlib;
+
+pub use texture::{Footprint, Subresource, Texture, TextureDesc, TextureDimension, TextureLayout};
+
+use std::{error::Error, fmt};
+
+use crate::gfx::Format;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AssetError {
+    /// The data isn't a file of the kind named.
+    WrongFormat(&'static str),
+    Truncated {
+        expected: usize,
+        actual: usize,
+    },
+    UnsupportedFormat(Format),
+    /// Valid, but using a feature that isn't implemented.
+    Unsupported(String),
+    Invalid(String),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongFormat(kind) => write!(f, "not a {kind} file"),
+            Self::Truncated { expected, actual } => {
+                write!(f, "expected {expected} bytes, got {actual}")
+            }
+            Self::UnsupportedFormat(format) => write!(f, "format {} isn't supported", format.0),
+            Self::Unsupported(what) => write!(f, "{what} isn't supported"),
+            Self::Invalid(message) => f.write_str(message),
+        }
+    }
+}
+
+impl Error for AssetError {}
@@ -0,0 +1,215 @@
+// DDS files: the legacy header, which describes its pixel format with a
+// FourCC or channel masks, and the DX10 extension, which names a DXGI format
+// and can describe arrays. Only pixel formats with a DXGI equivalent are
+// read, so the file's data is used as it is.
+
+use super::{AssetError, Texture, TextureDesc, TextureDimension, TextureLayout};
+use crate::gfx::Format;
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: usize = 124;
+const PIXEL_FORMAT_SIZE: u32 = 32;
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDSD_DEPTH: u32 = 0x80_0000;
+
+const DDPF_ALPHA: u32 = 0x2;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x2_0000;
+
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_CUBEMAP_ALL_FACES: u32 = 0xfc00;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+
+const D3D10_RESOURCE_DIMENSION_TEXTURE1D: u32 = 2;
+const D3D10_RESOURCE_DIMENSION_TEXTURE2D: u32 = 3;
+const D3D10_RESOURCE_DIMENSION_TEXTURE3D: u32 = 4;
+const D3D10_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/// `DDS_PIXELFORMAT`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct PixelFormat {
+    flags: u32,
+    four_cc: [u8; 4],
+    bit_count: u32,
+    /// Red, green, blue and alpha.
+    masks: [u32; 4],
+}
+
+impl PixelFormat {
+    /// The DXGI format a legacy header describes.
+    fn format(&self) -> Option<Format> {
+        if self.flags & DDPF_FOURCC != 0 {
+            return four_cc_format(self.four_cc);
+        }
+
+        let format = match (self.bit_count, self.masks) {
+            (32, [0xff, 0xff00, 0xff_0000, 0xff00_0000]) => Format::R8G8B8A8_UNORM,
+            (32, [0xff_0000, 0xff00, 0xff, 0xff00_0000]) => Format::B8G8R8A8_UNORM,
+            (32, [0xff_0000, 0xff00, 0xff, 0]) => Format::B8G8R8X8_UNORM,
+            // Written reversed by D3DX, and read as R10G10B10A2 by everyone.
+            (32, [0x3ff, 0xffc00, 0x3ff0_0000, 0xc000_0000]) => Format::R10G10B10A2_UNORM,
+            (32, [0xffff, 0xffff_0000, 0, 0]) => Format::R16G16_UNORM,
+            (32, [0xffff_ffff, 0, 0, 0]) => Format::R32_FLOAT,
+            (16, [0xf800, 0x7e0, 0x1f, 0]) => Format::B5G6R5_UNORM,
+            (16, [0x7c00, 0x3e0, 0x1f, 0x8000]) => Format::B5G5R5A1_UNORM,
+            (16, [0xf00, 0xf0, 0xf, 0xf000]) => Format::B4G4R4A4_UNORM,
+            (16, [0xffff, 0, 0, 0]) if self.flags & DDPF_LUMINANCE != 0 => Format::R16_UNORM,
+            (16, [0xff, 0, 0, 0xff00]) if self.flags & DDPF_LUMINANCE != 0 => Format::R8G8_UNORM,
+            (8, [0xff, 0, 0, 0]) if self.flags & DDPF_LUMINANCE != 0 => Format::R8_UNORM,
+            (8, [0, 0, 0, 0xff]) if self.flags & DDPF_ALPHA != 0 => Format::A8_UNORM,
+            _ => return None,
+        };
+        (self.flags & (DDPF_RGB | DDPF_LUMINANCE | DDPF_ALPHA) != 0).then_some(format)
+    }
+}
+
+fn four_cc_format(four_cc: [u8; 4]) -> Option<Format> {
+    let format = match &four_cc {
+        b"DXT1" => Format::BC1_UNORM,
+        // Premultiplied alpha is the same data.
+        b"DXT2" | b"DXT3" => Format::BC2_UNORM,
+        b"DXT4" | b"DXT5" => Format::BC3_UNORM,
+        b"ATI1" | b"BC4U" => Format::BC4_UNORM,
+        b"BC4S" => Format::BC4_SNORM,
+        b"ATI2" | b"BC5U" => Format::BC5_UNORM,
+        b"BC5S" => Format::BC5_SNORM,
+        // D3DFORMAT values stored in place of a FourCC.
+        _ => match u32::from_le_bytes(four_cc) {
+            36 => Format::R16G16B16A16_UNORM,
+            110 => Format::R16G16B16A16_SNORM,
+            111 => Format::R16_FLOAT,
+            112 => Format::R16G16_FLOAT,
+            113 => Format::R16G16B16A16_FLOAT,
+            114 => Format::R32_FLOAT,
+            115 => Format::R32G32_FLOAT,
+            116 => Format::R32G32B32A32_FLOAT,
+            _ => return None,
+        },
+    };
+    Some(format)
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+impl Texture {
+    pub fn from_dds(bytes: &[u8]) -> Result<Self, AssetError> {
+        if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(AssetError::WrongFormat("DDS"));
+        }
+        let header_end = MAGIC.len() + HEADER_SIZE;
+        if bytes.len() < header_end {
+            return Err(AssetError::Truncated {
+                expected: header_end,
+                actual: bytes.len(),
+            });
+        }
+
+        // Offsets are from the start of the file, past the magic.
+        let field = |index: usize| read_u32(bytes, MAGIC.len() + index * 4);
+        let size = field(0);
+        if size as usize != HEADER_SIZE {
+            return Err(AssetError::Invalid(format!(
+                "the header is {size} bytes, not {HEADER_SIZE}"
+            )));
+        }
+        let flags = field(1);
+        let height = field(2);
+        let width = field(3);
+        let depth = field(5);
+        let mip_levels = field(6).max(1);
+        if field(18) != PIXEL_FORMAT_SIZE {
+            return Err(AssetError::Invalid(format!(
+                "the pixel format is {} bytes, not {PIXEL_FORMAT_SIZE}",
+                field(18)
+            )));
+        }
+        let pixel_format = PixelFormat {
+            flags: field(19),
+            four_cc: field(20).to_le_bytes(),
+            bit_count: field(21),
+            masks: [field(22), field(23), field(24), field(25)],
+        };
+        let caps2 = field(27);
+
+        let mut data_start = header_end;
+        let desc = if pixel_format.flags & DDPF_FOURCC != 0 && &pixel_format.four_cc == b"DX10" {
+            data_start += DX10_HEADER_SIZE;
+            if bytes.len() < data_start {
+                return Err(AssetError::Truncated {
+                    expected: data_start,
+                    actual: bytes.len(),
+                });
+            }
+            let dx10 = |index: usize| read_u32(bytes, header_end + index * 4);
+            let format = Format(dx10(0));
+            let misc_flags = dx10(2);
+            let array_size = dx10(3);
+            let cube = misc_flags & D3D10_RESOURCE_MISC_TEXTURECUBE != 0;
+            let (dimension, height, depth) = match dx10(1) {
+                D3D10_RESOURCE_DIMENSION_TEXTURE1D => (TextureDimension::Texture1d, 1, 1),
+                D3D10_RESOURCE_DIMENSION_TEXTURE2D => (TextureDimension::Texture2d, height, 1),
+                D3D10_RESOURCE_DIMENSION_TEXTURE3D => {
+                    (TextureDimension::Texture3d, height, depth.max(1))
+                }
+                dimension => {
+                    return Err(AssetError::Invalid(format!(
+                        "resource dimension {dimension} isn't a texture"
+                    )))
+                }
+            };
+            TextureDesc {
+                dimension,
+                format,
+                width,
+                height,
+                depth,
+                array_size: if cube {
+                    array_size.saturating_mul(6)
+                } else {
+                    array_size
+                },
+                mip_levels,
+                cube,
+            }
+        } else {
+            let format = pixel_format.format().ok_or_else(|| {
+                AssetError::Unsupported(format!("the legacy pixel format {pixel_format:?}"))
+            })?;
+            let cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+            if cube && caps2 & DDSCAPS2_CUBEMAP_ALL_FACES != DDSCAPS2_CUBEMAP_ALL_FACES {
+                return Err(AssetError::Unsupported(
+                    "a cubemap without all six faces".into(),
+                ));
+            }
+            let volume = caps2 & DDSCAPS2_VOLUME != 0 && flags & DDSD_DEPTH != 0;
+            TextureDesc {
+                dimension: if volume {
+                    TextureDimension::Texture3d
+                } else {
+                    TextureDimension::Texture2d
+                },
+                format,
+                width,
+                height,
+                depth: if volume { depth } else { 1 },
+                array_size: if cube { 6 } else { 1 },
+                mip_levels,
+                cube,
+            }
+        };
+
+        let layout = TextureLayout::new(desc)?;
+        let data_end = data_start + layout.size as usize;
+        if bytes.len() < data_end {
+            return Err(AssetError::Truncated {
+                expected: data_end,
+                actual: bytes.len(),
+            });
+        }
+        Texture::new(layout, bytes[data_start..data_end].to_vec())
+    }
+}
@@ -0,0 +1,245 @@
+// PNG, JPEG, TGA and Radiance HDR images, decoded to RGBA pixels, and mip
+// chains for them built on the CPU. The 8-bit formats decode to `Rgba8` with
+// the file's values as they are, 16-bit PNGs to `Rgba16f` and HDR to
+// `Rgba32f`; `convert` and `as_srgb` get from there to what the texture
+// should be. `Screenshot` shares the PNG decoder.
+
+mod hdr;
+mod jpeg;
+mod mips;
+pub(crate) mod png;
+mod tga;
+
+pub use mips::MipFilter;
+
+use std::{error::Error, path::Path};
+
+use self::png::PngPixels;
+use super::{AssetError, Texture, TextureDesc, TextureLayout};
+use crate::{
+    gfx::Format,
+    math::{f16_to_f32, f32_to_f16, linear_to_srgb, srgb_to_linear},
+};
+
+/// The largest side `decode` takes, which is D3D12's texture limit.
+pub(crate) const MAX_IMAGE_SIZE: u32 = 16384;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    Rgba8,
+    /// 8-bit channels holding sRGB encoded color. Alpha is linear.
+    Rgba8Srgb,
+    Rgba16f,
+    Rgba32f,
+}
+
+impl PixelFormat {
+    pub fn format(self) -> Format {
+        match self {
+            Self::Rgba8 => Format::R8G8B8A8_UNORM,
+            Self::Rgba8Srgb => Format::R8G8B8A8_UNORM_SRGB,
+            Self::Rgba16f => Format::R16G16B16A16_FLOAT,
+            Self::Rgba32f => Format::R32G32B32A32_FLOAT,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgba8 | Self::Rgba8Srgb => 4,
+            Self::Rgba16f => 8,
+            Self::Rgba32f => 16,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFile {
+    Png,
+    Jpeg,
+    Tga,
+    /// Radiance RGBE.
+    Hdr,
+}
+
+impl ImageFile {
+    /// From the extension, ignoring case.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(Self::Png),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "tga" => Some(Self::Tga),
+            "hdr" => Some(Self::Hdr),
+            _ => None,
+        }
+    }
+}
+
+/// Pixels in rows top to bottom, with no padding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, format: PixelFormat, data: Vec<u8>) -> Self {
+        assert_eq!(
+            data.len(),
+            width as usize * height as usize * format.bytes_per_pixel(),
+            "expected {width}x{height} {format:?} pixels"
+        );
+        Self {
+            width,
+            height,
+            format,
+            data,
+        }
+    }
+
+    /// Stores linear RGBA values as `format`, clamping to what it can hold.
+    pub fn from_linear(width: u32, height: u32, format: PixelFormat, pixels: &[[f32; 4]]) -> Self {
+        let unorm = |value: f32| (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        let mut data = Vec::with_capacity(pixels.len() * format.bytes_per_pixel());
+        for &[r, g, b, a] in pixels {
+            match format {
+                PixelFormat::Rgba8 => data.extend([r, g, b, a].map(unorm)),
+                PixelFormat::Rgba8Srgb => {
+                    data.extend([r, g, b].map(|value| unorm(linear_to_srgb(value.max(0.0)))));
+                    data.push(unorm(a));
+                }
+                PixelFormat::Rgba16f => data.extend(
+                    [r, g, b, a]
+                        .iter()
+                        .flat_map(|&value| f32_to_f16(value).to_le_bytes()),
+                ),
+                PixelFormat::Rgba32f => {
+                    data.extend([r, g, b, a].iter().flat_map(|value| value.to_le_bytes()))
+                }
+            }
+        }
+        Self::new(width, height, format, data)
+    }
+
+    /// 8-bit pixels, which is what PNG, JPEG and TGA decode to.
+    fn from_rgba8(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        Self::new(width, height, PixelFormat::Rgba8, pixels)
+    }
+
+    /// The linear value of a pixel.
+    pub fn pixel(&self, x: u32, y: u32) -> [f32; 4] {
+        let size = self.format.bytes_per_pixel();
+        let i = (y as usize * self.width as usize + x as usize) * size;
+        decode_pixel(self.format, &self.data[i..i + size])
+    }
+
+    /// Every pixel's linear value, in order.
+    pub fn to_linear(&self) -> Vec<[f32; 4]> {
+        self.data
+            .chunks_exact(self.format.bytes_per_pixel())
+            .map(|texel| decode_pixel(self.format, texel))
+            .collect()
+    }
+
+    /// The same colors stored as `format`.
+    pub fn convert(&self, format: PixelFormat) -> Self {
+        if format == self.format {
+            return self.clone();
+        }
+        Self::from_linear(self.width, self.height, format, &self.to_linear())
+    }
+
+    /// Says 8-bit data is sRGB encoded without touching it. Color images from
+    /// PNG, JPEG and TGA nearly always are, though they decode as `Rgba8`.
+    pub fn as_srgb(mut self) -> Self {
+        assert_eq!(
+            self.format,
+            PixelFormat::Rgba8,
+            "only 8-bit data can be sRGB"
+        );
+        self.format = PixelFormat::Rgba8Srgb;
+        self
+    }
+
+    pub fn decode(bytes: &[u8], file: ImageFile) -> Result<Self, AssetError> {
+        let image = match file {
+            ImageFile::Png => Self::from_png(bytes)?,
+            ImageFile::Jpeg => Self::from_jpeg(bytes)?,
+            ImageFile::Tga => Self::from_tga(bytes)?,
+            ImageFile::Hdr => Self::from_hdr(bytes)?,
+        };
+        Ok(image)
+    }
+
+    /// Picks the format from the extension.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let file = ImageFile::from_path(path)
+            .ok_or_else(|| format!("{} isn't a .png, .jpg, .tga or .hdr file", path.display()))?;
+        Ok(Self::decode(&std::fs::read(path)?, file)?)
+    }
+
+    /// 16-bit channels become half floats, which keep more of their
+    /// precision than 8 bits would.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, AssetError> {
+        if !bytes.starts_with(b"\x89PNG") {
+            return Err(AssetError::WrongFormat("PNG"));
+        }
+        let png = png::decode(bytes).map_err(|message| AssetError::Invalid(message.into()))?;
+        check_size(png.width, png.height)?;
+        Ok(match png.pixels {
+            PngPixels::Rgba8(pixels) => Self::from_rgba8(png.width, png.height, pixels),
+            PngPixels::Rgba16(samples) => {
+                let data = samples
+                    .iter()
+                    .flat_map(|&s| f32_to_f16(s as f32 / 65535.0).to_le_bytes())
+                    .collect();
+                Self::new(png.width, png.height, PixelFormat::Rgba16f, data)
+            }
+        })
+    }
+
+    /// A 2D texture of this image, with mips made by `mip_filter` down to
+    /// 1x1 or just the one level.
+    pub fn to_texture(&self, mip_filter: Option<MipFilter>) -> Result<Texture, AssetError> {
+        let mips = match mip_filter {
+            Some(filter) => self.mip_chain(filter),
+            None => vec![self.clone()],
+        };
+        let desc = TextureDesc {
+            mip_levels: mips.len() as u32,
+            ..TextureDesc::texture_2d(self.format.format(), self.width, self.height)
+        };
+        let layout = TextureLayout::new(desc)?;
+        Texture::new(layout, mips.into_iter().flat_map(|mip| mip.data).collect())
+    }
+}
+
+fn decode_pixel(format: PixelFormat, texel: &[u8]) -> [f32; 4] {
+    let unorm = |value: u8| value as f32 / 255.0;
+    match format {
+        PixelFormat::Rgba8 => [0, 1, 2, 3].map(|i| unorm(texel[i])),
+        PixelFormat::Rgba8Srgb => [
+            srgb_to_linear(unorm(texel[0])),
+            srgb_to_linear(unorm(texel[1])),
+            srgb_to_linear(unorm(texel[2])),
+            unorm(texel[3]),
+        ],
+        PixelFormat::Rgba16f => {
+            [0, 1, 2, 3].map(|i| f16_to_f32(u16::from_le_bytes([texel[i * 2], texel[i * 2 + 1]])))
+        }
+        PixelFormat::Rgba32f => {
+            [0, 1, 2, 3].map(|i| f32::from_le_bytes(texel[i * 4..i * 4 + 4].try_into().unwrap()))
+        }
+    }
+}
+
+/// Keeps decoders from allocating for sizes no texture could have.
+fn check_size(width: u32, height: u32) -> Result<(), AssetError> {
+    if width == 0 || height == 0 || width.max(height) > MAX_IMAGE_SIZE {
+        return Err(AssetError::Unsupported(format!("a {width}x{height} image")));
+    }
+    Ok(())
+}
@@ -0,0 +1,157 @@
+// Radiance HDR: a text header, then RGBE pixels with a shared exponent,
+// usually run-length encoded a channel at a time. Decodes to `Rgba32f` with
+// an alpha of one. Only the standard orientations, rows top or bottom first
+// with columns left to right, are read.
+
+use super::{check_size, AssetError, Image, PixelFormat};
+
+/// Scanlines this long or longer can use the per-channel encoding.
+const MIN_RLE_WIDTH: usize = 8;
+const MAX_RLE_WIDTH: usize = 0x7fff;
+
+fn rgbe_to_rgba([r, g, b, e]: [u8; 4]) -> [f32; 4] {
+    if e == 0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+    let scale = 2f32.powi(e as i32 - 136);
+    [r as f32 * scale, g as f32 * scale, b as f32 * scale, 1.0]
+}
+
+fn invalid(message: &str) -> AssetError {
+    AssetError::Invalid(format!("invalid HDR: {message}"))
+}
+
+/// One scanline, in either encoding, and the bytes after it.
+fn read_scanline<'a>(bytes: &'a [u8], scanline: &mut [[u8; 4]]) -> Result<&'a [u8], AssetError> {
+    let width = scanline.len();
+    let ends_early = || invalid("the pixel data ends early");
+    if let [2, 2, hi, lo, ..] = *bytes {
+        if (MIN_RLE_WIDTH..=MAX_RLE_WIDTH).contains(&width)
+            && (hi as usize) << 8 | lo as usize == width
+        {
+            // Each channel in turn, as runs and literal spans.
+            let mut rest = &bytes[4..];
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let (&count, tail) = rest.split_first().ok_or_else(ends_early)?;
+                    let (run, literal) = if count > 128 {
+                        ((count - 128) as usize, false)
+                    } else {
+                        (count as usize, true)
+                    };
+                    if run == 0 || x + run > width {
+                        return Err(invalid("a run goes past the end of the scanline"));
+                    }
+                    let values = if literal {
+                        tail.get(..run).ok_or_else(ends_early)?
+                    } else {
+                        tail.get(..1).ok_or_else(ends_early)?
+                    };
+                    for (i, pixel) in scanline[x..x + run].iter_mut().enumerate() {
+                        pixel[channel] = values[if literal { i } else { 0 }];
+                    }
+                    rest = &tail[values.len()..];
+                    x += run;
+                }
+            }
+            return Ok(rest);
+        }
+    }
+
+    // Flat pixels, where (1, 1, 1, n) repeats the previous pixel n times,
+    // shifted left 8 bits more for each repeat in a row.
+    let mut rest = bytes;
+    let (mut x, mut shift) = (0, 0);
+    while x < width {
+        let pixel = rest.get(..4).ok_or_else(ends_early)?;
+        let pixel = [pixel[0], pixel[1], pixel[2], pixel[3]];
+        rest = &rest[4..];
+        if let [1, 1, 1, count] = pixel {
+            let previous = x
+                .checked_sub(1)
+                .map(|previous| scanline[previous])
+                .ok_or_else(|| invalid("a repeat comes before any pixel"))?;
+            let count = (count as usize)
+                .checked_shl(shift)
+                .filter(|&count| x + count <= width)
+                .ok_or_else(|| invalid("a repeat goes past the end of the scanline"))?;
+            scanline[x..x + count].fill(previous);
+            x += count;
+            shift += 8;
+        } else {
+            scanline[x] = pixel;
+            x += 1;
+            shift = 0;
+        }
+    }
+    Ok(rest)
+}
+
+impl Image {
+    pub fn from_hdr(bytes: &[u8]) -> Result<Self, AssetError> {
+        if !bytes.starts_with(b"#?RADIANCE\n") && !bytes.starts_with(b"#?RGBE\n") {
+            return Err(AssetError::WrongFormat("Radiance HDR"));
+        }
+
+        // Variables up to a blank line, then the resolution.
+        let mut lines = bytes.split(|&c| c == b'\n');
+        let mut header_size = 0;
+        let mut next_line = || {
+            let line = lines.next()?;
+            header_size += line.len() + 1;
+            Some(line)
+        };
+        next_line();
+        loop {
+            let line = next_line().ok_or_else(|| invalid("the header doesn't end"))?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix(b"FORMAT=") {
+                if format != b"32-bit_rle_rgbe" {
+                    return Err(AssetError::Unsupported(format!(
+                        "the HDR pixel format {}",
+                        String::from_utf8_lossy(format)
+                    )));
+                }
+            }
+        }
+        let resolution = next_line().ok_or_else(|| invalid("there's no resolution"))?;
+        let resolution = String::from_utf8_lossy(resolution);
+        let (bottom_up, height, width) = match *resolution.split_whitespace().collect::<Vec<_>>() {
+            [y @ ("-Y" | "+Y"), height, "+X", width] => (
+                y == "+Y",
+                height.parse::<u32>().ok(),
+                width.parse::<u32>().ok(),
+            ),
+            _ => {
+                return Err(AssetError::Unsupported(format!(
+                    "the HDR orientation {resolution}"
+                )))
+            }
+        };
+        let (Some(width), Some(height)) = (width, height) else {
+            return Err(invalid("the resolution isn't numbers"));
+        };
+        check_size(width, height)?;
+
+        let (width, height) = (width as usize, height as usize);
+        let mut rest = bytes.get(header_size..).unwrap_or_default();
+        let mut scanline = vec![[0; 4]; width];
+        let mut pixels = vec![[0.0; 4]; width * height];
+        for row in 0..height {
+            rest = read_scanline(rest, &mut scanline)?;
+            let y = if bottom_up { height - 1 - row } else { row };
+            for (pixel, &rgbe) in pixels[y * width..(y + 1) * width].iter_mut().zip(&scanline) {
+                *pixel = rgbe_to_rgba(rgbe);
+            }
+        }
+        Ok(Self::from_linear(
+            width as u32,
+            height as u32,
+            PixelFormat::Rgba32f,
+            &pixels,
+        ))
+    }
+}
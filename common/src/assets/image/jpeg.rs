@@ -0,0 +1,547 @@
+// Baseline JPEG: 8-bit samples, Huffman coded, sequential DCT, grey or
+// YCbCr with any chroma subsampling, restart intervals, and interleaved or
+// one-component scans. Subsampled chroma is upsampled by repeating samples.
+// Progressive, lossless, arithmetic coded and CMYK files are rejected.
+
+use super::{check_size, AssetError, Image};
+
+const SOI: u8 = 0xd8;
+const EOI: u8 = 0xd9;
+const SOF0: u8 = 0xc0;
+const SOF1: u8 = 0xc1;
+const DHT: u8 = 0xc4;
+const SOS: u8 = 0xda;
+const DQT: u8 = 0xdb;
+const DRI: u8 = 0xdd;
+const RST0: u8 = 0xd0;
+const RST7: u8 = 0xd7;
+
+/// Where each coefficient of a zigzag ordered block goes.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+fn invalid(message: &str) -> AssetError {
+    AssetError::Invalid(format!("invalid JPEG: {message}"))
+}
+
+/// A canonical Huffman code, decoded a bit at a time.
+#[derive(Clone, Default)]
+struct Huffman {
+    /// How many codes there are of each length from 1 to 16.
+    counts: [u8; 16],
+    symbols: Vec<u8>,
+}
+
+impl Huffman {
+    fn decode(&self, bits: &mut BitReader) -> Result<u8, AssetError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts {
+            code |= bits.bit() as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("a Huffman code isn't in its table"))
+    }
+}
+
+/// Reads entropy coded data most significant bit first, skipping the zero
+/// after each stuffed 0xff. At a marker it stops and reads ones.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> u32 {
+        if self.count == 0 {
+            self.buffer = match self.data.get(self.position..self.position + 2) {
+                Some([0xff, 0]) => {
+                    self.position += 2;
+                    0xff
+                }
+                Some([0xff, _]) => 0xff,
+                _ => match self.data.get(self.position) {
+                    Some(&byte) => {
+                        self.position += 1;
+                        byte as u32
+                    }
+                    None => 0xff,
+                },
+            };
+            self.count = 8;
+        }
+        self.count -= 1;
+        (self.buffer >> self.count) & 1
+    }
+
+    fn bits(&mut self, count: u8) -> u32 {
+        (0..count).fold(0, |value, _| value << 1 | self.bit())
+    }
+
+    /// A value coded as `size` bits, where ones in the top bit are positive.
+    fn signed(&mut self, size: u8) -> i32 {
+        if size == 0 {
+            return 0;
+        }
+        let value = self.bits(size) as i32;
+        if value < 1 << (size - 1) {
+            value - (1 << size) + 1
+        } else {
+            value
+        }
+    }
+
+    /// Drops the rest of the byte and the restart marker after it.
+    fn restart(&mut self) -> Result<(), AssetError> {
+        self.count = 0;
+        match self.data.get(self.position..self.position + 2) {
+            Some(&[0xff, marker]) if (RST0..=RST7).contains(&marker) => {
+                self.position += 2;
+                Ok(())
+            }
+            _ => Err(invalid("a restart marker is missing")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quantization: usize,
+}
+
+/// A component's samples, in whole blocks covering whole MCUs.
+struct Plane {
+    width: usize,
+    samples: Vec<u8>,
+}
+
+struct Frame {
+    width: usize,
+    height: usize,
+    components: Vec<Component>,
+    planes: Vec<Plane>,
+    max_h: usize,
+    max_v: usize,
+}
+
+impl Frame {
+    fn mcus(&self) -> (usize, usize) {
+        (
+            self.width.div_ceil(8 * self.max_h),
+            self.height.div_ceil(8 * self.max_v),
+        )
+    }
+}
+
+/// Cosines for the inverse DCT, with the 1/sqrt(2) for the first basis.
+fn idct_table() -> [[f32; 8]; 8] {
+    let mut table = [[0.0; 8]; 8];
+    for (x, row) in table.iter_mut().enumerate() {
+        for (u, value) in row.iter_mut().enumerate() {
+            let scale = if u == 0 { 0.5f32.sqrt() } else { 1.0 };
+            *value =
+                scale * ((2 * x + 1) as f32 * u as f32 * std::f32::consts::PI / 16.0).cos() / 2.0;
+        }
+    }
+    table
+}
+
+/// Rows then columns, each a 1D inverse DCT, then level shifted.
+fn idct(coefficients: &[f32; 64], table: &[[f32; 8]; 8], out: &mut [u8], stride: usize) {
+    let mut rows = [0.0; 64];
+    for y in 0..8 {
+        for x in 0..8 {
+            rows[y * 8 + x] = (0..8).map(|u| table[x][u] * coefficients[y * 8 + u]).sum();
+        }
+    }
+    for x in 0..8 {
+        for y in 0..8 {
+            let value: f32 = (0..8).map(|v| table[y][v] * rows[v * 8 + x]).sum();
+            out[y * stride + x] = (value + 128.0).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+struct Decoder {
+    quantization: [[u16; 64]; 4],
+    dc_tables: [Huffman; 4],
+    ac_tables: [Huffman; 4],
+    restart_interval: usize,
+    frame: Option<Frame>,
+    idct: [[f32; 8]; 8],
+}
+
+impl Decoder {
+    fn define_quantization(&mut self, mut segment: &[u8]) -> Result<(), AssetError> {
+        while let Some((&info, rest)) = segment.split_first() {
+            let (precision, id) = (info >> 4, (info & 0xf) as usize);
+            let size = if precision == 0 { 64 } else { 128 };
+            let values = rest.get(..size).ok_or_else(|| invalid("a DQT is short"))?;
+            let table = self
+                .quantization
+                .get_mut(id)
+                .ok_or_else(|| invalid("a quantization table id is over 3"))?;
+            for (i, value) in table.iter_mut().enumerate() {
+                *value = if precision == 0 {
+                    values[i] as u16
+                } else {
+                    u16::from_be_bytes([values[i * 2], values[i * 2 + 1]])
+                };
+            }
+            segment = &rest[size..];
+        }
+        Ok(())
+    }
+
+    fn define_huffman(&mut self, mut segment: &[u8]) -> Result<(), AssetError> {
+        while let Some((&info, rest)) = segment.split_first() {
+            let (class, id) = (info >> 4, (info & 0xf) as usize);
+            let counts: [u8; 16] = rest
+                .get(..16)
+                .ok_or_else(|| invalid("a DHT is short"))?
+                .try_into()
+                .unwrap();
+            let total = counts.iter().map(|&count| count as usize).sum::<usize>();
+            let symbols = rest
+                .get(16..16 + total)
+                .ok_or_else(|| invalid("a DHT is short"))?;
+            let tables = match class {
+                0 => &mut self.dc_tables,
+                1 => &mut self.ac_tables,
+                _ => return Err(invalid("a Huffman table class is over 1")),
+            };
+            *tables
+                .get_mut(id)
+                .ok_or_else(|| invalid("a Huffman table id is over 3"))? = Huffman {
+                counts,
+                symbols: symbols.to_vec(),
+            };
+            segment = &rest[16 + total..];
+        }
+        Ok(())
+    }
+
+    fn start_frame(&mut self, segment: &[u8]) -> Result<(), AssetError> {
+        let [precision, h0, h1, w0, w1, count, ref rest @ ..] = *segment else {
+            return Err(invalid("a frame header is short"));
+        };
+        if precision != 8 {
+            return Err(AssetError::Unsupported(format!("{precision}-bit JPEGs")));
+        }
+        if self.frame.is_some() {
+            return Err(invalid("there's more than one frame"));
+        }
+        let (width, height) = (u16::from_be_bytes([w0, w1]), u16::from_be_bytes([h0, h1]));
+        check_size(width as u32, height as u32)?;
+        if !matches!(count, 1 | 3) {
+            return Err(AssetError::Unsupported(format!(
+                "JPEGs with {count} components"
+            )));
+        }
+
+        let mut components = Vec::new();
+        for spec in rest.chunks_exact(3).take(count as usize) {
+            let (h, v) = ((spec[1] >> 4) as usize, (spec[1] & 0xf) as usize);
+            if !(1..=4).contains(&h) || !(1..=4).contains(&v) || spec[2] > 3 {
+                return Err(invalid("a component's sampling or table is out of range"));
+            }
+            components.push(Component {
+                id: spec[0],
+                h,
+                v,
+                quantization: spec[2] as usize,
+            });
+        }
+        if components.len() != count as usize {
+            return Err(invalid("a frame header is short"));
+        }
+
+        let max_h = components.iter().map(|c| c.h).max().unwrap();
+        let max_v = components.iter().map(|c| c.v).max().unwrap();
+        let mut frame = Frame {
+            width: width as usize,
+            height: height as usize,
+            components,
+            planes: Vec::new(),
+            max_h,
+            max_v,
+        };
+        let (mcus_x, mcus_y) = frame.mcus();
+        frame.planes = frame
+            .components
+            .iter()
+            .map(|c| {
+                let width = mcus_x * c.h * 8;
+                Plane {
+                    width,
+                    samples: vec![0; width * mcus_y * c.v * 8],
+                }
+            })
+            .collect();
+        self.frame = Some(frame);
+        Ok(())
+    }
+
+    fn decode_block(
+        &self,
+        bits: &mut BitReader,
+        component: &Component,
+        tables: (usize, usize),
+        prediction: &mut i32,
+        out: &mut [u8],
+        stride: usize,
+    ) -> Result<(), AssetError> {
+        let quantization = &self.quantization[component.quantization];
+        let mut coefficients = [0.0; 64];
+
+        let size = self.dc_tables[tables.0].decode(bits)?;
+        if size > 11 {
+            return Err(invalid("a DC difference is too big"));
+        }
+        *prediction += bits.signed(size);
+        coefficients[0] = *prediction as f32 * quantization[0] as f32;
+
+        let mut k = 1;
+        while k < 64 {
+            let symbol = self.ac_tables[tables.1].decode(bits)?;
+            let (run, size) = ((symbol >> 4) as usize, symbol & 0xf);
+            if size == 0 {
+                if run == 15 {
+                    k += 16;
+                    continue;
+                }
+                // End of block.
+                break;
+            }
+            k += run;
+            if k > 63 {
+                return Err(invalid("a block has more than 64 coefficients"));
+            }
+            coefficients[ZIGZAG[k]] = bits.signed(size) as f32 * quantization[k] as f32;
+            k += 1;
+        }
+
+        idct(&coefficients, &self.idct, out, stride);
+        Ok(())
+    }
+
+    /// Decodes a scan and returns how far into `data` it went.
+    fn decode_scan(&mut self, header: &[u8], data: &[u8]) -> Result<usize, AssetError> {
+        let mut frame = self
+            .frame
+            .take()
+            .ok_or_else(|| invalid("a scan comes before the frame"))?;
+        let result = self.decode_scan_into(&mut frame, header, data);
+        self.frame = Some(frame);
+        result
+    }
+
+    fn decode_scan_into(
+        &self,
+        frame: &mut Frame,
+        header: &[u8],
+        data: &[u8],
+    ) -> Result<usize, AssetError> {
+        let Some((&count, rest)) = header.split_first() else {
+            return Err(invalid("a scan header is short"));
+        };
+        let specs = rest
+            .get(..count as usize * 2)
+            .ok_or_else(|| invalid("a scan header is short"))?;
+        let mut scan = Vec::new();
+        for spec in specs.chunks_exact(2) {
+            let index = frame
+                .components
+                .iter()
+                .position(|c| c.id == spec[0])
+                .ok_or_else(|| invalid("a scan names a component the frame doesn't have"))?;
+            let tables = ((spec[1] >> 4) as usize, (spec[1] & 0xf) as usize);
+            if tables.0 > 3 || tables.1 > 3 {
+                return Err(invalid("a scan's Huffman table id is over 3"));
+            }
+            scan.push((index, tables));
+        }
+        if scan.is_empty() {
+            return Err(invalid("a scan has no components"));
+        }
+
+        // An MCU is every component's blocks for one area, except that a
+        // scan of one component goes block by block over just its own area.
+        let (units_x, units_y) = match *scan {
+            [(index, _)] => {
+                let c = &frame.components[index];
+                (
+                    (frame.width * c.h).div_ceil(frame.max_h).div_ceil(8),
+                    (frame.height * c.v).div_ceil(frame.max_v).div_ceil(8),
+                )
+            }
+            _ => frame.mcus(),
+        };
+        let single = scan.len() == 1;
+
+        let mut bits = BitReader {
+            data,
+            position: 0,
+            buffer: 0,
+            count: 0,
+        };
+        let mut predictions = vec![0; scan.len()];
+        for unit in 0..units_x * units_y {
+            if self.restart_interval != 0 && unit != 0 && unit % self.restart_interval == 0 {
+                bits.restart()?;
+                predictions.fill(0);
+            }
+            let (unit_x, unit_y) = (unit % units_x, unit / units_x);
+            for (&(index, tables), prediction) in scan.iter().zip(&mut predictions) {
+                let component = frame.components[index];
+                let (blocks_x, blocks_y) = if single {
+                    (1, 1)
+                } else {
+                    (component.h, component.v)
+                };
+                let plane = &mut frame.planes[index];
+                for block_y in 0..blocks_y {
+                    for block_x in 0..blocks_x {
+                        let x = (unit_x * blocks_x + block_x) * 8;
+                        let y = (unit_y * blocks_y + block_y) * 8;
+                        let start = y * plane.width + x;
+                        self.decode_block(
+                            &mut bits,
+                            &component,
+                            tables,
+                            prediction,
+                            &mut plane.samples[start..],
+                            plane.width,
+                        )?;
+                    }
+                }
+            }
+        }
+        Ok(bits.position)
+    }
+}
+
+/// Where the entropy coded data starting at `data` ends: the first marker
+/// that isn't a restart.
+fn entropy_coded_length(data: &[u8]) -> usize {
+    let mut i = 0;
+    while i + 1 < data.len() {
+        if data[i] == 0xff && data[i + 1] != 0 && !(RST0..=RST7).contains(&data[i + 1]) {
+            return i;
+        }
+        i += 1;
+    }
+    data.len()
+}
+
+fn ycbcr_to_rgb(y: u8, cb: u8, cr: u8) -> [u8; 3] {
+    let (y, cb, cr) = (y as f32, cb as f32 - 128.0, cr as f32 - 128.0);
+    let channel = |value: f32| value.round().clamp(0.0, 255.0) as u8;
+    [
+        channel(y + 1.402 * cr),
+        channel(y - 0.344_136 * cb - 0.714_136 * cr),
+        channel(y + 1.772 * cb),
+    ]
+}
+
+impl Image {
+    pub fn from_jpeg(bytes: &[u8]) -> Result<Self, AssetError> {
+        if !bytes.starts_with(&[0xff, SOI]) {
+            return Err(AssetError::WrongFormat("JPEG"));
+        }
+        let mut decoder = Decoder {
+            quantization: [[0; 64]; 4],
+            dc_tables: Default::default(),
+            ac_tables: Default::default(),
+            restart_interval: 0,
+            frame: None,
+            idct: idct_table(),
+        };
+
+        let mut rest = &bytes[2..];
+        loop {
+            // Any number of 0xff can pad before a marker.
+            let padding = rest.iter().take_while(|&&byte| byte == 0xff).count();
+            if padding == 0 {
+                return Err(invalid("expected a marker"));
+            }
+            let Some(&marker) = rest.get(padding) else {
+                return Err(invalid("the file ends before its end marker"));
+            };
+            rest = &rest[padding + 1..];
+            if marker == EOI {
+                break;
+            }
+
+            let length = rest
+                .get(..2)
+                .map(|length| u16::from_be_bytes([length[0], length[1]]) as usize)
+                .filter(|&length| length >= 2)
+                .ok_or_else(|| invalid("a segment length is missing"))?;
+            let segment = rest
+                .get(2..length)
+                .ok_or_else(|| invalid("a segment is longer than the file"))?;
+            rest = &rest[length..];
+            match marker {
+                SOF0 | SOF1 => decoder.start_frame(segment)?,
+                DHT => decoder.define_huffman(segment)?,
+                DQT => decoder.define_quantization(segment)?,
+                DRI => {
+                    let [hi, lo] = *segment else {
+                        return Err(invalid("a DRI is the wrong size"));
+                    };
+                    decoder.restart_interval = u16::from_be_bytes([hi, lo]) as usize;
+                }
+                SOS => {
+                    let length = entropy_coded_length(rest);
+                    decoder.decode_scan(segment, &rest[..length])?;
+                    rest = &rest[length..];
+                }
+                0xc2 | 0xc6 | 0xca | 0xce => {
+                    return Err(AssetError::Unsupported("progressive JPEGs".into()))
+                }
+                0xc3 | 0xc5 | 0xc7 | 0xc9 | 0xcb | 0xcd | 0xcf => {
+                    return Err(AssetError::Unsupported(
+                        "lossless, hierarchical and arithmetic coded JPEGs".into(),
+                    ))
+                }
+                // APPn, comments and anything else that can be skipped.
+                _ => {}
+            }
+        }
+
+        let frame = decoder.frame.ok_or_else(|| invalid("there's no frame"))?;
+        let (width, height) = (frame.width, frame.height);
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        // The sample of component `i` covering pixel (x, y).
+        let sample = |i: usize, x: usize, y: usize| {
+            let (component, plane) = (&frame.components[i], &frame.planes[i]);
+            let (x, y) = (x * component.h / frame.max_h, y * component.v / frame.max_v);
+            plane.samples[y * plane.width + x]
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let rgb = match frame.components.len() {
+                    1 => [sample(0, x, y); 3],
+                    _ => ycbcr_to_rgb(sample(0, x, y), sample(1, x, y), sample(2, x, y)),
+                };
+                pixels.extend_from_slice(&rgb);
+                pixels.push(255);
+            }
+        }
+        Ok(Self::from_rgba8(width as u32, height as u32, pixels))
+    }
+}
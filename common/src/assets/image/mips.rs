@@ -0,0 +1,148 @@
+use super::Image;
+
+/// Kaiser window parameters, as NVIDIA's texture tools use for mips.
+const KAISER_WIDTH: f32 = 3.0;
+const KAISER_ALPHA: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MipFilter {
+    /// Averages the texels each smaller texel covers.
+    Box,
+    /// A sinc windowed by a Kaiser window three smaller texels either side.
+    /// Sharper than `Box`, but it can ring around hard edges.
+    Kaiser,
+}
+
+impl Image {
+    /// This image followed by each mip down to 1x1. Filtering happens on
+    /// linear values, so sRGB images are decoded first and mips of mips are
+    /// made from unrounded values. Ringing below zero, or above one for
+    /// alpha, is clamped.
+    pub fn mip_chain(&self, filter: MipFilter) -> Vec<Image> {
+        let mut mips = vec![self.clone()];
+        let (mut width, mut height) = (self.width, self.height);
+        let mut pixels = self.to_linear();
+        while width > 1 || height > 1 {
+            let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+            pixels = resample(&pixels, width, height, next_width, next_height, filter);
+            (width, height) = (next_width, next_height);
+            mips.push(Image::from_linear(width, height, self.format, &pixels));
+        }
+        mips
+    }
+}
+
+fn resample(
+    pixels: &[[f32; 4]],
+    width: u32,
+    height: u32,
+    new_width: u32,
+    new_height: u32,
+    filter: MipFilter,
+) -> Vec<[f32; 4]> {
+    let (width, height) = (width as usize, height as usize);
+    let (new_width, new_height) = (new_width as usize, new_height as usize);
+
+    // Rows first, then columns.
+    let columns = weights(width, new_width, filter);
+    let mut rows = vec![[0.0; 4]; new_width * height];
+    for y in 0..height {
+        for (x, taps) in columns.iter().enumerate() {
+            rows[y * new_width + x] = sum(taps, |i| pixels[y * width + i]);
+        }
+    }
+    let row_weights = weights(height, new_height, filter);
+    let mut resampled = vec![[0.0; 4]; new_width * new_height];
+    for (y, taps) in row_weights.iter().enumerate() {
+        for x in 0..new_width {
+            resampled[y * new_width + x] = sum(taps, |i| rows[i * new_width + x]);
+        }
+    }
+
+    for [r, g, b, a] in &mut resampled {
+        *r = r.max(0.0);
+        *g = g.max(0.0);
+        *b = b.max(0.0);
+        *a = a.clamp(0.0, 1.0);
+    }
+    resampled
+}
+
+fn sum(taps: &[(usize, f32)], pixel: impl Fn(usize) -> [f32; 4]) -> [f32; 4] {
+    let mut total = [0.0; 4];
+    for &(i, weight) in taps {
+        for (total, value) in total.iter_mut().zip(pixel(i)) {
+            *total += value * weight;
+        }
+    }
+    total
+}
+
+/// For each texel of a row shrunk from `size` to `new_size`, the texels it
+/// comes from and their weights, which add up to one. Texels past the edges
+/// repeat the edge.
+fn weights(size: usize, new_size: usize, filter: MipFilter) -> Vec<Vec<(usize, f32)>> {
+    if size == new_size {
+        return (0..size).map(|i| vec![(i, 1.0)]).collect();
+    }
+    let scale = size as f32 / new_size as f32;
+    (0..new_size)
+        .map(|i| {
+            let mut taps = match filter {
+                MipFilter::Box => {
+                    let (start, end) = (i as f32 * scale, (i + 1) as f32 * scale);
+                    (start.floor() as usize..end.ceil() as usize)
+                        .map(|j| (j, end.min(j as f32 + 1.0) - start.max(j as f32)))
+                        .collect()
+                }
+                MipFilter::Kaiser => {
+                    let center = (i as f32 + 0.5) * scale;
+                    let radius = KAISER_WIDTH * scale;
+                    let first = (center - radius).floor() as isize;
+                    let last = (center + radius).ceil() as isize;
+                    (first..last)
+                        .map(|j| {
+                            // In smaller texels, measured at the texel center.
+                            let x = (j as f32 + 0.5 - center) / scale;
+                            (j.clamp(0, size as isize - 1) as usize, kaiser(x))
+                        })
+                        .collect::<Vec<_>>()
+                }
+            };
+            let total: f32 = taps.iter().map(|&(_, weight)| weight).sum();
+            for (_, weight) in &mut taps {
+                *weight /= total;
+            }
+            taps
+        })
+        .collect()
+}
+
+fn kaiser(x: f32) -> f32 {
+    let t = x / KAISER_WIDTH;
+    if t.abs() >= 1.0 {
+        return 0.0;
+    }
+    sinc(x) * bessel_i0(KAISER_ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(KAISER_ALPHA)
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * std::f32::consts::PI;
+        x.sin() / x
+    }
+}
+
+/// The zeroth order modified Bessel function of the first kind, from its
+/// power series.
+fn bessel_i0(x: f32) -> f32 {
+    let (mut sum, mut term, mut k) = (1.0, 1.0, 1.0);
+    while term > sum * 1e-8 {
+        term *= (x / (2.0 * k)) * (x / (2.0 * k));
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
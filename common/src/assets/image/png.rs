@@ -0,0 +1,207 @@
+// PNG decoding for `Image` and `Screenshot`: any bit depth and color type,
+// palettes included, without interlacing. Up to 8 bits a channel decodes to
+// RGBA8; 16-bit images keep every bit, so callers choose what to narrow them
+// to.
+
+use crate::assets::zlib;
+
+pub(crate) enum PngPixels {
+    Rgba8(Vec<u8>),
+    Rgba16(Vec<u16>),
+}
+
+pub(crate) struct Png {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: PngPixels,
+}
+
+pub(crate) fn decode(bytes: &[u8]) -> Result<Png, &'static str> {
+    let mut rest = bytes
+        .strip_prefix(b"\x89PNG\r\n\x1a\n")
+        .ok_or("not a PNG")?;
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency = None;
+    let mut compressed = Vec::new();
+    loop {
+        let (kind, data);
+        (kind, data, rest) = read_chunk(rest)?;
+        match kind {
+            b"IHDR" => header = Some(data),
+            b"PLTE" => palette = data,
+            b"tRNS" => transparency = Some(data),
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or("PNG has no header")?;
+    let [w0, w1, w2, w3, h0, h1, h2, h3, bit_depth, color_type, compression, filter, interlace] =
+        *header
+    else {
+        return Err("PNG header is the wrong size");
+    };
+    let width = u32::from_be_bytes([w0, w1, w2, w3]);
+    let height = u32::from_be_bytes([h0, h1, h2, h3]);
+    if compression != 0 || filter != 0 {
+        return Err("unknown PNG compression or filter method");
+    }
+    if interlace != 0 {
+        return Err("interlaced PNGs aren't supported");
+    }
+    let (channels, depths): (usize, &[u8]) = match color_type {
+        0 => (1, &[1, 2, 4, 8, 16]),
+        2 => (3, &[8, 16]),
+        3 => (1, &[1, 2, 4, 8]),
+        4 => (2, &[8, 16]),
+        6 => (4, &[8, 16]),
+        _ => return Err("unknown PNG color type"),
+    };
+    if !depths.contains(&bit_depth) {
+        return Err("PNG bit depth doesn't go with its color type");
+    }
+    if color_type == 3 && palette.is_empty() {
+        return Err("PNG has no palette");
+    }
+
+    let mut scanlines = zlib::decompress(&compressed)?;
+    let bits_per_pixel = channels * bit_depth as usize;
+    let stride = (width as usize * bits_per_pixel).div_ceil(8);
+    if scanlines.len() != height as usize * (stride + 1) {
+        return Err("PNG has the wrong amount of pixel data");
+    }
+    // Filters work on whole bytes, even when pixels are smaller.
+    unfilter(&mut scanlines, stride, bits_per_pixel.div_ceil(8))?;
+
+    let depth = bit_depth as usize;
+    let max = ((1u32 << depth) - 1) as u16;
+    // tRNS names one transparent color, or alphas for palette entries.
+    let transparent = |samples: &[u16]| {
+        transparency.is_some_and(|key| {
+            key.len() == samples.len() * 2
+                && samples
+                    .iter()
+                    .zip(key.chunks_exact(2))
+                    .all(|(&sample, key)| sample == u16::from_be_bytes([key[0], key[1]]))
+        })
+    };
+
+    // Samples at the file's depth, widened to 8 bits at the end if that's
+    // enough.
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    let mut texel = [0u16; 4];
+    for row in scanlines.chunks_exact(stride + 1) {
+        let row = &row[1..];
+        let sample = |i: usize| match depth {
+            16 => u16::from_be_bytes([row[i * 2], row[i * 2 + 1]]),
+            8 => row[i] as u16,
+            _ => {
+                let bit = i * depth;
+                (row[bit / 8] >> (8 - depth - bit % 8)) as u16 & max
+            }
+        };
+        for x in 0..width as usize {
+            let texel = &mut texel[..channels];
+            for (channel, value) in texel.iter_mut().enumerate() {
+                *value = sample(x * channels + channel);
+            }
+            let opaque = if transparent(texel) { 0 } else { max };
+            rgba.extend_from_slice(&match (color_type, &*texel) {
+                (3, &[index]) => {
+                    let index = index as usize;
+                    let rgb = palette
+                        .get(index * 3..index * 3 + 3)
+                        .ok_or("PNG pixel is outside the palette")?;
+                    let alpha = transparency
+                        .and_then(|alphas| alphas.get(index))
+                        .copied()
+                        .unwrap_or(255);
+                    [rgb[0], rgb[1], rgb[2], alpha].map(u16::from)
+                }
+                (_, &[l]) => [l, l, l, opaque],
+                (_, &[l, a]) => [l, l, l, a],
+                (_, &[r, g, b]) => [r, g, b, opaque],
+                (_, &[r, g, b, a]) => [r, g, b, a],
+                _ => unreachable!(),
+            });
+        }
+    }
+
+    let pixels = match depth {
+        16 => PngPixels::Rgba16(rgba),
+        // Palette entries are 8-bit already.
+        _ if color_type == 3 => PngPixels::Rgba8(rgba.into_iter().map(|s| s as u8).collect()),
+        _ => PngPixels::Rgba8(
+            rgba.into_iter()
+                .map(|s| (s as u32 * 255 / max as u32) as u8)
+                .collect(),
+        ),
+    };
+    Ok(Png {
+        width,
+        height,
+        pixels,
+    })
+}
+
+type Chunk<'a> = (&'a [u8; 4], &'a [u8], &'a [u8]);
+
+/// The type and data of the first chunk, and what follows it.
+fn read_chunk(bytes: &[u8]) -> Result<Chunk<'_>, &'static str> {
+    let truncated = "PNG is truncated";
+    let length = bytes.get(..4).ok_or(truncated)?;
+    let length = u32::from_be_bytes(length.try_into().unwrap()) as usize;
+    let chunk = bytes.get(4..8 + length).ok_or(truncated)?;
+    let crc = bytes.get(8 + length..12 + length).ok_or(truncated)?;
+    if u32::from_be_bytes(crc.try_into().unwrap()) != zlib::crc32(chunk) {
+        return Err("PNG chunk checksum doesn't match");
+    }
+    Ok((
+        chunk[..4].try_into().unwrap(),
+        &chunk[4..],
+        &bytes[12 + length..],
+    ))
+}
+
+/// Undoes PNG's per-row filters in place, leaving each row's filter byte.
+fn unfilter(scanlines: &mut [u8], stride: usize, bpp: usize) -> Result<(), &'static str> {
+    let mut previous = vec![0; stride];
+    for row in scanlines.chunks_exact_mut(stride + 1) {
+        let (filter, row) = row.split_first_mut().unwrap();
+        for i in 0..stride {
+            let left = if i >= bpp { row[i - bpp] } else { 0 };
+            let up = previous[i];
+            let up_left = if i >= bpp { previous[i - bpp] } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err("unknown PNG row filter"),
+            };
+            row[i] = row[i].wrapping_add(predicted);
+        }
+        previous.copy_from_slice(row);
+    }
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
@@ -0,0 +1,161 @@
+// Truevision TGA: 15, 16, 24 and 32-bit color, 8-bit grey with or without
+// alpha, and 8-bit color mapped images, each raw or run-length encoded.
+
+use super::{check_size, AssetError, Image};
+
+const HEADER_SIZE: usize = 18;
+
+const COLOR_MAPPED: u8 = 1;
+const TRUE_COLOR: u8 = 2;
+const GREY: u8 = 3;
+/// Added to the types above for run-length encoding.
+const RLE: u8 = 8;
+
+const DESCRIPTOR_ALPHA_BITS: u8 = 0x0f;
+const DESCRIPTOR_RIGHT_TO_LEFT: u8 = 0x10;
+const DESCRIPTOR_TOP_TO_BOTTOM: u8 = 0x20;
+
+/// BGR(A) as stored, or a little-endian ARGB1555 value.
+fn color_to_rgba(texel: &[u8], alpha_bits: u8) -> [u8; 4] {
+    match *texel {
+        [lo, hi] => {
+            let bits = u16::from_le_bytes([lo, hi]);
+            let channel = |shift: u16| {
+                let value = (bits >> shift) & 0x1f;
+                ((value << 3) | (value >> 2)) as u8
+            };
+            // Lots of writers leave the attribute bit clear on opaque images,
+            // so it only counts when the descriptor says there's alpha.
+            let alpha = if alpha_bits == 0 || bits & 0x8000 != 0 {
+                255
+            } else {
+                0
+            };
+            [channel(10), channel(5), channel(0), alpha]
+        }
+        [b, g, r] => [r, g, b, 255],
+        [b, g, r, a] => [r, g, b, if alpha_bits == 0 { 255 } else { a }],
+        _ => unreachable!("color texels are 2 to 4 bytes"),
+    }
+}
+
+impl Image {
+    pub fn from_tga(bytes: &[u8]) -> Result<Self, AssetError> {
+        let Some(header) = bytes.get(..HEADER_SIZE) else {
+            return Err(AssetError::Truncated {
+                expected: HEADER_SIZE,
+                actual: bytes.len(),
+            });
+        };
+        let u16_at = |i: usize| u16::from_le_bytes([header[i], header[i + 1]]);
+        let id_length = header[0] as usize;
+        let has_color_map = header[1];
+        let image_type = header[2];
+        let (map_first, map_length, map_bits) = (u16_at(3), u16_at(5) as usize, header[7]);
+        let (width, height) = (u16_at(12) as u32, u16_at(14) as u32);
+        let (pixel_bits, descriptor) = (header[16], header[17]);
+        // TGA has no magic number, so this is the best check there is.
+        if has_color_map > 1 || !matches!(image_type & !RLE, COLOR_MAPPED | TRUE_COLOR | GREY) {
+            return Err(AssetError::WrongFormat("TGA"));
+        }
+        check_size(width, height)?;
+
+        let color_type = image_type & !RLE;
+        let valid = match color_type {
+            COLOR_MAPPED => has_color_map == 1 && pixel_bits == 8,
+            TRUE_COLOR => matches!(pixel_bits, 15 | 16 | 24 | 32),
+            _ => matches!(pixel_bits, 8 | 16),
+        };
+        if !valid {
+            return Err(AssetError::Unsupported(format!(
+                "{pixel_bits}-bit TGAs of type {image_type}"
+            )));
+        }
+        let alpha_bits = descriptor & DESCRIPTOR_ALPHA_BITS;
+        let texel_size = (pixel_bits as usize).div_ceil(8);
+
+        let mut offset = HEADER_SIZE + id_length;
+        let mut palette = Vec::new();
+        if has_color_map == 1 {
+            if !matches!(map_bits, 15 | 16 | 24 | 32) {
+                return Err(AssetError::Unsupported(format!(
+                    "{map_bits}-bit TGA color maps"
+                )));
+            }
+            let entry_size = (map_bits as usize).div_ceil(8);
+            let end = offset + map_length * entry_size;
+            let map = bytes.get(offset..end).ok_or(AssetError::Truncated {
+                expected: end,
+                actual: bytes.len(),
+            })?;
+            palette = map
+                .chunks_exact(entry_size)
+                .map(|entry| color_to_rgba(entry, alpha_bits))
+                .collect();
+            offset = end;
+        }
+
+        let pixel_count = width as usize * height as usize;
+        let mut texels = Vec::with_capacity(pixel_count * texel_size);
+        if image_type & RLE != 0 {
+            // Packets: a count byte, then one texel repeated or count texels.
+            let truncated = AssetError::Invalid("the TGA's pixel data ends early".into());
+            while texels.len() < pixel_count * texel_size {
+                let count = *bytes.get(offset).ok_or(truncated.clone())?;
+                let run = (count & 0x7f) as usize + 1;
+                offset += 1;
+                if count & 0x80 != 0 {
+                    let texel = bytes
+                        .get(offset..offset + texel_size)
+                        .ok_or(truncated.clone())?;
+                    for _ in 0..run {
+                        texels.extend_from_slice(texel);
+                    }
+                    offset += texel_size;
+                } else {
+                    let raw = bytes
+                        .get(offset..offset + run * texel_size)
+                        .ok_or(truncated.clone())?;
+                    texels.extend_from_slice(raw);
+                    offset += raw.len();
+                }
+            }
+            // Packets may run across rows but not past the image.
+            texels.truncate(pixel_count * texel_size);
+        } else {
+            let end = offset + pixel_count * texel_size;
+            let raw = bytes.get(offset..end).ok_or(AssetError::Truncated {
+                expected: end,
+                actual: bytes.len(),
+            })?;
+            texels.extend_from_slice(raw);
+        }
+
+        let mut pixels = vec![0; pixel_count * 4];
+        let (width, height) = (width as usize, height as usize);
+        for (i, texel) in texels.chunks_exact(texel_size).enumerate() {
+            let (mut x, mut y) = (i % width, i / width);
+            if descriptor & DESCRIPTOR_RIGHT_TO_LEFT != 0 {
+                x = width - 1 - x;
+            }
+            // Bottom to top unless the descriptor says otherwise.
+            if descriptor & DESCRIPTOR_TOP_TO_BOTTOM == 0 {
+                y = height - 1 - y;
+            }
+            let rgba = match (color_type, texel) {
+                (COLOR_MAPPED, &[index]) => *(index as usize)
+                    .checked_sub(map_first as usize)
+                    .and_then(|index| palette.get(index))
+                    .ok_or_else(|| {
+                        AssetError::Invalid("a TGA pixel is outside the color map".into())
+                    })?,
+                (GREY, &[l]) => [l, l, l, 255],
+                (GREY, &[l, a]) => [l, l, l, a],
+                _ => color_to_rgba(texel, alpha_bits),
+            };
+            let start = (y * width + x) * 4;
+            pixels[start..start + 4].copy_from_slice(&rgba);
+        }
+        Ok(Self::from_rgba8(width as u32, height as u32, pixels))
+    }
+}
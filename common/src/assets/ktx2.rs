@@ -0,0 +1,397 @@
+// KTX2 files: a header, an index of where each mip level sits, a data format
+// descriptor, key/value metadata and the levels themselves, smallest first in
+// the file. Levels may be supercompressed with zstd or zlib, which is undone
+// on load. Basis Universal payloads, ETC1S under BasisLZ and UASTC, are
+// transcoded to BC1 or BC7 on load too.
+
+mod basis;
+pub mod dfd;
+mod zstd;
+
+pub use dfd::{BasisCodec, DataFormatDescriptor, Sample};
+
+use std::borrow::Cow;
+
+use super::zlib;
+use super::{AssetError, Texture, TextureDesc, TextureDimension, TextureLayout};
+use crate::gfx::Format;
+
+pub const IDENTIFIER: &[u8; 12] = b"\xabKTX 20\xbb\r\n\x1a\n";
+const HEADER_SIZE: usize = 80;
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Supercompression {
+    None,
+    BasisLz,
+    Zstd,
+    Zlib,
+}
+
+impl Supercompression {
+    fn from_scheme(scheme: u32) -> Result<Self, AssetError> {
+        match scheme {
+            0 => Ok(Self::None),
+            1 => Ok(Self::BasisLz),
+            2 => Ok(Self::Zstd),
+            3 => Ok(Self::Zlib),
+            _ => Err(AssetError::Unsupported(format!(
+                "KTX2 supercompression scheme {scheme}"
+            ))),
+        }
+    }
+}
+
+/// One entry of the level index, with its bytes as stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Level<'a> {
+    pub data: &'a [u8],
+    /// Zero for BasisLZ, which doesn't record it.
+    pub uncompressed_length: u64,
+}
+
+/// A parsed KTX2 file, borrowing its level data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ktx2<'a> {
+    /// A `VkFormat`, or zero when only the DFD describes the texels.
+    pub vk_format: u32,
+    pub type_size: u32,
+    /// Zero height means 1D and zero depth anything but a volume.
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    /// Zero for a texture that isn't an array.
+    pub layer_count: u32,
+    pub face_count: u32,
+    /// Zero asks for mips to be generated, with just the top level stored.
+    pub level_count: u32,
+    pub supercompression: Supercompression,
+    /// Largest first, unlike the data they point at.
+    pub levels: Vec<Level<'a>>,
+    pub dfd: DataFormatDescriptor,
+    /// Keys are UTF-8 without their terminator. Values are as stored, with
+    /// any terminator they have.
+    pub key_values: Vec<(String, &'a [u8])>,
+    pub supercompression_global_data: &'a [u8],
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// The bytes `offset..offset + length`, as a KTX2 index gives them.
+fn section(bytes: &[u8], offset: u64, length: u64) -> Result<&[u8], AssetError> {
+    let end = offset
+        .checked_add(length)
+        .filter(|&end| end <= bytes.len() as u64);
+    match end {
+        Some(end) => Ok(&bytes[offset as usize..end as usize]),
+        None => Err(AssetError::Truncated {
+            expected: offset.saturating_add(length).min(usize::MAX as u64) as usize,
+            actual: bytes.len(),
+        }),
+    }
+}
+
+fn parse_key_values(bytes: &[u8]) -> Result<Vec<(String, &[u8])>, AssetError> {
+    let invalid = || AssetError::Invalid("invalid KTX2 key/value data".into());
+    let mut key_values = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let length = rest.get(..4).ok_or_else(invalid)?;
+        let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+        let entry = rest.get(4..4 + length).ok_or_else(invalid)?;
+        let key_end = entry.iter().position(|&c| c == 0).ok_or_else(invalid)?;
+        let key = std::str::from_utf8(&entry[..key_end]).map_err(|_| invalid())?;
+        key_values.push((key.to_owned(), &entry[key_end + 1..]));
+        // Entries are padded to four bytes, though the last may not be.
+        rest = rest
+            .get((4 + length).next_multiple_of(4)..)
+            .unwrap_or_default();
+    }
+    Ok(key_values)
+}
+
+/// The DXGI format for a `VkFormat`, for the formats D3D12 shares.
+pub fn vk_format_to_dxgi(vk_format: u32) -> Option<Format> {
+    let format = match vk_format {
+        4 => Format::B5G6R5_UNORM,
+        8 => Format::B5G5R5A1_UNORM,
+        9 => Format::R8_UNORM,
+        16 => Format::R8G8_UNORM,
+        37 => Format::R8G8B8A8_UNORM,
+        41 => Format::R8G8B8A8_UINT,
+        43 => Format::R8G8B8A8_UNORM_SRGB,
+        44 => Format::B8G8R8A8_UNORM,
+        50 => Format::B8G8R8A8_UNORM_SRGB,
+        64 => Format::R10G10B10A2_UNORM,
+        70 => Format::R16_UNORM,
+        74 => Format::R16_UINT,
+        76 => Format::R16_FLOAT,
+        77 => Format::R16G16_UNORM,
+        83 => Format::R16G16_FLOAT,
+        91 => Format::R16G16B16A16_UNORM,
+        92 => Format::R16G16B16A16_SNORM,
+        97 => Format::R16G16B16A16_FLOAT,
+        98 => Format::R32_UINT,
+        99 => Format::R32_SINT,
+        100 => Format::R32_FLOAT,
+        101 => Format::R32G32_UINT,
+        102 => Format::R32G32_SINT,
+        103 => Format::R32G32_FLOAT,
+        104 => Format::R32G32B32_UINT,
+        105 => Format::R32G32B32_SINT,
+        106 => Format::R32G32B32_FLOAT,
+        107 => Format::R32G32B32A32_UINT,
+        108 => Format::R32G32B32A32_SINT,
+        109 => Format::R32G32B32A32_FLOAT,
+        122 => Format::R11G11B10_FLOAT,
+        126 => Format::D32_FLOAT,
+        // BC1 with and without alpha are the same data.
+        131 | 133 => Format::BC1_UNORM,
+        132 | 134 => Format::BC1_UNORM_SRGB,
+        135 => Format::BC2_UNORM,
+        136 => Format::BC2_UNORM_SRGB,
+        137 => Format::BC3_UNORM,
+        138 => Format::BC3_UNORM_SRGB,
+        139 => Format::BC4_UNORM,
+        140 => Format::BC4_SNORM,
+        141 => Format::BC5_UNORM,
+        142 => Format::BC5_SNORM,
+        143 => Format::BC6H_UF16,
+        144 => Format::BC6H_SF16,
+        145 => Format::BC7_UNORM,
+        146 => Format::BC7_UNORM_SRGB,
+        // VK_FORMAT_A4R4G4B4_UNORM_PACK16 and VK_FORMAT_A8_UNORM_KHR.
+        1_000_340_000 => Format::B4G4R4A4_UNORM,
+        1_000_470_001 => Format::A8_UNORM,
+        _ => return None,
+    };
+    Some(format)
+}
+
+impl<'a> Ktx2<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, AssetError> {
+        if !bytes.starts_with(IDENTIFIER) {
+            return Err(AssetError::WrongFormat("KTX2"));
+        }
+        if bytes.len() < HEADER_SIZE {
+            return Err(AssetError::Truncated {
+                expected: HEADER_SIZE,
+                actual: bytes.len(),
+            });
+        }
+
+        let field = |index: usize| read_u32(bytes, IDENTIFIER.len() + index * 4);
+        let supercompression = Supercompression::from_scheme(field(8))?;
+        let level_count = field(7);
+        let stored_levels = level_count.max(1) as usize;
+        let index_end = HEADER_SIZE + stored_levels * LEVEL_INDEX_ENTRY_SIZE;
+        if bytes.len() < index_end {
+            return Err(AssetError::Truncated {
+                expected: index_end,
+                actual: bytes.len(),
+            });
+        }
+
+        let levels = (0..stored_levels)
+            .map(|level| {
+                let entry = HEADER_SIZE + level * LEVEL_INDEX_ENTRY_SIZE;
+                Ok(Level {
+                    data: section(bytes, read_u64(bytes, entry), read_u64(bytes, entry + 8))?,
+                    uncompressed_length: read_u64(bytes, entry + 16),
+                })
+            })
+            .collect::<Result<_, AssetError>>()?;
+        let dfd = section(bytes, field(9) as u64, field(10) as u64)?;
+        let key_values = section(bytes, field(11) as u64, field(12) as u64)?;
+        let supercompression_global_data = section(
+            bytes,
+            read_u64(bytes, IDENTIFIER.len() + 13 * 4),
+            read_u64(bytes, IDENTIFIER.len() + 15 * 4),
+        )?;
+
+        Ok(Self {
+            vk_format: field(0),
+            type_size: field(1),
+            width: field(2),
+            height: field(3),
+            depth: field(4),
+            layer_count: field(5),
+            face_count: field(6),
+            level_count,
+            supercompression,
+            levels,
+            dfd: DataFormatDescriptor::parse(dfd)?,
+            key_values: parse_key_values(key_values)?,
+            supercompression_global_data,
+        })
+    }
+
+    /// The format from `vk_format` when it's set, which the DFD has to agree
+    /// with, or from the DFD alone. Basis payloads give the format they're
+    /// transcoded to.
+    pub fn format(&self) -> Result<Format, AssetError> {
+        if let Some(format) = self.dfd.transcode_format() {
+            return Ok(format);
+        }
+        let from_dfd = self.dfd.format();
+        match (vk_format_to_dxgi(self.vk_format), from_dfd) {
+            (Some(format), Some(from_dfd)) if format != from_dfd => {
+                Err(AssetError::Invalid(format!(
+                    "the KTX2 DFD says {} but VkFormat {} is {}",
+                    from_dfd.0, self.vk_format, format.0
+                )))
+            }
+            (Some(format), _) | (None, Some(format)) => Ok(format),
+            (None, None) => Err(AssetError::Unsupported(format!(
+                "the KTX2 VkFormat {} and color model {}",
+                self.vk_format, self.dfd.color_model
+            ))),
+        }
+    }
+
+    pub fn desc(&self) -> Result<TextureDesc, AssetError> {
+        let format = self.format()?;
+        let dimension = match (self.height, self.depth) {
+            (0, 0) => TextureDimension::Texture1d,
+            (_, 0) => TextureDimension::Texture2d,
+            _ => TextureDimension::Texture3d,
+        };
+        let cube = match self.face_count {
+            1 => false,
+            6 => true,
+            faces => {
+                return Err(AssetError::Invalid(format!(
+                    "a KTX2 texture can't have {faces} faces"
+                )))
+            }
+        };
+        Ok(TextureDesc {
+            dimension,
+            format,
+            width: self.width,
+            height: self.height.max(1),
+            depth: self.depth.max(1),
+            array_size: self.layer_count.max(1).saturating_mul(self.face_count),
+            mip_levels: self.levels.len() as u32,
+            cube,
+        })
+    }
+
+    /// The images in a level, for Basis payloads that are coded an image at
+    /// a time.
+    fn level_images(&self, level: usize) -> basis::LevelImages {
+        let size = |extent: u32| (extent >> level).max(1);
+        basis::LevelImages {
+            width: size(self.width),
+            height: size(self.height),
+            count: self.layer_count.max(1) as usize
+                * self.face_count as usize
+                * size(self.depth) as usize,
+        }
+    }
+
+    /// A level's bytes with any supercompression undone and any Basis
+    /// payload transcoded to `format`.
+    pub fn level_data(&self, level: usize) -> Result<Cow<'a, [u8]>, AssetError> {
+        let Some(&Level {
+            data,
+            uncompressed_length,
+        }) = self.levels.get(level)
+        else {
+            return Err(AssetError::Invalid(format!(
+                "the KTX2 has no mip {level}, only {}",
+                self.levels.len()
+            )));
+        };
+        let invalid =
+            |message: &str| AssetError::Invalid(format!("mip {level} of the KTX2: {message}"));
+        let codec = self.dfd.basis_codec();
+        if (codec == Some(BasisCodec::Etc1s))
+            != (self.supercompression == Supercompression::BasisLz)
+        {
+            return Err(AssetError::Invalid(
+                "KTX2 ETC1S payloads need BasisLZ supercompression, and only they can use it"
+                    .into(),
+            ));
+        }
+        let data = match self.supercompression {
+            Supercompression::None => Cow::Borrowed(data),
+            Supercompression::Zstd => {
+                Cow::Owned(zstd::decompress(data, uncompressed_length as usize).map_err(invalid)?)
+            }
+            Supercompression::Zlib => Cow::Owned(zlib::decompress(data).map_err(invalid)?),
+            Supercompression::BasisLz => {
+                // Images are numbered through every level, largest first.
+                let first_image = (0..level).map(|level| self.level_images(level).count).sum();
+                let image_count = first_image
+                    + (level..self.levels.len())
+                        .map(|level| self.level_images(level).count)
+                        .sum::<usize>();
+                let global =
+                    basis::GlobalData::parse(self.supercompression_global_data, image_count)
+                        .map_err(invalid)?;
+                let data = global
+                    .transcode_etc1s(
+                        data,
+                        first_image,
+                        self.level_images(level),
+                        self.dfd.basis_has_alpha(),
+                    )
+                    .map_err(invalid)?;
+                return Ok(Cow::Owned(data));
+            }
+        };
+        if data.len() as u64 != uncompressed_length {
+            return Err(AssetError::Invalid(format!(
+                "mip {level} of the KTX2 is {} bytes, not {uncompressed_length}",
+                data.len()
+            )));
+        }
+        if codec == Some(BasisCodec::Uastc) {
+            let data = basis::transcode_uastc(&data, self.level_images(level)).map_err(invalid)?;
+            return Ok(Cow::Owned(data));
+        }
+        Ok(data)
+    }
+}
+
+impl Texture {
+    pub fn from_ktx2(bytes: &[u8]) -> Result<Self, AssetError> {
+        let ktx2 = Ktx2::parse(bytes)?;
+        let layout = TextureLayout::new(ktx2.desc()?)?;
+
+        // Each level holds every array slice of one mip, where the texture
+        // wants every mip of one array slice together.
+        // Sizes are checked before anything is decompressed. BasisLZ doesn't
+        // record them, but transcoding makes levels the right size.
+        let desc = layout.desc;
+        let sized = ktx2.supercompression != Supercompression::BasisLz;
+        for (mip, level) in ktx2.levels.iter().enumerate() {
+            let index = desc.subresource_index(mip as u32, 0) as usize;
+            let expected = layout.subresources[index].size() * desc.array_size as u64;
+            if sized && level.uncompressed_length != expected {
+                return Err(AssetError::Invalid(format!(
+                    "mip {mip} of the KTX2 is {} bytes, not {expected}",
+                    level.uncompressed_length
+                )));
+            }
+        }
+        let levels = (0..ktx2.levels.len())
+            .map(|level| ktx2.level_data(level))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut data = Vec::with_capacity(layout.size as usize);
+        for array_slice in 0..desc.array_size as usize {
+            for level in &levels {
+                let size = level.len() / desc.array_size as usize;
+                data.extend_from_slice(&level[array_slice * size..(array_slice + 1) * size]);
+            }
+        }
+        Texture::new(layout, data)
+    }
+}
@@ -0,0 +1,152 @@
+// Basis Universal payloads transcoded to BC blocks for D3D12. UASTC blocks
+// are decoded and re-encoded one at a time. ETC1S images are BasisLZ slices
+// that share codebooks in the supercompression global data; opaque ones
+// become BC1 and ones with an alpha slice, which ETC1S codes as green,
+// become BC7.
+
+mod bc;
+mod etc1s;
+mod uastc;
+
+type Result<T> = std::result::Result<T, &'static str>;
+
+const GLOBAL_HEADER_SIZE: usize = 20;
+const IMAGE_DESC_SIZE: usize = 20;
+/// Set on the P-frames of ETC1S video, which textures don't use.
+const IMAGE_IS_P_FRAME: u32 = 2;
+
+/// The images of one level: every layer, face and depth slice, in that
+/// order, each `width` by `height` texels.
+#[derive(Clone, Copy)]
+pub(super) struct LevelImages {
+    pub width: u32,
+    pub height: u32,
+    pub count: usize,
+}
+
+impl LevelImages {
+    fn blocks(&self) -> (usize, usize) {
+        (
+            self.width.div_ceil(4) as usize,
+            self.height.div_ceil(4) as usize,
+        )
+    }
+}
+
+pub(super) fn transcode_uastc(data: &[u8], images: LevelImages) -> Result<Vec<u8>> {
+    let (blocks_x, blocks_y) = images.blocks();
+    if data.len() != blocks_x * blocks_y * images.count * 16 {
+        return Err("UASTC level is the wrong size");
+    }
+    let mut bc7 = Vec::with_capacity(data.len());
+    for block in data.chunks_exact(16) {
+        let (texels, partition) = uastc::decode_block(block.try_into().unwrap())?;
+        bc7.extend_from_slice(&bc::encode_bc7(&texels, partition));
+    }
+    Ok(bc7)
+}
+
+/// BasisLZ's supercompression global data, with the codebooks decoded.
+pub(super) struct GlobalData {
+    codebooks: etc1s::Codebooks,
+    /// (flags, RGB slice, alpha slice) for each image in the file, where a
+    /// slice is an offset and length in its level's data.
+    images: Vec<(u32, [(usize, usize); 2])>,
+}
+
+impl GlobalData {
+    pub(super) fn parse(data: &[u8], image_count: usize) -> Result<Self> {
+        let truncated = "BasisLZ global data is truncated";
+        let header = data.get(..GLOBAL_HEADER_SIZE).ok_or(truncated)?;
+        let u16_at = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+        let u32_at = |bytes: &[u8], offset: usize| {
+            u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+        };
+        let (endpoint_count, selector_count) = (u16_at(0) as usize, u16_at(2) as usize);
+        let lengths = [4, 8, 12, 16].map(|offset| u32_at(header, offset));
+
+        let descs_end = GLOBAL_HEADER_SIZE + image_count * IMAGE_DESC_SIZE;
+        let descs = data.get(GLOBAL_HEADER_SIZE..descs_end).ok_or(truncated)?;
+        let images = descs
+            .chunks_exact(IMAGE_DESC_SIZE)
+            .map(|desc| {
+                let field = |i: usize| u32_at(desc, i * 4);
+                (
+                    field(0) as u32,
+                    [(field(1), field(2)), (field(3), field(4))],
+                )
+            })
+            .collect();
+
+        let mut sections = [&data[..0]; 4];
+        let mut offset = descs_end;
+        for (section, length) in sections.iter_mut().zip(lengths) {
+            *section = data.get(offset..offset + length).ok_or(truncated)?;
+            offset += length;
+        }
+        let [endpoints, selectors, tables, _extended] = sections;
+        Ok(Self {
+            codebooks: etc1s::Codebooks::read(
+                (endpoint_count, endpoints),
+                (selector_count, selectors),
+                tables,
+            )?,
+            images,
+        })
+    }
+
+    /// Transcodes a level's images, starting at `first_image` of the whole
+    /// file, to BC7 if `alpha` or BC1 if not.
+    pub(super) fn transcode_etc1s(
+        &self,
+        level: &[u8],
+        first_image: usize,
+        images: LevelImages,
+        alpha: bool,
+    ) -> Result<Vec<u8>> {
+        let (blocks_x, blocks_y) = images.blocks();
+        let descs = self
+            .images
+            .get(first_image..first_image + images.count)
+            .ok_or("BasisLZ global data has too few images")?;
+        let slice = |(offset, length): (usize, usize)| {
+            level
+                .get(offset..offset + length)
+                .filter(|slice| !slice.is_empty())
+                .ok_or("BasisLZ slice is outside its level")
+        };
+
+        let mut out = Vec::new();
+        for &(flags, [rgb, alpha_slice]) in descs {
+            if flags & IMAGE_IS_P_FRAME != 0 {
+                return Err("ETC1S video frames aren't supported");
+            }
+            let rgb = self
+                .codebooks
+                .decode_slice(slice(rgb)?, blocks_x, blocks_y)?;
+            let alpha_blocks = if alpha {
+                Some(
+                    self.codebooks
+                        .decode_slice(slice(alpha_slice)?, blocks_x, blocks_y)?,
+                )
+            } else {
+                None
+            };
+            for (i, rgb) in rgb.iter().enumerate() {
+                let mut texels = [[0; 4]; 16];
+                for (texel, (color, out)) in rgb.iter().zip(&mut texels).enumerate() {
+                    let a = alpha_blocks
+                        .as_ref()
+                        .map_or(255, |blocks| blocks[i][texel][1]);
+                    *out = [color[0], color[1], color[2], a];
+                }
+                if alpha {
+                    out.extend_from_slice(&bc::encode_bc7(&texels, bc::Partition::Whole));
+                } else {
+                    out.extend_from_slice(&bc::encode_bc1(&texels));
+                }
+            }
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,619 @@
+// Just enough BC1 and BC7 encoding to hold transcoded Basis blocks. Each
+// subset's colors are fitted with a line, whose ends are then refined by
+// least squares. BC7 tries the modes that suit the block: 6 and 5 for a
+// single subset, and 1, 7 or 2 to keep the subsets of a partitioned source.
+
+type Block = [[u8; 4]; 16];
+
+/// BC7's two-subset partitions, as a bit per texel that's set for the
+/// second subset.
+const PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80, 0xc800, 0xffec, 0xfe80, 0xe800,
+    0xffe8, 0xff00, 0xfff0, 0xf000, 0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c, 0xaaaa, 0xf0f0, 0x5a5a, 0x33cc,
+    0x3c3c, 0x55aa, 0x9696, 0xa55a, 0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c, 0x9336, 0x9cc6, 0x817e, 0xe718,
+    0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+/// The anchor of each two-subset partition's second subset.
+const ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+/// Three-subset partitions, as two bits per texel.
+const PARTITIONS_3: [u32; 64] = [
+    0xaa685050, 0x6a5a5040, 0x5a5a4200, 0x5450a0a8, 0xa5a50000, 0xa0a05050, 0x5555a0a0, 0x5a5a5050,
+    0xaa550000, 0xaa555500, 0xaaaa5500, 0x90909090, 0x94949494, 0xa4a4a4a4, 0xa9a59450, 0x2a0a4250,
+    0xa5945040, 0x0a425054, 0xa5a5a500, 0x55a0a0a0, 0xa8a85454, 0x6a6a4040, 0xa4a45000, 0x1a1a0500,
+    0x0050a4a4, 0xaaa59090, 0x14696914, 0x69691400, 0xa08585a0, 0xaa821414, 0x50a4a450, 0x6a5a0200,
+    0xa9a58000, 0x5090a0a8, 0xa8a09050, 0x24242424, 0x00aa5500, 0x24924924, 0x24499224, 0x50a50a50,
+    0x500aa550, 0xaaaa4444, 0x66660000, 0xa5a0a5a0, 0x50a050a0, 0x69286928, 0x44aaaa44, 0x66666600,
+    0xaa444444, 0x54a854a8, 0x95809580, 0x96969600, 0xa85454a8, 0x80959580, 0xaa141414, 0x96960000,
+    0xaaaa1414, 0xa05050a0, 0xa0a5a5a0, 0x96000000, 0x40804080, 0xa9a8a9a8, 0xaaaaaa44, 0x2a4a5254,
+];
+/// The anchors of each three-subset partition's second and third subsets.
+const ANCHORS_3: [[u8; 2]; 64] = [
+    [3, 15],
+    [3, 8],
+    [15, 8],
+    [15, 3],
+    [8, 15],
+    [3, 15],
+    [15, 3],
+    [15, 8],
+    [8, 15],
+    [8, 15],
+    [6, 15],
+    [6, 15],
+    [6, 15],
+    [5, 15],
+    [3, 15],
+    [3, 8],
+    [3, 15],
+    [3, 8],
+    [8, 15],
+    [15, 3],
+    [3, 15],
+    [3, 8],
+    [6, 15],
+    [10, 8],
+    [5, 3],
+    [8, 15],
+    [8, 6],
+    [6, 10],
+    [8, 15],
+    [5, 15],
+    [15, 10],
+    [15, 8],
+    [8, 15],
+    [15, 3],
+    [3, 15],
+    [5, 10],
+    [6, 10],
+    [10, 8],
+    [8, 9],
+    [15, 10],
+    [15, 6],
+    [3, 15],
+    [15, 8],
+    [5, 15],
+    [15, 3],
+    [15, 6],
+    [15, 6],
+    [15, 8],
+    [3, 15],
+    [15, 3],
+    [5, 15],
+    [5, 15],
+    [5, 15],
+    [8, 15],
+    [5, 15],
+    [10, 15],
+    [5, 15],
+    [10, 15],
+    [8, 15],
+    [13, 15],
+    [15, 3],
+    [12, 15],
+    [3, 15],
+    [3, 8],
+];
+
+const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// How a BC7 block splits its texels into subsets.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum Partition {
+    Whole,
+    Two(usize),
+    Three(usize),
+}
+
+impl Partition {
+    fn subsets(self) -> usize {
+        match self {
+            Self::Whole => 1,
+            Self::Two(_) => 2,
+            Self::Three(_) => 3,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::Whole => 0,
+            Self::Two(index) | Self::Three(index) => index,
+        }
+    }
+
+    pub(super) fn subset(self, texel: usize) -> usize {
+        match self {
+            Self::Whole => 0,
+            Self::Two(index) => (PARTITIONS_2[index] >> texel & 1) as usize,
+            Self::Three(index) => (PARTITIONS_3[index] >> (2 * texel) & 3) as usize,
+        }
+    }
+
+    fn anchor(self, subset: usize) -> usize {
+        match (self, subset) {
+            (_, 0) => 0,
+            (Self::Two(index), _) => ANCHORS_2[index] as usize,
+            (Self::Three(index), _) => ANCHORS_3[index][subset - 1] as usize,
+            (Self::Whole, _) => unreachable!("a whole block has one subset"),
+        }
+    }
+
+    /// Whether `texel` is its subset's anchor, whose index is stored
+    /// without its top bit.
+    pub(super) fn is_anchor(self, texel: usize) -> bool {
+        self.anchor(self.subset(texel)) == texel
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PBits {
+    None,
+    /// One low bit for both of a subset's endpoints.
+    Shared,
+    /// A low bit for each endpoint.
+    Unique,
+}
+
+struct Mode {
+    number: u32,
+    partition_bits: u32,
+    color_bits: u32,
+    alpha: bool,
+    p_bits: PBits,
+    weights: &'static [u32],
+}
+
+const MODE_1: Mode = Mode {
+    number: 1,
+    partition_bits: 6,
+    color_bits: 6,
+    alpha: false,
+    p_bits: PBits::Shared,
+    weights: &WEIGHTS_3,
+};
+const MODE_2: Mode = Mode {
+    number: 2,
+    partition_bits: 6,
+    color_bits: 5,
+    alpha: false,
+    p_bits: PBits::None,
+    weights: &WEIGHTS_2,
+};
+const MODE_6: Mode = Mode {
+    number: 6,
+    partition_bits: 0,
+    color_bits: 7,
+    alpha: true,
+    p_bits: PBits::Unique,
+    weights: &WEIGHTS_4,
+};
+const MODE_7: Mode = Mode {
+    number: 7,
+    partition_bits: 6,
+    color_bits: 5,
+    alpha: true,
+    p_bits: PBits::Unique,
+    weights: &WEIGHTS_2,
+};
+
+fn distance(a: [f32; 4], b: [f32; 4], channels: usize) -> f32 {
+    (0..channels).map(|c| (a[c] - b[c]) * (a[c] - b[c])).sum()
+}
+
+/// The ends of the texels' colors along their principal axis.
+fn principal_ends(texels: &[[f32; 4]], channels: usize) -> [[f32; 4]; 2] {
+    let mut mean = [0.0; 4];
+    for texel in texels {
+        for c in 0..channels {
+            mean[c] += texel[c] / texels.len() as f32;
+        }
+    }
+    let mut covariance = [[0.0f32; 4]; 4];
+    for texel in texels {
+        for i in 0..channels {
+            for j in 0..channels {
+                covariance[i][j] += (texel[i] - mean[i]) * (texel[j] - mean[j]);
+            }
+        }
+    }
+    // Power iteration, starting from the widest channel.
+    let mut axis = [0.0f32; 4];
+    let widest = (0..channels)
+        .max_by(|&a, &b| covariance[a][a].total_cmp(&covariance[b][b]))
+        .unwrap();
+    axis[widest] = 1.0;
+    for _ in 0..8 {
+        let mut next = [0.0; 4];
+        for i in 0..channels {
+            next[i] = (0..channels).map(|j| covariance[i][j] * axis[j]).sum();
+        }
+        let length = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length < 1e-6 {
+            break;
+        }
+        axis = next.map(|v| v / length);
+    }
+
+    let project = |texel: &[f32; 4]| (0..channels).map(|c| (texel[c] - mean[c]) * axis[c]).sum();
+    let (mut low, mut high) = (f32::MAX, f32::MIN);
+    for texel in texels {
+        let t: f32 = project(texel);
+        low = low.min(t);
+        high = high.max(t);
+    }
+    let at = |t: f32| {
+        let mut color = mean;
+        for c in 0..channels {
+            color[c] = (mean[c] + axis[c] * t).clamp(0.0, 255.0);
+        }
+        color
+    };
+    [at(low), at(high)]
+}
+
+/// Ends that best fit `texels` when each sits `weights[i]` of the way from
+/// the first to the second, or `None` if every weight is the same.
+fn least_squares(texels: &[[f32; 4]], weights: &[f32]) -> Option<[[f32; 4]; 2]> {
+    let (mut aa, mut ab, mut bb) = (0.0, 0.0, 0.0);
+    let (mut ax, mut bx) = ([0.0f32; 4], [0.0f32; 4]);
+    for (texel, &w) in texels.iter().zip(weights) {
+        let (a, b) = (1.0 - w, w);
+        aa += a * a;
+        ab += a * b;
+        bb += b * b;
+        for c in 0..4 {
+            ax[c] += a * texel[c];
+            bx[c] += b * texel[c];
+        }
+    }
+    let determinant = aa * bb - ab * ab;
+    if determinant.abs() < 1e-6 {
+        return None;
+    }
+    let mut ends = [[0.0; 4]; 2];
+    for c in 0..4 {
+        ends[0][c] = ((bb * ax[c] - ab * bx[c]) / determinant).clamp(0.0, 255.0);
+        ends[1][c] = ((aa * bx[c] - ab * ax[c]) / determinant).clamp(0.0, 255.0);
+    }
+    Some(ends)
+}
+
+fn to_f32(block: &Block) -> [[f32; 4]; 16] {
+    block.map(|texel| texel.map(|c| c as f32))
+}
+
+/// Each texel's nearest palette entry and the total squared error.
+fn nearest(texels: &[[f32; 4]], palette: &[[f32; 4]], channels: usize) -> (Vec<usize>, f32) {
+    let mut error = 0.0;
+    let indices = texels
+        .iter()
+        .map(|&texel| {
+            let (best, best_error) = palette
+                .iter()
+                .map(|&entry| distance(texel, entry, channels))
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            error += best_error;
+            best
+        })
+        .collect();
+    (indices, error)
+}
+
+fn bc1_pack(color: [f32; 4]) -> u16 {
+    let quantize = |value: f32, max: f32| (value * max / 255.0).round() as u16;
+    quantize(color[0], 31.0) << 11 | quantize(color[1], 63.0) << 5 | quantize(color[2], 31.0)
+}
+
+fn bc1_unpack(color: u16) -> [f32; 4] {
+    let expand = |value: u16, bits: u32| {
+        let value = value as u32;
+        ((value << (8 - bits)) | (value >> (2 * bits - 8))) as f32
+    };
+    [
+        expand(color >> 11, 5),
+        expand((color >> 5) & 0x3f, 6),
+        expand(color & 0x1f, 5),
+        255.0,
+    ]
+}
+
+/// An opaque BC1 block, always in four-color mode.
+pub(super) fn encode_bc1(block: &Block) -> [u8; 8] {
+    let texels = to_f32(block);
+    let mut ends = principal_ends(&texels, 3);
+    let mut best: Option<(f32, u16, u16, Vec<usize>)> = None;
+    for _ in 0..2 {
+        let (mut c0, mut c1) = (bc1_pack(ends[0]), bc1_pack(ends[1]));
+        // Four-color mode needs the first color to be the larger.
+        if c0 < c1 {
+            std::mem::swap(&mut c0, &mut c1);
+        }
+        let (e0, e1) = (bc1_unpack(c0), bc1_unpack(c1));
+        let mix = |a: f32, b: f32| [0, 1, 2, 3].map(|c| (a * e0[c] + b * e1[c]) / 3.0);
+        let palette = [e0, e1, mix(2.0, 1.0), mix(1.0, 2.0)];
+        let (indices, error) = nearest(&texels, &palette, 3);
+        const POSITION: [f32; 4] = [0.0, 1.0, 1.0 / 3.0, 2.0 / 3.0];
+        let positions: Vec<_> = indices.iter().map(|&i| POSITION[i]).collect();
+        if best
+            .as_ref()
+            .is_none_or(|(best_error, ..)| error < *best_error)
+        {
+            best = Some((error, c0, c1, indices));
+        }
+        match least_squares(&texels, &positions) {
+            Some(fitted) => ends = fitted,
+            None => break,
+        }
+    }
+
+    let (_, c0, c1, mut indices) = best.unwrap();
+    if c0 == c1 {
+        indices.fill(0);
+    }
+    let mut bytes = [0; 8];
+    bytes[..2].copy_from_slice(&c0.to_le_bytes());
+    bytes[2..4].copy_from_slice(&c1.to_le_bytes());
+    let bits = indices
+        .iter()
+        .enumerate()
+        .fold(0u32, |bits, (i, &index)| bits | (index as u32) << (2 * i));
+    bytes[4..].copy_from_slice(&bits.to_le_bytes());
+    bytes
+}
+
+/// A BC7 endpoint: its fields at the mode's bits per channel, its p-bit,
+/// and the color they expand to.
+#[derive(Clone, Copy, Default)]
+struct Endpoint {
+    fields: [u32; 4],
+    p: u32,
+    color: [u32; 4],
+}
+
+/// `color` at `bits` per channel, plus the low bit `p` if the mode has
+/// one, and the squared error that leaves.
+fn quantize(color: [f32; 4], bits: u32, p: Option<u32>) -> (Endpoint, f32) {
+    let total = bits + p.is_some() as u32;
+    let top = ((1 << total) - 1) as f32;
+    let mut endpoint = Endpoint {
+        p: p.unwrap_or(0),
+        ..Default::default()
+    };
+    let mut error = 0.0;
+    for (c, &channel) in color.iter().enumerate() {
+        let target = channel * top / 255.0;
+        let field = match p {
+            Some(p) => ((target - p as f32) / 2.0).round(),
+            None => target.round(),
+        };
+        endpoint.fields[c] = field.clamp(0.0, ((1 << bits) - 1) as f32) as u32;
+        let value = match p {
+            Some(p) => endpoint.fields[c] << 1 | p,
+            None => endpoint.fields[c],
+        };
+        endpoint.color[c] = (value << (8 - total)) | (value >> (2 * total - 8).min(8));
+        error += (endpoint.color[c] as f32 - channel).powi(2);
+    }
+    (endpoint, error)
+}
+
+/// A subset's ends at `bits` per channel, choosing the p-bits that land
+/// closest.
+fn quantize_ends(ends: [[f32; 4]; 2], bits: u32, p_bits: PBits) -> [Endpoint; 2] {
+    let best = |end: [f32; 4]| {
+        let (zero, one) = (quantize(end, bits, Some(0)), quantize(end, bits, Some(1)));
+        if one.1 < zero.1 {
+            one
+        } else {
+            zero
+        }
+    };
+    match p_bits {
+        PBits::None => ends.map(|end| quantize(end, bits, None).0),
+        // Only a set p-bit reaches 255, which keeps opaque ends opaque.
+        PBits::Unique => ends.map(|end| {
+            if end[3].round() == 255.0 {
+                quantize(end, bits, Some(1)).0
+            } else {
+                best(end).0
+            }
+        }),
+        PBits::Shared => {
+            let with = |p| ends.map(|end| quantize(end, bits, Some(p)));
+            let (zero, one) = (with(0), with(1));
+            if one[0].1 + one[1].1 < zero[0].1 + zero[1].1 {
+                one.map(|(end, _)| end)
+            } else {
+                zero.map(|(end, _)| end)
+            }
+        }
+    }
+}
+
+/// Fits `texels` with a line whose ends `quantize` makes representable,
+/// returning the ends, each texel's index into `weights` along it, and the
+/// squared error.
+fn fit(
+    texels: &[[f32; 4]],
+    channels: usize,
+    weights: &[u32],
+    quantize: impl Fn([[f32; 4]; 2]) -> [Endpoint; 2],
+) -> ([Endpoint; 2], Vec<usize>, f32) {
+    let mut ends = principal_ends(texels, channels);
+    let mut best: Option<([Endpoint; 2], Vec<usize>, f32)> = None;
+    for _ in 0..3 {
+        let quantized = quantize(ends);
+        let [low, high] = quantized.map(|end| end.color);
+        let palette: Vec<_> = weights
+            .iter()
+            .map(|&w| [0, 1, 2, 3].map(|c| (((64 - w) * low[c] + w * high[c] + 32) >> 6) as f32))
+            .collect();
+        let (indices, error) = nearest(texels, &palette, channels);
+        let positions: Vec<_> = indices.iter().map(|&i| weights[i] as f32 / 64.0).collect();
+        if best
+            .as_ref()
+            .is_none_or(|(.., best_error)| error < *best_error)
+        {
+            best = Some((quantized, indices, error));
+        }
+        match least_squares(texels, &positions) {
+            Some(fitted) => ends = fitted,
+            None => break,
+        }
+    }
+    best.unwrap()
+}
+
+/// Swaps a subset's ends if its anchor's index has the top bit set, which
+/// BC7 doesn't store.
+fn clear_anchor_bit(ends: &mut [Endpoint; 2], indices: &mut [usize], anchor: usize, levels: usize) {
+    if indices[anchor] >= levels / 2 {
+        ends.swap(0, 1);
+        for index in indices {
+            *index = levels - 1 - *index;
+        }
+    }
+}
+
+struct Writer {
+    bits: u128,
+    position: u32,
+}
+
+impl Writer {
+    fn new(mode: u32) -> Self {
+        Self {
+            bits: 1 << mode,
+            position: mode + 1,
+        }
+    }
+
+    fn write(&mut self, value: u32, count: u32) {
+        self.bits |= (value as u128) << self.position;
+        self.position += count;
+    }
+}
+
+/// `texels` in one of the modes other than 4 and 5, with its squared error.
+fn encode_mode(texels: &[[f32; 4]; 16], mode: &Mode, partition: Partition) -> (f32, u128) {
+    let channels = if mode.alpha { 4 } else { 3 };
+    let mut ends = [[Endpoint::default(); 2]; 3];
+    let mut indices = [0; 16];
+    let mut error = 0.0;
+    for subset in 0..partition.subsets() {
+        let members: Vec<_> = (0..16).filter(|&t| partition.subset(t) == subset).collect();
+        let colors: Vec<_> = members.iter().map(|&t| texels[t]).collect();
+        let (mut subset_ends, mut subset_indices, subset_error) =
+            fit(&colors, channels, mode.weights, |ends| {
+                quantize_ends(ends, mode.color_bits, mode.p_bits)
+            });
+        let anchor = members
+            .iter()
+            .position(|&t| t == partition.anchor(subset))
+            .unwrap();
+        clear_anchor_bit(
+            &mut subset_ends,
+            &mut subset_indices,
+            anchor,
+            mode.weights.len(),
+        );
+        for (&texel, &index) in members.iter().zip(&subset_indices) {
+            indices[texel] = index;
+        }
+        ends[subset] = subset_ends;
+        error += subset_error;
+    }
+
+    let ends = &ends[..partition.subsets()];
+    let mut writer = Writer::new(mode.number);
+    writer.write(partition.index() as u32, mode.partition_bits);
+    for c in 0..channels {
+        for end in ends.iter().flatten() {
+            writer.write(end.fields[c], mode.color_bits);
+        }
+    }
+    match mode.p_bits {
+        PBits::None => {}
+        PBits::Shared => ends.iter().for_each(|[end, _]| writer.write(end.p, 1)),
+        PBits::Unique => ends.iter().flatten().for_each(|end| writer.write(end.p, 1)),
+    }
+    let index_bits = mode.weights.len().trailing_zeros();
+    for (texel, &index) in indices.iter().enumerate() {
+        writer.write(index as u32, index_bits - partition.is_anchor(texel) as u32);
+    }
+    debug_assert_eq!(writer.position, 128);
+    (error, writer.bits)
+}
+
+/// `texels` in mode 5, which gives one channel its own line and indices.
+fn encode_mode_5(texels: &[[f32; 4]; 16], rotation: u32) -> (f32, u128) {
+    // A rotation swaps alpha with red, green or blue, to give that channel
+    // the separate line instead.
+    let texels = &texels.map(|mut texel| {
+        if rotation > 0 {
+            texel.swap(rotation as usize - 1, 3);
+        }
+        texel
+    });
+    let (mut color_ends, mut color_indices, color_error) = fit(texels, 3, &WEIGHTS_2, |ends| {
+        quantize_ends(ends, 7, PBits::None)
+    });
+    let alphas = texels.map(|texel| [texel[3], 0.0, 0.0, 0.0]);
+    let (mut alpha_ends, mut alpha_indices, alpha_error) = fit(&alphas, 1, &WEIGHTS_2, |ends| {
+        quantize_ends(ends, 8, PBits::None)
+    });
+    clear_anchor_bit(&mut color_ends, &mut color_indices, 0, 4);
+    clear_anchor_bit(&mut alpha_ends, &mut alpha_indices, 0, 4);
+
+    let mut writer = Writer::new(5);
+    writer.write(rotation, 2);
+    for c in 0..3 {
+        for end in &color_ends {
+            writer.write(end.fields[c], 7);
+        }
+    }
+    for end in &alpha_ends {
+        writer.write(end.fields[0], 8);
+    }
+    for indices in [color_indices, alpha_indices] {
+        for (texel, &index) in indices.iter().enumerate() {
+            writer.write(index as u32, if texel == 0 { 1 } else { 2 });
+        }
+    }
+    debug_assert_eq!(writer.position, 128);
+    (color_error + alpha_error, writer.bits)
+}
+
+/// A BC7 block in whichever mode fits best, trying the partitioned modes
+/// with `partition` when the source block had subsets.
+pub(super) fn encode_bc7(block: &Block, partition: Partition) -> [u8; 16] {
+    let texels = to_f32(block);
+    let opaque = block.iter().all(|texel| texel[3] == 255);
+    let mut candidates = vec![
+        encode_mode(&texels, &MODE_6, Partition::Whole),
+        encode_mode_5(&texels, 0),
+        encode_mode_5(&texels, 1),
+        encode_mode_5(&texels, 2),
+        encode_mode_5(&texels, 3),
+    ];
+    match (partition, opaque) {
+        (Partition::Two(_), true) => candidates.push(encode_mode(&texels, &MODE_1, partition)),
+        (Partition::Two(_), false) => candidates.push(encode_mode(&texels, &MODE_7, partition)),
+        (Partition::Three(_), true) => candidates.push(encode_mode(&texels, &MODE_2, partition)),
+        _ => {}
+    }
+    let (_, bits) = candidates
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .unwrap();
+    bits.to_le_bytes()
+}
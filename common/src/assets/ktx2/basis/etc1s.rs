@@ -0,0 +1,405 @@
+// ETC1S slices as BasisLZ codes them. The supercompression global data holds
+// codebooks of endpoints (a 5-bit color and an intensity table) and of
+// selectors (2 bits a texel), plus the Huffman tables the slices use. Each
+// slice is a run of Huffman-coded indices into those codebooks, predicted from
+// neighboring blocks and a small move-to-front history of selectors.
+
+use super::Result;
+
+/// ETC1's intensity modifiers, in selector order from darkest to lightest.
+const INTENSITIES: [[i32; 4]; 8] = [
+    [-8, -2, 2, 8],
+    [-17, -5, 5, 17],
+    [-29, -9, 9, 29],
+    [-42, -13, 13, 42],
+    [-60, -18, 18, 60],
+    [-80, -24, 24, 80],
+    [-106, -33, 33, 106],
+    [-183, -47, 47, 183],
+];
+
+/// The order code length code lengths are stored in.
+const CODE_LENGTH_ORDER: [usize; 21] = [
+    17, 18, 19, 20, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15, 16,
+];
+const MAX_SYMBOLS: usize = 16384;
+
+const ENDPOINT_PRED_REPEAT_LAST: u16 = 256;
+const ENDPOINT_PRED_MIN_REPEAT: u32 = 3;
+const SELECTOR_RLE_MIN: u32 = 3;
+const SELECTOR_RLE_SYMBOLS: u16 = 64;
+
+fn truncated() -> &'static str {
+    "BasisLZ data is truncated"
+}
+
+/// Reads bits least significant first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self.data.get(self.position / 8).ok_or_else(truncated)?;
+            value |= ((byte >> (self.position % 8)) as u32 & 1) << i;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    /// A number in chunks of `chunk_bits`, each followed by a bit saying
+    /// whether another chunk comes.
+    fn vlc(&mut self, chunk_bits: u32) -> Result<u32> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let chunk = self.bits(chunk_bits + 1)?;
+            value |= (chunk & ((1 << chunk_bits) - 1)) << shift;
+            shift += chunk_bits;
+            if chunk >> chunk_bits == 0 {
+                return Ok(value);
+            }
+            if shift >= 32 {
+                return Err("BasisLZ number is too long");
+            }
+        }
+    }
+
+    fn huffman(&mut self) -> Result<Huffman> {
+        let symbol_count = self.bits(14)? as usize;
+        if symbol_count == 0 || symbol_count > MAX_SYMBOLS {
+            return Err("BasisLZ Huffman table has no symbols");
+        }
+        let mut code_length_lengths = [0; 21];
+        let stored = self.bits(5)? as usize;
+        if stored == 0 || stored > 21 {
+            return Err("invalid BasisLZ Huffman table");
+        }
+        for &symbol in &CODE_LENGTH_ORDER[..stored] {
+            code_length_lengths[symbol] = self.bits(3)? as u8;
+        }
+        let code_lengths = Huffman::new(&code_length_lengths)?;
+
+        let mut lengths = vec![0; symbol_count];
+        let mut i = 0;
+        while i < symbol_count {
+            let (value, run) = match code_lengths.decode(self)? {
+                length @ 0..=16 => (length as u8, 1),
+                17 => (0, self.bits(3)? as usize + 3),
+                18 => (0, self.bits(7)? as usize + 11),
+                code => {
+                    let previous = *lengths
+                        .get(i.wrapping_sub(1))
+                        .ok_or("BasisLZ Huffman table repeats nothing")?;
+                    let run = match code {
+                        19 => self.bits(2)? as usize + 3,
+                        _ => self.bits(7)? as usize + 7,
+                    };
+                    (previous, run)
+                }
+            };
+            let run = lengths
+                .get_mut(i..i + run)
+                .ok_or("BasisLZ Huffman table runs past its symbols")?;
+            run.fill(value);
+            i += run.len();
+        }
+        Huffman::new(&lengths)
+    }
+}
+
+/// A canonical Huffman code of up to 16 bits, decoded a bit at a time.
+struct Huffman {
+    counts: [u16; 17],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self> {
+        let mut counts = [0; 17];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        // An over-full code can't be decoded.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err("invalid BasisLZ Huffman table");
+            }
+        }
+
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16)
+            .filter(|&symbol| lengths[symbol as usize] != 0)
+            .collect();
+        if symbols.is_empty() {
+            return Err("BasisLZ Huffman table has no symbols");
+        }
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid BasisLZ Huffman code")
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Endpoint {
+    color: [u8; 3],
+    intensity: usize,
+}
+
+/// A selector's 2-bit values, a byte for each row.
+type Selector = [u8; 4];
+
+/// The codebooks and tables shared by every slice.
+pub(super) struct Codebooks {
+    endpoints: Vec<Endpoint>,
+    selectors: Vec<Selector>,
+    endpoint_pred: Huffman,
+    delta_endpoint: Huffman,
+    selector: Huffman,
+    selector_history_rle: Huffman,
+    selector_history_size: usize,
+}
+
+fn read_endpoints(data: &[u8], count: usize) -> Result<Vec<Endpoint>> {
+    let mut bits = BitReader::new(data);
+    let color_deltas = [bits.huffman()?, bits.huffman()?, bits.huffman()?];
+    let intensity_delta = bits.huffman()?;
+    let grayscale = bits.bits(1)? != 0;
+
+    let mut endpoints = Vec::with_capacity(count);
+    let mut previous = Endpoint {
+        color: [16; 3],
+        intensity: 0,
+    };
+    for _ in 0..count {
+        let intensity = (intensity_delta.decode(&mut bits)? as usize + previous.intensity) & 7;
+        let mut color = previous.color;
+        let channels = if grayscale { 1 } else { 3 };
+        for (c, value) in color.iter_mut().enumerate().take(channels) {
+            // The delta's table depends on where the previous value was.
+            let table = match previous.color[c] {
+                0..=9 => &color_deltas[0],
+                10..=21 => &color_deltas[1],
+                _ => &color_deltas[2],
+            };
+            *value = ((previous.color[c] as u16 + table.decode(&mut bits)?) & 31) as u8;
+        }
+        if grayscale {
+            color = [color[0]; 3];
+        }
+        previous = Endpoint { color, intensity };
+        endpoints.push(previous);
+    }
+    Ok(endpoints)
+}
+
+fn read_selectors(data: &[u8], count: usize) -> Result<Vec<Selector>> {
+    let mut bits = BitReader::new(data);
+    if bits.bits(1)? != 0 || bits.bits(1)? != 0 {
+        return Err("KTX2 doesn't allow Basis global selector codebooks");
+    }
+    let mut selectors = Vec::with_capacity(count);
+    if bits.bits(1)? != 0 {
+        for _ in 0..count {
+            let mut selector = [0; 4];
+            for row in &mut selector {
+                *row = bits.bits(8)? as u8;
+            }
+            selectors.push(selector);
+        }
+        return Ok(selectors);
+    }
+
+    // Each selector is XORed with the one before, except the first.
+    let deltas = bits.huffman()?;
+    let mut previous = [0; 4];
+    for i in 0..count {
+        for row in &mut previous {
+            *row ^= match i {
+                0 => bits.bits(8)? as u8,
+                _ => deltas.decode(&mut bits)? as u8,
+            };
+        }
+        selectors.push(previous);
+    }
+    Ok(selectors)
+}
+
+impl Codebooks {
+    pub(super) fn read(
+        (endpoint_count, endpoint_data): (usize, &[u8]),
+        (selector_count, selector_data): (usize, &[u8]),
+        table_data: &[u8],
+    ) -> Result<Self> {
+        let mut tables = BitReader::new(table_data);
+        let endpoint_pred = tables.huffman()?;
+        let delta_endpoint = tables.huffman()?;
+        let selector = tables.huffman()?;
+        let selector_history_rle = tables.huffman()?;
+        let selector_history_size = tables.bits(13)? as usize;
+        if selector_history_size == 0 {
+            return Err("BasisLZ selector history is empty");
+        }
+        if endpoint_count == 0 || selector_count == 0 {
+            return Err("BasisLZ codebooks are empty");
+        }
+        Ok(Self {
+            endpoints: read_endpoints(endpoint_data, endpoint_count)?,
+            selectors: read_selectors(selector_data, selector_count)?,
+            endpoint_pred,
+            delta_endpoint,
+            selector,
+            selector_history_rle,
+            selector_history_size,
+        })
+    }
+
+    /// Decodes a slice of `blocks_x` by `blocks_y` blocks to RGB texels.
+    pub(super) fn decode_slice(
+        &self,
+        data: &[u8],
+        blocks_x: usize,
+        blocks_y: usize,
+    ) -> Result<Vec<[[u8; 3]; 16]>> {
+        let invalid = || "invalid BasisLZ slice";
+        let mut bits = BitReader::new(data);
+        let mut blocks = Vec::with_capacity(blocks_x * blocks_y);
+
+        // Recently used selectors: new ones go in at a rover that sweeps the
+        // back half, and a reused one swaps halfway to the front.
+        let mut history = vec![0usize; self.selector_history_size];
+        let mut rover = history.len() / 2;
+        let history_rle_symbol = self.selectors.len() + history.len();
+        let mut selector_run = 0;
+
+        // Endpoint predictions come a 2x2 group of blocks at a time, so the
+        // even rows decode them and save the odd row's half.
+        let mut odd_row_preds = vec![0u16; blocks_x];
+        let mut rows = [vec![0usize; blocks_x], vec![0usize; blocks_x]];
+        let (mut preds, mut previous_preds, mut pred_repeat) = (0u16, 0u16, 0u32);
+        let mut previous_endpoint = 0;
+
+        for y in 0..blocks_y {
+            let (row, above) = match y & 1 {
+                0 => (1, 0),
+                _ => (0, 1),
+            };
+            for x in 0..blocks_x {
+                if x & 1 == 0 {
+                    if y & 1 == 0 {
+                        if pred_repeat > 0 {
+                            pred_repeat -= 1;
+                            preds = previous_preds;
+                        } else {
+                            preds = self.endpoint_pred.decode(&mut bits)?;
+                            if preds == ENDPOINT_PRED_REPEAT_LAST {
+                                pred_repeat = bits.vlc(4)? + ENDPOINT_PRED_MIN_REPEAT - 1;
+                                preds = previous_preds;
+                            } else {
+                                previous_preds = preds;
+                            }
+                        }
+                        odd_row_preds[x] = preds >> 4;
+                    } else {
+                        preds = odd_row_preds[x];
+                    }
+                }
+
+                let endpoint = match preds & 3 {
+                    0 if x > 0 => previous_endpoint,
+                    1 if y > 0 => rows[above][x],
+                    2 if x > 0 && y > 0 => rows[above][x - 1],
+                    3 => {
+                        let delta = self.delta_endpoint.decode(&mut bits)? as usize;
+                        let endpoint = previous_endpoint + delta;
+                        match endpoint.checked_sub(self.endpoints.len()) {
+                            Some(wrapped) => wrapped,
+                            None => endpoint,
+                        }
+                    }
+                    _ => return Err("BasisLZ slice predicts from outside the image"),
+                };
+                preds >>= 2;
+                rows[row][x] = endpoint;
+                previous_endpoint = endpoint;
+
+                let symbol = if selector_run > 0 {
+                    selector_run -= 1;
+                    self.selectors.len()
+                } else {
+                    let symbol = self.selector.decode(&mut bits)? as usize;
+                    if symbol == history_rle_symbol {
+                        let run = self.selector_history_rle.decode(&mut bits)?;
+                        // The last run length says the rest is coded apart.
+                        selector_run = if run == SELECTOR_RLE_SYMBOLS - 1 {
+                            bits.vlc(7)? + SELECTOR_RLE_MIN
+                        } else {
+                            run as u32 + SELECTOR_RLE_MIN
+                        };
+                        if selector_run as usize > blocks_x * blocks_y {
+                            return Err(invalid());
+                        }
+                        selector_run -= 1;
+                        self.selectors.len()
+                    } else {
+                        symbol
+                    }
+                };
+                let selector = match symbol.checked_sub(self.selectors.len()) {
+                    None => {
+                        history[rover] = symbol;
+                        rover += 1;
+                        if rover == history.len() {
+                            rover = history.len() / 2;
+                        }
+                        symbol
+                    }
+                    Some(used) => {
+                        let selector = *history.get(used).ok_or_else(invalid)?;
+                        history.swap(used / 2, used);
+                        selector
+                    }
+                };
+
+                let endpoint = self.endpoints.get(endpoint).ok_or_else(invalid)?;
+                let selector = self.selectors.get(selector).ok_or_else(invalid)?;
+                blocks.push(decode_block(endpoint, selector));
+            }
+        }
+        Ok(blocks)
+    }
+}
+
+fn decode_block(endpoint: &Endpoint, selector: &Selector) -> [[u8; 3]; 16] {
+    let base = endpoint.color.map(|c| ((c << 3) | (c >> 2)) as i32);
+    let modifiers = INTENSITIES[endpoint.intensity];
+    let mut texels = [[0; 3]; 16];
+    for (i, texel) in texels.iter_mut().enumerate() {
+        let modifier = modifiers[(selector[i / 4] >> (2 * (i % 4))) as usize & 3];
+        *texel = base.map(|c| (c + modifier).clamp(0, 255) as u8);
+    }
+    texels
+}
@@ -0,0 +1,445 @@
+// UASTC blocks decoded to RGBA. A UASTC block is an ASTC 4x4 block in a
+// tighter layout: a Huffman-coded mode picks one of 19 fixed configurations,
+// some transcoder hints follow, then ASTC endpoints and weights. Partitioned
+// modes only use the patterns ASTC shares with BC7.
+
+use super::{bc::Partition, Result};
+
+struct Mode {
+    code: u32,
+    code_bits: u32,
+    hint_bits: u32,
+    /// 3 for RGB, 4 for RGBA and 2 for luminance-alpha.
+    components: usize,
+    partitions: Partitions,
+    dual_plane: bool,
+    weight_bits: u32,
+    endpoint_range: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Partitions {
+    One,
+    Two,
+    Three,
+    /// BC7's three-subset patterns that ASTC can do with two subsets.
+    ThreeAsTwo,
+}
+
+const fn mode(
+    (code, code_bits): (u32, u32),
+    hint_bits: u32,
+    components: usize,
+    partitions: Partitions,
+    dual_plane: bool,
+    weight_bits: u32,
+    endpoint_range: usize,
+) -> Mode {
+    Mode {
+        code,
+        code_bits,
+        hint_bits,
+        components,
+        partitions,
+        dual_plane,
+        weight_bits,
+        endpoint_range,
+    }
+}
+
+const SOLID_MODE: usize = 8;
+const SOLID_CODE: (u32, u32) = (0x17, 5);
+
+const MODES: [Mode; 19] = {
+    use Partitions::*;
+    [
+        mode((0x1, 4), 15, 3, One, false, 4, 19),
+        mode((0x35, 6), 15, 3, One, false, 2, 20),
+        mode((0x1d, 5), 15, 3, Two, false, 3, 8),
+        mode((0x3, 5), 15, 3, Three, false, 2, 7),
+        mode((0x13, 5), 15, 3, Two, false, 2, 12),
+        mode((0xb, 5), 15, 3, One, false, 3, 20),
+        mode((0x1b, 5), 15, 3, One, true, 2, 18),
+        mode((0x7, 5), 15, 3, ThreeAsTwo, false, 2, 12),
+        // The solid color mode, which has its own layout.
+        mode(SOLID_CODE, 0, 4, One, false, 0, 0),
+        mode((0xf, 5), 23, 4, Two, false, 2, 8),
+        mode((0x2, 3), 17, 4, One, false, 4, 13),
+        mode((0x0, 2), 17, 4, One, true, 2, 13),
+        mode((0x6, 3), 17, 4, One, false, 3, 19),
+        mode((0x1f, 5), 23, 4, One, true, 1, 20),
+        mode((0xd, 5), 23, 4, One, false, 2, 20),
+        mode((0x5, 7), 23, 2, One, false, 4, 20),
+        mode((0x15, 6), 23, 2, Two, false, 2, 20),
+        mode((0x25, 6), 23, 2, One, true, 2, 20),
+        mode((0x9, 4), 15, 3, One, false, 5, 11),
+    ]
+};
+
+/// For each pattern a mode can name, its ASTC partition seed and the BC7
+/// partition it matches, whose anchors store their weight without its
+/// top bit. The encoder keeps those bits clear.
+const PATTERNS_2: [(u32, usize); 30] = [
+    (28, 0),
+    (20, 1),
+    (16, 2),
+    (29, 3),
+    (91, 4),
+    (9, 5),
+    (107, 6),
+    (72, 7),
+    (149, 8),
+    (204, 9),
+    (50, 10),
+    (114, 11),
+    (496, 12),
+    (17, 13),
+    (78, 14),
+    (39, 15),
+    (252, 17),
+    (828, 18),
+    (43, 19),
+    (156, 20),
+    (116, 21),
+    (210, 22),
+    (476, 23),
+    (273, 24),
+    (684, 25),
+    (359, 26),
+    (246, 29),
+    (195, 32),
+    (694, 33),
+    (524, 52),
+];
+const PATTERNS_3: [(u32, usize); 11] = [
+    (260, 4),
+    (74, 8),
+    (32, 9),
+    (156, 10),
+    (183, 11),
+    (15, 12),
+    (745, 13),
+    (0, 20),
+    (335, 35),
+    (902, 36),
+    (254, 57),
+];
+/// Patterns that BC7 splits three ways, where one ASTC subset covers two
+/// of BC7's.
+const PATTERNS_3_AS_2: [(u32, usize); 19] = [
+    (36, 10),
+    (48, 11),
+    (61, 0),
+    (137, 2),
+    (161, 8),
+    (183, 13),
+    (226, 1),
+    (281, 33),
+    (302, 40),
+    (307, 20),
+    (479, 21),
+    (495, 58),
+    (593, 3),
+    (594, 32),
+    (605, 59),
+    (799, 34),
+    (812, 20),
+    (988, 14),
+    (993, 31),
+];
+
+/// (bits, trits, quints) for each ASTC quantization range.
+const RANGES: [(u32, u32, u32); 21] = [
+    (1, 0, 0),
+    (0, 1, 0),
+    (2, 0, 0),
+    (0, 0, 1),
+    (1, 1, 0),
+    (3, 0, 0),
+    (1, 0, 1),
+    (2, 1, 0),
+    (4, 0, 0),
+    (2, 0, 1),
+    (3, 1, 0),
+    (5, 0, 0),
+    (3, 0, 1),
+    (4, 1, 0),
+    (6, 0, 0),
+    (4, 0, 1),
+    (5, 1, 0),
+    (7, 0, 0),
+    (5, 0, 1),
+    (6, 1, 0),
+    (8, 0, 0),
+];
+
+struct Bits {
+    block: u128,
+    position: u32,
+}
+
+impl Bits {
+    fn read(&mut self, count: u32) -> Result<u32> {
+        if self.position + count > 128 {
+            return Err("UASTC block overflows 128 bits");
+        }
+        let value = (self.block >> self.position) as u32 & ((1u64 << count) - 1) as u32;
+        self.position += count;
+        Ok(value)
+    }
+}
+
+/// `value` repeated from the top down to fill `bits`.
+fn replicate(value: u32, from: u32, bits: u32) -> u32 {
+    let mut result = 0;
+    let mut filled = 0;
+    while filled < bits {
+        result = (result << from) | value;
+        filled += from;
+    }
+    result >> (filled - bits)
+}
+
+/// An endpoint value as coded in the block, expanded to eight bits the
+/// way ASTC does.
+fn unquantize_endpoint(value: u32, range: usize) -> u8 {
+    let (bits, trits, quints) = RANGES[range];
+    if trits == 0 && quints == 0 {
+        return replicate(value, bits, 8) as u8;
+    }
+    let digit = value >> bits;
+    let bit = |n: u32| (value >> n) & 1;
+    let (b, c, d, e, f) = (bit(1), bit(2), bit(3), bit(4), bit(5));
+    let (scramble, scale) = match (trits != 0, bits) {
+        (true, 1) => (0, 204),
+        (true, 2) => (b * 0x116, 93),
+        (true, 3) => (c * 0x10a + b * 0x085, 44),
+        (true, 4) => (d * 0x104 + c * 0x082 + b * 0x041, 22),
+        (true, 5) => (e * 0x102 + d * 0x081 + c * 0x040 + b * 0x020, 11),
+        (true, _) => (f * 0x101 + e * 0x080 + d * 0x040 + c * 0x020 + b * 0x010, 5),
+        (false, 1) => (0, 113),
+        (false, 2) => (b * 0x10c, 54),
+        (false, 3) => (c * 0x105 + b * 0x082, 26),
+        (false, 4) => (d * 0x102 + c * 0x081 + b * 0x040, 13),
+        (false, _) => (e * 0x101 + d * 0x080 + c * 0x040 + b * 0x020, 6),
+    };
+    let low = if value & 1 != 0 { 0x1ff } else { 0 };
+    let t = (digit * scale + scramble) ^ low;
+    ((low & 0x80) | (t >> 2)) as u8
+}
+
+/// A weight expanded to ASTC's 0..=64. UASTC only uses plain-bit ranges.
+fn unquantize_weight(value: u32, bits: u32) -> u32 {
+    let weight = replicate(value, bits, 6);
+    weight + (weight > 32) as u32
+}
+
+/// Endpoint values as stored: the trits or quints of every value packed
+/// into whole numbers first, then each value's low bits.
+fn read_endpoints(bits: &mut Bits, count: usize, range: usize) -> Result<Vec<u8>> {
+    let (low_bits, trits, quints) = RANGES[range];
+    let (group, base, group_bits) = match (trits, quints) {
+        (0, 0) => (0, 1, 0),
+        (1, _) => (5, 3, 8),
+        _ => (3, 5, 7),
+    };
+    let mut digits = Vec::with_capacity(count);
+    if group > 0 {
+        for start in (0..count).step_by(group) {
+            let in_group = group.min(count - start);
+            // A short last group only takes the bits its digits need.
+            let packed_bits = match (base, in_group) {
+                (3, 1) => 2,
+                (3, 2) => 4,
+                (3, 3) => 5,
+                (3, 4) => 7,
+                (5, 1) => 3,
+                (5, 2) => 5,
+                _ => group_bits,
+            };
+            let mut packed = bits.read(packed_bits)?;
+            for _ in 0..in_group {
+                digits.push(packed % base);
+                packed /= base;
+            }
+        }
+    }
+    (0..count)
+        .map(|i| {
+            let low = bits.read(low_bits)?;
+            let digit = digits.get(i).copied().unwrap_or(0);
+            Ok(unquantize_endpoint(digit << low_bits | low, range))
+        })
+        .collect()
+}
+
+/// ASTC's partition hash for a 4x4 block.
+fn select_partition(seed: u32, x: u32, y: u32, count: u32) -> usize {
+    // Small blocks have their coordinates doubled.
+    let (x, y) = (x << 1, y << 1);
+    let seed = seed + (count - 1) * 1024;
+
+    let mut r = seed;
+    r ^= r >> 15;
+    r = r.wrapping_sub(r << 17);
+    r = r.wrapping_add(r << 7);
+    r = r.wrapping_add(r << 4);
+    r ^= r >> 5;
+    r = r.wrapping_add(r << 16);
+    r ^= r >> 7;
+    r ^= r >> 3;
+    r ^= r << 6;
+    r ^= r >> 17;
+
+    let mut s = [
+        r & 15,
+        (r >> 4) & 15,
+        (r >> 8) & 15,
+        (r >> 12) & 15,
+        (r >> 16) & 15,
+        (r >> 20) & 15,
+        (r >> 24) & 15,
+        (r >> 28) & 15,
+    ];
+    let (sh1, sh2) = match (seed & 1 != 0, seed & 2 != 0) {
+        (true, odd) => (if odd { 4 } else { 5 }, if count == 3 { 6 } else { 5 }),
+        (false, odd) => (if count == 3 { 6 } else { 5 }, if odd { 4 } else { 5 }),
+    };
+    for (i, s) in s.iter_mut().enumerate() {
+        *s = (*s * *s) >> if i % 2 == 0 { sh1 } else { sh2 };
+    }
+    // The z terms drop out for a 2D block.
+    let a = (s[0] * x + s[1] * y + (r >> 14)) & 0x3f;
+    let b = (s[2] * x + s[3] * y + (r >> 10)) & 0x3f;
+    let c = if count < 3 {
+        0
+    } else {
+        (s[4] * x + s[5] * y + (r >> 6)) & 0x3f
+    };
+    if a >= b && a >= c {
+        0
+    } else if b >= c {
+        1
+    } else {
+        2
+    }
+}
+
+fn interpolate(low: u8, high: u8, weight: u32) -> u8 {
+    let (low, high) = (low as u32 * 0x101, high as u32 * 0x101);
+    (((low * (64 - weight) + high * weight + 32) >> 6) >> 8) as u8
+}
+
+/// A subset's two RGBA endpoints from its endpoint values, applying ASTC's
+/// blue contraction when the second endpoint is the darker one.
+fn endpoint_pair(values: &[u8], components: usize) -> [[u8; 4]; 2] {
+    if components == 2 {
+        let [l0, l1, a0, a1] = [values[0], values[1], values[2], values[3]];
+        return [[l0, l0, l0, a0], [l1, l1, l1, a1]];
+    }
+    let v = |i: usize| values[i] as u32;
+    let (a0, a1) = match components {
+        4 => (values[6], values[7]),
+        _ => (255, 255),
+    };
+    if v(1) + v(3) + v(5) >= v(0) + v(2) + v(4) {
+        [
+            [values[0], values[2], values[4], a0],
+            [values[1], values[3], values[5], a1],
+        ]
+    } else {
+        let contract =
+            |r: u32, g: u32, b: u32| [((r + b) >> 1) as u8, ((g + b) >> 1) as u8, b as u8];
+        let [r0, g0, b0] = contract(v(1), v(3), v(5));
+        let [r1, g1, b1] = contract(v(0), v(2), v(4));
+        [[r0, g0, b0, a1], [r1, g1, b1, a0]]
+    }
+}
+
+fn pattern(patterns: &[(u32, usize)], index: u32) -> Result<(u32, usize)> {
+    patterns
+        .get(index as usize)
+        .copied()
+        .ok_or("UASTC block names a pattern that doesn't exist")
+}
+
+/// A block's texels, and the BC7 partition that keeps its subsets.
+pub(super) fn decode_block(block: &[u8; 16]) -> Result<([[u8; 4]; 16], Partition)> {
+    let mut bits = Bits {
+        block: u128::from_le_bytes(*block),
+        position: 0,
+    };
+    let index = MODES
+        .iter()
+        .position(|mode| block[0] as u32 & ((1 << mode.code_bits) - 1) == mode.code)
+        .ok_or("UASTC block uses a reserved mode")?;
+    let mode = &MODES[index];
+    bits.position = mode.code_bits;
+
+    if index == SOLID_MODE {
+        let mut color = [0; 4];
+        for channel in &mut color {
+            *channel = bits.read(8)? as u8;
+        }
+        return Ok(([color; 16], Partition::Whole));
+    }
+
+    // The hints only help transcoders that map blocks across directly.
+    bits.position += mode.hint_bits;
+
+    let (subsets, seed, partition) = match mode.partitions {
+        Partitions::One => (1, 0, Partition::Whole),
+        Partitions::Two => {
+            let (seed, bc7) = pattern(&PATTERNS_2, bits.read(5)?)?;
+            (2, seed, Partition::Two(bc7))
+        }
+        Partitions::Three => {
+            let (seed, bc7) = pattern(&PATTERNS_3, bits.read(4)?)?;
+            (3, seed, Partition::Three(bc7))
+        }
+        Partitions::ThreeAsTwo => {
+            let (seed, bc7) = pattern(&PATTERNS_3_AS_2, bits.read(5)?)?;
+            (2, seed, Partition::Three(bc7))
+        }
+    };
+    // The channel that takes the second plane of weights.
+    let plane_channel = match (mode.dual_plane, mode.components) {
+        (false, _) => None,
+        (true, 2) => Some(3),
+        (true, _) => Some(bits.read(2)? as usize),
+    };
+
+    let values_per_subset = mode.components * 2;
+    let values = read_endpoints(
+        &mut bits,
+        values_per_subset * subsets as usize,
+        mode.endpoint_range,
+    )?;
+    let planes = 1 + mode.dual_plane as usize;
+    let mut weights = [[0; 2]; 16];
+    for (texel, texel_weights) in weights.iter_mut().enumerate() {
+        let anchor = partition.is_anchor(texel);
+        for weight in &mut texel_weights[..planes] {
+            let value = bits.read(mode.weight_bits - anchor as u32)?;
+            *weight = unquantize_weight(value, mode.weight_bits);
+        }
+    }
+
+    let endpoints: Vec<_> = values
+        .chunks(values_per_subset)
+        .map(|values| endpoint_pair(values, mode.components))
+        .collect();
+    let mut texels = [[0; 4]; 16];
+    for (texel, color) in texels.iter_mut().enumerate() {
+        let subset = match subsets {
+            1 => 0,
+            _ => select_partition(seed, texel as u32 % 4, texel as u32 / 4, subsets),
+        };
+        let [low, high] = endpoints[subset];
+        for channel in 0..4 {
+            let plane = (plane_channel == Some(channel)) as usize;
+            color[channel] = interpolate(low[channel], high[channel], weights[texel][plane]);
+        }
+    }
+    Ok((texels, partition))
+}
@@ -0,0 +1,299 @@
+// Khronos data format descriptors, which KTX2 uses to say what its texels
+// are. Only the basic descriptor block is read; it's enough to name a DXGI
+// format, or to tell which Basis Universal codec a payload needs.
+
+use super::AssetError;
+use crate::gfx::Format;
+
+const BASIC_BLOCK_HEADER_SIZE: usize = 24;
+const SAMPLE_SIZE: usize = 16;
+
+pub const COLOR_MODEL_RGBSDA: u8 = 1;
+pub const COLOR_MODEL_BC1A: u8 = 128;
+pub const COLOR_MODEL_BC2: u8 = 129;
+pub const COLOR_MODEL_BC3: u8 = 130;
+pub const COLOR_MODEL_BC4: u8 = 131;
+pub const COLOR_MODEL_BC5: u8 = 132;
+pub const COLOR_MODEL_BC6H: u8 = 133;
+pub const COLOR_MODEL_BC7: u8 = 134;
+pub const COLOR_MODEL_ETC1S: u8 = 163;
+pub const COLOR_MODEL_UASTC: u8 = 166;
+
+pub const TRANSFER_FUNCTION_SRGB: u8 = 2;
+
+pub const CHANNEL_RED: u8 = 0;
+pub const CHANNEL_GREEN: u8 = 1;
+pub const CHANNEL_BLUE: u8 = 2;
+pub const CHANNEL_DEPTH: u8 = 14;
+pub const CHANNEL_ALPHA: u8 = 15;
+/// UASTC's channel ids that carry alpha: RGBA and RRRG.
+const UASTC_CHANNEL_RGBA: u8 = 3;
+const UASTC_CHANNEL_RRRG: u8 = 5;
+
+/// Qualifiers in the top nibble of a sample's channel type.
+pub const SAMPLE_LINEAR: u8 = 0x10;
+pub const SAMPLE_SIGNED: u8 = 0x40;
+pub const SAMPLE_FLOAT: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub bit_offset: u16,
+    pub bit_length: u32,
+    /// The channel id in the low nibble and qualifiers in the high one.
+    pub channel_type: u8,
+    pub lower: u32,
+    pub upper: u32,
+}
+
+impl Sample {
+    pub fn channel(&self) -> u8 {
+        self.channel_type & 0xf
+    }
+
+    fn kind(&self) -> SampleKind {
+        let (float, signed) = (
+            self.channel_type & SAMPLE_FLOAT != 0,
+            self.channel_type & SAMPLE_SIGNED != 0,
+        );
+        // Integer formats say so with an upper bound of one.
+        let normalized = self.upper != 1;
+        match (float, signed, normalized) {
+            (true, true, _) => SampleKind::Float,
+            (true, false, _) => SampleKind::UnsignedFloat,
+            (false, true, true) => SampleKind::Snorm,
+            (false, true, false) => SampleKind::Sint,
+            (false, false, true) => SampleKind::Unorm,
+            (false, false, false) => SampleKind::Uint,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SampleKind {
+    Unorm,
+    Snorm,
+    Uint,
+    Sint,
+    Float,
+    UnsignedFloat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BasisCodec {
+    Etc1s,
+    Uastc,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataFormatDescriptor {
+    pub color_model: u8,
+    pub color_primaries: u8,
+    pub transfer_function: u8,
+    pub flags: u8,
+    /// Texels per block in each dimension.
+    pub block_size: [u32; 4],
+    pub bytes_plane: [u8; 8],
+    pub samples: Vec<Sample>,
+}
+
+impl DataFormatDescriptor {
+    /// Parses a DFD, starting with its total size, and keeps the first
+    /// descriptor block.
+    pub fn parse(bytes: &[u8]) -> Result<Self, AssetError> {
+        let invalid = |message: &str| AssetError::Invalid(format!("invalid KTX2 DFD: {message}"));
+        let u32_at = |offset: usize| -> Result<u32, AssetError> {
+            let field = bytes.get(offset..offset + 4).ok_or(AssetError::Truncated {
+                expected: offset + 4,
+                actual: bytes.len(),
+            })?;
+            Ok(u32::from_le_bytes(field.try_into().unwrap()))
+        };
+        let total_size = u32_at(0)? as usize;
+        if total_size != bytes.len() {
+            return Err(invalid("its size doesn't match"));
+        }
+
+        let descriptor = u32_at(4)?;
+        let (vendor, descriptor_type) = (descriptor & 0x1_ffff, descriptor >> 17);
+        if vendor != 0 || descriptor_type != 0 {
+            return Err(AssetError::Unsupported(format!(
+                "KTX2 DFD blocks from vendor {vendor} of type {descriptor_type}"
+            )));
+        }
+        let block_size = (u32_at(8)? >> 16) as usize;
+        let block = bytes
+            .get(4..4 + block_size)
+            .ok_or_else(|| invalid("the basic block is bigger than the DFD"))?;
+        if block_size < BASIC_BLOCK_HEADER_SIZE
+            || !(block_size - BASIC_BLOCK_HEADER_SIZE).is_multiple_of(SAMPLE_SIZE)
+        {
+            return Err(invalid("the basic block's size isn't whole samples"));
+        }
+
+        let samples = block[BASIC_BLOCK_HEADER_SIZE..]
+            .chunks_exact(SAMPLE_SIZE)
+            .map(|sample| Sample {
+                bit_offset: u16::from_le_bytes([sample[0], sample[1]]),
+                bit_length: sample[2] as u32 + 1,
+                channel_type: sample[3],
+                lower: u32::from_le_bytes(sample[8..12].try_into().unwrap()),
+                upper: u32::from_le_bytes(sample[12..16].try_into().unwrap()),
+            })
+            .collect();
+        Ok(Self {
+            color_model: block[8],
+            color_primaries: block[9],
+            transfer_function: block[10],
+            flags: block[11],
+            block_size: [0, 1, 2, 3].map(|i| block[12 + i] as u32 + 1),
+            bytes_plane: block[16..24].try_into().unwrap(),
+            samples,
+        })
+    }
+
+    pub fn srgb(&self) -> bool {
+        self.transfer_function == TRANSFER_FUNCTION_SRGB
+    }
+
+    pub fn basis_codec(&self) -> Option<BasisCodec> {
+        match self.color_model {
+            COLOR_MODEL_ETC1S => Some(BasisCodec::Etc1s),
+            COLOR_MODEL_UASTC => Some(BasisCodec::Uastc),
+            _ => None,
+        }
+    }
+
+    /// Whether a Basis payload has alpha: ETC1S keeps it in a second slice
+    /// and UASTC says so with its channel id.
+    pub fn basis_has_alpha(&self) -> bool {
+        match self.basis_codec() {
+            Some(BasisCodec::Etc1s) => self.samples.len() > 1,
+            Some(BasisCodec::Uastc) => self.samples.first().is_some_and(|sample| {
+                matches!(sample.channel(), UASTC_CHANNEL_RGBA | UASTC_CHANNEL_RRRG)
+            }),
+            None => false,
+        }
+    }
+
+    /// The BC format a Basis payload becomes for D3D12: BC7 for UASTC and
+    /// for ETC1S with alpha, BC1 for opaque ETC1S.
+    pub fn transcode_format(&self) -> Option<Format> {
+        let srgb = self.srgb();
+        let format = match self.basis_codec()? {
+            BasisCodec::Etc1s if !self.basis_has_alpha() => {
+                [Format::BC1_UNORM, Format::BC1_UNORM_SRGB]
+            }
+            _ => [Format::BC7_UNORM, Format::BC7_UNORM_SRGB],
+        };
+        Some(format[srgb as usize])
+    }
+
+    /// The DXGI format with this layout, if there is one.
+    pub fn format(&self) -> Option<Format> {
+        let srgb = self.srgb();
+        let signed = self
+            .samples
+            .first()
+            .is_some_and(|sample| sample.channel_type & SAMPLE_SIGNED != 0);
+        let with_srgb = |linear: Format, encoded: Format| if srgb { encoded } else { linear };
+        let format = match self.color_model {
+            COLOR_MODEL_RGBSDA => return self.uncompressed_format(),
+            COLOR_MODEL_BC1A => with_srgb(Format::BC1_UNORM, Format::BC1_UNORM_SRGB),
+            COLOR_MODEL_BC2 => with_srgb(Format::BC2_UNORM, Format::BC2_UNORM_SRGB),
+            COLOR_MODEL_BC3 => with_srgb(Format::BC3_UNORM, Format::BC3_UNORM_SRGB),
+            COLOR_MODEL_BC4 if signed => Format::BC4_SNORM,
+            COLOR_MODEL_BC4 => Format::BC4_UNORM,
+            COLOR_MODEL_BC5 if signed => Format::BC5_SNORM,
+            COLOR_MODEL_BC5 => Format::BC5_UNORM,
+            COLOR_MODEL_BC6H if signed => Format::BC6H_SF16,
+            COLOR_MODEL_BC6H => Format::BC6H_UF16,
+            COLOR_MODEL_BC7 => with_srgb(Format::BC7_UNORM, Format::BC7_UNORM_SRGB),
+            _ => return None,
+        };
+        (self.block_size[..2] == [4, 4]).then_some(format)
+    }
+
+    /// Formats of one texel per block, told apart by where each channel
+    /// sits and what kind of number it is.
+    fn uncompressed_format(&self) -> Option<Format> {
+        use SampleKind::*;
+        const R: u8 = CHANNEL_RED;
+        const G: u8 = CHANNEL_GREEN;
+        const B: u8 = CHANNEL_BLUE;
+        const A: u8 = CHANNEL_ALPHA;
+        const D: u8 = CHANNEL_DEPTH;
+
+        if self.block_size != [1; 4] {
+            return None;
+        }
+        let kind = self.samples.first()?.kind();
+        if self.samples.iter().any(|sample| sample.kind() != kind) {
+            return None;
+        }
+        // Writers differ on sample order, so go by bit offset.
+        let mut layout: Vec<_> = self
+            .samples
+            .iter()
+            .map(|sample| (sample.channel(), sample.bit_offset, sample.bit_length))
+            .collect();
+        layout.sort_by_key(|&(_, offset, _)| offset);
+        let srgb = self.srgb();
+
+        let format = match (kind, layout.as_slice()) {
+            (Unorm, [(R, 0, 8)]) => Format::R8_UNORM,
+            (Unorm, [(A, 0, 8)]) => Format::A8_UNORM,
+            (Unorm, [(R, 0, 8), (G, 8, 8)]) => Format::R8G8_UNORM,
+            (Unorm, [(R, 0, 8), (G, 8, 8), (B, 16, 8), (A, 24, 8)]) if srgb => {
+                Format::R8G8B8A8_UNORM_SRGB
+            }
+            (Unorm, [(R, 0, 8), (G, 8, 8), (B, 16, 8), (A, 24, 8)]) => Format::R8G8B8A8_UNORM,
+            (Unorm, [(B, 0, 8), (G, 8, 8), (R, 16, 8), (A, 24, 8)]) if srgb => {
+                Format::B8G8R8A8_UNORM_SRGB
+            }
+            (Unorm, [(B, 0, 8), (G, 8, 8), (R, 16, 8), (A, 24, 8)]) => Format::B8G8R8A8_UNORM,
+            (Unorm, [(R, 0, 10), (G, 10, 10), (B, 20, 10), (A, 30, 2)]) => {
+                Format::R10G10B10A2_UNORM
+            }
+            (Unorm, [(B, 0, 5), (G, 5, 6), (R, 11, 5)]) => Format::B5G6R5_UNORM,
+            (Unorm, [(B, 0, 5), (G, 5, 5), (R, 10, 5), (A, 15, 1)]) => Format::B5G5R5A1_UNORM,
+            (Unorm, [(B, 0, 4), (G, 4, 4), (R, 8, 4), (A, 12, 4)]) => Format::B4G4R4A4_UNORM,
+            (Unorm, [(R, 0, 16)]) => Format::R16_UNORM,
+            (Unorm, [(R, 0, 16), (G, 16, 16)]) => Format::R16G16_UNORM,
+            (Unorm, [(R, 0, 16), (G, 16, 16), (B, 32, 16), (A, 48, 16)]) => {
+                Format::R16G16B16A16_UNORM
+            }
+            (Snorm, [(R, 0, 16), (G, 16, 16), (B, 32, 16), (A, 48, 16)]) => {
+                Format::R16G16B16A16_SNORM
+            }
+            (Uint, [(R, 0, 8), (G, 8, 8), (B, 16, 8), (A, 24, 8)]) => Format::R8G8B8A8_UINT,
+            (Uint, [(R, 0, 16)]) => Format::R16_UINT,
+            (Uint, [(R, 0, 32)]) => Format::R32_UINT,
+            (Uint, [(R, 0, 32), (G, 32, 32)]) => Format::R32G32_UINT,
+            (Uint, [(R, 0, 32), (G, 32, 32), (B, 64, 32)]) => Format::R32G32B32_UINT,
+            (Uint, [(R, 0, 32), (G, 32, 32), (B, 64, 32), (A, 96, 32)]) => {
+                Format::R32G32B32A32_UINT
+            }
+            (Sint, [(R, 0, 32)]) => Format::R32_SINT,
+            (Sint, [(R, 0, 32), (G, 32, 32)]) => Format::R32G32_SINT,
+            (Sint, [(R, 0, 32), (G, 32, 32), (B, 64, 32)]) => Format::R32G32B32_SINT,
+            (Sint, [(R, 0, 32), (G, 32, 32), (B, 64, 32), (A, 96, 32)]) => {
+                Format::R32G32B32A32_SINT
+            }
+            (Float, [(R, 0, 16)]) => Format::R16_FLOAT,
+            (Float, [(R, 0, 16), (G, 16, 16)]) => Format::R16G16_FLOAT,
+            (Float, [(R, 0, 16), (G, 16, 16), (B, 32, 16), (A, 48, 16)]) => {
+                Format::R16G16B16A16_FLOAT
+            }
+            (Float, [(R, 0, 32)]) => Format::R32_FLOAT,
+            (Float, [(D, 0, 32)]) => Format::D32_FLOAT,
+            (Float, [(R, 0, 32), (G, 32, 32)]) => Format::R32G32_FLOAT,
+            (Float, [(R, 0, 32), (G, 32, 32), (B, 64, 32)]) => Format::R32G32B32_FLOAT,
+            (Float, [(R, 0, 32), (G, 32, 32), (B, 64, 32), (A, 96, 32)]) => {
+                Format::R32G32B32A32_FLOAT
+            }
+            (UnsignedFloat, [(R, 0, 11), (G, 11, 11), (B, 22, 10)]) => Format::R11G11B10_FLOAT,
+            _ => return None,
+        };
+        Some(format)
+    }
+}
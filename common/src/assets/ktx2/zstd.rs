@@ -0,0 +1,903 @@
+// A Zstandard decompressor (RFC 8878) for KTX2's supercompression: every
+// block type, Huffman coded literals and FSE coded sequences. Frames that need
+// a dictionary aren't supported.
+
+const FRAME_MAGIC: u32 = 0xfd2f_b528;
+/// Skippable frames use any of the 16 magic numbers from this one up.
+const SKIPPABLE_MAGIC: u32 = 0x184d_2a50;
+const MAX_BLOCK_SIZE: usize = 128 * 1024;
+
+const MAX_HUFFMAN_BITS: u32 = 11;
+const MAX_LITERALS_LENGTH_CODE: usize = 35;
+const MAX_MATCH_LENGTH_CODE: usize = 52;
+const MAX_OFFSET_CODE: usize = 31;
+
+/// RFC 8878's predefined distributions, with -1 for "less than one".
+const LITERALS_LENGTH_DEFAULT: (u32, &[i16]) = (
+    6,
+    &[
+        4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1,
+        1, 1, -1, -1, -1, -1,
+    ],
+);
+const MATCH_LENGTH_DEFAULT: (u32, &[i16]) = (
+    6,
+    &[
+        1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+    ],
+);
+const OFFSET_DEFAULT: (u32, &[i16]) = (
+    5,
+    &[
+        1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+    ],
+);
+
+/// Baselines and extra bits for each literals length and match length code.
+const LITERALS_LENGTHS: [(u32, u32); 36] = [
+    (0, 0),
+    (1, 0),
+    (2, 0),
+    (3, 0),
+    (4, 0),
+    (5, 0),
+    (6, 0),
+    (7, 0),
+    (8, 0),
+    (9, 0),
+    (10, 0),
+    (11, 0),
+    (12, 0),
+    (13, 0),
+    (14, 0),
+    (15, 0),
+    (16, 1),
+    (18, 1),
+    (20, 1),
+    (22, 1),
+    (24, 2),
+    (28, 2),
+    (32, 3),
+    (40, 3),
+    (48, 4),
+    (64, 6),
+    (128, 7),
+    (256, 8),
+    (512, 9),
+    (1024, 10),
+    (2048, 11),
+    (4096, 12),
+    (8192, 13),
+    (16384, 14),
+    (32768, 15),
+    (65536, 16),
+];
+const MATCH_LENGTHS: [(u32, u32); 53] = {
+    let mut lengths = [(0, 0); 53];
+    let mut code = 0;
+    while code < 32 {
+        lengths[code] = (code as u32 + 3, 0);
+        code += 1;
+    }
+    let tail = [
+        (35, 1),
+        (37, 1),
+        (39, 1),
+        (41, 1),
+        (43, 2),
+        (47, 2),
+        (51, 3),
+        (59, 3),
+        (67, 4),
+        (83, 4),
+        (99, 5),
+        (131, 7),
+        (259, 8),
+        (515, 9),
+        (1027, 10),
+        (2051, 11),
+        (4099, 12),
+        (8195, 13),
+        (16387, 14),
+        (32771, 15),
+        (65539, 16),
+    ];
+    while code < 53 {
+        lengths[code] = tail[code - 32];
+        code += 1;
+    }
+    lengths
+};
+
+type Result<T> = std::result::Result<T, &'static str>;
+
+fn truncated() -> &'static str {
+    "zstd data is truncated"
+}
+
+/// Reads bits least significant first, for FSE table descriptions.
+struct ForwardBits<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl ForwardBits<'_> {
+    fn peek(&self, count: u32) -> u32 {
+        (0..count).fold(0, |value, i| {
+            let position = self.position + i as usize;
+            let bit = self
+                .data
+                .get(position / 8)
+                .map_or(0, |byte| (byte >> (position % 8)) & 1);
+            value | (bit as u32) << i
+        })
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32> {
+        if self.position + count as usize > self.data.len() * 8 {
+            return Err(truncated());
+        }
+        let value = self.peek(count);
+        self.position += count as usize;
+        Ok(value)
+    }
+}
+
+/// Reads from the end of a stream towards its start, which is how Huffman
+/// and FSE streams are written. The last byte's highest set bit marks where
+/// the stream starts, and bits past the start read as zeros.
+struct BackwardBits<'a> {
+    data: &'a [u8],
+    /// Bits left to read, negative once reads have gone past the start.
+    remaining: isize,
+}
+
+impl<'a> BackwardBits<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        match data.last() {
+            Some(&last) if last != 0 => Ok(Self {
+                data,
+                remaining: data.len() as isize * 8 - last.leading_zeros() as isize - 1,
+            }),
+            _ => Err("a zstd bitstream has no end marker"),
+        }
+    }
+
+    /// Up to 32 bits, the first read the most significant.
+    fn peek(&self, count: u32) -> u32 {
+        let start = self.remaining - count as isize;
+        let (start, count, shift) = if start < 0 {
+            (0, (count as isize + start).max(0) as u32, -start as u32)
+        } else {
+            (start as usize, count, 0)
+        };
+        if count == 0 {
+            return 0;
+        }
+        let mut window = [0; 8];
+        let bytes = self.data.get(start / 8..).unwrap_or_default();
+        let len = bytes.len().min(8);
+        window[..len].copy_from_slice(&bytes[..len]);
+        let value = (u64::from_le_bytes(window) >> (start % 8)) & ((1 << count) - 1);
+        (value as u32) << shift
+    }
+
+    fn consume(&mut self, count: u32) {
+        self.remaining -= count as isize;
+    }
+
+    fn bits(&mut self, count: u32) -> u32 {
+        let value = self.peek(count);
+        self.consume(count);
+        value
+    }
+
+    fn overflowed(&self) -> bool {
+        self.remaining < 0
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct FseEntry {
+    symbol: u8,
+    bits: u8,
+    baseline: u16,
+}
+
+/// A finite state entropy decoding table.
+#[derive(Clone, Default)]
+struct Fse {
+    accuracy_log: u32,
+    entries: Vec<FseEntry>,
+}
+
+impl Fse {
+    fn new(accuracy_log: u32, counts: &[i16]) -> Self {
+        let size = 1usize << accuracy_log;
+        let mut entries = vec![FseEntry::default(); size];
+        let mut next = vec![0u32; counts.len()];
+
+        // "Less than one" symbols take the last cells, then the rest are
+        // spread over the table with a fixed step.
+        let mut high = size - 1;
+        for (symbol, &count) in counts.iter().enumerate() {
+            if count == -1 {
+                entries[high].symbol = symbol as u8;
+                high = high.wrapping_sub(1);
+                next[symbol] = 1;
+            } else {
+                next[symbol] = count.max(0) as u32;
+            }
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in counts.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                entries[position].symbol = symbol as u8;
+                loop {
+                    position = (position + step) & (size - 1);
+                    if position <= high {
+                        break;
+                    }
+                }
+            }
+        }
+
+        for entry in &mut entries {
+            let state = next[entry.symbol as usize];
+            next[entry.symbol as usize] += 1;
+            let bits = accuracy_log - (31 - state.leading_zeros());
+            entry.bits = bits as u8;
+            entry.baseline = ((state << bits) - size as u32) as u16;
+        }
+        Self {
+            accuracy_log,
+            entries,
+        }
+    }
+
+    fn rle(symbol: u8) -> Self {
+        Self {
+            accuracy_log: 0,
+            entries: vec![FseEntry {
+                symbol,
+                bits: 0,
+                baseline: 0,
+            }],
+        }
+    }
+
+    /// Reads a table description, returning the table and its size in bytes.
+    fn read(data: &[u8], max_symbol: usize, max_accuracy_log: u32) -> Result<(Self, usize)> {
+        let mut bits = ForwardBits { data, position: 0 };
+        let accuracy_log = bits.bits(4)? + 5;
+        if accuracy_log > max_accuracy_log {
+            return Err("a zstd FSE table is too precise");
+        }
+
+        let mut counts = Vec::new();
+        let mut remaining = (1i32 << accuracy_log) + 1;
+        let mut threshold = 1i32 << accuracy_log;
+        let mut width = accuracy_log + 1;
+        while remaining > 1 {
+            if counts.len() > max_symbol {
+                return Err("a zstd FSE table has too many symbols");
+            }
+            let max = 2 * threshold - 1 - remaining;
+            let low = bits.peek(width - 1) as i32;
+            let value = if low < max {
+                bits.bits(width - 1)?;
+                low
+            } else {
+                let value = bits.bits(width)? as i32;
+                if value >= threshold {
+                    value - max
+                } else {
+                    value
+                }
+            };
+            let count = value - 1;
+            remaining -= count.abs();
+            counts.push(count as i16);
+            while remaining < threshold {
+                width -= 1;
+                threshold >>= 1;
+            }
+
+            // Zero is followed by 2-bit repeat counts of more zeros, with 3
+            // meaning another count follows.
+            if count == 0 {
+                loop {
+                    let repeat = bits.bits(2)?;
+                    counts.extend(std::iter::repeat_n(0, repeat as usize));
+                    if repeat != 3 {
+                        break;
+                    }
+                }
+            }
+        }
+        if remaining != 1 || counts.len() > max_symbol + 1 {
+            return Err("a zstd FSE table's counts don't add up");
+        }
+        Ok((Self::new(accuracy_log, &counts), bits.position.div_ceil(8)))
+    }
+}
+
+struct FseState<'a> {
+    table: &'a Fse,
+    state: usize,
+}
+
+impl<'a> FseState<'a> {
+    fn new(table: &'a Fse, bits: &mut BackwardBits) -> Self {
+        Self {
+            table,
+            state: bits.bits(table.accuracy_log) as usize,
+        }
+    }
+
+    fn symbol(&self) -> u8 {
+        self.table.entries[self.state].symbol
+    }
+
+    fn update(&mut self, bits: &mut BackwardBits) {
+        let entry = self.table.entries[self.state];
+        self.state = entry.baseline as usize + bits.bits(entry.bits as u32) as usize;
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct HuffmanEntry {
+    symbol: u8,
+    bits: u8,
+}
+
+/// A table indexed by the next `max_bits` bits of the stream.
+#[derive(Default)]
+struct Huffman {
+    max_bits: u32,
+    entries: Vec<HuffmanEntry>,
+}
+
+impl Huffman {
+    /// Reads a tree description, returning the table and its size in bytes.
+    fn read(data: &[u8]) -> Result<(Self, usize)> {
+        let &header = data.first().ok_or_else(truncated)?;
+        let (mut weights, size) = if header < 128 {
+            // FSE compressed, with two states taking turns.
+            let size = 1 + header as usize;
+            let compressed = data.get(1..size).ok_or_else(truncated)?;
+            let (table, table_size) = Fse::read(compressed, 255, 6)?;
+            let mut bits = BackwardBits::new(&compressed[table_size..])?;
+            let mut states = [
+                FseState::new(&table, &mut bits),
+                FseState::new(&table, &mut bits),
+            ];
+            let mut weights = Vec::new();
+            'decode: loop {
+                for turn in 0..2 {
+                    weights.push(states[turn].symbol());
+                    states[turn].update(&mut bits);
+                    if bits.overflowed() {
+                        weights.push(states[1 - turn].symbol());
+                        break 'decode;
+                    }
+                }
+                if weights.len() > 255 {
+                    return Err("a zstd Huffman tree has too many weights");
+                }
+            }
+            (weights, size)
+        } else {
+            // Four bits each.
+            let count = header as usize - 127;
+            let size = 1 + count.div_ceil(2);
+            let packed = data.get(1..size).ok_or_else(truncated)?;
+            let weights = (0..count)
+                .map(|i| {
+                    let byte = packed[i / 2];
+                    if i % 2 == 0 {
+                        byte >> 4
+                    } else {
+                        byte & 0xf
+                    }
+                })
+                .collect();
+            (weights, size)
+        };
+
+        // The last symbol's weight is whatever makes the total a power of two.
+        if weights.len() > 255
+            || weights
+                .iter()
+                .any(|&weight| weight > MAX_HUFFMAN_BITS as u8)
+        {
+            return Err("invalid zstd Huffman weights");
+        }
+        let total: u32 = weights
+            .iter()
+            .filter(|&&weight| weight > 0)
+            .map(|&weight| 1 << (weight - 1))
+            .sum();
+        if total == 0 {
+            return Err("invalid zstd Huffman weights");
+        }
+        let max_bits = 32 - total.leading_zeros();
+        let left = (1 << max_bits) - total;
+        if max_bits > MAX_HUFFMAN_BITS || !left.is_power_of_two() {
+            return Err("invalid zstd Huffman weights");
+        }
+        weights.push(left.trailing_zeros() as u8 + 1);
+
+        // Codes go to the lightest symbols first, in symbol order.
+        let mut entries = Vec::with_capacity(1 << max_bits);
+        for weight in 1..=max_bits as u8 {
+            for (symbol, _) in weights.iter().enumerate().filter(|(_, &w)| w == weight) {
+                let entry = HuffmanEntry {
+                    symbol: symbol as u8,
+                    bits: (max_bits + 1 - weight as u32) as u8,
+                };
+                entries.extend(std::iter::repeat_n(entry, 1 << (weight - 1)));
+            }
+        }
+        Ok((Self { max_bits, entries }, size))
+    }
+
+    fn decode_stream(&self, data: &[u8], out: &mut Vec<u8>, count: usize) -> Result<()> {
+        let mut bits = BackwardBits::new(data)?;
+        for _ in 0..count {
+            let entry = self.entries[bits.peek(self.max_bits) as usize];
+            bits.consume(entry.bits as u32);
+            out.push(entry.symbol);
+        }
+        if bits.remaining != 0 {
+            return Err("a zstd Huffman stream's length doesn't match");
+        }
+        Ok(())
+    }
+}
+
+/// What carries over from one block to the next within a frame.
+struct FrameState {
+    huffman: Option<Huffman>,
+    literals_lengths: Option<Fse>,
+    offsets: Option<Fse>,
+    match_lengths: Option<Fse>,
+    repeat_offsets: [usize; 3],
+}
+
+/// Returns the literals and the bytes after them.
+fn read_literals<'a>(block: &'a [u8], state: &mut FrameState) -> Result<(Vec<u8>, &'a [u8])> {
+    let &first = block.first().ok_or_else(truncated)?;
+    let header = |len: usize| -> Result<u64> {
+        let bytes = block.get(..len).ok_or_else(truncated)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| value << 8 | byte as u64))
+    };
+    let kind = first & 3;
+    let size_format = (first >> 2) & 3;
+
+    if kind < 2 {
+        // Raw or a single byte repeated.
+        let (header_size, size) = match size_format {
+            0 | 2 => (1, first as usize >> 3),
+            1 => (2, header(2)? as usize >> 4),
+            _ => (3, header(3)? as usize >> 4),
+        };
+        let rest = &block[header_size..];
+        return if kind == 0 {
+            let literals = rest.get(..size).ok_or_else(truncated)?;
+            Ok((literals.to_vec(), &rest[size..]))
+        } else {
+            let &byte = rest.first().ok_or_else(truncated)?;
+            Ok((vec![byte; size], &rest[1..]))
+        };
+    }
+
+    let (header_size, size_bits, streams) = match size_format {
+        0 => (3, 10, 1),
+        1 => (3, 10, 4),
+        2 => (4, 14, 4),
+        _ => (5, 18, 4),
+    };
+    let value = header(header_size)?;
+    let mask = (1 << size_bits) - 1;
+    let regenerated_size = (value >> 4 & mask) as usize;
+    let compressed_size = (value >> (4 + size_bits) & mask) as usize;
+    if regenerated_size > MAX_BLOCK_SIZE {
+        return Err("zstd literals are bigger than a block");
+    }
+    let compressed = block
+        .get(header_size..header_size + compressed_size)
+        .ok_or_else(truncated)?;
+    let rest = &block[header_size + compressed_size..];
+
+    let mut data = compressed;
+    if kind == 2 {
+        let (huffman, size) = Huffman::read(compressed)?;
+        state.huffman = Some(huffman);
+        data = &compressed[size..];
+    }
+    let huffman = state
+        .huffman
+        .as_ref()
+        .ok_or("zstd literals reuse a Huffman tree there isn't")?;
+
+    let mut literals = Vec::with_capacity(regenerated_size);
+    if streams == 1 {
+        huffman.decode_stream(data, &mut literals, regenerated_size)?;
+    } else {
+        // A jump table of the first three streams' sizes.
+        let jump = data.get(..6).ok_or_else(truncated)?;
+        let sizes = [0, 2, 4].map(|i| u16::from_le_bytes([jump[i], jump[i + 1]]) as usize);
+        let mut streams_data = &data[6..];
+        let each = regenerated_size.div_ceil(4);
+        for size in sizes {
+            if size > streams_data.len() {
+                return Err(truncated());
+            }
+            huffman.decode_stream(&streams_data[..size], &mut literals, each)?;
+            streams_data = &streams_data[size..];
+        }
+        let count = regenerated_size
+            .checked_sub(3 * each)
+            .ok_or("zstd literals are too short for four streams")?;
+        huffman.decode_stream(streams_data, &mut literals, count)?;
+    }
+    Ok((literals, rest))
+}
+
+/// Picks the table for one kind of sequence symbol by its compression mode,
+/// returning the bytes after any table description.
+fn read_table<'a>(
+    mode: u8,
+    data: &'a [u8],
+    table: &mut Option<Fse>,
+    default: (u32, &[i16]),
+    max_symbol: usize,
+    max_accuracy_log: u32,
+) -> Result<&'a [u8]> {
+    match mode {
+        0 => {
+            *table = Some(Fse::new(default.0, default.1));
+            Ok(data)
+        }
+        1 => {
+            let &symbol = data.first().ok_or_else(truncated)?;
+            if symbol as usize > max_symbol {
+                return Err("a zstd RLE symbol is out of range");
+            }
+            *table = Some(Fse::rle(symbol));
+            Ok(&data[1..])
+        }
+        2 => {
+            let (fse, size) = Fse::read(data, max_symbol, max_accuracy_log)?;
+            *table = Some(fse);
+            Ok(&data[size..])
+        }
+        _ => match table {
+            Some(_) => Ok(data),
+            None => Err("zstd sequences repeat a table there isn't"),
+        },
+    }
+}
+
+fn decompress_block(block: &[u8], state: &mut FrameState, out: &mut Vec<u8>) -> Result<()> {
+    let (literals, rest) = read_literals(block, state)?;
+
+    let sequence_count = match *rest {
+        [] => return Err(truncated()),
+        [0, ..] => 0,
+        [byte @ 1..=127, ..] => byte as usize,
+        [byte @ 128..=254, next, ..] => ((byte as usize - 128) << 8) + next as usize,
+        [255, lo, hi, ..] => u16::from_le_bytes([lo, hi]) as usize + 0x7f00,
+        _ => return Err(truncated()),
+    };
+    let header_size = match rest[0] {
+        0..=127 => 1,
+        128..=254 => 2,
+        255 => 3,
+    };
+    if sequence_count == 0 {
+        out.extend_from_slice(&literals);
+        return Ok(());
+    }
+
+    let &modes = rest.get(header_size).ok_or_else(truncated)?;
+    let data = &rest[header_size + 1..];
+    let data = read_table(
+        modes >> 6,
+        data,
+        &mut state.literals_lengths,
+        LITERALS_LENGTH_DEFAULT,
+        MAX_LITERALS_LENGTH_CODE,
+        9,
+    )?;
+    let data = read_table(
+        modes >> 4 & 3,
+        data,
+        &mut state.offsets,
+        OFFSET_DEFAULT,
+        MAX_OFFSET_CODE,
+        8,
+    )?;
+    let data = read_table(
+        modes >> 2 & 3,
+        data,
+        &mut state.match_lengths,
+        MATCH_LENGTH_DEFAULT,
+        MAX_MATCH_LENGTH_CODE,
+        9,
+    )?;
+
+    let mut bits = BackwardBits::new(data)?;
+    let tables = (
+        state.literals_lengths.as_ref().unwrap(),
+        state.offsets.as_ref().unwrap(),
+        state.match_lengths.as_ref().unwrap(),
+    );
+    let mut literals_length_state = FseState::new(tables.0, &mut bits);
+    let mut offset_state = FseState::new(tables.1, &mut bits);
+    let mut match_length_state = FseState::new(tables.2, &mut bits);
+
+    let mut literals_left = &literals[..];
+    let repeat = &mut state.repeat_offsets;
+    for i in 0..sequence_count {
+        let offset_code = offset_state.symbol() as u32;
+        let (match_base, match_bits) = MATCH_LENGTHS[match_length_state.symbol() as usize];
+        let (literals_base, literals_bits) =
+            LITERALS_LENGTHS[literals_length_state.symbol() as usize];
+        if offset_code > MAX_OFFSET_CODE as u32 {
+            return Err("a zstd offset code is out of range");
+        }
+        let offset_value = (1usize << offset_code) + bits.bits(offset_code) as usize;
+        let match_length = (match_base + bits.bits(match_bits)) as usize;
+        let literals_length = (literals_base + bits.bits(literals_bits)) as usize;
+
+        // Values 1 to 3 pick a recent offset, shifted by one when there are
+        // no literals.
+        let offset = if offset_value > 3 {
+            let offset = offset_value - 3;
+            *repeat = [offset, repeat[0], repeat[1]];
+            offset
+        } else {
+            let index = offset_value - 1 + (literals_length == 0) as usize;
+            match index {
+                0 => repeat[0],
+                1 => {
+                    *repeat = [repeat[1], repeat[0], repeat[2]];
+                    repeat[0]
+                }
+                2 => {
+                    *repeat = [repeat[2], repeat[0], repeat[1]];
+                    repeat[0]
+                }
+                _ => {
+                    let offset = repeat[0] - 1;
+                    if offset == 0 {
+                        return Err("a zstd offset is zero");
+                    }
+                    *repeat = [offset, repeat[0], repeat[1]];
+                    offset
+                }
+            }
+        };
+
+        let copied = literals_left
+            .get(..literals_length)
+            .ok_or("zstd sequences use more literals than there are")?;
+        out.extend_from_slice(copied);
+        literals_left = &literals_left[literals_length..];
+        if offset > out.len() {
+            return Err("a zstd match reaches before the start");
+        }
+        // Byte by byte, since the match may overlap what it copies.
+        let start = out.len() - offset;
+        for i in 0..match_length {
+            out.push(out[start + i]);
+        }
+
+        if i + 1 < sequence_count {
+            literals_length_state.update(&mut bits);
+            match_length_state.update(&mut bits);
+            offset_state.update(&mut bits);
+        }
+        if bits.overflowed() {
+            return Err("zstd sequences run past their bitstream");
+        }
+    }
+    if bits.remaining != 0 {
+        return Err("zstd sequences don't use their whole bitstream");
+    }
+    out.extend_from_slice(literals_left);
+    Ok(())
+}
+
+/// Decodes one frame onto `out`, returning the bytes after it.
+fn decompress_frame<'a>(data: &'a [u8], out: &mut Vec<u8>, max_size: usize) -> Result<&'a [u8]> {
+    let &descriptor = data.first().ok_or_else(truncated)?;
+    let content_size_flag = descriptor >> 6;
+    let single_segment = descriptor & 0x20 != 0;
+    let has_checksum = descriptor & 0x4 != 0;
+    let dictionary_id_size = [0, 1, 2, 4][(descriptor & 3) as usize];
+    if descriptor & 0x8 != 0 {
+        return Err("a zstd frame header sets a reserved bit");
+    }
+    let content_size_size = match content_size_flag {
+        0 => single_segment as usize,
+        1 => 2,
+        2 => 4,
+        _ => 8,
+    };
+    let mut position = 1 + !single_segment as usize;
+    let dictionary_id = data
+        .get(position..position + dictionary_id_size)
+        .ok_or_else(truncated)?;
+    if dictionary_id.iter().any(|&byte| byte != 0) {
+        return Err("zstd frames that need a dictionary aren't supported");
+    }
+    position += dictionary_id_size;
+    let content_size = data
+        .get(position..position + content_size_size)
+        .ok_or_else(truncated)?;
+    let content_size = content_size
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| value << 8 | byte as u64)
+        + if content_size_size == 2 { 256 } else { 0 };
+    position += content_size_size;
+
+    let start = out.len();
+    let mut state = FrameState {
+        huffman: None,
+        literals_lengths: None,
+        offsets: None,
+        match_lengths: None,
+        repeat_offsets: [1, 4, 8],
+    };
+    loop {
+        let header = data.get(position..position + 3).ok_or_else(truncated)?;
+        let header = u32::from_le_bytes([header[0], header[1], header[2], 0]);
+        let last = header & 1 != 0;
+        let size = (header >> 3) as usize;
+        position += 3;
+        match header >> 1 & 3 {
+            0 => {
+                let raw = data.get(position..position + size).ok_or_else(truncated)?;
+                out.extend_from_slice(raw);
+                position += size;
+            }
+            1 => {
+                let &byte = data.get(position).ok_or_else(truncated)?;
+                out.extend(std::iter::repeat_n(byte, size));
+                position += 1;
+            }
+            2 => {
+                if size > MAX_BLOCK_SIZE {
+                    return Err("a zstd block is too big");
+                }
+                let block = data.get(position..position + size).ok_or_else(truncated)?;
+                decompress_block(block, &mut state, out)?;
+                position += size;
+            }
+            _ => return Err("a zstd block has the reserved type"),
+        }
+        if out.len() > max_size {
+            return Err("zstd data is bigger than expected");
+        }
+        if last {
+            break;
+        }
+    }
+
+    if content_size_size > 0 && (out.len() - start) as u64 != content_size {
+        return Err("a zstd frame's size doesn't match its header");
+    }
+    if has_checksum {
+        let checksum = data.get(position..position + 4).ok_or_else(truncated)?;
+        if u32::from_le_bytes(checksum.try_into().unwrap()) != xxh64(&out[start..]) as u32 {
+            return Err("zstd checksum doesn't match");
+        }
+        position += 4;
+    }
+    Ok(&data[position..])
+}
+
+/// Fails rather than produce more than `max_size` bytes.
+pub(super) fn decompress(data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut rest = data;
+    if rest.is_empty() {
+        return Err(truncated());
+    }
+    while !rest.is_empty() {
+        let magic = rest.get(..4).ok_or_else(truncated)?;
+        let magic = u32::from_le_bytes(magic.try_into().unwrap());
+        if magic & !0xf == SKIPPABLE_MAGIC {
+            let size = rest.get(4..8).ok_or_else(truncated)?;
+            let size = u32::from_le_bytes(size.try_into().unwrap()) as usize;
+            rest = rest.get(8 + size..).ok_or_else(truncated)?;
+        } else if magic == FRAME_MAGIC {
+            rest = decompress_frame(&rest[4..], &mut out, max_size)?;
+        } else {
+            return Err("not a zstd frame");
+        }
+    }
+    Ok(out)
+}
+
+const PRIME_1: u64 = 0x9e37_79b1_85eb_ca87;
+const PRIME_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const PRIME_3: u64 = 0x1656_67b1_9e37_79f9;
+const PRIME_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const PRIME_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+fn xxh64_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME_1)
+}
+
+/// XXH64 with a seed of zero, which frame checksums keep the low half of.
+fn xxh64(data: &[u8]) -> u64 {
+    let u64_at = |bytes: &[u8]| u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let mut stripes = data.chunks_exact(32);
+    let mut hash = if data.len() >= 32 {
+        let mut acc = [
+            PRIME_1.wrapping_add(PRIME_2),
+            PRIME_2,
+            0,
+            0u64.wrapping_sub(PRIME_1),
+        ];
+        for stripe in &mut stripes {
+            for (i, lane) in acc.iter_mut().enumerate() {
+                *lane = xxh64_round(*lane, u64_at(&stripe[i * 8..]));
+            }
+        }
+        let mut hash = acc[0]
+            .rotate_left(1)
+            .wrapping_add(acc[1].rotate_left(7))
+            .wrapping_add(acc[2].rotate_left(12))
+            .wrapping_add(acc[3].rotate_left(18));
+        for lane in acc {
+            hash = (hash ^ xxh64_round(0, lane))
+                .wrapping_mul(PRIME_1)
+                .wrapping_add(PRIME_4);
+        }
+        hash
+    } else {
+        PRIME_5
+    };
+    hash = hash.wrapping_add(data.len() as u64);
+
+    let mut tail = stripes.remainder();
+    while tail.len() >= 8 {
+        hash = (hash ^ xxh64_round(0, u64_at(tail)))
+            .rotate_left(27)
+            .wrapping_mul(PRIME_1)
+            .wrapping_add(PRIME_4);
+        tail = &tail[8..];
+    }
+    if tail.len() >= 4 {
+        let value = u32::from_le_bytes(tail[..4].try_into().unwrap()) as u64;
+        hash = (hash ^ value.wrapping_mul(PRIME_1))
+            .rotate_left(23)
+            .wrapping_mul(PRIME_2)
+            .wrapping_add(PRIME_3);
+        tail = &tail[4..];
+    }
+    for &byte in tail {
+        hash = (hash ^ (byte as u64).wrapping_mul(PRIME_5))
+            .rotate_left(11)
+            .wrapping_mul(PRIME_1);
+    }
+
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(PRIME_2);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(PRIME_3);
+    hash ^ hash >> 32
+}
@@ -0,0 +1,295 @@
+#[cfg(windows)]
+mod d3d12;
+
+use std::{error::Error, path::Path};
+
+use super::{
+    image::{Image, ImageFile, MipFilter},
+    AssetError,
+};
+use crate::gfx::{
+    align_up, readback::TEXTURE_DATA_PITCH_ALIGNMENT, Format, TEXTURE_PLACEMENT_ALIGNMENT,
+};
+
+/// D3D12's limits, which also keep sizes from overflowing.
+const MAX_TEXTURE_SIZE: u32 = 16384;
+const MAX_VOLUME_SIZE: u32 = 2048;
+const MAX_ARRAY_SIZE: u32 = 2048;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextureDimension {
+    Texture1d,
+    Texture2d,
+    Texture3d,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureDesc {
+    pub dimension: TextureDimension,
+    pub format: Format,
+    pub width: u32,
+    pub height: u32,
+    /// Slices of a volume texture, 1 for anything else.
+    pub depth: u32,
+    /// Array slices, with each cube counting as six.
+    pub array_size: u32,
+    pub mip_levels: u32,
+    pub cube: bool,
+}
+
+impl TextureDesc {
+    /// A single 2D texture with one mip level.
+    pub fn texture_2d(format: Format, width: u32, height: u32) -> Self {
+        Self {
+            dimension: TextureDimension::Texture2d,
+            format,
+            width,
+            height,
+            depth: 1,
+            array_size: 1,
+            mip_levels: 1,
+            cube: false,
+        }
+    }
+
+    pub fn subresource_count(&self) -> u32 {
+        self.array_size * self.mip_levels
+    }
+
+    /// D3D12's numbering: all the mips of one array slice, then the next.
+    pub fn subresource_index(&self, mip: u32, array_slice: u32) -> u32 {
+        mip + array_slice * self.mip_levels
+    }
+
+    /// Width, height and depth of `mip`, which never drop below 1.
+    pub fn mip_size(&self, mip: u32) -> (u32, u32, u32) {
+        let size = |size: u32| (size >> mip).max(1);
+        (size(self.width), size(self.height), size(self.depth))
+    }
+
+    /// Mips down to 1x1x1.
+    pub fn full_mip_chain(&self) -> u32 {
+        let largest = self.width.max(self.height).max(self.depth).max(1);
+        32 - largest.leading_zeros()
+    }
+
+    fn validate(&self) -> Result<(), AssetError> {
+        let invalid = |message: String| Err(AssetError::Invalid(message));
+        let (width, height, depth) = (self.width, self.height, self.depth);
+        if width == 0 || height == 0 || depth == 0 || self.array_size == 0 || self.mip_levels == 0 {
+            return invalid(format!(
+                "a {width}x{height}x{depth} texture with {} slices and {} mips is empty",
+                self.array_size, self.mip_levels
+            ));
+        }
+        let max_size = match self.dimension {
+            TextureDimension::Texture3d => MAX_VOLUME_SIZE,
+            _ => MAX_TEXTURE_SIZE,
+        };
+        if width.max(height).max(depth) > max_size || self.array_size > MAX_ARRAY_SIZE {
+            return invalid(format!(
+                "a {width}x{height}x{depth} texture with {} slices is over D3D12's limits",
+                self.array_size
+            ));
+        }
+        match self.dimension {
+            TextureDimension::Texture1d if height != 1 || depth != 1 => {
+                return invalid(format!("a 1D texture can't be {width}x{height}x{depth}"))
+            }
+            TextureDimension::Texture2d if depth != 1 => {
+                return invalid(format!("a 2D texture can't be {depth} deep"))
+            }
+            TextureDimension::Texture3d if self.array_size != 1 || self.cube => {
+                return invalid("volume textures can't be arrays or cubes".into())
+            }
+            _ => {}
+        }
+        if self.cube {
+            if self.dimension != TextureDimension::Texture2d || width != height {
+                return invalid(format!(
+                    "cube faces have to be square, not {width}x{height}"
+                ));
+            }
+            if !self.array_size.is_multiple_of(6) {
+                return invalid(format!(
+                    "{} array slices don't make whole cubes",
+                    self.array_size
+                ));
+            }
+        }
+        if self.mip_levels > self.full_mip_chain() {
+            return invalid(format!(
+                "a {width}x{height}x{depth} texture can't have {} mips",
+                self.mip_levels
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Where one subresource sits in a tightly packed buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Subresource {
+    pub offset: u64,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    /// Bytes from one row to the next. A row of a block-compressed format is
+    /// a row of 4x4 blocks.
+    pub row_pitch: u64,
+    pub rows: u32,
+    /// Bytes from one depth slice to the next.
+    pub slice_pitch: u64,
+}
+
+impl Subresource {
+    pub fn size(&self) -> u64 {
+        self.slice_pitch * self.depth as u64
+    }
+}
+
+/// A subresource placed in an upload buffer, as `GetCopyableFootprints`
+/// would place it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Footprint {
+    pub offset: u64,
+    /// The size in texels, rounded up to whole blocks.
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub row_pitch: u64,
+    pub rows: u32,
+    /// The bytes of each row that hold texels.
+    pub row_size: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextureLayout {
+    pub desc: TextureDesc,
+    /// In subresource index order, which is also the order of the data.
+    pub subresources: Vec<Subresource>,
+    pub size: u64,
+}
+
+impl TextureLayout {
+    pub fn new(desc: TextureDesc) -> Result<Self, AssetError> {
+        let block_bytes = desc
+            .format
+            .block_bytes()
+            .ok_or(AssetError::UnsupportedFormat(desc.format))? as u64;
+        desc.validate()?;
+
+        let block = if desc.format.is_block_compressed() {
+            4
+        } else {
+            1
+        };
+        let mut subresources = Vec::with_capacity(desc.subresource_count() as usize);
+        let mut offset = 0;
+        for _ in 0..desc.array_size {
+            for mip in 0..desc.mip_levels {
+                let (width, height, depth) = desc.mip_size(mip);
+                let row_pitch = width.div_ceil(block) as u64 * block_bytes;
+                let rows = height.div_ceil(block);
+                let subresource = Subresource {
+                    offset,
+                    width,
+                    height,
+                    depth,
+                    row_pitch,
+                    rows,
+                    slice_pitch: row_pitch * rows as u64,
+                };
+                offset += subresource.size();
+                subresources.push(subresource);
+            }
+        }
+
+        Ok(Self {
+            desc,
+            subresources,
+            size: offset,
+        })
+    }
+
+    /// Where each subresource goes when staged for a copy into the texture
+    /// from `base_offset` on, and the bytes needed after it. Rows are 256
+    /// byte aligned and subresources 512.
+    pub fn copyable_footprints(&self, base_offset: u64) -> (Vec<Footprint>, u64) {
+        let block = if self.desc.format.is_block_compressed() {
+            4
+        } else {
+            1
+        };
+        let mut footprints = Vec::with_capacity(self.subresources.len());
+        let mut end = 0;
+        for subresource in &self.subresources {
+            let offset = align_up(end, TEXTURE_PLACEMENT_ALIGNMENT);
+            let row_size = subresource.row_pitch;
+            let row_pitch = align_up(row_size, TEXTURE_DATA_PITCH_ALIGNMENT);
+            let rows = subresource.rows;
+            end = offset + row_pitch * (rows * subresource.depth - 1) as u64 + row_size;
+            footprints.push(Footprint {
+                offset: base_offset + offset,
+                width: align_up(subresource.width as u64, block) as u32,
+                height: align_up(subresource.height as u64, block) as u32,
+                depth: subresource.depth,
+                row_pitch,
+                rows,
+                row_size,
+            });
+        }
+        (footprints, end)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Texture {
+    pub layout: TextureLayout,
+    pub data: Vec<u8>,
+}
+
+impl Texture {
+    pub fn new(layout: TextureLayout, data: Vec<u8>) -> Result<Self, AssetError> {
+        let (expected, actual) = (layout.size as usize, data.len());
+        if actual < expected {
+            return Err(AssetError::Truncated { expected, actual });
+        }
+        if actual > expected {
+            return Err(AssetError::Invalid(format!(
+                "expected {expected} bytes of texels, got {actual}"
+            )));
+        }
+        Ok(Self { layout, data })
+    }
+
+    pub fn desc(&self) -> &TextureDesc {
+        &self.layout.desc
+    }
+
+    pub fn subresource(&self, index: u32) -> &[u8] {
+        let subresource = &self.layout.subresources[index as usize];
+        let start = subresource.offset as usize;
+        &self.data[start..start + subresource.size() as usize]
+    }
+
+    /// Picks the format from the extension. Images get a full chain of box
+    /// filtered mips.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("dds") => return Ok(Self::from_dds(&bytes)?),
+            Some("ktx2") => return Ok(Self::from_ktx2(&bytes)?),
+            _ => {}
+        }
+        match ImageFile::from_path(path) {
+            Some(file) => Ok(Image::decode(&bytes, file)?.to_texture(Some(MipFilter::Box))?),
+            None => Err(format!("{} isn't a texture file this can load", path.display()).into()),
+        }
+    }
+}
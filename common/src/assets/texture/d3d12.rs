@@ -0,0 +1,145 @@
+use std::error::Error;
+
+use windows::Win32::Graphics::{
+    Direct3D12::{
+        ID3D12Device, ID3D12GraphicsCommandList, ID3D12Resource, D3D12_HEAP_FLAG_NONE,
+        D3D12_HEAP_TYPE_DEFAULT, D3D12_PLACED_SUBRESOURCE_FOOTPRINT, D3D12_RESOURCE_DESC,
+        D3D12_RESOURCE_DIMENSION_TEXTURE1D, D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+        D3D12_RESOURCE_DIMENSION_TEXTURE3D, D3D12_RESOURCE_STATE_COPY_DEST, D3D12_SUBRESOURCE_DATA,
+        D3D12_TEXTURE_COPY_LOCATION, D3D12_TEXTURE_COPY_LOCATION_0,
+        D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT, D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+    },
+    Dxgi::Common::{DXGI_FORMAT, DXGI_SAMPLE_DESC},
+};
+
+use super::{Texture, TextureDimension, TextureLayout};
+use crate::gfx::{heap_properties, UploadRing, TEXTURE_PLACEMENT_ALIGNMENT};
+
+impl TextureLayout {
+    pub fn resource_desc(&self) -> D3D12_RESOURCE_DESC {
+        let desc = &self.desc;
+        let (dimension, depth_or_array_size) = match desc.dimension {
+            TextureDimension::Texture1d => (D3D12_RESOURCE_DIMENSION_TEXTURE1D, desc.array_size),
+            TextureDimension::Texture2d => (D3D12_RESOURCE_DIMENSION_TEXTURE2D, desc.array_size),
+            TextureDimension::Texture3d => (D3D12_RESOURCE_DIMENSION_TEXTURE3D, desc.depth),
+        };
+        D3D12_RESOURCE_DESC {
+            Dimension: dimension,
+            Width: desc.width as u64,
+            Height: desc.height,
+            DepthOrArraySize: depth_or_array_size as u16,
+            MipLevels: desc.mip_levels as u16,
+            Format: DXGI_FORMAT(desc.format.0 as i32),
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            ..Default::default()
+        }
+    }
+}
+
+impl Texture {
+    /// One per subresource, pointing into `data`.
+    pub fn subresource_data(&self) -> Vec<D3D12_SUBRESOURCE_DATA> {
+        self.layout
+            .subresources
+            .iter()
+            .map(|subresource| D3D12_SUBRESOURCE_DATA {
+                pData: self.data[subresource.offset as usize..].as_ptr() as _,
+                RowPitch: subresource.row_pitch as isize,
+                SlicePitch: subresource.slice_pitch as isize,
+            })
+            .collect()
+    }
+
+    /// A texture in the DEFAULT heap to `upload` into, in COPY_DEST.
+    pub fn create_resource(&self, device: &ID3D12Device) -> windows::core::Result<ID3D12Resource> {
+        let mut resource: Option<ID3D12Resource> = None;
+        unsafe {
+            device.CreateCommittedResource(
+                &heap_properties(D3D12_HEAP_TYPE_DEFAULT),
+                D3D12_HEAP_FLAG_NONE,
+                &self.layout.resource_desc(),
+                D3D12_RESOURCE_STATE_COPY_DEST,
+                None,
+                &mut resource,
+            )
+        }?;
+        resource.ok_or_else(windows::core::Error::empty)
+    }
+
+    /// Stages every subresource in `ring` and records the copies into
+    /// `resource`, which has to be in COPY_DEST. The ring space is in use
+    /// until the list has executed.
+    pub fn upload(
+        &self,
+        device: &ID3D12Device,
+        list: &ID3D12GraphicsCommandList,
+        ring: &mut UploadRing,
+        resource: &ID3D12Resource,
+    ) -> Result<(), Box<dyn Error>> {
+        let count = self.layout.subresources.len();
+        let mut footprints = vec![D3D12_PLACED_SUBRESOURCE_FOOTPRINT::default(); count];
+        let mut rows = vec![0u32; count];
+        let mut row_sizes = vec![0u64; count];
+        let mut total = 0;
+        unsafe {
+            device.GetCopyableFootprints(
+                &self.layout.resource_desc(),
+                0,
+                count as u32,
+                0,
+                Some(footprints.as_mut_ptr()),
+                Some(rows.as_mut_ptr()),
+                Some(row_sizes.as_mut_ptr()),
+                Some(&mut total),
+            )
+        };
+
+        let allocation = ring
+            .allocate(total, TEXTURE_PLACEMENT_ALIGNMENT)
+            .ok_or("the upload ring doesn't have room for the texture")?;
+
+        for (index, (footprint, data)) in footprints
+            .iter_mut()
+            .zip(self.subresource_data())
+            .enumerate()
+        {
+            let (rows, row_size) = (rows[index] as usize, row_sizes[index] as usize);
+            let dst_row_pitch = footprint.Footprint.RowPitch as usize;
+            for slice in 0..footprint.Footprint.Depth as usize {
+                for row in 0..rows {
+                    unsafe {
+                        let src = (data.pData as *const u8).offset(
+                            data.SlicePitch * slice as isize + data.RowPitch * row as isize,
+                        );
+                        let dst = allocation
+                            .cpu_ptr
+                            .add(footprint.Offset as usize + dst_row_pitch * (slice * rows + row));
+                        std::ptr::copy_nonoverlapping(src, dst, row_size);
+                    }
+                }
+            }
+            footprint.Offset += allocation.offset;
+
+            let dst = D3D12_TEXTURE_COPY_LOCATION {
+                pResource: unsafe { std::mem::transmute_copy(resource) },
+                Type: D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+                Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                    SubresourceIndex: index as u32,
+                },
+            };
+            let src = D3D12_TEXTURE_COPY_LOCATION {
+                pResource: unsafe { std::mem::transmute_copy(ring.resource()) },
+                Type: D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
+                Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+                    PlacedFootprint: *footprint,
+                },
+            };
+            unsafe { list.CopyTextureRegion(&dst, 0, 0, 0, &src, None) };
+        }
+
+        Ok(())
+    }
+}
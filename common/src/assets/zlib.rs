@@ -0,0 +1,370 @@
+// Just enough zlib for PNG and KTX2: a simple compressor, a complete decompressor,
+// and the two checksums.
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+pub(super) fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed before `b` could overflow.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+/// Writes bits least significant first, as deflate wants.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, bits: u32, count: u32) {
+        self.buffer |= (bits as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Huffman codes are defined most significant bit first.
+    fn write_code(&mut self, code: u32, count: u32) {
+        self.write(code.reverse_bits() >> (32 - count), count);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+
+fn write_literal(out: &mut BitWriter, symbol: u32) {
+    // The fixed literal/length code from RFC 1951 3.2.6.
+    match symbol {
+        0..=143 => out.write_code(0x30 + symbol, 8),
+        144..=255 => out.write_code(0x190 + symbol - 144, 9),
+        256..=279 => out.write_code(symbol - 256, 7),
+        _ => out.write_code(0xc0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(out: &mut BitWriter, length: usize, distance: usize) {
+    let code = LENGTH_BASES.partition_point(|&base| base as usize <= length) - 1;
+    write_literal(out, 257 + code as u32);
+    out.write(
+        (length - LENGTH_BASES[code] as usize) as u32,
+        LENGTH_EXTRA_BITS[code] as u32,
+    );
+
+    let code = DISTANCE_BASES.partition_point(|&base| base as usize <= distance) - 1;
+    out.write_code(code as u32, 5);
+    out.write(
+        (distance - DISTANCE_BASES[code] as usize) as u32,
+        DISTANCE_EXTRA_BITS[code] as u32,
+    );
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let key = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+    (key.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+}
+
+/// A zlib stream holding one fixed-Huffman block, matching each position
+/// against the last one that started with the same three bytes.
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter {
+        bytes: vec![0x78, 0x01],
+        buffer: 0,
+        count: 0,
+    };
+    // BFINAL, then BTYPE 01.
+    out.write(1, 1);
+    out.write(1, 2);
+
+    let mut last_seen = vec![usize::MAX; 1 << HASH_BITS];
+    let mut i = 0;
+    while i < data.len() {
+        let mut length = 0;
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(&data[i..]);
+            let candidate = last_seen[h];
+            last_seen[h] = i;
+            if candidate != usize::MAX && i - candidate <= WINDOW_SIZE {
+                let max = MAX_MATCH.min(data.len() - i);
+                length = (0..max)
+                    .take_while(|&k| data[candidate + k] == data[i + k])
+                    .count();
+            }
+            if length >= MIN_MATCH {
+                write_match(&mut out, length, i - candidate);
+                // Keep the table up to date inside the match, so runs
+                // chain together.
+                for j in i + 1..(i + length).min(data.len() - MIN_MATCH + 1) {
+                    last_seen[hash(&data[j..])] = j;
+                }
+                i += length;
+                continue;
+            }
+        }
+        write_literal(&mut out, data[i] as u32);
+        i += 1;
+    }
+    write_literal(&mut out, 256);
+
+    let mut bytes = out.finish();
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+/// Reads bits least significant first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, &'static str> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or("deflate stream is truncated")?;
+            value |= ((byte >> (self.position % 8)) as u32 & 1) << i;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.position = self.position.next_multiple_of(8);
+    }
+}
+
+/// A canonical Huffman code, decoded a bit at a time as in zlib's puff.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16)
+            .filter(|&symbol| lengths[symbol as usize] != 0)
+            .collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> Result<u16, &'static str> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid Huffman code")
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_codes(bits: &mut BitReader) -> Result<(Huffman, Huffman), &'static str> {
+    const ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+    let literal_count = bits.bits(5)? as usize + 257;
+    let distance_count = bits.bits(5)? as usize + 1;
+    let code_length_count = bits.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0; 19];
+    for &i in &ORDER[..code_length_count] {
+        code_lengths[i] = bits.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_lengths.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths.last().ok_or("repeat with no previous length")?,
+                3 + bits.bits(2)?,
+            ),
+            17 => (0, 3 + bits.bits(3)?),
+            _ => (0, 11 + bits.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err("code lengths overrun");
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    bits: &mut BitReader,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), &'static str> {
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let code = symbol - 257;
+                let base = *LENGTH_BASES.get(code).ok_or("invalid length code")? as usize;
+                let length = base + bits.bits(LENGTH_EXTRA_BITS[code] as u32)? as usize;
+
+                let code = distances.decode(bits)? as usize;
+                let base = *DISTANCE_BASES.get(code).ok_or("invalid distance code")? as usize;
+                let distance = base + bits.bits(DISTANCE_EXTRA_BITS[code] as u32)? as usize;
+                if distance > out.len() {
+                    return Err("distance reaches before the start");
+                }
+
+                // Byte by byte, since the match may overlap what it copies.
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let [cmf, flg, ..] = *data else {
+        return Err("zlib stream is truncated");
+    };
+    if cmf & 0x0f != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err("not a zlib stream");
+    }
+    if flg & 0x20 != 0 {
+        return Err("zlib preset dictionaries aren't supported");
+    }
+
+    let mut bits = BitReader {
+        data: &data[2..],
+        position: 0,
+    };
+    let mut out = Vec::new();
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => {
+                bits.align_to_byte();
+                let length = bits.bits(16)?;
+                if bits.bits(16)? != !length & 0xffff {
+                    return Err("stored block length doesn't match its complement");
+                }
+                let start = bits.position / 8;
+                let stored = bits
+                    .data
+                    .get(start..start + length as usize)
+                    .ok_or("deflate stream is truncated")?;
+                out.extend_from_slice(stored);
+                bits.position += length as usize * 8;
+            }
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(&mut bits, &mut out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut out, &literals, &distances)?;
+            }
+            _ => return Err("invalid deflate block type"),
+        }
+        if last {
+            break;
+        }
+    }
+
+    bits.align_to_byte();
+    let checksum = bits.bits(32)?.swap_bytes();
+    if checksum != adler32(&out) {
+        return Err("zlib checksum doesn't match");
+    }
+    Ok(out)
+}
@@ -0,0 +1,209 @@
+// Camera controllers for the 3D samples. Each one is updated once per frame
+// from the `InputState` and the frame time, and hands out left-handed view
+// matrices and reversed-Z projections.
+
+use std::f32::consts::FRAC_PI_2;
+
+use crate::{
+    input::{InputState, Key, MouseButton},
+    math::{Mat4, Quat, Vec3},
+};
+
+/// Keeps the camera from flipping over when looking straight up or down.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// A reversed-Z perspective projection with an infinite far plane.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Perspective {
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub aspect: f32,
+    pub near: f32,
+}
+
+impl Perspective {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::perspective_infinite_reversed_z_lh(self.fov_y, self.aspect, self.near)
+    }
+
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+}
+
+impl Default for Perspective {
+    fn default() -> Self {
+        Self {
+            fov_y: 60f32.to_radians(),
+            aspect: 16.0 / 9.0,
+            near: 0.1,
+        }
+    }
+}
+
+/// `yaw` turns right from +z and `pitch` looks down, both in radians.
+fn orientation(yaw: f32, pitch: f32) -> Quat {
+    Quat::from_yaw_pitch_roll(yaw, pitch, 0.0)
+}
+
+/// Yaw and pitch that look along `direction`.
+fn yaw_pitch(direction: Vec3) -> (f32, f32) {
+    let direction = direction.normalize();
+    (
+        direction.x.atan2(direction.z),
+        (-direction.y).asin().clamp(-MAX_PITCH, MAX_PITCH),
+    )
+}
+
+/// A first-person camera: WASD moves, Q and E go down and up, Shift speeds
+/// up, and the mouse looks around while `look_button` is held.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlyCamera {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Units per second.
+    pub speed: f32,
+    /// Speed multiplier while Shift is held.
+    pub boost: f32,
+    /// Radians per pixel of mouse movement.
+    pub sensitivity: f32,
+    /// `None` looks with every mouse movement, for a captured cursor.
+    pub look_button: Option<MouseButton>,
+    pub projection: Perspective,
+}
+
+impl FlyCamera {
+    pub fn new(position: Vec3, target: Vec3) -> Self {
+        let (yaw, pitch) = yaw_pitch(target - position);
+        Self {
+            position,
+            yaw,
+            pitch,
+            speed: 5.0,
+            boost: 4.0,
+            sensitivity: 0.003,
+            look_button: Some(MouseButton::Right),
+            projection: Perspective::default(),
+        }
+    }
+
+    pub fn update(&mut self, input: &InputState, dt: f32) {
+        if self.look_button.is_none_or(|b| input.is_button_down(b)) {
+            let delta = input.mouse_delta() * self.sensitivity;
+            self.yaw += delta.x;
+            self.pitch = (self.pitch + delta.y).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        let axis = |positive: Key, negative: Key| {
+            input.is_key_down(positive) as i32 as f32 - input.is_key_down(negative) as i32 as f32
+        };
+        let rotation = self.rotation();
+        let direction = rotation * Vec3::Z * axis(Key::W, Key::S)
+            + rotation * Vec3::X * axis(Key::D, Key::A)
+            + Vec3::Y * axis(Key::E, Key::Q);
+
+        let mut speed = self.speed;
+        if input.is_key_down(Key::Shift) {
+            speed *= self.boost;
+        }
+        // Diagonals aren't faster.
+        self.position += direction.normalize_or_zero() * speed * dt;
+    }
+
+    pub fn rotation(&self) -> Quat {
+        orientation(self.yaw, self.pitch)
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation() * Vec3::Z
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_to_lh(self.position, self.forward(), Vec3::Y)
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection.matrix() * self.view()
+    }
+}
+
+/// Circles `target`: dragging with `rotate_button` rotates around it, the
+/// wheel zooms, and dragging with `pan_button` slides the target sideways.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrbitCamera {
+    pub target: Vec3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Radians per pixel dragged.
+    pub rotate_sensitivity: f32,
+    /// Fraction of the distance per pixel dragged.
+    pub pan_sensitivity: f32,
+    /// Fraction of the distance each wheel notch moves closer.
+    pub zoom_step: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub rotate_button: MouseButton,
+    pub pan_button: MouseButton,
+    pub projection: Perspective,
+}
+
+impl OrbitCamera {
+    pub fn new(eye: Vec3, target: Vec3) -> Self {
+        let (yaw, pitch) = yaw_pitch(target - eye);
+        Self {
+            target,
+            distance: eye.distance(target),
+            yaw,
+            pitch,
+            rotate_sensitivity: 0.005,
+            pan_sensitivity: 0.001,
+            zoom_step: 0.1,
+            min_distance: 0.1,
+            max_distance: 1000.0,
+            rotate_button: MouseButton::Left,
+            pan_button: MouseButton::Middle,
+            projection: Perspective::default(),
+        }
+    }
+
+    /// Orbiting follows the mouse directly, so `dt` is unused; it's taken so
+    /// both cameras update the same way.
+    pub fn update(&mut self, input: &InputState, _dt: f32) {
+        let delta = input.mouse_delta();
+        if input.is_button_down(self.rotate_button) {
+            // The model follows the cursor, so the camera goes the other way.
+            self.yaw += delta.x * self.rotate_sensitivity;
+            self.pitch =
+                (self.pitch + delta.y * self.rotate_sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        if input.is_button_down(self.pan_button) {
+            let rotation = self.rotation();
+            let scale = self.pan_sensitivity * self.distance;
+            self.target += (rotation * Vec3::Y * delta.y - rotation * Vec3::X * delta.x) * scale;
+        }
+
+        let zoom = (1.0 - self.zoom_step).powf(input.wheel_delta());
+        self.distance = (self.distance * zoom).clamp(self.min_distance, self.max_distance);
+    }
+
+    pub fn rotation(&self) -> Quat {
+        orientation(self.yaw, self.pitch)
+    }
+
+    pub fn eye(&self) -> Vec3 {
+        self.target - self.rotation() * Vec3::Z * self.distance
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_to_lh(self.eye(), self.rotation() * Vec3::Z, Vec3::Y)
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection.matrix() * self.view()
+    }
+}
@@ -1,25 +1,49 @@
-use windows::Win32::Graphics::Direct3D12::{
-    ID3D12Resource, D3D12_RESOURCE_BARRIER, D3D12_RESOURCE_BARRIER_0,
-    D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES, D3D12_RESOURCE_BARRIER_FLAG_NONE,
-    D3D12_RESOURCE_BARRIER_TYPE_TRANSITION, D3D12_RESOURCE_STATES,
-    D3D12_RESOURCE_TRANSITION_BARRIER,
-};
+pub mod backend;
+pub mod cbuffer;
+pub mod graph;
+pub mod pipeline_state;
+pub mod readback;
+pub mod root_signature;
+
+#[cfg(windows)]
+mod command_list_pool;
+mod command_queue;
+#[cfg(windows)]
+mod d3d12;
+mod deferred_deleter;
+mod deferred_queue;
+mod format;
+#[cfg(windows)]
+mod heap_allocator;
+#[cfg(windows)]
+mod parallel_recording;
+mod queue_type;
+mod recycle_pool;
+mod ring_allocator;
+mod tlsf;
+#[cfg(windows)]
+mod upload_ring;
 
-pub fn transition_barrier(
-    resource: &ID3D12Resource,
-    state_before: D3D12_RESOURCE_STATES,
-    state_after: D3D12_RESOURCE_STATES,
-) -> D3D12_RESOURCE_BARRIER {
-    D3D12_RESOURCE_BARRIER {
-        Type: D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
-        Flags: D3D12_RESOURCE_BARRIER_FLAG_NONE,
-        Anonymous: D3D12_RESOURCE_BARRIER_0 {
-            Transition: std::mem::ManuallyDrop::new(D3D12_RESOURCE_TRANSITION_BARRIER {
-                pResource: unsafe { std::mem::transmute_copy(resource) },
-                StateBefore: state_before,
-                StateAfter: state_after,
-                Subresource: D3D12_RESOURCE_BARRIER_ALL_SUBRESOURCES,
-            }),
-        },
-    }
-}
+#[cfg(windows)]
+pub use command_list_pool::CommandListPool;
+pub use command_queue::{CommandQueue, SyncPoint};
+#[cfg(windows)]
+pub use d3d12::{buffer_resource_desc, heap_properties, transition_barrier, uav_barrier};
+pub use deferred_deleter::DeferredDeleter;
+pub use deferred_queue::DeferredQueue;
+pub use format::Format;
+#[cfg(windows)]
+pub use heap_allocator::{
+    video_memory_budget, GpuAllocation, HeapAllocator, HeapCategory, PlacedResource, PoolStats,
+    DEFAULT_HEAP_BLOCK_SIZE,
+};
+#[cfg(windows)]
+pub use parallel_recording::record_parallel;
+pub use queue_type::QueueType;
+pub use recycle_pool::RecyclePool;
+pub use ring_allocator::{
+    align_up, RingAllocation, RingAllocator, CONSTANT_BUFFER_ALIGNMENT, TEXTURE_PLACEMENT_ALIGNMENT,
+};
+pub use tlsf::{TlsfAllocation, TlsfAllocator, TlsfStats};
+#[cfg(windows)]
+pub use upload_ring::{UploadAllocation, UploadRing};
@@ -0,0 +1,183 @@
+// A thin layer over the graphics API: devices, queues, fences, command lists
+// and resources as traits, grouped by an `Api`. `d3d12` implements them on
+// Windows; `null` records every call, runs a pretend GPU and checks the basic
+// rules, so code written against the traits can be tested anywhere. Resource
+// states are the render graph's `ResourceState`.
+
+#[cfg(windows)]
+mod d3d12;
+mod null;
+
+#[cfg(windows)]
+pub use d3d12::{D3d12, D3d12CommandList, D3d12Device, D3d12Fence, D3d12Queue};
+pub use null::{Null, NullCommandList, NullDevice, NullFence, NullQueue, NullResource};
+
+use std::{error::Error, fmt};
+
+use super::{
+    format::Format, graph::ResourceState, queue_type::QueueType, readback::ReadbackLayout,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BackendError {
+    /// The API failed a call.
+    Device(String),
+    /// The call broke a usage rule.
+    Validation(String),
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Device(message) => write!(f, "device error: {message}"),
+            Self::Validation(message) => write!(f, "validation error: {message}"),
+        }
+    }
+}
+
+impl Error for BackendError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HeapType {
+    /// GPU memory.
+    Default,
+    /// CPU-written memory the GPU reads.
+    Upload,
+    /// GPU-written memory the CPU reads.
+    Readback,
+}
+
+impl HeapType {
+    /// D3D12 requires upload and readback resources to stay in one state.
+    pub fn required_state(self) -> Option<ResourceState> {
+        match self {
+            HeapType::Default => None,
+            HeapType::Upload => Some(ResourceState::GenericRead),
+            HeapType::Readback => Some(ResourceState::CopyDest),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceDesc {
+    Buffer {
+        size: u64,
+    },
+    Texture2d {
+        width: u32,
+        height: u32,
+        format: Format,
+        mip_levels: u16,
+        render_target: bool,
+    },
+}
+
+impl ResourceDesc {
+    pub fn buffer(size: u64) -> Self {
+        Self::Buffer { size }
+    }
+
+    /// One mip level that can't be rendered to.
+    pub fn texture_2d(width: u32, height: u32, format: Format) -> Self {
+        Self::Texture2d {
+            width,
+            height,
+            format,
+            mip_levels: 1,
+            render_target: false,
+        }
+    }
+
+    pub fn render_target(width: u32, height: u32, format: Format) -> Self {
+        Self::Texture2d {
+            width,
+            height,
+            format,
+            mip_levels: 1,
+            render_target: true,
+        }
+    }
+}
+
+/// The types one API implements the traits with.
+pub trait Api: Sized + 'static {
+    type Device: Device<Self>;
+    type Queue: Queue<Self>;
+    /// Clones refer to the same fence.
+    type Fence: Fence + Clone;
+    type CommandList: CommandList<Self>;
+    type Resource: Clone;
+}
+
+pub trait Device<A: Api> {
+    fn create_queue(&self, queue_type: QueueType) -> Result<A::Queue, BackendError>;
+
+    fn create_fence(&self, initial_value: u64) -> Result<A::Fence, BackendError>;
+
+    /// A list that's open and ready for recording.
+    fn create_command_list(&self, queue_type: QueueType) -> Result<A::CommandList, BackendError>;
+
+    fn create_resource(
+        &self,
+        desc: &ResourceDesc,
+        heap: HeapType,
+        initial_state: ResourceState,
+    ) -> Result<A::Resource, BackendError>;
+}
+
+pub trait Queue<A: Api> {
+    fn queue_type(&self) -> QueueType;
+
+    /// Executes closed lists in order.
+    fn submit(&mut self, lists: &[&A::CommandList]) -> Result<(), BackendError>;
+
+    /// Sets `fence` to `value` once the work submitted so far has finished.
+    fn signal(&mut self, fence: &A::Fence, value: u64) -> Result<(), BackendError>;
+
+    /// Holds back later work on this queue until `fence` reaches `value`.
+    fn wait(&mut self, fence: &A::Fence, value: u64) -> Result<(), BackendError>;
+}
+
+pub trait Fence {
+    fn completed_value(&self) -> u64;
+
+    /// Blocks until the fence reaches `value`.
+    fn wait(&self, value: u64) -> Result<(), BackendError>;
+}
+
+pub trait CommandList<A: Api> {
+    fn queue_type(&self) -> QueueType;
+
+    /// Starts recording again. The GPU has to be done with what was recorded
+    /// before.
+    fn reset(&mut self) -> Result<(), BackendError>;
+
+    fn close(&mut self) -> Result<(), BackendError>;
+
+    fn barrier(
+        &mut self,
+        resource: &A::Resource,
+        before: ResourceState,
+        after: ResourceState,
+    ) -> Result<(), BackendError>;
+
+    /// Makes unordered access before the barrier finish before any after it.
+    fn uav_barrier(&mut self, resource: &A::Resource) -> Result<(), BackendError>;
+
+    fn copy_buffer(
+        &mut self,
+        dst: &A::Resource,
+        dst_offset: u64,
+        src: &A::Resource,
+        src_offset: u64,
+        size: u64,
+    ) -> Result<(), BackendError>;
+
+    /// Copies mip 0 of a 2D texture into a buffer laid out as `layout`.
+    fn copy_texture_to_buffer(
+        &mut self,
+        dst: &A::Resource,
+        layout: &ReadbackLayout,
+        src: &A::Resource,
+    ) -> Result<(), BackendError>;
+}
@@ -0,0 +1,329 @@
+use std::sync::{Arc, Mutex};
+
+use windows::{
+    core::Interface,
+    Win32::{
+        Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0, WAIT_TIMEOUT},
+        Graphics::{
+            Direct3D12::{
+                ID3D12CommandAllocator, ID3D12CommandList, ID3D12CommandQueue, ID3D12Device,
+                ID3D12Fence, ID3D12GraphicsCommandList, ID3D12Resource, D3D12_COMMAND_QUEUE_DESC,
+                D3D12_FENCE_FLAG_NONE, D3D12_HEAP_FLAG_NONE, D3D12_HEAP_TYPE_DEFAULT,
+                D3D12_HEAP_TYPE_READBACK, D3D12_HEAP_TYPE_UPLOAD, D3D12_RESOURCE_DESC,
+                D3D12_RESOURCE_DIMENSION_TEXTURE2D, D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET,
+                D3D12_RESOURCE_FLAG_NONE,
+            },
+            Dxgi::Common::{DXGI_FORMAT, DXGI_SAMPLE_DESC},
+        },
+        System::Threading::{CreateEventA, WaitForSingleObject, INFINITE},
+    },
+};
+
+use super::{Api, BackendError, CommandList, Device, Fence, HeapType, Queue, ResourceDesc};
+use crate::gfx::{
+    buffer_resource_desc,
+    graph::{ResourceState, ResourceStateExt},
+    heap_properties,
+    queue_type::QueueType,
+    readback,
+    readback::ReadbackLayout,
+    transition_barrier, uav_barrier,
+};
+
+impl From<windows::core::Error> for BackendError {
+    fn from(e: windows::core::Error) -> Self {
+        Self::Device(e.to_string())
+    }
+}
+
+pub struct D3d12;
+
+impl Api for D3d12 {
+    type Device = D3d12Device;
+    type Queue = D3d12Queue;
+    type Fence = D3d12Fence;
+    type CommandList = D3d12CommandList;
+    type Resource = ID3D12Resource;
+}
+
+pub struct D3d12Device {
+    device: ID3D12Device,
+}
+
+impl D3d12Device {
+    pub fn new(device: &ID3D12Device) -> Self {
+        Self {
+            device: device.clone(),
+        }
+    }
+
+    pub fn device(&self) -> &ID3D12Device {
+        &self.device
+    }
+}
+
+impl Device<D3d12> for D3d12Device {
+    fn create_queue(&self, queue_type: QueueType) -> Result<D3d12Queue, BackendError> {
+        let queue = unsafe {
+            self.device.CreateCommandQueue(&D3D12_COMMAND_QUEUE_DESC {
+                Type: queue_type.command_list_type(),
+                ..Default::default()
+            })
+        }?;
+        Ok(D3d12Queue { queue, queue_type })
+    }
+
+    fn create_fence(&self, initial_value: u64) -> Result<D3d12Fence, BackendError> {
+        let fence = unsafe {
+            self.device
+                .CreateFence(initial_value, D3D12_FENCE_FLAG_NONE)
+        }?;
+        let event = unsafe { CreateEventA(None, false, false, None) }?;
+        Ok(D3d12Fence {
+            fence,
+            event: Arc::new(Mutex::new(FenceEvent(event))),
+        })
+    }
+
+    fn create_command_list(&self, queue_type: QueueType) -> Result<D3d12CommandList, BackendError> {
+        let list_type = queue_type.command_list_type();
+        let allocator: ID3D12CommandAllocator =
+            unsafe { self.device.CreateCommandAllocator(list_type) }?;
+        let list = unsafe {
+            self.device
+                .CreateCommandList(0, list_type, &allocator, None)
+        }?;
+        Ok(D3d12CommandList {
+            list,
+            allocator,
+            queue_type,
+        })
+    }
+
+    fn create_resource(
+        &self,
+        desc: &ResourceDesc,
+        heap: HeapType,
+        initial_state: ResourceState,
+    ) -> Result<ID3D12Resource, BackendError> {
+        let heap_type = match heap {
+            HeapType::Default => D3D12_HEAP_TYPE_DEFAULT,
+            HeapType::Upload => D3D12_HEAP_TYPE_UPLOAD,
+            HeapType::Readback => D3D12_HEAP_TYPE_READBACK,
+        };
+        let desc = match *desc {
+            ResourceDesc::Buffer { size } => buffer_resource_desc(size),
+            ResourceDesc::Texture2d {
+                width,
+                height,
+                format,
+                mip_levels,
+                render_target,
+            } => D3D12_RESOURCE_DESC {
+                Dimension: D3D12_RESOURCE_DIMENSION_TEXTURE2D,
+                Width: width as u64,
+                Height: height,
+                DepthOrArraySize: 1,
+                MipLevels: mip_levels,
+                Format: DXGI_FORMAT(format.0 as i32),
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Flags: if render_target {
+                    D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET
+                } else {
+                    D3D12_RESOURCE_FLAG_NONE
+                },
+                ..Default::default()
+            },
+        };
+
+        let mut resource: Option<ID3D12Resource> = None;
+        unsafe {
+            self.device.CreateCommittedResource(
+                &heap_properties(heap_type),
+                D3D12_HEAP_FLAG_NONE,
+                &desc,
+                initial_state.to_d3d12(),
+                None,
+                &mut resource,
+            )
+        }?;
+        Ok(resource.ok_or_else(windows::core::Error::empty)?)
+    }
+}
+
+pub struct D3d12Queue {
+    queue: ID3D12CommandQueue,
+    queue_type: QueueType,
+}
+
+impl D3d12Queue {
+    pub fn queue(&self) -> &ID3D12CommandQueue {
+        &self.queue
+    }
+}
+
+impl Queue<D3d12> for D3d12Queue {
+    fn queue_type(&self) -> QueueType {
+        self.queue_type
+    }
+
+    fn submit(&mut self, lists: &[&D3d12CommandList]) -> Result<(), BackendError> {
+        let lists = lists
+            .iter()
+            .map(|list| Ok(Some(list.list.cast::<ID3D12CommandList>()?)))
+            .collect::<windows::core::Result<Vec<_>>>()?;
+        unsafe { self.queue.ExecuteCommandLists(&lists) };
+        Ok(())
+    }
+
+    fn signal(&mut self, fence: &D3d12Fence, value: u64) -> Result<(), BackendError> {
+        Ok(unsafe { self.queue.Signal(&fence.fence, value) }?)
+    }
+
+    fn wait(&mut self, fence: &D3d12Fence, value: u64) -> Result<(), BackendError> {
+        Ok(unsafe { self.queue.Wait(&fence.fence, value) }?)
+    }
+}
+
+/// A fence and the event its CPU waits block on. Clones share both, and take
+/// turns with the event so one waiter can't consume another's wakeup.
+#[derive(Clone)]
+pub struct D3d12Fence {
+    fence: ID3D12Fence,
+    event: Arc<Mutex<FenceEvent>>,
+}
+
+struct FenceEvent(HANDLE);
+
+// Events can be signaled and waited on from any thread.
+unsafe impl Send for FenceEvent {}
+
+impl Drop for FenceEvent {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseHandle(self.0) };
+    }
+}
+
+impl D3d12Fence {
+    pub fn fence(&self) -> &ID3D12Fence {
+        &self.fence
+    }
+
+    /// Blocks until the fence reaches `value` or `timeout_ms` passes, and
+    /// returns whether it got there.
+    pub fn wait_timeout(&self, value: u64, timeout_ms: u32) -> Result<bool, BackendError> {
+        if self.completed_value() >= value {
+            return Ok(true);
+        }
+        let event = self.event.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            unsafe { self.fence.SetEventOnCompletion(value, event.0) }?;
+            match unsafe { WaitForSingleObject(event.0, timeout_ms) } {
+                WAIT_OBJECT_0 if self.completed_value() >= value => return Ok(true),
+                // Left set for an earlier wait that timed out.
+                WAIT_OBJECT_0 => continue,
+                WAIT_TIMEOUT => return Ok(self.completed_value() >= value),
+                _ => return Err(windows::core::Error::from_win32().into()),
+            }
+        }
+    }
+}
+
+impl Fence for D3d12Fence {
+    fn completed_value(&self) -> u64 {
+        unsafe { self.fence.GetCompletedValue() }
+    }
+
+    fn wait(&self, value: u64) -> Result<(), BackendError> {
+        self.wait_timeout(value, INFINITE)?;
+        Ok(())
+    }
+}
+
+/// A graphics command list with an allocator of its own.
+pub struct D3d12CommandList {
+    list: ID3D12GraphicsCommandList,
+    allocator: ID3D12CommandAllocator,
+    queue_type: QueueType,
+}
+
+impl D3d12CommandList {
+    pub(crate) fn from_parts(
+        list: ID3D12GraphicsCommandList,
+        allocator: ID3D12CommandAllocator,
+        queue_type: QueueType,
+    ) -> Self {
+        Self {
+            list,
+            allocator,
+            queue_type,
+        }
+    }
+
+    pub(crate) fn into_parts(self) -> (ID3D12GraphicsCommandList, ID3D12CommandAllocator) {
+        (self.list, self.allocator)
+    }
+
+    pub fn list(&self) -> &ID3D12GraphicsCommandList {
+        &self.list
+    }
+}
+
+impl CommandList<D3d12> for D3d12CommandList {
+    fn queue_type(&self) -> QueueType {
+        self.queue_type
+    }
+
+    fn reset(&mut self) -> Result<(), BackendError> {
+        unsafe { self.allocator.Reset() }?;
+        Ok(unsafe { self.list.Reset(&self.allocator, None) }?)
+    }
+
+    fn close(&mut self) -> Result<(), BackendError> {
+        Ok(unsafe { self.list.Close() }?)
+    }
+
+    fn barrier(
+        &mut self,
+        resource: &ID3D12Resource,
+        before: ResourceState,
+        after: ResourceState,
+    ) -> Result<(), BackendError> {
+        let barrier = transition_barrier(resource, before.to_d3d12(), after.to_d3d12());
+        unsafe { self.list.ResourceBarrier(&[barrier]) };
+        Ok(())
+    }
+
+    fn uav_barrier(&mut self, resource: &ID3D12Resource) -> Result<(), BackendError> {
+        unsafe { self.list.ResourceBarrier(&[uav_barrier(resource)]) };
+        Ok(())
+    }
+
+    fn copy_buffer(
+        &mut self,
+        dst: &ID3D12Resource,
+        dst_offset: u64,
+        src: &ID3D12Resource,
+        src_offset: u64,
+        size: u64,
+    ) -> Result<(), BackendError> {
+        unsafe {
+            self.list
+                .CopyBufferRegion(dst, dst_offset, src, src_offset, size)
+        };
+        Ok(())
+    }
+
+    fn copy_texture_to_buffer(
+        &mut self,
+        dst: &ID3D12Resource,
+        layout: &ReadbackLayout,
+        src: &ID3D12Resource,
+    ) -> Result<(), BackendError> {
+        readback::copy_texture_to_buffer(&self.list, dst, layout, src);
+        Ok(())
+    }
+}
@@ -0,0 +1,544 @@
+// A backend with no GPU behind it. Every successful call is logged, submitted
+// work sits in per-queue FIFOs until a fence wait or `NullDevice::run_gpu`
+// lets the pretend GPU drain them, and calls that would upset the debug layer
+// fail with `BackendError::Validation`. Resource states are checked in
+// submission order against what each list expects, comparing D3D12 bits so
+// `Present` and `Common` are the same state; implicit state promotion isn't
+// modelled, so every resource has to be transitioned explicitly.
+
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use super::{Api, BackendError, CommandList, Device, Fence, HeapType, Queue, ResourceDesc};
+use crate::gfx::{graph::ResourceState, queue_type::QueueType, readback::ReadbackLayout};
+
+pub struct Null;
+
+impl Api for Null {
+    type Device = NullDevice;
+    type Queue = NullQueue;
+    type Fence = NullFence;
+    type CommandList = NullCommandList;
+    type Resource = NullResource;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NullResource {
+    id: usize,
+}
+
+struct ResourceInfo {
+    desc: ResourceDesc,
+    heap: HeapType,
+    state: ResourceState,
+}
+
+struct FenceInfo {
+    completed: u64,
+    last_signaled: u64,
+}
+
+enum Op {
+    Execute(usize),
+    Signal { fence: usize, value: u64 },
+    Wait { fence: usize, value: u64 },
+}
+
+#[derive(Default)]
+struct State {
+    log: Vec<String>,
+    resources: Vec<ResourceInfo>,
+    fences: Vec<FenceInfo>,
+    queues: Vec<VecDeque<Op>>,
+    /// Submissions of each list the GPU hasn't got through yet.
+    pending: Vec<u32>,
+}
+
+impl State {
+    fn run_gpu(&mut self) {
+        let mut progress = true;
+        while progress {
+            progress = false;
+            for queue in 0..self.queues.len() {
+                while let Some(op) = self.queues[queue].front() {
+                    let entry = match *op {
+                        Op::Execute(list) => {
+                            self.pending[list] -= 1;
+                            format!("gpu: queue {queue} executed list {list}")
+                        }
+                        Op::Signal { fence, value } => {
+                            self.fences[fence].completed = value;
+                            format!("gpu: fence {fence} = {value}")
+                        }
+                        Op::Wait { fence, value } if self.fences[fence].completed >= value => {
+                            format!("gpu: queue {queue} waited for fence {fence} >= {value}")
+                        }
+                        Op::Wait { .. } => break,
+                    };
+                    self.log.push(entry);
+                    self.queues[queue].pop_front();
+                    progress = true;
+                }
+            }
+        }
+    }
+}
+
+fn invalid<T>(message: String) -> Result<T, BackendError> {
+    Err(BackendError::Validation(message))
+}
+
+#[derive(Clone, Default)]
+pub struct NullDevice {
+    state: Rc<RefCell<State>>,
+}
+
+impl NullDevice {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn log(&self) -> Vec<String> {
+        self.state.borrow().log.clone()
+    }
+
+    pub fn take_log(&self) -> Vec<String> {
+        std::mem::take(&mut self.state.borrow_mut().log)
+    }
+
+    /// The state as of the last submission.
+    pub fn resource_state(&self, resource: &NullResource) -> ResourceState {
+        self.state.borrow().resources[resource.id].state
+    }
+
+    /// Lets the GPU run until every queue is empty or stuck on a fence.
+    pub fn run_gpu(&self) {
+        self.state.borrow_mut().run_gpu();
+    }
+}
+
+impl Device<Null> for NullDevice {
+    fn create_queue(&self, queue_type: QueueType) -> Result<NullQueue, BackendError> {
+        let mut state = self.state.borrow_mut();
+        let id = state.queues.len();
+        state.queues.push(VecDeque::new());
+        state
+            .log
+            .push(format!("device: create_queue {queue_type:?} -> queue {id}"));
+        Ok(NullQueue {
+            state: self.state.clone(),
+            id,
+            queue_type,
+        })
+    }
+
+    fn create_fence(&self, initial_value: u64) -> Result<NullFence, BackendError> {
+        let mut state = self.state.borrow_mut();
+        let id = state.fences.len();
+        state.fences.push(FenceInfo {
+            completed: initial_value,
+            last_signaled: initial_value,
+        });
+        state.log.push(format!(
+            "device: create_fence {initial_value} -> fence {id}"
+        ));
+        Ok(NullFence {
+            state: self.state.clone(),
+            id,
+        })
+    }
+
+    fn create_command_list(&self, queue_type: QueueType) -> Result<NullCommandList, BackendError> {
+        let mut state = self.state.borrow_mut();
+        let id = state.pending.len();
+        state.pending.push(0);
+        state.log.push(format!(
+            "device: create_command_list {queue_type:?} -> list {id}"
+        ));
+        Ok(NullCommandList {
+            state: self.state.clone(),
+            id,
+            queue_type,
+            open: true,
+            tracked: Vec::new(),
+        })
+    }
+
+    fn create_resource(
+        &self,
+        desc: &ResourceDesc,
+        heap: HeapType,
+        initial_state: ResourceState,
+    ) -> Result<NullResource, BackendError> {
+        match *desc {
+            ResourceDesc::Buffer { size: 0 } => return invalid("buffers can't be empty".into()),
+            ResourceDesc::Texture2d { .. } if heap != HeapType::Default => {
+                return invalid(format!("textures can't live in {heap:?} heaps"))
+            }
+            ResourceDesc::Texture2d { width, height, .. } if width == 0 || height == 0 => {
+                return invalid(format!("a {width}x{height} texture is empty"))
+            }
+            _ => {}
+        }
+        if let Some(required) = heap.required_state() {
+            if initial_state != required {
+                return invalid(format!(
+                    "resources in {heap:?} heaps start in {required:?}, not {initial_state:?}"
+                ));
+            }
+        }
+        check_state(desc, initial_state)?;
+
+        let mut state = self.state.borrow_mut();
+        let id = state.resources.len();
+        state.resources.push(ResourceInfo {
+            desc: *desc,
+            heap,
+            state: initial_state,
+        });
+        state.log.push(format!(
+            "device: create_resource {desc:?} {heap:?} {initial_state:?} -> resource {id}"
+        ));
+        Ok(NullResource { id })
+    }
+}
+
+fn check_state(desc: &ResourceDesc, state: ResourceState) -> Result<(), BackendError> {
+    let render_target = matches!(
+        desc,
+        ResourceDesc::Texture2d {
+            render_target: true,
+            ..
+        }
+    );
+    if state == ResourceState::RenderTarget && !render_target {
+        return invalid(format!("{desc:?} isn't a render target"));
+    }
+    Ok(())
+}
+
+pub struct NullQueue {
+    state: Rc<RefCell<State>>,
+    id: usize,
+    queue_type: QueueType,
+}
+
+impl Queue<Null> for NullQueue {
+    fn queue_type(&self) -> QueueType {
+        self.queue_type
+    }
+
+    fn submit(&mut self, lists: &[&NullCommandList]) -> Result<(), BackendError> {
+        let mut state = self.state.borrow_mut();
+
+        // Check everything before changing anything, so a failed submit
+        // leaves no trace.
+        let mut states: Vec<_> = state.resources.iter().map(|r| r.state).collect();
+        for list in lists {
+            if list.open {
+                return invalid(format!("list {} is still open", list.id));
+            }
+            if list.queue_type != self.queue_type {
+                return invalid(format!(
+                    "list {} is a {:?} list, but queue {} is {:?}",
+                    list.id, list.queue_type, self.id, self.queue_type
+                ));
+            }
+            for tracked in &list.tracked {
+                let current = states[tracked.resource];
+                if current.bits() != tracked.before.bits() {
+                    return invalid(format!(
+                        "list {} expects resource {} in {:?}, but it's in {current:?}",
+                        list.id, tracked.resource, tracked.before
+                    ));
+                }
+                states[tracked.resource] = tracked.after;
+            }
+        }
+
+        for (resource, new_state) in state.resources.iter_mut().zip(states) {
+            resource.state = new_state;
+        }
+        for list in lists {
+            state.pending[list.id] += 1;
+            state.queues[self.id].push_back(Op::Execute(list.id));
+        }
+        let names: Vec<_> = lists
+            .iter()
+            .map(|list| format!("list {}", list.id))
+            .collect();
+        let entry = format!("queue {}: submit {}", self.id, names.join(", "));
+        state.log.push(entry);
+        Ok(())
+    }
+
+    fn signal(&mut self, fence: &NullFence, value: u64) -> Result<(), BackendError> {
+        let mut state = self.state.borrow_mut();
+        let last_signaled = state.fences[fence.id].last_signaled;
+        if value <= last_signaled {
+            return invalid(format!(
+                "fence {} was already signaled with {last_signaled}, {value} goes backwards",
+                fence.id
+            ));
+        }
+        state.fences[fence.id].last_signaled = value;
+        state.queues[self.id].push_back(Op::Signal {
+            fence: fence.id,
+            value,
+        });
+        let entry = format!("queue {}: signal fence {} = {value}", self.id, fence.id);
+        state.log.push(entry);
+        Ok(())
+    }
+
+    fn wait(&mut self, fence: &NullFence, value: u64) -> Result<(), BackendError> {
+        let mut state = self.state.borrow_mut();
+        state.queues[self.id].push_back(Op::Wait {
+            fence: fence.id,
+            value,
+        });
+        let entry = format!("queue {}: wait fence {} >= {value}", self.id, fence.id);
+        state.log.push(entry);
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct NullFence {
+    state: Rc<RefCell<State>>,
+    id: usize,
+}
+
+impl Fence for NullFence {
+    fn completed_value(&self) -> u64 {
+        self.state.borrow().fences[self.id].completed
+    }
+
+    /// Runs the GPU, failing if that isn't enough to reach `value`.
+    fn wait(&self, value: u64) -> Result<(), BackendError> {
+        let mut state = self.state.borrow_mut();
+        state.run_gpu();
+        let completed = state.fences[self.id].completed;
+        if completed < value {
+            return invalid(format!(
+                "fence {} is stuck at {completed} and will never reach {value}",
+                self.id
+            ));
+        }
+        state
+            .log
+            .push(format!("cpu: wait fence {} >= {value}", self.id));
+        Ok(())
+    }
+}
+
+/// The state a list needs a resource in when it runs, and the state it
+/// leaves it in.
+struct Tracked {
+    resource: usize,
+    before: ResourceState,
+    after: ResourceState,
+}
+
+pub struct NullCommandList {
+    state: Rc<RefCell<State>>,
+    id: usize,
+    queue_type: QueueType,
+    open: bool,
+    tracked: Vec<Tracked>,
+}
+
+impl NullCommandList {
+    fn check_open(&self) -> Result<(), BackendError> {
+        if !self.open {
+            return invalid(format!("list {} is closed", self.id));
+        }
+        Ok(())
+    }
+
+    fn log(&self, entry: String) {
+        let entry = format!("list {}: {entry}", self.id);
+        self.state.borrow_mut().log.push(entry);
+    }
+
+    fn desc(&self, resource: &NullResource) -> ResourceDesc {
+        self.state.borrow().resources[resource.id].desc
+    }
+
+    /// Checks `resource` will be in `needed` at this point of the list.
+    fn expect_state(
+        &mut self,
+        resource: &NullResource,
+        needed: ResourceState,
+    ) -> Result<(), BackendError> {
+        let state = self.state.borrow();
+        let info = &state.resources[resource.id];
+        let current = match info.heap.required_state() {
+            Some(fixed) => fixed,
+            None => match self.tracked.iter().find(|t| t.resource == resource.id) {
+                Some(tracked) => tracked.after,
+                None => {
+                    self.tracked.push(Tracked {
+                        resource: resource.id,
+                        before: needed,
+                        after: needed,
+                    });
+                    needed
+                }
+            },
+        };
+        if !current.contains(needed) {
+            return invalid(format!(
+                "resource {} needs to be in {needed:?}, but it's in {current:?}",
+                resource.id
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl CommandList<Null> for NullCommandList {
+    fn queue_type(&self) -> QueueType {
+        self.queue_type
+    }
+
+    fn reset(&mut self) -> Result<(), BackendError> {
+        if self.open {
+            return invalid(format!("list {} has to be closed to be reset", self.id));
+        }
+        if self.state.borrow().pending[self.id] > 0 {
+            return invalid(format!("list {} is still executing", self.id));
+        }
+        self.open = true;
+        self.tracked.clear();
+        self.log("reset".into());
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), BackendError> {
+        self.check_open()?;
+        self.open = false;
+        self.log("close".into());
+        Ok(())
+    }
+
+    fn barrier(
+        &mut self,
+        resource: &NullResource,
+        before: ResourceState,
+        after: ResourceState,
+    ) -> Result<(), BackendError> {
+        self.check_open()?;
+        let (desc, heap) = {
+            let state = self.state.borrow();
+            let info = &state.resources[resource.id];
+            (info.desc, info.heap)
+        };
+        if heap.required_state().is_some() {
+            return invalid(format!(
+                "resource {} lives in a {heap:?} heap and can't change state",
+                resource.id
+            ));
+        }
+        if before.bits() == after.bits() {
+            return invalid(format!("resource {} is already in {after:?}", resource.id));
+        }
+        check_state(&desc, after)?;
+
+        match self.tracked.iter_mut().find(|t| t.resource == resource.id) {
+            Some(tracked) if tracked.after.bits() != before.bits() => {
+                return invalid(format!(
+                    "resource {} is in {:?}, not {before:?}",
+                    resource.id, tracked.after
+                ))
+            }
+            Some(tracked) => tracked.after = after,
+            None => self.tracked.push(Tracked {
+                resource: resource.id,
+                before,
+                after,
+            }),
+        }
+        self.log(format!(
+            "barrier resource {} {before:?} -> {after:?}",
+            resource.id
+        ));
+        Ok(())
+    }
+
+    fn uav_barrier(&mut self, resource: &NullResource) -> Result<(), BackendError> {
+        self.check_open()?;
+        self.log(format!("uav_barrier resource {}", resource.id));
+        Ok(())
+    }
+
+    fn copy_buffer(
+        &mut self,
+        dst: &NullResource,
+        dst_offset: u64,
+        src: &NullResource,
+        src_offset: u64,
+        size: u64,
+    ) -> Result<(), BackendError> {
+        self.check_open()?;
+        for (resource, offset) in [(dst, dst_offset), (src, src_offset)] {
+            let ResourceDesc::Buffer { size: buffer_size } = self.desc(resource) else {
+                return invalid(format!("resource {} isn't a buffer", resource.id));
+            };
+            if offset.checked_add(size).is_none_or(|end| end > buffer_size) {
+                return invalid(format!(
+                    "copying {size} bytes at {offset} overruns resource {}, which is {buffer_size} bytes",
+                    resource.id
+                ));
+            }
+        }
+        self.expect_state(src, ResourceState::CopySource)?;
+        self.expect_state(dst, ResourceState::CopyDest)?;
+
+        self.log(format!(
+            "copy_buffer resource {}+{dst_offset} <- resource {}+{src_offset}, {size} bytes",
+            dst.id, src.id
+        ));
+        Ok(())
+    }
+
+    fn copy_texture_to_buffer(
+        &mut self,
+        dst: &NullResource,
+        layout: &ReadbackLayout,
+        src: &NullResource,
+    ) -> Result<(), BackendError> {
+        self.check_open()?;
+        match self.desc(src) {
+            ResourceDesc::Texture2d {
+                width,
+                height,
+                format,
+                ..
+            } if (width, height, format) == (layout.width, layout.height, layout.format) => {}
+            desc => {
+                return invalid(format!(
+                    "resource {} is {desc:?}, which doesn't match {layout:?}",
+                    src.id
+                ))
+            }
+        }
+        match self.desc(dst) {
+            ResourceDesc::Buffer { size } if size >= layout.size() => {}
+            desc => {
+                return invalid(format!(
+                    "resource {} is {desc:?}, but {} bytes are needed",
+                    dst.id,
+                    layout.size()
+                ))
+            }
+        }
+        self.expect_state(src, ResourceState::CopySource)?;
+        self.expect_state(dst, ResourceState::CopyDest)?;
+
+        self.log(format!(
+            "copy_texture_to_buffer resource {} <- resource {}",
+            dst.id, src.id
+        ));
+        Ok(())
+    }
+}
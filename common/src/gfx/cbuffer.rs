@@ -0,0 +1,271 @@
+// HLSL constant buffer packing: members are packed into 16-byte registers,
+// and a member that would straddle a register boundary starts the next one.
+// Arrays, matrices and structs always start a new register, and every array
+// element but the last fills a whole register.
+//
+// `cbuffer!` declares a `#[repr(C)]` struct and checks at compile time that
+// Rust lays out every field where HLSL expects it, so a struct that needs
+// explicit padding fails to build instead of rendering garbage.
+
+use super::ring_allocator::CONSTANT_BUFFER_ALIGNMENT;
+
+pub const REGISTER_SIZE: u32 = 16;
+/// `D3D12_REQ_CONSTANT_BUFFER_ELEMENT_COUNT` registers.
+pub const MAX_CONSTANT_BUFFER_SIZE: u32 = 4096 * REGISTER_SIZE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HlslTypeDesc {
+    pub name: &'static str,
+    pub size: u32,
+    /// Arrays, matrices and structs start on a register boundary.
+    pub register_aligned: bool,
+    pub array_len: Option<u32>,
+}
+
+impl HlslTypeDesc {
+    /// A scalar or vector.
+    pub const fn packed(name: &'static str, size: u32) -> Self {
+        Self {
+            name,
+            size,
+            register_aligned: false,
+            array_len: None,
+        }
+    }
+
+    pub const fn aligned(name: &'static str, size: u32) -> Self {
+        Self {
+            name,
+            size,
+            register_aligned: true,
+            array_len: None,
+        }
+    }
+
+    /// `len` elements of a type that fills whole registers.
+    pub const fn array(element: HlslTypeDesc, len: u32) -> Self {
+        Self {
+            name: element.name,
+            size: element.size * len,
+            register_aligned: true,
+            array_len: Some(len),
+        }
+    }
+}
+
+/// A Rust type with a known HLSL equivalent. Arrays are only implemented
+/// for 16-byte elements (`float4 name[N]`), since HLSL pads smaller ones to
+/// a full register and `[f32; N]` can't match that.
+pub trait HlslType {
+    const TYPE: HlslTypeDesc;
+}
+
+macro_rules! hlsl_types {
+    ($($ty:ty => $name:literal),* $(,)?) => {
+        $(
+            impl HlslType for $ty {
+                const TYPE: HlslTypeDesc =
+                    HlslTypeDesc::packed($name, std::mem::size_of::<$ty>() as u32);
+            }
+        )*
+    };
+}
+
+hlsl_types! {
+    f32 => "float",
+    [f32; 2] => "float2",
+    [f32; 3] => "float3",
+    [f32; 4] => "float4",
+    u32 => "uint",
+    [u32; 2] => "uint2",
+    [u32; 3] => "uint3",
+    [u32; 4] => "uint4",
+    i32 => "int",
+    [i32; 2] => "int2",
+    [i32; 3] => "int3",
+    [i32; 4] => "int4",
+}
+
+impl<T: HlslType, const N: usize> HlslType for [[T; 4]; N]
+where
+    [T; 4]: HlslType,
+{
+    const TYPE: HlslTypeDesc = HlslTypeDesc::array(<[T; 4]>::TYPE, N as u32);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Member {
+    pub name: &'static str,
+    pub ty: HlslTypeDesc,
+    pub offset: u32,
+}
+
+pub trait ConstantBuffer: HlslType + Sized {
+    const MEMBERS: &'static [Member];
+    /// The size of a CBV for this buffer, rounded up to the 256-byte
+    /// placement alignment.
+    const CBV_SIZE: u32 = align_up(
+        std::mem::size_of::<Self>() as u32,
+        CONSTANT_BUFFER_ALIGNMENT as u32,
+    );
+
+    /// The matching HLSL struct, with offsets as comments.
+    fn hlsl_struct() -> String {
+        let mut hlsl = format!("struct {}\n{{\n", Self::TYPE.name);
+        hlsl_members(&mut hlsl, Self::MEMBERS);
+        hlsl.push_str("};\n");
+        hlsl
+    }
+
+    /// The members as a `cbuffer` block bound to `register(b<register>)`.
+    fn hlsl_cbuffer(name: &str, register: u32, space: u32) -> String {
+        let mut hlsl = format!("cbuffer {name} : register(b{register}");
+        if space != 0 {
+            hlsl.push_str(&format!(", space{space}"));
+        }
+        hlsl.push_str(")\n{\n");
+        hlsl_members(&mut hlsl, Self::MEMBERS);
+        hlsl.push_str("};\n");
+        hlsl
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: `cbuffer!` structs are `repr(C)` and only contain `f32`,
+        // `u32`, `i32` and arrays or structs of them, so there's no padding.
+        unsafe {
+            std::slice::from_raw_parts(
+                self as *const Self as *const u8,
+                std::mem::size_of::<Self>(),
+            )
+        }
+    }
+}
+
+fn hlsl_members(hlsl: &mut String, members: &[Member]) {
+    for member in members {
+        let array = match member.ty.array_len {
+            Some(len) => format!("[{len}]"),
+            None => String::new(),
+        };
+        hlsl.push_str(&format!(
+            "    {} {}{array}; // offset {}\n",
+            member.ty.name, member.name, member.offset
+        ));
+    }
+}
+
+pub const fn align_up(value: u32, alignment: u32) -> u32 {
+    value.div_ceil(alignment) * alignment
+}
+
+/// Where HLSL puts member `index` of a cbuffer with the given member types.
+pub const fn member_offset(types: &[HlslTypeDesc], index: usize) -> u32 {
+    let mut offset = 0;
+    let mut i = 0;
+    while i <= index {
+        let ty = types[i];
+        let straddles = offset % REGISTER_SIZE + ty.size > REGISTER_SIZE;
+        if ty.register_aligned || straddles {
+            offset = align_up(offset, REGISTER_SIZE);
+        }
+        if i == index {
+            return offset;
+        }
+        offset += ty.size;
+        i += 1;
+    }
+    unreachable!()
+}
+
+/// The end of the last member. HLSL rounds cbuffer and struct sizes up to
+/// whole registers; this doesn't, so it's what Rust needs to write.
+pub const fn packed_size(types: &[HlslTypeDesc]) -> u32 {
+    match types.len() {
+        0 => 0,
+        n => member_offset(types, n - 1) + types[n - 1].size,
+    }
+}
+
+/// Declares a `#[repr(C)]` struct whose layout matches the HLSL cbuffer
+/// packing of its fields, failing to compile if it doesn't.
+///
+/// ```ignore
+/// cbuffer! {
+///     #[derive(Clone, Copy, Default)]
+///     pub struct Lighting {
+///         pub direction: [f32; 3],
+///         pub intensity: f32,
+///         pub colors: [[f32; 4]; 4],
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! cbuffer {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_attr:meta])* $field_vis:vis $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[repr(C)]
+        $vis struct $name {
+            $($(#[$field_attr])* $field_vis $field: $ty),*
+        }
+
+        impl $crate::gfx::cbuffer::HlslType for $name {
+            // Structs occupy whole registers.
+            const TYPE: $crate::gfx::cbuffer::HlslTypeDesc = $crate::gfx::cbuffer::HlslTypeDesc::aligned(
+                stringify!($name),
+                $crate::gfx::cbuffer::align_up(
+                    $crate::gfx::cbuffer::packed_size(&[
+                        $(<$ty as $crate::gfx::cbuffer::HlslType>::TYPE),*
+                    ]),
+                    $crate::gfx::cbuffer::REGISTER_SIZE,
+                ),
+            );
+        }
+
+        impl $crate::gfx::cbuffer::ConstantBuffer for $name {
+            const MEMBERS: &'static [$crate::gfx::cbuffer::Member] = &[
+                $($crate::gfx::cbuffer::Member {
+                    name: stringify!($field),
+                    ty: <$ty as $crate::gfx::cbuffer::HlslType>::TYPE,
+                    offset: ::std::mem::offset_of!($name, $field) as u32,
+                }),*
+            ];
+        }
+
+        const _: () = {
+            use $crate::gfx::cbuffer::{member_offset, packed_size, HlslType, MAX_CONSTANT_BUFFER_SIZE};
+
+            let types: &[$crate::gfx::cbuffer::HlslTypeDesc] = &[$(<$ty as HlslType>::TYPE),*];
+            let mut index = 0;
+            $(
+                assert!(
+                    ::std::mem::offset_of!($name, $field) as u32 == member_offset(types, index),
+                    concat!(
+                        "`", stringify!($name), "::", stringify!($field),
+                        "` is not where HLSL packs it; add or remove padding before it",
+                    ),
+                );
+                assert!(
+                    ::std::mem::size_of::<$ty>() as u32 <= <$ty as HlslType>::TYPE.size,
+                    concat!("`", stringify!($name), "::", stringify!($field), "` is larger in Rust than in HLSL"),
+                );
+                index += 1;
+            )*
+            let _ = index;
+
+            assert!(
+                ::std::mem::size_of::<$name>() as u32
+                    <= $crate::gfx::cbuffer::align_up(packed_size(types), $crate::gfx::cbuffer::REGISTER_SIZE),
+                concat!("`", stringify!($name), "` has trailing data HLSL doesn't"),
+            );
+            assert!(
+                <$name as $crate::gfx::cbuffer::ConstantBuffer>::CBV_SIZE <= MAX_CONSTANT_BUFFER_SIZE,
+                concat!("`", stringify!($name), "` is larger than a constant buffer can be"),
+            );
+        };
+    };
+}
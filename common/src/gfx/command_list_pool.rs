@@ -0,0 +1,76 @@
+use windows::Win32::Graphics::Direct3D12::{
+    ID3D12CommandAllocator, ID3D12Device, ID3D12GraphicsCommandList,
+};
+
+use super::{
+    backend::{CommandList, D3d12, D3d12CommandList},
+    command_queue::{CommandQueue, SyncPoint},
+    recycle_pool::RecyclePool,
+};
+
+/// Hands out ready to record command lists, recycling allocators once the
+/// GPU is done with them so several lists can be recorded each frame.
+pub struct CommandListPool {
+    device: ID3D12Device,
+    allocators: RecyclePool<ID3D12CommandAllocator>,
+    lists: [Vec<ID3D12GraphicsCommandList>; 3],
+}
+
+impl CommandListPool {
+    pub fn new(device: &ID3D12Device) -> Self {
+        Self {
+            device: device.clone(),
+            allocators: RecyclePool::new(),
+            lists: Default::default(),
+        }
+    }
+
+    pub fn acquire(
+        &mut self,
+        queue: &CommandQueue<D3d12>,
+    ) -> windows::core::Result<D3d12CommandList> {
+        let queue_type = queue.queue_type();
+        let list_type = queue_type.command_list_type();
+
+        let allocator = match self.allocators.acquire(queue_type, queue.completed_value()) {
+            Some(allocator) => {
+                // Command list allocators can only be reset when the associated
+                // command lists have finished execution on the GPU.
+                unsafe { allocator.Reset() }?;
+                allocator
+            }
+            None => unsafe { self.device.CreateCommandAllocator(list_type) }?,
+        };
+
+        // A command list can be reset as soon as it has been submitted.
+        let list = match self.lists[queue_type.index()].pop() {
+            Some(list) => {
+                unsafe { list.Reset(&allocator, None) }?;
+                list
+            }
+            // todo: initial state PSO gets passed here instead of None.
+            None => unsafe {
+                self.device
+                    .CreateCommandList(0, list_type, &allocator, None)
+            }?,
+        };
+
+        Ok(D3d12CommandList::from_parts(list, allocator, queue_type))
+    }
+
+    /// Returns lists after they have been executed; their allocators become
+    /// available again once the sync point is reached.
+    pub fn retire(
+        &mut self,
+        lists: impl IntoIterator<Item = D3d12CommandList>,
+        sync_point: &SyncPoint<D3d12>,
+    ) {
+        for list in lists {
+            let queue_type = list.queue_type();
+            let (list, allocator) = list.into_parts();
+            self.allocators
+                .retire(queue_type, allocator, sync_point.value());
+            self.lists[queue_type.index()].push(list);
+        }
+    }
+}
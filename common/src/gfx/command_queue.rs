@@ -0,0 +1,130 @@
+use std::fmt;
+
+use super::{
+    backend::{Api, BackendError, Device, Fence, Queue},
+    queue_type::QueueType,
+};
+
+/// A point in a queue's timeline: work submitted up to it has finished once
+/// the fence reaches the value.
+pub struct SyncPoint<A: Api> {
+    fence: A::Fence,
+    value: u64,
+}
+
+impl<A: Api> SyncPoint<A> {
+    pub fn fence(&self) -> &A::Fence {
+        &self.fence
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.fence.completed_value() >= self.value
+    }
+}
+
+impl<A: Api> Clone for SyncPoint<A> {
+    fn clone(&self) -> Self {
+        Self {
+            fence: self.fence.clone(),
+            value: self.value,
+        }
+    }
+}
+
+impl<A: Api> fmt::Debug for SyncPoint<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SyncPoint")
+            .field("value", &self.value)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A command queue that owns the fence it signals after each submission.
+pub struct CommandQueue<A: Api> {
+    queue: A::Queue,
+    fence: A::Fence,
+    next_fence_value: u64,
+}
+
+impl<A: Api> CommandQueue<A> {
+    pub fn new(device: &A::Device, queue_type: QueueType) -> Result<Self, BackendError> {
+        Ok(Self {
+            queue: device.create_queue(queue_type)?,
+            fence: device.create_fence(0)?,
+            next_fence_value: 1,
+        })
+    }
+
+    pub fn queue(&self) -> &A::Queue {
+        &self.queue
+    }
+
+    pub fn queue_type(&self) -> QueueType {
+        self.queue.queue_type()
+    }
+
+    pub fn fence(&self) -> &A::Fence {
+        &self.fence
+    }
+
+    pub fn completed_value(&self) -> u64 {
+        self.fence.completed_value()
+    }
+
+    /// The sync point of the most recent submission.
+    pub fn last_sync_point(&self) -> SyncPoint<A> {
+        SyncPoint {
+            fence: self.fence.clone(),
+            value: self.next_fence_value - 1,
+        }
+    }
+
+    pub fn execute(&mut self, lists: &[&A::CommandList]) -> Result<SyncPoint<A>, BackendError> {
+        self.queue.submit(lists)?;
+        self.signal()
+    }
+
+    pub fn signal(&mut self) -> Result<SyncPoint<A>, BackendError> {
+        let value = self.next_fence_value;
+        self.queue.signal(&self.fence, value)?;
+        self.next_fence_value += 1;
+
+        Ok(SyncPoint {
+            fence: self.fence.clone(),
+            value,
+        })
+    }
+
+    pub fn is_complete(&self, sync_point: &SyncPoint<A>) -> bool {
+        sync_point.is_complete()
+    }
+
+    /// Blocks the calling thread until the sync point is reached.
+    pub fn wait_cpu(&self, sync_point: &SyncPoint<A>) -> Result<(), BackendError> {
+        sync_point.fence.wait(sync_point.value)
+    }
+
+    /// Makes work submitted to this queue after this call wait on the GPU for
+    /// a sync point, typically one from another queue.
+    pub fn wait_gpu(&mut self, sync_point: &SyncPoint<A>) -> Result<(), BackendError> {
+        self.queue.wait(&sync_point.fence, sync_point.value)
+    }
+
+    /// Blocks until everything submitted so far has finished executing.
+    pub fn flush(&mut self) -> Result<(), BackendError> {
+        let sync_point = self.signal()?;
+        self.wait_cpu(&sync_point)
+    }
+}
+
+impl<A: Api> Drop for CommandQueue<A> {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            crate::util::print_debug_string(&format!("failed to flush command queue {e}"));
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::any::Any;
+
+use super::{
+    backend::{Api, Fence},
+    deferred_queue::DeferredQueue,
+};
+
+enum Retired<A: Api> {
+    Resource(A::Resource),
+    Object(Box<dyn Any>),
+}
+
+/// Keeps GPU objects alive until the submission that last used them has
+/// completed, rather than stalling the CPU to release them immediately.
+/// Memory from a `HeapAllocator` is deferred by the allocator instead, since
+/// only it can free it.
+pub struct DeferredDeleter<A: Api> {
+    queue: DeferredQueue<Retired<A>>,
+}
+
+impl<A: Api> Default for DeferredDeleter<A> {
+    fn default() -> Self {
+        Self {
+            queue: DeferredQueue::new(),
+        }
+    }
+}
+
+impl<A: Api> DeferredDeleter<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn retire_resource(&mut self, resource: A::Resource, fence_value: u64) {
+        self.queue.retire(fence_value, Retired::Resource(resource));
+    }
+
+    /// Anything else that's released by dropping it, like a descriptor heap.
+    pub fn retire<T: 'static>(&mut self, object: T, fence_value: u64) {
+        self.queue
+            .retire(fence_value, Retired::Object(Box::new(object)));
+    }
+
+    /// Releases everything retired with a fence value the GPU has passed.
+    pub fn collect(&mut self, fence: &A::Fence) {
+        let completed = fence.completed_value();
+        release(self.queue.drain_completed(completed));
+    }
+
+    /// Releases everything regardless of fence values. Only call this once the
+    /// GPU is idle, e.g. on shutdown before reporting live objects.
+    pub fn flush_all(&mut self) {
+        release(self.queue.drain_all());
+    }
+}
+
+fn release<A: Api>(retired: impl Iterator<Item = Retired<A>>) {
+    for item in retired {
+        match item {
+            Retired::Resource(resource) => drop(resource),
+            Retired::Object(object) => drop(object),
+        }
+    }
+}
+
+impl<A: Api> Drop for DeferredDeleter<A> {
+    fn drop(&mut self) {
+        if !self.queue.is_empty() {
+            crate::util::print_debug_string(&format!(
+                "DeferredDeleter dropped with {} objects still pending",
+                self.queue.len()
+            ));
+        }
+    }
+}
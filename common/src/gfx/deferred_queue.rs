@@ -0,0 +1,49 @@
+use std::collections::VecDeque;
+
+/// Holds on to items until the GPU has passed the fence value they were last
+/// used with. Items come back out in fence order, and in the order they were
+/// retired for equal fence values.
+#[derive(Debug)]
+pub struct DeferredQueue<T> {
+    items: VecDeque<(u64, T)>,
+}
+
+impl<T> Default for DeferredQueue<T> {
+    fn default() -> Self {
+        Self {
+            items: VecDeque::new(),
+        }
+    }
+}
+
+impl<T> DeferredQueue<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn retire(&mut self, fence_value: u64, item: T) {
+        // Fence values normally arrive in order, so this is almost always a push
+        // to the back.
+        let index = self.items.partition_point(|(f, _)| *f <= fence_value);
+        self.items.insert(index, (fence_value, item));
+    }
+
+    pub fn drain_completed(&mut self, completed_fence_value: u64) -> impl Iterator<Item = T> + '_ {
+        let count = self
+            .items
+            .partition_point(|(f, _)| *f <= completed_fence_value);
+        self.items.drain(..count).map(|(_, item)| item)
+    }
+
+    pub fn drain_all(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.items.drain(..).map(|(_, item)| item)
+    }
+}
@@ -0,0 +1,83 @@
+/// A `DXGI_FORMAT` value, kept as a plain number so descriptions that use it
+/// don't depend on D3D12.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Format(pub u32);
+
+impl Format {
+    pub const UNKNOWN: Self = Self(0);
+    pub const R32G32B32A32_FLOAT: Self = Self(2);
+    pub const R32G32B32A32_UINT: Self = Self(3);
+    pub const R32G32B32A32_SINT: Self = Self(4);
+    pub const R32G32B32_FLOAT: Self = Self(6);
+    pub const R32G32B32_UINT: Self = Self(7);
+    pub const R32G32B32_SINT: Self = Self(8);
+    pub const R16G16B16A16_FLOAT: Self = Self(10);
+    pub const R16G16B16A16_UNORM: Self = Self(11);
+    pub const R16G16B16A16_SNORM: Self = Self(13);
+    pub const R32G32_FLOAT: Self = Self(16);
+    pub const R32G32_UINT: Self = Self(17);
+    pub const R32G32_SINT: Self = Self(18);
+    pub const R10G10B10A2_UNORM: Self = Self(24);
+    pub const R11G11B10_FLOAT: Self = Self(26);
+    pub const R8G8B8A8_UNORM: Self = Self(28);
+    pub const R8G8B8A8_UNORM_SRGB: Self = Self(29);
+    pub const R8G8B8A8_UINT: Self = Self(30);
+    pub const R16G16_FLOAT: Self = Self(34);
+    pub const R16G16_UNORM: Self = Self(35);
+    pub const D32_FLOAT: Self = Self(40);
+    pub const R32_FLOAT: Self = Self(41);
+    pub const R32_UINT: Self = Self(42);
+    pub const R32_SINT: Self = Self(43);
+    pub const D24_UNORM_S8_UINT: Self = Self(45);
+    pub const R8G8_UNORM: Self = Self(49);
+    pub const R16_FLOAT: Self = Self(54);
+    pub const R16_UNORM: Self = Self(56);
+    pub const R16_UINT: Self = Self(57);
+    pub const R8_UNORM: Self = Self(61);
+    pub const A8_UNORM: Self = Self(65);
+    pub const BC1_UNORM: Self = Self(71);
+    pub const BC1_UNORM_SRGB: Self = Self(72);
+    pub const BC2_UNORM: Self = Self(74);
+    pub const BC2_UNORM_SRGB: Self = Self(75);
+    pub const BC3_UNORM: Self = Self(77);
+    pub const BC3_UNORM_SRGB: Self = Self(78);
+    pub const BC4_UNORM: Self = Self(80);
+    pub const BC4_SNORM: Self = Self(81);
+    pub const BC5_UNORM: Self = Self(83);
+    pub const BC5_SNORM: Self = Self(84);
+    pub const B5G6R5_UNORM: Self = Self(85);
+    pub const B5G5R5A1_UNORM: Self = Self(86);
+    pub const B8G8R8A8_UNORM: Self = Self(87);
+    pub const B8G8R8X8_UNORM: Self = Self(88);
+    pub const B8G8R8A8_UNORM_SRGB: Self = Self(91);
+    pub const B8G8R8X8_UNORM_SRGB: Self = Self(93);
+    pub const BC6H_UF16: Self = Self(95);
+    pub const BC6H_SF16: Self = Self(96);
+    pub const BC7_UNORM: Self = Self(98);
+    pub const BC7_UNORM_SRGB: Self = Self(99);
+    pub const B4G4R4A4_UNORM: Self = Self(115);
+
+    /// Whether texels are stored in 4x4 blocks.
+    pub fn is_block_compressed(self) -> bool {
+        matches!(self.0, 70..=84 | 94..=99)
+    }
+
+    /// Bytes per 4x4 block of a block-compressed format, or per texel of
+    /// anything else. None for the video and sub-byte formats.
+    pub fn block_bytes(self) -> Option<u32> {
+        let bytes = match self.0 {
+            1..=4 => 16,
+            5..=8 => 12,
+            9..=22 => 8,
+            23..=47 | 67 => 4,
+            48..=59 => 2,
+            60..=65 => 1,
+            70..=72 | 79..=81 => 8,
+            73..=78 | 82..=84 | 94..=99 => 16,
+            85 | 86 | 115 => 2,
+            87..=93 => 4,
+            _ => return None,
+        };
+        Some(bytes)
+    }
+}
@@ -0,0 +1,656 @@
+// Render graph: passes declare which resources they read and write, and
+// compiling the graph culls passes whose results are never used, works out
+// transient resource lifetimes so that resources can be shared between passes
+// that don't overlap, and places the barriers between passes. Compilation knows
+// nothing about D3D12; `record_barriers` turns the result into backend calls,
+// and `d3d12` into batched D3D12 barriers.
+
+#[cfg(windows)]
+mod d3d12;
+
+#[cfg(windows)]
+pub use d3d12::{d3d12_barriers, ResourceStateExt};
+
+use std::fmt;
+
+use super::backend::{Api, BackendError, CommandList};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ResourceId(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PassId(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceState {
+    Common,
+    Present,
+    VertexAndConstantBuffer,
+    IndexBuffer,
+    RenderTarget,
+    DepthWrite,
+    DepthRead,
+    /// Readable from every shader stage.
+    ShaderResource,
+    UnorderedAccess,
+    IndirectArgument,
+    CopySource,
+    CopyDest,
+    /// Every read state at once, which upload heap resources have to stay in.
+    GenericRead,
+}
+
+impl ResourceState {
+    /// The `D3D12_RESOURCE_STATES` bits. `Present` and `Common` are both zero.
+    pub fn bits(self) -> u32 {
+        match self {
+            ResourceState::Common | ResourceState::Present => 0,
+            ResourceState::VertexAndConstantBuffer => 0x1,
+            ResourceState::IndexBuffer => 0x2,
+            ResourceState::RenderTarget => 0x4,
+            ResourceState::UnorderedAccess => 0x8,
+            ResourceState::DepthWrite => 0x10,
+            ResourceState::DepthRead => 0x20,
+            ResourceState::ShaderResource => 0x40 | 0x80,
+            ResourceState::IndirectArgument => 0x200,
+            ResourceState::CopyDest => 0x400,
+            ResourceState::CopySource => 0x800,
+            ResourceState::GenericRead => 0x1 | 0x2 | 0x40 | 0x80 | 0x200 | 0x800,
+        }
+    }
+
+    /// Whether a resource in this state can be used as `other` without a
+    /// barrier, e.g. `GenericRead` as `CopySource`.
+    pub fn contains(self, other: ResourceState) -> bool {
+        self.bits() & other.bits() == other.bits()
+    }
+}
+
+/// Transient resources with equal descriptions and lifetimes that don't
+/// overlap share one physical resource. `format` is the backend's format value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceDesc {
+    Texture {
+        width: u32,
+        height: u32,
+        format: u32,
+    },
+    Buffer {
+        size: u64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Barrier {
+    Transition {
+        resource: ResourceId,
+        before: ResourceState,
+        after: ResourceState,
+    },
+    UnorderedAccess {
+        resource: ResourceId,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphError {
+    ReadBeforeWrite { pass: String, resource: String },
+    ConflictingAccess { pass: String, resource: String },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::ReadBeforeWrite { pass, resource } => {
+                write!(
+                    f,
+                    "pass `{pass}` reads `{resource}` before anything writes it"
+                )
+            }
+            GraphError::ConflictingAccess { pass, resource } => {
+                write!(
+                    f,
+                    "pass `{pass}` accesses `{resource}` in more than one state"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+enum ResourceKind {
+    Imported {
+        initial: ResourceState,
+        final_state: ResourceState,
+    },
+    Transient(ResourceDesc),
+}
+
+struct ResourceNode {
+    name: String,
+    kind: ResourceKind,
+}
+
+#[derive(Clone, Copy)]
+struct Access {
+    resource: ResourceId,
+    state: ResourceState,
+    write: bool,
+}
+
+type Execute<'a, C> = Box<dyn FnOnce(&mut C) + 'a>;
+
+struct PassNode<'a, C> {
+    name: String,
+    accesses: Vec<Access>,
+    side_effects: bool,
+    execute: Option<Execute<'a, C>>,
+}
+
+pub struct PassBuilder {
+    accesses: Vec<Access>,
+    side_effects: bool,
+}
+
+impl PassBuilder {
+    pub fn read(&mut self, resource: ResourceId, state: ResourceState) -> &mut Self {
+        self.accesses.push(Access {
+            resource,
+            state,
+            write: false,
+        });
+        self
+    }
+
+    pub fn write(&mut self, resource: ResourceId, state: ResourceState) -> &mut Self {
+        self.accesses.push(Access {
+            resource,
+            state,
+            write: true,
+        });
+        self
+    }
+
+    /// Keeps the pass even if nothing reads what it writes, e.g. readback.
+    pub fn side_effects(&mut self) -> &mut Self {
+        self.side_effects = true;
+        self
+    }
+}
+
+/// `C` is whatever the pass callbacks record into, typically a command list.
+pub struct RenderGraph<'a, C> {
+    resources: Vec<ResourceNode>,
+    passes: Vec<PassNode<'a, C>>,
+}
+
+impl<C> Default for RenderGraph<'_, C> {
+    fn default() -> Self {
+        Self {
+            resources: Vec::new(),
+            passes: Vec::new(),
+        }
+    }
+}
+
+impl<'a, C> RenderGraph<'a, C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A resource owned outside the graph, like the swapchain back buffer. It
+    /// is left in `final_state` once the graph has run.
+    pub fn import(
+        &mut self,
+        name: impl Into<String>,
+        initial: ResourceState,
+        final_state: ResourceState,
+    ) -> ResourceId {
+        self.add_resource(
+            name,
+            ResourceKind::Imported {
+                initial,
+                final_state,
+            },
+        )
+    }
+
+    pub fn create(&mut self, name: impl Into<String>, desc: ResourceDesc) -> ResourceId {
+        self.add_resource(name, ResourceKind::Transient(desc))
+    }
+
+    /// Passes run in the order they are added, less any that get culled.
+    pub fn add_pass(
+        &mut self,
+        name: impl Into<String>,
+        setup: impl FnOnce(&mut PassBuilder),
+        execute: impl FnOnce(&mut C) + 'a,
+    ) -> PassId {
+        let mut builder = PassBuilder {
+            accesses: Vec::new(),
+            side_effects: false,
+        };
+        setup(&mut builder);
+
+        // Reading and writing a resource in the same state is one access.
+        let mut accesses: Vec<Access> = Vec::with_capacity(builder.accesses.len());
+        for access in builder.accesses {
+            match accesses
+                .iter_mut()
+                .find(|a| a.resource == access.resource && a.state == access.state)
+            {
+                Some(existing) => existing.write |= access.write,
+                None => accesses.push(access),
+            }
+        }
+
+        self.passes.push(PassNode {
+            name: name.into(),
+            accesses,
+            side_effects: builder.side_effects,
+            execute: Some(Box::new(execute)),
+        });
+
+        PassId(self.passes.len() - 1)
+    }
+
+    pub fn compile(&self) -> Result<CompiledGraph, GraphError> {
+        self.validate()?;
+
+        let needed = self.find_needed_passes()?;
+        let schedule: Vec<usize> = (0..self.passes.len()).filter(|&p| needed[p]).collect();
+
+        // Lifetimes are in schedule positions.
+        let mut lifetimes: Vec<Option<(usize, usize)>> = vec![None; self.resources.len()];
+        for (position, &pass) in schedule.iter().enumerate() {
+            for access in &self.passes[pass].accesses {
+                let lifetime = &mut lifetimes[access.resource.0];
+                *lifetime =
+                    Some(lifetime.map_or((position, position), |(first, _)| (first, position)));
+            }
+        }
+
+        let (physical, physical_descs) = self.assign_physical_resources(&lifetimes);
+
+        // Track the state of every physical resource through the schedule.
+        let mut states: Vec<Option<ResourceState>> = physical
+            .iter()
+            .enumerate()
+            .map(|(r, _)| match self.resources[r].kind {
+                ResourceKind::Imported { initial, .. } => Some(initial),
+                ResourceKind::Transient(_) => None,
+            })
+            .collect();
+        let mut last_uav_write = vec![false; self.resources.len()];
+        let mut initial_states = vec![None; self.resources.len()];
+
+        let mut passes = Vec::with_capacity(schedule.len());
+        for &pass in &schedule {
+            let node = &self.passes[pass];
+            let mut barriers = Vec::new();
+
+            for access in &node.accesses {
+                let slot = physical[access.resource.0];
+                match states[slot] {
+                    // A transient resource is created in the state of its
+                    // first use.
+                    None => initial_states[slot] = Some(access.state),
+                    Some(before) if before != access.state => {
+                        barriers.push(Barrier::Transition {
+                            resource: access.resource,
+                            before,
+                            after: access.state,
+                        });
+                    }
+                    // Back to back unordered access needs a UAV barrier if
+                    // either side writes.
+                    Some(_)
+                        if access.state == ResourceState::UnorderedAccess
+                            && (last_uav_write[slot] || access.write) =>
+                    {
+                        barriers.push(Barrier::UnorderedAccess {
+                            resource: access.resource,
+                        });
+                    }
+                    Some(_) => {}
+                }
+
+                states[slot] = Some(access.state);
+                last_uav_write[slot] = access.write;
+            }
+
+            passes.push(ScheduledPass {
+                id: PassId(pass),
+                name: node.name.clone(),
+                barriers,
+            });
+        }
+
+        let mut final_barriers = Vec::new();
+        for (r, resource) in self.resources.iter().enumerate() {
+            if let ResourceKind::Imported { final_state, .. } = resource.kind {
+                if let Some(before) = states[physical[r]].filter(|s| *s != final_state) {
+                    final_barriers.push(Barrier::Transition {
+                        resource: ResourceId(r),
+                        before,
+                        after: final_state,
+                    });
+                }
+            }
+        }
+
+        Ok(CompiledGraph {
+            resource_names: self.resources.iter().map(|r| r.name.clone()).collect(),
+            imported: self
+                .resources
+                .iter()
+                .map(|r| matches!(r.kind, ResourceKind::Imported { .. }))
+                .collect(),
+            culled: (0..self.passes.len())
+                .filter(|&p| !needed[p])
+                .map(|p| self.passes[p].name.clone())
+                .collect(),
+            passes,
+            final_barriers,
+            lifetimes,
+            physical,
+            physical_descs,
+            initial_states,
+        })
+    }
+
+    /// Runs the callbacks of the scheduled passes, handing each pass's barriers
+    /// to `barriers` first.
+    pub fn execute(
+        mut self,
+        compiled: &CompiledGraph,
+        context: &mut C,
+        mut barriers: impl FnMut(&mut C, &[Barrier]),
+    ) {
+        for pass in &compiled.passes {
+            if !pass.barriers.is_empty() {
+                barriers(context, &pass.barriers);
+            }
+
+            if let Some(execute) = self.passes[pass.id.0].execute.take() {
+                execute(context);
+            }
+        }
+
+        if !compiled.final_barriers.is_empty() {
+            barriers(context, &compiled.final_barriers);
+        }
+    }
+
+    fn add_resource(&mut self, name: impl Into<String>, kind: ResourceKind) -> ResourceId {
+        self.resources.push(ResourceNode {
+            name: name.into(),
+            kind,
+        });
+        ResourceId(self.resources.len() - 1)
+    }
+
+    fn validate(&self) -> Result<(), GraphError> {
+        for pass in &self.passes {
+            for (i, a) in pass.accesses.iter().enumerate() {
+                let conflict = pass.accesses[..i]
+                    .iter()
+                    .any(|b| b.resource == a.resource && b.state != a.state);
+                if conflict {
+                    return Err(GraphError::ConflictingAccess {
+                        pass: pass.name.clone(),
+                        resource: self.resources[a.resource.0].name.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // A pass is needed if it has side effects, is the last writer of an
+    // imported resource, or writes something a needed pass reads. Writes
+    // depend on the previous writer as render targets keep their contents.
+    fn find_needed_passes(&self) -> Result<Vec<bool>, GraphError> {
+        let mut producers: Vec<Vec<usize>> = vec![Vec::new(); self.passes.len()];
+        let mut last_writer: Vec<Option<usize>> = vec![None; self.resources.len()];
+
+        for (p, pass) in self.passes.iter().enumerate() {
+            for access in &pass.accesses {
+                let r = access.resource.0;
+                match last_writer[r] {
+                    Some(writer) => {
+                        if writer != p {
+                            producers[p].push(writer);
+                        }
+                    }
+                    None => {
+                        let imported =
+                            matches!(self.resources[r].kind, ResourceKind::Imported { .. });
+                        if !access.write && !imported {
+                            return Err(GraphError::ReadBeforeWrite {
+                                pass: pass.name.clone(),
+                                resource: self.resources[r].name.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+
+            for access in pass.accesses.iter().filter(|a| a.write) {
+                last_writer[access.resource.0] = Some(p);
+            }
+        }
+
+        let mut needed = vec![false; self.passes.len()];
+        let mut stack: Vec<usize> = self
+            .passes
+            .iter()
+            .enumerate()
+            .filter(|(_, pass)| pass.side_effects)
+            .map(|(p, _)| p)
+            .collect();
+
+        for (r, resource) in self.resources.iter().enumerate() {
+            if let (ResourceKind::Imported { .. }, Some(writer)) = (&resource.kind, last_writer[r])
+            {
+                stack.push(writer);
+            }
+        }
+
+        while let Some(p) = stack.pop() {
+            if !needed[p] {
+                needed[p] = true;
+                stack.extend(&producers[p]);
+            }
+        }
+
+        Ok(needed)
+    }
+
+    // Maps every resource to a physical resource index. Imported resources
+    // are always their own; transient ones are shared greedily.
+    fn assign_physical_resources(
+        &self,
+        lifetimes: &[Option<(usize, usize)>],
+    ) -> (Vec<usize>, Vec<Option<ResourceDesc>>) {
+        let mut physical: Vec<usize> = (0..self.resources.len()).collect();
+        let mut descs: Vec<Option<ResourceDesc>> = vec![None; self.resources.len()];
+
+        let mut transients: Vec<(usize, ResourceDesc, (usize, usize))> = self
+            .resources
+            .iter()
+            .enumerate()
+            .filter_map(|(r, resource)| match (&resource.kind, lifetimes[r]) {
+                (ResourceKind::Transient(desc), Some(lifetime)) => Some((r, *desc, lifetime)),
+                _ => None,
+            })
+            .collect();
+        transients.sort_by_key(|&(r, _, (first, _))| (first, r));
+
+        // (physical index, description, last use) of each slot handed out.
+        let mut slots: Vec<(usize, ResourceDesc, usize)> = Vec::new();
+        for (r, desc, (first, last)) in transients {
+            match slots
+                .iter_mut()
+                .find(|(_, slot_desc, slot_last)| *slot_desc == desc && *slot_last < first)
+            {
+                Some(slot) => {
+                    physical[r] = slot.0;
+                    slot.2 = last;
+                }
+                None => {
+                    descs[r] = Some(desc);
+                    slots.push((r, desc, last));
+                }
+            }
+        }
+
+        (physical, descs)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ScheduledPass {
+    pub id: PassId,
+    pub name: String,
+    /// Barriers to record before the pass runs.
+    pub barriers: Vec<Barrier>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CompiledGraph {
+    resource_names: Vec<String>,
+    imported: Vec<bool>,
+    culled: Vec<String>,
+    passes: Vec<ScheduledPass>,
+    final_barriers: Vec<Barrier>,
+    lifetimes: Vec<Option<(usize, usize)>>,
+    physical: Vec<usize>,
+    physical_descs: Vec<Option<ResourceDesc>>,
+    initial_states: Vec<Option<ResourceState>>,
+}
+
+impl CompiledGraph {
+    pub fn passes(&self) -> &[ScheduledPass] {
+        &self.passes
+    }
+
+    pub fn culled(&self) -> &[String] {
+        &self.culled
+    }
+
+    /// Barriers that return imported resources to their final state.
+    pub fn final_barriers(&self) -> &[Barrier] {
+        &self.final_barriers
+    }
+
+    pub fn resource_name(&self, resource: ResourceId) -> &str {
+        &self.resource_names[resource.0]
+    }
+
+    /// First and last schedule position using the resource, if any pass does.
+    pub fn lifetime(&self, resource: ResourceId) -> Option<(usize, usize)> {
+        self.lifetimes[resource.0]
+    }
+
+    /// Resources sharing a physical index share the same backing resource.
+    pub fn physical_index(&self, resource: ResourceId) -> usize {
+        self.physical[resource.0]
+    }
+
+    /// Physical transient resources the backend needs to create, indexed by
+    /// physical index, along with the state to create them in.
+    pub fn physical_resources(
+        &self,
+    ) -> impl Iterator<Item = (usize, ResourceDesc, ResourceState)> + '_ {
+        self.physical_descs
+            .iter()
+            .zip(&self.initial_states)
+            .enumerate()
+            .filter_map(|(i, (desc, state))| Some((i, (*desc)?, (*state)?)))
+    }
+
+    fn fmt_barrier(&self, f: &mut fmt::Formatter<'_>, barrier: &Barrier) -> fmt::Result {
+        match barrier {
+            Barrier::Transition {
+                resource,
+                before,
+                after,
+            } => writeln!(
+                f,
+                "  transition {} {before:?} -> {after:?}",
+                self.resource_name(*resource)
+            ),
+            Barrier::UnorderedAccess { resource } => {
+                writeln!(f, "  uav {}", self.resource_name(*resource))
+            }
+        }
+    }
+}
+
+/// A textual form of the schedule, handy for checking it at a glance.
+impl fmt::Display for CompiledGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pass in &self.passes {
+            writeln!(f, "pass {}", pass.name)?;
+            for barrier in &pass.barriers {
+                self.fmt_barrier(f, barrier)?;
+            }
+        }
+
+        if !self.final_barriers.is_empty() {
+            writeln!(f, "end")?;
+            for barrier in &self.final_barriers {
+                self.fmt_barrier(f, barrier)?;
+            }
+        }
+
+        for name in &self.culled {
+            writeln!(f, "culled {name}")?;
+        }
+
+        for (r, name) in self.resource_names.iter().enumerate() {
+            let Some((first, last)) = self.lifetimes[r] else {
+                continue;
+            };
+
+            if self.imported[r] {
+                writeln!(f, "resource {name} imported {first}..{last}")?;
+            } else {
+                writeln!(
+                    f,
+                    "resource {name} {first}..{last} physical {}",
+                    self.physical[r]
+                )?;
+            }
+        }
+
+        for (i, _, state) in self.physical_resources() {
+            writeln!(f, "physical {i} initial {state:?}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Records graph barriers on a backend command list, looking up the physical
+/// resource behind each graph resource.
+pub fn record_barriers<'r, A: Api>(
+    list: &mut A::CommandList,
+    barriers: &[Barrier],
+    resource: impl Fn(ResourceId) -> &'r A::Resource,
+) -> Result<(), BackendError> {
+    for barrier in barriers {
+        match *barrier {
+            Barrier::Transition {
+                resource: id,
+                before,
+                after,
+            } => list.barrier(resource(id), before, after)?,
+            Barrier::UnorderedAccess { resource: id } => list.uav_barrier(resource(id))?,
+        }
+    }
+    Ok(())
+}
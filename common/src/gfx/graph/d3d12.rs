@@ -0,0 +1,35 @@
+use windows::Win32::Graphics::Direct3D12::{
+    ID3D12Resource, D3D12_RESOURCE_BARRIER, D3D12_RESOURCE_STATES,
+};
+
+use super::{Barrier, ResourceId, ResourceState};
+use crate::gfx::{transition_barrier, uav_barrier};
+
+pub trait ResourceStateExt {
+    fn to_d3d12(self) -> D3D12_RESOURCE_STATES;
+}
+
+impl ResourceStateExt for ResourceState {
+    fn to_d3d12(self) -> D3D12_RESOURCE_STATES {
+        D3D12_RESOURCE_STATES(self.bits() as i32)
+    }
+}
+
+/// Converts graph barriers into D3D12 ones, looking up the physical resource
+/// behind each graph resource.
+pub fn d3d12_barriers<'r>(
+    barriers: &[Barrier],
+    resource: impl Fn(ResourceId) -> &'r ID3D12Resource,
+) -> Vec<D3D12_RESOURCE_BARRIER> {
+    barriers
+        .iter()
+        .map(|barrier| match *barrier {
+            Barrier::Transition {
+                resource: id,
+                before,
+                after,
+            } => transition_barrier(resource(id), before.to_d3d12(), after.to_d3d12()),
+            Barrier::UnorderedAccess { resource: id } => uav_barrier(resource(id)),
+        })
+        .collect()
+}
@@ -0,0 +1,374 @@
+use windows::Win32::Graphics::{
+    Direct3D12::{
+        ID3D12Device, ID3D12Heap, ID3D12Resource, D3D12_CLEAR_VALUE,
+        D3D12_DEFAULT_MSAA_RESOURCE_PLACEMENT_ALIGNMENT,
+        D3D12_DEFAULT_RESOURCE_PLACEMENT_ALIGNMENT, D3D12_FEATURE_D3D12_OPTIONS,
+        D3D12_FEATURE_DATA_D3D12_OPTIONS, D3D12_HEAP_DESC, D3D12_HEAP_FLAGS,
+        D3D12_HEAP_FLAG_ALLOW_ALL_BUFFERS_AND_TEXTURES, D3D12_HEAP_FLAG_ALLOW_ONLY_BUFFERS,
+        D3D12_HEAP_FLAG_ALLOW_ONLY_NON_RT_DS_TEXTURES, D3D12_HEAP_FLAG_ALLOW_ONLY_RT_DS_TEXTURES,
+        D3D12_HEAP_TYPE, D3D12_RESOURCE_DESC, D3D12_RESOURCE_DIMENSION_BUFFER,
+        D3D12_RESOURCE_FLAG_ALLOW_DEPTH_STENCIL, D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET,
+        D3D12_RESOURCE_HEAP_TIER_1, D3D12_RESOURCE_STATES,
+    },
+    Dxgi::{IDXGIAdapter3, DXGI_MEMORY_SEGMENT_GROUP_LOCAL, DXGI_QUERY_VIDEO_MEMORY_INFO},
+};
+
+use super::{
+    deferred_queue::DeferredQueue,
+    heap_properties,
+    tlsf::{TlsfAllocation, TlsfAllocator, TlsfStats},
+};
+
+pub const DEFAULT_HEAP_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Resource heap tier 1 hardware can't mix buffers, render target/depth
+/// stencil textures and other textures in one heap, so they get separate pools.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HeapCategory {
+    All,
+    Buffers,
+    RtDsTextures,
+    OtherTextures,
+}
+
+impl HeapCategory {
+    pub fn for_resource(desc: &D3D12_RESOURCE_DESC, tier_1: bool) -> Self {
+        if !tier_1 {
+            HeapCategory::All
+        } else if desc.Dimension == D3D12_RESOURCE_DIMENSION_BUFFER {
+            HeapCategory::Buffers
+        } else if (desc.Flags
+            & (D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET | D3D12_RESOURCE_FLAG_ALLOW_DEPTH_STENCIL))
+            .0
+            != 0
+        {
+            HeapCategory::RtDsTextures
+        } else {
+            HeapCategory::OtherTextures
+        }
+    }
+
+    pub fn heap_flags(self) -> D3D12_HEAP_FLAGS {
+        match self {
+            HeapCategory::All => D3D12_HEAP_FLAG_ALLOW_ALL_BUFFERS_AND_TEXTURES,
+            HeapCategory::Buffers => D3D12_HEAP_FLAG_ALLOW_ONLY_BUFFERS,
+            HeapCategory::RtDsTextures => D3D12_HEAP_FLAG_ALLOW_ONLY_RT_DS_TEXTURES,
+            HeapCategory::OtherTextures => D3D12_HEAP_FLAG_ALLOW_ONLY_NON_RT_DS_TEXTURES,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GpuAllocation {
+    pool: usize,
+    heap: usize,
+    inner: TlsfAllocation,
+}
+
+impl GpuAllocation {
+    pub fn offset(&self) -> u64 {
+        self.inner.offset
+    }
+
+    pub fn size(&self) -> u64 {
+        self.inner.size
+    }
+}
+
+pub struct PlacedResource {
+    pub resource: ID3D12Resource,
+    pub allocation: GpuAllocation,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PoolStats {
+    pub heap_type: D3D12_HEAP_TYPE,
+    pub category: HeapCategory,
+    pub heap_count: usize,
+    pub reserved: u64,
+    pub used: u64,
+    pub allocation_count: usize,
+    pub largest_free_block: u64,
+    pub fragmentation: f32,
+}
+
+struct Heap {
+    heap: ID3D12Heap,
+    allocator: TlsfAllocator,
+}
+
+struct Pool {
+    heap_type: D3D12_HEAP_TYPE,
+    category: HeapCategory,
+    heaps: Vec<Option<Heap>>,
+}
+
+/// Places resources into large `ID3D12Heap`s, grouped into pools by heap type
+/// and category, instead of creating a committed resource for each one.
+pub struct HeapAllocator {
+    device: ID3D12Device,
+    tier_1: bool,
+    heap_block_size: u64,
+    pools: Vec<Pool>,
+    /// Allocations waiting for the GPU, with the resource placed in each.
+    retired: DeferredQueue<(Option<ID3D12Resource>, GpuAllocation)>,
+}
+
+impl HeapAllocator {
+    pub fn new(device: &ID3D12Device) -> windows::core::Result<Self> {
+        Self::with_heap_block_size(device, DEFAULT_HEAP_BLOCK_SIZE)
+    }
+
+    pub fn with_heap_block_size(
+        device: &ID3D12Device,
+        heap_block_size: u64,
+    ) -> windows::core::Result<Self> {
+        let mut options = D3D12_FEATURE_DATA_D3D12_OPTIONS::default();
+        unsafe {
+            device.CheckFeatureSupport(
+                D3D12_FEATURE_D3D12_OPTIONS,
+                &mut options as *mut _ as _,
+                std::mem::size_of_val(&options) as u32,
+            )
+        }?;
+
+        Ok(Self {
+            device: device.clone(),
+            tier_1: options.ResourceHeapTier == D3D12_RESOURCE_HEAP_TIER_1,
+            heap_block_size,
+            pools: Vec::new(),
+            retired: DeferredQueue::new(),
+        })
+    }
+
+    pub fn create_resource(
+        &mut self,
+        heap_type: D3D12_HEAP_TYPE,
+        desc: &D3D12_RESOURCE_DESC,
+        initial_state: D3D12_RESOURCE_STATES,
+        clear_value: Option<&D3D12_CLEAR_VALUE>,
+    ) -> windows::core::Result<PlacedResource> {
+        let info = unsafe { self.device.GetResourceAllocationInfo(0, &[*desc]) };
+        if info.SizeInBytes == u64::MAX {
+            return Err(windows::core::Error::new(
+                windows::Win32::Foundation::E_INVALIDARG,
+                "invalid resource description",
+            ));
+        }
+
+        let category = HeapCategory::for_resource(desc, self.tier_1);
+        let allocation = self.allocate(heap_type, category, info.SizeInBytes, info.Alignment)?;
+
+        let heap = self.heap(&allocation);
+        let mut resource: Option<ID3D12Resource> = None;
+        let result = unsafe {
+            self.device.CreatePlacedResource(
+                heap,
+                allocation.offset(),
+                desc,
+                initial_state,
+                clear_value.map(|c| c as *const _),
+                &mut resource,
+            )
+        }
+        .and_then(|_| resource.ok_or_else(windows::core::Error::empty));
+
+        match result {
+            Ok(resource) => Ok(PlacedResource {
+                resource,
+                allocation,
+            }),
+            Err(e) => {
+                self.free(allocation);
+                Err(e)
+            }
+        }
+    }
+
+    /// The resource placed in the allocation must have been released, and the
+    /// GPU finished with it, before its memory is returned.
+    pub fn free(&mut self, allocation: GpuAllocation) {
+        let pool = &mut self.pools[allocation.pool];
+        let heap = pool.heaps[allocation.heap]
+            .as_mut()
+            .expect("allocation belongs to a heap that was already released");
+        heap.allocator.free(allocation.inner);
+        let empty = heap.allocator.is_empty();
+
+        // Keep one heap per pool around so the next allocation doesn't have to
+        // create it again.
+        let live_heaps = pool.heaps.iter().flatten().count();
+        if empty && live_heaps > 1 {
+            pool.heaps[allocation.heap] = None;
+        }
+    }
+
+    /// Frees the allocation once `collect` sees the GPU has passed
+    /// `fence_value`.
+    pub fn free_after(&mut self, allocation: GpuAllocation, fence_value: u64) {
+        self.retired.retire(fence_value, (None, allocation));
+    }
+
+    /// Releases the resource, then its memory, once `collect` sees the GPU has
+    /// passed `fence_value`.
+    pub fn release_after(&mut self, placed: PlacedResource, fence_value: u64) {
+        self.retired
+            .retire(fence_value, (Some(placed.resource), placed.allocation));
+    }
+
+    pub fn collect(&mut self, completed_fence_value: u64) {
+        let retired: Vec<_> = self
+            .retired
+            .drain_completed(completed_fence_value)
+            .collect();
+        for (resource, allocation) in retired {
+            // The resource has to go before the memory it was placed in.
+            drop(resource);
+            self.free(allocation);
+        }
+    }
+
+    pub fn stats(&self) -> Vec<PoolStats> {
+        self.pools
+            .iter()
+            .map(|pool| {
+                let heap_stats: Vec<TlsfStats> = pool
+                    .heaps
+                    .iter()
+                    .flatten()
+                    .map(|h| h.allocator.stats())
+                    .collect();
+
+                let reserved: u64 = heap_stats.iter().map(|s| s.capacity).sum();
+                let used: u64 = heap_stats.iter().map(|s| s.used).sum();
+                let largest_free_block = heap_stats
+                    .iter()
+                    .map(|s| s.largest_free_block)
+                    .max()
+                    .unwrap_or_default();
+                let free = reserved - used;
+
+                PoolStats {
+                    heap_type: pool.heap_type,
+                    category: pool.category,
+                    heap_count: heap_stats.len(),
+                    reserved,
+                    used,
+                    allocation_count: heap_stats.iter().map(|s| s.allocation_count).sum(),
+                    largest_free_block,
+                    fragmentation: if free == 0 {
+                        0.0
+                    } else {
+                        1.0 - largest_free_block as f32 / free as f32
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn heap(&self, allocation: &GpuAllocation) -> &ID3D12Heap {
+        &self.pools[allocation.pool].heaps[allocation.heap]
+            .as_ref()
+            .expect("allocation belongs to a heap that was already released")
+            .heap
+    }
+
+    fn allocate(
+        &mut self,
+        heap_type: D3D12_HEAP_TYPE,
+        category: HeapCategory,
+        size: u64,
+        alignment: u64,
+    ) -> windows::core::Result<GpuAllocation> {
+        let pool_index = match self
+            .pools
+            .iter()
+            .position(|p| p.heap_type == heap_type && p.category == category)
+        {
+            Some(index) => index,
+            None => {
+                self.pools.push(Pool {
+                    heap_type,
+                    category,
+                    heaps: Vec::new(),
+                });
+                self.pools.len() - 1
+            }
+        };
+
+        let pool = &mut self.pools[pool_index];
+        for (heap_index, heap) in pool.heaps.iter_mut().enumerate() {
+            if let Some(inner) = heap
+                .as_mut()
+                .and_then(|h| h.allocator.allocate(size, alignment))
+            {
+                return Ok(GpuAllocation {
+                    pool: pool_index,
+                    heap: heap_index,
+                    inner,
+                });
+            }
+        }
+
+        // Resources larger than a heap block get a heap of their own.
+        let heap_size = size
+            .next_multiple_of(D3D12_DEFAULT_RESOURCE_PLACEMENT_ALIGNMENT as u64)
+            .max(self.heap_block_size);
+        let heap_alignment = match category {
+            HeapCategory::All | HeapCategory::RtDsTextures => {
+                D3D12_DEFAULT_MSAA_RESOURCE_PLACEMENT_ALIGNMENT as u64
+            }
+            HeapCategory::Buffers | HeapCategory::OtherTextures => {
+                D3D12_DEFAULT_RESOURCE_PLACEMENT_ALIGNMENT as u64
+            }
+        };
+
+        let mut heap: Option<ID3D12Heap> = None;
+        unsafe {
+            self.device.CreateHeap(
+                &D3D12_HEAP_DESC {
+                    SizeInBytes: heap_size,
+                    Properties: heap_properties(heap_type),
+                    Alignment: heap_alignment,
+                    Flags: category.heap_flags(),
+                },
+                &mut heap,
+            )
+        }?;
+        let heap = heap.ok_or_else(windows::core::Error::empty)?;
+
+        let mut allocator =
+            TlsfAllocator::new(heap_size, D3D12_DEFAULT_RESOURCE_PLACEMENT_ALIGNMENT as u64);
+        let inner = allocator
+            .allocate(size, alignment)
+            .expect("a new heap has room for the allocation it was sized for");
+
+        let heap = Some(Heap { heap, allocator });
+        let heap_index = match pool.heaps.iter().position(Option::is_none) {
+            Some(index) => {
+                pool.heaps[index] = heap;
+                index
+            }
+            None => {
+                pool.heaps.push(heap);
+                pool.heaps.len() - 1
+            }
+        };
+
+        Ok(GpuAllocation {
+            pool: pool_index,
+            heap: heap_index,
+            inner,
+        })
+    }
+}
+
+/// Asks DXGI how much local video memory the process may use and how much it
+/// is currently using.
+pub fn video_memory_budget(
+    adapter: &IDXGIAdapter3,
+) -> windows::core::Result<DXGI_QUERY_VIDEO_MEMORY_INFO> {
+    let mut info = DXGI_QUERY_VIDEO_MEMORY_INFO::default();
+    unsafe { adapter.QueryVideoMemoryInfo(0, DXGI_MEMORY_SEGMENT_GROUP_LOCAL, &mut info) }?;
+    Ok(info)
+}
@@ -0,0 +1,46 @@
+use windows::Win32::Graphics::Direct3D12::ID3D12GraphicsCommandList;
+
+use super::{
+    backend::{BackendError, CommandList, D3d12, D3d12CommandList},
+    command_list_pool::CommandListPool,
+    command_queue::{CommandQueue, SyncPoint},
+};
+use crate::jobs::JobSystem;
+
+/// Records `count` command lists in parallel on the job system, then submits
+/// them in index order with a single submission.
+///
+/// `record` is called once per list with its index and an open list; the
+/// list is closed afterwards.
+pub fn record_parallel<F>(
+    jobs: &JobSystem,
+    pool: &mut CommandListPool,
+    queue: &mut CommandQueue<D3d12>,
+    count: usize,
+    record: F,
+) -> Result<SyncPoint<D3d12>, BackendError>
+where
+    F: Fn(usize, &ID3D12GraphicsCommandList) -> windows::core::Result<()> + Sync,
+{
+    // Lists are acquired up front on this thread since the pool isn't shared.
+    let mut recordings = (0..count)
+        .map(|_| pool.acquire(queue).map(|list| (list, Ok(()))))
+        .collect::<windows::core::Result<Vec<(D3d12CommandList, Result<(), BackendError>)>>>()?;
+
+    jobs.parallel_for(&mut recordings, |index, (list, result)| {
+        *result = record(index, list.list())
+            .map_err(BackendError::from)
+            .and_then(|_| list.close());
+    });
+
+    let mut lists = Vec::with_capacity(count);
+    for (list, result) in recordings {
+        result?;
+        lists.push(list);
+    }
+
+    let sync_point = queue.execute(&lists.iter().collect::<Vec<_>>())?;
+    pool.retire(lists, &sync_point);
+
+    Ok(sync_point)
+}
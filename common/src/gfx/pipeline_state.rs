@@ -0,0 +1,561 @@
+// Pipeline state descriptions with the same defaults as the d3dx12 helpers,
+// built without a device so they can be hashed into stable cache keys. See
+// `d3d12` for creating the pipeline objects and the on-disk cache.
+
+#[cfg(windows)]
+mod d3d12;
+
+#[cfg(windows)]
+pub use d3d12::{create_pipeline_state, with_input_element_descs, PipelineCache};
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use super::{
+    format::Format,
+    root_signature::{ComparisonFunc, RootSignatureDesc},
+};
+use crate::hash::stable_hash;
+
+pub const MAX_RENDER_TARGETS: usize = 8;
+
+/// Per-vertex data stepping for one vertex buffer slot element.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InputElement {
+    pub semantic: String,
+    pub semantic_index: u32,
+    pub format: Format,
+    pub slot: u32,
+    /// Byte offset in the vertex, or `None` to follow the previous element.
+    pub offset: Option<u32>,
+    /// Instances drawn per step, or `None` for per-vertex data.
+    pub instance_step_rate: Option<u32>,
+}
+
+impl InputElement {
+    pub fn new(semantic: impl Into<String>, format: Format) -> Self {
+        Self {
+            semantic: semantic.into(),
+            semantic_index: 0,
+            format,
+            slot: 0,
+            offset: None,
+            instance_step_rate: None,
+        }
+    }
+
+    pub fn index(mut self, semantic_index: u32) -> Self {
+        self.semantic_index = semantic_index;
+        self
+    }
+
+    pub fn slot(mut self, slot: u32) -> Self {
+        self.slot = slot;
+        self
+    }
+
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn per_instance(mut self, step_rate: u32) -> Self {
+        self.instance_step_rate = Some(step_rate);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PrimitiveTopology {
+    Point,
+    Line,
+    #[default]
+    Triangle,
+    Patch,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Blend {
+    Zero,
+    One,
+    SrcColor,
+    InvSrcColor,
+    SrcAlpha,
+    InvSrcAlpha,
+    DestAlpha,
+    InvDestAlpha,
+    DestColor,
+    InvDestColor,
+    SrcAlphaSat,
+    BlendFactor,
+    InvBlendFactor,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlendOp {
+    Add,
+    Subtract,
+    RevSubtract,
+    Min,
+    Max,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderTargetBlend {
+    pub enabled: bool,
+    pub src: Blend,
+    pub dest: Blend,
+    pub op: BlendOp,
+    pub src_alpha: Blend,
+    pub dest_alpha: Blend,
+    pub op_alpha: BlendOp,
+    /// Bits for red, green, blue and alpha, from the lowest.
+    pub write_mask: u8,
+}
+
+impl RenderTargetBlend {
+    pub const WRITE_ALL: u8 = 0xf;
+
+    pub const DISABLED: Self = Self {
+        enabled: false,
+        src: Blend::One,
+        dest: Blend::Zero,
+        op: BlendOp::Add,
+        src_alpha: Blend::One,
+        dest_alpha: Blend::Zero,
+        op_alpha: BlendOp::Add,
+        write_mask: Self::WRITE_ALL,
+    };
+
+    pub const ALPHA: Self = Self {
+        enabled: true,
+        src: Blend::SrcAlpha,
+        dest: Blend::InvSrcAlpha,
+        op: BlendOp::Add,
+        src_alpha: Blend::One,
+        dest_alpha: Blend::InvSrcAlpha,
+        op_alpha: BlendOp::Add,
+        write_mask: Self::WRITE_ALL,
+    };
+
+    pub const PREMULTIPLIED_ALPHA: Self = Self {
+        src: Blend::One,
+        ..Self::ALPHA
+    };
+
+    pub const ADDITIVE: Self = Self {
+        enabled: true,
+        src: Blend::One,
+        dest: Blend::One,
+        op: BlendOp::Add,
+        src_alpha: Blend::One,
+        dest_alpha: Blend::One,
+        op_alpha: BlendOp::Add,
+        write_mask: Self::WRITE_ALL,
+    };
+}
+
+impl Default for RenderTargetBlend {
+    fn default() -> Self {
+        Self::DISABLED
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BlendState {
+    pub alpha_to_coverage: bool,
+    /// Without it every target uses the first target's blend.
+    pub independent_blend: bool,
+    pub render_targets: [RenderTargetBlend; MAX_RENDER_TARGETS],
+}
+
+impl BlendState {
+    /// The same blend on every target.
+    pub fn all(blend: RenderTargetBlend) -> Self {
+        Self {
+            alpha_to_coverage: false,
+            independent_blend: false,
+            render_targets: [blend; MAX_RENDER_TARGETS],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FillMode {
+    Solid,
+    Wireframe,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RasterizerState {
+    pub fill: FillMode,
+    pub cull: CullMode,
+    pub front_counter_clockwise: bool,
+    pub depth_bias: i32,
+    pub depth_bias_clamp: f32,
+    pub slope_scaled_depth_bias: f32,
+    pub depth_clip: bool,
+    pub multisample: bool,
+    pub antialiased_lines: bool,
+    pub conservative: bool,
+}
+
+impl RasterizerState {
+    pub const DEFAULT: Self = Self {
+        fill: FillMode::Solid,
+        cull: CullMode::Back,
+        front_counter_clockwise: false,
+        depth_bias: 0,
+        depth_bias_clamp: 0.0,
+        slope_scaled_depth_bias: 0.0,
+        depth_clip: true,
+        multisample: false,
+        antialiased_lines: false,
+        conservative: false,
+    };
+
+    pub const NO_CULL: Self = Self {
+        cull: CullMode::None,
+        ..Self::DEFAULT
+    };
+
+    pub const WIREFRAME: Self = Self {
+        fill: FillMode::Wireframe,
+        cull: CullMode::None,
+        ..Self::DEFAULT
+    };
+}
+
+impl Default for RasterizerState {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Eq for RasterizerState {}
+
+impl Hash for RasterizerState {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fill.hash(state);
+        self.cull.hash(state);
+        self.front_counter_clockwise.hash(state);
+        self.depth_bias.hash(state);
+        self.depth_bias_clamp.to_bits().hash(state);
+        self.slope_scaled_depth_bias.to_bits().hash(state);
+        self.depth_clip.hash(state);
+        self.multisample.hash(state);
+        self.antialiased_lines.hash(state);
+        self.conservative.hash(state);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    IncrSat,
+    DecrSat,
+    Invert,
+    Incr,
+    Decr,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StencilFace {
+    pub fail: StencilOp,
+    pub depth_fail: StencilOp,
+    pub pass: StencilOp,
+    pub func: ComparisonFunc,
+}
+
+impl StencilFace {
+    pub const KEEP: Self = Self {
+        fail: StencilOp::Keep,
+        depth_fail: StencilOp::Keep,
+        pass: StencilOp::Keep,
+        func: ComparisonFunc::Always,
+    };
+}
+
+impl Default for StencilFace {
+    fn default() -> Self {
+        Self::KEEP
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DepthStencilState {
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub depth_func: ComparisonFunc,
+    pub stencil_test: bool,
+    pub stencil_read_mask: u8,
+    pub stencil_write_mask: u8,
+    pub front: StencilFace,
+    pub back: StencilFace,
+}
+
+impl DepthStencilState {
+    pub const DEFAULT: Self = Self {
+        depth_test: true,
+        depth_write: true,
+        depth_func: ComparisonFunc::Less,
+        stencil_test: false,
+        stencil_read_mask: 0xff,
+        stencil_write_mask: 0xff,
+        front: StencilFace::KEEP,
+        back: StencilFace::KEEP,
+    };
+
+    pub const DISABLED: Self = Self {
+        depth_test: false,
+        depth_write: false,
+        ..Self::DEFAULT
+    };
+
+    /// For a depth buffer cleared to 0 with near mapped to 1.
+    pub const REVERSED_Z: Self = Self {
+        depth_func: ComparisonFunc::GreaterEqual,
+        ..Self::DEFAULT
+    };
+
+    /// Tests against depth without writing it, e.g. after a depth prepass.
+    pub const READ_ONLY: Self = Self {
+        depth_write: false,
+        depth_func: ComparisonFunc::LessEqual,
+        ..Self::DEFAULT
+    };
+
+    fn uses_depth_buffer(&self) -> bool {
+        self.depth_test || self.depth_write || self.stencil_test
+    }
+}
+
+impl Default for DepthStencilState {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GraphicsPipelineDesc {
+    pub input_layout: Vec<InputElement>,
+    pub topology: PrimitiveTopology,
+    pub vs: Vec<u8>,
+    pub ps: Vec<u8>,
+    pub ds: Vec<u8>,
+    pub hs: Vec<u8>,
+    pub gs: Vec<u8>,
+    pub blend: BlendState,
+    pub sample_mask: u32,
+    pub rasterizer: RasterizerState,
+    pub depth_stencil: DepthStencilState,
+    pub render_target_formats: Vec<Format>,
+    pub depth_format: Format,
+    pub sample_count: u32,
+    pub sample_quality: u32,
+}
+
+impl Default for GraphicsPipelineDesc {
+    /// One RGBA8 target, no depth buffer, opaque, back face culled.
+    fn default() -> Self {
+        Self {
+            input_layout: Vec::new(),
+            topology: PrimitiveTopology::Triangle,
+            vs: Vec::new(),
+            ps: Vec::new(),
+            ds: Vec::new(),
+            hs: Vec::new(),
+            gs: Vec::new(),
+            blend: BlendState::default(),
+            sample_mask: u32::MAX,
+            rasterizer: RasterizerState::DEFAULT,
+            depth_stencil: DepthStencilState::DISABLED,
+            render_target_formats: vec![Format::R8G8B8A8_UNORM],
+            depth_format: Format::UNKNOWN,
+            sample_count: 1,
+            sample_quality: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ComputePipelineDesc {
+    pub cs: Vec<u8>,
+}
+
+// Descriptions are built once per pipeline, so the size difference doesn't
+// matter.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PipelineDesc {
+    Graphics(GraphicsPipelineDesc),
+    Compute(ComputePipelineDesc),
+}
+
+/// Identifies a pipeline across runs: the same root signature and description
+/// always give the same key.
+pub fn pipeline_key(root_signature: &RootSignatureDesc, desc: &PipelineDesc) -> u64 {
+    stable_hash(&(root_signature.to_hlsl(), desc))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PipelineError {
+    MissingVertexShader,
+    ComputeWithGraphicsShaders,
+    TooManyRenderTargets { count: usize },
+    MissingDepthFormat,
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::MissingVertexShader => {
+                write!(f, "graphics pipeline has no vertex shader")
+            }
+            PipelineError::ComputeWithGraphicsShaders => {
+                write!(f, "pipeline has both a compute shader and graphics shaders")
+            }
+            PipelineError::TooManyRenderTargets { count } => write!(
+                f,
+                "pipeline has {count} render targets, the limit is {MAX_RENDER_TARGETS}"
+            ),
+            PipelineError::MissingDepthFormat => {
+                write!(f, "depth or stencil is enabled without a depth format")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {}
+
+/// Builds a graphics pipeline, or a compute pipeline if given a compute
+/// shader. Anything not set keeps the `GraphicsPipelineDesc` default.
+#[derive(Clone, Debug, Default)]
+pub struct PipelineStateBuilder {
+    graphics: GraphicsPipelineDesc,
+    cs: Vec<u8>,
+}
+
+impl PipelineStateBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn input_layout(mut self, elements: impl IntoIterator<Item = InputElement>) -> Self {
+        self.graphics.input_layout = elements.into_iter().collect();
+        self
+    }
+
+    pub fn topology(mut self, topology: PrimitiveTopology) -> Self {
+        self.graphics.topology = topology;
+        self
+    }
+
+    pub fn vertex_shader(mut self, bytecode: impl Into<Vec<u8>>) -> Self {
+        self.graphics.vs = bytecode.into();
+        self
+    }
+
+    pub fn pixel_shader(mut self, bytecode: impl Into<Vec<u8>>) -> Self {
+        self.graphics.ps = bytecode.into();
+        self
+    }
+
+    pub fn domain_shader(mut self, bytecode: impl Into<Vec<u8>>) -> Self {
+        self.graphics.ds = bytecode.into();
+        self
+    }
+
+    pub fn hull_shader(mut self, bytecode: impl Into<Vec<u8>>) -> Self {
+        self.graphics.hs = bytecode.into();
+        self
+    }
+
+    pub fn geometry_shader(mut self, bytecode: impl Into<Vec<u8>>) -> Self {
+        self.graphics.gs = bytecode.into();
+        self
+    }
+
+    pub fn compute_shader(mut self, bytecode: impl Into<Vec<u8>>) -> Self {
+        self.cs = bytecode.into();
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendState) -> Self {
+        self.graphics.blend = blend;
+        self
+    }
+
+    pub fn sample_mask(mut self, sample_mask: u32) -> Self {
+        self.graphics.sample_mask = sample_mask;
+        self
+    }
+
+    pub fn rasterizer(mut self, rasterizer: RasterizerState) -> Self {
+        self.graphics.rasterizer = rasterizer;
+        self
+    }
+
+    /// The depth buffer format and how it is used; depth is off by default.
+    pub fn depth_stencil(mut self, format: Format, state: DepthStencilState) -> Self {
+        self.graphics.depth_format = format;
+        self.graphics.depth_stencil = state;
+        self
+    }
+
+    pub fn render_target_formats(mut self, formats: &[Format]) -> Self {
+        self.graphics.render_target_formats = formats.to_vec();
+        self
+    }
+
+    pub fn sample_desc(mut self, count: u32, quality: u32) -> Self {
+        self.graphics.sample_count = count;
+        self.graphics.sample_quality = quality;
+        self
+    }
+
+    pub fn build(self) -> Result<PipelineDesc, PipelineError> {
+        let graphics = self.graphics;
+        let has_graphics_shaders = [
+            &graphics.vs,
+            &graphics.ps,
+            &graphics.ds,
+            &graphics.hs,
+            &graphics.gs,
+        ]
+        .iter()
+        .any(|bytecode| !bytecode.is_empty());
+
+        if !self.cs.is_empty() {
+            if has_graphics_shaders {
+                return Err(PipelineError::ComputeWithGraphicsShaders);
+            }
+            return Ok(PipelineDesc::Compute(ComputePipelineDesc { cs: self.cs }));
+        }
+
+        if graphics.vs.is_empty() {
+            return Err(PipelineError::MissingVertexShader);
+        }
+        if graphics.render_target_formats.len() > MAX_RENDER_TARGETS {
+            return Err(PipelineError::TooManyRenderTargets {
+                count: graphics.render_target_formats.len(),
+            });
+        }
+        if graphics.depth_stencil.uses_depth_buffer() && graphics.depth_format == Format::UNKNOWN {
+            return Err(PipelineError::MissingDepthFormat);
+        }
+
+        Ok(PipelineDesc::Graphics(graphics))
+    }
+}
@@ -0,0 +1,448 @@
+use std::{collections::HashMap, error::Error, ffi::CString, path::PathBuf};
+
+use windows::{
+    core::{Interface, HSTRING, PCSTR},
+    Win32::Graphics::{
+        Direct3D12::{
+            ID3D12Device, ID3D12Device1, ID3D12PipelineLibrary, ID3D12PipelineState,
+            ID3D12RootSignature, D3D12_APPEND_ALIGNED_ELEMENT, D3D12_BLEND,
+            D3D12_BLEND_BLEND_FACTOR, D3D12_BLEND_DESC, D3D12_BLEND_DEST_ALPHA,
+            D3D12_BLEND_DEST_COLOR, D3D12_BLEND_INV_BLEND_FACTOR, D3D12_BLEND_INV_DEST_ALPHA,
+            D3D12_BLEND_INV_DEST_COLOR, D3D12_BLEND_INV_SRC_ALPHA, D3D12_BLEND_INV_SRC_COLOR,
+            D3D12_BLEND_ONE, D3D12_BLEND_OP, D3D12_BLEND_OP_ADD, D3D12_BLEND_OP_MAX,
+            D3D12_BLEND_OP_MIN, D3D12_BLEND_OP_REV_SUBTRACT, D3D12_BLEND_OP_SUBTRACT,
+            D3D12_BLEND_SRC_ALPHA, D3D12_BLEND_SRC_ALPHA_SAT, D3D12_BLEND_SRC_COLOR,
+            D3D12_BLEND_ZERO, D3D12_COMPUTE_PIPELINE_STATE_DESC,
+            D3D12_CONSERVATIVE_RASTERIZATION_MODE_OFF, D3D12_CONSERVATIVE_RASTERIZATION_MODE_ON,
+            D3D12_CULL_MODE, D3D12_CULL_MODE_BACK, D3D12_CULL_MODE_FRONT, D3D12_CULL_MODE_NONE,
+            D3D12_DEPTH_STENCILOP_DESC, D3D12_DEPTH_STENCIL_DESC, D3D12_DEPTH_WRITE_MASK_ALL,
+            D3D12_DEPTH_WRITE_MASK_ZERO, D3D12_FILL_MODE_SOLID, D3D12_FILL_MODE_WIREFRAME,
+            D3D12_GRAPHICS_PIPELINE_STATE_DESC, D3D12_INPUT_CLASSIFICATION_PER_INSTANCE_DATA,
+            D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA, D3D12_INPUT_ELEMENT_DESC,
+            D3D12_INPUT_LAYOUT_DESC, D3D12_LOGIC_OP_NOOP, D3D12_PRIMITIVE_TOPOLOGY_TYPE,
+            D3D12_PRIMITIVE_TOPOLOGY_TYPE_LINE, D3D12_PRIMITIVE_TOPOLOGY_TYPE_PATCH,
+            D3D12_PRIMITIVE_TOPOLOGY_TYPE_POINT, D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
+            D3D12_RASTERIZER_DESC, D3D12_RENDER_TARGET_BLEND_DESC, D3D12_SHADER_BYTECODE,
+            D3D12_STENCIL_OP, D3D12_STENCIL_OP_DECR, D3D12_STENCIL_OP_DECR_SAT,
+            D3D12_STENCIL_OP_INCR, D3D12_STENCIL_OP_INCR_SAT, D3D12_STENCIL_OP_INVERT,
+            D3D12_STENCIL_OP_KEEP, D3D12_STENCIL_OP_REPLACE, D3D12_STENCIL_OP_ZERO,
+        },
+        Dxgi::Common::{DXGI_FORMAT, DXGI_SAMPLE_DESC},
+    },
+};
+
+use super::{
+    pipeline_key, Blend, BlendOp, BlendState, ComputePipelineDesc, CullMode, DepthStencilState,
+    FillMode, GraphicsPipelineDesc, InputElement, PipelineDesc, PrimitiveTopology, RasterizerState,
+    RenderTargetBlend, StencilFace, StencilOp,
+};
+use crate::gfx::{
+    backend::D3d12,
+    root_signature::{comparison_func, RootSignatureDesc},
+    DeferredDeleter,
+};
+
+const CACHE_MAGIC: &[u8; 4] = b"PSOL";
+const CACHE_VERSION: u32 = 1;
+
+/// Creates a pipeline without going through a cache.
+pub fn create_pipeline_state(
+    device: &ID3D12Device,
+    root_signature: &ID3D12RootSignature,
+    desc: &PipelineDesc,
+) -> windows::core::Result<ID3D12PipelineState> {
+    match desc {
+        PipelineDesc::Graphics(desc) => with_graphics_desc(root_signature, desc, |desc| unsafe {
+            device.CreateGraphicsPipelineState(desc)
+        }),
+        PipelineDesc::Compute(desc) => with_compute_desc(root_signature, desc, |desc| unsafe {
+            device.CreateComputePipelineState(desc)
+        }),
+    }
+}
+
+/// Pipelines keyed by `pipeline_key`, backed by an `ID3D12PipelineLibrary`
+/// that is loaded from and saved to a file so the driver doesn't have to
+/// compile them again on the next run.
+pub struct PipelineCache {
+    device: ID3D12Device,
+    path: PathBuf,
+    pipelines: HashMap<u64, ID3D12PipelineState>,
+    library: Option<ID3D12PipelineLibrary>,
+    // The library reads from the blob it was created from for as long as it
+    // lives, so this has to be dropped after it.
+    _blob: Vec<u8>,
+    dirty: bool,
+}
+
+impl PipelineCache {
+    /// A missing, corrupt or stale cache file (e.g. after a driver update)
+    /// just means starting with an empty library. If the device can't create
+    /// a library at all, pipelines are still cached for the run but nothing is
+    /// loaded from or saved to `path`.
+    pub fn new(device: &ID3D12Device, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let device1: Option<ID3D12Device1> = device.cast().ok();
+
+        let mut blob = std::fs::read(&path)
+            .ok()
+            .and_then(|contents| {
+                let header = contents.get(..8)?;
+                let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+                (&header[..4] == CACHE_MAGIC && version == CACHE_VERSION).then_some(contents)
+            })
+            .map(|contents| contents[8..].to_vec())
+            .unwrap_or_default();
+
+        let mut library = device1.as_ref().and_then(|device1| {
+            unsafe { device1.CreatePipelineLibrary::<ID3D12PipelineLibrary>(&blob) }.ok()
+        });
+
+        if library.is_none() && !blob.is_empty() {
+            crate::util::print_debug_string("Discarding stale pipeline cache");
+            blob.clear();
+            library = device1.as_ref().and_then(|device1| {
+                unsafe { device1.CreatePipelineLibrary::<ID3D12PipelineLibrary>(&blob) }.ok()
+            });
+        }
+
+        Self {
+            device: device.clone(),
+            path,
+            pipelines: HashMap::new(),
+            library,
+            _blob: blob,
+            dirty: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipelines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipelines.is_empty()
+    }
+
+    pub fn get_or_create(
+        &mut self,
+        root_signature: &ID3D12RootSignature,
+        root_signature_desc: &RootSignatureDesc,
+        desc: &PipelineDesc,
+    ) -> windows::core::Result<ID3D12PipelineState> {
+        let key = pipeline_key(root_signature_desc, desc);
+        if let Some(pipeline) = self.pipelines.get(&key) {
+            return Ok(pipeline.clone());
+        }
+
+        let name = HSTRING::from(format!("{key:016x}"));
+        let loaded = self.library.as_ref().and_then(|library| {
+            match desc {
+                PipelineDesc::Graphics(desc) => {
+                    with_graphics_desc(root_signature, desc, |desc| unsafe {
+                        library.LoadGraphicsPipeline::<_, ID3D12PipelineState>(&name, desc)
+                    })
+                }
+                PipelineDesc::Compute(desc) => {
+                    with_compute_desc(root_signature, desc, |desc| unsafe {
+                        library.LoadComputePipeline::<_, ID3D12PipelineState>(&name, desc)
+                    })
+                }
+            }
+            .ok()
+        });
+
+        let pipeline = match loaded {
+            Some(pipeline) => pipeline,
+            None => {
+                let pipeline = create_pipeline_state(&self.device, root_signature, desc)?;
+                if let Some(library) = &self.library {
+                    match unsafe { library.StorePipeline(&name, &pipeline) } {
+                        Ok(()) => self.dirty = true,
+                        Err(e) => crate::util::print_debug_string(&format!(
+                            "Failed to store pipeline {key:016x}: {e}"
+                        )),
+                    }
+                }
+                pipeline
+            }
+        };
+
+        self.pipelines.insert(key, pipeline.clone());
+        Ok(pipeline)
+    }
+
+    /// Takes the pipeline for `key` out of the cache, e.g. once a hot reload
+    /// has replaced it, and releases the cache's reference when the GPU
+    /// passes `fence_value`. Returns whether there was one. The library keeps
+    /// its copy, since pipelines can't be removed from one.
+    pub fn remove(
+        &mut self,
+        key: u64,
+        deleter: &mut DeferredDeleter<D3d12>,
+        fence_value: u64,
+    ) -> bool {
+        match self.pipelines.remove(&key) {
+            Some(pipeline) => {
+                deleter.retire(pipeline, fence_value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Writes the library out if any pipelines were added to it.
+    pub fn save(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(library) = &self.library else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let mut contents = Vec::new();
+        contents.extend_from_slice(CACHE_MAGIC);
+        contents.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        let header_len = contents.len();
+        contents.resize(header_len + unsafe { library.GetSerializedSize() }, 0);
+        unsafe { library.Serialize(&mut contents[header_len..]) }?;
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write to the side first so a crash can't leave a truncated cache.
+        crate::util::write_atomically(&self.path, &contents)?;
+
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+fn with_graphics_desc<R>(
+    root_signature: &ID3D12RootSignature,
+    desc: &GraphicsPipelineDesc,
+    f: impl FnOnce(&D3D12_GRAPHICS_PIPELINE_STATE_DESC) -> R,
+) -> R {
+    with_input_element_descs(&desc.input_layout, |elements| {
+        let mut rtv_formats = [DXGI_FORMAT::default(); 8];
+        for (slot, format) in rtv_formats.iter_mut().zip(&desc.render_target_formats) {
+            *slot = DXGI_FORMAT(format.0 as i32);
+        }
+
+        let d3d12_desc = D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+            pRootSignature: unsafe { std::mem::transmute_copy(root_signature) },
+            VS: bytecode(&desc.vs),
+            PS: bytecode(&desc.ps),
+            DS: bytecode(&desc.ds),
+            HS: bytecode(&desc.hs),
+            GS: bytecode(&desc.gs),
+            BlendState: blend_desc(&desc.blend),
+            SampleMask: desc.sample_mask,
+            RasterizerState: rasterizer_desc(&desc.rasterizer),
+            DepthStencilState: depth_stencil_desc(&desc.depth_stencil),
+            InputLayout: D3D12_INPUT_LAYOUT_DESC {
+                pInputElementDescs: elements.as_ptr(),
+                NumElements: elements.len() as u32,
+            },
+            PrimitiveTopologyType: topology_type(desc.topology),
+            NumRenderTargets: desc.render_target_formats.len() as u32,
+            RTVFormats: rtv_formats,
+            DSVFormat: DXGI_FORMAT(desc.depth_format.0 as i32),
+            SampleDesc: DXGI_SAMPLE_DESC {
+                Count: desc.sample_count,
+                Quality: desc.sample_quality,
+            },
+            ..Default::default()
+        };
+
+        f(&d3d12_desc)
+    })
+}
+
+/// Calls `f` with the `D3D12_INPUT_ELEMENT_DESC`s for `layout`; the semantic
+/// name strings only live for the duration of the call.
+pub fn with_input_element_descs<R>(
+    layout: &[InputElement],
+    f: impl FnOnce(&[D3D12_INPUT_ELEMENT_DESC]) -> R,
+) -> R {
+    let semantics: Vec<CString> = layout
+        .iter()
+        .map(|element| CString::new(element.semantic.as_str()).unwrap_or_default())
+        .collect();
+    let elements: Vec<D3D12_INPUT_ELEMENT_DESC> = layout
+        .iter()
+        .zip(&semantics)
+        .map(|(element, semantic)| D3D12_INPUT_ELEMENT_DESC {
+            SemanticName: PCSTR(semantic.as_ptr() as _),
+            SemanticIndex: element.semantic_index,
+            Format: DXGI_FORMAT(element.format.0 as i32),
+            InputSlot: element.slot,
+            AlignedByteOffset: element.offset.unwrap_or(D3D12_APPEND_ALIGNED_ELEMENT),
+            InputSlotClass: if element.instance_step_rate.is_some() {
+                D3D12_INPUT_CLASSIFICATION_PER_INSTANCE_DATA
+            } else {
+                D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA
+            },
+            InstanceDataStepRate: element.instance_step_rate.unwrap_or(0),
+        })
+        .collect();
+
+    f(&elements)
+}
+
+fn with_compute_desc<R>(
+    root_signature: &ID3D12RootSignature,
+    desc: &ComputePipelineDesc,
+    f: impl FnOnce(&D3D12_COMPUTE_PIPELINE_STATE_DESC) -> R,
+) -> R {
+    let d3d12_desc = D3D12_COMPUTE_PIPELINE_STATE_DESC {
+        pRootSignature: unsafe { std::mem::transmute_copy(root_signature) },
+        CS: bytecode(&desc.cs),
+        ..Default::default()
+    };
+
+    f(&d3d12_desc)
+}
+
+fn bytecode(bytecode: &[u8]) -> D3D12_SHADER_BYTECODE {
+    if bytecode.is_empty() {
+        D3D12_SHADER_BYTECODE::default()
+    } else {
+        D3D12_SHADER_BYTECODE {
+            pShaderBytecode: bytecode.as_ptr() as _,
+            BytecodeLength: bytecode.len(),
+        }
+    }
+}
+
+fn topology_type(topology: PrimitiveTopology) -> D3D12_PRIMITIVE_TOPOLOGY_TYPE {
+    match topology {
+        PrimitiveTopology::Point => D3D12_PRIMITIVE_TOPOLOGY_TYPE_POINT,
+        PrimitiveTopology::Line => D3D12_PRIMITIVE_TOPOLOGY_TYPE_LINE,
+        PrimitiveTopology::Triangle => D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
+        PrimitiveTopology::Patch => D3D12_PRIMITIVE_TOPOLOGY_TYPE_PATCH,
+    }
+}
+
+fn blend_desc(blend: &BlendState) -> D3D12_BLEND_DESC {
+    D3D12_BLEND_DESC {
+        AlphaToCoverageEnable: blend.alpha_to_coverage.into(),
+        IndependentBlendEnable: blend.independent_blend.into(),
+        RenderTarget: blend
+            .render_targets
+            .map(|target| render_target_blend_desc(&target)),
+    }
+}
+
+fn render_target_blend_desc(blend: &RenderTargetBlend) -> D3D12_RENDER_TARGET_BLEND_DESC {
+    D3D12_RENDER_TARGET_BLEND_DESC {
+        BlendEnable: blend.enabled.into(),
+        LogicOpEnable: false.into(),
+        SrcBlend: blend_factor(blend.src),
+        DestBlend: blend_factor(blend.dest),
+        BlendOp: blend_op(blend.op),
+        SrcBlendAlpha: blend_factor(blend.src_alpha),
+        DestBlendAlpha: blend_factor(blend.dest_alpha),
+        BlendOpAlpha: blend_op(blend.op_alpha),
+        LogicOp: D3D12_LOGIC_OP_NOOP,
+        RenderTargetWriteMask: blend.write_mask,
+    }
+}
+
+fn blend_factor(blend: Blend) -> D3D12_BLEND {
+    match blend {
+        Blend::Zero => D3D12_BLEND_ZERO,
+        Blend::One => D3D12_BLEND_ONE,
+        Blend::SrcColor => D3D12_BLEND_SRC_COLOR,
+        Blend::InvSrcColor => D3D12_BLEND_INV_SRC_COLOR,
+        Blend::SrcAlpha => D3D12_BLEND_SRC_ALPHA,
+        Blend::InvSrcAlpha => D3D12_BLEND_INV_SRC_ALPHA,
+        Blend::DestAlpha => D3D12_BLEND_DEST_ALPHA,
+        Blend::InvDestAlpha => D3D12_BLEND_INV_DEST_ALPHA,
+        Blend::DestColor => D3D12_BLEND_DEST_COLOR,
+        Blend::InvDestColor => D3D12_BLEND_INV_DEST_COLOR,
+        Blend::SrcAlphaSat => D3D12_BLEND_SRC_ALPHA_SAT,
+        Blend::BlendFactor => D3D12_BLEND_BLEND_FACTOR,
+        Blend::InvBlendFactor => D3D12_BLEND_INV_BLEND_FACTOR,
+    }
+}
+
+fn blend_op(op: BlendOp) -> D3D12_BLEND_OP {
+    match op {
+        BlendOp::Add => D3D12_BLEND_OP_ADD,
+        BlendOp::Subtract => D3D12_BLEND_OP_SUBTRACT,
+        BlendOp::RevSubtract => D3D12_BLEND_OP_REV_SUBTRACT,
+        BlendOp::Min => D3D12_BLEND_OP_MIN,
+        BlendOp::Max => D3D12_BLEND_OP_MAX,
+    }
+}
+
+fn rasterizer_desc(rasterizer: &RasterizerState) -> D3D12_RASTERIZER_DESC {
+    D3D12_RASTERIZER_DESC {
+        FillMode: match rasterizer.fill {
+            FillMode::Solid => D3D12_FILL_MODE_SOLID,
+            FillMode::Wireframe => D3D12_FILL_MODE_WIREFRAME,
+        },
+        CullMode: cull_mode(rasterizer.cull),
+        FrontCounterClockwise: rasterizer.front_counter_clockwise.into(),
+        DepthBias: rasterizer.depth_bias,
+        DepthBiasClamp: rasterizer.depth_bias_clamp,
+        SlopeScaledDepthBias: rasterizer.slope_scaled_depth_bias,
+        DepthClipEnable: rasterizer.depth_clip.into(),
+        MultisampleEnable: rasterizer.multisample.into(),
+        AntialiasedLineEnable: rasterizer.antialiased_lines.into(),
+        ForcedSampleCount: 0,
+        ConservativeRaster: if rasterizer.conservative {
+            D3D12_CONSERVATIVE_RASTERIZATION_MODE_ON
+        } else {
+            D3D12_CONSERVATIVE_RASTERIZATION_MODE_OFF
+        },
+    }
+}
+
+fn cull_mode(cull: CullMode) -> D3D12_CULL_MODE {
+    match cull {
+        CullMode::None => D3D12_CULL_MODE_NONE,
+        CullMode::Front => D3D12_CULL_MODE_FRONT,
+        CullMode::Back => D3D12_CULL_MODE_BACK,
+    }
+}
+
+fn depth_stencil_desc(depth_stencil: &DepthStencilState) -> D3D12_DEPTH_STENCIL_DESC {
+    D3D12_DEPTH_STENCIL_DESC {
+        DepthEnable: depth_stencil.depth_test.into(),
+        DepthWriteMask: if depth_stencil.depth_write {
+            D3D12_DEPTH_WRITE_MASK_ALL
+        } else {
+            D3D12_DEPTH_WRITE_MASK_ZERO
+        },
+        DepthFunc: comparison_func(depth_stencil.depth_func),
+        StencilEnable: depth_stencil.stencil_test.into(),
+        StencilReadMask: depth_stencil.stencil_read_mask,
+        StencilWriteMask: depth_stencil.stencil_write_mask,
+        FrontFace: stencil_op_desc(&depth_stencil.front),
+        BackFace: stencil_op_desc(&depth_stencil.back),
+    }
+}
+
+fn stencil_op_desc(face: &StencilFace) -> D3D12_DEPTH_STENCILOP_DESC {
+    D3D12_DEPTH_STENCILOP_DESC {
+        StencilFailOp: stencil_op(face.fail),
+        StencilDepthFailOp: stencil_op(face.depth_fail),
+        StencilPassOp: stencil_op(face.pass),
+        StencilFunc: comparison_func(face.func),
+    }
+}
+
+fn stencil_op(op: StencilOp) -> D3D12_STENCIL_OP {
+    match op {
+        StencilOp::Keep => D3D12_STENCIL_OP_KEEP,
+        StencilOp::Zero => D3D12_STENCIL_OP_ZERO,
+        StencilOp::Replace => D3D12_STENCIL_OP_REPLACE,
+        StencilOp::IncrSat => D3D12_STENCIL_OP_INCR_SAT,
+        StencilOp::DecrSat => D3D12_STENCIL_OP_DECR_SAT,
+        StencilOp::Invert => D3D12_STENCIL_OP_INVERT,
+        StencilOp::Incr => D3D12_STENCIL_OP_INCR,
+        StencilOp::Decr => D3D12_STENCIL_OP_DECR,
+    }
+}
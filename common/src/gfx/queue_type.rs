@@ -0,0 +1,14 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QueueType {
+    Direct,
+    Compute,
+    Copy,
+}
+
+impl QueueType {
+    pub const ALL: [QueueType; 3] = [QueueType::Direct, QueueType::Compute, QueueType::Copy];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
@@ -0,0 +1,170 @@
+// Getting texture data back from the GPU. Rows copied into a buffer are
+// padded out to 256 bytes, so `ReadbackLayout` says where each row lands and
+// `unpad_rows` packs them back together. `to_rgba8` then turns render target
+// formats into 8-bit RGBA for saving. See `d3d12` for the copy itself.
+
+#[cfg(windows)]
+mod d3d12;
+
+#[cfg(windows)]
+pub(crate) use d3d12::copy_texture_to_buffer;
+#[cfg(windows)]
+pub use d3d12::Readback;
+
+use std::{error::Error, fmt};
+
+use super::{format::Format, ring_allocator::align_up};
+use crate::math::{f16_to_f32, linear_to_srgb};
+
+/// D3D12_TEXTURE_DATA_PITCH_ALIGNMENT: rows in a buffer start on this.
+pub const TEXTURE_DATA_PITCH_ALIGNMENT: u64 = 256;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReadbackError {
+    UnsupportedFormat(Format),
+    /// The data is shorter than the layout says it should be.
+    TooShort {
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat(format) => write!(f, "can't read back format {}", format.0),
+            Self::TooShort { expected, actual } => {
+                write!(f, "expected {expected} bytes of pixels, got {actual}")
+            }
+        }
+    }
+}
+
+impl Error for ReadbackError {}
+
+/// Bytes per pixel of the formats `to_rgba8` understands.
+pub fn bytes_per_pixel(format: Format) -> Option<u32> {
+    match format {
+        Format::R8G8B8A8_UNORM
+        | Format::R8G8B8A8_UNORM_SRGB
+        | Format::B8G8R8A8_UNORM
+        | Format::B8G8R8A8_UNORM_SRGB
+        | Format::R10G10B10A2_UNORM => Some(4),
+        Format::R16G16B16A16_FLOAT => Some(8),
+        Format::R32G32B32A32_FLOAT => Some(16),
+        _ => None,
+    }
+}
+
+/// Where the rows of a 2D texture end up when copied into a buffer, as
+/// `GetCopyableFootprints` would place them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadbackLayout {
+    pub width: u32,
+    pub height: u32,
+    pub format: Format,
+    /// The pixels in one row.
+    pub row_bytes: u64,
+    /// The distance between rows in the buffer.
+    pub row_pitch: u64,
+}
+
+impl ReadbackLayout {
+    pub fn new(width: u32, height: u32, format: Format) -> Result<Self, ReadbackError> {
+        let bytes_per_pixel =
+            bytes_per_pixel(format).ok_or(ReadbackError::UnsupportedFormat(format))?;
+        let row_bytes = width as u64 * bytes_per_pixel as u64;
+        Ok(Self {
+            width,
+            height,
+            format,
+            row_bytes,
+            row_pitch: align_up(row_bytes, TEXTURE_DATA_PITCH_ALIGNMENT),
+        })
+    }
+
+    /// The buffer size needed. The last row isn't padded.
+    pub fn size(&self) -> u64 {
+        match self.height {
+            0 => 0,
+            height => self.row_pitch * (height as u64 - 1) + self.row_bytes,
+        }
+    }
+}
+
+/// Copies the rows out of a readback buffer without the padding between
+/// them.
+pub fn unpad_rows(data: &[u8], layout: &ReadbackLayout) -> Result<Vec<u8>, ReadbackError> {
+    let expected = layout.size() as usize;
+    if data.len() < expected {
+        return Err(ReadbackError::TooShort {
+            expected,
+            actual: data.len(),
+        });
+    }
+
+    let row_bytes = layout.row_bytes as usize;
+    let mut pixels = Vec::with_capacity(row_bytes * layout.height as usize);
+    for row in 0..layout.height as usize {
+        let start = row * layout.row_pitch as usize;
+        pixels.extend_from_slice(&data[start..start + row_bytes]);
+    }
+    Ok(pixels)
+}
+
+/// Converts tightly packed pixels to 8-bit RGBA. 8-bit formats are copied
+/// as they are, since that's what ends up on screen; float formats hold
+/// linear values, so they're clamped and sRGB encoded.
+pub fn to_rgba8(format: Format, pixels: &[u8]) -> Result<Vec<u8>, ReadbackError> {
+    let bytes_per_pixel =
+        bytes_per_pixel(format).ok_or(ReadbackError::UnsupportedFormat(format))? as usize;
+    let texels = pixels.chunks_exact(bytes_per_pixel);
+
+    let rgba = match format {
+        Format::R8G8B8A8_UNORM | Format::R8G8B8A8_UNORM_SRGB => pixels.to_vec(),
+        Format::B8G8R8A8_UNORM | Format::B8G8R8A8_UNORM_SRGB => texels
+            .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+            .collect(),
+        Format::R10G10B10A2_UNORM => texels
+            .flat_map(|texel| {
+                let bits = u32::from_le_bytes(texel.try_into().unwrap());
+                let channel = |shift: u32| {
+                    let value = (bits >> shift) & 0x3ff;
+                    ((value * 255 + 511) / 1023) as u8
+                };
+                [
+                    channel(0),
+                    channel(10),
+                    channel(20),
+                    ((bits >> 30) * 85) as u8,
+                ]
+            })
+            .collect(),
+        Format::R16G16B16A16_FLOAT => texels
+            .flat_map(|texel| {
+                let channel =
+                    |i: usize| f16_to_f32(u16::from_le_bytes([texel[i * 2], texel[i * 2 + 1]]));
+                encode_linear([channel(0), channel(1), channel(2), channel(3)])
+            })
+            .collect(),
+        Format::R32G32B32A32_FLOAT => texels
+            .flat_map(|texel| {
+                let channel =
+                    |i: usize| f32::from_le_bytes(texel[i * 4..i * 4 + 4].try_into().unwrap());
+                encode_linear([channel(0), channel(1), channel(2), channel(3)])
+            })
+            .collect(),
+        _ => unreachable!("bytes_per_pixel only knows the formats above"),
+    };
+    Ok(rgba)
+}
+
+fn encode_linear([r, g, b, a]: [f32; 4]) -> [u8; 4] {
+    let unorm = |value: f32| (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+    [
+        unorm(linear_to_srgb(r)),
+        unorm(linear_to_srgb(g)),
+        unorm(linear_to_srgb(b)),
+        unorm(a),
+    ]
+}
@@ -0,0 +1,123 @@
+use windows::Win32::Graphics::{
+    Direct3D12::{
+        ID3D12Device, ID3D12GraphicsCommandList, ID3D12Resource, D3D12_HEAP_FLAG_NONE,
+        D3D12_HEAP_TYPE_READBACK, D3D12_PLACED_SUBRESOURCE_FOOTPRINT, D3D12_RANGE,
+        D3D12_RESOURCE_STATES, D3D12_RESOURCE_STATE_COPY_DEST, D3D12_RESOURCE_STATE_COPY_SOURCE,
+        D3D12_SUBRESOURCE_FOOTPRINT, D3D12_TEXTURE_COPY_LOCATION, D3D12_TEXTURE_COPY_LOCATION_0,
+        D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT, D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+    },
+    Dxgi::Common::DXGI_FORMAT,
+};
+
+use super::{unpad_rows, ReadbackLayout};
+use crate::gfx::{buffer_resource_desc, heap_properties, transition_barrier};
+
+/// A READBACK heap buffer sized for one 2D texture. Record the copy, wait
+/// for it to finish on the GPU, then `read`.
+pub struct Readback {
+    buffer: ID3D12Resource,
+    layout: ReadbackLayout,
+}
+
+impl Readback {
+    pub fn new(device: &ID3D12Device, layout: ReadbackLayout) -> windows::core::Result<Self> {
+        let mut buffer: Option<ID3D12Resource> = None;
+        unsafe {
+            device.CreateCommittedResource(
+                &heap_properties(D3D12_HEAP_TYPE_READBACK),
+                D3D12_HEAP_FLAG_NONE,
+                &buffer_resource_desc(layout.size()),
+                D3D12_RESOURCE_STATE_COPY_DEST,
+                None,
+                &mut buffer,
+            )
+        }?;
+        let buffer = buffer.ok_or_else(windows::core::Error::empty)?;
+
+        Ok(Self { buffer, layout })
+    }
+
+    pub fn layout(&self) -> &ReadbackLayout {
+        &self.layout
+    }
+
+    /// Copies subresource 0 of `texture`, which is in `state` and is left
+    /// there afterwards.
+    pub fn record_copy(
+        &self,
+        list: &ID3D12GraphicsCommandList,
+        texture: &ID3D12Resource,
+        state: D3D12_RESOURCE_STATES,
+    ) {
+        unsafe {
+            list.ResourceBarrier(&[transition_barrier(
+                texture,
+                state,
+                D3D12_RESOURCE_STATE_COPY_SOURCE,
+            )]);
+            copy_texture_to_buffer(list, &self.buffer, &self.layout, texture);
+            list.ResourceBarrier(&[transition_barrier(
+                texture,
+                D3D12_RESOURCE_STATE_COPY_SOURCE,
+                state,
+            )]);
+        }
+    }
+
+    /// The copied pixels, tightly packed. Only valid once the copy has
+    /// finished executing.
+    pub fn read(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let size = self.layout.size() as usize;
+        let read_range = D3D12_RANGE {
+            Begin: 0,
+            End: size,
+        };
+        let mut data = std::ptr::null_mut();
+        unsafe { self.buffer.Map(0, Some(&read_range), Some(&mut data)) }?;
+
+        let mapped = unsafe { std::slice::from_raw_parts(data as *const u8, size) };
+        let pixels = unpad_rows(mapped, &self.layout);
+
+        // Nothing was written.
+        let written_range = D3D12_RANGE { Begin: 0, End: 0 };
+        unsafe { self.buffer.Unmap(0, Some(&written_range)) };
+
+        Ok(pixels?)
+    }
+}
+
+/// Records a copy of subresource 0 of `texture` into `buffer`, laid out as
+/// `layout`. The texture has to be in COPY_SOURCE and the buffer in
+/// COPY_DEST.
+pub(crate) fn copy_texture_to_buffer(
+    list: &ID3D12GraphicsCommandList,
+    buffer: &ID3D12Resource,
+    layout: &ReadbackLayout,
+    texture: &ID3D12Resource,
+) {
+    let dst = D3D12_TEXTURE_COPY_LOCATION {
+        pResource: unsafe { std::mem::transmute_copy(buffer) },
+        Type: D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
+        Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+            PlacedFootprint: D3D12_PLACED_SUBRESOURCE_FOOTPRINT {
+                Offset: 0,
+                Footprint: D3D12_SUBRESOURCE_FOOTPRINT {
+                    Format: DXGI_FORMAT(layout.format.0 as i32),
+                    Width: layout.width,
+                    Height: layout.height,
+                    Depth: 1,
+                    RowPitch: layout.row_pitch as u32,
+                },
+            },
+        },
+    };
+    let src = D3D12_TEXTURE_COPY_LOCATION {
+        pResource: unsafe { std::mem::transmute_copy(texture) },
+        Type: D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
+        Anonymous: D3D12_TEXTURE_COPY_LOCATION_0 {
+            SubresourceIndex: 0,
+        },
+    };
+
+    unsafe { list.CopyTextureRegion(&dst, 0, 0, 0, &src, None) };
+}
@@ -0,0 +1,45 @@
+use super::{deferred_queue::DeferredQueue, queue_type::QueueType};
+
+/// Per queue type free-lists for objects, such as command allocators, that
+/// can only be reused once the GPU has finished the work recorded with them.
+#[derive(Debug)]
+pub struct RecyclePool<T> {
+    free: [Vec<T>; 3],
+    in_flight: [DeferredQueue<T>; 3],
+}
+
+impl<T> Default for RecyclePool<T> {
+    fn default() -> Self {
+        Self {
+            free: Default::default(),
+            in_flight: Default::default(),
+        }
+    }
+}
+
+impl<T> RecyclePool<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a free object, or `None` if the caller needs to create one.
+    pub fn acquire(&mut self, queue_type: QueueType, completed_fence_value: u64) -> Option<T> {
+        let index = queue_type.index();
+        self.free[index].extend(self.in_flight[index].drain_completed(completed_fence_value));
+        self.free[index].pop()
+    }
+
+    /// Hands an object back once the work recorded with it has been submitted
+    /// with `fence_value`.
+    pub fn retire(&mut self, queue_type: QueueType, item: T, fence_value: u64) {
+        self.in_flight[queue_type.index()].retire(fence_value, item);
+    }
+
+    pub fn free_count(&self, queue_type: QueueType) -> usize {
+        self.free[queue_type.index()].len()
+    }
+
+    pub fn in_flight_count(&self, queue_type: QueueType) -> usize {
+        self.in_flight[queue_type.index()].len()
+    }
+}
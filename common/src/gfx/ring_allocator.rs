@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+
+pub const CONSTANT_BUFFER_ALIGNMENT: u64 = 256;
+pub const TEXTURE_PLACEMENT_ALIGNMENT: u64 = 512;
+
+pub fn align_up(value: u64, alignment: u64) -> u64 {
+    debug_assert!(alignment.is_power_of_two());
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RingAllocation {
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// Hands out linear sub-allocations from a fixed size ring. Space is reclaimed
+/// a frame at a time once the fence value the frame was submitted with completes.
+///
+/// `head` and `tail` are virtual positions that only ever grow; the physical
+/// offset is the position modulo the capacity.
+#[derive(Debug)]
+pub struct RingAllocator {
+    capacity: u64,
+    head: u64,
+    tail: u64,
+    in_flight: VecDeque<(u64, u64)>,
+}
+
+impl RingAllocator {
+    pub fn new(capacity: u64) -> Self {
+        assert!(
+            capacity.is_multiple_of(TEXTURE_PLACEMENT_ALIGNMENT),
+            "ring capacity must be a multiple of {TEXTURE_PLACEMENT_ALIGNMENT} bytes"
+        );
+
+        Self {
+            capacity,
+            head: 0,
+            tail: 0,
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn used(&self) -> u64 {
+        self.head - self.tail
+    }
+
+    pub fn available(&self) -> u64 {
+        self.capacity - self.used()
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<RingAllocation> {
+        assert!(
+            alignment.is_power_of_two(),
+            "alignment must be a power of two"
+        );
+        assert!(
+            alignment <= TEXTURE_PLACEMENT_ALIGNMENT,
+            "alignment must not exceed {TEXTURE_PLACEMENT_ALIGNMENT} bytes"
+        );
+
+        if size > self.capacity {
+            return None;
+        }
+
+        let offset = self.head % self.capacity;
+        let mut aligned = align_up(offset, alignment);
+        let mut padding = aligned - offset;
+
+        if aligned + size > self.capacity {
+            // Not enough room before the end of the buffer, so skip what is
+            // left of it and wrap around to the start.
+            padding = self.capacity - offset;
+            aligned = 0;
+        }
+
+        let head = self.head + padding + size;
+        if head - self.tail > self.capacity {
+            return None;
+        }
+
+        self.head = head;
+
+        Some(RingAllocation {
+            offset: aligned,
+            size,
+        })
+    }
+
+    /// Marks the end of the allocations for a frame; they become reclaimable
+    /// once `fence_value` has completed.
+    pub fn finish_frame(&mut self, fence_value: u64) {
+        debug_assert!(
+            self.in_flight.back().is_none_or(|&(f, _)| f <= fence_value),
+            "fence values must be submitted in increasing order"
+        );
+
+        self.in_flight.push_back((fence_value, self.head));
+    }
+
+    pub fn reclaim(&mut self, completed_fence_value: u64) {
+        while let Some(&(fence_value, end)) = self.in_flight.front() {
+            if fence_value > completed_fence_value {
+                break;
+            }
+
+            self.tail = end;
+            self.in_flight.pop_front();
+        }
+    }
+}
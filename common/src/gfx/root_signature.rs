@@ -0,0 +1,869 @@
+// Root signature description: built and validated without touching D3D12, and
+// turned into either a serialized blob (see `d3d12`) or the equivalent HLSL
+// root signature string for embedding in shaders.
+
+#[cfg(windows)]
+mod d3d12;
+
+#[cfg(windows)]
+pub use d3d12::{create_root_signature, highest_root_signature_version, serialize_root_signature};
+
+#[cfg(windows)]
+pub(crate) use d3d12::comparison_func;
+
+use std::{fmt, ops::BitOr};
+
+/// The most a root signature can hold, counted in 32-bit values.
+pub const MAX_ROOT_SIGNATURE_DWORDS: u32 = 64;
+
+/// Descriptor count for a range that runs to the end of the heap.
+pub const UNBOUNDED: u32 = u32::MAX;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RootSignatureVersion {
+    V1_0,
+    #[default]
+    V1_1,
+}
+
+macro_rules! flags {
+    ($(#[$meta:meta])* $name:ident { $($flag:ident = $value:expr,)* }) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+        pub struct $name(u32);
+
+        impl $name {
+            pub const NONE: Self = Self(0);
+            $(pub const $flag: Self = Self($value);)*
+
+            const NAMES: &'static [(Self, &'static str)] = &[$((Self::$flag, stringify!($flag)),)*];
+
+            /// The D3D12 value of the flags.
+            pub fn bits(self) -> u32 {
+                self.0
+            }
+
+            pub fn is_empty(self) -> bool {
+                self.0 == 0
+            }
+
+            pub fn contains(self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            fn hlsl(self) -> String {
+                Self::NAMES
+                    .iter()
+                    .filter(|(flag, _)| self.contains(*flag))
+                    .map(|(_, name)| *name)
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = Self;
+
+            fn bitor(self, rhs: Self) -> Self {
+                Self(self.0 | rhs.0)
+            }
+        }
+    };
+}
+
+flags!(RootSignatureFlags {
+    ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT = 0x1,
+    DENY_VERTEX_SHADER_ROOT_ACCESS = 0x2,
+    DENY_HULL_SHADER_ROOT_ACCESS = 0x4,
+    DENY_DOMAIN_SHADER_ROOT_ACCESS = 0x8,
+    DENY_GEOMETRY_SHADER_ROOT_ACCESS = 0x10,
+    DENY_PIXEL_SHADER_ROOT_ACCESS = 0x20,
+    ALLOW_STREAM_OUTPUT = 0x40,
+    LOCAL_ROOT_SIGNATURE = 0x80,
+    DENY_AMPLIFICATION_SHADER_ROOT_ACCESS = 0x100,
+    DENY_MESH_SHADER_ROOT_ACCESS = 0x200,
+    CBV_SRV_UAV_HEAP_DIRECTLY_INDEXED = 0x400,
+    SAMPLER_HEAP_DIRECTLY_INDEXED = 0x800,
+});
+
+flags!(
+    /// Version 1.1 volatility hints for root descriptors and descriptor
+    /// ranges. Root descriptors only take the `DATA_*` flags.
+    DescriptorFlags {
+        DESCRIPTORS_VOLATILE = 0x1,
+        DATA_VOLATILE = 0x2,
+        DATA_STATIC_WHILE_SET_AT_EXECUTE = 0x4,
+        DATA_STATIC = 0x8,
+        DESCRIPTORS_STATIC_KEEPING_BUFFER_BOUNDS_CHECKS = 0x10000,
+    }
+);
+
+impl DescriptorFlags {
+    const DATA: Self = Self(0x2 | 0x4 | 0x8);
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShaderVisibility {
+    #[default]
+    All,
+    Vertex,
+    Hull,
+    Domain,
+    Geometry,
+    Pixel,
+    Amplification,
+    Mesh,
+}
+
+impl ShaderVisibility {
+    fn hlsl(self) -> &'static str {
+        match self {
+            ShaderVisibility::All => "SHADER_VISIBILITY_ALL",
+            ShaderVisibility::Vertex => "SHADER_VISIBILITY_VERTEX",
+            ShaderVisibility::Hull => "SHADER_VISIBILITY_HULL",
+            ShaderVisibility::Domain => "SHADER_VISIBILITY_DOMAIN",
+            ShaderVisibility::Geometry => "SHADER_VISIBILITY_GEOMETRY",
+            ShaderVisibility::Pixel => "SHADER_VISIBILITY_PIXEL",
+            ShaderVisibility::Amplification => "SHADER_VISIBILITY_AMPLIFICATION",
+            ShaderVisibility::Mesh => "SHADER_VISIBILITY_MESH",
+        }
+    }
+
+    fn overlaps(self, other: Self) -> bool {
+        self == ShaderVisibility::All || other == ShaderVisibility::All || self == other
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DescriptorType {
+    Cbv,
+    Srv,
+    Uav,
+    Sampler,
+}
+
+impl DescriptorType {
+    fn hlsl(self) -> &'static str {
+        match self {
+            DescriptorType::Cbv => "CBV",
+            DescriptorType::Srv => "SRV",
+            DescriptorType::Uav => "UAV",
+            DescriptorType::Sampler => "Sampler",
+        }
+    }
+
+    fn register_prefix(self) -> char {
+        match self {
+            DescriptorType::Cbv => 'b',
+            DescriptorType::Srv => 't',
+            DescriptorType::Uav => 'u',
+            DescriptorType::Sampler => 's',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DescriptorRange {
+    pub range_type: DescriptorType,
+    pub count: u32,
+    pub base_register: u32,
+    pub space: u32,
+    pub flags: DescriptorFlags,
+    /// Offset from the start of the table, or `None` to follow the previous
+    /// range.
+    pub offset: Option<u32>,
+}
+
+impl DescriptorRange {
+    pub fn new(range_type: DescriptorType, count: u32, base_register: u32) -> Self {
+        Self {
+            range_type,
+            count,
+            base_register,
+            space: 0,
+            flags: DescriptorFlags::NONE,
+            offset: None,
+        }
+    }
+
+    pub fn cbv(count: u32, base_register: u32) -> Self {
+        Self::new(DescriptorType::Cbv, count, base_register)
+    }
+
+    pub fn srv(count: u32, base_register: u32) -> Self {
+        Self::new(DescriptorType::Srv, count, base_register)
+    }
+
+    pub fn uav(count: u32, base_register: u32) -> Self {
+        Self::new(DescriptorType::Uav, count, base_register)
+    }
+
+    pub fn sampler(count: u32, base_register: u32) -> Self {
+        Self::new(DescriptorType::Sampler, count, base_register)
+    }
+
+    pub fn space(mut self, space: u32) -> Self {
+        self.space = space;
+        self
+    }
+
+    pub fn flags(mut self, flags: DescriptorFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    fn last_register(&self) -> u32 {
+        if self.count == UNBOUNDED {
+            u32::MAX
+        } else {
+            self.base_register.saturating_add(self.count - 1)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RootParameter {
+    /// 32-bit values bound to a constant buffer register.
+    Constants {
+        count: u32,
+        register: u32,
+        space: u32,
+        visibility: ShaderVisibility,
+    },
+    /// A CBV, SRV or UAV bound by GPU virtual address, without a heap.
+    Descriptor {
+        descriptor_type: DescriptorType,
+        register: u32,
+        space: u32,
+        flags: DescriptorFlags,
+        visibility: ShaderVisibility,
+    },
+    DescriptorTable {
+        ranges: Vec<DescriptorRange>,
+        visibility: ShaderVisibility,
+    },
+}
+
+impl RootParameter {
+    /// Constants cost one DWORD each, root descriptors two and tables one.
+    pub fn dword_cost(&self) -> u32 {
+        match self {
+            RootParameter::Constants { count, .. } => *count,
+            RootParameter::Descriptor { .. } => 2,
+            RootParameter::DescriptorTable { .. } => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Filter {
+    Point,
+    MinMagLinearMipPoint,
+    Linear,
+    Anisotropic,
+    ComparisonPoint,
+    ComparisonLinear,
+    ComparisonAnisotropic,
+}
+
+impl Filter {
+    fn hlsl(self) -> &'static str {
+        match self {
+            Filter::Point => "FILTER_MIN_MAG_MIP_POINT",
+            Filter::MinMagLinearMipPoint => "FILTER_MIN_MAG_LINEAR_MIP_POINT",
+            Filter::Linear => "FILTER_MIN_MAG_MIP_LINEAR",
+            Filter::Anisotropic => "FILTER_ANISOTROPIC",
+            Filter::ComparisonPoint => "FILTER_COMPARISON_MIN_MAG_MIP_POINT",
+            Filter::ComparisonLinear => "FILTER_COMPARISON_MIN_MAG_MIP_LINEAR",
+            Filter::ComparisonAnisotropic => "FILTER_COMPARISON_ANISOTROPIC",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AddressMode {
+    Wrap,
+    Mirror,
+    Clamp,
+    Border,
+    MirrorOnce,
+}
+
+impl AddressMode {
+    fn hlsl(self) -> &'static str {
+        match self {
+            AddressMode::Wrap => "TEXTURE_ADDRESS_WRAP",
+            AddressMode::Mirror => "TEXTURE_ADDRESS_MIRROR",
+            AddressMode::Clamp => "TEXTURE_ADDRESS_CLAMP",
+            AddressMode::Border => "TEXTURE_ADDRESS_BORDER",
+            AddressMode::MirrorOnce => "TEXTURE_ADDRESS_MIRROR_ONCE",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ComparisonFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl ComparisonFunc {
+    fn hlsl(self) -> &'static str {
+        match self {
+            ComparisonFunc::Never => "COMPARISON_NEVER",
+            ComparisonFunc::Less => "COMPARISON_LESS",
+            ComparisonFunc::Equal => "COMPARISON_EQUAL",
+            ComparisonFunc::LessEqual => "COMPARISON_LESS_EQUAL",
+            ComparisonFunc::Greater => "COMPARISON_GREATER",
+            ComparisonFunc::NotEqual => "COMPARISON_NOT_EQUAL",
+            ComparisonFunc::GreaterEqual => "COMPARISON_GREATER_EQUAL",
+            ComparisonFunc::Always => "COMPARISON_ALWAYS",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BorderColor {
+    TransparentBlack,
+    OpaqueBlack,
+    OpaqueWhite,
+}
+
+impl BorderColor {
+    fn hlsl(self) -> &'static str {
+        match self {
+            BorderColor::TransparentBlack => "STATIC_BORDER_COLOR_TRANSPARENT_BLACK",
+            BorderColor::OpaqueBlack => "STATIC_BORDER_COLOR_OPAQUE_BLACK",
+            BorderColor::OpaqueWhite => "STATIC_BORDER_COLOR_OPAQUE_WHITE",
+        }
+    }
+}
+
+/// Defaults match the HLSL `StaticSampler` defaults, so only the fields that
+/// differ from them show up in the emitted string.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StaticSampler {
+    pub register: u32,
+    pub space: u32,
+    pub filter: Filter,
+    pub address_u: AddressMode,
+    pub address_v: AddressMode,
+    pub address_w: AddressMode,
+    pub mip_lod_bias: f32,
+    pub max_anisotropy: u32,
+    pub comparison: ComparisonFunc,
+    pub border_color: BorderColor,
+    pub min_lod: f32,
+    pub max_lod: f32,
+    pub visibility: ShaderVisibility,
+}
+
+impl StaticSampler {
+    pub fn new(register: u32) -> Self {
+        Self {
+            register,
+            space: 0,
+            filter: Filter::Anisotropic,
+            address_u: AddressMode::Wrap,
+            address_v: AddressMode::Wrap,
+            address_w: AddressMode::Wrap,
+            mip_lod_bias: 0.0,
+            max_anisotropy: 16,
+            comparison: ComparisonFunc::LessEqual,
+            border_color: BorderColor::OpaqueWhite,
+            min_lod: 0.0,
+            max_lod: f32::MAX,
+            visibility: ShaderVisibility::All,
+        }
+    }
+
+    pub fn space(mut self, space: u32) -> Self {
+        self.space = space;
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets the same mode on all three axes.
+    pub fn address_mode(mut self, mode: AddressMode) -> Self {
+        self.address_u = mode;
+        self.address_v = mode;
+        self.address_w = mode;
+        self
+    }
+
+    pub fn max_anisotropy(mut self, max_anisotropy: u32) -> Self {
+        self.max_anisotropy = max_anisotropy;
+        self
+    }
+
+    pub fn comparison(mut self, comparison: ComparisonFunc) -> Self {
+        self.comparison = comparison;
+        self
+    }
+
+    pub fn border_color(mut self, border_color: BorderColor) -> Self {
+        self.border_color = border_color;
+        self
+    }
+
+    pub fn lod_range(mut self, min_lod: f32, max_lod: f32) -> Self {
+        self.min_lod = min_lod;
+        self.max_lod = max_lod;
+        self
+    }
+
+    pub fn visibility(mut self, visibility: ShaderVisibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+
+    fn hlsl(&self) -> String {
+        let defaults = Self::new(self.register);
+        let mut args = vec![format!("s{}", self.register)];
+
+        if self.filter != defaults.filter {
+            args.push(format!("filter={}", self.filter.hlsl()));
+        }
+        for (name, mode) in [
+            ("addressU", self.address_u),
+            ("addressV", self.address_v),
+            ("addressW", self.address_w),
+        ] {
+            if mode != AddressMode::Wrap {
+                args.push(format!("{name}={}", mode.hlsl()));
+            }
+        }
+        if self.mip_lod_bias != defaults.mip_lod_bias {
+            args.push(format!("mipLODBias={}", hlsl_float(self.mip_lod_bias)));
+        }
+        if self.max_anisotropy != defaults.max_anisotropy {
+            args.push(format!("maxAnisotropy={}", self.max_anisotropy));
+        }
+        if self.comparison != defaults.comparison {
+            args.push(format!("comparisonFunc={}", self.comparison.hlsl()));
+        }
+        if self.border_color != defaults.border_color {
+            args.push(format!("borderColor={}", self.border_color.hlsl()));
+        }
+        if self.min_lod != defaults.min_lod {
+            args.push(format!("minLOD={}", hlsl_float(self.min_lod)));
+        }
+        if self.max_lod != defaults.max_lod {
+            args.push(format!("maxLOD={}", hlsl_float(self.max_lod)));
+        }
+        push_space_and_visibility(&mut args, self.space, self.visibility);
+
+        format!("StaticSampler({})", args.join(", "))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RootSignatureError {
+    TooLarge { dwords: u32 },
+    EmptyDescriptorTable { parameter: usize },
+    EmptyRange { parameter: usize, range: usize },
+    MixedSamplerTable { parameter: usize },
+    SamplerRootDescriptor { parameter: usize },
+    InvalidFlags { parameter: usize },
+    OverlappingRegisters { register: String },
+}
+
+impl fmt::Display for RootSignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RootSignatureError::TooLarge { dwords } => write!(
+                f,
+                "root signature uses {dwords} DWORDs, the limit is {MAX_ROOT_SIGNATURE_DWORDS}"
+            ),
+            RootSignatureError::EmptyDescriptorTable { parameter } => {
+                write!(f, "descriptor table {parameter} has no ranges")
+            }
+            RootSignatureError::EmptyRange { parameter, range } => {
+                write!(f, "range {range} of descriptor table {parameter} is empty")
+            }
+            RootSignatureError::MixedSamplerTable { parameter } => write!(
+                f,
+                "descriptor table {parameter} mixes samplers with other descriptors"
+            ),
+            RootSignatureError::SamplerRootDescriptor { parameter } => {
+                write!(f, "root parameter {parameter} is a sampler root descriptor")
+            }
+            RootSignatureError::InvalidFlags { parameter } => {
+                write!(f, "root parameter {parameter} has invalid flags")
+            }
+            RootSignatureError::OverlappingRegisters { register } => {
+                write!(f, "register {register} is bound more than once")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RootSignatureError {}
+
+#[derive(Clone, Debug, Default)]
+pub struct RootSignatureBuilder {
+    flags: RootSignatureFlags,
+    parameters: Vec<RootParameter>,
+    static_samplers: Vec<StaticSampler>,
+}
+
+impl RootSignatureBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn flags(mut self, flags: RootSignatureFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn parameter(mut self, parameter: RootParameter) -> Self {
+        self.parameters.push(parameter);
+        self
+    }
+
+    pub fn constants(self, count: u32, register: u32, visibility: ShaderVisibility) -> Self {
+        self.parameter(RootParameter::Constants {
+            count,
+            register,
+            space: 0,
+            visibility,
+        })
+    }
+
+    pub fn cbv(self, register: u32, visibility: ShaderVisibility) -> Self {
+        self.root_descriptor(DescriptorType::Cbv, register, visibility)
+    }
+
+    pub fn srv(self, register: u32, visibility: ShaderVisibility) -> Self {
+        self.root_descriptor(DescriptorType::Srv, register, visibility)
+    }
+
+    pub fn uav(self, register: u32, visibility: ShaderVisibility) -> Self {
+        self.root_descriptor(DescriptorType::Uav, register, visibility)
+    }
+
+    pub fn descriptor_table(
+        self,
+        ranges: impl IntoIterator<Item = DescriptorRange>,
+        visibility: ShaderVisibility,
+    ) -> Self {
+        self.parameter(RootParameter::DescriptorTable {
+            ranges: ranges.into_iter().collect(),
+            visibility,
+        })
+    }
+
+    pub fn static_sampler(mut self, sampler: StaticSampler) -> Self {
+        self.static_samplers.push(sampler);
+        self
+    }
+
+    pub fn build(self) -> Result<RootSignatureDesc, RootSignatureError> {
+        let desc = RootSignatureDesc {
+            flags: self.flags,
+            parameters: self.parameters,
+            static_samplers: self.static_samplers,
+        };
+        desc.validate()?;
+        Ok(desc)
+    }
+
+    fn root_descriptor(
+        self,
+        descriptor_type: DescriptorType,
+        register: u32,
+        visibility: ShaderVisibility,
+    ) -> Self {
+        self.parameter(RootParameter::Descriptor {
+            descriptor_type,
+            register,
+            space: 0,
+            flags: DescriptorFlags::NONE,
+            visibility,
+        })
+    }
+}
+
+/// A validated root signature.
+#[derive(Clone, Debug)]
+pub struct RootSignatureDesc {
+    flags: RootSignatureFlags,
+    parameters: Vec<RootParameter>,
+    static_samplers: Vec<StaticSampler>,
+}
+
+impl RootSignatureDesc {
+    pub fn flags(&self) -> RootSignatureFlags {
+        self.flags
+    }
+
+    pub fn parameters(&self) -> &[RootParameter] {
+        &self.parameters
+    }
+
+    pub fn static_samplers(&self) -> &[StaticSampler] {
+        &self.static_samplers
+    }
+
+    pub fn dword_cost(&self) -> u32 {
+        self.parameters.iter().map(RootParameter::dword_cost).sum()
+    }
+
+    /// Whether serializing needs version 1.1, i.e. any flags are set on root
+    /// descriptors or descriptor ranges.
+    pub fn requires_version_1_1(&self) -> bool {
+        self.parameters.iter().any(|parameter| match parameter {
+            RootParameter::Constants { .. } => false,
+            RootParameter::Descriptor { flags, .. } => !flags.is_empty(),
+            RootParameter::DescriptorTable { ranges, .. } => {
+                ranges.iter().any(|range| !range.flags.is_empty())
+            }
+        })
+    }
+
+    /// The root signature in HLSL syntax, e.g. for `[RootSignature(...)]`.
+    pub fn to_hlsl(&self) -> String {
+        self.hlsl_elements().join(", ")
+    }
+
+    /// A `#define` with one root signature element per line, ready to be
+    /// written into a shader header.
+    pub fn to_hlsl_define(&self, name: &str) -> String {
+        let elements = self.hlsl_elements();
+        let mut define = format!("#define {name}");
+        for (i, element) in elements.iter().enumerate() {
+            let separator = if i + 1 < elements.len() { ", " } else { "" };
+            define.push_str(&format!(" \\\n    \"{element}{separator}\""));
+        }
+        if elements.is_empty() {
+            define.push_str(" \"\"");
+        }
+        define.push('\n');
+        define
+    }
+
+    fn hlsl_elements(&self) -> Vec<String> {
+        let mut elements = Vec::new();
+
+        if !self.flags.is_empty() {
+            elements.push(format!("RootFlags({})", self.flags.hlsl()));
+        }
+
+        for parameter in &self.parameters {
+            elements.push(match parameter {
+                RootParameter::Constants {
+                    count,
+                    register,
+                    space,
+                    visibility,
+                } => {
+                    let mut args =
+                        vec![format!("num32BitConstants={count}"), format!("b{register}")];
+                    push_space_and_visibility(&mut args, *space, *visibility);
+                    format!("RootConstants({})", args.join(", "))
+                }
+                RootParameter::Descriptor {
+                    descriptor_type,
+                    register,
+                    space,
+                    flags,
+                    visibility,
+                } => {
+                    let mut args = vec![format!("{}{register}", descriptor_type.register_prefix())];
+                    push_space_and_visibility(&mut args, *space, *visibility);
+                    if !flags.is_empty() {
+                        args.push(format!("flags={}", flags.hlsl()));
+                    }
+                    format!("{}({})", descriptor_type.hlsl(), args.join(", "))
+                }
+                RootParameter::DescriptorTable { ranges, visibility } => {
+                    let mut args: Vec<String> = ranges.iter().map(range_hlsl).collect();
+                    push_space_and_visibility(&mut args, 0, *visibility);
+                    format!("DescriptorTable({})", args.join(", "))
+                }
+            });
+        }
+
+        elements.extend(self.static_samplers.iter().map(StaticSampler::hlsl));
+        elements
+    }
+
+    fn validate(&self) -> Result<(), RootSignatureError> {
+        let dwords = self.dword_cost();
+        if dwords > MAX_ROOT_SIGNATURE_DWORDS {
+            return Err(RootSignatureError::TooLarge { dwords });
+        }
+
+        let mut bindings = Vec::new();
+        for (parameter, root_parameter) in self.parameters.iter().enumerate() {
+            match root_parameter {
+                RootParameter::Constants {
+                    register,
+                    space,
+                    visibility,
+                    ..
+                } => bindings.push(Binding {
+                    descriptor_type: DescriptorType::Cbv,
+                    space: *space,
+                    first: *register,
+                    last: *register,
+                    visibility: *visibility,
+                }),
+                RootParameter::Descriptor {
+                    descriptor_type,
+                    register,
+                    space,
+                    flags,
+                    visibility,
+                } => {
+                    if *descriptor_type == DescriptorType::Sampler {
+                        return Err(RootSignatureError::SamplerRootDescriptor { parameter });
+                    }
+                    if !DescriptorFlags::DATA.contains(*flags) || !single_data_flag(*flags) {
+                        return Err(RootSignatureError::InvalidFlags { parameter });
+                    }
+                    bindings.push(Binding {
+                        descriptor_type: *descriptor_type,
+                        space: *space,
+                        first: *register,
+                        last: *register,
+                        visibility: *visibility,
+                    });
+                }
+                RootParameter::DescriptorTable { ranges, visibility } => {
+                    if ranges.is_empty() {
+                        return Err(RootSignatureError::EmptyDescriptorTable { parameter });
+                    }
+
+                    let samplers = ranges
+                        .iter()
+                        .filter(|r| r.range_type == DescriptorType::Sampler)
+                        .count();
+                    if samplers != 0 && samplers != ranges.len() {
+                        return Err(RootSignatureError::MixedSamplerTable { parameter });
+                    }
+
+                    for (range_index, range) in ranges.iter().enumerate() {
+                        if range.count == 0 {
+                            return Err(RootSignatureError::EmptyRange {
+                                parameter,
+                                range: range_index,
+                            });
+                        }
+
+                        let valid_flags = if range.range_type == DescriptorType::Sampler {
+                            DescriptorFlags::DESCRIPTORS_VOLATILE.contains(range.flags)
+                        } else {
+                            single_data_flag(range.flags)
+                        };
+                        if !valid_flags {
+                            return Err(RootSignatureError::InvalidFlags { parameter });
+                        }
+
+                        bindings.push(Binding {
+                            descriptor_type: range.range_type,
+                            space: range.space,
+                            first: range.base_register,
+                            last: range.last_register(),
+                            visibility: *visibility,
+                        });
+                    }
+                }
+            }
+        }
+
+        bindings.extend(self.static_samplers.iter().map(|sampler| Binding {
+            descriptor_type: DescriptorType::Sampler,
+            space: sampler.space,
+            first: sampler.register,
+            last: sampler.register,
+            visibility: sampler.visibility,
+        }));
+
+        for (i, a) in bindings.iter().enumerate() {
+            for b in &bindings[i + 1..] {
+                if a.descriptor_type == b.descriptor_type
+                    && a.space == b.space
+                    && a.visibility.overlaps(b.visibility)
+                    && a.first <= b.last
+                    && b.first <= a.last
+                {
+                    return Err(RootSignatureError::OverlappingRegisters {
+                        register: format!(
+                            "{}{} space{}",
+                            a.descriptor_type.register_prefix(),
+                            a.first.max(b.first),
+                            a.space
+                        ),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct Binding {
+    descriptor_type: DescriptorType,
+    space: u32,
+    first: u32,
+    last: u32,
+    visibility: ShaderVisibility,
+}
+
+fn single_data_flag(flags: DescriptorFlags) -> bool {
+    (flags.bits() & DescriptorFlags::DATA.bits()).count_ones() <= 1
+}
+
+fn range_hlsl(range: &DescriptorRange) -> String {
+    let mut args = vec![format!(
+        "{}{}",
+        range.range_type.register_prefix(),
+        range.base_register
+    )];
+    if range.count == UNBOUNDED {
+        args.push("numDescriptors=unbounded".to_string());
+    } else if range.count != 1 {
+        args.push(format!("numDescriptors={}", range.count));
+    }
+    if range.space != 0 {
+        args.push(format!("space={}", range.space));
+    }
+    if let Some(offset) = range.offset {
+        args.push(format!("offset={offset}"));
+    }
+    if !range.flags.is_empty() {
+        args.push(format!("flags={}", range.flags.hlsl()));
+    }
+    format!("{}({})", range.range_type.hlsl(), args.join(", "))
+}
+
+fn push_space_and_visibility(args: &mut Vec<String>, space: u32, visibility: ShaderVisibility) {
+    if space != 0 {
+        args.push(format!("space={space}"));
+    }
+    if visibility != ShaderVisibility::All {
+        args.push(format!("visibility={}", visibility.hlsl()));
+    }
+}
+
+fn hlsl_float(value: f32) -> String {
+    format!("{value:?}f")
+}
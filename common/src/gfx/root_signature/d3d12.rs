@@ -0,0 +1,408 @@
+use windows::{
+    core::Error,
+    Win32::{
+        Foundation::E_INVALIDARG,
+        Graphics::{
+            Direct3D::ID3DBlob,
+            Direct3D12::{
+                D3D12SerializeVersionedRootSignature, ID3D12Device, ID3D12RootSignature,
+                D3D12_COMPARISON_FUNC, D3D12_COMPARISON_FUNC_ALWAYS, D3D12_COMPARISON_FUNC_EQUAL,
+                D3D12_COMPARISON_FUNC_GREATER, D3D12_COMPARISON_FUNC_GREATER_EQUAL,
+                D3D12_COMPARISON_FUNC_LESS, D3D12_COMPARISON_FUNC_LESS_EQUAL,
+                D3D12_COMPARISON_FUNC_NEVER, D3D12_COMPARISON_FUNC_NOT_EQUAL,
+                D3D12_DESCRIPTOR_RANGE, D3D12_DESCRIPTOR_RANGE1, D3D12_DESCRIPTOR_RANGE_FLAGS,
+                D3D12_DESCRIPTOR_RANGE_OFFSET_APPEND, D3D12_DESCRIPTOR_RANGE_TYPE,
+                D3D12_DESCRIPTOR_RANGE_TYPE_CBV, D3D12_DESCRIPTOR_RANGE_TYPE_SAMPLER,
+                D3D12_DESCRIPTOR_RANGE_TYPE_SRV, D3D12_DESCRIPTOR_RANGE_TYPE_UAV,
+                D3D12_FEATURE_DATA_ROOT_SIGNATURE, D3D12_FEATURE_ROOT_SIGNATURE, D3D12_FILTER,
+                D3D12_FILTER_ANISOTROPIC, D3D12_FILTER_COMPARISON_ANISOTROPIC,
+                D3D12_FILTER_COMPARISON_MIN_MAG_MIP_LINEAR,
+                D3D12_FILTER_COMPARISON_MIN_MAG_MIP_POINT, D3D12_FILTER_MIN_MAG_LINEAR_MIP_POINT,
+                D3D12_FILTER_MIN_MAG_MIP_LINEAR, D3D12_FILTER_MIN_MAG_MIP_POINT,
+                D3D12_ROOT_CONSTANTS, D3D12_ROOT_DESCRIPTOR, D3D12_ROOT_DESCRIPTOR1,
+                D3D12_ROOT_DESCRIPTOR_FLAGS, D3D12_ROOT_DESCRIPTOR_TABLE,
+                D3D12_ROOT_DESCRIPTOR_TABLE1, D3D12_ROOT_PARAMETER, D3D12_ROOT_PARAMETER1,
+                D3D12_ROOT_PARAMETER1_0, D3D12_ROOT_PARAMETER_0, D3D12_ROOT_PARAMETER_TYPE,
+                D3D12_ROOT_PARAMETER_TYPE_32BIT_CONSTANTS, D3D12_ROOT_PARAMETER_TYPE_CBV,
+                D3D12_ROOT_PARAMETER_TYPE_DESCRIPTOR_TABLE, D3D12_ROOT_PARAMETER_TYPE_SRV,
+                D3D12_ROOT_PARAMETER_TYPE_UAV, D3D12_ROOT_SIGNATURE_DESC,
+                D3D12_ROOT_SIGNATURE_DESC1, D3D12_ROOT_SIGNATURE_FLAGS, D3D12_SHADER_VISIBILITY,
+                D3D12_SHADER_VISIBILITY_ALL, D3D12_SHADER_VISIBILITY_AMPLIFICATION,
+                D3D12_SHADER_VISIBILITY_DOMAIN, D3D12_SHADER_VISIBILITY_GEOMETRY,
+                D3D12_SHADER_VISIBILITY_HULL, D3D12_SHADER_VISIBILITY_MESH,
+                D3D12_SHADER_VISIBILITY_PIXEL, D3D12_SHADER_VISIBILITY_VERTEX,
+                D3D12_STATIC_BORDER_COLOR, D3D12_STATIC_BORDER_COLOR_OPAQUE_BLACK,
+                D3D12_STATIC_BORDER_COLOR_OPAQUE_WHITE,
+                D3D12_STATIC_BORDER_COLOR_TRANSPARENT_BLACK, D3D12_STATIC_SAMPLER_DESC,
+                D3D12_TEXTURE_ADDRESS_MODE, D3D12_TEXTURE_ADDRESS_MODE_BORDER,
+                D3D12_TEXTURE_ADDRESS_MODE_CLAMP, D3D12_TEXTURE_ADDRESS_MODE_MIRROR,
+                D3D12_TEXTURE_ADDRESS_MODE_MIRROR_ONCE, D3D12_TEXTURE_ADDRESS_MODE_WRAP,
+                D3D12_VERSIONED_ROOT_SIGNATURE_DESC, D3D12_VERSIONED_ROOT_SIGNATURE_DESC_0,
+                D3D_ROOT_SIGNATURE_VERSION_1_0, D3D_ROOT_SIGNATURE_VERSION_1_1,
+            },
+        },
+    },
+};
+
+use super::{
+    AddressMode, BorderColor, ComparisonFunc, DescriptorRange, DescriptorType, Filter,
+    RootParameter, RootSignatureDesc, RootSignatureVersion, ShaderVisibility, StaticSampler,
+};
+
+/// The newest root signature version the device supports.
+pub fn highest_root_signature_version(device: &ID3D12Device) -> RootSignatureVersion {
+    let mut data = D3D12_FEATURE_DATA_ROOT_SIGNATURE {
+        HighestVersion: D3D_ROOT_SIGNATURE_VERSION_1_1,
+    };
+    let supported = unsafe {
+        device.CheckFeatureSupport(
+            D3D12_FEATURE_ROOT_SIGNATURE,
+            &mut data as *mut _ as _,
+            std::mem::size_of_val(&data) as u32,
+        )
+    };
+
+    // Runtimes that don't know about 1.1 fail the query.
+    if supported.is_ok() && data.HighestVersion.0 >= D3D_ROOT_SIGNATURE_VERSION_1_1.0 {
+        RootSignatureVersion::V1_1
+    } else {
+        RootSignatureVersion::V1_0
+    }
+}
+
+pub fn serialize_root_signature(
+    desc: &RootSignatureDesc,
+    version: RootSignatureVersion,
+) -> windows::core::Result<ID3DBlob> {
+    if version == RootSignatureVersion::V1_0 && desc.requires_version_1_1() {
+        return Err(Error::new(
+            E_INVALIDARG,
+            "root signature uses flags that need version 1.1",
+        ));
+    }
+
+    let static_samplers: Vec<D3D12_STATIC_SAMPLER_DESC> =
+        desc.static_samplers.iter().map(static_sampler).collect();
+    let flags = D3D12_ROOT_SIGNATURE_FLAGS(desc.flags.bits() as i32);
+
+    // The ranges have to stay put until serialization is done, since the
+    // parameters point into them.
+    let mut blob: Option<ID3DBlob> = None;
+    let mut error: Option<ID3DBlob> = None;
+    let result = match version {
+        RootSignatureVersion::V1_0 => {
+            let ranges: Vec<Vec<D3D12_DESCRIPTOR_RANGE>> = desc
+                .parameters
+                .iter()
+                .map(|parameter| match parameter {
+                    RootParameter::DescriptorTable { ranges, .. } => {
+                        ranges.iter().map(descriptor_range_1_0).collect()
+                    }
+                    _ => Vec::new(),
+                })
+                .collect();
+            let parameters: Vec<D3D12_ROOT_PARAMETER> = desc
+                .parameters
+                .iter()
+                .zip(&ranges)
+                .map(|(parameter, ranges)| root_parameter_1_0(parameter, ranges))
+                .collect();
+
+            let versioned = D3D12_VERSIONED_ROOT_SIGNATURE_DESC {
+                Version: D3D_ROOT_SIGNATURE_VERSION_1_0,
+                Anonymous: D3D12_VERSIONED_ROOT_SIGNATURE_DESC_0 {
+                    Desc_1_0: D3D12_ROOT_SIGNATURE_DESC {
+                        NumParameters: parameters.len() as u32,
+                        pParameters: parameters.as_ptr(),
+                        NumStaticSamplers: static_samplers.len() as u32,
+                        pStaticSamplers: static_samplers.as_ptr(),
+                        Flags: flags,
+                    },
+                },
+            };
+            unsafe { D3D12SerializeVersionedRootSignature(&versioned, &mut blob, Some(&mut error)) }
+        }
+        RootSignatureVersion::V1_1 => {
+            let ranges: Vec<Vec<D3D12_DESCRIPTOR_RANGE1>> = desc
+                .parameters
+                .iter()
+                .map(|parameter| match parameter {
+                    RootParameter::DescriptorTable { ranges, .. } => {
+                        ranges.iter().map(descriptor_range_1_1).collect()
+                    }
+                    _ => Vec::new(),
+                })
+                .collect();
+            let parameters: Vec<D3D12_ROOT_PARAMETER1> = desc
+                .parameters
+                .iter()
+                .zip(&ranges)
+                .map(|(parameter, ranges)| root_parameter_1_1(parameter, ranges))
+                .collect();
+
+            let versioned = D3D12_VERSIONED_ROOT_SIGNATURE_DESC {
+                Version: D3D_ROOT_SIGNATURE_VERSION_1_1,
+                Anonymous: D3D12_VERSIONED_ROOT_SIGNATURE_DESC_0 {
+                    Desc_1_1: D3D12_ROOT_SIGNATURE_DESC1 {
+                        NumParameters: parameters.len() as u32,
+                        pParameters: parameters.as_ptr(),
+                        NumStaticSamplers: static_samplers.len() as u32,
+                        pStaticSamplers: static_samplers.as_ptr(),
+                        Flags: flags,
+                    },
+                },
+            };
+            unsafe { D3D12SerializeVersionedRootSignature(&versioned, &mut blob, Some(&mut error)) }
+        }
+    };
+
+    if let Err(e) = result {
+        return Err(match error {
+            Some(error) => Error::new(e.code(), blob_to_string(&error)),
+            None => e,
+        });
+    }
+
+    blob.ok_or_else(Error::empty)
+}
+
+pub fn create_root_signature(
+    device: &ID3D12Device,
+    desc: &RootSignatureDesc,
+    version: RootSignatureVersion,
+) -> windows::core::Result<ID3D12RootSignature> {
+    let blob = serialize_root_signature(desc, version)?;
+    let bytes = unsafe {
+        std::slice::from_raw_parts(blob.GetBufferPointer() as *const u8, blob.GetBufferSize())
+    };
+    unsafe { device.CreateRootSignature(0, bytes) }
+}
+
+fn blob_to_string(blob: &ID3DBlob) -> String {
+    let bytes = unsafe {
+        std::slice::from_raw_parts(blob.GetBufferPointer() as *const u8, blob.GetBufferSize())
+    };
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+fn root_parameter_1_0(
+    parameter: &RootParameter,
+    ranges: &[D3D12_DESCRIPTOR_RANGE],
+) -> D3D12_ROOT_PARAMETER {
+    match *parameter {
+        RootParameter::Constants {
+            count,
+            register,
+            space,
+            visibility,
+        } => D3D12_ROOT_PARAMETER {
+            ParameterType: D3D12_ROOT_PARAMETER_TYPE_32BIT_CONSTANTS,
+            Anonymous: D3D12_ROOT_PARAMETER_0 {
+                Constants: D3D12_ROOT_CONSTANTS {
+                    ShaderRegister: register,
+                    RegisterSpace: space,
+                    Num32BitValues: count,
+                },
+            },
+            ShaderVisibility: shader_visibility(visibility),
+        },
+        RootParameter::Descriptor {
+            descriptor_type,
+            register,
+            space,
+            visibility,
+            ..
+        } => D3D12_ROOT_PARAMETER {
+            ParameterType: root_parameter_type(descriptor_type),
+            Anonymous: D3D12_ROOT_PARAMETER_0 {
+                Descriptor: D3D12_ROOT_DESCRIPTOR {
+                    ShaderRegister: register,
+                    RegisterSpace: space,
+                },
+            },
+            ShaderVisibility: shader_visibility(visibility),
+        },
+        RootParameter::DescriptorTable { visibility, .. } => D3D12_ROOT_PARAMETER {
+            ParameterType: D3D12_ROOT_PARAMETER_TYPE_DESCRIPTOR_TABLE,
+            Anonymous: D3D12_ROOT_PARAMETER_0 {
+                DescriptorTable: D3D12_ROOT_DESCRIPTOR_TABLE {
+                    NumDescriptorRanges: ranges.len() as u32,
+                    pDescriptorRanges: ranges.as_ptr(),
+                },
+            },
+            ShaderVisibility: shader_visibility(visibility),
+        },
+    }
+}
+
+fn root_parameter_1_1(
+    parameter: &RootParameter,
+    ranges: &[D3D12_DESCRIPTOR_RANGE1],
+) -> D3D12_ROOT_PARAMETER1 {
+    match *parameter {
+        RootParameter::Constants {
+            count,
+            register,
+            space,
+            visibility,
+        } => D3D12_ROOT_PARAMETER1 {
+            ParameterType: D3D12_ROOT_PARAMETER_TYPE_32BIT_CONSTANTS,
+            Anonymous: D3D12_ROOT_PARAMETER1_0 {
+                Constants: D3D12_ROOT_CONSTANTS {
+                    ShaderRegister: register,
+                    RegisterSpace: space,
+                    Num32BitValues: count,
+                },
+            },
+            ShaderVisibility: shader_visibility(visibility),
+        },
+        RootParameter::Descriptor {
+            descriptor_type,
+            register,
+            space,
+            flags,
+            visibility,
+        } => D3D12_ROOT_PARAMETER1 {
+            ParameterType: root_parameter_type(descriptor_type),
+            Anonymous: D3D12_ROOT_PARAMETER1_0 {
+                Descriptor: D3D12_ROOT_DESCRIPTOR1 {
+                    ShaderRegister: register,
+                    RegisterSpace: space,
+                    Flags: D3D12_ROOT_DESCRIPTOR_FLAGS(flags.bits() as i32),
+                },
+            },
+            ShaderVisibility: shader_visibility(visibility),
+        },
+        RootParameter::DescriptorTable { visibility, .. } => D3D12_ROOT_PARAMETER1 {
+            ParameterType: D3D12_ROOT_PARAMETER_TYPE_DESCRIPTOR_TABLE,
+            Anonymous: D3D12_ROOT_PARAMETER1_0 {
+                DescriptorTable: D3D12_ROOT_DESCRIPTOR_TABLE1 {
+                    NumDescriptorRanges: ranges.len() as u32,
+                    pDescriptorRanges: ranges.as_ptr(),
+                },
+            },
+            ShaderVisibility: shader_visibility(visibility),
+        },
+    }
+}
+
+fn descriptor_range_1_0(range: &DescriptorRange) -> D3D12_DESCRIPTOR_RANGE {
+    D3D12_DESCRIPTOR_RANGE {
+        RangeType: descriptor_range_type(range.range_type),
+        NumDescriptors: range.count,
+        BaseShaderRegister: range.base_register,
+        RegisterSpace: range.space,
+        OffsetInDescriptorsFromTableStart: range
+            .offset
+            .unwrap_or(D3D12_DESCRIPTOR_RANGE_OFFSET_APPEND),
+    }
+}
+
+fn descriptor_range_1_1(range: &DescriptorRange) -> D3D12_DESCRIPTOR_RANGE1 {
+    D3D12_DESCRIPTOR_RANGE1 {
+        RangeType: descriptor_range_type(range.range_type),
+        NumDescriptors: range.count,
+        BaseShaderRegister: range.base_register,
+        RegisterSpace: range.space,
+        Flags: D3D12_DESCRIPTOR_RANGE_FLAGS(range.flags.bits() as i32),
+        OffsetInDescriptorsFromTableStart: range
+            .offset
+            .unwrap_or(D3D12_DESCRIPTOR_RANGE_OFFSET_APPEND),
+    }
+}
+
+fn static_sampler(sampler: &StaticSampler) -> D3D12_STATIC_SAMPLER_DESC {
+    D3D12_STATIC_SAMPLER_DESC {
+        Filter: filter(sampler.filter),
+        AddressU: address_mode(sampler.address_u),
+        AddressV: address_mode(sampler.address_v),
+        AddressW: address_mode(sampler.address_w),
+        MipLODBias: sampler.mip_lod_bias,
+        MaxAnisotropy: sampler.max_anisotropy,
+        ComparisonFunc: comparison_func(sampler.comparison),
+        BorderColor: border_color(sampler.border_color),
+        MinLOD: sampler.min_lod,
+        MaxLOD: sampler.max_lod,
+        ShaderRegister: sampler.register,
+        RegisterSpace: sampler.space,
+        ShaderVisibility: shader_visibility(sampler.visibility),
+    }
+}
+
+fn root_parameter_type(descriptor_type: DescriptorType) -> D3D12_ROOT_PARAMETER_TYPE {
+    match descriptor_type {
+        DescriptorType::Cbv => D3D12_ROOT_PARAMETER_TYPE_CBV,
+        DescriptorType::Srv => D3D12_ROOT_PARAMETER_TYPE_SRV,
+        DescriptorType::Uav => D3D12_ROOT_PARAMETER_TYPE_UAV,
+        DescriptorType::Sampler => {
+            unreachable!("validated root signatures have no sampler root descriptors")
+        }
+    }
+}
+
+fn descriptor_range_type(descriptor_type: DescriptorType) -> D3D12_DESCRIPTOR_RANGE_TYPE {
+    match descriptor_type {
+        DescriptorType::Cbv => D3D12_DESCRIPTOR_RANGE_TYPE_CBV,
+        DescriptorType::Srv => D3D12_DESCRIPTOR_RANGE_TYPE_SRV,
+        DescriptorType::Uav => D3D12_DESCRIPTOR_RANGE_TYPE_UAV,
+        DescriptorType::Sampler => D3D12_DESCRIPTOR_RANGE_TYPE_SAMPLER,
+    }
+}
+
+fn shader_visibility(visibility: ShaderVisibility) -> D3D12_SHADER_VISIBILITY {
+    match visibility {
+        ShaderVisibility::All => D3D12_SHADER_VISIBILITY_ALL,
+        ShaderVisibility::Vertex => D3D12_SHADER_VISIBILITY_VERTEX,
+        ShaderVisibility::Hull => D3D12_SHADER_VISIBILITY_HULL,
+        ShaderVisibility::Domain => D3D12_SHADER_VISIBILITY_DOMAIN,
+        ShaderVisibility::Geometry => D3D12_SHADER_VISIBILITY_GEOMETRY,
+        ShaderVisibility::Pixel => D3D12_SHADER_VISIBILITY_PIXEL,
+        ShaderVisibility::Amplification => D3D12_SHADER_VISIBILITY_AMPLIFICATION,
+        ShaderVisibility::Mesh => D3D12_SHADER_VISIBILITY_MESH,
+    }
+}
+
+fn filter(filter: Filter) -> D3D12_FILTER {
+    match filter {
+        Filter::Point => D3D12_FILTER_MIN_MAG_MIP_POINT,
+        Filter::MinMagLinearMipPoint => D3D12_FILTER_MIN_MAG_LINEAR_MIP_POINT,
+        Filter::Linear => D3D12_FILTER_MIN_MAG_MIP_LINEAR,
+        Filter::Anisotropic => D3D12_FILTER_ANISOTROPIC,
+        Filter::ComparisonPoint => D3D12_FILTER_COMPARISON_MIN_MAG_MIP_POINT,
+        Filter::ComparisonLinear => D3D12_FILTER_COMPARISON_MIN_MAG_MIP_LINEAR,
+        Filter::ComparisonAnisotropic => D3D12_FILTER_COMPARISON_ANISOTROPIC,
+    }
+}
+
+fn address_mode(mode: AddressMode) -> D3D12_TEXTURE_ADDRESS_MODE {
+    match mode {
+        AddressMode::Wrap => D3D12_TEXTURE_ADDRESS_MODE_WRAP,
+        AddressMode::Mirror => D3D12_TEXTURE_ADDRESS_MODE_MIRROR,
+        AddressMode::Clamp => D3D12_TEXTURE_ADDRESS_MODE_CLAMP,
+        AddressMode::Border => D3D12_TEXTURE_ADDRESS_MODE_BORDER,
+        AddressMode::MirrorOnce => D3D12_TEXTURE_ADDRESS_MODE_MIRROR_ONCE,
+    }
+}
+
+pub(crate) fn comparison_func(func: ComparisonFunc) -> D3D12_COMPARISON_FUNC {
+    match func {
+        ComparisonFunc::Never => D3D12_COMPARISON_FUNC_NEVER,
+        ComparisonFunc::Less => D3D12_COMPARISON_FUNC_LESS,
+        ComparisonFunc::Equal => D3D12_COMPARISON_FUNC_EQUAL,
+        ComparisonFunc::LessEqual => D3D12_COMPARISON_FUNC_LESS_EQUAL,
+        ComparisonFunc::Greater => D3D12_COMPARISON_FUNC_GREATER,
+        ComparisonFunc::NotEqual => D3D12_COMPARISON_FUNC_NOT_EQUAL,
+        ComparisonFunc::GreaterEqual => D3D12_COMPARISON_FUNC_GREATER_EQUAL,
+        ComparisonFunc::Always => D3D12_COMPARISON_FUNC_ALWAYS,
+    }
+}
+
+fn border_color(color: BorderColor) -> D3D12_STATIC_BORDER_COLOR {
+    match color {
+        BorderColor::TransparentBlack => D3D12_STATIC_BORDER_COLOR_TRANSPARENT_BLACK,
+        BorderColor::OpaqueBlack => D3D12_STATIC_BORDER_COLOR_OPAQUE_BLACK,
+        BorderColor::OpaqueWhite => D3D12_STATIC_BORDER_COLOR_OPAQUE_WHITE,
+    }
+}
@@ -0,0 +1,483 @@
+// Two-level segregated fit allocator that manages offsets into a block of
+// memory it never touches, which makes it usable for GPU heaps.
+
+const SL_BITS: u32 = 5;
+const SL_COUNT: usize = 1 << SL_BITS;
+const FL_COUNT: usize = 64 - SL_BITS as usize + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TlsfAllocation {
+    pub offset: u64,
+    pub size: u64,
+    block: u32,
+    generation: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TlsfStats {
+    pub capacity: u64,
+    pub used: u64,
+    pub allocation_count: usize,
+    pub free_block_count: usize,
+    pub largest_free_block: u64,
+}
+
+impl TlsfStats {
+    pub fn free(&self) -> u64 {
+        self.capacity - self.used
+    }
+
+    /// 0.0 when all free memory is one contiguous block, approaching 1.0 as
+    /// it gets split into many small blocks.
+    pub fn fragmentation(&self) -> f32 {
+        let free = self.free();
+        if free == 0 {
+            0.0
+        } else {
+            1.0 - self.largest_free_block as f32 / free as f32
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Block {
+    offset: u64,
+    size: u64,
+    free: bool,
+    /// Bumped whenever the block is handed out, so a stale handle to a block
+    /// that's since been reallocated doesn't match it.
+    generation: u32,
+    prev_physical: Option<u32>,
+    next_physical: Option<u32>,
+    prev_free: Option<u32>,
+    next_free: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct TlsfAllocator {
+    capacity: u64,
+    granularity: u64,
+    blocks: Vec<Block>,
+    unused_blocks: Vec<u32>,
+    fl_bitmap: u64,
+    sl_bitmaps: [u32; FL_COUNT],
+    free_heads: [[Option<u32>; SL_COUNT]; FL_COUNT],
+    used: u64,
+    allocation_count: usize,
+}
+
+fn mapping_insert(size: u64) -> (usize, usize) {
+    if size < SL_COUNT as u64 {
+        (0, size as usize)
+    } else {
+        let fl = 63 - size.leading_zeros();
+        let sl = (size >> (fl - SL_BITS)) as usize ^ SL_COUNT;
+        ((fl - SL_BITS + 1) as usize, sl)
+    }
+}
+
+// Rounds the size up to the next list boundary so that every block in the
+// returned list is large enough.
+fn mapping_search(size: u64) -> (usize, usize) {
+    if size < SL_COUNT as u64 {
+        mapping_insert(size)
+    } else {
+        let fl = 63 - size.leading_zeros();
+        mapping_insert(size.saturating_add((1 << (fl - SL_BITS)) - 1))
+    }
+}
+
+impl TlsfAllocator {
+    /// Every offset and size handed out is a multiple of `granularity`.
+    pub fn new(capacity: u64, granularity: u64) -> Self {
+        assert!(
+            granularity.is_power_of_two(),
+            "granularity must be a power of two"
+        );
+        assert!(
+            capacity > 0 && capacity.is_multiple_of(granularity),
+            "capacity must be a non-zero multiple of the granularity"
+        );
+
+        let mut allocator = Self {
+            capacity,
+            granularity,
+            blocks: Vec::new(),
+            unused_blocks: Vec::new(),
+            fl_bitmap: 0,
+            sl_bitmaps: [0; FL_COUNT],
+            free_heads: [[None; SL_COUNT]; FL_COUNT],
+            used: 0,
+            allocation_count: 0,
+        };
+
+        let block = allocator.new_block(Block {
+            offset: 0,
+            size: capacity,
+            free: true,
+            generation: 0,
+            prev_physical: None,
+            next_physical: None,
+            prev_free: None,
+            next_free: None,
+        });
+        allocator.insert_free(block);
+
+        allocator
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allocation_count == 0
+    }
+
+    pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<TlsfAllocation> {
+        assert!(size > 0, "cannot allocate zero bytes");
+        assert!(
+            alignment.is_power_of_two(),
+            "alignment must be a power of two"
+        );
+
+        let size = size.checked_next_multiple_of(self.granularity)?;
+        let alignment = alignment.max(self.granularity);
+
+        if size > self.capacity {
+            return None;
+        }
+
+        // Try for a block of exactly the right size first; if its alignment
+        // doesn't work out, look for one with room for worst case padding.
+        let block = match self.find_free(size) {
+            Some(block) if self.fits(block, size, alignment) => block,
+            _ => {
+                let padded = size.checked_add(alignment - self.granularity)?;
+                self.find_free(padded)?
+            }
+        };
+
+        self.remove_free(block);
+
+        let offset = self.blocks[block as usize].offset;
+        let aligned = offset.next_multiple_of(alignment);
+        let block = if aligned > offset {
+            let (front, back) = self.split(block, aligned - offset);
+            self.insert_free(front);
+            back
+        } else {
+            block
+        };
+
+        if self.blocks[block as usize].size > size {
+            let (_, back) = self.split(block, size);
+            self.insert_free(back);
+        }
+
+        let b = &mut self.blocks[block as usize];
+        b.free = false;
+        b.generation = b.generation.wrapping_add(1);
+        let generation = b.generation;
+        self.used += size;
+        self.allocation_count += 1;
+
+        Some(TlsfAllocation {
+            offset: aligned,
+            size,
+            block,
+            generation,
+        })
+    }
+
+    pub fn free(&mut self, allocation: TlsfAllocation) {
+        let mut block = allocation.block;
+        {
+            let b = &self.blocks[block as usize];
+            assert!(
+                !b.free
+                    && b.generation == allocation.generation
+                    && b.offset == allocation.offset
+                    && b.size == allocation.size,
+                "allocation does not belong to this allocator or was already freed"
+            );
+        }
+
+        self.used -= allocation.size;
+        self.allocation_count -= 1;
+
+        if let Some(prev) = self.blocks[block as usize].prev_physical {
+            if self.blocks[prev as usize].free {
+                self.remove_free(prev);
+                block = self.merge(prev, block);
+            }
+        }
+
+        if let Some(next) = self.blocks[block as usize].next_physical {
+            if self.blocks[next as usize].free {
+                self.remove_free(next);
+                block = self.merge(block, next);
+            }
+        }
+
+        self.insert_free(block);
+    }
+
+    pub fn stats(&self) -> TlsfStats {
+        let mut stats = TlsfStats {
+            capacity: self.capacity,
+            used: self.used,
+            allocation_count: self.allocation_count,
+            ..Default::default()
+        };
+
+        for heads in &self.free_heads {
+            for head in heads {
+                let mut next = *head;
+                while let Some(block) = next {
+                    let b = &self.blocks[block as usize];
+                    stats.free_block_count += 1;
+                    stats.largest_free_block = stats.largest_free_block.max(b.size);
+                    next = b.next_free;
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// Walks every internal structure checking the allocator's invariants.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut expected_offset = 0;
+        let mut prev: Option<u32> = None;
+        let mut next = Some(self.first_block());
+        let mut free_blocks = 0;
+        let mut used = 0;
+
+        while let Some(block) = next {
+            let b = &self.blocks[block as usize];
+            if b.offset != expected_offset {
+                return Err(format!(
+                    "block {block} starts at {} not {expected_offset}",
+                    b.offset
+                ));
+            }
+            if b.size == 0 || !b.size.is_multiple_of(self.granularity) {
+                return Err(format!("block {block} has invalid size {}", b.size));
+            }
+            if b.prev_physical != prev {
+                return Err(format!("block {block} has a broken physical link"));
+            }
+            if b.free {
+                free_blocks += 1;
+                if prev.is_some_and(|p| self.blocks[p as usize].free) {
+                    return Err(format!(
+                        "free block {block} was not merged with its neighbour"
+                    ));
+                }
+                let (fl, sl) = mapping_insert(b.size);
+                if !self.list_contains(fl, sl, block) {
+                    return Err(format!(
+                        "free block {block} is missing from list ({fl}, {sl})"
+                    ));
+                }
+            } else {
+                used += b.size;
+            }
+
+            expected_offset += b.size;
+            prev = Some(block);
+            next = b.next_physical;
+        }
+
+        if expected_offset != self.capacity {
+            return Err(format!(
+                "blocks cover {expected_offset} of {} bytes",
+                self.capacity
+            ));
+        }
+        if used != self.used {
+            return Err(format!("{used} bytes allocated but {} recorded", self.used));
+        }
+
+        let mut listed = 0;
+        for fl in 0..FL_COUNT {
+            for sl in 0..SL_COUNT {
+                let has_blocks = self.free_heads[fl][sl].is_some();
+                if has_blocks != (self.sl_bitmaps[fl] & (1 << sl) != 0) {
+                    return Err(format!("second level bitmap out of sync at ({fl}, {sl})"));
+                }
+
+                let mut next = self.free_heads[fl][sl];
+                while let Some(block) = next {
+                    listed += 1;
+                    next = self.blocks[block as usize].next_free;
+                }
+            }
+            if (self.sl_bitmaps[fl] != 0) != (self.fl_bitmap & (1 << fl) != 0) {
+                return Err(format!("first level bitmap out of sync at {fl}"));
+            }
+        }
+
+        if listed != free_blocks {
+            return Err(format!(
+                "{listed} blocks in free lists but {free_blocks} free blocks"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn first_block(&self) -> u32 {
+        // Splits and merges always keep the front block's index, so the block
+        // at offset zero is the one created up front.
+        0
+    }
+
+    fn list_contains(&self, fl: usize, sl: usize, block: u32) -> bool {
+        let mut next = self.free_heads[fl][sl];
+        while let Some(b) = next {
+            if b == block {
+                return true;
+            }
+            next = self.blocks[b as usize].next_free;
+        }
+        false
+    }
+
+    fn fits(&self, block: u32, size: u64, alignment: u64) -> bool {
+        let b = &self.blocks[block as usize];
+        b.offset.next_multiple_of(alignment) + size <= b.offset + b.size
+    }
+
+    fn find_free(&self, size: u64) -> Option<u32> {
+        let (mut fl, sl) = mapping_search(size);
+        if fl >= FL_COUNT {
+            return None;
+        }
+
+        let mut sl_map = self.sl_bitmaps[fl] & (!0u32 << sl);
+        if sl_map == 0 {
+            let fl_map = self.fl_bitmap & (!0u64).checked_shl(fl as u32 + 1).unwrap_or(0);
+            if fl_map == 0 {
+                return None;
+            }
+            fl = fl_map.trailing_zeros() as usize;
+            sl_map = self.sl_bitmaps[fl];
+        }
+
+        self.free_heads[fl][sl_map.trailing_zeros() as usize]
+    }
+
+    fn new_block(&mut self, block: Block) -> u32 {
+        if let Some(index) = self.unused_blocks.pop() {
+            let generation = self.blocks[index as usize].generation;
+            self.blocks[index as usize] = Block {
+                generation,
+                ..block
+            };
+            index
+        } else {
+            self.blocks.push(block);
+            (self.blocks.len() - 1) as u32
+        }
+    }
+
+    fn insert_free(&mut self, block: u32) {
+        let (fl, sl) = mapping_insert(self.blocks[block as usize].size);
+        let head = self.free_heads[fl][sl];
+
+        let b = &mut self.blocks[block as usize];
+        b.free = true;
+        b.prev_free = None;
+        b.next_free = head;
+
+        if let Some(head) = head {
+            self.blocks[head as usize].prev_free = Some(block);
+        }
+
+        self.free_heads[fl][sl] = Some(block);
+        self.fl_bitmap |= 1 << fl;
+        self.sl_bitmaps[fl] |= 1 << sl;
+    }
+
+    fn remove_free(&mut self, block: u32) {
+        let (fl, sl) = mapping_insert(self.blocks[block as usize].size);
+        let Block {
+            prev_free,
+            next_free,
+            ..
+        } = self.blocks[block as usize];
+
+        if let Some(prev) = prev_free {
+            self.blocks[prev as usize].next_free = next_free;
+        } else {
+            self.free_heads[fl][sl] = next_free;
+        }
+
+        if let Some(next) = next_free {
+            self.blocks[next as usize].prev_free = prev_free;
+        }
+
+        if self.free_heads[fl][sl].is_none() {
+            self.sl_bitmaps[fl] &= !(1 << sl);
+            if self.sl_bitmaps[fl] == 0 {
+                self.fl_bitmap &= !(1 << fl);
+            }
+        }
+
+        let b = &mut self.blocks[block as usize];
+        b.free = false;
+        b.prev_free = None;
+        b.next_free = None;
+    }
+
+    // Splits a block that is not in any free list into two, returning both.
+    fn split(&mut self, block: u32, size: u64) -> (u32, u32) {
+        let b = self.blocks[block as usize];
+        debug_assert!(size < b.size);
+
+        let back = self.new_block(Block {
+            offset: b.offset + size,
+            size: b.size - size,
+            free: false,
+            generation: 0,
+            prev_physical: Some(block),
+            next_physical: b.next_physical,
+            prev_free: None,
+            next_free: None,
+        });
+
+        if let Some(next) = b.next_physical {
+            self.blocks[next as usize].prev_physical = Some(back);
+        }
+
+        let front = &mut self.blocks[block as usize];
+        front.size = size;
+        front.next_physical = Some(back);
+
+        (block, back)
+    }
+
+    // Absorbs `back` into `front`; neither may be in a free list.
+    fn merge(&mut self, front: u32, back: u32) -> u32 {
+        let b = self.blocks[back as usize];
+
+        if let Some(next) = b.next_physical {
+            self.blocks[next as usize].prev_physical = Some(front);
+        }
+
+        let f = &mut self.blocks[front as usize];
+        f.size += b.size;
+        f.next_physical = b.next_physical;
+
+        // A size no allocation has, so freeing `back` again is caught.
+        let dead = &mut self.blocks[back as usize];
+        dead.size = 0;
+        dead.next_physical = None;
+        dead.prev_physical = None;
+        self.unused_blocks.push(back);
+
+        front
+    }
+}
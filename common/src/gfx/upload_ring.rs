@@ -0,0 +1,122 @@
+use windows::Win32::Graphics::Direct3D12::{
+    ID3D12Device, ID3D12Resource, D3D12_HEAP_FLAG_NONE, D3D12_HEAP_TYPE_UPLOAD, D3D12_RANGE,
+    D3D12_RESOURCE_STATE_GENERIC_READ,
+};
+
+use super::{
+    buffer_resource_desc, heap_properties,
+    ring_allocator::{align_up, RingAllocation, RingAllocator, CONSTANT_BUFFER_ALIGNMENT},
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct UploadAllocation {
+    pub cpu_ptr: *mut u8,
+    pub gpu_address: u64,
+    pub offset: u64,
+    pub size: u64,
+}
+
+/// A persistently mapped UPLOAD heap buffer for streaming per-frame data such
+/// as constants, vertices and texture rows to the GPU.
+pub struct UploadRing {
+    buffer: ID3D12Resource,
+    cpu_base: *mut u8,
+    gpu_base: u64,
+    allocator: RingAllocator,
+}
+
+impl UploadRing {
+    pub fn new(device: &ID3D12Device, size: u64) -> windows::core::Result<Self> {
+        let allocator = RingAllocator::new(size);
+
+        let mut buffer: Option<ID3D12Resource> = None;
+        unsafe {
+            device.CreateCommittedResource(
+                &heap_properties(D3D12_HEAP_TYPE_UPLOAD),
+                D3D12_HEAP_FLAG_NONE,
+                &buffer_resource_desc(size),
+                D3D12_RESOURCE_STATE_GENERIC_READ,
+                None,
+                &mut buffer,
+            )
+        }?;
+        let buffer = buffer.ok_or_else(windows::core::Error::empty)?;
+
+        // We never read from this resource on the CPU.
+        let read_range = D3D12_RANGE { Begin: 0, End: 0 };
+        let mut cpu_base = std::ptr::null_mut();
+        unsafe { buffer.Map(0, Some(&read_range), Some(&mut cpu_base)) }?;
+
+        let gpu_base = unsafe { buffer.GetGPUVirtualAddress() };
+
+        Ok(Self {
+            buffer,
+            cpu_base: cpu_base as *mut u8,
+            gpu_base,
+            allocator,
+        })
+    }
+
+    pub fn resource(&self) -> &ID3D12Resource {
+        &self.buffer
+    }
+
+    pub fn allocator(&self) -> &RingAllocator {
+        &self.allocator
+    }
+
+    pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<UploadAllocation> {
+        let RingAllocation { offset, size } = self.allocator.allocate(size, alignment)?;
+
+        Some(UploadAllocation {
+            cpu_ptr: unsafe { self.cpu_base.add(offset as usize) },
+            gpu_address: self.gpu_base + offset,
+            offset,
+            size,
+        })
+    }
+
+    pub fn upload<T: Copy>(&mut self, data: &[T], alignment: u64) -> Option<UploadAllocation> {
+        let size = std::mem::size_of_val(data);
+        let allocation = self.allocate(size as u64, alignment)?;
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr() as *const u8, allocation.cpu_ptr, size);
+        }
+
+        Some(allocation)
+    }
+
+    pub fn upload_constants<T: Copy>(&mut self, constants: &T) -> Option<UploadAllocation> {
+        // Constant buffer views must cover a multiple of 256 bytes.
+        let size = std::mem::size_of::<T>();
+        let allocation = self.allocate(
+            align_up(size as u64, CONSTANT_BUFFER_ALIGNMENT),
+            CONSTANT_BUFFER_ALIGNMENT,
+        )?;
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                constants as *const T as *const u8,
+                allocation.cpu_ptr,
+                size,
+            );
+        }
+
+        Some(allocation)
+    }
+
+    pub fn finish_frame(&mut self, fence_value: u64) {
+        self.allocator.finish_frame(fence_value);
+    }
+
+    pub fn reclaim(&mut self, completed_fence_value: u64) {
+        self.allocator.reclaim(completed_fence_value);
+    }
+}
+
+impl Drop for UploadRing {
+    fn drop(&mut self) {
+        unsafe { self.buffer.Unmap(0, None) };
+    }
+}
@@ -0,0 +1,325 @@
+// Golden-image tests. A sample's integration test hands its binary to
+// `GoldenTest::run`, which renders a few frames headless on WARP with
+// `--screenshot` and compares the last one against the reference PNG checked
+// in next to the sample. Set `UPDATE_GOLDEN=1` to write new references
+// instead. The comparison itself is plain pixel maths.
+
+use std::{
+    env,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{app::DEFAULT_HEADLESS_FRAMES, screenshot::Screenshot};
+
+/// How far an image may drift from its reference. Alpha is ignored, since
+/// it's the color that ends up on screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// Channel differences up to this don't count.
+    pub channel_delta: u8,
+    /// The share of pixels, in percent, that may differ by more.
+    pub max_differing_percent: f64,
+    /// The lowest acceptable peak signal-to-noise ratio, in dB.
+    pub min_psnr: f64,
+}
+
+impl Tolerance {
+    pub const EXACT: Self = Self {
+        channel_delta: 0,
+        max_differing_percent: 0.0,
+        min_psnr: f64::INFINITY,
+    };
+}
+
+/// Allows the rounding differences between WARP versions.
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            channel_delta: 2,
+            max_differing_percent: 0.1,
+            min_psnr: 40.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Comparison {
+    /// The largest difference in any channel.
+    pub max_delta: u8,
+    /// Pixels with a channel further off than the tolerance allows.
+    pub differing_pixels: u64,
+    pub total_pixels: u64,
+    /// Infinite for identical images.
+    pub psnr: f64,
+}
+
+impl Comparison {
+    pub fn differing_percent(&self) -> f64 {
+        match self.total_pixels {
+            0 => 0.0,
+            total => self.differing_pixels as f64 * 100.0 / total as f64,
+        }
+    }
+
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        self.differing_percent() <= tolerance.max_differing_percent
+            && self.psnr >= tolerance.min_psnr
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "max delta {}, {:.3}% of pixels differ, PSNR {:.2} dB",
+            self.max_delta,
+            self.differing_percent(),
+            self.psnr
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum GoldenError {
+    SizeMismatch {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    MissingReference(PathBuf),
+    Mismatch {
+        comparison: Comparison,
+        tolerance: Tolerance,
+        diff: PathBuf,
+    },
+    SampleFailed {
+        status: String,
+        stderr: String,
+    },
+    Io(Box<dyn Error>),
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SizeMismatch { expected, actual } => write!(
+                f,
+                "expected a {}x{} image, got {}x{}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+            Self::MissingReference(path) => write!(
+                f,
+                "{} doesn't exist, run with UPDATE_GOLDEN=1 to create it",
+                path.display()
+            ),
+            Self::Mismatch {
+                comparison,
+                tolerance,
+                diff,
+            } => write!(
+                f,
+                "{comparison}, allowed {}% and {} dB; see {}",
+                tolerance.max_differing_percent,
+                tolerance.min_psnr,
+                diff.display()
+            ),
+            Self::SampleFailed { status, stderr } => {
+                write!(f, "the sample exited with {status}: {stderr}")
+            }
+            Self::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for GoldenError {}
+
+fn check_size(expected: &Screenshot, actual: &Screenshot) -> Result<(), GoldenError> {
+    let (expected, actual) = (
+        (expected.width, expected.height),
+        (actual.width, actual.height),
+    );
+    if expected != actual {
+        return Err(GoldenError::SizeMismatch { expected, actual });
+    }
+    Ok(())
+}
+
+/// The largest RGB difference of each pixel.
+fn pixel_deltas<'a>(
+    expected: &'a Screenshot,
+    actual: &'a Screenshot,
+) -> impl Iterator<Item = [u8; 3]> + 'a {
+    expected
+        .pixels
+        .chunks_exact(4)
+        .zip(actual.pixels.chunks_exact(4))
+        .map(|(e, a)| std::array::from_fn(|i| e[i].abs_diff(a[i])))
+}
+
+pub fn compare(
+    expected: &Screenshot,
+    actual: &Screenshot,
+    channel_delta: u8,
+) -> Result<Comparison, GoldenError> {
+    check_size(expected, actual)?;
+
+    let mut max_delta = 0;
+    let mut differing_pixels = 0;
+    let mut squared_error = 0u64;
+    for deltas in pixel_deltas(expected, actual) {
+        let delta = deltas.into_iter().max().unwrap();
+        max_delta = max_delta.max(delta);
+        differing_pixels += (delta > channel_delta) as u64;
+        squared_error += deltas.iter().map(|&d| d as u64 * d as u64).sum::<u64>();
+    }
+
+    let total_pixels = expected.width as u64 * expected.height as u64;
+    let psnr = match squared_error {
+        0 => f64::INFINITY,
+        _ => {
+            let mse = squared_error as f64 / (total_pixels * 3) as f64;
+            10.0 * (255.0 * 255.0 / mse).log10()
+        }
+    };
+
+    Ok(Comparison {
+        max_delta,
+        differing_pixels,
+        total_pixels,
+        psnr,
+    })
+}
+
+/// Pixels within `channel_delta` are a dim grey copy of the reference, and
+/// the rest are red, brighter the further off they are.
+pub fn diff_image(
+    expected: &Screenshot,
+    actual: &Screenshot,
+    channel_delta: u8,
+) -> Result<Screenshot, GoldenError> {
+    check_size(expected, actual)?;
+
+    let pixels = pixel_deltas(expected, actual)
+        .zip(expected.pixels.chunks_exact(4))
+        .flat_map(|(deltas, rgba)| {
+            let delta = deltas.into_iter().max().unwrap();
+            if delta > channel_delta {
+                [128 + delta / 2, 0, 0, 255]
+            } else {
+                let luma = (rgba[0] as u32 * 54 + rgba[1] as u32 * 183 + rgba[2] as u32 * 19) >> 8;
+                let dim = (luma / 4) as u8;
+                [dim, dim, dim, 255]
+            }
+        })
+        .collect();
+    Ok(Screenshot::new(expected.width, expected.height, pixels))
+}
+
+/// One sample's reference image and where to put what a run produced.
+#[derive(Clone, Debug)]
+pub struct GoldenTest {
+    pub name: String,
+    pub reference_dir: PathBuf,
+    /// Where the rendered frame and any diff image go.
+    pub output_dir: PathBuf,
+    pub frames: u64,
+    pub tolerance: Tolerance,
+    /// Overwrite the reference instead of comparing. Defaults to whether
+    /// `UPDATE_GOLDEN` is set.
+    pub update: bool,
+}
+
+impl GoldenTest {
+    pub fn new(reference_dir: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            reference_dir: reference_dir.into(),
+            output_dir: env::temp_dir().join("golden"),
+            frames: DEFAULT_HEADLESS_FRAMES,
+            tolerance: Tolerance::default(),
+            update: env::var_os("UPDATE_GOLDEN").is_some(),
+        }
+    }
+
+    pub fn output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = output_dir.into();
+        self
+    }
+
+    pub fn frames(mut self, frames: u64) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    pub fn tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn reference_path(&self) -> PathBuf {
+        self.reference_dir.join(format!("{}.png", self.name))
+    }
+
+    pub fn actual_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}.actual.png", self.name))
+    }
+
+    pub fn diff_path(&self) -> PathBuf {
+        self.output_dir.join(format!("{}.diff.png", self.name))
+    }
+
+    /// Renders with the sample binary at `exe`, then `check`s the result.
+    pub fn run(&self, exe: &Path) -> Result<Comparison, GoldenError> {
+        fs::create_dir_all(&self.output_dir).map_err(|e| GoldenError::Io(e.into()))?;
+        let actual_path = self.actual_path();
+
+        let output = Command::new(exe)
+            .arg("--headless")
+            .arg("-warp")
+            .arg(format!("--frames={}", self.frames))
+            .arg(format!("--screenshot={}", actual_path.display()))
+            .output()
+            .map_err(|e| GoldenError::Io(e.into()))?;
+        if !output.status.success() {
+            return Err(GoldenError::SampleFailed {
+                status: output.status.to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+
+        self.check(&Screenshot::load(&actual_path).map_err(GoldenError::Io)?)
+    }
+
+    /// Compares `actual` against the reference, writing a diff image next
+    /// to the output when they're too far apart.
+    pub fn check(&self, actual: &Screenshot) -> Result<Comparison, GoldenError> {
+        let reference_path = self.reference_path();
+        if self.update {
+            fs::create_dir_all(&self.reference_dir).map_err(|e| GoldenError::Io(e.into()))?;
+            actual.save(&reference_path).map_err(GoldenError::Io)?;
+            return compare(actual, actual, self.tolerance.channel_delta);
+        }
+        if !reference_path.exists() {
+            return Err(GoldenError::MissingReference(reference_path));
+        }
+
+        let expected = Screenshot::load(&reference_path).map_err(GoldenError::Io)?;
+        let comparison = compare(&expected, actual, self.tolerance.channel_delta)?;
+        if comparison.passes(&self.tolerance) {
+            return Ok(comparison);
+        }
+
+        let diff = self.diff_path();
+        fs::create_dir_all(&self.output_dir).map_err(|e| GoldenError::Io(e.into()))?;
+        diff_image(&expected, actual, self.tolerance.channel_delta)?
+            .save(&diff)
+            .map_err(GoldenError::Io)?;
+        Err(GoldenError::Mismatch {
+            comparison,
+            tolerance: self.tolerance,
+            diff,
+        })
+    }
+}
@@ -0,0 +1,181 @@
+// Keyboard and mouse state, built up from window events once per frame.
+// `os::App` translates window messages into `InputEvent`s; anything else,
+// like a test, can feed `InputState::handle` directly.
+
+use std::collections::HashSet;
+
+use crate::math::Vec2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[rustfmt::skip]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M,
+    N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Left, Right, Up, Down,
+    Space, Enter, Escape, Tab, Backspace,
+    Shift, Control, Alt,
+}
+
+impl Key {
+    #[rustfmt::skip]
+    const LETTERS: [Key; 26] = [
+        Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K,
+        Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V,
+        Key::W, Key::X, Key::Y, Key::Z,
+    ];
+    #[rustfmt::skip]
+    const DIGITS: [Key; 10] = [
+        Key::Digit0, Key::Digit1, Key::Digit2, Key::Digit3, Key::Digit4,
+        Key::Digit5, Key::Digit6, Key::Digit7, Key::Digit8, Key::Digit9,
+    ];
+    #[rustfmt::skip]
+    const FUNCTION_KEYS: [Key; 12] = [
+        Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
+        Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+    ];
+
+    /// The key for an ASCII letter or digit, in either case.
+    pub fn from_char(c: char) -> Option<Key> {
+        match c.to_ascii_uppercase() {
+            c @ 'A'..='Z' => Some(Self::LETTERS[c as usize - 'A' as usize]),
+            c @ '0'..='9' => Some(Self::DIGITS[c as usize - '0' as usize]),
+            _ => None,
+        }
+    }
+
+    /// `F1` for 1, up to `F12`.
+    pub fn function(n: u32) -> Option<Key> {
+        Self::FUNCTION_KEYS.get(n.checked_sub(1)? as usize).copied()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    KeyDown(Key),
+    KeyUp(Key),
+    ButtonDown(MouseButton),
+    ButtonUp(MouseButton),
+    /// The cursor position in client pixels, y down.
+    MouseMove(Vec2),
+    /// Wheel notches, positive away from the user.
+    Wheel(f32),
+    /// The window lost focus, so release notifications won't arrive.
+    FocusLost,
+}
+
+/// What's held now, plus what changed since `begin_frame`.
+#[derive(Clone, Debug, Default)]
+pub struct InputState {
+    keys_down: HashSet<Key>,
+    keys_pressed: HashSet<Key>,
+    keys_released: HashSet<Key>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    mouse_position: Option<Vec2>,
+    mouse_delta: Vec2,
+    wheel_delta: f32,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets this frame's presses, releases and movement. Held keys and
+    /// buttons stay held.
+    pub fn begin_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = Vec2::ZERO;
+        self.wheel_delta = 0.0;
+    }
+
+    pub fn handle(&mut self, event: InputEvent) {
+        match event {
+            // Auto-repeat sends more downs without ups.
+            InputEvent::KeyDown(key) => {
+                if self.keys_down.insert(key) {
+                    self.keys_pressed.insert(key);
+                }
+            }
+            InputEvent::KeyUp(key) => {
+                if self.keys_down.remove(&key) {
+                    self.keys_released.insert(key);
+                }
+            }
+            InputEvent::ButtonDown(button) => {
+                if self.buttons_down.insert(button) {
+                    self.buttons_pressed.insert(button);
+                }
+            }
+            InputEvent::ButtonUp(button) => {
+                if self.buttons_down.remove(&button) {
+                    self.buttons_released.insert(button);
+                }
+            }
+            InputEvent::MouseMove(position) => {
+                if let Some(last) = self.mouse_position {
+                    self.mouse_delta += position - last;
+                }
+                self.mouse_position = Some(position);
+            }
+            InputEvent::Wheel(notches) => self.wheel_delta += notches,
+            InputEvent::FocusLost => {
+                self.keys_released.extend(self.keys_down.drain());
+                self.buttons_released.extend(self.buttons_down.drain());
+                self.mouse_position = None;
+            }
+        }
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    /// Whether `key` went down this frame.
+    pub fn was_key_pressed(&self, key: Key) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn was_key_released(&self, key: Key) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn was_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn was_button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// `None` until the cursor has moved over the window.
+    pub fn mouse_position(&self) -> Option<Vec2> {
+        self.mouse_position
+    }
+
+    /// How far the cursor moved this frame, in pixels.
+    pub fn mouse_delta(&self) -> Vec2 {
+        self.mouse_delta
+    }
+
+    pub fn wheel_delta(&self) -> f32 {
+        self.wheel_delta
+    }
+}
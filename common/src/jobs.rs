@@ -0,0 +1,326 @@
+use std::{
+    any::Any,
+    collections::VecDeque,
+    marker::PhantomData,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    thread::JoinHandle,
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    shutdown: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    condvar: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        // Jobs never run while the lock is held, so it can't be poisoned by them.
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, job: Job) {
+        self.lock().jobs.push_back(job);
+        self.condvar.notify_all();
+    }
+
+    // Runs queued jobs on the calling thread until the scope has no work left,
+    // so waiting threads (and nested scopes on workers) never stall the pool.
+    fn help_until_done(&self, state: &ScopeState) {
+        let mut queue = self.lock();
+        loop {
+            if state.pending.load(Ordering::Acquire) == 0 {
+                return;
+            }
+
+            if let Some(job) = queue.jobs.pop_front() {
+                drop(queue);
+                job();
+                queue = self.lock();
+            } else {
+                queue = self.condvar.wait(queue).unwrap_or_else(|e| e.into_inner());
+            }
+        }
+    }
+}
+
+/// A fixed pool of worker threads running jobs spawned inside scopes.
+pub struct JobSystem {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl JobSystem {
+    /// With zero workers every job runs on the thread waiting for its scope.
+    pub fn new(worker_count: usize) -> Self {
+        let shared = Arc::new(Shared::default());
+
+        let workers = (0..worker_count)
+            .map(|i| {
+                let shared = Arc::clone(&shared);
+                std::thread::Builder::new()
+                    .name(format!("worker {i}"))
+                    .spawn(move || worker_main(&shared))
+                    .expect("failed to spawn worker thread")
+            })
+            .collect();
+
+        Self { shared, workers }
+    }
+
+    /// One worker per logical core, leaving one for the calling thread.
+    pub fn with_available_parallelism() -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::new(cores.saturating_sub(1).max(1))
+    }
+
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Runs `f`, which may spawn jobs borrowing from the enclosing stack frame,
+    /// and returns once all of them have finished. A panic in any job is
+    /// resumed on the calling thread.
+    pub fn scope<'env, F, R>(&self, f: F) -> R
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R,
+    {
+        let scope = Scope {
+            shared: Arc::clone(&self.shared),
+            state: Arc::new(ScopeState::default()),
+            _scope: PhantomData,
+            _env: PhantomData,
+        };
+
+        let result = catch_unwind(AssertUnwindSafe(|| f(&scope)));
+
+        // Jobs borrow from the caller, so they must finish even when `f` panics.
+        self.shared.help_until_done(&scope.state);
+
+        if let Some(payload) = scope.state.take_panic() {
+            resume_unwind(payload);
+        }
+
+        match result {
+            Ok(result) => result,
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+
+    /// Calls `f` for every item, splitting the slice into chunks across the
+    /// workers.
+    pub fn parallel_for<T, F>(&self, items: &mut [T], f: F)
+    where
+        T: Send,
+        F: Fn(usize, &mut T) + Sync,
+    {
+        let batches = (self.worker_count() + 1) * 4;
+        let chunk_size = items.len().div_ceil(batches).max(1);
+        let f = &f;
+
+        self.scope(|s| {
+            for (chunk_index, chunk) in items.chunks_mut(chunk_size).enumerate() {
+                s.spawn(move |_| {
+                    for (i, item) in chunk.iter_mut().enumerate() {
+                        f(chunk_index * chunk_size + i, item);
+                    }
+                });
+            }
+        });
+    }
+
+    /// Runs every task in the graph, each one only after all of its
+    /// dependencies have finished.
+    pub fn run(&self, graph: TaskGraph<'_>) {
+        let nodes = graph.nodes;
+
+        // Find the roots up front; once tasks start running, other counts drop
+        // to zero and those tasks get spawned by their last dependency.
+        let roots: Vec<usize> = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.remaining.load(Ordering::Relaxed) == 0)
+            .map(|(index, _)| index)
+            .collect();
+
+        self.scope(|s| {
+            let nodes = &nodes;
+            for index in roots {
+                s.spawn(move |s| run_node(s, nodes, index));
+            }
+        });
+    }
+}
+
+impl Drop for JobSystem {
+    fn drop(&mut self) {
+        self.shared.lock().shutdown = true;
+        self.shared.condvar.notify_all();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker_main(shared: &Shared) {
+    let mut queue = shared.lock();
+    loop {
+        if let Some(job) = queue.jobs.pop_front() {
+            drop(queue);
+            job();
+            queue = shared.lock();
+        } else if queue.shutdown {
+            return;
+        } else {
+            queue = shared
+                .condvar
+                .wait(queue)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+#[derive(Default)]
+struct ScopeState {
+    pending: AtomicUsize,
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+impl ScopeState {
+    fn take_panic(&self) -> Option<Box<dyn Any + Send>> {
+        self.panic.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+pub struct Scope<'scope, 'env: 'scope> {
+    shared: Arc<Shared>,
+    state: Arc<ScopeState>,
+    _scope: PhantomData<&'scope mut &'scope ()>,
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope, 'env> Scope<'scope, 'env> {
+    pub fn spawn<F>(&'scope self, f: F)
+    where
+        F: FnOnce(&'scope Scope<'scope, 'env>) + Send + 'scope,
+    {
+        self.state.pending.fetch_add(1, Ordering::AcqRel);
+
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            if let Err(payload) = catch_unwind(AssertUnwindSafe(|| f(self))) {
+                let mut panic = self.state.panic.lock().unwrap_or_else(|e| e.into_inner());
+                panic.get_or_insert(payload);
+            }
+
+            // Keep the shared state alive past the decrement; once pending hits
+            // zero the scope, and `self` with it, may be gone.
+            let shared = Arc::clone(&self.shared);
+            let state = Arc::clone(&self.state);
+            if state.pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+                let _queue = shared.lock();
+                shared.condvar.notify_all();
+            }
+        });
+
+        // SAFETY: `JobSystem::scope` doesn't return until every job spawned in
+        // the scope has run, so nothing the job borrows can go away first.
+        let job: Job = unsafe { std::mem::transmute(job) };
+
+        self.shared.push(job);
+    }
+}
+
+struct Node<'env> {
+    work: Mutex<Option<Box<dyn FnOnce() + Send + 'env>>>,
+    remaining: AtomicUsize,
+    dependents: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TaskId(usize);
+
+/// Tasks can only depend on tasks added before them, so the graph is always
+/// acyclic.
+#[derive(Default)]
+pub struct TaskGraph<'env> {
+    nodes: Vec<Node<'env>>,
+}
+
+impl<'env> TaskGraph<'env> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn add(&mut self, f: impl FnOnce() + Send + 'env) -> TaskId {
+        self.add_with_dependencies(&[], f)
+    }
+
+    pub fn add_with_dependencies(
+        &mut self,
+        dependencies: &[TaskId],
+        f: impl FnOnce() + Send + 'env,
+    ) -> TaskId {
+        let id = self.nodes.len();
+
+        let mut dependencies = dependencies.to_vec();
+        dependencies.sort_unstable_by_key(|d| d.0);
+        dependencies.dedup();
+
+        for dependency in &dependencies {
+            assert!(
+                dependency.0 < id,
+                "task depends on a task from another graph"
+            );
+            self.nodes[dependency.0].dependents.push(id);
+        }
+
+        self.nodes.push(Node {
+            work: Mutex::new(Some(Box::new(f))),
+            remaining: AtomicUsize::new(dependencies.len()),
+            dependents: Vec::new(),
+        });
+
+        TaskId(id)
+    }
+}
+
+fn run_node<'scope, 'env, 'tasks: 'scope>(
+    scope: &'scope Scope<'scope, 'env>,
+    nodes: &'scope [Node<'tasks>],
+    index: usize,
+) {
+    let node = &nodes[index];
+    let work = node
+        .work
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+        .expect("task ran twice");
+    work();
+
+    for &dependent in &node.dependents {
+        if nodes[dependent].remaining.fetch_sub(1, Ordering::AcqRel) == 1 {
+            scope.spawn(move |s| run_node(s, nodes, dependent));
+        }
+    }
+}
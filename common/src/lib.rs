@@ -1,3 +1,14 @@
+pub mod app;
+pub mod assets;
+pub mod camera;
 pub mod gfx;
+pub mod golden;
+pub mod hash;
+pub mod input;
+pub mod jobs;
+pub mod math;
 pub mod os;
+pub mod screenshot;
+pub mod shader;
+pub mod time;
 pub mod util;
@@ -0,0 +1,19 @@
+// Vectors, matrices and quaternions for transforms and cameras, and the
+// color encodings textures are stored in.
+//
+// Conventions follow D3D: coordinates are left-handed (+x right, +y up, +z
+// into the screen) and clip-space depth runs from 0 to 1. Vectors are
+// columns, so `projection * view * model * v` transforms model space to clip
+// space, and `Mat4` is stored column by column. That's what HLSL expects for
+// a `float4x4` with the default `column_major` packing used with
+// `mul(matrix, vector)`; see `Mat4::to_row_major` for the other way around.
+
+mod color;
+mod matrix;
+mod quat;
+mod vector;
+
+pub use color::{f16_to_f32, f32_to_f16, linear_to_srgb, srgb_to_linear};
+pub use matrix::Mat4;
+pub use quat::Quat;
+pub use vector::{Vec2, Vec3, Vec4};
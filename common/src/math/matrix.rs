@@ -0,0 +1,343 @@
+use std::ops::{Mul, MulAssign};
+
+use super::{Quat, Vec3, Vec4};
+use crate::gfx::cbuffer::{HlslType, HlslTypeDesc};
+
+/// A 4x4 matrix stored as columns. `cols[3]` holds the translation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct Mat4 {
+    pub cols: [Vec4; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Self = Self::from_cols(Vec4::X, Vec4::Y, Vec4::Z, Vec4::W);
+    pub const ZERO: Self = Self::from_cols(Vec4::ZERO, Vec4::ZERO, Vec4::ZERO, Vec4::ZERO);
+
+    pub const fn from_cols(x: Vec4, y: Vec4, z: Vec4, w: Vec4) -> Self {
+        Self { cols: [x, y, z, w] }
+    }
+
+    pub const fn from_column_major(m: [[f32; 4]; 4]) -> Self {
+        let [x, y, z, w] = m;
+        Self::from_cols(
+            Vec4::new(x[0], x[1], x[2], x[3]),
+            Vec4::new(y[0], y[1], y[2], y[3]),
+            Vec4::new(z[0], z[1], z[2], z[3]),
+            Vec4::new(w[0], w[1], w[2], w[3]),
+        )
+    }
+
+    /// Rows as written on paper, so `m[0][3]` is the x translation.
+    pub fn from_row_major(m: [[f32; 4]; 4]) -> Self {
+        Self::from_column_major(m).transpose()
+    }
+
+    /// The layout HLSL reads a default (`column_major`) `float4x4` from.
+    pub const fn to_column_major(&self) -> [[f32; 4]; 4] {
+        let [x, y, z, w] = self.cols;
+        [x.to_array(), y.to_array(), z.to_array(), w.to_array()]
+    }
+
+    /// The layout for a `row_major float4x4`, or for DirectXMath-style
+    /// shaders that multiply `mul(vector, matrix)` with default packing.
+    pub fn to_row_major(&self) -> [[f32; 4]; 4] {
+        self.transpose().to_column_major()
+    }
+
+    pub const fn col(&self, index: usize) -> Vec4 {
+        self.cols[index]
+    }
+
+    pub fn row(&self, index: usize) -> Vec4 {
+        let [x, y, z, w] = self.cols.map(|col| col.to_array()[index]);
+        Vec4::new(x, y, z, w)
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self::from_cols(self.row(0), self.row(1), self.row(2), self.row(3))
+    }
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self::from_cols(Vec4::X, Vec4::Y, Vec4::Z, translation.extend(1.0))
+    }
+
+    pub fn from_scale(scale: Vec3) -> Self {
+        Self::from_cols(
+            Vec4::X * scale.x,
+            Vec4::Y * scale.y,
+            Vec4::Z * scale.z,
+            Vec4::W,
+        )
+    }
+
+    pub fn from_rotation_x(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::from_cols(
+            Vec4::X,
+            Vec4::new(0.0, cos, sin, 0.0),
+            Vec4::new(0.0, -sin, cos, 0.0),
+            Vec4::W,
+        )
+    }
+
+    pub fn from_rotation_y(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::from_cols(
+            Vec4::new(cos, 0.0, -sin, 0.0),
+            Vec4::Y,
+            Vec4::new(sin, 0.0, cos, 0.0),
+            Vec4::W,
+        )
+    }
+
+    pub fn from_rotation_z(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::from_cols(
+            Vec4::new(cos, sin, 0.0, 0.0),
+            Vec4::new(-sin, cos, 0.0, 0.0),
+            Vec4::Z,
+            Vec4::W,
+        )
+    }
+
+    /// `rotation` must be normalized.
+    pub fn from_quat(rotation: Quat) -> Self {
+        let Quat { x, y, z, w } = rotation;
+        let (x2, y2, z2) = (x + x, y + y, z + z);
+        let (xx, yy, zz) = (x * x2, y * y2, z * z2);
+        let (xy, xz, yz) = (x * y2, x * z2, y * z2);
+        let (wx, wy, wz) = (w * x2, w * y2, w * z2);
+        Self::from_cols(
+            Vec4::new(1.0 - (yy + zz), xy + wz, xz - wy, 0.0),
+            Vec4::new(xy - wz, 1.0 - (xx + zz), yz + wx, 0.0),
+            Vec4::new(xz + wy, yz - wx, 1.0 - (xx + yy), 0.0),
+            Vec4::W,
+        )
+    }
+
+    /// Scales, then rotates, then translates.
+    pub fn from_scale_rotation_translation(scale: Vec3, rotation: Quat, translation: Vec3) -> Self {
+        let [x, y, z, _] = Self::from_quat(rotation).cols;
+        Self::from_cols(
+            x * scale.x,
+            y * scale.y,
+            z * scale.z,
+            translation.extend(1.0),
+        )
+    }
+
+    /// A left-handed view matrix for a camera at `eye` looking at `target`,
+    /// like `XMMatrixLookAtLH`. The camera looks down +z in view space.
+    pub fn look_at_lh(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        Self::look_to_lh(eye, target - eye, up)
+    }
+
+    /// `look_at_lh` with a direction instead of a target.
+    pub fn look_to_lh(eye: Vec3, direction: Vec3, up: Vec3) -> Self {
+        let forward = direction.normalize();
+        let right = up.cross(forward).normalize();
+        let up = forward.cross(right);
+        Self::from_cols(
+            Vec4::new(right.x, up.x, forward.x, 0.0),
+            Vec4::new(right.y, up.y, forward.y, 0.0),
+            Vec4::new(right.z, up.z, forward.z, 0.0),
+            Vec4::new(-right.dot(eye), -up.dot(eye), -forward.dot(eye), 1.0),
+        )
+    }
+
+    /// Maps `near` to depth 0 and `far` to depth 1, like
+    /// `XMMatrixPerspectiveFovLH`. `fov_y` is in radians.
+    pub fn perspective_lh(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let (x, y) = focal_lengths(fov_y, aspect);
+        let range = far / (far - near);
+        Self::from_cols(
+            Vec4::new(x, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, y, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, range, 1.0),
+            Vec4::new(0.0, 0.0, -range * near, 0.0),
+        )
+    }
+
+    /// Maps `near` to depth 1 and `far` to depth 0. Floating-point depth has
+    /// the most precision near 0, so reversing the range spreads it much more
+    /// evenly; use with a `GreaterEqual` depth test and a clear to 0.
+    pub fn perspective_reversed_z_lh(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let (x, y) = focal_lengths(fov_y, aspect);
+        let range = near / (near - far);
+        Self::from_cols(
+            Vec4::new(x, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, y, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, range, 1.0),
+            Vec4::new(0.0, 0.0, -range * far, 0.0),
+        )
+    }
+
+    /// Reversed-Z with the far plane at infinity: depth is `near / z`.
+    pub fn perspective_infinite_reversed_z_lh(fov_y: f32, aspect: f32, near: f32) -> Self {
+        let (x, y) = focal_lengths(fov_y, aspect);
+        Self::from_cols(
+            Vec4::new(x, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, y, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+            Vec4::new(0.0, 0.0, near, 0.0),
+        )
+    }
+
+    /// Maps the box to x and y in -1..1 and depth 0..1, like
+    /// `XMMatrixOrthographicOffCenterLH`.
+    pub fn orthographic_lh(
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        near: f32,
+        far: f32,
+    ) -> Self {
+        let width = 1.0 / (right - left);
+        let height = 1.0 / (top - bottom);
+        let depth = 1.0 / (far - near);
+        Self::from_cols(
+            Vec4::new(2.0 * width, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 * height, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, depth, 0.0),
+            Vec4::new(
+                -(left + right) * width,
+                -(top + bottom) * height,
+                -near * depth,
+                1.0,
+            ),
+        )
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let (s, c) = self.minors();
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    /// `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det.abs() <= f32::MIN_POSITIVE {
+            return None;
+        }
+
+        let (s, c) = self.minors();
+        let [[a00, a01, a02, a03], [a10, a11, a12, a13], [a20, a21, a22, a23], [a30, a31, a32, a33]] =
+            self.to_column_major();
+        let inv = 1.0 / det;
+        Some(Self::from_column_major([
+            [
+                (a11 * c[5] - a12 * c[4] + a13 * c[3]) * inv,
+                (-a01 * c[5] + a02 * c[4] - a03 * c[3]) * inv,
+                (a31 * s[5] - a32 * s[4] + a33 * s[3]) * inv,
+                (-a21 * s[5] + a22 * s[4] - a23 * s[3]) * inv,
+            ],
+            [
+                (-a10 * c[5] + a12 * c[2] - a13 * c[1]) * inv,
+                (a00 * c[5] - a02 * c[2] + a03 * c[1]) * inv,
+                (-a30 * s[5] + a32 * s[2] - a33 * s[1]) * inv,
+                (a20 * s[5] - a22 * s[2] + a23 * s[1]) * inv,
+            ],
+            [
+                (a10 * c[4] - a11 * c[2] + a13 * c[0]) * inv,
+                (-a00 * c[4] + a01 * c[2] - a03 * c[0]) * inv,
+                (a30 * s[4] - a31 * s[2] + a33 * s[0]) * inv,
+                (-a20 * s[4] + a21 * s[2] - a23 * s[0]) * inv,
+            ],
+            [
+                (-a10 * c[3] + a11 * c[1] - a12 * c[0]) * inv,
+                (a00 * c[3] - a01 * c[1] + a02 * c[0]) * inv,
+                (-a30 * s[3] + a31 * s[1] - a32 * s[0]) * inv,
+                (a20 * s[3] - a21 * s[1] + a22 * s[0]) * inv,
+            ],
+        ]))
+    }
+
+    /// The 2x2 determinants of the first two and of the last two columns,
+    /// which the determinant and the inverse are both built from.
+    fn minors(&self) -> ([f32; 6], [f32; 6]) {
+        let [[a00, a01, a02, a03], [a10, a11, a12, a13], [a20, a21, a22, a23], [a30, a31, a32, a33]] =
+            self.to_column_major();
+        (
+            [
+                a00 * a11 - a10 * a01,
+                a00 * a12 - a10 * a02,
+                a00 * a13 - a10 * a03,
+                a01 * a12 - a11 * a02,
+                a01 * a13 - a11 * a03,
+                a02 * a13 - a12 * a03,
+            ],
+            [
+                a20 * a31 - a30 * a21,
+                a20 * a32 - a30 * a22,
+                a20 * a33 - a30 * a23,
+                a21 * a32 - a31 * a22,
+                a21 * a33 - a31 * a23,
+                a22 * a33 - a32 * a23,
+            ],
+        )
+    }
+
+    /// Transforms a position, ignoring the bottom row.
+    pub fn transform_point3(&self, point: Vec3) -> Vec3 {
+        (*self * point.extend(1.0)).truncate()
+    }
+
+    /// Transforms a position and divides by w, for projections.
+    pub fn project_point3(&self, point: Vec3) -> Vec3 {
+        let v = *self * point.extend(1.0);
+        v.truncate() / v.w
+    }
+
+    /// Transforms a direction, which translation doesn't affect.
+    pub fn transform_vector3(&self, vector: Vec3) -> Vec3 {
+        (*self * vector.extend(0.0)).truncate()
+    }
+
+    pub fn abs_diff_eq(&self, other: &Self, epsilon: f32) -> bool {
+        (0..4).all(|i| self.cols[i].abs_diff_eq(other.cols[i], epsilon))
+    }
+}
+
+fn focal_lengths(fov_y: f32, aspect: f32) -> (f32, f32) {
+    let y = 1.0 / (fov_y * 0.5).tan();
+    (y / aspect, y)
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Uploaded as is, this is right for HLSL's default `column_major` packing.
+impl HlslType for Mat4 {
+    const TYPE: HlslTypeDesc = HlslTypeDesc::aligned("float4x4", 64);
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+
+    fn mul(self, v: Vec4) -> Vec4 {
+        let [x, y, z, w] = self.cols;
+        x * v.x + y * v.y + z * v.z + w * v.w
+    }
+}
+
+/// `a * b` applies `b` first.
+impl Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self {
+            cols: other.cols.map(|col| self * col),
+        }
+    }
+}
+
+impl MulAssign for Mat4 {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
@@ -0,0 +1,153 @@
+use std::ops::{Mul, MulAssign};
+
+use super::Vec3;
+use crate::gfx::cbuffer::{HlslType, HlslTypeDesc};
+
+/// A rotation, as `x, y, z` = axis * sin(angle / 2) and `w` = cos(angle / 2).
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quat {
+    pub const IDENTITY: Self = Self::new(0.0, 0.0, 0.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    /// `axis` must be normalized. Positive angles turn clockwise when looking
+    /// from the tip of the axis toward the origin, as in D3D.
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Self {
+        let (sin, cos) = (angle * 0.5).sin_cos();
+        let v = axis * sin;
+        Self::new(v.x, v.y, v.z, cos)
+    }
+
+    pub fn from_rotation_x(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::X, angle)
+    }
+
+    pub fn from_rotation_y(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::Y, angle)
+    }
+
+    pub fn from_rotation_z(angle: f32) -> Self {
+        Self::from_axis_angle(Vec3::Z, angle)
+    }
+
+    /// Roll about z, then pitch about x, then yaw about y, like
+    /// `XMQuaternionRotationRollPitchYaw`.
+    pub fn from_yaw_pitch_roll(yaw: f32, pitch: f32, roll: f32) -> Self {
+        Self::from_rotation_y(yaw) * Self::from_rotation_x(pitch) * Self::from_rotation_z(roll)
+    }
+
+    fn vector(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Self {
+        let scale = 1.0 / self.length();
+        Self::new(
+            self.x * scale,
+            self.y * scale,
+            self.z * scale,
+            self.w * scale,
+        )
+    }
+
+    pub fn conjugate(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    /// The opposite rotation, assuming `self` is normalized.
+    pub fn inverse(self) -> Self {
+        self.conjugate()
+    }
+
+    /// Interpolates along the shorter arc at constant angular speed.
+    pub fn slerp(self, other: Self, t: f32) -> Self {
+        let mut cos = self.dot(other);
+        let mut end = other;
+        if cos < 0.0 {
+            cos = -cos;
+            end = Self::new(-other.x, -other.y, -other.z, -other.w);
+        }
+
+        let (from, to) = if cos > 0.9995 {
+            // Nearly parallel, where sin(angle) is too small to divide by.
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Self::new(
+            self.x * from + end.x * to,
+            self.y * from + end.y * to,
+            self.z * from + end.z * to,
+            self.w * from + end.w * to,
+        )
+        .normalize()
+    }
+
+    /// Whether both rotate the same way; `q` and `-q` are the same rotation.
+    pub fn abs_diff_eq(self, other: Self, epsilon: f32) -> bool {
+        let close = |a: Self, b: Self| {
+            (a.x - b.x).abs() <= epsilon
+                && (a.y - b.y).abs() <= epsilon
+                && (a.z - b.z).abs() <= epsilon
+                && (a.w - b.w).abs() <= epsilon
+        };
+        close(self, other) || close(self, Self::new(-other.x, -other.y, -other.z, -other.w))
+    }
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl HlslType for Quat {
+    const TYPE: HlslTypeDesc = HlslTypeDesc::packed("float4", 16);
+}
+
+/// `a * b` rotates by `b`, then by `a`.
+impl Mul for Quat {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let (a, b) = (self.vector(), other.vector());
+        let v = b * self.w + a * other.w + a.cross(b);
+        Self::new(v.x, v.y, v.z, self.w * other.w - a.dot(b))
+    }
+}
+
+impl MulAssign for Quat {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+
+    fn mul(self, v: Vec3) -> Vec3 {
+        let q = self.vector();
+        let t = q.cross(v) * 2.0;
+        v + t * self.w + q.cross(t)
+    }
+}
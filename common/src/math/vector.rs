@@ -0,0 +1,219 @@
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use crate::gfx::cbuffer::{HlslType, HlslTypeDesc};
+
+macro_rules! vector {
+    ($name:ident { $($field:ident),+ }, $len:literal, $hlsl:literal) => {
+        #[derive(Clone, Copy, Debug, Default, PartialEq)]
+        #[repr(C)]
+        pub struct $name {
+            $(pub $field: f32),+
+        }
+
+        impl $name {
+            pub const ZERO: Self = Self::splat(0.0);
+            pub const ONE: Self = Self::splat(1.0);
+
+            pub const fn new($($field: f32),+) -> Self {
+                Self { $($field),+ }
+            }
+
+            pub const fn splat(value: f32) -> Self {
+                Self { $($field: value),+ }
+            }
+
+            pub fn dot(self, other: Self) -> f32 {
+                0.0 $(+ self.$field * other.$field)+
+            }
+
+            pub fn length_squared(self) -> f32 {
+                self.dot(self)
+            }
+
+            pub fn length(self) -> f32 {
+                self.length_squared().sqrt()
+            }
+
+            pub fn distance(self, other: Self) -> f32 {
+                (self - other).length()
+            }
+
+            /// The vector scaled to length 1. Zero vectors give NaNs.
+            pub fn normalize(self) -> Self {
+                self / self.length()
+            }
+
+            pub fn normalize_or_zero(self) -> Self {
+                let length = self.length();
+                if length > f32::EPSILON {
+                    self / length
+                } else {
+                    Self::ZERO
+                }
+            }
+
+            pub fn lerp(self, other: Self, t: f32) -> Self {
+                self + (other - self) * t
+            }
+
+            pub fn min(self, other: Self) -> Self {
+                Self { $($field: self.$field.min(other.$field)),+ }
+            }
+
+            pub fn max(self, other: Self) -> Self {
+                Self { $($field: self.$field.max(other.$field)),+ }
+            }
+
+            pub fn abs_diff_eq(self, other: Self, epsilon: f32) -> bool {
+                true $(&& (self.$field - other.$field).abs() <= epsilon)+
+            }
+
+            pub const fn to_array(self) -> [f32; $len] {
+                [$(self.$field),+]
+            }
+        }
+
+        impl From<[f32; $len]> for $name {
+            fn from([$($field),+]: [f32; $len]) -> Self {
+                Self { $($field),+ }
+            }
+        }
+
+        impl From<$name> for [f32; $len] {
+            fn from(v: $name) -> Self {
+                v.to_array()
+            }
+        }
+
+        impl HlslType for $name {
+            const TYPE: HlslTypeDesc = HlslTypeDesc::packed($hlsl, $len * 4);
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, other: Self) -> Self {
+                Self { $($field: self.$field + other.$field),+ }
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self {
+                Self { $($field: self.$field - other.$field),+ }
+            }
+        }
+
+        /// Component-wise.
+        impl Mul for $name {
+            type Output = Self;
+
+            fn mul(self, other: Self) -> Self {
+                Self { $($field: self.$field * other.$field),+ }
+            }
+        }
+
+        impl Mul<f32> for $name {
+            type Output = Self;
+
+            fn mul(self, scale: f32) -> Self {
+                Self { $($field: self.$field * scale),+ }
+            }
+        }
+
+        impl Mul<$name> for f32 {
+            type Output = $name;
+
+            fn mul(self, v: $name) -> $name {
+                v * self
+            }
+        }
+
+        impl Div<f32> for $name {
+            type Output = Self;
+
+            fn div(self, divisor: f32) -> Self {
+                Self { $($field: self.$field / divisor),+ }
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self {
+                Self { $($field: -self.$field),+ }
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: Self) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, other: Self) {
+                *self = *self - other;
+            }
+        }
+
+        impl MulAssign<f32> for $name {
+            fn mul_assign(&mut self, scale: f32) {
+                *self = *self * scale;
+            }
+        }
+
+        impl DivAssign<f32> for $name {
+            fn div_assign(&mut self, divisor: f32) {
+                *self = *self / divisor;
+            }
+        }
+    };
+}
+
+vector!(Vec2 { x, y }, 2, "float2");
+vector!(Vec3 { x, y, z }, 3, "float3");
+vector!(Vec4 { x, y, z, w }, 4, "float4");
+
+impl Vec2 {
+    pub const X: Self = Self::new(1.0, 0.0);
+    pub const Y: Self = Self::new(0.0, 1.0);
+
+    pub const fn extend(self, z: f32) -> Vec3 {
+        Vec3::new(self.x, self.y, z)
+    }
+}
+
+impl Vec3 {
+    pub const X: Self = Self::new(1.0, 0.0, 0.0);
+    pub const Y: Self = Self::new(0.0, 1.0, 0.0);
+    pub const Z: Self = Self::new(0.0, 0.0, 1.0);
+
+    pub fn cross(self, other: Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub const fn extend(self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
+
+    pub const fn truncate(self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+}
+
+impl Vec4 {
+    pub const X: Self = Self::new(1.0, 0.0, 0.0, 0.0);
+    pub const Y: Self = Self::new(0.0, 1.0, 0.0, 0.0);
+    pub const Z: Self = Self::new(0.0, 0.0, 1.0, 0.0);
+    pub const W: Self = Self::new(0.0, 0.0, 0.0, 1.0);
+
+    pub const fn truncate(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+}
@@ -0,0 +1,46 @@
+use crate::input::{InputEvent, InputState};
+
+/// Only the size it was asked for, since there's nothing to show.
+pub struct Window {
+    size: (i32, i32),
+}
+
+impl Window {
+    pub fn get_physical_size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    pub fn set_title(&self, _title: &str) {}
+
+    pub fn set_visible(&self, _visible: bool) {}
+}
+
+pub struct App {
+    input: InputState,
+}
+
+impl App {
+    pub fn init(
+        _title: impl Into<String>,
+        window_size: (i32, i32),
+    ) -> Result<(App, Window), Box<dyn std::error::Error>> {
+        let app = App {
+            input: InputState::new(),
+        };
+        Ok((app, Window { size: window_size }))
+    }
+
+    pub fn input(&self) -> &InputState {
+        &self.input
+    }
+
+    /// There's no window to keep open, so this is always false.
+    pub fn run(&mut self) -> bool {
+        self.input.begin_frame();
+        false
+    }
+
+    pub fn pump_messages(&mut self, _events: &mut Vec<InputEvent>) -> bool {
+        false
+    }
+}
@@ -0,0 +1,270 @@
+use windows::{
+    core::{s, PCSTR},
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM},
+        System::LibraryLoader::GetModuleHandleA,
+        UI::{
+            Input::KeyboardAndMouse::{
+                VIRTUAL_KEY, VK_BACK, VK_CONTROL, VK_DOWN, VK_ESCAPE, VK_F1, VK_F12, VK_LEFT,
+                VK_MENU, VK_RETURN, VK_RIGHT, VK_SHIFT, VK_SPACE, VK_TAB, VK_UP,
+            },
+            WindowsAndMessaging::{
+                AdjustWindowRect, CreateWindowExA, DefWindowProcA, DispatchMessageA, GetClientRect,
+                GetWindowLongPtrA, LoadCursorA, PeekMessageA, PostQuitMessage, RegisterClassExA,
+                SetWindowLongPtrA, SetWindowTextA, ShowWindow, TranslateMessage, CREATESTRUCTA,
+                CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, GWLP_USERDATA, IDC_ARROW, MSG, PM_REMOVE,
+                SW_HIDE, SW_SHOW, WHEEL_DELTA, WM_CREATE, WM_DESTROY, WM_KEYDOWN, WM_KEYUP,
+                WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEMOVE,
+                WM_MOUSEWHEEL, WM_QUIT, WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP,
+                WNDCLASSEXA, WS_OVERLAPPEDWINDOW,
+            },
+        },
+    },
+};
+
+use crate::{
+    input::{InputEvent, InputState, Key, MouseButton},
+    math::Vec2,
+    util::{print_debug_string, AsCString},
+};
+
+pub struct Window {
+    hwnd: HWND,
+}
+
+impl Window {
+    fn new(
+        title: impl Into<String>,
+        window_size: (i32, i32),
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let instance = unsafe { GetModuleHandleA(None) }?;
+
+        let class_name = s!("LearnD3D12Class");
+
+        let wc = WNDCLASSEXA {
+            cbSize: std::mem::size_of::<WNDCLASSEXA>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(wndproc),
+            hInstance: instance.into(),
+            hCursor: unsafe { LoadCursorA(None, PCSTR(IDC_ARROW.0 as _)) }?,
+            lpszClassName: class_name,
+            ..Default::default()
+        };
+
+        if unsafe { RegisterClassExA(&wc) } == 0 {
+            panic!("LearnD3D12Class is already registered");
+        }
+
+        let mut window_rect = RECT {
+            left: 0,
+            top: 0,
+            right: window_size.0,
+            bottom: window_size.1,
+        };
+        unsafe { AdjustWindowRect(&mut window_rect, WS_OVERLAPPEDWINDOW, false) }?;
+
+        let title = title.into();
+
+        let hwnd = unsafe {
+            CreateWindowExA(
+                Default::default(),
+                class_name,
+                PCSTR(title.as_c_string().as_ptr() as _),
+                WS_OVERLAPPEDWINDOW,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                window_rect.right - window_rect.left,
+                window_rect.bottom - window_rect.top,
+                None, // No parent window.
+                None, // No menus.
+                instance,
+                None, // No window data.
+            )
+        }?;
+
+        if hwnd == HWND::default() {
+            panic!("failed to create a window handle");
+        }
+
+        Ok(Self { hwnd })
+    }
+
+    fn on_key_down(&mut self) {
+        print_debug_string("WINDOW: key down");
+    }
+
+    pub fn get_handle(&self) -> HWND {
+        self.hwnd
+    }
+
+    pub fn get_physical_size(&self) -> (i32, i32) {
+        let mut window_rect = RECT::default();
+        if let Err(e) = unsafe { GetClientRect(self.hwnd, &mut window_rect) } {
+            print_debug_string(&format!("failed to get client rect {e}"));
+        }
+
+        (
+            window_rect.right - window_rect.left,
+            window_rect.bottom - window_rect.top,
+        )
+    }
+
+    /// For showing `time::FrameStats` and the like.
+    pub fn set_title(&self, title: &str) {
+        let title = title.as_c_string();
+        if let Err(e) = unsafe { SetWindowTextA(self.hwnd, PCSTR(title.as_ptr() as _)) } {
+            print_debug_string(&format!("failed to set window title {e}"));
+        }
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        let show = if visible { SW_SHOW } else { SW_HIDE };
+        let _ = unsafe { ShowWindow(self.hwnd, show) };
+    }
+}
+
+pub struct App {
+    input: InputState,
+}
+
+impl App {
+    pub fn init(
+        title: impl Into<String>,
+        window_size: (i32, i32),
+    ) -> Result<(App, Window), Box<dyn std::error::Error>> {
+        let app = App {
+            input: InputState::new(),
+        };
+
+        let window = Window::new(title, window_size)?;
+        window.set_visible(true);
+
+        Ok((app, window))
+    }
+
+    /// Keyboard and mouse input gathered by the last `run`.
+    pub fn input(&self) -> &InputState {
+        &self.input
+    }
+
+    pub fn run(&mut self) -> bool {
+        self.input.begin_frame();
+
+        let mut events = Vec::new();
+        let running = self.pump_messages(&mut events);
+        for event in events {
+            self.input.handle(event);
+        }
+
+        running
+    }
+
+    /// Dispatches the waiting window messages, collecting the input they
+    /// carry. Returns false once the window has been closed.
+    pub fn pump_messages(&mut self, events: &mut Vec<InputEvent>) -> bool {
+        let mut message = MSG::default();
+        while unsafe { PeekMessageA(&mut message, None, 0, 0, PM_REMOVE).as_bool() } {
+            events.extend(input_event(&message));
+
+            unsafe {
+                let _ = TranslateMessage(&message);
+                DispatchMessageA(&message);
+            }
+
+            if message.message == WM_QUIT {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+fn input_event(message: &MSG) -> Option<InputEvent> {
+    let word = |value: usize, shift: u32| (value >> shift) as u16 as i16 as f32;
+    let (wparam, lparam) = (message.wParam.0, message.lParam.0 as usize);
+
+    let event = match message.message {
+        WM_KEYDOWN | WM_SYSKEYDOWN => InputEvent::KeyDown(key(VIRTUAL_KEY(wparam as u16))?),
+        WM_KEYUP | WM_SYSKEYUP => InputEvent::KeyUp(key(VIRTUAL_KEY(wparam as u16))?),
+        WM_LBUTTONDOWN => InputEvent::ButtonDown(MouseButton::Left),
+        WM_LBUTTONUP => InputEvent::ButtonUp(MouseButton::Left),
+        WM_RBUTTONDOWN => InputEvent::ButtonDown(MouseButton::Right),
+        WM_RBUTTONUP => InputEvent::ButtonUp(MouseButton::Right),
+        WM_MBUTTONDOWN => InputEvent::ButtonDown(MouseButton::Middle),
+        WM_MBUTTONUP => InputEvent::ButtonUp(MouseButton::Middle),
+        WM_MOUSEMOVE => InputEvent::MouseMove(Vec2::new(word(lparam, 0), word(lparam, 16))),
+        WM_MOUSEWHEEL => InputEvent::Wheel(word(wparam, 16) / WHEEL_DELTA as f32),
+        _ => return None,
+    };
+    Some(event)
+}
+
+fn key(vk: VIRTUAL_KEY) -> Option<Key> {
+    // Digits and letters use their ASCII codes; lowercase ones are the numpad.
+    if let 0x30..=0x39 | 0x41..=0x5a = vk.0 {
+        return Key::from_char(vk.0 as u8 as char);
+    }
+    if (VK_F1.0..=VK_F12.0).contains(&vk.0) {
+        return Key::function((vk.0 - VK_F1.0) as u32 + 1);
+    }
+
+    let key = match vk {
+        VK_LEFT => Key::Left,
+        VK_RIGHT => Key::Right,
+        VK_UP => Key::Up,
+        VK_DOWN => Key::Down,
+        VK_SPACE => Key::Space,
+        VK_RETURN => Key::Enter,
+        VK_ESCAPE => Key::Escape,
+        VK_TAB => Key::Tab,
+        VK_BACK => Key::Backspace,
+        VK_SHIFT => Key::Shift,
+        VK_CONTROL => Key::Control,
+        VK_MENU => Key::Alt,
+        _ => return None,
+    };
+    Some(key)
+}
+
+fn window_wndproc(window: &mut Window, message: u32, wparam: WPARAM) -> bool {
+    match message {
+        // todo: handle window sizing, keys, etc.
+        WM_KEYDOWN => {
+            let w = wparam.0 as u8;
+            print_debug_string(&format!("KEY DOWN: {w}"));
+            window.on_key_down();
+            true
+        }
+
+        _ => false,
+    }
+}
+
+extern "system" fn wndproc(hwnd: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match message {
+        WM_CREATE => {
+            let create_struct: &CREATESTRUCTA = unsafe { std::mem::transmute(lparam) };
+            unsafe { SetWindowLongPtrA(hwnd, GWLP_USERDATA, create_struct.lpCreateParams as _) };
+            LRESULT::default()
+        }
+
+        WM_DESTROY => {
+            unsafe { PostQuitMessage(0) };
+            LRESULT::default()
+        }
+
+        _ => {
+            let user_data = unsafe { GetWindowLongPtrA(hwnd, GWLP_USERDATA) };
+            let window = std::ptr::NonNull::<Window>::new(user_data as _);
+            let handled =
+                window.is_some_and(|mut w| window_wndproc(unsafe { w.as_mut() }, message, wparam));
+
+            if handled {
+                LRESULT::default()
+            } else {
+                unsafe { DefWindowProcA(hwnd, message, wparam, lparam) }
+            }
+        }
+    }
+}
@@ -0,0 +1,312 @@
+// Saving and loading RGBA8 pixels as PNG, BMP or binary PPM, picked by file
+// extension. There are no dependencies. PNG is written as a single
+// fixed-Huffman deflate block, which is nowhere near optimal but shrinks
+// rendered images well enough for screenshots and golden images; loading
+// takes any non-interlaced PNG, through the `assets` decoder, so references
+// can be edited elsewhere.
+
+use std::{error::Error, fmt, fs, path::Path};
+
+use crate::assets::{
+    image::{
+        png::{self, PngPixels},
+        MAX_IMAGE_SIZE,
+    },
+    zlib,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFileFormat {
+    Png,
+    Bmp,
+    /// Binary PPM (P6).
+    Ppm,
+}
+
+impl ImageFileFormat {
+    /// From the extension, ignoring case.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(Self::Png),
+            "bmp" => Some(Self::Bmp),
+            "ppm" => Some(Self::Ppm),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownImageFormat(pub String);
+
+impl fmt::Display for UnknownImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} isn't a .png, .bmp or .ppm file", self.0)
+    }
+}
+
+impl Error for UnknownImageFormat {}
+
+/// The file is damaged or uses a feature the decoders don't have.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidImage(pub &'static str);
+
+impl fmt::Display for InvalidImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid image: {}", self.0)
+    }
+}
+
+impl Error for InvalidImage {}
+
+/// 8-bit RGBA pixels, rows top to bottom with no padding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Screenshot {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width as usize * height as usize * 4,
+            "expected {width}x{height} RGBA8 pixels"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        self.pixels[i..i + 4].try_into().unwrap()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        let format = ImageFileFormat::from_path(path)
+            .ok_or_else(|| UnknownImageFormat(path.display().to_string()))?;
+        fs::write(path, self.encode(format))?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let format = ImageFileFormat::from_path(path)
+            .ok_or_else(|| UnknownImageFormat(path.display().to_string()))?;
+        Ok(Self::decode(&fs::read(path)?, format)?)
+    }
+
+    pub fn decode(bytes: &[u8], format: ImageFileFormat) -> Result<Self, InvalidImage> {
+        match format {
+            ImageFileFormat::Png => Self::from_png(bytes),
+            ImageFileFormat::Bmp => Self::from_bmp(bytes),
+            ImageFileFormat::Ppm => Self::from_ppm(bytes),
+        }
+    }
+
+    pub fn encode(&self, format: ImageFileFormat) -> Vec<u8> {
+        match format {
+            ImageFileFormat::Png => self.to_png(),
+            ImageFileFormat::Bmp => self.to_bmp(),
+            ImageFileFormat::Ppm => self.to_ppm(),
+        }
+    }
+
+    fn rows(&self) -> std::slice::ChunksExact<'_, u8> {
+        self.pixels.chunks_exact(self.width.max(1) as usize * 4)
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        // Every row starts with its filter type, which is always None.
+        let mut scanlines = Vec::with_capacity(self.pixels.len() + self.height as usize);
+        for row in self.rows() {
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlace.
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &zlib::compress(&scanlines));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// 24-bit and bottom-up, which everything can open. Alpha is dropped.
+    pub fn to_bmp(&self) -> Vec<u8> {
+        const HEADERS_SIZE: u32 = 14 + 40;
+        let row_size = (self.width * 3).next_multiple_of(4);
+        let image_size = row_size * self.height;
+
+        let mut bmp = Vec::with_capacity((HEADERS_SIZE + image_size) as usize);
+        // BITMAPFILEHEADER
+        bmp.extend_from_slice(b"BM");
+        bmp.extend_from_slice(&(HEADERS_SIZE + image_size).to_le_bytes());
+        bmp.extend_from_slice(&[0; 4]);
+        bmp.extend_from_slice(&HEADERS_SIZE.to_le_bytes());
+        // BITMAPINFOHEADER
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        bmp.extend_from_slice(&(self.width as i32).to_le_bytes());
+        bmp.extend_from_slice(&(self.height as i32).to_le_bytes());
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&24u16.to_le_bytes());
+        bmp.extend_from_slice(&0u32.to_le_bytes()); // BI_RGB
+        bmp.extend_from_slice(&image_size.to_le_bytes());
+        bmp.extend_from_slice(&2835i32.to_le_bytes()); // 72 dpi
+        bmp.extend_from_slice(&2835i32.to_le_bytes());
+        bmp.extend_from_slice(&[0; 8]);
+
+        let padding = (row_size - self.width * 3) as usize;
+        for row in self.rows().rev() {
+            for rgba in row.chunks_exact(4) {
+                bmp.extend_from_slice(&[rgba[2], rgba[1], rgba[0]]);
+            }
+            bmp.extend(std::iter::repeat_n(0, padding));
+        }
+        bmp
+    }
+
+    /// Alpha is dropped.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        for rgba in self.pixels.chunks_exact(4) {
+            ppm.extend_from_slice(&rgba[..3]);
+        }
+        ppm
+    }
+
+    /// Any bit depth and color type, palettes included, without
+    /// interlacing. 16-bit channels keep their high byte.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, InvalidImage> {
+        let png = png::decode(bytes).map_err(InvalidImage)?;
+        let pixels = match png.pixels {
+            PngPixels::Rgba8(pixels) => pixels,
+            PngPixels::Rgba16(samples) => samples.iter().map(|&s| (s >> 8) as u8).collect(),
+        };
+        Ok(Self::new(png.width, png.height, pixels))
+    }
+
+    /// Uncompressed 24 or 32 bits per pixel, either way up. The fourth byte
+    /// of 32-bit pixels is ignored, as Windows does.
+    pub fn from_bmp(bytes: &[u8]) -> Result<Self, InvalidImage> {
+        let truncated = InvalidImage("BMP is truncated");
+        let u16_at = |i: usize| {
+            bytes
+                .get(i..i + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        };
+        let u32_at = |i: usize| {
+            bytes
+                .get(i..i + 4)
+                .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        };
+
+        if !bytes.starts_with(b"BM") {
+            return Err(InvalidImage("not a BMP"));
+        }
+        let offset = u32_at(10).ok_or(truncated.clone())? as usize;
+        if u32_at(14).ok_or(truncated.clone())? < 40 {
+            return Err(InvalidImage(
+                "BMP headers older than BITMAPINFOHEADER aren't supported",
+            ));
+        }
+        let width = u32_at(18).ok_or(truncated.clone())? as i32;
+        let height = u32_at(22).ok_or(truncated.clone())? as i32;
+        let bits = u16_at(28).ok_or(truncated.clone())?;
+        if u32_at(30).ok_or(truncated.clone())? != 0 || !matches!(bits, 24 | 32) {
+            return Err(InvalidImage(
+                "only uncompressed 24 and 32-bit BMPs are supported",
+            ));
+        }
+        if width < 0 {
+            return Err(InvalidImage("BMP has a negative width"));
+        }
+
+        let (width, rows) = (width as u32, height.unsigned_abs());
+        if width == 0 || rows == 0 || width.max(rows) > MAX_IMAGE_SIZE {
+            return Err(InvalidImage("BMP is empty or too large"));
+        }
+        let bytes_per_pixel = bits as usize / 8;
+        let row_size = (width as usize * bytes_per_pixel).next_multiple_of(4);
+        let end = row_size
+            .checked_mul(rows as usize)
+            .and_then(|size| size.checked_add(offset))
+            .ok_or(truncated.clone())?;
+        let data = bytes.get(offset..end).ok_or(truncated)?;
+
+        let mut pixels = Vec::with_capacity(width as usize * rows as usize * 4);
+        let mut add_row = |row: &[u8]| {
+            for bgr in row.chunks_exact(bytes_per_pixel).take(width as usize) {
+                pixels.extend_from_slice(&[bgr[2], bgr[1], bgr[0], 255]);
+            }
+        };
+        // Positive heights are bottom-up.
+        if height > 0 {
+            data.chunks_exact(row_size).rev().for_each(&mut add_row);
+        } else {
+            data.chunks_exact(row_size).for_each(&mut add_row);
+        }
+        Ok(Self::new(width, rows, pixels))
+    }
+
+    /// Binary PPM with a maximum value of 255.
+    pub fn from_ppm(bytes: &[u8]) -> Result<Self, InvalidImage> {
+        let mut rest = bytes
+            .strip_prefix(b"P6")
+            .ok_or(InvalidImage("not a binary PPM"))?;
+        let mut field = || -> Result<u32, InvalidImage> {
+            // Whitespace and comments, then digits.
+            loop {
+                match rest.first() {
+                    Some(c) if c.is_ascii_whitespace() => rest = &rest[1..],
+                    Some(b'#') => {
+                        let end = rest.iter().position(|&c| c == b'\n').unwrap_or(rest.len());
+                        rest = &rest[end..];
+                    }
+                    _ => break,
+                }
+            }
+            let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+            let value = std::str::from_utf8(&rest[..digits])
+                .ok()
+                .and_then(|digits| digits.parse().ok())
+                .ok_or(InvalidImage("PPM header is malformed"))?;
+            rest = &rest[digits..];
+            Ok(value)
+        };
+
+        let (width, height, max_value) = (field()?, field()?, field()?);
+        if max_value != 255 {
+            return Err(InvalidImage("only 8-bit PPMs are supported"));
+        }
+        // Exactly one whitespace byte separates the header from the pixels.
+        let data = rest
+            .get(1..1 + width as usize * height as usize * 3)
+            .ok_or(InvalidImage("PPM is truncated"))?;
+
+        let pixels = data
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect();
+        Ok(Self::new(width, height, pixels))
+    }
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = zlib::crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
@@ -0,0 +1,227 @@
+// HLSL compilation: `#include`s are expanded in Rust through a `FileSystem`
+// before the source reaches FXC (shader model 5.x) or DXC (6.x), which gives
+// the cache key and the list of files to watch for hot reload without either
+// compiler being involved.
+
+pub mod reflection;
+
+mod cache;
+#[cfg(windows)]
+mod compiler;
+mod include;
+mod watch;
+
+pub use cache::{cache_key, ShaderCache};
+#[cfg(windows)]
+pub use compiler::ShaderCompiler;
+pub use include::{
+    expand_includes, includes, scan_dependencies, DiskFileSystem, ExpandedSource, FileSystem,
+    MemoryFileSystem,
+};
+pub use watch::{Debouncer, FileWatcher, HotReload, Rebuilt, ReloadablePipeline};
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    Pixel,
+    Geometry,
+    Hull,
+    Domain,
+    Compute,
+    Amplification,
+    Mesh,
+}
+
+impl ShaderStage {
+    fn prefix(self) -> &'static str {
+        match self {
+            ShaderStage::Vertex => "vs",
+            ShaderStage::Pixel => "ps",
+            ShaderStage::Geometry => "gs",
+            ShaderStage::Hull => "hs",
+            ShaderStage::Domain => "ds",
+            ShaderStage::Compute => "cs",
+            ShaderStage::Amplification => "as",
+            ShaderStage::Mesh => "ms",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShaderModel {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl ShaderModel {
+    pub const SM_5_1: Self = Self::new(5, 1);
+    pub const SM_6_0: Self = Self::new(6, 0);
+    pub const SM_6_5: Self = Self::new(6, 5);
+    pub const SM_6_6: Self = Self::new(6, 6);
+
+    pub const fn new(major: u8, minor: u8) -> Self {
+        Self { major, minor }
+    }
+
+    /// Shader model 6 and up is only available through DXC.
+    pub fn uses_dxc(self) -> bool {
+        self.major >= 6
+    }
+}
+
+/// The target profile passed to the compiler, e.g. `ps_5_1`.
+pub fn profile(stage: ShaderStage, model: ShaderModel) -> Result<String, ShaderError> {
+    let supported = match stage {
+        ShaderStage::Amplification | ShaderStage::Mesh => model >= ShaderModel::SM_6_5,
+        _ => model >= ShaderModel::new(5, 0),
+    };
+    if !supported || model.major > 6 || (model.major == 5 && model.minor > 1) {
+        return Err(ShaderError::UnsupportedProfile { stage, model });
+    }
+
+    Ok(format!(
+        "{}_{}_{}",
+        stage.prefix(),
+        model.major,
+        model.minor
+    ))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderDesc {
+    pub path: PathBuf,
+    pub entry_point: String,
+    pub stage: ShaderStage,
+    pub model: ShaderModel,
+    pub defines: Vec<(String, String)>,
+    /// Skips optimization and keeps debug information for PIX.
+    pub debug: bool,
+}
+
+impl ShaderDesc {
+    pub fn new(
+        path: impl Into<PathBuf>,
+        entry_point: impl Into<String>,
+        stage: ShaderStage,
+        model: ShaderModel,
+    ) -> Self {
+        Self {
+            path: path.into(),
+            entry_point: entry_point.into(),
+            stage,
+            model,
+            defines: Vec::new(),
+            debug: cfg!(debug_assertions),
+        }
+    }
+
+    pub fn define(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.defines.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CompiledShader {
+    pub bytecode: Vec<u8>,
+    /// Every file the source was built from, the shader itself first.
+    pub dependencies: Vec<PathBuf>,
+    pub key: u64,
+    pub from_cache: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShaderError {
+    NotFound {
+        path: PathBuf,
+        included_from: Option<PathBuf>,
+    },
+    IncludeCycle {
+        path: PathBuf,
+    },
+    UnsupportedProfile {
+        stage: ShaderStage,
+        model: ShaderModel,
+    },
+    CompilerUnavailable(String),
+    Compile {
+        path: PathBuf,
+        message: String,
+    },
+    /// The pipeline being rebuilt has nowhere to put a shader of this stage.
+    StageMismatch {
+        path: PathBuf,
+        stage: ShaderStage,
+    },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::NotFound {
+                path,
+                included_from: None,
+            } => write!(f, "shader `{}` not found", path.display()),
+            ShaderError::NotFound {
+                path,
+                included_from: Some(from),
+            } => write!(
+                f,
+                "`{}` included from `{}` not found",
+                path.display(),
+                from.display()
+            ),
+            ShaderError::IncludeCycle { path } => {
+                write!(f, "`{}` includes itself", path.display())
+            }
+            ShaderError::UnsupportedProfile { stage, model } => write!(
+                f,
+                "no {stage:?} shader profile for shader model {}.{}",
+                model.major, model.minor
+            ),
+            ShaderError::CompilerUnavailable(message) => {
+                write!(f, "shader compiler unavailable: {message}")
+            }
+            ShaderError::Compile { path, message } => {
+                write!(f, "failed to compile `{}`:\n{message}", path.display())
+            }
+            ShaderError::StageMismatch { path, stage } => write!(
+                f,
+                "`{}` is a {stage:?} shader, which the pipeline has no slot for",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Removes `.` and `..` components without touching the file system, so the
+/// same file reached through different relative paths gets the same name.
+fn normalize(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use super::{profile, ShaderDesc, ShaderError};
+use crate::{hash::stable_hash, util::write_atomically};
+
+/// Identifies the bytecode a compile would produce: anything that changes
+/// the output, including the expanded source, changes the key.
+pub fn cache_key(
+    desc: &ShaderDesc,
+    expanded_source: &str,
+    compiler: &str,
+) -> Result<u64, ShaderError> {
+    let profile = profile(desc.stage, desc.model)?;
+    Ok(stable_hash(&(
+        compiler,
+        desc.path.to_string_lossy(),
+        &desc.entry_point,
+        profile,
+        &desc.defines,
+        desc.debug,
+        expanded_source,
+    )))
+}
+
+/// Compiled bytecode on disk, one file per cache key.
+pub struct ShaderCache {
+    dir: PathBuf,
+}
+
+impl ShaderCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn get(&self, key: u64) -> Option<Vec<u8>> {
+        std::fs::read(self.path(key)).ok()
+    }
+
+    /// Failing to write the cache only costs a recompile next time, so errors
+    /// are returned for logging rather than treated as fatal.
+    pub fn put(&self, key: u64, bytecode: &[u8]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        write_atomically(&self.path(key), bytecode)
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{key:016x}.cso"))
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    ffi::CString,
+    path::{Path, PathBuf},
+};
+
+use windows::{
+    core::{s, w, Interface, GUID, HSTRING, PCSTR, PCWSTR},
+    Win32::{
+        Graphics::Direct3D::{
+            Dxc::{
+                CLSID_DxcCompiler, DxcBuffer, DxcCreateInstanceProc, IDxcBlob, IDxcBlobUtf8,
+                IDxcCompiler3, IDxcIncludeHandler, IDxcResult, DXC_CP_UTF8, DXC_OUT_ERRORS,
+                DXC_OUT_OBJECT,
+            },
+            Fxc::{
+                D3DCompile, D3DCOMPILE_DEBUG, D3DCOMPILE_ENABLE_STRICTNESS,
+                D3DCOMPILE_OPTIMIZATION_LEVEL3, D3DCOMPILE_SKIP_OPTIMIZATION,
+            },
+            ID3DBlob, ID3DInclude, D3D_SHADER_MACRO,
+        },
+        System::LibraryLoader::{GetProcAddress, LoadLibraryW},
+    },
+};
+
+use super::{
+    cache_key, expand_includes, profile, CompiledShader, FileSystem, ShaderCache, ShaderDesc,
+    ShaderError,
+};
+use crate::util::print_debug_string;
+
+/// Compiles shader model 5.x with FXC and 6.x with DXC. `dxcompiler.dll` is
+/// loaded the first time a 6.x shader is compiled, so samples that only use
+/// FXC don't need to ship it.
+pub struct ShaderCompiler {
+    fs: Box<dyn FileSystem>,
+    include_dirs: Vec<PathBuf>,
+    cache: Option<ShaderCache>,
+    dxc: Option<IDxcCompiler3>,
+}
+
+impl ShaderCompiler {
+    pub fn new(fs: impl FileSystem + 'static) -> Self {
+        Self {
+            fs: Box::new(fs),
+            include_dirs: Vec::new(),
+            cache: None,
+            dxc: None,
+        }
+    }
+
+    pub fn include_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    pub fn cache(mut self, cache: ShaderCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn compile(&mut self, desc: &ShaderDesc) -> Result<CompiledShader, ShaderError> {
+        let profile = profile(desc.stage, desc.model)?;
+        let expanded = expand_includes(self.fs.as_ref(), &desc.path, &self.include_dirs)?;
+        let compiler = if desc.model.uses_dxc() { "dxc" } else { "fxc" };
+        let key = cache_key(desc, &expanded.source, compiler)?;
+
+        // Hot reload watches the real files when there are any.
+        let dependencies = expanded
+            .dependencies
+            .iter()
+            .map(|path| self.fs.disk_path(path).unwrap_or_else(|| path.clone()))
+            .collect();
+
+        if let Some(bytecode) = self.cache.as_ref().and_then(|cache| cache.get(key)) {
+            return Ok(CompiledShader {
+                bytecode,
+                dependencies,
+                key,
+                from_cache: true,
+            });
+        }
+
+        let bytecode = if desc.model.uses_dxc() {
+            let dxc = self.dxc()?;
+            compile_dxc(&dxc, desc, &expanded.source, &profile)?
+        } else {
+            compile_fxc(desc, &expanded.source, &profile)?
+        };
+
+        if let Some(cache) = &self.cache {
+            if let Err(error) = cache.put(key, &bytecode) {
+                print_debug_string(&format!("failed to cache shader: {error}"));
+            }
+        }
+
+        Ok(CompiledShader {
+            bytecode,
+            dependencies,
+            key,
+            from_cache: false,
+        })
+    }
+
+    fn dxc(&mut self) -> Result<IDxcCompiler3, ShaderError> {
+        if self.dxc.is_none() {
+            let dxc = create_dxc_instance(&CLSID_DxcCompiler).map_err(|error| {
+                ShaderError::CompilerUnavailable(format!("dxcompiler.dll: {error}"))
+            })?;
+            self.dxc = Some(dxc);
+        }
+        Ok(self.dxc.clone().unwrap())
+    }
+}
+
+/// Creates a DXC object, loading `dxcompiler.dll` at runtime.
+pub(super) fn create_dxc_instance<T: Interface>(clsid: &GUID) -> windows::core::Result<T> {
+    unsafe {
+        let module = LoadLibraryW(w!("dxcompiler.dll"))?;
+        let create: DxcCreateInstanceProc =
+            std::mem::transmute(GetProcAddress(module, s!("DxcCreateInstance")));
+        let create = create.ok_or_else(windows::core::Error::from_win32)?;
+
+        let mut instance = std::ptr::null_mut();
+        create(clsid, &T::IID, &mut instance).ok()?;
+        Ok(T::from_raw(instance))
+    }
+}
+
+fn source_name(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+fn compile_fxc(desc: &ShaderDesc, source: &str, profile: &str) -> Result<Vec<u8>, ShaderError> {
+    let compile_error = |message: String| ShaderError::Compile {
+        path: desc.path.clone(),
+        message,
+    };
+    let cstring = |s: &str| CString::new(s).map_err(|e| compile_error(e.to_string()));
+
+    let defines = desc
+        .defines
+        .iter()
+        .map(|(name, value)| Ok((cstring(name)?, cstring(value)?)))
+        .collect::<Result<Vec<_>, ShaderError>>()?;
+    let mut macros: Vec<D3D_SHADER_MACRO> = defines
+        .iter()
+        .map(|(name, value)| D3D_SHADER_MACRO {
+            Name: PCSTR(name.as_ptr() as _),
+            Definition: PCSTR(value.as_ptr() as _),
+        })
+        .collect();
+    macros.push(D3D_SHADER_MACRO::default());
+
+    let name = cstring(&source_name(&desc.path))?;
+    let entry_point = cstring(&desc.entry_point)?;
+    let profile = cstring(profile)?;
+
+    let flags = D3DCOMPILE_ENABLE_STRICTNESS
+        | if desc.debug {
+            D3DCOMPILE_DEBUG | D3DCOMPILE_SKIP_OPTIMIZATION
+        } else {
+            D3DCOMPILE_OPTIMIZATION_LEVEL3
+        };
+
+    let mut code: Option<ID3DBlob> = None;
+    let mut errors: Option<ID3DBlob> = None;
+    let result = unsafe {
+        D3DCompile(
+            source.as_ptr() as _,
+            source.len(),
+            PCSTR(name.as_ptr() as _),
+            Some(macros.as_ptr()),
+            None::<&ID3DInclude>,
+            PCSTR(entry_point.as_ptr() as _),
+            PCSTR(profile.as_ptr() as _),
+            flags,
+            0,
+            &mut code,
+            Some(&mut errors),
+        )
+    };
+
+    match (result, code) {
+        (Ok(()), Some(code)) => Ok(blob_bytes(&code)),
+        (result, _) => Err(compile_error(match errors {
+            Some(errors) => String::from_utf8_lossy(&blob_bytes(&errors))
+                .trim_end_matches('\0')
+                .to_string(),
+            None => result.err().map(|e| e.to_string()).unwrap_or_default(),
+        })),
+    }
+}
+
+fn compile_dxc(
+    dxc: &IDxcCompiler3,
+    desc: &ShaderDesc,
+    source: &str,
+    profile: &str,
+) -> Result<Vec<u8>, ShaderError> {
+    let compile_error = |message: String| ShaderError::Compile {
+        path: desc.path.clone(),
+        message,
+    };
+
+    let mut args = vec![
+        HSTRING::from(source_name(&desc.path)),
+        HSTRING::from("-E"),
+        HSTRING::from(desc.entry_point.as_str()),
+        HSTRING::from("-T"),
+        HSTRING::from(profile),
+    ];
+    for (name, value) in &desc.defines {
+        args.push(HSTRING::from("-D"));
+        args.push(HSTRING::from(format!("{name}={value}")));
+    }
+    if desc.debug {
+        args.extend(["-Zi", "-Qembed_debug", "-Od"].map(HSTRING::from));
+    } else {
+        args.push(HSTRING::from("-O3"));
+    }
+    let args: Vec<PCWSTR> = args.iter().map(|arg| PCWSTR(arg.as_ptr())).collect();
+
+    let buffer = DxcBuffer {
+        Ptr: source.as_ptr() as _,
+        Size: source.len(),
+        Encoding: DXC_CP_UTF8.0,
+    };
+
+    unsafe {
+        let result: IDxcResult = dxc
+            .Compile(&buffer, Some(&args), None::<&IDxcIncludeHandler>)
+            .map_err(|e| compile_error(e.to_string()))?;
+
+        let status = result
+            .GetStatus()
+            .map_err(|e| compile_error(e.to_string()))?;
+        if status.is_err() {
+            let mut errors: Option<IDxcBlobUtf8> = None;
+            let _ = result.GetOutput(DXC_OUT_ERRORS, &mut None, &mut errors);
+            let message = match errors {
+                Some(errors) => String::from_utf8_lossy(std::slice::from_raw_parts(
+                    errors.GetStringPointer().0,
+                    errors.GetStringLength(),
+                ))
+                .into_owned(),
+                None => windows::core::Error::from(status).to_string(),
+            };
+            return Err(compile_error(message));
+        }
+
+        let mut object: Option<IDxcBlob> = None;
+        result
+            .GetOutput(DXC_OUT_OBJECT, &mut None, &mut object)
+            .map_err(|e| compile_error(e.to_string()))?;
+        let object = object.ok_or_else(|| compile_error("no shader object".into()))?;
+        Ok(std::slice::from_raw_parts(
+            object.GetBufferPointer() as *const u8,
+            object.GetBufferSize(),
+        )
+        .to_vec())
+    }
+}
+
+fn blob_bytes(blob: &ID3DBlob) -> Vec<u8> {
+    unsafe {
+        std::slice::from_raw_parts(blob.GetBufferPointer() as *const u8, blob.GetBufferSize())
+            .to_vec()
+    }
+}
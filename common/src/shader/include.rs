@@ -0,0 +1,275 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use super::{normalize, ShaderError};
+
+/// Where shader source comes from. Paths are relative to the file system's
+/// root, so shaders can be loaded from disk or served from memory in tests.
+pub trait FileSystem {
+    fn read(&self, path: &Path) -> Option<String>;
+
+    /// The file on disk behind `path`, if there is one to watch.
+    fn disk_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+pub struct DiskFileSystem {
+    root: PathBuf,
+}
+
+impl DiskFileSystem {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl FileSystem for DiskFileSystem {
+    fn read(&self, path: &Path) -> Option<String> {
+        std::fs::read_to_string(self.root.join(path)).ok()
+    }
+
+    fn disk_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(self.root.join(path))
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryFileSystem {
+    files: HashMap<PathBuf, String>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, path: impl AsRef<Path>, source: impl Into<String>) {
+        self.files.insert(normalize(path.as_ref()), source.into());
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read(&self, path: &Path) -> Option<String> {
+        self.files.get(&normalize(path)).cloned()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpandedSource {
+    pub source: String,
+    /// The root file first, then includes in the order they were reached.
+    pub dependencies: Vec<PathBuf>,
+}
+
+/// Replaces every `#include` with the contents of the file, adding `#line`
+/// directives so compiler errors point at the original files.
+///
+/// `"quoted"` includes are looked up next to the including file first, then
+/// in `include_dirs`; `<angled>` ones only in `include_dirs`. Includes are
+/// expanded even inside `#if` blocks, since the preprocessor hasn't run yet.
+pub fn expand_includes(
+    fs: &dyn FileSystem,
+    path: &Path,
+    include_dirs: &[PathBuf],
+) -> Result<ExpandedSource, ShaderError> {
+    let path = normalize(path);
+    let source = fs.read(&path).ok_or_else(|| ShaderError::NotFound {
+        path: path.clone(),
+        included_from: None,
+    })?;
+
+    let mut expander = Expander {
+        fs,
+        include_dirs,
+        output: String::new(),
+        dependencies: Vec::new(),
+        stack: Vec::new(),
+        once: HashSet::new(),
+    };
+    expander.expand(&path, &source)?;
+
+    Ok(ExpandedSource {
+        source: expander.output,
+        dependencies: expander.dependencies,
+    })
+}
+
+/// Every file `path` depends on through `#include`, itself first, each once.
+///
+/// Unlike `expand_includes` this tolerates include cycles, and a missing root
+/// is simply its own only dependency, so a build script can still watch for
+/// it to appear.
+pub fn scan_dependencies(
+    fs: &dyn FileSystem,
+    path: &Path,
+    include_dirs: &[PathBuf],
+) -> Result<Vec<PathBuf>, ShaderError> {
+    let mut dependencies = vec![normalize(path)];
+    let mut next = 0;
+
+    while next < dependencies.len() {
+        let file = dependencies[next].clone();
+        next += 1;
+        let Some(source) = fs.read(&file) else {
+            continue;
+        };
+
+        for (name, quoted) in includes(&source) {
+            let (resolved, _) = resolve(fs, include_dirs, &file, name, quoted)?;
+            if !dependencies.contains(&resolved) {
+                dependencies.push(resolved);
+            }
+        }
+    }
+
+    Ok(dependencies)
+}
+
+/// The `#include` directives in `source` as `(name, quoted)`, skipping ones
+/// in comments.
+pub fn includes(source: &str) -> Vec<(&str, bool)> {
+    let mut scanner = DirectiveScanner::default();
+    source
+        .lines()
+        .filter_map(|line| match scanner.next_line(line) {
+            Some(Directive::Include { name, quoted }) => Some((name, quoted)),
+            _ => None,
+        })
+        .collect()
+}
+
+struct Expander<'a> {
+    fs: &'a dyn FileSystem,
+    include_dirs: &'a [PathBuf],
+    output: String,
+    dependencies: Vec<PathBuf>,
+    stack: Vec<PathBuf>,
+    once: HashSet<PathBuf>,
+}
+
+enum Directive<'a> {
+    Include { name: &'a str, quoted: bool },
+    PragmaOnce,
+}
+
+impl Expander<'_> {
+    fn expand(&mut self, path: &Path, source: &str) -> Result<(), ShaderError> {
+        if self.stack.iter().any(|p| p == path) {
+            return Err(ShaderError::IncludeCycle {
+                path: path.to_path_buf(),
+            });
+        }
+        if self.once.contains(path) {
+            return Ok(());
+        }
+        if !self.dependencies.iter().any(|p| p == path) {
+            self.dependencies.push(path.to_path_buf());
+        }
+
+        self.stack.push(path.to_path_buf());
+        self.line_directive(1, path);
+
+        let mut scanner = DirectiveScanner::default();
+        for (index, line) in source.lines().enumerate() {
+            match scanner.next_line(line) {
+                Some(Directive::PragmaOnce) => {
+                    self.once.insert(path.to_path_buf());
+                    self.output.push('\n');
+                }
+                Some(Directive::Include { name, quoted }) => {
+                    let (included, source) =
+                        resolve(self.fs, self.include_dirs, path, name, quoted)?;
+                    self.expand(&included, &source)?;
+                    self.line_directive(index + 2, path);
+                }
+                None => {
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                }
+            }
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+
+    fn line_directive(&mut self, line: usize, path: &Path) {
+        // Forward slashes so Windows paths don't turn into escape sequences.
+        let name = path.to_string_lossy().replace('\\', "/");
+        self.output.push_str(&format!("#line {line} \"{name}\"\n"));
+    }
+}
+
+fn resolve(
+    fs: &dyn FileSystem,
+    include_dirs: &[PathBuf],
+    includer: &Path,
+    name: &str,
+    quoted: bool,
+) -> Result<(PathBuf, String), ShaderError> {
+    let local = quoted.then(|| includer.parent().unwrap_or(Path::new("")).join(name));
+    let candidates = local
+        .into_iter()
+        .chain(include_dirs.iter().map(|dir| dir.join(name)));
+
+    for candidate in candidates {
+        let candidate = normalize(&candidate);
+        if let Some(source) = fs.read(&candidate) {
+            return Ok((candidate, source));
+        }
+    }
+
+    Err(ShaderError::NotFound {
+        path: PathBuf::from(name),
+        included_from: Some(includer.to_path_buf()),
+    })
+}
+
+/// Finds directives a line at a time, ignoring lines inside `/* */` comments
+/// the way the preprocessor does.
+#[derive(Default)]
+struct DirectiveScanner {
+    in_block_comment: bool,
+}
+
+impl DirectiveScanner {
+    fn next_line<'a>(&mut self, line: &'a str) -> Option<Directive<'a>> {
+        let mut line = line;
+        if self.in_block_comment {
+            let end = line.find("*/")?;
+            line = &line[end + 2..];
+            self.in_block_comment = false;
+        }
+        if let Some(start) = line.find("/*") {
+            if !line[start..].contains("*/") {
+                self.in_block_comment = true;
+            }
+        }
+
+        parse_directive(line)
+    }
+}
+
+fn parse_directive(line: &str) -> Option<Directive<'_>> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+
+    if let Some(rest) = rest.strip_prefix("include") {
+        let rest = rest.trim_start();
+        let (close, quoted) = match rest.chars().next()? {
+            '"' => ('"', true),
+            '<' => ('>', false),
+            _ => return None,
+        };
+        let end = rest[1..].find(close)?;
+        return Some(Directive::Include {
+            name: &rest[1..1 + end],
+            quoted,
+        });
+    }
+
+    let rest = rest.strip_prefix("pragma")?;
+    (rest.split_whitespace().next() == Some("once")).then_some(Directive::PragmaOnce)
+}
@@ -0,0 +1,350 @@
+// What a compiled shader says about its inputs and bindings, copied out of
+// the D3D12 reflection interfaces (see `d3d12`), and the pure mapping from
+// that to an input layout and a root signature.
+
+#[cfg(windows)]
+mod d3d12;
+
+#[cfg(windows)]
+pub use d3d12::reflect;
+
+use std::fmt;
+
+use super::ShaderStage;
+use crate::gfx::{
+    pipeline_state::InputElement,
+    root_signature::{
+        DescriptorFlags, DescriptorRange, DescriptorType, RootParameter, RootSignatureBuilder,
+        RootSignatureDesc, RootSignatureError, RootSignatureFlags, ShaderVisibility, UNBOUNDED,
+    },
+    Format,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ComponentType {
+    Unknown,
+    Uint32,
+    Sint32,
+    Float32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SignatureParameter {
+    pub semantic: String,
+    pub semantic_index: u32,
+    pub register: u32,
+    /// `SV_VertexID` and friends, which the input assembler generates.
+    pub system_value: bool,
+    pub component_type: ComponentType,
+    /// Components used, `x` in the lowest bit.
+    pub mask: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderVariable {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ConstantBufferLayout {
+    pub name: String,
+    pub size: u32,
+    pub variables: Vec<ShaderVariable>,
+}
+
+impl ConstantBufferLayout {
+    pub fn variable(&self, name: &str) -> Option<&ShaderVariable> {
+        self.variables.iter().find(|v| v.name == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    ConstantBuffer,
+    TextureBuffer,
+    Texture,
+    Sampler,
+    StructuredBuffer,
+    ByteAddressBuffer,
+    AccelerationStructure,
+    RwTexture,
+    RwStructuredBuffer,
+    RwByteAddressBuffer,
+    AppendStructuredBuffer,
+    ConsumeStructuredBuffer,
+    RwStructuredBufferWithCounter,
+    FeedbackTexture,
+}
+
+impl ResourceKind {
+    pub fn descriptor_type(self) -> DescriptorType {
+        match self {
+            ResourceKind::ConstantBuffer => DescriptorType::Cbv,
+            ResourceKind::TextureBuffer
+            | ResourceKind::Texture
+            | ResourceKind::StructuredBuffer
+            | ResourceKind::ByteAddressBuffer
+            | ResourceKind::AccelerationStructure => DescriptorType::Srv,
+            ResourceKind::Sampler => DescriptorType::Sampler,
+            ResourceKind::RwTexture
+            | ResourceKind::RwStructuredBuffer
+            | ResourceKind::RwByteAddressBuffer
+            | ResourceKind::AppendStructuredBuffer
+            | ResourceKind::ConsumeStructuredBuffer
+            | ResourceKind::RwStructuredBufferWithCounter
+            | ResourceKind::FeedbackTexture => DescriptorType::Uav,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BoundResource {
+    pub name: String,
+    pub kind: ResourceKind,
+    pub register: u32,
+    /// Array size, or `UNBOUNDED`.
+    pub count: u32,
+    pub space: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderReflection {
+    pub stage: ShaderStage,
+    pub input_parameters: Vec<SignatureParameter>,
+    pub constant_buffers: Vec<ConstantBufferLayout>,
+    pub bound_resources: Vec<BoundResource>,
+}
+
+impl ShaderReflection {
+    pub fn constant_buffer(&self, name: &str) -> Option<&ConstantBufferLayout> {
+        self.constant_buffers.iter().find(|cb| cb.name == name)
+    }
+
+    pub fn resource(&self, name: &str) -> Option<&BoundResource> {
+        self.bound_resources.iter().find(|r| r.name == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReflectionError {
+    UnsupportedInput {
+        semantic: String,
+        semantic_index: u32,
+    },
+    RootSignature(RootSignatureError),
+}
+
+impl fmt::Display for ReflectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflectionError::UnsupportedInput {
+                semantic,
+                semantic_index,
+            } => write!(f, "no vertex format for input {semantic}{semantic_index}"),
+            ReflectionError::RootSignature(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReflectionError {}
+
+impl From<RootSignatureError> for ReflectionError {
+    fn from(error: RootSignatureError) -> Self {
+        ReflectionError::RootSignature(error)
+    }
+}
+
+/// One element per vertex shader input, all in slot 0 and packed in
+/// declaration order. Inputs the input assembler generates are skipped.
+pub fn input_layout(reflection: &ShaderReflection) -> Result<Vec<InputElement>, ReflectionError> {
+    reflection
+        .input_parameters
+        .iter()
+        .filter(|parameter| !parameter.system_value)
+        .map(|parameter| {
+            let components = 8 - parameter.mask.leading_zeros();
+            let format = match (parameter.component_type, components) {
+                (ComponentType::Float32, 1) => Format::R32_FLOAT,
+                (ComponentType::Float32, 2) => Format::R32G32_FLOAT,
+                (ComponentType::Float32, 3) => Format::R32G32B32_FLOAT,
+                (ComponentType::Float32, 4) => Format::R32G32B32A32_FLOAT,
+                (ComponentType::Uint32, 1) => Format::R32_UINT,
+                (ComponentType::Uint32, 2) => Format::R32G32_UINT,
+                (ComponentType::Uint32, 3) => Format::R32G32B32_UINT,
+                (ComponentType::Uint32, 4) => Format::R32G32B32A32_UINT,
+                (ComponentType::Sint32, 1) => Format::R32_SINT,
+                (ComponentType::Sint32, 2) => Format::R32G32_SINT,
+                (ComponentType::Sint32, 3) => Format::R32G32B32_SINT,
+                (ComponentType::Sint32, 4) => Format::R32G32B32A32_SINT,
+                _ => {
+                    return Err(ReflectionError::UnsupportedInput {
+                        semantic: parameter.semantic.clone(),
+                        semantic_index: parameter.semantic_index,
+                    })
+                }
+            };
+            Ok(InputElement::new(parameter.semantic.as_str(), format)
+                .index(parameter.semantic_index))
+        })
+        .collect()
+}
+
+/// A root signature binding everything the shaders of one pipeline use.
+///
+/// Single constant buffers become root CBVs; everything else goes in one
+/// descriptor table per visibility, with samplers in a table of their own
+/// and consecutive registers sharing a range.
+/// A binding used by one stage is only visible to that stage. Overlapping
+/// ranges from different stages are merged and made visible to all.
+pub fn root_signature(shaders: &[&ShaderReflection]) -> Result<RootSignatureDesc, ReflectionError> {
+    let mut bindings: Vec<Binding> = Vec::new();
+    for shader in shaders {
+        for resource in &shader.bound_resources {
+            bindings.push(Binding {
+                descriptor_type: resource.kind.descriptor_type(),
+                space: resource.space,
+                register: resource.register,
+                count: resource.count,
+                stages: vec![shader.stage],
+            });
+        }
+    }
+    let bindings = merge_overlapping(bindings);
+
+    let mut flags = RootSignatureFlags::NONE;
+    let vertex_input = shaders.iter().any(|shader| {
+        shader.stage == ShaderStage::Vertex
+            && shader.input_parameters.iter().any(|p| !p.system_value)
+    });
+    if vertex_input {
+        flags = flags | RootSignatureFlags::ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT;
+    }
+    let mut builder = RootSignatureBuilder::new().flags(flags);
+
+    let mut visibilities: Vec<ShaderVisibility> =
+        bindings.iter().map(Binding::visibility).collect();
+    visibilities.sort();
+    visibilities.dedup();
+
+    for &visibility in &visibilities {
+        for binding in bindings.iter().filter(|b| b.visibility() == visibility) {
+            if binding.is_root_cbv() {
+                builder = builder.parameter(RootParameter::Descriptor {
+                    descriptor_type: DescriptorType::Cbv,
+                    register: binding.register,
+                    space: binding.space,
+                    flags: DescriptorFlags::NONE,
+                    visibility,
+                });
+            }
+        }
+    }
+
+    for samplers in [false, true] {
+        for &visibility in &visibilities {
+            let mut ranges: Vec<DescriptorRange> = Vec::new();
+            let table_bindings = bindings
+                .iter()
+                .filter(|b| b.visibility() == visibility && !b.is_root_cbv())
+                .filter(|b| (b.descriptor_type == DescriptorType::Sampler) == samplers);
+            for binding in table_bindings {
+                // Bindings are sorted, so consecutive registers extend the last range.
+                match ranges.last_mut() {
+                    Some(last)
+                        if last.range_type == binding.descriptor_type
+                            && last.space == binding.space
+                            && last.count != UNBOUNDED
+                            && last.base_register + last.count == binding.register =>
+                    {
+                        last.count = if binding.count == UNBOUNDED {
+                            UNBOUNDED
+                        } else {
+                            last.count + binding.count
+                        };
+                    }
+                    _ => ranges.push(
+                        DescriptorRange::new(
+                            binding.descriptor_type,
+                            binding.count,
+                            binding.register,
+                        )
+                        .space(binding.space),
+                    ),
+                }
+            }
+            if !ranges.is_empty() {
+                builder = builder.descriptor_table(ranges, visibility);
+            }
+        }
+    }
+
+    Ok(builder.build()?)
+}
+
+struct Binding {
+    descriptor_type: DescriptorType,
+    space: u32,
+    register: u32,
+    count: u32,
+    stages: Vec<ShaderStage>,
+}
+
+impl Binding {
+    fn end(&self) -> u64 {
+        if self.count == UNBOUNDED {
+            u64::MAX
+        } else {
+            self.register as u64 + self.count as u64
+        }
+    }
+
+    fn is_root_cbv(&self) -> bool {
+        self.descriptor_type == DescriptorType::Cbv && self.count == 1
+    }
+
+    fn visibility(&self) -> ShaderVisibility {
+        match self.stages[..] {
+            [ShaderStage::Vertex] => ShaderVisibility::Vertex,
+            [ShaderStage::Hull] => ShaderVisibility::Hull,
+            [ShaderStage::Domain] => ShaderVisibility::Domain,
+            [ShaderStage::Geometry] => ShaderVisibility::Geometry,
+            [ShaderStage::Pixel] => ShaderVisibility::Pixel,
+            [ShaderStage::Amplification] => ShaderVisibility::Amplification,
+            [ShaderStage::Mesh] => ShaderVisibility::Mesh,
+            _ => ShaderVisibility::All,
+        }
+    }
+}
+
+/// Sorts by type, space and register, and joins bindings whose registers
+/// overlap, so each register ends up in exactly one range.
+fn merge_overlapping(mut bindings: Vec<Binding>) -> Vec<Binding> {
+    bindings.sort_by_key(|b| (b.descriptor_type as u8, b.space, b.register));
+
+    let mut merged: Vec<Binding> = Vec::new();
+    for binding in bindings {
+        match merged.last_mut() {
+            Some(last)
+                if last.descriptor_type == binding.descriptor_type
+                    && last.space == binding.space
+                    && (binding.register as u64) < last.end() =>
+            {
+                let end = last.end().max(binding.end());
+                last.count = if end == u64::MAX {
+                    UNBOUNDED
+                } else {
+                    (end - last.register as u64) as u32
+                };
+                for stage in binding.stages {
+                    if !last.stages.contains(&stage) {
+                        last.stages.push(stage);
+                    }
+                }
+            }
+            _ => merged.push(binding),
+        }
+    }
+    merged
+}
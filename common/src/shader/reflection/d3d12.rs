@@ -0,0 +1,218 @@
+use windows::{
+    core::{Interface, PCSTR},
+    Win32::{
+        Foundation::E_INVALIDARG,
+        Graphics::{
+            Direct3D::{
+                Dxc::{CLSID_DxcUtils, DxcBuffer, IDxcUtils},
+                Fxc::D3DReflect,
+                D3D_NAME_UNDEFINED, D3D_REGISTER_COMPONENT_FLOAT32, D3D_REGISTER_COMPONENT_SINT32,
+                D3D_REGISTER_COMPONENT_TYPE, D3D_REGISTER_COMPONENT_UINT32, D3D_SHADER_INPUT_TYPE,
+                D3D_SIT_BYTEADDRESS, D3D_SIT_CBUFFER, D3D_SIT_RTACCELERATIONSTRUCTURE,
+                D3D_SIT_SAMPLER, D3D_SIT_STRUCTURED, D3D_SIT_TBUFFER, D3D_SIT_TEXTURE,
+                D3D_SIT_UAV_APPEND_STRUCTURED, D3D_SIT_UAV_CONSUME_STRUCTURED,
+                D3D_SIT_UAV_FEEDBACKTEXTURE, D3D_SIT_UAV_RWBYTEADDRESS, D3D_SIT_UAV_RWSTRUCTURED,
+                D3D_SIT_UAV_RWSTRUCTURED_WITH_COUNTER, D3D_SIT_UAV_RWTYPED,
+            },
+            Direct3D12::{
+                ID3D12ShaderReflection, D3D12_SHADER_BUFFER_DESC, D3D12_SHADER_DESC,
+                D3D12_SHADER_INPUT_BIND_DESC, D3D12_SHADER_VARIABLE_DESC,
+                D3D12_SHVER_AMPLIFICATION_SHADER, D3D12_SHVER_COMPUTE_SHADER,
+                D3D12_SHVER_DOMAIN_SHADER, D3D12_SHVER_GEOMETRY_SHADER, D3D12_SHVER_HULL_SHADER,
+                D3D12_SHVER_MESH_SHADER, D3D12_SHVER_PIXEL_SHADER, D3D12_SHVER_VERTEX_SHADER,
+                D3D12_SIGNATURE_PARAMETER_DESC,
+            },
+        },
+    },
+};
+
+use super::{
+    BoundResource, ComponentType, ConstantBufferLayout, ResourceKind, ShaderReflection,
+    ShaderVariable, SignatureParameter,
+};
+use crate::{
+    gfx::root_signature::UNBOUNDED,
+    shader::{compiler::create_dxc_instance, ShaderStage},
+};
+
+/// Reflects FXC (DXBC) or DXC (DXIL) bytecode. DXIL goes through
+/// `dxcompiler.dll`, since `D3DReflect` only understands DXBC.
+pub fn reflect(bytecode: &[u8]) -> windows::core::Result<ShaderReflection> {
+    let reflection: ID3D12ShaderReflection = unsafe {
+        if has_part(bytecode, b"DXIL") {
+            let utils: IDxcUtils = create_dxc_instance(&CLSID_DxcUtils)?;
+            let buffer = DxcBuffer {
+                Ptr: bytecode.as_ptr() as _,
+                Size: bytecode.len(),
+                Encoding: 0,
+            };
+            let mut reflection = std::ptr::null_mut();
+            utils.CreateReflection(&buffer, &ID3D12ShaderReflection::IID, &mut reflection)?;
+            ID3D12ShaderReflection::from_raw(reflection)
+        } else {
+            let mut reflection = std::ptr::null_mut();
+            D3DReflect(
+                bytecode.as_ptr() as _,
+                bytecode.len(),
+                &ID3D12ShaderReflection::IID,
+                &mut reflection,
+            )?;
+            ID3D12ShaderReflection::from_raw(reflection)
+        }
+    };
+
+    unsafe {
+        let mut desc = D3D12_SHADER_DESC::default();
+        reflection.GetDesc(&mut desc)?;
+
+        let mut input_parameters = Vec::new();
+        for index in 0..desc.InputParameters {
+            let mut parameter = D3D12_SIGNATURE_PARAMETER_DESC::default();
+            reflection.GetInputParameterDesc(index, &mut parameter)?;
+            input_parameters.push(SignatureParameter {
+                semantic: string(parameter.SemanticName),
+                semantic_index: parameter.SemanticIndex,
+                register: parameter.Register,
+                system_value: parameter.SystemValueType != D3D_NAME_UNDEFINED,
+                component_type: component_type(parameter.ComponentType),
+                mask: parameter.Mask,
+            });
+        }
+
+        let mut constant_buffers = Vec::new();
+        for index in 0..desc.ConstantBuffers {
+            let Some(buffer) = reflection.GetConstantBufferByIndex(index) else {
+                continue;
+            };
+            let mut buffer_desc = D3D12_SHADER_BUFFER_DESC::default();
+            buffer.GetDesc(&mut buffer_desc)?;
+
+            let mut variables = Vec::new();
+            for variable in 0..buffer_desc.Variables {
+                let Some(variable) = buffer.GetVariableByIndex(variable) else {
+                    continue;
+                };
+                let mut variable_desc = D3D12_SHADER_VARIABLE_DESC::default();
+                variable.GetDesc(&mut variable_desc)?;
+                variables.push(ShaderVariable {
+                    name: string(variable_desc.Name),
+                    offset: variable_desc.StartOffset,
+                    size: variable_desc.Size,
+                });
+            }
+
+            constant_buffers.push(ConstantBufferLayout {
+                name: string(buffer_desc.Name),
+                size: buffer_desc.Size,
+                variables,
+            });
+        }
+
+        let mut bound_resources = Vec::new();
+        for index in 0..desc.BoundResources {
+            let mut binding = D3D12_SHADER_INPUT_BIND_DESC::default();
+            reflection.GetResourceBindingDesc(index, &mut binding)?;
+            let Some(kind) = resource_kind(binding.Type) else {
+                continue;
+            };
+            bound_resources.push(BoundResource {
+                name: string(binding.Name),
+                kind,
+                register: binding.BindPoint,
+                // DXC reports unbounded arrays with a count of zero.
+                count: if binding.BindCount == 0 {
+                    UNBOUNDED
+                } else {
+                    binding.BindCount
+                },
+                space: binding.Space,
+            });
+        }
+
+        Ok(ShaderReflection {
+            stage: stage(desc.Version)?,
+            input_parameters,
+            constant_buffers,
+            bound_resources,
+        })
+    }
+}
+
+/// Whether a shader container has a part with the given four-character
+/// code. The container starts with `DXBC`, a 16-byte digest, a version, the
+/// total size and the part count, followed by the offset of each part.
+fn has_part(bytecode: &[u8], fourcc: &[u8; 4]) -> bool {
+    let u32_at = |offset: usize| {
+        bytecode
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    };
+    if bytecode.get(..4) != Some(b"DXBC") {
+        return false;
+    }
+
+    let parts = u32_at(28).unwrap_or(0);
+    (0..parts).any(|part| {
+        u32_at(32 + part * 4)
+            .and_then(|offset| bytecode.get(offset..offset + 4))
+            .is_some_and(|code| code == fourcc)
+    })
+}
+
+unsafe fn string(s: PCSTR) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        s.to_string().unwrap_or_default()
+    }
+}
+
+fn stage(version: u32) -> windows::core::Result<ShaderStage> {
+    let program_type = ((version >> 16) & 0xffff) as i32;
+    let stage = match program_type {
+        t if t == D3D12_SHVER_VERTEX_SHADER.0 => ShaderStage::Vertex,
+        t if t == D3D12_SHVER_PIXEL_SHADER.0 => ShaderStage::Pixel,
+        t if t == D3D12_SHVER_GEOMETRY_SHADER.0 => ShaderStage::Geometry,
+        t if t == D3D12_SHVER_HULL_SHADER.0 => ShaderStage::Hull,
+        t if t == D3D12_SHVER_DOMAIN_SHADER.0 => ShaderStage::Domain,
+        t if t == D3D12_SHVER_COMPUTE_SHADER.0 => ShaderStage::Compute,
+        t if t == D3D12_SHVER_AMPLIFICATION_SHADER.0 => ShaderStage::Amplification,
+        t if t == D3D12_SHVER_MESH_SHADER.0 => ShaderStage::Mesh,
+        _ => {
+            return Err(windows::core::Error::new(
+                E_INVALIDARG,
+                "unsupported shader type",
+            ))
+        }
+    };
+    Ok(stage)
+}
+
+fn component_type(component_type: D3D_REGISTER_COMPONENT_TYPE) -> ComponentType {
+    match component_type {
+        D3D_REGISTER_COMPONENT_UINT32 => ComponentType::Uint32,
+        D3D_REGISTER_COMPONENT_SINT32 => ComponentType::Sint32,
+        D3D_REGISTER_COMPONENT_FLOAT32 => ComponentType::Float32,
+        _ => ComponentType::Unknown,
+    }
+}
+
+fn resource_kind(input_type: D3D_SHADER_INPUT_TYPE) -> Option<ResourceKind> {
+    Some(match input_type {
+        D3D_SIT_CBUFFER => ResourceKind::ConstantBuffer,
+        D3D_SIT_TBUFFER => ResourceKind::TextureBuffer,
+        D3D_SIT_TEXTURE => ResourceKind::Texture,
+        D3D_SIT_SAMPLER => ResourceKind::Sampler,
+        D3D_SIT_STRUCTURED => ResourceKind::StructuredBuffer,
+        D3D_SIT_BYTEADDRESS => ResourceKind::ByteAddressBuffer,
+        D3D_SIT_RTACCELERATIONSTRUCTURE => ResourceKind::AccelerationStructure,
+        D3D_SIT_UAV_RWTYPED => ResourceKind::RwTexture,
+        D3D_SIT_UAV_RWSTRUCTURED => ResourceKind::RwStructuredBuffer,
+        D3D_SIT_UAV_RWBYTEADDRESS => ResourceKind::RwByteAddressBuffer,
+        D3D_SIT_UAV_APPEND_STRUCTURED => ResourceKind::AppendStructuredBuffer,
+        D3D_SIT_UAV_CONSUME_STRUCTURED => ResourceKind::ConsumeStructuredBuffer,
+        D3D_SIT_UAV_RWSTRUCTURED_WITH_COUNTER => ResourceKind::RwStructuredBufferWithCounter,
+        D3D_SIT_UAV_FEEDBACKTEXTURE => ResourceKind::FeedbackTexture,
+        _ => return None,
+    })
+}
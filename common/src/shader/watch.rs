@@ -0,0 +1,263 @@
+#[cfg(windows)]
+mod d3d12;
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use super::{CompiledShader, ShaderDesc, ShaderError, ShaderStage};
+use crate::gfx::pipeline_state::PipelineDesc;
+
+/// Editors often write a file several times when saving, so a change is only
+/// reported once a key has been quiet for `delay`.
+pub struct Debouncer<K> {
+    delay: Duration,
+    pending: Vec<(K, Instant)>,
+}
+
+impl<K: PartialEq> Debouncer<K> {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn notify(&mut self, key: K, now: Instant) {
+        match self.pending.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, last)) => *last = now,
+            None => self.pending.push((key, now)),
+        }
+    }
+
+    /// Keys that have settled, in the order they first changed.
+    pub fn ready(&mut self, now: Instant) -> Vec<K> {
+        let mut ready = Vec::new();
+        let mut i = 0;
+        while i < self.pending.len() {
+            if now.saturating_duration_since(self.pending[i].1) >= self.delay {
+                ready.push(self.pending.remove(i).0);
+            } else {
+                i += 1;
+            }
+        }
+        ready
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Polls modification times; cheap enough to call once a frame for the
+/// handful of files a sample's shaders are built from.
+pub struct FileWatcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    debouncer: Debouncer<PathBuf>,
+}
+
+impl FileWatcher {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            files: Vec::new(),
+            debouncer: Debouncer::new(delay),
+        }
+    }
+
+    pub fn watch(&mut self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        if !self.files.iter().any(|(p, _)| *p == path) {
+            let modified = modified(&path);
+            self.files.push((path, modified));
+        }
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        self.files.retain(|(p, _)| p != path);
+    }
+
+    pub fn is_watching(&self, path: &Path) -> bool {
+        self.files.iter().any(|(p, _)| p == path)
+    }
+
+    /// Files that changed, or were deleted or created, and have since settled.
+    pub fn poll(&mut self, now: Instant) -> Vec<PathBuf> {
+        for (path, last) in &mut self.files {
+            let current = modified(path);
+            if current != *last {
+                *last = current;
+                self.debouncer.notify(path.clone(), now);
+            }
+        }
+        self.debouncer.ready(now)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Tracks which files each shader (or pipeline) was built from and reports
+/// the ones to rebuild when any of those files change. `reload` rebuilds
+/// them, swaps them in and registers the new dependency lists, since edits
+/// can add or remove includes.
+pub struct HotReload<K> {
+    watcher: FileWatcher,
+    dependencies: Vec<(K, Vec<PathBuf>)>,
+}
+
+impl<K: Clone + PartialEq> HotReload<K> {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            watcher: FileWatcher::new(delay),
+            dependencies: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, key: K, dependencies: impl IntoIterator<Item = PathBuf>) {
+        let dependencies: Vec<PathBuf> = dependencies.into_iter().collect();
+        for path in &dependencies {
+            self.watcher.watch(path.clone());
+        }
+
+        match self.dependencies.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => *existing = dependencies,
+            None => self.dependencies.push((key, dependencies)),
+        }
+        self.unwatch_unused();
+    }
+
+    pub fn unregister(&mut self, key: &K) {
+        self.dependencies.retain(|(k, _)| k != key);
+        self.unwatch_unused();
+    }
+
+    /// Keys with at least one changed dependency, each reported once, in the
+    /// order they were registered.
+    pub fn poll(&mut self, now: Instant) -> Vec<K> {
+        let changed = self.watcher.poll(now);
+        if changed.is_empty() {
+            return Vec::new();
+        }
+
+        self.dependencies
+            .iter()
+            .filter(|(_, dependencies)| dependencies.iter().any(|d| changed.contains(d)))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Rebuilds the keys `poll` reports and swaps each result into `live`. A
+    /// failed build, e.g. of a file saved halfway through an edit, keeps the
+    /// old value and dependencies so the next save tries again.
+    pub fn reload<T, E>(
+        &mut self,
+        now: Instant,
+        live: &mut Vec<(K, T)>,
+        mut rebuild: impl FnMut(&K) -> Result<Rebuilt<T>, E>,
+    ) -> Vec<(K, Result<(), E>)> {
+        self.poll(now)
+            .into_iter()
+            .map(|key| {
+                let result = rebuild(&key).map(|rebuilt| {
+                    match live.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, value)) => *value = rebuilt.value,
+                        None => live.push((key.clone(), rebuilt.value)),
+                    }
+                    self.register(key.clone(), rebuilt.dependencies);
+                });
+                (key, result)
+            })
+            .collect()
+    }
+
+    fn unwatch_unused(&mut self) {
+        let watched: Vec<PathBuf> = self.watcher.files.iter().map(|(p, _)| p.clone()).collect();
+        for path in watched {
+            let used = self
+                .dependencies
+                .iter()
+                .any(|(_, dependencies)| dependencies.contains(&path));
+            if !used {
+                self.watcher.unwatch(&path);
+            }
+        }
+    }
+}
+
+/// A rebuilt object and the files it was built from this time.
+#[derive(Clone, Debug)]
+pub struct Rebuilt<T> {
+    pub value: T,
+    pub dependencies: Vec<PathBuf>,
+}
+
+/// The description of a pipeline and the shaders whose bytecode goes in it,
+/// so it can be rebuilt when they change.
+#[derive(Clone, Debug)]
+pub struct ReloadablePipeline {
+    pub desc: PipelineDesc,
+    pub shaders: Vec<ShaderDesc>,
+    /// The cache key of the pipeline last built from this.
+    built: Option<u64>,
+}
+
+impl ReloadablePipeline {
+    pub fn new(desc: PipelineDesc, shaders: impl IntoIterator<Item = ShaderDesc>) -> Self {
+        Self {
+            desc,
+            shaders: shaders.into_iter().collect(),
+            built: None,
+        }
+    }
+
+    /// Records that the pipeline with cache key `key` is now the one built
+    /// from this, and returns the key of the one it replaces, if different.
+    pub fn replace_built(&mut self, key: u64) -> Option<u64> {
+        self.built.replace(key).filter(|&old| old != key)
+    }
+
+    /// The description with freshly compiled bytecode in each shader's slot,
+    /// and every file any of the shaders was built from.
+    pub fn recompile(
+        &self,
+        mut compile: impl FnMut(&ShaderDesc) -> Result<CompiledShader, ShaderError>,
+    ) -> Result<Rebuilt<PipelineDesc>, ShaderError> {
+        let mut desc = self.desc.clone();
+        let mut dependencies: Vec<PathBuf> = Vec::new();
+
+        for shader in &self.shaders {
+            let compiled = compile(shader)?;
+            *shader_slot(&mut desc, shader)? = compiled.bytecode;
+            for path in compiled.dependencies {
+                if !dependencies.contains(&path) {
+                    dependencies.push(path);
+                }
+            }
+        }
+
+        Ok(Rebuilt {
+            value: desc,
+            dependencies,
+        })
+    }
+}
+
+fn shader_slot<'a>(
+    desc: &'a mut PipelineDesc,
+    shader: &ShaderDesc,
+) -> Result<&'a mut Vec<u8>, ShaderError> {
+    match (desc, shader.stage) {
+        (PipelineDesc::Graphics(desc), ShaderStage::Vertex) => Ok(&mut desc.vs),
+        (PipelineDesc::Graphics(desc), ShaderStage::Pixel) => Ok(&mut desc.ps),
+        (PipelineDesc::Graphics(desc), ShaderStage::Domain) => Ok(&mut desc.ds),
+        (PipelineDesc::Graphics(desc), ShaderStage::Hull) => Ok(&mut desc.hs),
+        (PipelineDesc::Graphics(desc), ShaderStage::Geometry) => Ok(&mut desc.gs),
+        (PipelineDesc::Compute(desc), ShaderStage::Compute) => Ok(&mut desc.cs),
+        (_, stage) => Err(ShaderError::StageMismatch {
+            path: shader.path.clone(),
+            stage,
+        }),
+    }
+}
@@ -0,0 +1,40 @@
+use std::error::Error;
+
+use windows::Win32::Graphics::Direct3D12::{ID3D12PipelineState, ID3D12RootSignature};
+
+use super::{Rebuilt, ReloadablePipeline};
+use crate::{
+    gfx::{
+        backend::D3d12,
+        pipeline_state::{pipeline_key, PipelineCache},
+        root_signature::RootSignatureDesc,
+        DeferredDeleter,
+    },
+    shader::ShaderCompiler,
+};
+
+impl ReloadablePipeline {
+    /// Recompiles the shaders and gets the pipeline for the new bytecode from
+    /// `cache`. The pipeline this replaces leaves the cache and is released
+    /// once the GPU passes `fence_value`, so frames still using it are safe.
+    pub fn rebuild(
+        &mut self,
+        compiler: &mut ShaderCompiler,
+        cache: &mut PipelineCache,
+        root_signature: &ID3D12RootSignature,
+        root_signature_desc: &RootSignatureDesc,
+        deleter: &mut DeferredDeleter<D3d12>,
+        fence_value: u64,
+    ) -> Result<Rebuilt<ID3D12PipelineState>, Box<dyn Error>> {
+        let rebuilt = self.recompile(|shader| compiler.compile(shader))?;
+        let pipeline = cache.get_or_create(root_signature, root_signature_desc, &rebuilt.value)?;
+        let key = pipeline_key(root_signature_desc, &rebuilt.value);
+        if let Some(replaced) = self.replace_built(key) {
+            cache.remove(replaced, deleter, fence_value);
+        }
+        Ok(Rebuilt {
+            value: pipeline,
+            dependencies: rebuilt.dependencies,
+        })
+    }
+}
@@ -0,0 +1,301 @@
+// Frame timing. `FrameTimer` measures how long each frame took,
+// `FixedTimestep` turns those variable deltas into a whole number of
+// constant simulation steps, and `FrameStats` keeps a window of recent frame
+// times for display. Time comes from a `Clock` so tests can drive it.
+
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+pub trait Clock {
+    /// Time since some fixed point, which never goes backwards.
+    fn now(&self) -> Duration;
+}
+
+/// The monotonic system clock, which is QueryPerformanceCounter on Windows.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a
+/// test can keep one and hand another to a `FrameTimer`.
+#[derive(Clone, Debug, Default)]
+pub struct FakeClock {
+    now: Rc<Cell<Duration>>,
+}
+
+impl FakeClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+/// Long enough for a 10 Hz frame, short enough that a breakpoint or a
+/// dragged window doesn't make the simulation jump.
+pub const DEFAULT_MAX_DELTA: Duration = Duration::from_millis(100);
+
+pub struct FrameTimer<C: Clock = SystemClock> {
+    clock: C,
+    last: Duration,
+    max_delta: Duration,
+    raw_delta: Duration,
+    delta: Duration,
+    elapsed: Duration,
+    frame_count: u64,
+}
+
+impl FrameTimer<SystemClock> {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock::new())
+    }
+}
+
+impl Default for FrameTimer<SystemClock> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> FrameTimer<C> {
+    /// The first `tick` measures from here.
+    pub fn with_clock(clock: C) -> Self {
+        let last = clock.now();
+        Self {
+            clock,
+            last,
+            max_delta: DEFAULT_MAX_DELTA,
+            raw_delta: Duration::ZERO,
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            frame_count: 0,
+        }
+    }
+
+    pub fn max_delta(mut self, max_delta: Duration) -> Self {
+        self.max_delta = max_delta;
+        self
+    }
+
+    /// Starts a new frame and returns how long the last one took, clamped to
+    /// the maximum delta.
+    pub fn tick(&mut self) -> Duration {
+        let now = self.clock.now();
+        self.raw_delta = now.saturating_sub(self.last);
+        self.delta = self.raw_delta.min(self.max_delta);
+        self.last = now;
+        self.elapsed += self.delta;
+        self.frame_count += 1;
+        self.delta
+    }
+
+    /// The clamped length of the last frame.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    /// The measured length of the last frame, for statistics.
+    pub fn raw_delta(&self) -> Duration {
+        self.raw_delta
+    }
+
+    /// The sum of the clamped deltas, which is the time the simulation saw.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+}
+
+/// Accumulates frame time and hands it back in fixed steps:
+///
+/// ```ignore
+/// for _ in 0..fixed.advance(timer.tick()) {
+///     simulate(fixed.step_seconds());
+/// }
+/// render(fixed.alpha());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedTimestep {
+    step: Duration,
+    accumulator: Duration,
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration) -> Self {
+        assert!(!step.is_zero(), "a fixed timestep can't be zero");
+        Self {
+            step,
+            accumulator: Duration::ZERO,
+            max_steps: 8,
+        }
+    }
+
+    pub fn from_hz(hz: u32) -> Self {
+        Self::new(Duration::from_secs(1) / hz)
+    }
+
+    /// Caps the steps per frame. Time beyond that is dropped, so a
+    /// simulation slower than real time falls behind instead of spiralling.
+    pub fn max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Adds a frame's time and returns how many steps to simulate.
+    pub fn advance(&mut self, delta: Duration) -> u32 {
+        let accumulated = (self.accumulator + delta).as_nanos();
+        let step = self.step.as_nanos();
+        self.accumulator = Duration::from_nanos((accumulated % step) as u64);
+        (accumulated / step).min(self.max_steps as u128) as u32
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    pub fn step_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// How far between the last step and the next one the current frame
+    /// is, from 0 to 1, for interpolating what's rendered.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+/// Frame times over the last `capacity` frames.
+#[derive(Clone, Debug)]
+pub struct FrameStats {
+    samples: VecDeque<Duration>,
+    capacity: usize,
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0);
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn average(&self) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<Duration>() / self.samples.len() as u32)
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.samples.iter().min().copied()
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.samples.iter().max().copied()
+    }
+
+    /// The nearest-rank percentile: the shortest frame time that at least
+    /// `percent`% of frames are no longer than.
+    pub fn percentile(&self, percent: f64) -> Option<Duration> {
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted.get(rank.max(1) - 1).copied()
+    }
+
+    /// Frames per second from the average frame time.
+    pub fn fps(&self) -> Option<f64> {
+        Some(1.0 / self.average()?.as_secs_f64())
+    }
+}
+
+/// `"60.0 fps, 16.67 ms (min 16.01, max 17.50, 99% 17.42)"`, for a window
+/// title.
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (Some(average), Some(min), Some(max), Some(p99)) = (
+            self.average(),
+            self.min(),
+            self.max(),
+            self.percentile(99.0),
+        ) else {
+            return write!(f, "-- fps");
+        };
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "{:.1} fps, {:.2} ms (min {:.2}, max {:.2}, 99% {:.2})",
+            1.0 / average.as_secs_f64(),
+            ms(average),
+            ms(min),
+            ms(max),
+            ms(p99)
+        )
+    }
+}
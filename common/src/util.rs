@@ -1,5 +1,10 @@
-use std::ffi::CString;
+use std::{
+    ffi::CString,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
+#[cfg(windows)]
 use windows::{core::PCSTR, Win32::System::Diagnostics::Debug::OutputDebugStringA};
 
 pub trait AsCString {
@@ -18,6 +23,9 @@ impl AsCString for &str {
     }
 }
 
+/// Goes to the debugger's output window on Windows and stderr elsewhere,
+/// in debug builds only.
+#[cfg(windows)]
 pub fn print_debug_string(s: &str) {
     if cfg!(debug_assertions) {
         let message = s.as_c_string();
@@ -26,3 +34,29 @@ pub fn print_debug_string(s: &str) {
         }
     }
 }
+
+#[cfg(not(windows))]
+pub fn print_debug_string(s: &str) {
+    if cfg!(debug_assertions) {
+        eprintln!("{s}");
+    }
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so
+/// readers never see a partial file. The temporary name is unique to the
+/// process and the call, so concurrent writers don't clobber each other.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp);
+    })
+}
@@ -0,0 +1,482 @@
+use std::{cell::RefCell, collections::VecDeque, error::Error, rc::Rc, time::Duration};
+
+use common::{
+    app::{
+        run, Backend, Config, Event, Frame, HeadlessPlatform, NullBackend, Platform, Sample,
+        DEFAULT_HEADLESS_FRAMES,
+    },
+    input::{InputEvent, Key},
+    time::FakeClock,
+};
+
+type Log = Rc<RefCell<Vec<String>>>;
+
+fn log(log: &Log, entry: impl Into<String>) {
+    log.borrow_mut().push(entry.into());
+}
+
+/// Replays one batch of events per poll, advancing the clock 10ms each
+/// time, and reports the window closed when it runs out.
+struct MockPlatform {
+    frames: VecDeque<Vec<Event>>,
+    clock: FakeClock,
+    titles: Vec<String>,
+    log: Log,
+}
+
+impl Platform for MockPlatform {
+    fn poll_events(&mut self, events: &mut Vec<Event>) -> bool {
+        self.clock.advance(Duration::from_millis(10));
+        match self.frames.pop_front() {
+            Some(frame) => {
+                events.extend(frame);
+                true
+            }
+            None => {
+                log(&self.log, "closed");
+                false
+            }
+        }
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.titles.push(title.to_string());
+    }
+}
+
+#[derive(Default)]
+struct MockBackend {
+    log: Log,
+    fail_present: bool,
+    size: (u32, u32),
+}
+
+impl Backend for MockBackend {
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+        log(&self.log, format!("resize {width}x{height}"));
+        self.size = (width, height);
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), Box<dyn Error>> {
+        log(&self.log, "present");
+        if self.fail_present {
+            return Err("device removed".into());
+        }
+        Ok(())
+    }
+
+    fn wait_idle(&mut self) {
+        log(&self.log, "wait_idle");
+    }
+}
+
+/// Logs every call, and fails `init` or `render` when the config title
+/// says so.
+struct MockSample {
+    log: Log,
+    fail_render: bool,
+}
+
+impl Sample<MockBackend> for MockSample {
+    const TITLE: &'static str = "Mock";
+
+    fn init(config: &Config, backend: &mut MockBackend) -> Result<Self, Box<dyn Error>> {
+        log(&backend.log, "init");
+        if config.title == "fail init" {
+            return Err("no adapter".into());
+        }
+        Ok(Self {
+            log: backend.log.clone(),
+            fail_render: config.title == "fail render",
+        })
+    }
+
+    fn update(&mut self, frame: &Frame) {
+        let w = if frame.input.is_key_down(Key::W) {
+            " W"
+        } else {
+            ""
+        };
+        log(
+            &self.log,
+            format!("update {} {:.3}{w}", frame.index, frame.dt),
+        );
+    }
+
+    fn render(&mut self, backend: &mut MockBackend) -> Result<(), Box<dyn Error>> {
+        log(
+            &self.log,
+            format!("render {}x{}", backend.size.0, backend.size.1),
+        );
+        if self.fail_render {
+            return Err("out of memory".into());
+        }
+        Ok(())
+    }
+
+    fn on_resize(
+        &mut self,
+        _backend: &mut MockBackend,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        log(&self.log, format!("on_resize {width}x{height}"));
+        Ok(())
+    }
+
+    fn on_event(&mut self, event: &Event) {
+        log(&self.log, format!("on_event {event:?}"));
+    }
+
+    fn shutdown(&mut self, _backend: &mut MockBackend) {
+        log(&self.log, "shutdown");
+    }
+}
+
+struct Harness {
+    platform: MockPlatform,
+    backend: MockBackend,
+    clock: FakeClock,
+    log: Log,
+}
+
+impl Harness {
+    fn new(frames: Vec<Vec<Event>>) -> Self {
+        let log = Log::default();
+        let clock = FakeClock::new();
+        Self {
+            platform: MockPlatform {
+                frames: frames.into(),
+                clock: clock.clone(),
+                titles: Vec::new(),
+                log: log.clone(),
+            },
+            backend: MockBackend {
+                log: log.clone(),
+                fail_present: false,
+                size: (800, 600),
+            },
+            clock,
+            log,
+        }
+    }
+
+    fn run(&mut self, config: &Config) -> Result<(), String> {
+        run::<MockSample, _, _, _>(
+            config,
+            &mut self.platform,
+            &mut self.backend,
+            self.clock.clone(),
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    fn log(&self) -> Vec<String> {
+        self.log.borrow().clone()
+    }
+}
+
+fn config() -> Config {
+    MockSample::config()
+}
+
+#[test]
+fn lifecycle_order() {
+    let mut harness = Harness::new(vec![vec![], vec![]]);
+
+    assert_eq!(harness.run(&config()), Ok(()));
+    assert_eq!(
+        harness.log(),
+        [
+            "init",
+            "update 0 0.010",
+            "render 800x600",
+            "present",
+            "update 1 0.010",
+            "render 800x600",
+            "present",
+            "closed",
+            "wait_idle",
+            "shutdown",
+        ]
+    );
+}
+
+#[test]
+fn events_reach_input_and_the_sample() {
+    let w = Event::Input(InputEvent::KeyDown(Key::W));
+    let mut harness = Harness::new(vec![vec![w], vec![]]);
+
+    assert_eq!(harness.run(&config()), Ok(()));
+    assert_eq!(
+        harness.log()[1..5],
+        [
+            "on_event Input(KeyDown(W))",
+            "update 0 0.010 W",
+            "render 800x600",
+            "present",
+        ]
+    );
+    // Still held on the next frame.
+    assert_eq!(harness.log()[5], "update 1 0.010 W");
+}
+
+#[test]
+fn resizing_waits_for_the_gpu_first() {
+    let resized = Event::Resized {
+        width: 1024,
+        height: 768,
+    };
+    let mut harness = Harness::new(vec![vec![resized]]);
+
+    assert_eq!(harness.run(&config()), Ok(()));
+    assert_eq!(
+        harness.log()[1..7],
+        [
+            "wait_idle",
+            "resize 1024x768",
+            "on_resize 1024x768",
+            "on_event Resized { width: 1024, height: 768 }",
+            "update 0 0.010",
+            "render 1024x768",
+        ]
+    );
+}
+
+#[test]
+fn minimized_windows_skip_rendering() {
+    let minimized = Event::Resized {
+        width: 0,
+        height: 0,
+    };
+    let restored = Event::Resized {
+        width: 800,
+        height: 600,
+    };
+    let mut harness = Harness::new(vec![vec![minimized], vec![restored]]);
+
+    assert_eq!(harness.run(&config()), Ok(()));
+    let log = harness.log();
+    // Same size as before, so no resize when restored.
+    assert!(!log.iter().any(|entry| entry.starts_with("resize")));
+    assert_eq!(log[2], "update 0 0.010");
+    assert_eq!(log[3], "on_event Resized { width: 800, height: 600 }");
+    assert_eq!(log[4..7], ["update 1 0.010", "render 800x600", "present"]);
+}
+
+#[test]
+fn escape_exits() {
+    let escape = Event::Input(InputEvent::KeyDown(Key::Escape));
+    let mut harness = Harness::new(vec![vec![], vec![escape], vec![]]);
+
+    assert_eq!(harness.run(&config()), Ok(()));
+    let log = harness.log();
+    assert_eq!(log.iter().filter(|e| e.starts_with("update")).count(), 1);
+    assert_eq!(log[log.len() - 2..], ["wait_idle", "shutdown"]);
+    assert!(!log.contains(&"closed".to_string()));
+}
+
+#[test]
+fn failed_init_skips_shutdown() {
+    let mut harness = Harness::new(vec![vec![]]);
+    let config = Config::new("fail init");
+
+    assert_eq!(harness.run(&config), Err("init failed: no adapter".into()));
+    assert_eq!(harness.log(), ["init"]);
+}
+
+#[test]
+fn failed_frames_still_shut_down() {
+    let mut harness = Harness::new(vec![vec![], vec![]]);
+    assert_eq!(
+        harness.run(&Config::new("fail render")),
+        Err("render failed: out of memory".into())
+    );
+    assert_eq!(
+        harness.log(),
+        [
+            "init",
+            "update 0 0.010",
+            "render 800x600",
+            "wait_idle",
+            "shutdown"
+        ]
+    );
+
+    let mut harness = Harness::new(vec![vec![], vec![]]);
+    harness.backend.fail_present = true;
+    assert_eq!(
+        harness.run(&config()),
+        Err("present failed: device removed".into())
+    );
+    assert_eq!(harness.log()[3..], ["present", "wait_idle", "shutdown"]);
+}
+
+#[test]
+fn title_shows_frame_stats_every_second() {
+    let mut harness = Harness::new(vec![vec![]; 250]);
+
+    assert_eq!(harness.run(&config().args(["/WARP"])), Ok(()));
+    assert_eq!(harness.platform.titles.len(), 2);
+    assert_eq!(
+        harness.platform.titles[0],
+        "Mock (WARP) - 100.0 fps, 10.00 ms (min 10.00, max 10.00, 99% 10.00)"
+    );
+}
+
+#[test]
+fn frame_limit_stops_the_loop() {
+    let mut harness = Harness::new(vec![vec![]; 5]);
+
+    assert_eq!(harness.run(&config().args(["--frames=2"])), Ok(()));
+    let log = harness.log();
+    assert_eq!(log.iter().filter(|e| *e == "present").count(), 2);
+    assert_eq!(log[log.len() - 2..], ["wait_idle", "shutdown"]);
+    assert!(!log.contains(&"closed".to_string()));
+}
+
+/// Counts frames against the null backend.
+struct Counter {
+    updates: u64,
+    renders: u64,
+}
+
+impl Sample<NullBackend> for Counter {
+    const TITLE: &'static str = "Counter";
+
+    fn init(_config: &Config, _backend: &mut NullBackend) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            updates: 0,
+            renders: 0,
+        })
+    }
+
+    fn update(&mut self, frame: &Frame) {
+        assert_eq!(frame.index, self.updates);
+        self.updates += 1;
+    }
+
+    fn render(&mut self, backend: &mut NullBackend) -> Result<(), Box<dyn Error>> {
+        assert_eq!(backend.frames_presented, self.renders);
+        self.renders += 1;
+        Ok(())
+    }
+
+    fn shutdown(&mut self, backend: &mut NullBackend) {
+        assert_eq!(self.updates, backend.frames_presented);
+        assert_eq!(self.renders, backend.frames_presented);
+    }
+}
+
+fn run_headless(config: &Config) -> NullBackend {
+    let mut backend = NullBackend::new(config);
+    run::<Counter, _, _, _>(
+        config,
+        &mut HeadlessPlatform,
+        &mut backend,
+        FakeClock::new(),
+    )
+    .unwrap();
+    backend
+}
+
+#[test]
+fn headless_runs_a_fixed_number_of_frames() {
+    let config = Counter::config().args(["--headless"]);
+    assert_eq!(config.frame_limit(), Some(DEFAULT_HEADLESS_FRAMES));
+    let backend = run_headless(&config);
+    assert_eq!(backend.size, (800, 600));
+    assert_eq!(backend.frames_presented, DEFAULT_HEADLESS_FRAMES);
+
+    let config = Counter::config()
+        .size(64, 32)
+        .args(["--headless", "--frames=40"]);
+    let backend = run_headless(&config);
+    assert_eq!(backend.size, (64, 32));
+    assert_eq!(backend.frames_presented, 40);
+}
+
+#[test]
+fn the_null_backend_releases_retired_objects_once_the_frame_is_done() {
+    let mut backend = NullBackend::new(&Config::new("Null"));
+    let object = Rc::new(());
+    let frame_end = backend.command_queue().last_sync_point().value() + 1;
+    backend.deleter().retire(object.clone(), frame_end);
+
+    assert_eq!(Rc::strong_count(&object), 2);
+    backend.present().unwrap();
+    assert_eq!(Rc::strong_count(&object), 1);
+    assert!(backend.deleter().is_empty());
+    assert_eq!(backend.command_queue().completed_value(), frame_end);
+}
+
+#[test]
+fn screenshots_need_a_backend_that_can_take_them() {
+    // F12 failing isn't fatal.
+    let f12 = Event::Input(InputEvent::KeyDown(Key::F12));
+    let mut harness = Harness::new(vec![vec![f12], vec![]]);
+    assert_eq!(harness.run(&config()), Ok(()));
+    assert_eq!(harness.log().iter().filter(|e| *e == "present").count(), 2);
+
+    // Asking for one on the command line is.
+    let mut harness = Harness::new(vec![vec![]; 5]);
+    assert_eq!(
+        harness.run(&config().args(["--screenshot=shot.png", "--frames=2"])),
+        Err("screenshot failed: this backend can't take screenshots".into())
+    );
+    assert_eq!(
+        harness
+            .log()
+            .iter()
+            .filter(|e| e.starts_with("render"))
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn headless_screenshot_saves_the_last_frame() {
+    let path = std::env::temp_dir().join(format!("app-test-{}.ppm", std::process::id()));
+    let config = Counter::config().size(4, 2).args([
+        "--headless".to_string(),
+        format!("--screenshot={}", path.display()),
+    ]);
+    assert_eq!(config.frame_limit(), Some(DEFAULT_HEADLESS_FRAMES));
+
+    run_headless(&config);
+    let ppm = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        ppm,
+        [b"P6\n4 2\n255\n".as_slice(), &[0; 4 * 2 * 3]].concat()
+    );
+}
+
+#[test]
+fn config_flags() {
+    let config = Config::new("Sample").size(1280, 720);
+    assert_eq!((config.width, config.height), (1280, 720));
+    assert!(!config.warp);
+    assert_eq!(config.window_title(), "Sample");
+
+    assert!(!config.headless);
+    assert_eq!(config.frame_limit(), None);
+
+    let config = config.args(["--unknown", "-warp", "--frames=ten"]);
+    assert!(config.warp);
+    assert_eq!(config.window_title(), "Sample (WARP)");
+    assert_eq!(config.frames, None);
+
+    let config = config.args(["--frames=10"]);
+    assert!(!config.headless);
+    assert_eq!(config.frame_limit(), Some(10));
+
+    let config = config.args(["--screenshot=out/frame.bmp"]);
+    assert_eq!(config.screenshot, Some("out/frame.bmp".into()));
+    assert_eq!(config.frame_limit(), Some(10));
+    assert_eq!(
+        Config::new("Hello Window Clear").screenshot_file_name(42),
+        std::path::Path::new("hello_window_clear_000042.png")
+    );
+}
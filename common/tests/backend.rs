@@ -0,0 +1,640 @@
+use std::rc::Rc;
+
+use common::gfx::{
+    backend::{
+        Api, BackendError, CommandList, Device, Fence, HeapType, Null, NullCommandList, NullDevice,
+        NullResource, Queue, ResourceDesc,
+    },
+    graph::{self, record_barriers, RenderGraph, ResourceState},
+    readback::ReadbackLayout,
+    CommandQueue, DeferredDeleter, Format, QueueType,
+};
+
+/// Written against the traits, like renderer code would be.
+fn upload_and_read_back<A: Api>(
+    device: &A::Device,
+    queue: &mut A::Queue,
+    fence: &A::Fence,
+    size: u64,
+) -> Result<A::Resource, BackendError> {
+    let upload = device.create_resource(
+        &ResourceDesc::buffer(size),
+        HeapType::Upload,
+        ResourceState::GenericRead,
+    )?;
+    let gpu = device.create_resource(
+        &ResourceDesc::buffer(size),
+        HeapType::Default,
+        ResourceState::CopyDest,
+    )?;
+    let readback = device.create_resource(
+        &ResourceDesc::buffer(size),
+        HeapType::Readback,
+        ResourceState::CopyDest,
+    )?;
+
+    let mut list = device.create_command_list(queue.queue_type())?;
+    list.copy_buffer(&gpu, 0, &upload, 0, size)?;
+    list.barrier(&gpu, ResourceState::CopyDest, ResourceState::CopySource)?;
+    list.copy_buffer(&readback, 0, &gpu, 0, size)?;
+    list.close()?;
+
+    let value = fence.completed_value() + 1;
+    queue.submit(&[&list])?;
+    queue.signal(fence, value)?;
+    fence.wait(value)?;
+    Ok(readback)
+}
+
+fn validation(result: Result<(), BackendError>, expected: &str) {
+    match result {
+        Err(BackendError::Validation(message)) => assert_eq!(message, expected),
+        other => panic!("expected a validation error, got {other:?}"),
+    }
+}
+
+fn texture(device: &NullDevice, state: ResourceState) -> NullResource {
+    device
+        .create_resource(
+            &ResourceDesc::render_target(4, 4, Format::R8G8B8A8_UNORM),
+            HeapType::Default,
+            state,
+        )
+        .unwrap()
+}
+
+fn closed_list(device: &NullDevice, record: impl FnOnce(&mut NullCommandList)) -> NullCommandList {
+    let mut list = device.create_command_list(QueueType::Direct).unwrap();
+    record(&mut list);
+    list.close().unwrap();
+    list
+}
+
+#[test]
+fn generic_code_runs_on_the_null_backend() {
+    let device = NullDevice::new();
+    let mut queue = device.create_queue(QueueType::Copy).unwrap();
+    let fence = device.create_fence(0).unwrap();
+
+    upload_and_read_back::<Null>(&device, &mut queue, &fence, 256).unwrap();
+
+    assert_eq!(
+        device.log(),
+        [
+            "device: create_queue Copy -> queue 0",
+            "device: create_fence 0 -> fence 0",
+            "device: create_resource Buffer { size: 256 } Upload GenericRead -> resource 0",
+            "device: create_resource Buffer { size: 256 } Default CopyDest -> resource 1",
+            "device: create_resource Buffer { size: 256 } Readback CopyDest -> resource 2",
+            "device: create_command_list Copy -> list 0",
+            "list 0: copy_buffer resource 1+0 <- resource 0+0, 256 bytes",
+            "list 0: barrier resource 1 CopyDest -> CopySource",
+            "list 0: copy_buffer resource 2+0 <- resource 1+0, 256 bytes",
+            "list 0: close",
+            "queue 0: submit list 0",
+            "queue 0: signal fence 0 = 1",
+            "gpu: queue 0 executed list 0",
+            "gpu: fence 0 = 1",
+            "cpu: wait fence 0 >= 1",
+        ]
+    );
+    assert_eq!(fence.completed_value(), 1);
+}
+
+#[test]
+fn work_only_completes_when_the_gpu_runs() {
+    let device = NullDevice::new();
+    let mut queue = device.create_queue(QueueType::Direct).unwrap();
+    let fence = device.create_fence(0).unwrap();
+    let list = closed_list(&device, |_| {});
+
+    queue.submit(&[&list]).unwrap();
+    queue.signal(&fence, 1).unwrap();
+    assert_eq!(fence.completed_value(), 0);
+
+    device.take_log();
+    device.run_gpu();
+    assert_eq!(fence.completed_value(), 1);
+    assert_eq!(
+        device.take_log(),
+        ["gpu: queue 0 executed list 0", "gpu: fence 0 = 1"]
+    );
+}
+
+#[test]
+fn queues_wait_for_each_other() {
+    let device = NullDevice::new();
+    let mut direct = device.create_queue(QueueType::Direct).unwrap();
+    let mut copy = device.create_queue(QueueType::Copy).unwrap();
+    let copied = device.create_fence(0).unwrap();
+    let fence = device.create_fence(0).unwrap();
+
+    // The direct queue is first in line but has to wait for the copy.
+    direct.wait(&copied, 1).unwrap();
+    direct.signal(&fence, 1).unwrap();
+    copy.signal(&copied, 1).unwrap();
+
+    device.take_log();
+    fence.wait(1).unwrap();
+    assert_eq!(
+        device.take_log(),
+        [
+            "gpu: fence 0 = 1",
+            "gpu: queue 0 waited for fence 0 >= 1",
+            "gpu: fence 1 = 1",
+            "cpu: wait fence 1 >= 1",
+        ]
+    );
+}
+
+#[test]
+fn waiting_on_a_fence_nothing_signals_deadlocks() {
+    let device = NullDevice::new();
+    let mut queue = device.create_queue(QueueType::Direct).unwrap();
+    let fence = device.create_fence(0).unwrap();
+    let other = device.create_fence(0).unwrap();
+
+    queue.wait(&other, 1).unwrap();
+    queue.signal(&fence, 1).unwrap();
+    validation(
+        fence.wait(1),
+        "fence 0 is stuck at 0 and will never reach 1",
+    );
+
+    // Signaling the other fence from another queue unblocks it.
+    let mut copy = device.create_queue(QueueType::Copy).unwrap();
+    copy.signal(&other, 1).unwrap();
+    fence.wait(1).unwrap();
+}
+
+#[test]
+fn signals_have_to_increase() {
+    let device = NullDevice::new();
+    let mut queue = device.create_queue(QueueType::Direct).unwrap();
+    let fence = device.create_fence(5).unwrap();
+
+    validation(
+        queue.signal(&fence, 5),
+        "fence 0 was already signaled with 5, 5 goes backwards",
+    );
+    queue.signal(&fence, 6).unwrap();
+    validation(
+        queue.signal(&fence, 6),
+        "fence 0 was already signaled with 6, 6 goes backwards",
+    );
+}
+
+#[test]
+fn states_are_tracked_in_submission_order() {
+    let device = NullDevice::new();
+    let mut queue = device.create_queue(QueueType::Direct).unwrap();
+    let target = texture(&device, ResourceState::Present);
+
+    let to_render_target = closed_list(&device, |list| {
+        list.barrier(&target, ResourceState::Present, ResourceState::RenderTarget)
+            .unwrap()
+    });
+    let to_present = closed_list(&device, |list| {
+        list.barrier(&target, ResourceState::RenderTarget, ResourceState::Present)
+            .unwrap()
+    });
+
+    validation(
+        queue.submit(&[&to_present]),
+        "list 1 expects resource 0 in RenderTarget, but it's in Present",
+    );
+    validation(
+        queue.submit(&[&to_present, &to_render_target]),
+        "list 1 expects resource 0 in RenderTarget, but it's in Present",
+    );
+    // The failed submits changed nothing.
+    assert_eq!(device.resource_state(&target), ResourceState::Present);
+
+    queue.submit(&[&to_render_target, &to_present]).unwrap();
+    assert_eq!(device.resource_state(&target), ResourceState::Present);
+    queue.submit(&[&to_render_target]).unwrap();
+    assert_eq!(device.resource_state(&target), ResourceState::RenderTarget);
+}
+
+#[test]
+fn barriers_have_to_match_the_state_within_a_list() {
+    let device = NullDevice::new();
+    let target = texture(&device, ResourceState::Common);
+    let mut list = device.create_command_list(QueueType::Direct).unwrap();
+
+    list.barrier(&target, ResourceState::Common, ResourceState::RenderTarget)
+        .unwrap();
+    validation(
+        list.barrier(&target, ResourceState::Common, ResourceState::CopySource),
+        "resource 0 is in RenderTarget, not Common",
+    );
+    validation(
+        list.barrier(
+            &target,
+            ResourceState::RenderTarget,
+            ResourceState::RenderTarget,
+        ),
+        "resource 0 is already in RenderTarget",
+    );
+
+    // Present and Common are the same state.
+    list.barrier(&target, ResourceState::RenderTarget, ResourceState::Present)
+        .unwrap();
+    validation(
+        list.barrier(&target, ResourceState::Present, ResourceState::Common),
+        "resource 0 is already in Common",
+    );
+    list.barrier(
+        &target,
+        ResourceState::Common,
+        ResourceState::ShaderResource,
+    )
+    .unwrap();
+}
+
+#[test]
+fn resources_are_validated_on_creation() {
+    let device = NullDevice::new();
+    let create =
+        |desc: ResourceDesc, heap, state| device.create_resource(&desc, heap, state).map(|_| ());
+
+    validation(
+        create(
+            ResourceDesc::buffer(64),
+            HeapType::Upload,
+            ResourceState::CopySource,
+        ),
+        "resources in Upload heaps start in GenericRead, not CopySource",
+    );
+    validation(
+        create(
+            ResourceDesc::buffer(64),
+            HeapType::Readback,
+            ResourceState::Common,
+        ),
+        "resources in Readback heaps start in CopyDest, not Common",
+    );
+    validation(
+        create(
+            ResourceDesc::buffer(0),
+            HeapType::Default,
+            ResourceState::Common,
+        ),
+        "buffers can't be empty",
+    );
+    validation(
+        create(
+            ResourceDesc::texture_2d(4, 4, Format::R8G8B8A8_UNORM),
+            HeapType::Upload,
+            ResourceState::GenericRead,
+        ),
+        "textures can't live in Upload heaps",
+    );
+    validation(
+        create(
+            ResourceDesc::texture_2d(4, 4, Format::R8G8B8A8_UNORM),
+            HeapType::Default,
+            ResourceState::RenderTarget,
+        ),
+        "Texture2d { width: 4, height: 4, format: Format(28), mip_levels: 1, render_target: false } isn't a render target",
+    );
+}
+
+#[test]
+fn upload_and_readback_resources_keep_their_state() {
+    let device = NullDevice::new();
+    let upload = device
+        .create_resource(
+            &ResourceDesc::buffer(64),
+            HeapType::Upload,
+            ResourceState::GenericRead,
+        )
+        .unwrap();
+    let readback = device
+        .create_resource(
+            &ResourceDesc::buffer(64),
+            HeapType::Readback,
+            ResourceState::CopyDest,
+        )
+        .unwrap();
+    let mut list = device.create_command_list(QueueType::Copy).unwrap();
+
+    validation(
+        list.barrier(&upload, ResourceState::GenericRead, ResourceState::CopyDest),
+        "resource 0 lives in a Upload heap and can't change state",
+    );
+    validation(
+        list.copy_buffer(&upload, 0, &readback, 0, 64),
+        "resource 1 needs to be in CopySource, but it's in CopyDest",
+    );
+}
+
+#[test]
+fn copies_check_states_and_bounds() {
+    let device = NullDevice::new();
+    let buffer = |size, state| {
+        device
+            .create_resource(&ResourceDesc::buffer(size), HeapType::Default, state)
+            .unwrap()
+    };
+    let src = buffer(64, ResourceState::CopySource);
+    let dst = buffer(32, ResourceState::CopyDest);
+    let target = texture(&device, ResourceState::CopySource);
+    let mut list = device.create_command_list(QueueType::Direct).unwrap();
+
+    validation(
+        list.copy_buffer(&dst, 16, &src, 0, 32),
+        "copying 32 bytes at 16 overruns resource 1, which is 32 bytes",
+    );
+    validation(
+        list.copy_buffer(&dst, 0, &src, u64::MAX, 2),
+        "copying 2 bytes at 18446744073709551615 overruns resource 0, which is 64 bytes",
+    );
+    validation(
+        list.copy_buffer(&dst, 0, &target, 0, 2),
+        "resource 2 isn't a buffer",
+    );
+    list.copy_buffer(&dst, 0, &src, 32, 32).unwrap();
+
+    // The list now expects the buffers to stay in those states, so they
+    // can't swap roles without barriers.
+    validation(
+        list.copy_buffer(&src, 0, &dst, 0, 32),
+        "resource 1 needs to be in CopySource, but it's in CopyDest",
+    );
+
+    list.close().unwrap();
+    let mut queue = device.create_queue(QueueType::Direct).unwrap();
+    queue.submit(&[&list]).unwrap();
+    assert_eq!(device.resource_state(&src), ResourceState::CopySource);
+}
+
+#[test]
+fn texture_copies_match_the_layout() {
+    let device = NullDevice::new();
+    let target = texture(&device, ResourceState::RenderTarget);
+    let layout = ReadbackLayout::new(4, 4, Format::R8G8B8A8_UNORM).unwrap();
+    let readback = device
+        .create_resource(
+            &ResourceDesc::buffer(layout.size()),
+            HeapType::Readback,
+            ResourceState::CopyDest,
+        )
+        .unwrap();
+    let small = device
+        .create_resource(
+            &ResourceDesc::buffer(layout.size() - 1),
+            HeapType::Readback,
+            ResourceState::CopyDest,
+        )
+        .unwrap();
+    let mut list = device.create_command_list(QueueType::Direct).unwrap();
+
+    let wrong_size = ReadbackLayout::new(2, 4, Format::R8G8B8A8_UNORM).unwrap();
+    assert!(list
+        .copy_texture_to_buffer(&readback, &wrong_size, &target)
+        .is_err());
+    validation(
+        list.copy_texture_to_buffer(&small, &layout, &target),
+        "resource 2 is Buffer { size: 783 }, but 784 bytes are needed",
+    );
+
+    // Without a barrier the texture is expected in CopySource when the list
+    // runs, which it isn't.
+    list.copy_texture_to_buffer(&readback, &layout, &target)
+        .unwrap();
+    list.close().unwrap();
+    let mut queue = device.create_queue(QueueType::Direct).unwrap();
+    validation(
+        queue.submit(&[&list]),
+        "list 0 expects resource 0 in CopySource, but it's in RenderTarget",
+    );
+
+    let list = closed_list(&device, |list| {
+        list.barrier(
+            &target,
+            ResourceState::RenderTarget,
+            ResourceState::CopySource,
+        )
+        .unwrap();
+        list.copy_texture_to_buffer(&readback, &layout, &target)
+            .unwrap();
+    });
+    queue.submit(&[&list]).unwrap();
+    assert_eq!(device.resource_state(&target), ResourceState::CopySource);
+}
+
+#[test]
+fn command_lists_follow_the_recording_rules() {
+    let device = NullDevice::new();
+    let mut queue = device.create_queue(QueueType::Direct).unwrap();
+    let mut compute = device.create_queue(QueueType::Compute).unwrap();
+    let fence = device.create_fence(0).unwrap();
+    let target = texture(&device, ResourceState::Common);
+    let mut list = device.create_command_list(QueueType::Direct).unwrap();
+
+    validation(list.reset(), "list 0 has to be closed to be reset");
+    validation(queue.submit(&[&list]), "list 0 is still open");
+    list.close().unwrap();
+    validation(list.close(), "list 0 is closed");
+    validation(
+        list.barrier(&target, ResourceState::Common, ResourceState::CopySource),
+        "list 0 is closed",
+    );
+    validation(
+        compute.submit(&[&list]),
+        "list 0 is a Direct list, but queue 1 is Compute",
+    );
+
+    queue.submit(&[&list]).unwrap();
+    queue.signal(&fence, 1).unwrap();
+    validation(list.reset(), "list 0 is still executing");
+    fence.wait(1).unwrap();
+    list.reset().unwrap();
+}
+
+#[test]
+fn command_queues_signal_their_fence_after_each_submission() {
+    let device = NullDevice::new();
+    let mut queue = CommandQueue::<Null>::new(&device, QueueType::Direct).unwrap();
+    let first = closed_list(&device, |_| {});
+    let second = closed_list(&device, |_| {});
+
+    let a = queue.execute(&[&first]).unwrap();
+    let b = queue.execute(&[&second]).unwrap();
+    assert_eq!((a.value(), b.value()), (1, 2));
+    assert_eq!(queue.last_sync_point().value(), 2);
+    assert!(!queue.is_complete(&a));
+
+    device.take_log();
+    queue.wait_cpu(&a).unwrap();
+    assert!(a.is_complete());
+    assert!(b.is_complete(), "the GPU doesn't stop at the first signal");
+    assert_eq!(
+        device.take_log(),
+        [
+            "gpu: queue 0 executed list 0",
+            "gpu: fence 0 = 1",
+            "gpu: queue 0 executed list 1",
+            "gpu: fence 0 = 2",
+            "cpu: wait fence 0 >= 1",
+        ]
+    );
+}
+
+#[test]
+fn command_queues_wait_for_each_other_on_the_gpu() {
+    let device = NullDevice::new();
+    let mut direct = CommandQueue::<Null>::new(&device, QueueType::Direct).unwrap();
+    let mut copy = CommandQueue::<Null>::new(&device, QueueType::Copy).unwrap();
+
+    let upload = copy.signal().unwrap();
+    direct.wait_gpu(&upload).unwrap();
+    let frame = direct.signal().unwrap();
+
+    device.take_log();
+    direct.wait_cpu(&frame).unwrap();
+    assert_eq!(
+        device.take_log(),
+        [
+            "gpu: fence 1 = 1",
+            "gpu: queue 0 waited for fence 1 >= 1",
+            "gpu: fence 0 = 1",
+            "cpu: wait fence 0 >= 1",
+        ]
+    );
+}
+
+#[test]
+fn dropping_a_command_queue_flushes_it() {
+    let device = NullDevice::new();
+    let fence = {
+        let mut queue = CommandQueue::<Null>::new(&device, QueueType::Direct).unwrap();
+        let list = closed_list(&device, |_| {});
+        queue.execute(&[&list]).unwrap();
+        queue.fence().clone()
+    };
+    assert_eq!(fence.completed_value(), 2);
+}
+
+#[test]
+fn retired_objects_live_until_the_gpu_is_done() {
+    let device = NullDevice::new();
+    let mut queue = CommandQueue::<Null>::new(&device, QueueType::Direct).unwrap();
+    let mut deleter = DeferredDeleter::<Null>::new();
+    let buffer = device
+        .create_resource(
+            &ResourceDesc::buffer(64),
+            HeapType::Default,
+            ResourceState::Common,
+        )
+        .unwrap();
+    let heap = Rc::new(());
+
+    let first = queue.signal().unwrap();
+    deleter.retire_resource(buffer, first.value());
+    let second = queue.signal().unwrap();
+    deleter.retire(heap.clone(), second.value());
+    assert_eq!(deleter.len(), 2);
+
+    deleter.collect(queue.fence());
+    assert_eq!(deleter.len(), 2);
+
+    queue.wait_cpu(&first).unwrap();
+    device.run_gpu();
+    queue.signal().unwrap();
+    deleter.collect(queue.fence());
+    assert!(deleter.is_empty());
+    assert_eq!(Rc::strong_count(&heap), 1);
+
+    deleter.retire(heap.clone(), queue.last_sync_point().value() + 1);
+    deleter.flush_all();
+    assert_eq!(Rc::strong_count(&heap), 1);
+}
+
+#[test]
+fn graph_barriers_are_recorded_through_the_traits() {
+    const HDR: graph::ResourceDesc = graph::ResourceDesc::Texture {
+        width: 4,
+        height: 4,
+        format: 10,
+    };
+
+    let device = NullDevice::new();
+    let back_buffer = texture(&device, ResourceState::Present);
+
+    let mut graph = RenderGraph::new();
+    let swapchain = graph.import(
+        "back_buffer",
+        ResourceState::Present,
+        ResourceState::Present,
+    );
+    let hdr = graph.create("hdr", HDR);
+    graph.add_pass(
+        "simulate",
+        |p| {
+            p.write(hdr, ResourceState::UnorderedAccess);
+        },
+        |_: &mut NullCommandList| {},
+    );
+    graph.add_pass(
+        "blur",
+        |p| {
+            p.write(hdr, ResourceState::UnorderedAccess);
+        },
+        |_| {},
+    );
+    graph.add_pass(
+        "tonemap",
+        |p| {
+            p.read(hdr, ResourceState::ShaderResource)
+                .write(swapchain, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+    let compiled = graph.compile().unwrap();
+
+    // Physical resources by physical index, with the back buffer imported.
+    let mut physical: Vec<Option<NullResource>> = vec![None; 2];
+    physical[compiled.physical_index(swapchain)] = Some(back_buffer);
+    for (index, desc, state) in compiled.physical_resources() {
+        let graph::ResourceDesc::Texture {
+            width,
+            height,
+            format,
+        } = desc
+        else {
+            unreachable!()
+        };
+        let desc = ResourceDesc::render_target(width, height, Format(format));
+        let resource = device
+            .create_resource(&desc, HeapType::Default, state)
+            .unwrap();
+        physical[index] = Some(resource);
+    }
+
+    let mut queue = device.create_queue(QueueType::Direct).unwrap();
+    let mut list = device.create_command_list(QueueType::Direct).unwrap();
+    device.take_log();
+    graph.execute(&compiled, &mut list, |list, barriers| {
+        record_barriers::<Null>(list, barriers, |id| {
+            physical[compiled.physical_index(id)].as_ref().unwrap()
+        })
+        .unwrap()
+    });
+    list.close().unwrap();
+    queue.submit(&[&list]).unwrap();
+
+    assert_eq!(
+        device.take_log(),
+        [
+            "list 0: uav_barrier resource 1",
+            "list 0: barrier resource 1 UnorderedAccess -> ShaderResource",
+            "list 0: barrier resource 0 Present -> RenderTarget",
+            "list 0: barrier resource 0 RenderTarget -> Present",
+            "list 0: close",
+            "queue 0: submit list 0",
+        ]
+    );
+    assert_eq!(device.resource_state(&back_buffer), ResourceState::Present);
+}
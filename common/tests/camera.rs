@@ -0,0 +1,263 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use common::{
+    camera::{FlyCamera, OrbitCamera, Perspective},
+    input::{InputEvent, InputState, Key, MouseButton},
+    math::{Vec2, Vec3},
+};
+
+const EPSILON: f32 = 1e-4;
+const DT: f32 = 0.1;
+
+#[track_caller]
+fn assert_vec3(actual: Vec3, expected: Vec3) {
+    assert!(
+        actual.abs_diff_eq(expected, EPSILON),
+        "{actual:?} != {expected:?}"
+    );
+}
+
+/// Replays one frame of events, like `os::App::run` does, and returns the
+/// state to update a camera with.
+fn frame<'a>(input: &'a mut InputState, events: &[InputEvent]) -> &'a InputState {
+    input.begin_frame();
+    for &event in events {
+        input.handle(event);
+    }
+    input
+}
+
+fn mouse(x: f32, y: f32) -> InputEvent {
+    InputEvent::MouseMove(Vec2::new(x, y))
+}
+
+fn fly_camera() -> FlyCamera {
+    FlyCamera::new(Vec3::ZERO, Vec3::Z)
+}
+
+#[test]
+fn fly_camera_looks_at_its_target() {
+    let camera = FlyCamera::new(Vec3::new(0.0, 0.0, -5.0), Vec3::ZERO);
+    assert_vec3(camera.forward(), Vec3::Z);
+    assert_vec3(
+        camera.view().transform_point3(Vec3::ZERO),
+        Vec3::new(0.0, 0.0, 5.0),
+    );
+
+    let camera = FlyCamera::new(Vec3::ZERO, Vec3::new(3.0, -3.0, 0.0));
+    assert!((camera.yaw - FRAC_PI_2).abs() < EPSILON);
+    assert_vec3(camera.forward(), Vec3::new(1.0, -1.0, 0.0).normalize());
+}
+
+#[test]
+fn wasd_moves_at_speed() {
+    let mut camera = fly_camera();
+    let mut input = InputState::new();
+
+    camera.update(frame(&mut input, &[InputEvent::KeyDown(Key::W)]), DT);
+    for _ in 1..10 {
+        camera.update(frame(&mut input, &[]), DT);
+    }
+    assert_vec3(camera.position, Vec3::new(0.0, 0.0, camera.speed));
+
+    camera.update(
+        frame(
+            &mut input,
+            &[InputEvent::KeyUp(Key::W), InputEvent::KeyDown(Key::A)],
+        ),
+        DT,
+    );
+    assert_vec3(
+        camera.position,
+        Vec3::new(-camera.speed * DT, 0.0, camera.speed),
+    );
+
+    camera.update(frame(&mut input, &[InputEvent::KeyUp(Key::A)]), DT);
+    camera.update(frame(&mut input, &[]), DT);
+    assert_vec3(
+        camera.position,
+        Vec3::new(-camera.speed * DT, 0.0, camera.speed),
+    );
+}
+
+#[test]
+fn opposite_keys_cancel_and_diagonals_are_not_faster() {
+    let mut camera = fly_camera();
+    let mut input = InputState::new();
+
+    let keys = [Key::W, Key::S, Key::D].map(InputEvent::KeyDown);
+    camera.update(frame(&mut input, &keys), 1.0);
+    assert_vec3(camera.position, Vec3::new(camera.speed, 0.0, 0.0));
+
+    let mut camera = fly_camera();
+    let mut input = InputState::new();
+    let keys = [Key::W, Key::D, Key::E].map(InputEvent::KeyDown);
+    camera.update(frame(&mut input, &keys), 1.0);
+    assert!((camera.position.length() - camera.speed).abs() < EPSILON);
+    assert!(camera.position.x > 0.0 && camera.position.y > 0.0 && camera.position.z > 0.0);
+}
+
+#[test]
+fn shift_boosts() {
+    let mut camera = fly_camera();
+    let mut input = InputState::new();
+
+    let keys = [Key::Shift, Key::S].map(InputEvent::KeyDown);
+    camera.update(frame(&mut input, &keys), DT);
+    assert_vec3(
+        camera.position,
+        Vec3::new(0.0, 0.0, -camera.speed * camera.boost * DT),
+    );
+}
+
+#[test]
+fn vertical_movement_ignores_pitch() {
+    let mut camera = FlyCamera::new(Vec3::ZERO, Vec3::new(0.0, -1.0, 1.0));
+    let mut input = InputState::new();
+
+    camera.update(frame(&mut input, &[InputEvent::KeyDown(Key::Q)]), 1.0);
+    assert_vec3(camera.position, Vec3::new(0.0, -camera.speed, 0.0));
+}
+
+#[test]
+fn mouse_looks_only_while_the_button_is_held() {
+    let mut camera = fly_camera();
+    camera.sensitivity = FRAC_PI_2 / 500.0;
+    let mut input = InputState::new();
+
+    camera.update(frame(&mut input, &[mouse(100.0, 100.0)]), DT);
+    camera.update(frame(&mut input, &[mouse(300.0, 100.0)]), DT);
+    assert_eq!(camera.yaw, 0.0);
+
+    // Dragging right turns right, 500 pixels to a quarter turn.
+    let drag = [
+        InputEvent::ButtonDown(MouseButton::Right),
+        mouse(400.0, 100.0),
+        mouse(800.0, 100.0),
+    ];
+    camera.update(frame(&mut input, &drag), DT);
+    assert!((camera.yaw - FRAC_PI_2).abs() < EPSILON);
+    assert_vec3(camera.forward(), Vec3::X);
+
+    camera.update(frame(&mut input, &[InputEvent::KeyDown(Key::W)]), 1.0);
+    assert_vec3(camera.position, Vec3::new(camera.speed, 0.0, 0.0));
+}
+
+#[test]
+fn captured_mouse_always_looks() {
+    let mut camera = fly_camera();
+    camera.look_button = None;
+    let mut input = InputState::new();
+
+    camera.update(frame(&mut input, &[mouse(0.0, 0.0)]), DT);
+    camera.update(frame(&mut input, &[mouse(0.0, 100.0)]), DT);
+    assert!((camera.pitch - 100.0 * camera.sensitivity).abs() < EPSILON);
+    // Moving the mouse down looks down.
+    assert!(camera.forward().y < 0.0);
+}
+
+#[test]
+fn pitch_stops_short_of_straight_up_and_down() {
+    let mut camera = fly_camera();
+    camera.look_button = None;
+    let mut input = InputState::new();
+
+    camera.update(frame(&mut input, &[mouse(0.0, 0.0)]), DT);
+    camera.update(frame(&mut input, &[mouse(0.0, 1e6)]), DT);
+    assert!(camera.pitch < FRAC_PI_2 && camera.pitch > 1.5);
+
+    camera.update(frame(&mut input, &[mouse(0.0, -1e6)]), DT);
+    assert!(camera.pitch > -FRAC_PI_2 && camera.pitch < -1.5);
+    assert!(camera.view().cols.iter().all(|c| c.length().is_finite()));
+}
+
+#[test]
+fn orbit_camera_starts_at_its_eye() {
+    let camera = OrbitCamera::new(Vec3::new(0.0, 5.0, -5.0), Vec3::new(0.0, 1.0, 0.0));
+
+    assert_vec3(camera.eye(), Vec3::new(0.0, 5.0, -5.0));
+    assert!((camera.distance - 41f32.sqrt()).abs() < EPSILON);
+    assert_vec3(
+        camera.view().transform_point3(camera.target),
+        Vec3::new(0.0, 0.0, camera.distance),
+    );
+}
+
+#[test]
+fn dragging_orbits_around_the_target() {
+    let mut camera = OrbitCamera::new(Vec3::new(0.0, 0.0, -10.0), Vec3::ZERO);
+    camera.rotate_sensitivity = PI / 1000.0;
+    let mut input = InputState::new();
+
+    camera.update(frame(&mut input, &[mouse(0.0, 0.0)]), DT);
+    // Hovering does nothing.
+    camera.update(frame(&mut input, &[mouse(500.0, 0.0)]), DT);
+    assert_vec3(camera.eye(), Vec3::new(0.0, 0.0, -10.0));
+
+    // Dragging right swings the camera to its left, so the model appears to
+    // follow the cursor.
+    let drag = [
+        InputEvent::ButtonDown(MouseButton::Left),
+        mouse(1000.0, 0.0),
+    ];
+    camera.update(frame(&mut input, &drag), DT);
+    assert_vec3(camera.eye(), Vec3::new(-10.0, 0.0, 0.0));
+    assert_vec3(
+        camera.view().transform_point3(Vec3::ZERO),
+        Vec3::new(0.0, 0.0, 10.0),
+    );
+
+    // Dragging down moves the camera up over the top.
+    camera.update(frame(&mut input, &[mouse(1000.0, 250.0)]), DT);
+    assert!(camera.eye().y > 7.0);
+    assert!((camera.eye().length() - 10.0).abs() < EPSILON);
+}
+
+#[test]
+fn wheel_zooms_within_limits() {
+    let mut camera = OrbitCamera::new(Vec3::new(0.0, 0.0, -10.0), Vec3::ZERO);
+    let mut input = InputState::new();
+
+    camera.update(frame(&mut input, &[InputEvent::Wheel(1.0)]), DT);
+    assert!((camera.distance - 9.0).abs() < EPSILON);
+    camera.update(frame(&mut input, &[InputEvent::Wheel(-1.0)]), DT);
+    assert!((camera.distance - 10.0).abs() < EPSILON);
+
+    camera.update(frame(&mut input, &[InputEvent::Wheel(1000.0)]), DT);
+    assert_eq!(camera.distance, camera.min_distance);
+    camera.update(frame(&mut input, &[InputEvent::Wheel(-1000.0)]), DT);
+    assert_eq!(camera.distance, camera.max_distance);
+}
+
+#[test]
+fn panning_moves_the_target_with_the_view() {
+    let mut camera = OrbitCamera::new(Vec3::new(0.0, 0.0, -10.0), Vec3::ZERO);
+    let mut input = InputState::new();
+
+    camera.update(frame(&mut input, &[mouse(0.0, 0.0)]), DT);
+    let drag = [
+        InputEvent::ButtonDown(MouseButton::Middle),
+        mouse(100.0, 100.0),
+    ];
+    camera.update(frame(&mut input, &drag), DT);
+
+    // The scene follows the cursor: right and down on screen.
+    let pan = 100.0 * camera.pan_sensitivity * camera.distance;
+    assert_vec3(camera.target, Vec3::new(-pan, pan, 0.0));
+    assert_vec3(camera.eye(), Vec3::new(-pan, pan, -10.0));
+    assert_eq!(camera.yaw, 0.0);
+}
+
+#[test]
+fn projection_uses_reversed_z() {
+    let mut camera = OrbitCamera::new(Vec3::new(0.0, 0.0, -10.0), Vec3::ZERO);
+    camera.projection.set_viewport_size(1280, 720);
+    camera.projection.set_viewport_size(0, 720);
+
+    assert!((camera.projection.aspect - 16.0 / 9.0).abs() < EPSILON);
+    assert_vec3(
+        camera.view_projection().project_point3(Vec3::ZERO),
+        Vec3::new(0.0, 0.0, camera.projection.near / 10.0),
+    );
+    assert_eq!(camera.projection, Perspective::default());
+}
@@ -0,0 +1,244 @@
+use common::{
+    cbuffer,
+    gfx::cbuffer::{
+        member_offset, packed_size, ConstantBuffer, HlslType, HlslTypeDesc, Member,
+        MAX_CONSTANT_BUFFER_SIZE,
+    },
+};
+
+fn float(n: usize) -> HlslTypeDesc {
+    match n {
+        1 => f32::TYPE,
+        2 => <[f32; 2]>::TYPE,
+        3 => <[f32; 3]>::TYPE,
+        4 => <[f32; 4]>::TYPE,
+        _ => unreachable!(),
+    }
+}
+
+fn offsets(types: &[HlslTypeDesc]) -> Vec<u32> {
+    (0..types.len()).map(|i| member_offset(types, i)).collect()
+}
+
+#[test]
+fn type_descriptions() {
+    assert_eq!(f32::TYPE, HlslTypeDesc::packed("float", 4));
+    assert_eq!(<[u32; 3]>::TYPE, HlslTypeDesc::packed("uint3", 12));
+    assert_eq!(<[i32; 2]>::TYPE, HlslTypeDesc::packed("int2", 8));
+    assert_eq!(
+        <[[f32; 4]; 3]>::TYPE,
+        HlslTypeDesc {
+            name: "float4",
+            size: 48,
+            register_aligned: true,
+            array_len: Some(3),
+        }
+    );
+    assert_eq!(<[[u32; 4]; 1]>::TYPE.name, "uint4");
+}
+
+#[test]
+fn vectors_after_scalars() {
+    // Every run of scalars followed by every vector width: the vector stays
+    // in the register if it fits, otherwise it starts the next one.
+    for scalars in 0..4 {
+        for width in 1..=4 {
+            let mut types = vec![f32::TYPE; scalars];
+            types.push(float(width));
+
+            let expected = if (scalars + width) * 4 <= 16 {
+                scalars as u32 * 4
+            } else {
+                16
+            };
+            assert_eq!(
+                member_offset(&types, scalars),
+                expected,
+                "{scalars} floats then float{width}"
+            );
+        }
+    }
+}
+
+#[test]
+fn every_pair_of_vectors() {
+    for first in 1..=4 {
+        for second in 1..=4 {
+            let types = [float(first), float(second)];
+            let expected = if first + second <= 4 { first * 4 } else { 16 };
+
+            assert_eq!(
+                offsets(&types),
+                [0, expected as u32],
+                "float{first} then float{second}"
+            );
+            assert_eq!(packed_size(&types), expected as u32 + second as u32 * 4);
+        }
+    }
+}
+
+#[test]
+fn known_hlsl_offsets() {
+    let float4x4 = <[[f32; 4]; 4]>::TYPE;
+    let cases: [(&[HlslTypeDesc], &[u32], u32); 10] = [
+        // The examples from "Packing Rules for Constant Variables".
+        (&[float(1), float(4), float(4)], &[0, 16, 32], 48),
+        (&[float(4), float(2), float(2)], &[0, 16, 24], 32),
+        (&[float(2), float(4), float(2)], &[0, 16, 32], 40),
+        (&[float(2), float(2), float(3)], &[0, 8, 16], 28),
+        (&[float(1), float(2), float(1)], &[0, 4, 12], 16),
+        (&[float(1), float(2), float(2)], &[0, 4, 16], 24),
+        (&[float(3), float(3), float(1)], &[0, 16, 28], 32),
+        (
+            &[float4x4, float(3), float(1), float(2)],
+            &[0, 64, 76, 80],
+            88,
+        ),
+        // Arrays start a new register, and so does whatever follows them.
+        (
+            &[float(1), <[[f32; 4]; 2]>::TYPE, float(1)],
+            &[0, 16, 48],
+            52,
+        ),
+        (
+            &[u32::TYPE, <[i32; 2]>::TYPE, float(1), <[u32; 4]>::TYPE],
+            &[0, 4, 12, 16],
+            32,
+        ),
+    ];
+
+    for (types, expected, size) in cases {
+        assert_eq!(offsets(types), expected, "{types:?}");
+        assert_eq!(packed_size(types), size, "{types:?}");
+    }
+    assert_eq!(packed_size(&[]), 0);
+}
+
+cbuffer! {
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    pub struct Light {
+        pub direction: [f32; 3],
+        pub intensity: f32,
+        pub color: [f32; 3],
+        pub _pad: f32,
+    }
+}
+
+cbuffer! {
+    /// Nested structs and arrays start on a register boundary.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct Scene {
+        pub view_projection: [[f32; 4]; 4],
+        pub eye: [f32; 3],
+        pub time: f32,
+        pub frame: u32,
+        pub jitter: [f32; 2],
+        pub light_count: u32,
+        pub sun: Light,
+        pub exposure: f32,
+    }
+}
+
+cbuffer! {
+    struct Empty {}
+}
+
+#[test]
+fn struct_layouts_match_hlsl() {
+    assert_eq!(
+        Light::MEMBERS.iter().map(|m| m.offset).collect::<Vec<_>>(),
+        [0, 12, 16, 28]
+    );
+    assert_eq!(Light::TYPE, HlslTypeDesc::aligned("Light", 32));
+
+    let offsets: Vec<(&str, u32)> = Scene::MEMBERS.iter().map(|m| (m.name, m.offset)).collect();
+    assert_eq!(
+        offsets,
+        [
+            ("view_projection", 0),
+            ("eye", 64),
+            ("time", 76),
+            ("frame", 80),
+            ("jitter", 84),
+            ("light_count", 92),
+            ("sun", 96),
+            ("exposure", 128),
+        ]
+    );
+    assert_eq!(std::mem::size_of::<Scene>(), 132);
+    assert_eq!(Scene::CBV_SIZE, 256);
+    assert!(Empty::MEMBERS.is_empty());
+    assert_eq!(Empty::CBV_SIZE, 0);
+}
+
+#[test]
+fn members_describe_their_types() {
+    assert_eq!(
+        Scene::MEMBERS[6],
+        Member {
+            name: "sun",
+            ty: HlslTypeDesc::aligned("Light", 32),
+            offset: 96,
+        }
+    );
+}
+
+#[test]
+fn cbv_sizes_round_up_to_256() {
+    cbuffer! {
+        struct Big {
+            a: [[f32; 4]; 16],
+            b: f32,
+        }
+    }
+    cbuffer! {
+        struct Huge {
+            a: [[f32; 4]; 4096],
+        }
+    }
+
+    assert_eq!(std::mem::size_of::<Big>(), 260);
+    assert_eq!(Big::CBV_SIZE, 512);
+    assert_eq!(Huge::CBV_SIZE, MAX_CONSTANT_BUFFER_SIZE);
+}
+
+#[test]
+fn hlsl_struct_text() {
+    assert_eq!(
+        Light::hlsl_struct(),
+        "struct Light\n\
+         {\n\
+         \x20   float3 direction; // offset 0\n\
+         \x20   float intensity; // offset 12\n\
+         \x20   float3 color; // offset 16\n\
+         \x20   float _pad; // offset 28\n\
+         };\n"
+    );
+}
+
+#[test]
+fn hlsl_cbuffer_text() {
+    let hlsl = Scene::hlsl_cbuffer("SceneConstants", 0, 0);
+
+    assert!(hlsl.starts_with("cbuffer SceneConstants : register(b0)\n{\n"));
+    assert!(hlsl.contains("    float4 view_projection[4]; // offset 0\n"));
+    assert!(hlsl.contains("    uint frame; // offset 80\n"));
+    assert!(hlsl.contains("    Light sun; // offset 96\n"));
+    assert!(hlsl.ends_with("    float exposure; // offset 128\n};\n"));
+    assert!(Light::hlsl_cbuffer("L", 2, 1).starts_with("cbuffer L : register(b2, space1)\n"));
+}
+
+#[test]
+fn bytes_are_the_struct_in_memory() {
+    let light = Light {
+        direction: [0.0, -1.0, 0.0],
+        intensity: 2.0,
+        color: [1.0, 0.5, 0.25],
+        _pad: 0.0,
+    };
+    let bytes = light.as_bytes();
+
+    assert_eq!(bytes.len(), 32);
+    assert_eq!(&bytes[12..16], &2.0f32.to_le_bytes());
+    assert_eq!(&bytes[20..24], &0.5f32.to_le_bytes());
+}
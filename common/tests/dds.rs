@@ -0,0 +1,297 @@
+use std::path::Path;
+
+use common::{
+    assets::{AssetError, Texture, TextureDesc, TextureDimension},
+    gfx::Format,
+};
+
+/// The fixtures' texels count up from 0, wrapping at 251.
+fn fixture_data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+fn fixture(name: &str) -> Texture {
+    Texture::load(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name),
+    )
+    .unwrap()
+}
+
+fn desc(
+    dimension: TextureDimension,
+    format: Format,
+    (width, height, depth): (u32, u32, u32),
+    array_size: u32,
+    mip_levels: u32,
+    cube: bool,
+) -> TextureDesc {
+    TextureDesc {
+        dimension,
+        format,
+        width,
+        height,
+        depth,
+        array_size,
+        mip_levels,
+        cube,
+    }
+}
+
+/// A legacy header for a 4x4 DXT1 texture, to be broken by the tests.
+fn dxt1_header() -> Vec<u8> {
+    let mut fields = [0u32; 31];
+    fields[0] = 124;
+    fields[1] = 0x1007;
+    fields[2] = 4;
+    fields[3] = 4;
+    fields[18] = 32;
+    fields[19] = 0x4;
+    fields[20] = u32::from_le_bytes(*b"DXT1");
+    fields[26] = 0x1000;
+
+    let mut bytes = b"DDS ".to_vec();
+    bytes.extend(fields.iter().flat_map(|field| field.to_le_bytes()));
+    bytes
+}
+
+fn set_field(bytes: &mut [u8], index: usize, value: u32) {
+    bytes[4 + index * 4..8 + index * 4].copy_from_slice(&value.to_le_bytes());
+}
+
+#[test]
+fn legacy_bc1_with_mips() {
+    let texture = fixture("bc1_mips.dds");
+    assert_eq!(
+        *texture.desc(),
+        desc(
+            TextureDimension::Texture2d,
+            Format::BC1_UNORM,
+            (8, 8, 1),
+            1,
+            4,
+            false
+        )
+    );
+    assert_eq!(texture.data, fixture_data(56));
+
+    // 8x8 is 2x2 blocks, and the smaller mips are still a whole block.
+    let sizes: Vec<_> = texture
+        .layout
+        .subresources
+        .iter()
+        .map(|s| (s.width, s.height, s.row_pitch, s.rows, s.offset))
+        .collect();
+    assert_eq!(
+        sizes,
+        [
+            (8, 8, 16, 2, 0),
+            (4, 4, 8, 1, 32),
+            (2, 2, 8, 1, 40),
+            (1, 1, 8, 1, 48)
+        ]
+    );
+    assert_eq!(texture.subresource(1), &fixture_data(56)[32..40]);
+}
+
+#[test]
+fn legacy_bgra_cubemap() {
+    let texture = fixture("bgra_cube.dds");
+    assert_eq!(
+        *texture.desc(),
+        desc(
+            TextureDimension::Texture2d,
+            Format::B8G8R8A8_UNORM,
+            (2, 2, 1),
+            6,
+            2,
+            true
+        )
+    );
+    assert_eq!(texture.layout.subresources.len(), 12);
+
+    // Each face has its whole mip chain before the next face starts.
+    let index = texture.desc().subresource_index(1, 3);
+    assert_eq!(index, 7);
+    assert_eq!(texture.layout.subresources[7].offset, 3 * 20 + 16);
+    assert_eq!(texture.subresource(index), &fixture_data(120)[76..80]);
+}
+
+#[test]
+fn dx10_bc7_array() {
+    let texture = fixture("bc7_array.dds");
+    assert_eq!(
+        *texture.desc(),
+        desc(
+            TextureDimension::Texture2d,
+            Format::BC7_UNORM_SRGB,
+            (8, 4, 1),
+            3,
+            2,
+            false
+        )
+    );
+    // Two blocks, then one, for each of the three slices.
+    assert_eq!(texture.layout.size, 3 * (32 + 16));
+    assert_eq!(texture.subresource(5), &fixture_data(144)[128..144]);
+}
+
+#[test]
+fn legacy_luminance_volume() {
+    let texture = fixture("l8_volume.dds");
+    assert_eq!(
+        *texture.desc(),
+        desc(
+            TextureDimension::Texture3d,
+            Format::R8_UNORM,
+            (4, 4, 4),
+            1,
+            3,
+            false
+        )
+    );
+    let subresource = texture.layout.subresources[1];
+    assert_eq!(
+        (
+            subresource.depth,
+            subresource.row_pitch,
+            subresource.slice_pitch
+        ),
+        (2, 2, 4)
+    );
+    assert_eq!(texture.subresource(2), &[72]);
+}
+
+#[test]
+fn dx10_cube_array() {
+    let texture = fixture("rgba16f_cube_array.dds");
+    assert_eq!(
+        *texture.desc(),
+        desc(
+            TextureDimension::Texture2d,
+            Format::R16G16B16A16_FLOAT,
+            (1, 1, 1),
+            12,
+            1,
+            true
+        )
+    );
+    assert_eq!(texture.layout.size, 12 * 8);
+}
+
+#[test]
+fn dxt1_header_parses() {
+    let mut bytes = dxt1_header();
+    bytes.extend([0; 8]);
+    let texture = Texture::from_dds(&bytes).unwrap();
+    assert_eq!(texture.desc().format, Format::BC1_UNORM);
+
+    // Anything after the texels is ignored.
+    bytes.extend([1; 3]);
+    assert_eq!(Texture::from_dds(&bytes).unwrap(), texture);
+}
+
+#[test]
+fn broken_files_are_rejected() {
+    assert_eq!(
+        Texture::from_dds(b"PNG whatever"),
+        Err(AssetError::WrongFormat("DDS"))
+    );
+    assert_eq!(
+        Texture::from_dds(b"DDS "),
+        Err(AssetError::Truncated {
+            expected: 128,
+            actual: 4
+        })
+    );
+
+    let header = dxt1_header();
+    assert_eq!(
+        Texture::from_dds(&header),
+        Err(AssetError::Truncated {
+            expected: 136,
+            actual: 128
+        })
+    );
+
+    let mut bytes = header.clone();
+    set_field(&mut bytes, 0, 100);
+    assert_eq!(
+        Texture::from_dds(&bytes),
+        Err(AssetError::Invalid(
+            "the header is 100 bytes, not 124".into()
+        ))
+    );
+
+    let mut bytes = header.clone();
+    set_field(&mut bytes, 6, 4);
+    bytes.extend([0; 32]);
+    assert_eq!(
+        Texture::from_dds(&bytes),
+        Err(AssetError::Invalid(
+            "a 4x4x1 texture can't have 4 mips".into()
+        ))
+    );
+
+    let mut bytes = header.clone();
+    set_field(&mut bytes, 20, u32::from_le_bytes(*b"DX10"));
+    assert!(matches!(
+        Texture::from_dds(&bytes),
+        Err(AssetError::Truncated { expected: 148, .. })
+    ));
+}
+
+#[test]
+fn partial_cubemaps_and_unknown_formats_are_unsupported() {
+    let mut bytes = dxt1_header();
+    // Only the positive X face.
+    set_field(&mut bytes, 27, 0x200 | 0x400);
+    assert_eq!(
+        Texture::from_dds(&bytes),
+        Err(AssetError::Unsupported(
+            "a cubemap without all six faces".into()
+        ))
+    );
+
+    let mut bytes = dxt1_header();
+    set_field(&mut bytes, 20, u32::from_le_bytes(*b"ETC1"));
+    assert!(matches!(
+        Texture::from_dds(&bytes),
+        Err(AssetError::Unsupported(_))
+    ));
+
+    // 24-bit RGB has no DXGI format.
+    let mut bytes = dxt1_header();
+    set_field(&mut bytes, 19, 0x40);
+    set_field(&mut bytes, 21, 24);
+    set_field(&mut bytes, 22, 0xff_0000);
+    set_field(&mut bytes, 23, 0xff00);
+    set_field(&mut bytes, 24, 0xff);
+    assert!(matches!(
+        Texture::from_dds(&bytes),
+        Err(AssetError::Unsupported(_))
+    ));
+
+    let mut bytes = dxt1_header();
+    set_field(&mut bytes, 20, u32::from_le_bytes(*b"DX10"));
+    bytes.extend([66, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(
+        Texture::from_dds(&bytes),
+        Err(AssetError::UnsupportedFormat(Format(66)))
+    );
+}
+
+#[test]
+fn huge_arrays_are_rejected_before_allocating() {
+    let mut bytes = dxt1_header();
+    set_field(&mut bytes, 20, u32::from_le_bytes(*b"DX10"));
+    // A cube array of u32::MAX cubes.
+    bytes.extend([
+        71, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0, 255, 255, 255, 255, 0, 0, 0, 0,
+    ]);
+    assert!(matches!(
+        Texture::from_dds(&bytes),
+        Err(AssetError::Invalid(_))
+    ));
+}
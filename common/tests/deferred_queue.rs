@@ -0,0 +1,90 @@
+use common::gfx::DeferredQueue;
+
+#[test]
+fn nothing_is_released_before_its_fence_completes() {
+    let mut queue = DeferredQueue::new();
+    queue.retire(3, "a");
+    queue.retire(5, "b");
+
+    assert_eq!(queue.drain_completed(2).count(), 0);
+    assert_eq!(queue.len(), 2);
+}
+
+#[test]
+fn completed_items_are_released_in_fence_order() {
+    let mut queue = DeferredQueue::new();
+    queue.retire(1, "a");
+    queue.retire(2, "b");
+    queue.retire(3, "c");
+
+    assert_eq!(queue.drain_completed(2).collect::<Vec<_>>(), ["a", "b"]);
+    assert_eq!(queue.drain_completed(2).count(), 0);
+    assert_eq!(queue.drain_completed(10).collect::<Vec<_>>(), ["c"]);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn completed_value_equal_to_fence_releases_item() {
+    let mut queue = DeferredQueue::new();
+    queue.retire(4, "a");
+
+    assert_eq!(queue.drain_completed(4).collect::<Vec<_>>(), ["a"]);
+}
+
+#[test]
+fn items_with_equal_fence_values_keep_retirement_order() {
+    let mut queue = DeferredQueue::new();
+    for item in ["a", "b", "c", "d"] {
+        queue.retire(7, item);
+    }
+
+    assert_eq!(
+        queue.drain_completed(7).collect::<Vec<_>>(),
+        ["a", "b", "c", "d"]
+    );
+}
+
+#[test]
+fn out_of_order_retirement_is_sorted_by_fence() {
+    let mut queue = DeferredQueue::new();
+    queue.retire(5, "late");
+    queue.retire(2, "early");
+    queue.retire(5, "late too");
+    queue.retire(3, "middle");
+
+    assert_eq!(
+        queue.drain_completed(3).collect::<Vec<_>>(),
+        ["early", "middle"]
+    );
+    assert_eq!(
+        queue.drain_completed(5).collect::<Vec<_>>(),
+        ["late", "late too"]
+    );
+}
+
+#[test]
+fn drain_all_ignores_fence_values() {
+    let mut queue = DeferredQueue::new();
+    queue.retire(100, "a");
+    queue.retire(1, "b");
+
+    assert_eq!(queue.drain_all().collect::<Vec<_>>(), ["b", "a"]);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn items_are_dropped_when_drained() {
+    use std::rc::Rc;
+
+    let tracker = Rc::new(());
+    let mut queue = DeferredQueue::new();
+    queue.retire(1, Rc::clone(&tracker));
+    queue.retire(2, Rc::clone(&tracker));
+    assert_eq!(Rc::strong_count(&tracker), 3);
+
+    queue.drain_completed(1).for_each(drop);
+    assert_eq!(Rc::strong_count(&tracker), 2);
+
+    queue.drain_all().for_each(drop);
+    assert_eq!(Rc::strong_count(&tracker), 1);
+}
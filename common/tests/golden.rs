@@ -0,0 +1,221 @@
+use std::path::PathBuf;
+
+use common::{
+    golden::{compare, diff_image, Comparison, GoldenError, GoldenTest, Tolerance},
+    screenshot::Screenshot,
+};
+
+fn solid(width: u32, height: u32, rgba: [u8; 4]) -> Screenshot {
+    Screenshot::new(width, height, rgba.repeat((width * height) as usize))
+}
+
+fn with_pixel(mut image: Screenshot, x: u32, y: u32, rgba: [u8; 4]) -> Screenshot {
+    let i = ((y * image.width + x) * 4) as usize;
+    image.pixels[i..i + 4].copy_from_slice(&rgba);
+    image
+}
+
+/// A scratch directory for one test, removed again when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("golden-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn golden_test(dir: &TempDir) -> GoldenTest {
+    let mut test = GoldenTest::new(dir.0.join("reference"), "sample").output_dir(dir.0.join("out"));
+    test.update = false;
+    test
+}
+
+#[test]
+fn identical_images_match_exactly() {
+    let image = solid(8, 4, [0, 51, 102, 255]);
+    let comparison = compare(&image, &image, 0).unwrap();
+
+    assert_eq!(
+        comparison,
+        Comparison {
+            max_delta: 0,
+            differing_pixels: 0,
+            total_pixels: 32,
+            psnr: f64::INFINITY,
+        }
+    );
+    assert!(comparison.passes(&Tolerance::EXACT));
+    assert_eq!(
+        comparison.to_string(),
+        "max delta 0, 0.000% of pixels differ, PSNR inf dB"
+    );
+}
+
+#[test]
+fn metrics() {
+    let expected = solid(10, 10, [100, 100, 100, 255]);
+    // One pixel off by 30 in red, and every pixel off by 1 in blue.
+    let actual = with_pixel(
+        solid(10, 10, [100, 100, 101, 255]),
+        3,
+        4,
+        [130, 100, 101, 255],
+    );
+
+    let comparison = compare(&expected, &actual, 2).unwrap();
+    assert_eq!(comparison.max_delta, 30);
+    assert_eq!(comparison.differing_pixels, 1);
+    assert_eq!(comparison.differing_percent(), 1.0);
+
+    // MSE = (30² + 100 * 1²) / 300.
+    let mse: f64 = (900.0 + 100.0) / 300.0;
+    let psnr = 10.0 * (255.0f64 * 255.0 / mse).log10();
+    assert!((comparison.psnr - psnr).abs() < 1e-9);
+    assert!((comparison.psnr - 42.90).abs() < 0.01);
+
+    // Every pixel differs once nothing is tolerated.
+    assert_eq!(
+        compare(&expected, &actual, 0).unwrap().differing_pixels,
+        100
+    );
+}
+
+#[test]
+fn alpha_is_ignored() {
+    let comparison = compare(&solid(2, 2, [1, 2, 3, 255]), &solid(2, 2, [1, 2, 3, 0]), 0).unwrap();
+    assert_eq!(comparison.max_delta, 0);
+    assert_eq!(comparison.psnr, f64::INFINITY);
+}
+
+#[test]
+fn each_tolerance_limit_is_checked() {
+    let comparison = Comparison {
+        max_delta: 40,
+        differing_pixels: 5,
+        total_pixels: 1000,
+        psnr: 35.0,
+    };
+    let loose = Tolerance {
+        channel_delta: 2,
+        max_differing_percent: 1.0,
+        min_psnr: 30.0,
+    };
+
+    assert!(comparison.passes(&loose));
+    assert!(!comparison.passes(&Tolerance {
+        max_differing_percent: 0.4,
+        ..loose
+    }));
+    assert!(!comparison.passes(&Tolerance {
+        min_psnr: 36.0,
+        ..loose
+    }));
+    assert!(!comparison.passes(&Tolerance::default()));
+}
+
+#[test]
+fn sizes_must_match() {
+    let error = compare(&solid(4, 4, [0; 4]), &solid(4, 2, [0; 4]), 0).unwrap_err();
+    assert!(matches!(
+        error,
+        GoldenError::SizeMismatch {
+            expected: (4, 4),
+            actual: (4, 2)
+        }
+    ));
+    assert_eq!(error.to_string(), "expected a 4x4 image, got 4x2");
+    assert!(diff_image(&solid(1, 1, [0; 4]), &solid(2, 1, [0; 4]), 0).is_err());
+}
+
+#[test]
+fn diff_image_marks_differing_pixels() {
+    let expected = solid(3, 1, [200, 200, 200, 255]);
+    let actual = with_pixel(
+        with_pixel(expected.clone(), 1, 0, [210, 200, 200, 255]),
+        2,
+        0,
+        [200, 201, 200, 255],
+    );
+
+    let diff = diff_image(&expected, &actual, 2).unwrap();
+    assert_eq!((diff.width, diff.height), (3, 1));
+    assert_eq!(diff.pixel(0, 0), [50, 50, 50, 255]);
+    assert_eq!(diff.pixel(1, 0), [133, 0, 0, 255]);
+    // Within tolerance.
+    assert_eq!(diff.pixel(2, 0), [50, 50, 50, 255]);
+
+    let fully_off = diff_image(&solid(1, 1, [0; 4]), &solid(1, 1, [255; 4]), 0).unwrap();
+    assert_eq!(fully_off.pixel(0, 0), [255, 0, 0, 255]);
+}
+
+#[test]
+fn missing_references_say_how_to_make_one() {
+    let dir = TempDir::new("missing");
+    let error = golden_test(&dir).check(&solid(2, 2, [0; 4])).unwrap_err();
+
+    assert!(matches!(error, GoldenError::MissingReference(_)));
+    assert!(error.to_string().contains("UPDATE_GOLDEN=1"), "{error}");
+}
+
+#[test]
+fn updating_writes_the_reference() {
+    let dir = TempDir::new("update");
+    let image = with_pixel(solid(4, 3, [10, 20, 30, 255]), 1, 1, [90, 80, 70, 255]);
+
+    let mut test = golden_test(&dir);
+    test.update = true;
+    test.check(&image).unwrap();
+    assert_eq!(Screenshot::load(test.reference_path()).unwrap(), image);
+
+    test.update = false;
+    let comparison = test.check(&image).unwrap();
+    assert_eq!(comparison.psnr, f64::INFINITY);
+    assert!(!test.diff_path().exists());
+}
+
+#[test]
+fn mismatches_write_a_diff_image() {
+    let dir = TempDir::new("mismatch");
+    let mut test = golden_test(&dir).tolerance(Tolerance::EXACT);
+    test.update = true;
+    test.check(&solid(4, 4, [0, 0, 0, 255])).unwrap();
+    test.update = false;
+
+    let actual = with_pixel(solid(4, 4, [0, 0, 0, 255]), 0, 0, [0, 0, 1, 255]);
+    let error = test.check(&actual).unwrap_err();
+    let GoldenError::Mismatch {
+        comparison, diff, ..
+    } = &error
+    else {
+        panic!("unexpected error {error}");
+    };
+    assert_eq!(comparison.differing_pixels, 1);
+    assert_eq!(diff, &test.diff_path());
+    assert_eq!(
+        Screenshot::load(diff).unwrap().pixel(0, 0),
+        [128, 0, 0, 255]
+    );
+
+    // The default tolerance lets a delta of 1 through.
+    test.tolerance = Tolerance::default();
+    test.check(&actual).unwrap();
+}
+
+#[test]
+fn paths_follow_the_name() {
+    let test = GoldenTest::new("golden", "hello").output_dir("target/out");
+    assert_eq!(test.reference_path(), PathBuf::from("golden/hello.png"));
+    assert_eq!(
+        test.actual_path(),
+        PathBuf::from("target/out/hello.actual.png")
+    );
+    assert_eq!(test.diff_path(), PathBuf::from("target/out/hello.diff.png"));
+}
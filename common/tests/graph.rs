@@ -0,0 +1,456 @@
+use common::gfx::graph::{
+    Barrier, CompiledGraph, GraphError, RenderGraph, ResourceDesc, ResourceId, ResourceState,
+};
+
+const HDR: ResourceDesc = ResourceDesc::Texture {
+    width: 800,
+    height: 600,
+    format: 10,
+};
+
+const DEPTH: ResourceDesc = ResourceDesc::Texture {
+    width: 800,
+    height: 600,
+    format: 40,
+};
+
+fn compile(graph: &RenderGraph<'_, Vec<String>>) -> CompiledGraph {
+    graph.compile().unwrap()
+}
+
+fn assert_schedule(compiled: &CompiledGraph, expected: &str) {
+    let actual = compiled.to_string();
+    let expected: String = expected
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|l| {
+            if l.starts_with("transition") || l.starts_with("uav") {
+                format!("  {l}\n")
+            } else {
+                format!("{l}\n")
+            }
+        })
+        .collect();
+
+    assert_eq!(actual, expected, "\nactual schedule:\n{actual}");
+}
+
+#[test]
+fn clear_back_buffer() {
+    let mut graph = RenderGraph::new();
+    let back_buffer = graph.import(
+        "back_buffer",
+        ResourceState::Present,
+        ResourceState::Present,
+    );
+    graph.add_pass(
+        "clear",
+        |p| {
+            p.write(back_buffer, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+
+    assert_schedule(
+        &compile(&graph),
+        "
+        pass clear
+        transition back_buffer Present -> RenderTarget
+        end
+        transition back_buffer RenderTarget -> Present
+        resource back_buffer imported 0..0
+        ",
+    );
+}
+
+#[test]
+fn deferred_frame() {
+    let mut graph = RenderGraph::new();
+    let back_buffer = graph.import(
+        "back_buffer",
+        ResourceState::Present,
+        ResourceState::Present,
+    );
+    let depth = graph.create("depth", DEPTH);
+    let albedo = graph.create("albedo", HDR);
+    let lit = graph.create("lit", HDR);
+
+    graph.add_pass(
+        "gbuffer",
+        |p| {
+            p.write(depth, ResourceState::DepthWrite)
+                .write(albedo, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+    graph.add_pass(
+        "lighting",
+        |p| {
+            p.read(depth, ResourceState::DepthRead)
+                .read(albedo, ResourceState::ShaderResource)
+                .write(lit, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+    graph.add_pass(
+        "tonemap",
+        |p| {
+            p.read(lit, ResourceState::ShaderResource)
+                .write(back_buffer, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+
+    assert_schedule(
+        &compile(&graph),
+        "
+        pass gbuffer
+        pass lighting
+        transition depth DepthWrite -> DepthRead
+        transition albedo RenderTarget -> ShaderResource
+        pass tonemap
+        transition lit RenderTarget -> ShaderResource
+        transition back_buffer Present -> RenderTarget
+        end
+        transition back_buffer RenderTarget -> Present
+        resource back_buffer imported 2..2
+        resource depth 0..1 physical 1
+        resource albedo 0..1 physical 2
+        resource lit 1..2 physical 3
+        physical 1 initial DepthWrite
+        physical 2 initial RenderTarget
+        physical 3 initial RenderTarget
+        ",
+    );
+}
+
+#[test]
+fn unused_passes_are_culled() {
+    let mut graph = RenderGraph::new();
+    let back_buffer = graph.import(
+        "back_buffer",
+        ResourceState::Present,
+        ResourceState::Present,
+    );
+    let debug = graph.create("debug", HDR);
+    let shadow = graph.create("shadow", DEPTH);
+
+    graph.add_pass(
+        "shadow",
+        |p| {
+            p.write(shadow, ResourceState::DepthWrite);
+        },
+        |_| {},
+    );
+    // Writes a resource nobody reads.
+    graph.add_pass(
+        "debug",
+        |p| {
+            p.read(shadow, ResourceState::ShaderResource)
+                .write(debug, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+    graph.add_pass(
+        "main",
+        |p| {
+            p.write(back_buffer, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+
+    let compiled = compile(&graph);
+    assert_eq!(compiled.culled(), ["shadow", "debug"]);
+    assert_eq!(compiled.lifetime(shadow), None);
+    assert_schedule(
+        &compiled,
+        "
+        pass main
+        transition back_buffer Present -> RenderTarget
+        end
+        transition back_buffer RenderTarget -> Present
+        culled shadow
+        culled debug
+        resource back_buffer imported 0..0
+        ",
+    );
+}
+
+#[test]
+fn side_effects_keep_a_pass_alive() {
+    let mut graph = RenderGraph::new();
+    let target = graph.create("target", HDR);
+
+    graph.add_pass(
+        "draw",
+        |p| {
+            p.write(target, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+    graph.add_pass(
+        "readback",
+        |p| {
+            p.read(target, ResourceState::CopySource).side_effects();
+        },
+        |_| {},
+    );
+
+    assert_schedule(
+        &compile(&graph),
+        "
+        pass draw
+        pass readback
+        transition target RenderTarget -> CopySource
+        resource target 0..1 physical 0
+        physical 0 initial RenderTarget
+        ",
+    );
+}
+
+#[test]
+fn transients_with_disjoint_lifetimes_share_a_physical_resource() {
+    let mut graph = RenderGraph::new();
+    let back_buffer = graph.import(
+        "back_buffer",
+        ResourceState::Present,
+        ResourceState::Present,
+    );
+    let a = graph.create("bloom_a", HDR);
+    let b = graph.create("bloom_b", HDR);
+    let c = graph.create("bloom_c", HDR);
+
+    for (name, input, output) in [("down", None, a), ("blur", Some(a), b), ("up", Some(b), c)] {
+        graph.add_pass(
+            name,
+            |p| {
+                if let Some(input) = input {
+                    p.read(input, ResourceState::ShaderResource);
+                }
+                p.write(output, ResourceState::RenderTarget);
+            },
+            |_| {},
+        );
+    }
+    graph.add_pass(
+        "composite",
+        |p| {
+            p.read(c, ResourceState::ShaderResource)
+                .write(back_buffer, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+
+    let compiled = compile(&graph);
+    // bloom_a is dead by the time bloom_c is first written.
+    assert_eq!(compiled.physical_index(a), compiled.physical_index(c));
+    assert_ne!(compiled.physical_index(a), compiled.physical_index(b));
+    assert_eq!(compiled.physical_resources().count(), 2);
+
+    assert_schedule(
+        &compiled,
+        "
+        pass down
+        pass blur
+        transition bloom_a RenderTarget -> ShaderResource
+        pass up
+        transition bloom_b RenderTarget -> ShaderResource
+        transition bloom_c ShaderResource -> RenderTarget
+        pass composite
+        transition bloom_c RenderTarget -> ShaderResource
+        transition back_buffer Present -> RenderTarget
+        end
+        transition back_buffer RenderTarget -> Present
+        resource back_buffer imported 3..3
+        resource bloom_a 0..1 physical 1
+        resource bloom_b 1..2 physical 2
+        resource bloom_c 2..3 physical 1
+        physical 1 initial RenderTarget
+        physical 2 initial RenderTarget
+        ",
+    );
+}
+
+#[test]
+fn different_descriptions_are_not_shared() {
+    let mut graph = RenderGraph::new();
+    let out = graph.import("out", ResourceState::Common, ResourceState::Common);
+    let a = graph.create("a", HDR);
+    let b = graph.create("b", ResourceDesc::Buffer { size: 1024 });
+
+    graph.add_pass(
+        "first",
+        |p| {
+            p.write(a, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+    graph.add_pass(
+        "second",
+        |p| {
+            p.read(a, ResourceState::ShaderResource)
+                .write(out, ResourceState::UnorderedAccess);
+        },
+        |_| {},
+    );
+    graph.add_pass(
+        "third",
+        |p| {
+            p.write(b, ResourceState::UnorderedAccess);
+        },
+        |_| {},
+    );
+    graph.add_pass(
+        "fourth",
+        |p| {
+            p.read(b, ResourceState::ShaderResource)
+                .write(out, ResourceState::UnorderedAccess);
+        },
+        |_| {},
+    );
+
+    let compiled = compile(&graph);
+    assert_ne!(compiled.physical_index(a), compiled.physical_index(b));
+
+    // `b` is first used as a UAV, so that's the state it's created in.
+    let initial: Vec<_> = compiled
+        .physical_resources()
+        .map(|(i, _, state)| (i, state))
+        .collect();
+    assert_eq!(
+        initial,
+        [
+            (compiled.physical_index(a), ResourceState::RenderTarget),
+            (compiled.physical_index(b), ResourceState::UnorderedAccess),
+        ]
+    );
+}
+
+#[test]
+fn consecutive_unordered_access_gets_uav_barriers() {
+    let mut graph = RenderGraph::new();
+    let buffer = graph.import(
+        "particles",
+        ResourceState::UnorderedAccess,
+        ResourceState::ShaderResource,
+    );
+
+    for name in ["emit", "simulate"] {
+        graph.add_pass(
+            name,
+            |p| {
+                p.read(buffer, ResourceState::UnorderedAccess)
+                    .write(buffer, ResourceState::UnorderedAccess);
+            },
+            |_| {},
+        );
+    }
+
+    assert_schedule(
+        &compile(&graph),
+        "
+        pass emit
+        uav particles
+        pass simulate
+        uav particles
+        end
+        transition particles UnorderedAccess -> ShaderResource
+        resource particles imported 0..1
+        ",
+    );
+}
+
+#[test]
+fn reading_a_transient_before_it_is_written_is_an_error() {
+    let mut graph: RenderGraph<'_, ()> = RenderGraph::new();
+    let missing = graph.create("missing", HDR);
+    graph.add_pass(
+        "reader",
+        |p| {
+            p.read(missing, ResourceState::ShaderResource)
+                .side_effects();
+        },
+        |_| {},
+    );
+
+    assert_eq!(
+        graph.compile().unwrap_err(),
+        GraphError::ReadBeforeWrite {
+            pass: "reader".into(),
+            resource: "missing".into()
+        }
+    );
+}
+
+#[test]
+fn one_resource_in_two_states_in_a_pass_is_an_error() {
+    let mut graph: RenderGraph<'_, ()> = RenderGraph::new();
+    let target = graph.import("target", ResourceState::Common, ResourceState::Common);
+    graph.add_pass(
+        "confused",
+        |p| {
+            p.read(target, ResourceState::ShaderResource)
+                .write(target, ResourceState::RenderTarget);
+        },
+        |_| {},
+    );
+
+    assert!(matches!(
+        graph.compile(),
+        Err(GraphError::ConflictingAccess { .. })
+    ));
+}
+
+#[test]
+fn execute_runs_scheduled_passes_with_their_barriers() {
+    let mut graph = RenderGraph::new();
+    let back_buffer = graph.import(
+        "back_buffer",
+        ResourceState::Present,
+        ResourceState::Present,
+    );
+    let scratch = graph.create("scratch", HDR);
+
+    graph.add_pass(
+        "unused",
+        |p| {
+            p.write(scratch, ResourceState::RenderTarget);
+        },
+        |log: &mut Vec<String>| log.push("unused".into()),
+    );
+    graph.add_pass(
+        "draw",
+        |p| {
+            p.write(back_buffer, ResourceState::RenderTarget);
+        },
+        |log: &mut Vec<String>| log.push("draw".into()),
+    );
+
+    let compiled = compile(&graph);
+    let mut log = Vec::new();
+    graph.execute(&compiled, &mut log, |log, barriers| {
+        for barrier in barriers {
+            if let Barrier::Transition { before, after, .. } = barrier {
+                log.push(format!("{before:?} -> {after:?}"));
+            }
+        }
+    });
+
+    assert_eq!(
+        log,
+        ["Present -> RenderTarget", "draw", "RenderTarget -> Present"]
+    );
+}
+
+#[test]
+fn resource_ids_map_back_to_names() {
+    let mut graph: RenderGraph<'_, ()> = RenderGraph::new();
+    let ids: Vec<ResourceId> = ["a", "b", "c"]
+        .into_iter()
+        .map(|name| graph.create(name, HDR))
+        .collect();
+
+    let compiled = graph.compile().unwrap();
+    assert_eq!(compiled.resource_name(ids[1]), "b");
+}
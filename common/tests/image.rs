@@ -0,0 +1,376 @@
+use std::path::Path;
+
+use common::{
+    assets::{
+        image::{Image, ImageFile, MipFilter, PixelFormat},
+        AssetError, Texture,
+    },
+    gfx::Format,
+};
+
+fn fixture_path(name: &str) -> std::path::PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+fn fixture(name: &str) -> Image {
+    Image::load(fixture_path(name)).unwrap()
+}
+
+fn rgba8(image: &Image, x: u32, y: u32) -> [u8; 4] {
+    let i = (y * image.width + x) as usize * 4;
+    image.data[i..i + 4].try_into().unwrap()
+}
+
+/// Every pixel is within `tolerance` of `expected`, which is what the
+/// fixture was encoded from.
+fn assert_close(
+    image: &Image,
+    size: (u32, u32),
+    tolerance: u8,
+    expected: impl Fn(u32, u32) -> [u8; 4],
+) {
+    assert_eq!((image.width, image.height), size);
+    assert_eq!(image.format, PixelFormat::Rgba8);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let (actual, expected) = (rgba8(image, x, y), expected(x, y));
+            let close = actual
+                .iter()
+                .zip(expected)
+                .all(|(&a, e)| a.abs_diff(e) <= tolerance);
+            assert!(close, "at {x}, {y}: {actual:?} isn't close to {expected:?}");
+        }
+    }
+}
+
+fn assert_floats(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-4, "{actual:?} isn't {expected:?}");
+    }
+}
+
+#[test]
+fn image_files_come_from_the_extension() {
+    let file = |name: &str| ImageFile::from_path(Path::new(name));
+    assert_eq!(file("a.PNG"), Some(ImageFile::Png));
+    assert_eq!(file("a.jpg"), Some(ImageFile::Jpeg));
+    assert_eq!(file("a.jpeg"), Some(ImageFile::Jpeg));
+    assert_eq!(file("a.tga"), Some(ImageFile::Tga));
+    assert_eq!(file("a.hdr"), Some(ImageFile::Hdr));
+    assert_eq!(file("a.dds"), None);
+    assert!(Image::load("missing.gif").is_err());
+}
+
+#[test]
+fn eight_bit_png() {
+    assert_close(&fixture("grey_alpha.png"), (16, 8), 0, |x, y| {
+        let l = (x * 16 + y) as u8;
+        [l, l, l, 255u32.wrapping_sub(x * 8 + y * 4) as u8]
+    });
+}
+
+#[test]
+fn sixteen_bit_png_keeps_the_low_byte() {
+    let image = fixture("rgb_16bit.png");
+    assert_eq!((image.width, image.height), (4, 2));
+    assert_eq!(image.format, PixelFormat::Rgba16f);
+    for y in 0..2 {
+        for x in 0..4 {
+            let sample = |c: u32| x * 0x1234 + y * 0x0101 + c * 0x2000;
+            let pixel = image.pixel(x, y);
+            for c in 0..3 {
+                // Half floats have 11 significant bits.
+                let expected = sample(c) as f32 / 65535.0;
+                let error = (pixel[c as usize] - expected).abs();
+                assert!(error <= expected / 2048.0, "{pixel:?} at {x}, {y}");
+            }
+            assert_eq!(pixel[3], 1.0);
+        }
+    }
+
+    // 0x0101 and 0x0100 share a high byte, but not a value.
+    let red = image.pixel(0, 1)[0] * 65535.0;
+    assert_eq!(red.round(), 257.0);
+}
+
+#[test]
+fn jpeg_without_subsampling() {
+    // Quantized by one, so only rounding separates it from the source.
+    assert_close(&fixture("rgb_444.jpg"), (20, 12), 3, |x, y| {
+        [
+            (x * 12) as u8,
+            (y * 20) as u8,
+            (255 - x * 6 - y * 4) as u8,
+            255,
+        ]
+    });
+}
+
+#[test]
+fn jpeg_with_420_chroma_and_restarts() {
+    // Each 2x2 square is one color, so repeating chroma samples is exact.
+    assert_close(&fixture("rgb_420_restart.jpg"), (20, 12), 3, |x, y| {
+        let (x, y) = (x / 2, y / 2);
+        [(x * 25) as u8, (y * 40) as u8, (200 - x * 10) as u8, 255]
+    });
+}
+
+#[test]
+fn grey_jpeg() {
+    assert_close(&fixture("grey.jpg"), (9, 9), 2, |x, y| {
+        let l = (x * 20 + y) as u8;
+        [l, l, l, 255]
+    });
+}
+
+#[test]
+fn run_length_encoded_tga() {
+    assert_close(&fixture("rle_bottom_up.tga"), (5, 3), 0, |x, y| {
+        [if y == 1 { 99 } else { x as u8 * 40 }, y as u8 * 60, 7, 200]
+    });
+}
+
+fn tga_header(image_type: u8, width: u16, height: u16, bits: u8, descriptor: u8) -> Vec<u8> {
+    let mut header = vec![0, 0, image_type, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    header.extend(width.to_le_bytes());
+    header.extend(height.to_le_bytes());
+    header.extend([bits, descriptor]);
+    header
+}
+
+#[test]
+fn tga_pixel_types_and_origins() {
+    // 24-bit, top to bottom and right to left.
+    let mut tga = tga_header(2, 2, 2, 24, 0x30);
+    tga.extend([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+    let image = Image::from_tga(&tga).unwrap();
+    assert_eq!(
+        image.data,
+        [6, 5, 4, 255, 3, 2, 1, 255, 12, 11, 10, 255, 9, 8, 7, 255]
+    );
+
+    // 16-bit with the alpha bit ignored, since the descriptor has no alpha.
+    let mut tga = tga_header(2, 2, 1, 16, 0);
+    tga.extend((0x7c00u16 | 0x1f).to_le_bytes());
+    tga.extend(0x83e0u16.to_le_bytes());
+    let image = Image::from_tga(&tga).unwrap();
+    assert_eq!(image.data, [255, 0, 255, 255, 0, 255, 0, 255]);
+
+    // Color mapped, with a map starting at index 10.
+    let mut tga = tga_header(1, 3, 1, 8, 0x20);
+    tga[1] = 1;
+    tga[3..8].copy_from_slice(&[10, 0, 2, 0, 24]);
+    tga.extend([30, 20, 10, 60, 50, 40]);
+    tga.extend([11, 10, 11]);
+    let image = Image::from_tga(&tga).unwrap();
+    assert_eq!(
+        image.data,
+        [40, 50, 60, 255, 10, 20, 30, 255, 40, 50, 60, 255]
+    );
+
+    // Grey and alpha, run-length encoded across the row boundary.
+    let mut tga = tga_header(11, 2, 2, 16, 0x28);
+    tga.extend([0x82, 7, 128, 0, 9, 255]);
+    let image = Image::from_tga(&tga).unwrap();
+    assert_eq!(
+        image.data,
+        [7, 7, 7, 128, 7, 7, 7, 128, 7, 7, 7, 128, 9, 9, 9, 255]
+    );
+}
+
+#[test]
+fn hdr_with_run_length_encoded_scanlines() {
+    let image = fixture("gradient.hdr");
+    assert_eq!(
+        (image.width, image.height, image.format),
+        (9, 2, PixelFormat::Rgba32f)
+    );
+    for y in 0..2 {
+        for x in 0..9 {
+            let scale = 2f32.powi(y as i32 - 7);
+            let expected = [x as f32 * 10.0, 128.0, 255.0 - x as f32, 1.0 / scale];
+            assert_floats(&image.pixel(x, y), &expected.map(|value| value * scale));
+        }
+    }
+}
+
+#[test]
+fn flat_hdr_with_repeats_bottom_up() {
+    let mut hdr = b"#?RGBE\n\n+Y 2 +X 3\n".to_vec();
+    // A pixel, then a repeat of it twice.
+    hdr.extend([128, 64, 32, 129, 1, 1, 1, 2]);
+    hdr.extend([0, 0, 0, 0, 255, 0, 0, 136, 1, 1, 1, 1]);
+    let image = Image::from_hdr(&hdr).unwrap();
+    assert_floats(&image.pixel(2, 1), &[1.0, 0.5, 0.25, 1.0]);
+    assert_floats(&image.pixel(0, 0), &[0.0, 0.0, 0.0, 1.0]);
+    assert_floats(&image.pixel(2, 0), &[255.0, 0.0, 0.0, 1.0]);
+
+    assert_eq!(
+        Image::from_hdr(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0"),
+        Err(AssetError::Unsupported(
+            "the HDR pixel format 32-bit_rle_xyze".into()
+        ))
+    );
+}
+
+#[test]
+fn conversions_keep_colors() {
+    let image = Image::new(
+        2,
+        1,
+        PixelFormat::Rgba8,
+        vec![0, 128, 255, 255, 188, 64, 32, 0],
+    );
+    let srgb = image.clone().as_srgb();
+    assert_eq!(srgb.data, image.data);
+    assert_eq!(srgb.format.format(), Format::R8G8B8A8_UNORM_SRGB);
+    assert!((srgb.pixel(1, 0)[0] - 0.5029).abs() < 1e-3);
+
+    // The same values, re-encoded so the GPU decodes them to what they were.
+    let encoded = image.convert(PixelFormat::Rgba8Srgb);
+    assert_eq!(encoded.data, [0, 188, 255, 255, 223, 137, 99, 0]);
+    assert_eq!(encoded.convert(PixelFormat::Rgba8), image);
+
+    let half = srgb.convert(PixelFormat::Rgba16f);
+    assert_eq!(half.data.len(), 16);
+    let float = half.convert(PixelFormat::Rgba32f);
+    assert!(float
+        .to_linear()
+        .iter()
+        .zip(srgb.to_linear())
+        .all(|(a, b)| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-3)));
+    assert_eq!(float.convert(PixelFormat::Rgba8Srgb), srgb);
+}
+
+#[test]
+fn box_mips_are_gamma_correct() {
+    let image = Image::new(
+        2,
+        1,
+        PixelFormat::Rgba8,
+        vec![0, 64, 0, 0, 255, 200, 0, 255],
+    );
+    let mips = image.clone().as_srgb().mip_chain(MipFilter::Box);
+    assert_eq!(mips.len(), 2);
+    // Alpha is averaged as it is.
+    assert_eq!(mips[1].data, [188, 152, 0, 128]);
+
+    let mips = image.mip_chain(MipFilter::Box);
+    assert_eq!(mips[1].data, [128, 132, 0, 128]);
+}
+
+#[test]
+fn box_mips_of_odd_sizes_cover_every_texel() {
+    let pixels = [0.0, 90.0, 180.0].map(|value| [value, 0.0, 0.0, 1.0]);
+    let image = Image::from_linear(3, 1, PixelFormat::Rgba32f, &pixels);
+    let mips = image.mip_chain(MipFilter::Box);
+    assert_eq!(mips.len(), 2);
+    assert_floats(&mips[1].pixel(0, 0), &[90.0, 0.0, 0.0, 1.0]);
+
+    let image = Image::from_linear(5, 3, PixelFormat::Rgba8, &vec![[1.0; 4]; 15]);
+    let sizes: Vec<_> = image
+        .mip_chain(MipFilter::Box)
+        .iter()
+        .map(|mip| (mip.width, mip.height))
+        .collect();
+    assert_eq!(sizes, [(5, 3), (2, 1), (1, 1)]);
+}
+
+#[test]
+fn kaiser_mips_match_reference() {
+    let step = [0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0];
+    let pixels = step.map(|value| [value, value, 1.0 - value, 1.0]);
+    let image = Image::from_linear(8, 1, PixelFormat::Rgba32f, &pixels);
+    let mips = image.mip_chain(MipFilter::Kaiser);
+
+    // Ringing below zero is clamped before the next mip is made.
+    let red = |mip: &Image| {
+        (0..mip.width)
+            .map(|x| mip.pixel(x, 0)[0])
+            .collect::<Vec<_>>()
+    };
+    assert_floats(&red(&mips[1]), &[0.0, 0.05692, 0.94308, 1.01138]);
+    assert_floats(&red(&mips[2]), &[0.07364, 0.93117]);
+    assert_floats(&red(&mips[3]), &[0.50241]);
+    assert_floats(&[mips[1].pixel(3, 0)[2]], &[0.0]);
+}
+
+#[test]
+fn kaiser_keeps_flat_images_flat() {
+    let image = Image::new(7, 5, PixelFormat::Rgba8, [90, 160, 20, 255].repeat(35));
+    for mip in image.as_srgb().mip_chain(MipFilter::Kaiser) {
+        assert!(mip
+            .data
+            .chunks_exact(4)
+            .all(|texel| texel == [90, 160, 20, 255]));
+    }
+}
+
+#[test]
+fn images_become_textures_with_mips() {
+    let image = fixture("rgb_444.jpg");
+    let texture = image.to_texture(Some(MipFilter::Box)).unwrap();
+    assert_eq!(texture.desc().mip_levels, 5);
+    assert_eq!(texture.desc().format, Format::R8G8B8A8_UNORM);
+    assert_eq!(texture.subresource(0), image.data);
+    assert_eq!(texture.subresource(4).len(), 4);
+
+    let texture = image.to_texture(None).unwrap();
+    assert_eq!(texture.desc().mip_levels, 1);
+
+    let texture = Texture::load(fixture_path("gradient.hdr")).unwrap();
+    assert_eq!(texture.desc().format, Format::R32G32B32A32_FLOAT);
+    assert_eq!(texture.desc().mip_levels, 4);
+}
+
+#[test]
+fn damaged_images_are_rejected() {
+    assert_eq!(
+        Image::from_jpeg(b"GIF89a"),
+        Err(AssetError::WrongFormat("JPEG"))
+    );
+    assert_eq!(
+        Image::from_png(b"GIF89a"),
+        Err(AssetError::WrongFormat("PNG"))
+    );
+    assert_eq!(
+        Image::from_hdr(b"GIF89a"),
+        Err(AssetError::WrongFormat("Radiance HDR"))
+    );
+    assert_eq!(
+        Image::from_tga(&tga_header(7, 1, 1, 8, 0)),
+        Err(AssetError::WrongFormat("TGA"))
+    );
+    assert_eq!(
+        Image::from_tga(&tga_header(2, 2, 2, 24, 0)),
+        Err(AssetError::Truncated {
+            expected: 30,
+            actual: 18
+        })
+    );
+    assert_eq!(
+        Image::from_tga(&tga_header(2, 0, 2, 24, 0)),
+        Err(AssetError::Unsupported("a 0x2 image".into()))
+    );
+
+    let jpeg = std::fs::read(fixture_path("rgb_444.jpg")).unwrap();
+    assert!(matches!(
+        Image::from_jpeg(&jpeg[..jpeg.len() / 2]),
+        Err(AssetError::Invalid(_))
+    ));
+
+    // The same file claiming to be progressive.
+    let mut progressive = jpeg.clone();
+    let sof = progressive
+        .windows(2)
+        .position(|marker| marker == [0xff, 0xc0])
+        .unwrap();
+    progressive[sof + 1] = 0xc2;
+    assert_eq!(
+        Image::from_jpeg(&progressive),
+        Err(AssetError::Unsupported("progressive JPEGs".into()))
+    );
+}
@@ -0,0 +1,118 @@
+use common::{
+    input::{InputEvent, InputState, Key, MouseButton},
+    math::Vec2,
+};
+
+fn frame(input: &mut InputState, events: &[InputEvent]) {
+    input.begin_frame();
+    for &event in events {
+        input.handle(event);
+    }
+}
+
+#[test]
+fn presses_last_one_frame() {
+    let mut input = InputState::new();
+
+    frame(&mut input, &[InputEvent::KeyDown(Key::W)]);
+    assert!(input.is_key_down(Key::W));
+    assert!(input.was_key_pressed(Key::W));
+
+    // Auto-repeat doesn't press again.
+    frame(&mut input, &[InputEvent::KeyDown(Key::W)]);
+    assert!(input.is_key_down(Key::W));
+    assert!(!input.was_key_pressed(Key::W));
+
+    frame(&mut input, &[InputEvent::KeyUp(Key::W)]);
+    assert!(!input.is_key_down(Key::W));
+    assert!(input.was_key_released(Key::W));
+
+    frame(&mut input, &[]);
+    assert!(!input.was_key_released(Key::W));
+}
+
+#[test]
+fn tap_within_a_frame() {
+    let mut input = InputState::new();
+
+    frame(
+        &mut input,
+        &[
+            InputEvent::ButtonDown(MouseButton::Left),
+            InputEvent::ButtonUp(MouseButton::Left),
+        ],
+    );
+    assert!(!input.is_button_down(MouseButton::Left));
+    assert!(input.was_button_pressed(MouseButton::Left));
+    assert!(input.was_button_released(MouseButton::Left));
+    assert!(!input.was_button_pressed(MouseButton::Right));
+
+    // Releasing something that was never down isn't a release.
+    frame(&mut input, &[InputEvent::KeyUp(Key::A)]);
+    assert!(!input.was_key_released(Key::A));
+}
+
+#[test]
+fn mouse_movement() {
+    let mut input = InputState::new();
+    assert_eq!(input.mouse_position(), None);
+
+    // The first position has nothing to be relative to.
+    frame(&mut input, &[InputEvent::MouseMove(Vec2::new(100.0, 50.0))]);
+    assert_eq!(input.mouse_delta(), Vec2::ZERO);
+    assert_eq!(input.mouse_position(), Some(Vec2::new(100.0, 50.0)));
+
+    frame(
+        &mut input,
+        &[
+            InputEvent::MouseMove(Vec2::new(110.0, 40.0)),
+            InputEvent::MouseMove(Vec2::new(125.0, 45.0)),
+            InputEvent::Wheel(1.0),
+            InputEvent::Wheel(0.5),
+        ],
+    );
+    assert_eq!(input.mouse_delta(), Vec2::new(25.0, -5.0));
+    assert_eq!(input.wheel_delta(), 1.5);
+
+    frame(&mut input, &[]);
+    assert_eq!(input.mouse_delta(), Vec2::ZERO);
+    assert_eq!(input.wheel_delta(), 0.0);
+    assert_eq!(input.mouse_position(), Some(Vec2::new(125.0, 45.0)));
+}
+
+#[test]
+fn losing_focus_releases_everything() {
+    let mut input = InputState::new();
+    frame(
+        &mut input,
+        &[
+            InputEvent::KeyDown(Key::Shift),
+            InputEvent::ButtonDown(MouseButton::Right),
+            InputEvent::MouseMove(Vec2::new(1.0, 1.0)),
+        ],
+    );
+
+    frame(
+        &mut input,
+        &[
+            InputEvent::FocusLost,
+            InputEvent::MouseMove(Vec2::new(500.0, 500.0)),
+        ],
+    );
+    assert!(!input.is_key_down(Key::Shift));
+    assert!(input.was_key_released(Key::Shift));
+    assert!(input.was_button_released(MouseButton::Right));
+    assert_eq!(input.mouse_delta(), Vec2::ZERO);
+}
+
+#[test]
+fn key_names() {
+    assert_eq!(Key::from_char('a'), Some(Key::A));
+    assert_eq!(Key::from_char('Z'), Some(Key::Z));
+    assert_eq!(Key::from_char('7'), Some(Key::Digit7));
+    assert_eq!(Key::from_char(' '), None);
+    assert_eq!(Key::function(1), Some(Key::F1));
+    assert_eq!(Key::function(12), Some(Key::F12));
+    assert_eq!(Key::function(0), None);
+    assert_eq!(Key::function(13), None);
+}
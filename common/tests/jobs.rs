@@ -0,0 +1,242 @@
+mod support;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use common::jobs::{JobSystem, TaskGraph, TaskId};
+use support::Rng;
+
+#[test]
+fn scope_runs_every_job_before_returning() {
+    let jobs = JobSystem::new(4);
+    let counter = AtomicUsize::new(0);
+
+    jobs.scope(|s| {
+        for _ in 0..1000 {
+            s.spawn(|_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+    });
+
+    assert_eq!(counter.load(Ordering::Relaxed), 1000);
+}
+
+#[test]
+fn scope_returns_the_closure_result() {
+    let jobs = JobSystem::new(2);
+    assert_eq!(jobs.scope(|_| 42), 42);
+}
+
+#[test]
+fn zero_workers_runs_everything_on_the_caller() {
+    let jobs = JobSystem::new(0);
+    let caller = std::thread::current().id();
+    let ran_elsewhere = AtomicUsize::new(0);
+
+    jobs.scope(|s| {
+        for _ in 0..100 {
+            s.spawn(|_| {
+                if std::thread::current().id() != caller {
+                    ran_elsewhere.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    assert_eq!(ran_elsewhere.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn jobs_can_spawn_more_jobs_and_nest_scopes() {
+    let jobs = JobSystem::new(3);
+    let counter = AtomicUsize::new(0);
+
+    jobs.scope(|s| {
+        for _ in 0..16 {
+            s.spawn(|s| {
+                for _ in 0..16 {
+                    s.spawn(|_| {
+                        // A nested scope inside a worker must not deadlock even
+                        // when every worker is blocked in one.
+                        jobs.scope(|inner| {
+                            for _ in 0..4 {
+                                inner.spawn(|_| {
+                                    counter.fetch_add(1, Ordering::Relaxed);
+                                });
+                            }
+                        });
+                    });
+                }
+            });
+        }
+    });
+
+    assert_eq!(counter.load(Ordering::Relaxed), 16 * 16 * 4);
+}
+
+#[test]
+fn parallel_for_visits_each_item_once_with_its_index() {
+    for workers in [0, 1, 3, 8] {
+        let jobs = JobSystem::new(workers);
+        for len in [0, 1, 7, 64, 1001] {
+            let mut items = vec![usize::MAX; len];
+            jobs.parallel_for(&mut items, |i, item| {
+                assert_eq!(*item, usize::MAX);
+                *item = i;
+            });
+
+            assert!(items.iter().enumerate().all(|(i, item)| i == *item));
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "job failed")]
+fn panic_in_a_job_is_resumed_on_the_caller() {
+    let jobs = JobSystem::new(2);
+    jobs.scope(|s| {
+        s.spawn(|_| panic!("job failed"));
+    });
+}
+
+#[test]
+fn job_system_is_usable_after_a_panic() {
+    let jobs = JobSystem::new(2);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        jobs.scope(|s| {
+            for i in 0..10 {
+                s.spawn(move |_| {
+                    if i == 5 {
+                        panic!("job failed");
+                    }
+                });
+            }
+        })
+    }));
+    assert!(result.is_err());
+
+    let counter = AtomicUsize::new(0);
+    jobs.parallel_for(&mut [(); 100], |_, _| {
+        counter.fetch_add(1, Ordering::Relaxed);
+    });
+    assert_eq!(counter.load(Ordering::Relaxed), 100);
+}
+
+#[test]
+fn task_graph_respects_a_chain() {
+    let jobs = JobSystem::new(4);
+    let order = Mutex::new(Vec::new());
+
+    let mut graph = TaskGraph::new();
+    let mut previous: Option<TaskId> = None;
+    for i in 0..50 {
+        let order = &order;
+        let dependencies: Vec<TaskId> = previous.into_iter().collect();
+        previous =
+            Some(graph.add_with_dependencies(&dependencies, move || order.lock().unwrap().push(i)));
+    }
+    jobs.run(graph);
+
+    assert_eq!(*order.lock().unwrap(), (0..50).collect::<Vec<_>>());
+}
+
+#[test]
+fn task_graph_diamond_joins_after_both_branches() {
+    let jobs = JobSystem::new(4);
+    let log = Mutex::new(Vec::new());
+    let log = &log;
+
+    let mut graph = TaskGraph::new();
+    let top = graph.add(|| log.lock().unwrap().push("top"));
+    let left = graph.add_with_dependencies(&[top], || log.lock().unwrap().push("left"));
+    let right = graph.add_with_dependencies(&[top], || log.lock().unwrap().push("right"));
+    graph.add_with_dependencies(&[left, right, left], || log.lock().unwrap().push("bottom"));
+    jobs.run(graph);
+
+    let log = log.lock().unwrap();
+    assert_eq!(log.len(), 4);
+    assert_eq!(log[0], "top");
+    assert_eq!(log[3], "bottom");
+}
+
+#[test]
+fn stress_random_task_graphs() {
+    let jobs = JobSystem::new(6);
+
+    for seed in 1..=40 {
+        let mut rng = Rng::new(seed);
+        let count = rng.range(1, 300) as usize;
+
+        // Each task records when it finished; every dependency must have
+        // finished earlier.
+        let clock = AtomicUsize::new(1);
+        let finished: Vec<AtomicUsize> = (0..count).map(|_| AtomicUsize::new(0)).collect();
+        let mut all_dependencies = Vec::new();
+
+        let mut graph = TaskGraph::new();
+        let mut ids = Vec::new();
+        for i in 0..count {
+            let dependency_count = if i == 0 { 0 } else { rng.range(0, 4) };
+            let dependencies: Vec<usize> = (0..dependency_count)
+                .map(|_| rng.range(0, i as u64) as usize)
+                .collect();
+
+            let task_dependencies: Vec<TaskId> = dependencies.iter().map(|&d| ids[d]).collect();
+            let (clock, finished) = (&clock, &finished);
+            let deps = dependencies.clone();
+            ids.push(graph.add_with_dependencies(&task_dependencies, move || {
+                for d in &deps {
+                    assert_ne!(finished[*d].load(Ordering::Acquire), 0);
+                }
+                let now = clock.fetch_add(1, Ordering::AcqRel);
+                finished[i].store(now, Ordering::Release);
+            }));
+
+            all_dependencies.push(dependencies);
+        }
+
+        assert_eq!(graph.len(), count);
+        jobs.run(graph);
+
+        for (task, dependencies) in all_dependencies.iter().enumerate() {
+            let task_time = finished[task].load(Ordering::Relaxed);
+            assert_ne!(task_time, 0, "seed {seed}: task {task} never ran");
+            for &d in dependencies {
+                assert!(finished[d].load(Ordering::Relaxed) < task_time);
+            }
+        }
+    }
+}
+
+#[test]
+fn stress_many_scopes_from_many_threads() {
+    let jobs = JobSystem::new(4);
+    let total = AtomicUsize::new(0);
+
+    std::thread::scope(|threads| {
+        for _ in 0..4 {
+            threads.spawn(|| {
+                for _ in 0..200 {
+                    let mut values = [1usize; 37];
+                    jobs.parallel_for(&mut values, |i, v| *v += i);
+                    total.fetch_add(values.iter().sum::<usize>(), Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    let per_call: usize = (0..37).map(|i| 1 + i).sum();
+    assert_eq!(total.load(Ordering::Relaxed), per_call * 200 * 4);
+}
+
+#[test]
+fn dropping_the_job_system_joins_idle_workers() {
+    for _ in 0..50 {
+        let jobs = JobSystem::new(8);
+        jobs.scope(|s| s.spawn(|_| {}));
+        drop(jobs);
+    }
+}
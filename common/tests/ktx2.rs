@@ -0,0 +1,830 @@
+use std::path::{Path, PathBuf};
+
+use common::{
+    assets::{
+        ktx2::{
+            dfd::{
+                CHANNEL_ALPHA, CHANNEL_BLUE, CHANNEL_GREEN, CHANNEL_RED, COLOR_MODEL_BC1A,
+                COLOR_MODEL_BC4, COLOR_MODEL_BC6H, COLOR_MODEL_BC7, COLOR_MODEL_ETC1S,
+                COLOR_MODEL_RGBSDA, COLOR_MODEL_UASTC, SAMPLE_FLOAT, SAMPLE_LINEAR, SAMPLE_SIGNED,
+                TRANSFER_FUNCTION_SRGB,
+            },
+            vk_format_to_dxgi, BasisCodec, DataFormatDescriptor, Ktx2, Supercompression,
+            IDENTIFIER,
+        },
+        AssetError, Texture, TextureDimension,
+    },
+    gfx::Format,
+};
+
+const VK_FORMAT_R8_UNORM: u32 = 9;
+const VK_FORMAT_R8G8B8A8_UNORM: u32 = 37;
+const VK_FORMAT_BC7_UNORM_BLOCK: u32 = 145;
+
+/// Noise with long stretches repeated from 3000 bytes back, so zstd has
+/// both literals and matches to code. The fixtures' levels are made with
+/// the level number as the seed.
+fn level_data(len: usize, seed: u32) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::with_capacity(len);
+    let mut state = 0x9e37_79b9 ^ seed;
+    for i in 0..len {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let byte = if i >= 3000 && (i / 700) % 6 != 0 {
+            data[i - 3000]
+        } else {
+            (state >> 26) as u8
+        };
+        data.push(byte);
+    }
+    data
+}
+
+fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// A basic DFD block. Samples are (bit offset, bits, channel type, upper).
+fn dfd(
+    color_model: u8,
+    transfer_function: u8,
+    block_size: [u8; 2],
+    samples: &[(u16, u8, u8, u32)],
+) -> Vec<u8> {
+    let block_size_bytes = 24 + 16 * samples.len() as u32;
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(4 + block_size_bytes).to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&(2 | block_size_bytes << 16).to_le_bytes());
+    bytes.extend_from_slice(&[color_model, 1, transfer_function, 0]);
+    bytes.extend_from_slice(&[block_size[0] - 1, block_size[1] - 1, 0, 0]);
+    bytes.extend_from_slice(&[0; 8]);
+    for &(offset, bits, channel_type, upper) in samples {
+        bytes.extend_from_slice(&offset.to_le_bytes());
+        bytes.extend_from_slice(&[bits - 1, channel_type, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&upper.to_le_bytes());
+    }
+    bytes
+}
+
+fn rgba8_dfd(transfer_function: u8) -> Vec<u8> {
+    dfd(
+        COLOR_MODEL_RGBSDA,
+        transfer_function,
+        [1, 1],
+        &[
+            (0, 8, CHANNEL_RED, 255),
+            (8, 8, CHANNEL_GREEN, 255),
+            (16, 8, CHANNEL_BLUE, 255),
+            (24, 8, CHANNEL_ALPHA | SAMPLE_LINEAR, 255),
+        ],
+    )
+}
+
+/// An uncompressed KTX2 with its levels stored smallest first, as writers
+/// do.
+fn ktx2_file(
+    vk_format: u32,
+    (width, height, depth): (u32, u32, u32),
+    layer_count: u32,
+    face_count: u32,
+    levels: &[Vec<u8>],
+    dfd: &[u8],
+) -> Vec<u8> {
+    let header_size = 80 + 24 * levels.len();
+    let mut bytes = IDENTIFIER.to_vec();
+    for field in [
+        vk_format,
+        1,
+        width,
+        height,
+        depth,
+        layer_count,
+        face_count,
+        levels.len() as u32,
+        0,
+        header_size as u32,
+        dfd.len() as u32,
+        0,
+        0,
+    ] {
+        bytes.extend_from_slice(&field.to_le_bytes());
+    }
+    bytes.extend_from_slice(&[0; 16]);
+
+    let mut offset = header_size + dfd.len();
+    let mut index = vec![[0u64; 3]; levels.len()];
+    for (level, data) in levels.iter().enumerate().rev() {
+        index[level] = [offset as u64, data.len() as u64, data.len() as u64];
+        offset += data.len();
+    }
+    for entry in index {
+        for value in entry {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    bytes.extend_from_slice(dfd);
+    for data in levels.iter().rev() {
+        bytes.extend_from_slice(data);
+    }
+    bytes
+}
+
+#[test]
+fn zstd_supercompressed_rgba8_with_metadata() {
+    let bytes = std::fs::read(fixture_path("rgba8_srgb_zstd.ktx2")).unwrap();
+    let ktx2 = Ktx2::parse(&bytes).unwrap();
+    assert_eq!(ktx2.supercompression, Supercompression::Zstd);
+    assert_eq!((ktx2.width, ktx2.height, ktx2.depth), (192, 192, 0));
+    assert_eq!(ktx2.levels[1].uncompressed_length, 96 * 96 * 4);
+    assert_eq!(
+        ktx2.key_values,
+        [
+            ("KTXorientation".to_owned(), &b"rd\0"[..]),
+            ("KTXwriter".to_owned(), &b"gen.py\0"[..]),
+        ]
+    );
+    assert!(ktx2.dfd.srgb());
+
+    // The top level is big enough to take two zstd blocks.
+    let texture = Texture::load(fixture_path("rgba8_srgb_zstd.ktx2")).unwrap();
+    let desc = *texture.desc();
+    assert_eq!(desc.format, Format::R8G8B8A8_UNORM_SRGB);
+    assert_eq!(desc.dimension, TextureDimension::Texture2d);
+    assert_eq!((desc.width, desc.height, desc.mip_levels), (192, 192, 2));
+    assert_eq!(texture.subresource(0), level_data(192 * 192 * 4, 0));
+    assert_eq!(texture.subresource(1), level_data(96 * 96 * 4, 1));
+}
+
+#[test]
+fn zstd_supercompressed_bc1_cube() {
+    let texture = Texture::load(fixture_path("bc1_cube_zstd.ktx2")).unwrap();
+    let desc = *texture.desc();
+    assert_eq!(desc.format, Format::BC1_UNORM);
+    assert!(desc.cube);
+    assert_eq!((desc.width, desc.array_size, desc.mip_levels), (8, 6, 4));
+
+    // Levels hold all six faces, which the texture splits up.
+    let levels: Vec<_> = [32, 8, 8, 8]
+        .iter()
+        .enumerate()
+        .map(|(mip, size)| level_data(6 * size, mip as u32))
+        .collect();
+    for face in 0..6 {
+        for (mip, level) in levels.iter().enumerate() {
+            let size = level.len() / 6;
+            assert_eq!(
+                texture.subresource(desc.subresource_index(mip as u32, face)),
+                &level[face as usize * size..(face as usize + 1) * size]
+            );
+        }
+    }
+}
+
+#[test]
+fn zlib_supercompressed_half_float_array() {
+    let texture = Texture::load(fixture_path("r16f_array_zlib.ktx2")).unwrap();
+    let desc = *texture.desc();
+    assert_eq!(desc.format, Format::R16_FLOAT);
+    assert_eq!((desc.width, desc.height), (6, 5));
+    assert_eq!((desc.array_size, desc.mip_levels, desc.cube), (2, 3, false));
+    let level = level_data(2 * 3 * 2 * 2, 1);
+    assert_eq!(
+        texture.subresource(desc.subresource_index(1, 1)),
+        &level[12..]
+    );
+}
+
+#[test]
+fn container_header_and_level_index() {
+    let levels = [(0..32).collect::<Vec<u8>>(), vec![100, 101, 102, 103]];
+    let dfd = dfd(COLOR_MODEL_RGBSDA, 1, [1, 1], &[(0, 8, CHANNEL_RED, 255)]);
+    let bytes = ktx2_file(VK_FORMAT_R8_UNORM, (4, 4, 2), 0, 1, &levels, &dfd);
+
+    let ktx2 = Ktx2::parse(&bytes).unwrap();
+    assert_eq!(ktx2.vk_format, VK_FORMAT_R8_UNORM);
+    assert_eq!((ktx2.width, ktx2.height, ktx2.depth), (4, 4, 2));
+    assert_eq!(
+        (ktx2.layer_count, ktx2.face_count, ktx2.level_count),
+        (0, 1, 2)
+    );
+    assert_eq!(ktx2.supercompression, Supercompression::None);
+    assert_eq!(ktx2.levels[0].data, &levels[0][..]);
+    assert_eq!(ktx2.levels[1].data, &levels[1][..]);
+    assert!(ktx2.key_values.is_empty());
+    assert_eq!(ktx2.dfd.samples.len(), 1);
+    assert_eq!(ktx2.level_data(1).unwrap(), &levels[1][..]);
+    assert_eq!(
+        ktx2.level_data(2),
+        Err(AssetError::Invalid("the KTX2 has no mip 2, only 2".into()))
+    );
+
+    let texture = Texture::from_ktx2(&bytes).unwrap();
+    assert_eq!(texture.desc().dimension, TextureDimension::Texture3d);
+    assert_eq!(texture.desc().mip_size(1), (2, 2, 1));
+    assert_eq!(texture.data, [&levels[0][..], &levels[1][..]].concat());
+}
+
+#[test]
+fn dfds_map_to_dxgi_formats() {
+    let float = CHANNEL_RED | SAMPLE_FLOAT | SAMPLE_SIGNED;
+    let cases = [
+        (rgba8_dfd(1), Some(Format::R8G8B8A8_UNORM)),
+        (
+            rgba8_dfd(TRANSFER_FUNCTION_SRGB),
+            Some(Format::R8G8B8A8_UNORM_SRGB),
+        ),
+        (
+            dfd(
+                COLOR_MODEL_RGBSDA,
+                TRANSFER_FUNCTION_SRGB,
+                [1, 1],
+                &[
+                    (0, 8, CHANNEL_BLUE, 255),
+                    (8, 8, CHANNEL_GREEN, 255),
+                    (16, 8, CHANNEL_RED, 255),
+                    (24, 8, CHANNEL_ALPHA, 255),
+                ],
+            ),
+            Some(Format::B8G8R8A8_UNORM_SRGB),
+        ),
+        // Packed formats go by bit offset, whatever order the samples are in.
+        (
+            dfd(
+                COLOR_MODEL_RGBSDA,
+                1,
+                [1, 1],
+                &[
+                    (11, 5, CHANNEL_RED, 31),
+                    (5, 6, CHANNEL_GREEN, 63),
+                    (0, 5, CHANNEL_BLUE, 31),
+                ],
+            ),
+            Some(Format::B5G6R5_UNORM),
+        ),
+        (
+            dfd(
+                COLOR_MODEL_RGBSDA,
+                1,
+                [1, 1],
+                &[
+                    (0, 10, CHANNEL_RED, 1023),
+                    (10, 10, CHANNEL_GREEN, 1023),
+                    (20, 10, CHANNEL_BLUE, 1023),
+                    (30, 2, CHANNEL_ALPHA, 3),
+                ],
+            ),
+            Some(Format::R10G10B10A2_UNORM),
+        ),
+        (
+            dfd(
+                COLOR_MODEL_RGBSDA,
+                1,
+                [1, 1],
+                &[(0, 16, float, 0x3f80_0000)],
+            ),
+            Some(Format::R16_FLOAT),
+        ),
+        (
+            dfd(
+                COLOR_MODEL_RGBSDA,
+                1,
+                [1, 1],
+                &[
+                    (0, 32, float, 0x3f80_0000),
+                    (
+                        32,
+                        32,
+                        CHANNEL_GREEN | SAMPLE_FLOAT | SAMPLE_SIGNED,
+                        0x3f80_0000,
+                    ),
+                    (
+                        64,
+                        32,
+                        CHANNEL_BLUE | SAMPLE_FLOAT | SAMPLE_SIGNED,
+                        0x3f80_0000,
+                    ),
+                    (
+                        96,
+                        32,
+                        CHANNEL_ALPHA | SAMPLE_FLOAT | SAMPLE_SIGNED,
+                        0x3f80_0000,
+                    ),
+                ],
+            ),
+            Some(Format::R32G32B32A32_FLOAT),
+        ),
+        (
+            dfd(
+                COLOR_MODEL_RGBSDA,
+                1,
+                [1, 1],
+                &[
+                    (0, 11, CHANNEL_RED | SAMPLE_FLOAT, 0x7bc0),
+                    (11, 11, CHANNEL_GREEN | SAMPLE_FLOAT, 0x7bc0),
+                    (22, 10, CHANNEL_BLUE | SAMPLE_FLOAT, 0x7bc0),
+                ],
+            ),
+            Some(Format::R11G11B10_FLOAT),
+        ),
+        // An upper bound of one means integers rather than normalized.
+        (
+            dfd(COLOR_MODEL_RGBSDA, 1, [1, 1], &[(0, 32, CHANNEL_RED, 1)]),
+            Some(Format::R32_UINT),
+        ),
+        (
+            dfd(
+                COLOR_MODEL_BC1A,
+                TRANSFER_FUNCTION_SRGB,
+                [4, 4],
+                &[(0, 64, 0, !0)],
+            ),
+            Some(Format::BC1_UNORM_SRGB),
+        ),
+        (
+            dfd(
+                COLOR_MODEL_BC4,
+                1,
+                [4, 4],
+                &[(0, 64, SAMPLE_SIGNED, 0x7fff_ffff)],
+            ),
+            Some(Format::BC4_SNORM),
+        ),
+        (
+            dfd(COLOR_MODEL_BC6H, 1, [4, 4], &[(0, 128, SAMPLE_FLOAT, !0)]),
+            Some(Format::BC6H_UF16),
+        ),
+        (
+            dfd(
+                COLOR_MODEL_BC7,
+                TRANSFER_FUNCTION_SRGB,
+                [4, 4],
+                &[(0, 128, 0, !0)],
+            ),
+            Some(Format::BC7_UNORM_SRGB),
+        ),
+        // Mixed kinds of number, 8x8 blocks and 24-bit RGB have no match.
+        (
+            dfd(
+                COLOR_MODEL_RGBSDA,
+                1,
+                [1, 1],
+                &[(0, 16, float, 0x3f80_0000), (16, 16, CHANNEL_GREEN, 65535)],
+            ),
+            None,
+        ),
+        (dfd(COLOR_MODEL_BC7, 1, [8, 8], &[(0, 128, 0, !0)]), None),
+        (
+            dfd(
+                COLOR_MODEL_RGBSDA,
+                1,
+                [1, 1],
+                &[
+                    (0, 8, CHANNEL_RED, 255),
+                    (8, 8, CHANNEL_GREEN, 255),
+                    (16, 8, CHANNEL_BLUE, 255),
+                ],
+            ),
+            None,
+        ),
+    ];
+    for (i, (bytes, format)) in cases.iter().enumerate() {
+        let dfd = DataFormatDescriptor::parse(bytes).unwrap();
+        assert_eq!(dfd.format(), *format, "case {i}");
+    }
+
+    assert_eq!(vk_format_to_dxgi(43), Some(Format::R8G8B8A8_UNORM_SRGB));
+    assert_eq!(vk_format_to_dxgi(131), Some(Format::BC1_UNORM));
+    assert_eq!(vk_format_to_dxgi(0), None);
+}
+
+#[test]
+fn the_dfd_has_to_agree_with_the_vk_format() {
+    let levels = [vec![0; 4]];
+    let bytes = ktx2_file(
+        VK_FORMAT_R8G8B8A8_UNORM,
+        (1, 1, 0),
+        0,
+        1,
+        &levels,
+        &rgba8_dfd(TRANSFER_FUNCTION_SRGB),
+    );
+    assert!(matches!(
+        Texture::from_ktx2(&bytes),
+        Err(AssetError::Invalid(_))
+    ));
+
+    // Without a VkFormat, the DFD alone says what the texels are.
+    let bytes = ktx2_file(0, (1, 1, 0), 0, 1, &levels, &rgba8_dfd(1));
+    assert_eq!(
+        Texture::from_ktx2(&bytes).unwrap().desc().format,
+        Format::R8G8B8A8_UNORM
+    );
+}
+
+#[test]
+fn basis_payloads_name_their_transcode_target() {
+    let etc1s = dfd(
+        COLOR_MODEL_ETC1S,
+        TRANSFER_FUNCTION_SRGB,
+        [4, 4],
+        &[(0, 64, 0, !0)],
+    );
+    let etc1s_alpha = dfd(
+        COLOR_MODEL_ETC1S,
+        1,
+        [4, 4],
+        &[(0, 64, 0, !0), (64, 64, CHANNEL_ALPHA, !0)],
+    );
+    let uastc_rgba = dfd(COLOR_MODEL_UASTC, 1, [4, 4], &[(0, 128, 3, !0)]);
+    let cases = [
+        (&etc1s, BasisCodec::Etc1s, Format::BC1_UNORM_SRGB),
+        (&etc1s_alpha, BasisCodec::Etc1s, Format::BC7_UNORM),
+        (&uastc_rgba, BasisCodec::Uastc, Format::BC7_UNORM),
+    ];
+    for (bytes, codec, format) in cases {
+        let dfd = DataFormatDescriptor::parse(bytes).unwrap();
+        assert_eq!(dfd.basis_codec(), Some(codec));
+        assert_eq!(dfd.format(), None);
+        assert_eq!(dfd.transcode_format(), Some(format));
+    }
+
+    let bytes = ktx2_file(0, (4, 4, 0), 0, 1, &[vec![0; 16]], &uastc_rgba);
+    assert_eq!(
+        Texture::from_ktx2(&bytes).unwrap().desc().format,
+        Format::BC7_UNORM
+    );
+}
+
+#[test]
+fn damaged_ktx2_files_are_rejected() {
+    let levels = [vec![0; 16]];
+    let dfd = dfd(COLOR_MODEL_BC7, 1, [4, 4], &[(0, 128, 0, !0)]);
+    let good = ktx2_file(VK_FORMAT_BC7_UNORM_BLOCK, (4, 4, 0), 0, 1, &levels, &dfd);
+    assert!(Texture::from_ktx2(&good).is_ok());
+
+    let mut wrong = good.clone();
+    wrong[1] = b'D';
+    assert_eq!(
+        Texture::from_ktx2(&wrong),
+        Err(AssetError::WrongFormat("KTX2"))
+    );
+    assert!(matches!(
+        Texture::from_ktx2(&good[..good.len() - 1]),
+        Err(AssetError::Truncated { .. })
+    ));
+
+    // A level that's the wrong size for the texture.
+    let short = ktx2_file(VK_FORMAT_BC7_UNORM_BLOCK, (8, 4, 0), 0, 1, &levels, &dfd);
+    assert!(matches!(
+        Texture::from_ktx2(&short),
+        Err(AssetError::Invalid(_))
+    ));
+
+    let mut faces = good.clone();
+    faces[12 + 6 * 4] = 2;
+    assert!(matches!(
+        Texture::from_ktx2(&faces),
+        Err(AssetError::Invalid(_))
+    ));
+
+    let mut scheme = good;
+    scheme[12 + 8 * 4] = 9;
+    assert!(matches!(
+        Texture::from_ktx2(&scheme),
+        Err(AssetError::Unsupported(_))
+    ));
+
+    // Corrupt zstd data fails cleanly wherever the damage is, past the
+    // window size that a whole-frame decoder doesn't need.
+    let bytes = std::fs::read(fixture_path("bc1_cube_zstd.ktx2")).unwrap();
+    let ktx2 = Ktx2::parse(&bytes).unwrap();
+    let start = ktx2.levels[0].data.as_ptr() as usize - bytes.as_ptr() as usize;
+    for i in (start..start + ktx2.levels[0].data.len()).filter(|&i| i != start + 5) {
+        let mut damaged = bytes.clone();
+        damaged[i] ^= 0x5a;
+        assert!(Texture::from_ktx2(&damaged).is_err(), "byte {i}");
+    }
+}
+
+/// Texels of a BC1 block, which the transcoder always writes in
+/// four-color mode.
+fn decode_bc1(block: &[u8]) -> [[u8; 4]; 16] {
+    let expand = |color: u16| {
+        let [r, g, b] = [color >> 11, (color >> 5) & 0x3f, color & 0x1f].map(u32::from);
+        [r << 3 | r >> 2, g << 2 | g >> 4, b << 3 | b >> 2]
+    };
+    let (c0, c1) = (
+        u16::from_le_bytes([block[0], block[1]]),
+        u16::from_le_bytes([block[2], block[3]]),
+    );
+    assert!(c0 > c1 || c0 == c1 && block[4..] == [0; 4]);
+    let (e0, e1) = (expand(c0), expand(c1));
+    let mix = |a: u32, b: u32| [0, 1, 2].map(|c| ((a * e0[c] + b * e1[c]) / 3) as u8);
+    let palette = [
+        e0.map(|c| c as u8),
+        e1.map(|c| c as u8),
+        mix(2, 1),
+        mix(1, 2),
+    ];
+    let indices = u32::from_le_bytes(block[4..8].try_into().unwrap());
+    std::array::from_fn(|i| {
+        let [r, g, b] = palette[(indices >> (2 * i)) as usize & 3];
+        [r, g, b, 255]
+    })
+}
+
+/// BC7's partitions, as a bit per texel for two subsets and two bits per
+/// texel for three, and the anchors of their later subsets.
+const BC7_PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80, 0xc800, 0xffec, 0xfe80, 0xe800,
+    0xffe8, 0xff00, 0xfff0, 0xf000, 0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c, 0xaaaa, 0xf0f0, 0x5a5a, 0x33cc,
+    0x3c3c, 0x55aa, 0x9696, 0xa55a, 0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c, 0x9336, 0x9cc6, 0x817e, 0xe718,
+    0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+const BC7_ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+const BC7_PARTITIONS_3: [u32; 64] = [
+    0xaa685050, 0x6a5a5040, 0x5a5a4200, 0x5450a0a8, 0xa5a50000, 0xa0a05050, 0x5555a0a0, 0x5a5a5050,
+    0xaa550000, 0xaa555500, 0xaaaa5500, 0x90909090, 0x94949494, 0xa4a4a4a4, 0xa9a59450, 0x2a0a4250,
+    0xa5945040, 0x0a425054, 0xa5a5a500, 0x55a0a0a0, 0xa8a85454, 0x6a6a4040, 0xa4a45000, 0x1a1a0500,
+    0x0050a4a4, 0xaaa59090, 0x14696914, 0x69691400, 0xa08585a0, 0xaa821414, 0x50a4a450, 0x6a5a0200,
+    0xa9a58000, 0x5090a0a8, 0xa8a09050, 0x24242424, 0x00aa5500, 0x24924924, 0x24499224, 0x50a50a50,
+    0x500aa550, 0xaaaa4444, 0x66660000, 0xa5a0a5a0, 0x50a050a0, 0x69286928, 0x44aaaa44, 0x66666600,
+    0xaa444444, 0x54a854a8, 0x95809580, 0x96969600, 0xa85454a8, 0x80959580, 0xaa141414, 0x96960000,
+    0xaaaa1414, 0xa05050a0, 0xa0a5a5a0, 0x96000000, 0x40804080, 0xa9a8a9a8, 0xaaaaaa44, 0x2a4a5254,
+];
+const BC7_ANCHORS_3: [[u8; 2]; 64] = [
+    [3, 15],
+    [3, 8],
+    [15, 8],
+    [15, 3],
+    [8, 15],
+    [3, 15],
+    [15, 3],
+    [15, 8],
+    [8, 15],
+    [8, 15],
+    [6, 15],
+    [6, 15],
+    [6, 15],
+    [5, 15],
+    [3, 15],
+    [3, 8],
+    [3, 15],
+    [3, 8],
+    [8, 15],
+    [15, 3],
+    [3, 15],
+    [3, 8],
+    [6, 15],
+    [10, 8],
+    [5, 3],
+    [8, 15],
+    [8, 6],
+    [6, 10],
+    [8, 15],
+    [5, 15],
+    [15, 10],
+    [15, 8],
+    [8, 15],
+    [15, 3],
+    [3, 15],
+    [5, 10],
+    [6, 10],
+    [10, 8],
+    [8, 9],
+    [15, 10],
+    [15, 6],
+    [3, 15],
+    [15, 8],
+    [5, 15],
+    [15, 3],
+    [15, 6],
+    [15, 6],
+    [15, 8],
+    [3, 15],
+    [15, 3],
+    [5, 15],
+    [5, 15],
+    [5, 15],
+    [8, 15],
+    [5, 15],
+    [10, 15],
+    [5, 15],
+    [10, 15],
+    [8, 15],
+    [13, 15],
+    [15, 3],
+    [12, 15],
+    [3, 15],
+    [3, 8],
+];
+
+/// Texels of a BC7 block in one of the modes the transcoder writes: 1, 2,
+/// 5, 6 or 7.
+fn decode_bc7(block: &[u8]) -> [[u8; 4]; 16] {
+    let bits = u128::from_le_bytes(block.try_into().unwrap());
+    let mode = bits.trailing_zeros();
+    let mut position = mode + 1;
+    let mut read = |count: u32| {
+        let value = (bits >> position) as u32 & ((1 << count) - 1);
+        position += count;
+        value
+    };
+    let expand = |value: u32, bits: u32| value << (8 - bits) | value >> (2 * bits - 8);
+    let weights = |index_bits: u32| match index_bits {
+        2 => &[0, 21, 43, 64][..],
+        3 => &[0, 9, 18, 27, 37, 46, 55, 64],
+        _ => &[0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64],
+    };
+    let interpolate = |[low, high]: [u32; 2], weight: u32| {
+        (((64 - weight) * low + weight * high + 32) >> 6) as u8
+    };
+
+    if mode == 5 {
+        let rotation = read(2) as usize;
+        let color: [[u32; 2]; 3] =
+            std::array::from_fn(|_| [read(7), read(7)].map(|v| expand(v, 7)));
+        let alpha = [read(8), read(8)];
+        let mut indices =
+            || -> [u32; 16] { std::array::from_fn(|i| read(if i == 0 { 1 } else { 2 })) };
+        let (color_indices, alpha_indices) = (indices(), indices());
+        return std::array::from_fn(|i| {
+            let [r, g, b] =
+                color.map(|ends| interpolate(ends, weights(2)[color_indices[i] as usize]));
+            let a = interpolate(alpha, weights(2)[alpha_indices[i] as usize]);
+            let mut texel = [r, g, b, a];
+            // Rotation swaps alpha back with the channel that had its line.
+            if rotation > 0 {
+                texel.swap(rotation - 1, 3);
+            }
+            texel
+        });
+    }
+
+    // (subsets, color bits, alpha bits, p-bits per subset, index bits)
+    let (subsets, color_bits, alpha_bits, p_bits, index_bits) = match mode {
+        1 => (2, 6, 0, 1, 3),
+        2 => (3, 5, 0, 0, 2),
+        6 => (1, 7, 7, 2, 4),
+        7 => (2, 5, 5, 2, 2),
+        _ => panic!("the transcoder doesn't write BC7 mode {mode}"),
+    };
+    let partition = if subsets > 1 { read(6) as usize } else { 0 };
+    let subset = |texel: usize| match subsets {
+        1 => 0,
+        2 => (BC7_PARTITIONS_2[partition] >> texel & 1) as usize,
+        _ => (BC7_PARTITIONS_3[partition] >> (2 * texel) & 3) as usize,
+    };
+    let anchors = match subsets {
+        1 => [0; 3],
+        2 => [0, BC7_ANCHORS_2[partition] as usize, 16],
+        _ => [
+            0,
+            BC7_ANCHORS_3[partition][0] as usize,
+            BC7_ANCHORS_3[partition][1] as usize,
+        ],
+    };
+
+    let channels = if alpha_bits > 0 { 4 } else { 3 };
+    let mut fields = [[[0; 4]; 2]; 3];
+    for c in 0..channels {
+        for ends in &mut fields[..subsets] {
+            for end in ends {
+                end[c] = read(color_bits);
+            }
+        }
+    }
+    let mut ends = [[[255; 4]; 2]; 3];
+    for s in 0..subsets {
+        let p = match p_bits {
+            0 => None,
+            1 => Some([read(1); 2]),
+            _ => Some([read(1), read(1)]),
+        };
+        for e in 0..2 {
+            for c in 0..channels {
+                ends[s][e][c] = match p {
+                    Some(p) => expand(fields[s][e][c] << 1 | p[e], color_bits + 1),
+                    None => expand(fields[s][e][c], color_bits),
+                };
+            }
+        }
+    }
+    let indices: [u32; 16] =
+        std::array::from_fn(|texel| read(index_bits - anchors.contains(&texel) as u32));
+    std::array::from_fn(|texel| {
+        let [low, high] = ends[subset(texel)];
+        let weight = weights(index_bits)[indices[texel] as usize];
+        std::array::from_fn(|c| interpolate([low[c], high[c]], weight))
+    })
+}
+
+/// Checks every subresource of `texture` against the texels in the
+/// fixture `name`, which holds each level's images in order, padded out to
+/// whole blocks.
+fn assert_texels(texture: &Texture, name: &str, decode: fn(&[u8]) -> [[u8; 4]; 16], tolerance: u8) {
+    let desc = *texture.desc();
+    let expected = std::fs::read(fixture_path(name)).unwrap();
+    let mut expected = expected.chunks_exact(4);
+    for mip in 0..desc.mip_levels {
+        let blocks_x = (desc.width >> mip).max(1).div_ceil(4) as usize;
+        let blocks_y = (desc.height >> mip).max(1).div_ceil(4) as usize;
+        for slice in 0..desc.array_size {
+            let data = texture.subresource(desc.subresource_index(mip, slice));
+            let blocks: Vec<_> = data
+                .chunks_exact(data.len() / (blocks_x * blocks_y))
+                .map(decode)
+                .collect();
+            assert_eq!(blocks.len(), blocks_x * blocks_y);
+            for y in 0..blocks_y * 4 {
+                for x in 0..blocks_x * 4 {
+                    let texel = blocks[y / 4 * blocks_x + x / 4][y % 4 * 4 + x % 4];
+                    let want = expected.next().unwrap();
+                    assert!(
+                        (0..4).all(|c| texel[c].abs_diff(want[c]) <= tolerance),
+                        "mip {mip} slice {slice} ({x}, {y}): {texel:?}, not {want:?}"
+                    );
+                }
+            }
+        }
+    }
+    assert!(expected.next().is_none());
+}
+
+#[test]
+fn uastc_transcodes_to_bc7() {
+    // A block in each kind of mode: solid, one to three subsets, RGBA, dual
+    // plane and luminance-alpha.
+    let texture = Texture::load(fixture_path("uastc_rgba.ktx2")).unwrap();
+    let desc = *texture.desc();
+    assert_eq!(desc.format, Format::BC7_UNORM);
+    assert_eq!((desc.width, desc.height, desc.mip_levels), (16, 8, 2));
+    // BC7 only has five bits per channel for three subsets.
+    assert_texels(&texture, "uastc_rgba.rgba", decode_bc7, 12);
+}
+
+#[test]
+fn basislz_etc1s_transcodes_to_bc1() {
+    let texture = Texture::load(fixture_path("etc1s_array_basislz.ktx2")).unwrap();
+    let desc = *texture.desc();
+    assert_eq!(desc.format, Format::BC1_UNORM_SRGB);
+    assert_eq!((desc.width, desc.height), (12, 8));
+    assert_eq!((desc.array_size, desc.mip_levels), (2, 2));
+    // ETC1S spaces a block's four colors unevenly, which BC1 can't quite
+    // follow.
+    assert_texels(&texture, "etc1s_array_basislz.rgba", decode_bc1, 20);
+}
+
+#[test]
+fn basislz_etc1s_with_alpha_transcodes_to_bc7() {
+    let texture = Texture::load(fixture_path("etc1s_alpha_basislz.ktx2")).unwrap();
+    assert_eq!(texture.desc().format, Format::BC7_UNORM);
+    assert_texels(&texture, "etc1s_alpha_basislz.rgba", decode_bc7, 20);
+}
+
+#[test]
+fn damaged_basis_payloads_are_rejected() {
+    let etc1s = dfd(COLOR_MODEL_ETC1S, 1, [4, 4], &[(0, 64, 0, !0)]);
+    assert!(matches!(
+        Texture::from_ktx2(&ktx2_file(0, (4, 4, 0), 0, 1, &[vec![0; 8]], &etc1s)),
+        Err(AssetError::Invalid(_))
+    ));
+
+    let bytes = std::fs::read(fixture_path("etc1s_array_basislz.ktx2")).unwrap();
+    let ktx2 = Ktx2::parse(&bytes).unwrap();
+    let global_data = ktx2.supercompression_global_data;
+    let start = global_data.as_ptr() as usize - bytes.as_ptr() as usize;
+    let mut truncated = bytes.clone();
+    truncated[72..80].copy_from_slice(&(global_data.len() as u64 - 1).to_le_bytes());
+    assert!(matches!(
+        Texture::from_ktx2(&truncated),
+        Err(AssetError::Invalid(_))
+    ));
+
+    // Codebooks and slices are all bit streams, so damage anywhere in them
+    // has to come out as an error or wrong texels, never a panic.
+    for i in start..bytes.len() {
+        let mut damaged = bytes.clone();
+        damaged[i] ^= 0x5a;
+        let _ = Texture::from_ktx2(&damaged);
+    }
+    let bytes = std::fs::read(fixture_path("uastc_rgba.ktx2")).unwrap();
+    let ktx2 = Ktx2::parse(&bytes).unwrap();
+    let start = ktx2.levels[1].data.as_ptr() as usize - bytes.as_ptr() as usize;
+    for i in start..bytes.len() {
+        let mut damaged = bytes.clone();
+        damaged[i] ^= 0x5a;
+        let _ = Texture::from_ktx2(&damaged);
+    }
+}
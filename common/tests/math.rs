@@ -0,0 +1,424 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use common::{
+    cbuffer,
+    gfx::cbuffer::{ConstantBuffer, HlslType, HlslTypeDesc},
+    math::{f16_to_f32, f32_to_f16, linear_to_srgb, srgb_to_linear, Mat4, Quat, Vec2, Vec3, Vec4},
+};
+
+const EPSILON: f32 = 1e-5;
+
+#[track_caller]
+fn assert_vec3(actual: Vec3, expected: Vec3) {
+    assert!(
+        actual.abs_diff_eq(expected, EPSILON),
+        "{actual:?} != {expected:?}"
+    );
+}
+
+#[track_caller]
+fn assert_mat4(actual: Mat4, expected: Mat4) {
+    assert!(
+        actual.abs_diff_eq(&expected, EPSILON),
+        "{actual:?} != {expected:?}"
+    );
+}
+
+fn transform() -> Mat4 {
+    Mat4::from_scale_rotation_translation(
+        Vec3::new(2.0, 0.5, 3.0),
+        Quat::from_axis_angle(Vec3::new(1.0, 2.0, -0.5).normalize(), 0.7),
+        Vec3::new(-4.0, 1.5, 9.0),
+    )
+}
+
+#[test]
+fn vector_arithmetic() {
+    let a = Vec3::new(1.0, 2.0, 3.0);
+    let b = Vec3::new(-2.0, 0.5, 4.0);
+
+    assert_eq!(a + b, Vec3::new(-1.0, 2.5, 7.0));
+    assert_eq!(a - b, Vec3::new(3.0, 1.5, -1.0));
+    assert_eq!(a * b, Vec3::new(-2.0, 1.0, 12.0));
+    assert_eq!(2.0 * a, a * 2.0);
+    assert_eq!(-a / 2.0, Vec3::new(-0.5, -1.0, -1.5));
+    assert_eq!(a.dot(b), 11.0);
+    assert_eq!(a.min(b), Vec3::new(-2.0, 0.5, 3.0));
+    assert_eq!(a.max(b), Vec3::new(1.0, 2.0, 4.0));
+    assert_eq!(a.lerp(b, 0.5), Vec3::new(-0.5, 1.25, 3.5));
+    assert_eq!(Vec2::new(3.0, 4.0).length(), 5.0);
+    assert_eq!(Vec4::new(1.0, 2.0, 2.0, 4.0).length(), 5.0);
+    assert_eq!(
+        Vec3::new(0.0, 3.0, 4.0).normalize(),
+        Vec3::new(0.0, 0.6, 0.8)
+    );
+    assert_eq!(Vec3::ZERO.normalize_or_zero(), Vec3::ZERO);
+
+    let mut c = a;
+    c += b;
+    c -= a;
+    c *= 2.0;
+    c /= 4.0;
+    assert_eq!(c, b * 0.5);
+}
+
+#[test]
+fn vector_conversions() {
+    assert_eq!(Vec3::from([1.0, 2.0, 3.0]), Vec3::new(1.0, 2.0, 3.0));
+    assert_eq!(<[f32; 4]>::from(Vec4::W), [0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(
+        Vec2::X.extend(2.0).extend(3.0),
+        Vec4::new(1.0, 0.0, 2.0, 3.0)
+    );
+    assert_eq!(Vec4::ONE.truncate().truncate(), Vec2::ONE);
+    assert_eq!(Vec3::splat(2.0).to_array(), [2.0; 3]);
+}
+
+#[test]
+fn cross_product() {
+    assert_eq!(Vec3::X.cross(Vec3::Y), Vec3::Z);
+    assert_eq!(Vec3::Y.cross(Vec3::Z), Vec3::X);
+    assert_eq!(Vec3::Z.cross(Vec3::X), Vec3::Y);
+    assert_eq!(Vec3::Y.cross(Vec3::X), -Vec3::Z);
+
+    let a = Vec3::new(1.0, 2.0, 3.0);
+    let b = Vec3::new(4.0, 5.0, 6.0);
+    assert_eq!(a.cross(b), Vec3::new(-3.0, 6.0, -3.0));
+    assert_eq!(a.cross(b).dot(a), 0.0);
+}
+
+#[test]
+fn rotations_are_clockwise_looking_at_the_origin() {
+    // As XMMatrixRotationX/Y/Z.
+    assert_vec3(
+        Mat4::from_rotation_x(FRAC_PI_2).transform_vector3(Vec3::Y),
+        Vec3::Z,
+    );
+    assert_vec3(
+        Mat4::from_rotation_y(FRAC_PI_2).transform_vector3(Vec3::X),
+        -Vec3::Z,
+    );
+    assert_vec3(
+        Mat4::from_rotation_y(FRAC_PI_2).transform_vector3(Vec3::Z),
+        Vec3::X,
+    );
+    assert_vec3(
+        Mat4::from_rotation_z(FRAC_PI_2).transform_vector3(Vec3::X),
+        Vec3::Y,
+    );
+}
+
+#[test]
+fn quaternions_match_matrices() {
+    for angle in [-2.5, -FRAC_PI_2, 0.0, 0.3, PI] {
+        assert_mat4(
+            Mat4::from_quat(Quat::from_rotation_x(angle)),
+            Mat4::from_rotation_x(angle),
+        );
+        assert_mat4(
+            Mat4::from_quat(Quat::from_rotation_y(angle)),
+            Mat4::from_rotation_y(angle),
+        );
+        assert_mat4(
+            Mat4::from_quat(Quat::from_rotation_z(angle)),
+            Mat4::from_rotation_z(angle),
+        );
+    }
+
+    let q = Quat::from_axis_angle(Vec3::new(-1.0, 3.0, 2.0).normalize(), 1.2);
+    let v = Vec3::new(0.5, -7.0, 2.0);
+    assert_vec3(q * v, Mat4::from_quat(q).transform_vector3(v));
+    assert_vec3(q.inverse() * (q * v), v);
+}
+
+#[test]
+fn quaternion_products_compose_right_to_left() {
+    let a = Quat::from_rotation_y(0.4);
+    let b = Quat::from_rotation_x(-1.1);
+    let v = Vec3::new(1.0, 2.0, 3.0);
+
+    assert_vec3((a * b) * v, a * (b * v));
+    assert_mat4(
+        Mat4::from_quat(a * b),
+        Mat4::from_quat(a) * Mat4::from_quat(b),
+    );
+    assert!((a * b).abs_diff_eq(Quat::from_yaw_pitch_roll(0.4, -1.1, 0.0), EPSILON));
+
+    let mut c = a;
+    c *= b;
+    assert_eq!(c, a * b);
+}
+
+#[test]
+fn yaw_pitch_roll() {
+    let (yaw, pitch, roll) = (0.3, -0.6, 1.9);
+
+    assert_mat4(
+        Mat4::from_quat(Quat::from_yaw_pitch_roll(yaw, pitch, roll)),
+        Mat4::from_rotation_y(yaw) * Mat4::from_rotation_x(pitch) * Mat4::from_rotation_z(roll),
+    );
+    // Positive pitch looks down.
+    assert!((Quat::from_yaw_pitch_roll(0.0, 0.5, 0.0) * Vec3::Z).y < 0.0);
+}
+
+#[test]
+fn slerp() {
+    let a = Quat::from_rotation_y(0.0);
+    let b = Quat::from_rotation_y(FRAC_PI_2);
+
+    assert!(a.slerp(b, 0.0).abs_diff_eq(a, EPSILON));
+    assert!(a.slerp(b, 1.0).abs_diff_eq(b, EPSILON));
+    assert!(a
+        .slerp(b, 0.5)
+        .abs_diff_eq(Quat::from_rotation_y(FRAC_PI_4), EPSILON));
+    assert!(a
+        .slerp(b, 0.25)
+        .abs_diff_eq(Quat::from_rotation_y(FRAC_PI_2 / 4.0), EPSILON));
+
+    // -b is the same rotation, and slerp takes the short way to it.
+    let negated = Quat::new(-b.x, -b.y, -b.z, -b.w);
+    assert!(a
+        .slerp(negated, 0.5)
+        .abs_diff_eq(Quat::from_rotation_y(FRAC_PI_4), EPSILON));
+    assert!(a.slerp(a, 0.5).abs_diff_eq(a, EPSILON));
+    assert!((a.slerp(b, 0.3).length() - 1.0).abs() < EPSILON);
+}
+
+#[test]
+fn matrix_products_apply_right_to_left() {
+    let scale = Mat4::from_scale(Vec3::splat(2.0));
+    let translate = Mat4::from_translation(Vec3::new(1.0, 0.0, 0.0));
+
+    assert_vec3(
+        (translate * scale).transform_point3(Vec3::X),
+        Vec3::new(3.0, 0.0, 0.0),
+    );
+    assert_vec3(
+        (scale * translate).transform_point3(Vec3::X),
+        Vec3::new(4.0, 0.0, 0.0),
+    );
+    assert_vec3(translate.transform_vector3(Vec3::Y), Vec3::Y);
+    assert_eq!(Mat4::IDENTITY * transform(), transform());
+    assert_eq!(Mat4::default(), Mat4::IDENTITY);
+
+    let mut m = translate;
+    m *= scale;
+    assert_eq!(m, translate * scale);
+}
+
+#[test]
+fn scale_rotation_translation() {
+    let rotation = Quat::from_rotation_z(0.8);
+    let m = Mat4::from_scale_rotation_translation(
+        Vec3::new(1.0, 2.0, 3.0),
+        rotation,
+        Vec3::new(4.0, 5.0, 6.0),
+    );
+
+    assert_mat4(
+        m,
+        Mat4::from_translation(Vec3::new(4.0, 5.0, 6.0))
+            * Mat4::from_quat(rotation)
+            * Mat4::from_scale(Vec3::new(1.0, 2.0, 3.0)),
+    );
+}
+
+#[test]
+fn determinant_and_inverse() {
+    assert_eq!(
+        Mat4::from_scale(Vec3::new(2.0, 3.0, 4.0)).determinant(),
+        24.0
+    );
+    assert_eq!(Mat4::from_rotation_x(0.5).transpose().determinant(), 1.0);
+    assert!((transform().determinant() - 3.0).abs() < 1e-4);
+
+    let m = transform();
+    let inverse = m.inverse().unwrap();
+    assert_mat4(m * inverse, Mat4::IDENTITY);
+    assert_mat4(inverse * m, Mat4::IDENTITY);
+
+    let projection = Mat4::perspective_lh(1.0, 1.5, 0.1, 100.0);
+    assert_mat4(projection * projection.inverse().unwrap(), Mat4::IDENTITY);
+
+    assert_eq!(Mat4::ZERO.inverse(), None);
+    assert_eq!(Mat4::from_scale(Vec3::new(1.0, 0.0, 1.0)).inverse(), None);
+}
+
+#[test]
+fn look_at() {
+    // A camera on -z looking at the origin only needs to move the world.
+    assert_mat4(
+        Mat4::look_at_lh(Vec3::new(0.0, 0.0, -5.0), Vec3::ZERO, Vec3::Y),
+        Mat4::from_translation(Vec3::new(0.0, 0.0, 5.0)),
+    );
+
+    // On +x looking back at the origin, +z is on the right.
+    let view = Mat4::look_at_lh(Vec3::new(5.0, 0.0, 0.0), Vec3::ZERO, Vec3::Y);
+    assert_vec3(view.transform_point3(Vec3::ZERO), Vec3::new(0.0, 0.0, 5.0));
+    assert_vec3(view.transform_point3(Vec3::Z), Vec3::new(1.0, 0.0, 5.0));
+    assert_vec3(view.transform_point3(Vec3::Y), Vec3::new(0.0, 1.0, 5.0));
+
+    let eye = Vec3::new(3.0, 4.0, -2.0);
+    let target = Vec3::new(-1.0, 0.5, 6.0);
+    let view = Mat4::look_at_lh(eye, target, Vec3::Y);
+    assert_vec3(view.transform_point3(eye), Vec3::ZERO);
+    assert_vec3(
+        view.transform_point3(target),
+        Vec3::new(0.0, 0.0, eye.distance(target)),
+    );
+    assert_mat4(view, Mat4::look_to_lh(eye, (target - eye) * 3.0, Vec3::Y));
+}
+
+#[test]
+fn perspective_matches_directxmath() {
+    // XMMatrixPerspectiveFovLH(XM_PIDIV2, 2.0f, 1.0f, 11.0f), whose rows are
+    // our columns.
+    assert_eq!(
+        Mat4::perspective_lh(FRAC_PI_2, 2.0, 1.0, 11.0).to_column_major(),
+        [
+            [0.5, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.1, 1.0],
+            [0.0, 0.0, -1.1, 0.0],
+        ]
+    );
+}
+
+#[test]
+fn perspective_depth() {
+    let (near, far) = (0.5, 200.0);
+    let depth = |m: Mat4, z: f32| m.project_point3(Vec3::new(0.0, 0.0, z)).z;
+
+    let forward = Mat4::perspective_lh(1.0, 1.0, near, far);
+    assert!(depth(forward, near).abs() < EPSILON);
+    assert!((depth(forward, far) - 1.0).abs() < EPSILON);
+
+    let reversed = Mat4::perspective_reversed_z_lh(1.0, 1.0, near, far);
+    assert!((depth(reversed, near) - 1.0).abs() < EPSILON);
+    assert!(depth(reversed, far).abs() < EPSILON);
+    for z in [1.0, 10.0, 100.0] {
+        assert!((depth(reversed, z) - (1.0 - depth(forward, z))).abs() < EPSILON);
+    }
+
+    let infinite = Mat4::perspective_infinite_reversed_z_lh(1.0, 1.0, near);
+    assert_eq!(depth(infinite, near), 1.0);
+    assert_eq!(depth(infinite, 50.0), near / 50.0);
+    assert!(depth(infinite, 1e30) > 0.0);
+    assert!(depth(infinite, 1e30) < 1e-20);
+}
+
+#[test]
+fn perspective_frustum_edges() {
+    let fov_y = 1.2f32;
+    let aspect = 16.0 / 9.0;
+    let m = Mat4::perspective_infinite_reversed_z_lh(fov_y, aspect, 0.1);
+    let z = 10.0;
+    let half_height = z * (fov_y / 2.0).tan();
+
+    assert_vec3(
+        m.project_point3(Vec3::new(half_height * aspect, half_height, z)),
+        Vec3::new(1.0, 1.0, 0.01),
+    );
+    assert_vec3(
+        m.project_point3(Vec3::new(-half_height * aspect, -half_height, z)),
+        Vec3::new(-1.0, -1.0, 0.01),
+    );
+}
+
+#[test]
+fn orthographic() {
+    let m = Mat4::orthographic_lh(-2.0, 6.0, -1.0, 3.0, 1.0, 5.0);
+
+    assert_vec3(
+        m.project_point3(Vec3::new(-2.0, -1.0, 1.0)),
+        Vec3::new(-1.0, -1.0, 0.0),
+    );
+    assert_vec3(
+        m.project_point3(Vec3::new(6.0, 3.0, 5.0)),
+        Vec3::new(1.0, 1.0, 1.0),
+    );
+    assert_vec3(
+        m.project_point3(Vec3::new(2.0, 1.0, 3.0)),
+        Vec3::new(0.0, 0.0, 0.5),
+    );
+    assert_eq!(m.row(3), Vec4::W);
+}
+
+#[test]
+fn upload_layouts() {
+    let m = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0));
+
+    assert_eq!(m.to_column_major()[3], [1.0, 2.0, 3.0, 1.0]);
+    assert_eq!(m.to_row_major()[0], [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(m.to_row_major()[2], [0.0, 0.0, 1.0, 3.0]);
+    assert_eq!(Mat4::from_row_major(m.to_row_major()), m);
+    assert_eq!(Mat4::from_column_major(m.to_column_major()), m);
+    assert_eq!(m.transpose().to_column_major(), m.to_row_major());
+    assert_eq!(m.col(3), Vec4::new(1.0, 2.0, 3.0, 1.0));
+    assert_eq!(m.row(0), Vec4::new(1.0, 0.0, 0.0, 1.0));
+}
+
+cbuffer! {
+    #[derive(Clone, Copy)]
+    struct Camera {
+        view_projection: Mat4,
+        position: Vec3,
+        near: f32,
+        jitter: Vec2,
+    }
+}
+
+#[test]
+fn math_types_in_constant_buffers() {
+    assert_eq!(Mat4::TYPE, HlslTypeDesc::aligned("float4x4", 64));
+    assert_eq!(Vec3::TYPE, HlslTypeDesc::packed("float3", 12));
+    assert_eq!(Quat::TYPE, HlslTypeDesc::packed("float4", 16));
+
+    assert_eq!(
+        Camera::MEMBERS.iter().map(|m| m.offset).collect::<Vec<_>>(),
+        [0, 64, 76, 80]
+    );
+
+    let camera = Camera {
+        view_projection: Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)),
+        position: Vec3::ONE,
+        near: 0.1,
+        jitter: Vec2::ZERO,
+    };
+    let bytes = camera.as_bytes();
+    assert_eq!(&bytes[48..52], &1.0f32.to_le_bytes());
+    assert_eq!(&bytes[56..60], &3.0f32.to_le_bytes());
+}
+
+#[test]
+fn half_floats_round_to_nearest_even() {
+    for (value, bits) in [
+        (0.0, 0x0000),
+        (-0.0, 0x8000),
+        (1.0, 0x3c00),
+        (-2.5, 0xc100),
+        (65504.0, 0x7bff),
+        (f32::INFINITY, 0x7c00),
+        // The smallest subnormal.
+        (2f32.powi(-24), 0x0001),
+    ] {
+        assert_eq!(f32_to_f16(value), bits, "{value}");
+        assert_eq!(f16_to_f32(bits), value);
+    }
+    // Halfway cases go to whichever neighbor is even.
+    assert_eq!(f32_to_f16(2f32.powi(-25)), 0x0000);
+    assert_eq!(f32_to_f16(1.5 * 2f32.powi(-24)), 0x0002);
+    assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)), 0x3c00);
+    assert_eq!(f32_to_f16(1.0 + 3.0 * 2f32.powi(-11)), 0x3c02);
+    assert_eq!(f32_to_f16(65520.0), 0x7c00);
+    assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+}
+
+#[test]
+fn srgb_round_trips() {
+    for i in 0..=255 {
+        let value = i as f32 / 255.0;
+        assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < EPSILON);
+    }
+    assert!((srgb_to_linear(0.5) - 0.214_041).abs() < EPSILON);
+}
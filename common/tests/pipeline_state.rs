@@ -0,0 +1,257 @@
+use std::hash::Hasher;
+
+use common::{
+    gfx::{
+        pipeline_state::{
+            pipeline_key, Blend, BlendOp, BlendState, CullMode, DepthStencilState, FillMode,
+            GraphicsPipelineDesc, InputElement, PipelineDesc, PipelineError, PipelineStateBuilder,
+            PrimitiveTopology, RasterizerState, RenderTargetBlend, StencilFace, StencilOp,
+            MAX_RENDER_TARGETS,
+        },
+        root_signature::{
+            ComparisonFunc, RootSignatureBuilder, RootSignatureDesc, ShaderVisibility,
+        },
+        Format,
+    },
+    hash::{stable_hash, StableHasher},
+};
+
+const VS: &[u8] = b"DXBC vertex";
+const PS: &[u8] = b"DXBC pixel";
+const CS: &[u8] = b"DXBC compute";
+
+fn root_signature() -> RootSignatureDesc {
+    RootSignatureBuilder::new()
+        .cbv(0, ShaderVisibility::All)
+        .build()
+        .unwrap()
+}
+
+fn graphics() -> PipelineStateBuilder {
+    PipelineStateBuilder::new()
+        .input_layout([
+            InputElement::new("POSITION", Format::R32G32B32_FLOAT),
+            InputElement::new("TEXCOORD", Format::R32G32_FLOAT),
+        ])
+        .vertex_shader(VS)
+        .pixel_shader(PS)
+}
+
+fn key(desc: &PipelineDesc) -> u64 {
+    pipeline_key(&root_signature(), desc)
+}
+
+#[test]
+fn stable_hasher_is_fnv1a() {
+    let fnv = |bytes: &[u8]| {
+        let mut hasher = StableHasher::new();
+        hasher.write(bytes);
+        hasher.finish()
+    };
+
+    assert_eq!(fnv(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(fnv(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(fnv(b"foobar"), 0x8594_4171_f739_67e8);
+}
+
+#[test]
+fn integers_hash_the_same_on_every_platform() {
+    let mut hasher = StableHasher::new();
+    hasher.write_usize(0x0102_0304);
+    let mut expected = StableHasher::new();
+    expected.write(&[4, 3, 2, 1, 0, 0, 0, 0]);
+
+    assert_eq!(hasher.finish(), expected.finish());
+}
+
+#[test]
+fn blend_defaults_match_d3dx12() {
+    let blend = BlendState::default();
+    assert!(!blend.alpha_to_coverage);
+    assert!(!blend.independent_blend);
+
+    for target in blend.render_targets {
+        assert_eq!(
+            target,
+            RenderTargetBlend {
+                enabled: false,
+                src: Blend::One,
+                dest: Blend::Zero,
+                op: BlendOp::Add,
+                src_alpha: Blend::One,
+                dest_alpha: Blend::Zero,
+                op_alpha: BlendOp::Add,
+                write_mask: 0xf,
+            }
+        );
+    }
+}
+
+#[test]
+fn rasterizer_defaults_match_d3dx12() {
+    let rasterizer = RasterizerState::default();
+
+    assert_eq!(rasterizer.fill, FillMode::Solid);
+    assert_eq!(rasterizer.cull, CullMode::Back);
+    assert!(!rasterizer.front_counter_clockwise);
+    assert_eq!(rasterizer.depth_bias, 0);
+    assert_eq!(rasterizer.depth_bias_clamp, 0.0);
+    assert_eq!(rasterizer.slope_scaled_depth_bias, 0.0);
+    assert!(rasterizer.depth_clip);
+    assert!(!rasterizer.multisample);
+    assert!(!rasterizer.antialiased_lines);
+    assert!(!rasterizer.conservative);
+}
+
+#[test]
+fn depth_stencil_defaults_match_d3dx12() {
+    let depth_stencil = DepthStencilState::default();
+
+    assert!(depth_stencil.depth_test);
+    assert!(depth_stencil.depth_write);
+    assert_eq!(depth_stencil.depth_func, ComparisonFunc::Less);
+    assert!(!depth_stencil.stencil_test);
+    assert_eq!(depth_stencil.stencil_read_mask, 0xff);
+    assert_eq!(depth_stencil.stencil_write_mask, 0xff);
+    for face in [depth_stencil.front, depth_stencil.back] {
+        assert_eq!(
+            face,
+            StencilFace {
+                fail: StencilOp::Keep,
+                depth_fail: StencilOp::Keep,
+                pass: StencilOp::Keep,
+                func: ComparisonFunc::Always,
+            }
+        );
+    }
+
+    let reversed_z = DepthStencilState::REVERSED_Z;
+    assert_eq!(reversed_z.depth_func, ComparisonFunc::GreaterEqual);
+    assert_eq!(
+        DepthStencilState::DISABLED,
+        DepthStencilState {
+            depth_test: false,
+            depth_write: false,
+            ..depth_stencil
+        }
+    );
+}
+
+#[test]
+fn graphics_defaults() {
+    let PipelineDesc::Graphics(desc) = graphics().build().unwrap() else {
+        panic!("expected a graphics pipeline");
+    };
+
+    assert_eq!(desc.topology, PrimitiveTopology::Triangle);
+    assert_eq!(desc.sample_mask, u32::MAX);
+    assert_eq!(desc.render_target_formats, [Format::R8G8B8A8_UNORM]);
+    assert_eq!(desc.depth_format, Format::UNKNOWN);
+    assert_eq!(desc.depth_stencil, DepthStencilState::DISABLED);
+    assert_eq!((desc.sample_count, desc.sample_quality), (1, 0));
+    assert_eq!(desc.input_layout[1].offset, None);
+    assert_eq!(desc.input_layout[1].instance_step_rate, None);
+}
+
+#[test]
+fn compute_shader_makes_a_compute_pipeline() {
+    let desc = PipelineStateBuilder::new()
+        .compute_shader(CS)
+        .build()
+        .unwrap();
+
+    assert!(matches!(desc, PipelineDesc::Compute(ref c) if c.cs == CS));
+}
+
+#[test]
+fn builder_validation() {
+    assert_eq!(
+        PipelineStateBuilder::new().pixel_shader(PS).build(),
+        Err(PipelineError::MissingVertexShader)
+    );
+    assert_eq!(
+        graphics().compute_shader(CS).build(),
+        Err(PipelineError::ComputeWithGraphicsShaders)
+    );
+    assert_eq!(
+        graphics()
+            .render_target_formats(&[Format::R8G8B8A8_UNORM; MAX_RENDER_TARGETS + 1])
+            .build(),
+        Err(PipelineError::TooManyRenderTargets { count: 9 })
+    );
+    assert_eq!(
+        graphics()
+            .depth_stencil(Format::UNKNOWN, DepthStencilState::DEFAULT)
+            .build(),
+        Err(PipelineError::MissingDepthFormat)
+    );
+    assert!(graphics()
+        .depth_stencil(Format::D32_FLOAT, DepthStencilState::REVERSED_Z)
+        .render_target_formats(&[])
+        .build()
+        .is_ok());
+}
+
+#[test]
+fn equal_descriptions_have_equal_keys() {
+    let a = graphics().build().unwrap();
+    let b = graphics().build().unwrap();
+
+    assert_eq!(key(&a), key(&b));
+    assert_eq!(key(&a), key(&a.clone()));
+}
+
+#[test]
+fn every_field_changes_the_key() {
+    let base = graphics().build().unwrap();
+    let variants = [
+        graphics().vertex_shader(b"DXBC other".as_slice()),
+        graphics().pixel_shader(b"DXBC other".as_slice()),
+        graphics().geometry_shader(b"DXBC gs".as_slice()),
+        graphics().input_layout([InputElement::new("POSITION", Format::R32G32B32_FLOAT)]),
+        graphics().input_layout([
+            InputElement::new("POSITION", Format::R32G32B32_FLOAT),
+            InputElement::new("TEXCOORD", Format::R32G32_FLOAT).per_instance(1),
+        ]),
+        graphics().topology(PrimitiveTopology::Line),
+        graphics().blend(BlendState::all(RenderTargetBlend::ALPHA)),
+        graphics().sample_mask(1),
+        graphics().rasterizer(RasterizerState::NO_CULL),
+        graphics().rasterizer(RasterizerState {
+            slope_scaled_depth_bias: 1.5,
+            ..RasterizerState::DEFAULT
+        }),
+        graphics().depth_stencil(Format::D32_FLOAT, DepthStencilState::DEFAULT),
+        graphics().render_target_formats(&[Format::R16G16B16A16_FLOAT]),
+        graphics().sample_desc(4, 0),
+    ];
+
+    let mut keys = vec![key(&base)];
+    for variant in variants {
+        keys.push(key(&variant.build().unwrap()));
+    }
+    let unique: std::collections::HashSet<u64> = keys.iter().copied().collect();
+    assert_eq!(unique.len(), keys.len());
+}
+
+#[test]
+fn root_signature_is_part_of_the_key() {
+    let desc = graphics().build().unwrap();
+    let other = RootSignatureBuilder::new()
+        .cbv(1, ShaderVisibility::All)
+        .build()
+        .unwrap();
+
+    assert_ne!(
+        pipeline_key(&root_signature(), &desc),
+        pipeline_key(&other, &desc)
+    );
+}
+
+#[test]
+fn graphics_and_compute_keys_differ() {
+    let compute = PipelineDesc::Compute(Default::default());
+    let graphics = PipelineDesc::Graphics(GraphicsPipelineDesc::default());
+
+    assert_ne!(stable_hash(&compute), stable_hash(&graphics));
+}
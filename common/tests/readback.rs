@@ -0,0 +1,124 @@
+use common::gfx::{
+    readback::{
+        bytes_per_pixel, to_rgba8, unpad_rows, ReadbackError, ReadbackLayout,
+        TEXTURE_DATA_PITCH_ALIGNMENT,
+    },
+    Format,
+};
+
+#[test]
+fn rows_are_aligned_to_256_bytes() {
+    let layout = ReadbackLayout::new(100, 3, Format::R8G8B8A8_UNORM).unwrap();
+    assert_eq!(layout.row_bytes, 400);
+    assert_eq!(layout.row_pitch, 512);
+    // The last row isn't padded.
+    assert_eq!(layout.size(), 512 * 2 + 400);
+
+    let layout = ReadbackLayout::new(64, 2, Format::R8G8B8A8_UNORM).unwrap();
+    assert_eq!(layout.row_pitch, TEXTURE_DATA_PITCH_ALIGNMENT);
+    assert_eq!(layout.size(), 512);
+
+    let layout = ReadbackLayout::new(1, 1, Format::R32G32B32A32_FLOAT).unwrap();
+    assert_eq!(
+        (layout.row_bytes, layout.row_pitch, layout.size()),
+        (16, 256, 16)
+    );
+
+    let layout = ReadbackLayout::new(1920, 0, Format::R16G16B16A16_FLOAT).unwrap();
+    assert_eq!((layout.row_pitch, layout.size()), (15360, 0));
+}
+
+#[test]
+fn unsupported_formats_are_rejected() {
+    assert_eq!(bytes_per_pixel(Format::D32_FLOAT), None);
+    assert_eq!(
+        ReadbackLayout::new(4, 4, Format::D32_FLOAT),
+        Err(ReadbackError::UnsupportedFormat(Format::D32_FLOAT))
+    );
+    assert_eq!(
+        to_rgba8(Format::R8_UNORM, &[0; 4]),
+        Err(ReadbackError::UnsupportedFormat(Format::R8_UNORM))
+    );
+}
+
+#[test]
+fn padding_is_removed() {
+    let layout = ReadbackLayout::new(3, 3, Format::R8G8B8A8_UNORM).unwrap();
+    let mut data = vec![0xee; layout.size() as usize];
+    for row in 0..3 {
+        let start = row * 256;
+        for byte in 0..12 {
+            data[start + byte] = (row * 12 + byte) as u8;
+        }
+    }
+
+    let pixels = unpad_rows(&data, &layout).unwrap();
+    assert_eq!(pixels, (0..36).collect::<Vec<u8>>());
+
+    // Anything after the last row is ignored.
+    data.extend_from_slice(&[0xee; 100]);
+    assert_eq!(unpad_rows(&data, &layout).unwrap(), pixels);
+    assert_eq!(
+        unpad_rows(&data[..523], &layout),
+        Err(ReadbackError::TooShort {
+            expected: 524,
+            actual: 523
+        })
+    );
+}
+
+#[test]
+fn eight_bit_formats_are_swizzled_not_converted() {
+    let texel = [10, 20, 30, 40];
+    assert_eq!(to_rgba8(Format::R8G8B8A8_UNORM, &texel).unwrap(), texel);
+    assert_eq!(
+        to_rgba8(Format::R8G8B8A8_UNORM_SRGB, &texel).unwrap(),
+        texel
+    );
+    assert_eq!(
+        to_rgba8(Format::B8G8R8A8_UNORM, &texel).unwrap(),
+        [30, 20, 10, 40]
+    );
+    assert_eq!(
+        to_rgba8(Format::B8G8R8A8_UNORM_SRGB, &[texel, texel].concat()).unwrap(),
+        [30, 20, 10, 40, 30, 20, 10, 40]
+    );
+}
+
+#[test]
+fn ten_bit_channels_are_rescaled() {
+    let pack = |r: u32, g: u32, b: u32, a: u32| (r | g << 10 | b << 20 | a << 30).to_le_bytes();
+    assert_eq!(
+        to_rgba8(Format::R10G10B10A2_UNORM, &pack(1023, 0, 512, 3)).unwrap(),
+        [255, 0, 128, 255]
+    );
+    assert_eq!(
+        to_rgba8(Format::R10G10B10A2_UNORM, &pack(0, 1023, 0, 1)).unwrap(),
+        [0, 255, 0, 85]
+    );
+}
+
+#[test]
+fn float_formats_are_srgb_encoded() {
+    let floats: Vec<u8> = [0.0f32, 1.0, 0.215_8, 0.5, 2.0, -1.0, 0.001, 1.0]
+        .iter()
+        .flat_map(|f| f.to_le_bytes())
+        .collect();
+    assert_eq!(
+        to_rgba8(Format::R32G32B32A32_FLOAT, &floats).unwrap(),
+        // Linear 0.2158 is just over sRGB 0.5; alpha stays linear.
+        [0, 255, 128, 128, 255, 0, 3, 255]
+    );
+
+    // 0.0, 1.0, 0.5, 0.25 and a denormal as halves.
+    let halves: Vec<u8> = [
+        0x0000u16, 0x3c00, 0x3800, 0x3400, 0x0001, 0xfc00, 0x7c00, 0x3c00,
+    ]
+    .iter()
+    .flat_map(|h| h.to_le_bytes())
+    .collect();
+    assert_eq!(
+        to_rgba8(Format::R16G16B16A16_FLOAT, &halves).unwrap(),
+        [0, 255, 188, 64, 0, 0, 255, 255]
+    );
+}
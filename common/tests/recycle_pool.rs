@@ -0,0 +1,108 @@
+use common::gfx::{QueueType, RecyclePool};
+
+// Stands in for a command allocator, remembering the fence value of the
+// submission that last used it.
+#[derive(Debug, PartialEq)]
+struct StubAllocator {
+    id: u32,
+    last_fence_value: u64,
+}
+
+#[test]
+fn empty_pool_asks_for_a_new_object() {
+    let mut pool: RecyclePool<StubAllocator> = RecyclePool::new();
+    assert!(pool.acquire(QueueType::Direct, 0).is_none());
+}
+
+#[test]
+fn retired_object_is_not_reused_before_its_fence_completes() {
+    let mut pool = RecyclePool::new();
+    pool.retire(
+        QueueType::Direct,
+        StubAllocator {
+            id: 0,
+            last_fence_value: 1,
+        },
+        1,
+    );
+
+    assert!(pool.acquire(QueueType::Direct, 0).is_none());
+    assert_eq!(pool.in_flight_count(QueueType::Direct), 1);
+
+    let allocator = pool.acquire(QueueType::Direct, 1).unwrap();
+    assert_eq!(allocator.id, 0);
+    assert_eq!(pool.in_flight_count(QueueType::Direct), 0);
+}
+
+#[test]
+fn queue_types_have_separate_free_lists() {
+    let mut pool = RecyclePool::new();
+    pool.retire(QueueType::Copy, "copy", 1);
+    pool.retire(QueueType::Compute, "compute", 1);
+
+    assert_eq!(pool.acquire(QueueType::Direct, 10), None);
+    assert_eq!(pool.acquire(QueueType::Compute, 10), Some("compute"));
+    assert_eq!(pool.acquire(QueueType::Copy, 10), Some("copy"));
+}
+
+#[test]
+fn several_objects_per_frame_are_recycled_together() {
+    let mut pool = RecyclePool::new();
+    for id in 0..4 {
+        pool.retire(QueueType::Direct, id, 1);
+    }
+    pool.retire(QueueType::Direct, 4, 2);
+
+    let mut recycled = Vec::new();
+    while let Some(id) = pool.acquire(QueueType::Direct, 1) {
+        recycled.push(id);
+    }
+    recycled.sort();
+
+    assert_eq!(recycled, [0, 1, 2, 3]);
+    assert_eq!(pool.in_flight_count(QueueType::Direct), 1);
+}
+
+#[test]
+fn simulated_frames_with_latency_never_reuse_in_flight_allocators() {
+    const FRAME_LATENCY: u64 = 2;
+    const LISTS_PER_FRAME: usize = 3;
+
+    let mut pool = RecyclePool::new();
+    let mut next_id = 0;
+    let mut gpu_completed = 0;
+
+    for frame in 1..=100u64 {
+        // The GPU lags behind the CPU by up to FRAME_LATENCY frames.
+        gpu_completed = gpu_completed.max(frame.saturating_sub(FRAME_LATENCY));
+
+        let mut recorded = Vec::new();
+        for _ in 0..LISTS_PER_FRAME {
+            let allocator = pool
+                .acquire(QueueType::Direct, gpu_completed)
+                .unwrap_or_else(|| {
+                    next_id += 1;
+                    StubAllocator {
+                        id: next_id,
+                        last_fence_value: 0,
+                    }
+                });
+
+            assert!(
+                allocator.last_fence_value <= gpu_completed,
+                "allocator {} reused while fence {} is still in flight",
+                allocator.id,
+                allocator.last_fence_value
+            );
+            recorded.push(allocator);
+        }
+
+        for mut allocator in recorded {
+            allocator.last_fence_value = frame;
+            pool.retire(QueueType::Direct, allocator, frame);
+        }
+    }
+
+    // Only enough allocators for the frames in flight were ever created.
+    assert_eq!(next_id, FRAME_LATENCY as u32 * LISTS_PER_FRAME as u32);
+}
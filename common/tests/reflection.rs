@@ -0,0 +1,370 @@
+use common::{
+    gfx::{
+        pipeline_state::InputElement,
+        root_signature::{
+            DescriptorFlags, DescriptorRange, RootParameter, RootSignatureError,
+            RootSignatureFlags, ShaderVisibility, UNBOUNDED,
+        },
+        Format,
+    },
+    shader::{
+        reflection::{
+            input_layout, root_signature, BoundResource, ComponentType, ConstantBufferLayout,
+            ReflectionError, ResourceKind, ShaderReflection, ShaderVariable, SignatureParameter,
+        },
+        ShaderStage,
+    },
+};
+
+fn input(
+    semantic: &str,
+    index: u32,
+    component_type: ComponentType,
+    mask: u8,
+) -> SignatureParameter {
+    SignatureParameter {
+        semantic: semantic.into(),
+        semantic_index: index,
+        register: 0,
+        system_value: semantic.starts_with("SV_"),
+        component_type,
+        mask,
+    }
+}
+
+fn resource(name: &str, kind: ResourceKind, register: u32, count: u32) -> BoundResource {
+    BoundResource {
+        name: name.into(),
+        kind,
+        register,
+        count,
+        space: 0,
+    }
+}
+
+fn shader(stage: ShaderStage, bound_resources: Vec<BoundResource>) -> ShaderReflection {
+    ShaderReflection {
+        stage,
+        input_parameters: Vec::new(),
+        constant_buffers: Vec::new(),
+        bound_resources,
+    }
+}
+
+fn root_cbv(register: u32, space: u32, visibility: ShaderVisibility) -> RootParameter {
+    RootParameter::Descriptor {
+        descriptor_type: common::gfx::root_signature::DescriptorType::Cbv,
+        register,
+        space,
+        flags: DescriptorFlags::NONE,
+        visibility,
+    }
+}
+
+fn table(
+    ranges: impl IntoIterator<Item = DescriptorRange>,
+    visibility: ShaderVisibility,
+) -> RootParameter {
+    RootParameter::DescriptorTable {
+        ranges: ranges.into_iter().collect(),
+        visibility,
+    }
+}
+
+// What FXC reports for a typical mesh vertex shader.
+fn mesh_vs() -> ShaderReflection {
+    ShaderReflection {
+        stage: ShaderStage::Vertex,
+        input_parameters: vec![
+            input("POSITION", 0, ComponentType::Float32, 0b0111),
+            input("NORMAL", 0, ComponentType::Float32, 0b0111),
+            input("TEXCOORD", 0, ComponentType::Float32, 0b0011),
+            input("TEXCOORD", 1, ComponentType::Float32, 0b1111),
+            input("BLENDINDICES", 0, ComponentType::Uint32, 0b1111),
+            input("SV_InstanceID", 0, ComponentType::Uint32, 0b0001),
+        ],
+        constant_buffers: vec![ConstantBufferLayout {
+            name: "Camera".into(),
+            size: 144,
+            variables: vec![
+                ShaderVariable {
+                    name: "view_projection".into(),
+                    offset: 0,
+                    size: 64,
+                },
+                ShaderVariable {
+                    name: "world".into(),
+                    offset: 64,
+                    size: 64,
+                },
+                ShaderVariable {
+                    name: "eye".into(),
+                    offset: 128,
+                    size: 12,
+                },
+            ],
+        }],
+        bound_resources: vec![resource("Camera", ResourceKind::ConstantBuffer, 0, 1)],
+    }
+}
+
+fn mesh_ps() -> ShaderReflection {
+    shader(
+        ShaderStage::Pixel,
+        vec![
+            resource("Camera", ResourceKind::ConstantBuffer, 0, 1),
+            resource("Material", ResourceKind::ConstantBuffer, 1, 1),
+            resource("albedo", ResourceKind::Texture, 0, 1),
+            resource("normal_map", ResourceKind::Texture, 1, 1),
+            resource("shadow_map", ResourceKind::Texture, 4, 1),
+            resource("linear", ResourceKind::Sampler, 0, 1),
+        ],
+    )
+}
+
+#[test]
+fn input_layout_from_signature() {
+    let layout = input_layout(&mesh_vs()).unwrap();
+
+    assert_eq!(
+        layout,
+        [
+            InputElement::new("POSITION", Format::R32G32B32_FLOAT),
+            InputElement::new("NORMAL", Format::R32G32B32_FLOAT),
+            InputElement::new("TEXCOORD", Format::R32G32_FLOAT),
+            InputElement::new("TEXCOORD", Format::R32G32B32A32_FLOAT).index(1),
+            InputElement::new("BLENDINDICES", Format::R32G32B32A32_UINT),
+        ]
+    );
+}
+
+#[test]
+fn input_formats_by_type_and_width() {
+    let cases = [
+        (ComponentType::Float32, 0b0001, Format::R32_FLOAT),
+        (ComponentType::Float32, 0b0011, Format::R32G32_FLOAT),
+        (ComponentType::Uint32, 0b0001, Format::R32_UINT),
+        (ComponentType::Uint32, 0b0011, Format::R32G32_UINT),
+        (ComponentType::Uint32, 0b0111, Format::R32G32B32_UINT),
+        (ComponentType::Sint32, 0b0001, Format::R32_SINT),
+        (ComponentType::Sint32, 0b0011, Format::R32G32_SINT),
+        (ComponentType::Sint32, 0b0111, Format::R32G32B32_SINT),
+        (ComponentType::Sint32, 0b1111, Format::R32G32B32A32_SINT),
+        // The width comes from the highest component used.
+        (ComponentType::Float32, 0b0100, Format::R32G32B32_FLOAT),
+    ];
+
+    for (component_type, mask, format) in cases {
+        let mut vs = shader(ShaderStage::Vertex, Vec::new());
+        vs.input_parameters = vec![input("ATTR", 0, component_type, mask)];
+        assert_eq!(input_layout(&vs).unwrap()[0].format, format, "{mask:#06b}");
+    }
+}
+
+#[test]
+fn unknown_input_types_are_rejected() {
+    let mut vs = shader(ShaderStage::Vertex, Vec::new());
+    vs.input_parameters = vec![input("COLOR", 2, ComponentType::Unknown, 0b1111)];
+
+    assert_eq!(
+        input_layout(&vs),
+        Err(ReflectionError::UnsupportedInput {
+            semantic: "COLOR".into(),
+            semantic_index: 2
+        })
+    );
+}
+
+#[test]
+fn constant_buffer_lookup() {
+    let vs = mesh_vs();
+    let camera = vs.constant_buffer("Camera").unwrap();
+
+    assert_eq!(camera.size, 144);
+    assert_eq!(
+        camera.variable("eye").map(|v| (v.offset, v.size)),
+        Some((128, 12))
+    );
+    assert!(camera.variable("missing").is_none());
+    assert!(vs.constant_buffer("Material").is_none());
+    assert_eq!(
+        vs.resource("Camera").unwrap().kind,
+        ResourceKind::ConstantBuffer
+    );
+}
+
+#[test]
+fn mesh_root_signature() {
+    let desc = root_signature(&[&mesh_vs(), &mesh_ps()]).unwrap();
+
+    assert_eq!(
+        desc.flags(),
+        RootSignatureFlags::ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT
+    );
+    assert_eq!(
+        desc.parameters(),
+        [
+            root_cbv(0, 0, ShaderVisibility::All),
+            root_cbv(1, 0, ShaderVisibility::Pixel),
+            table(
+                [DescriptorRange::srv(2, 0), DescriptorRange::srv(1, 4)],
+                ShaderVisibility::Pixel
+            ),
+            table([DescriptorRange::sampler(1, 0)], ShaderVisibility::Pixel),
+        ]
+    );
+    assert!(desc.static_samplers().is_empty());
+}
+
+#[test]
+fn compute_bindings_are_visible_to_all() {
+    let cs = shader(
+        ShaderStage::Compute,
+        vec![
+            resource("Constants", ResourceKind::ConstantBuffer, 0, 1),
+            resource("input", ResourceKind::StructuredBuffer, 0, 1),
+            resource("output", ResourceKind::RwTexture, 0, 1),
+            resource("counter", ResourceKind::RwByteAddressBuffer, 1, 1),
+        ],
+    );
+
+    let desc = root_signature(&[&cs]).unwrap();
+
+    assert_eq!(desc.flags(), RootSignatureFlags::NONE);
+    assert_eq!(
+        desc.parameters(),
+        [
+            root_cbv(0, 0, ShaderVisibility::All),
+            table(
+                [DescriptorRange::srv(1, 0), DescriptorRange::uav(2, 0)],
+                ShaderVisibility::All
+            ),
+        ]
+    );
+}
+
+#[test]
+fn system_value_only_vertex_shaders_skip_the_input_assembler() {
+    let mut vs = shader(ShaderStage::Vertex, Vec::new());
+    vs.input_parameters = vec![input("SV_VertexID", 0, ComponentType::Uint32, 0b0001)];
+
+    assert!(input_layout(&vs).unwrap().is_empty());
+    assert_eq!(
+        root_signature(&[&vs]).unwrap().flags(),
+        RootSignatureFlags::NONE
+    );
+}
+
+#[test]
+fn arrays_unbounded_ranges_and_spaces() {
+    let mut bindless = resource("textures", ResourceKind::Texture, 0, UNBOUNDED);
+    bindless.space = 1;
+    let ps = shader(
+        ShaderStage::Pixel,
+        vec![
+            resource("Lights", ResourceKind::ConstantBuffer, 2, 4),
+            resource("shadow_maps", ResourceKind::Texture, 0, 4),
+            resource("gbuffer", ResourceKind::Texture, 4, 3),
+            bindless,
+        ],
+    );
+
+    let desc = root_signature(&[&ps]).unwrap();
+
+    assert_eq!(
+        desc.parameters(),
+        [table(
+            [
+                DescriptorRange::cbv(4, 2),
+                DescriptorRange::srv(7, 0),
+                DescriptorRange::srv(UNBOUNDED, 0).space(1),
+            ],
+            ShaderVisibility::Pixel
+        )]
+    );
+}
+
+#[test]
+fn overlapping_bindings_across_stages_are_merged() {
+    let vs = shader(
+        ShaderStage::Vertex,
+        vec![resource("skinning", ResourceKind::StructuredBuffer, 0, 4)],
+    );
+    let ps = shader(
+        ShaderStage::Pixel,
+        vec![
+            resource("albedo", ResourceKind::Texture, 2, 4),
+            resource("detail", ResourceKind::Texture, 8, 1),
+        ],
+    );
+
+    let desc = root_signature(&[&vs, &ps]).unwrap();
+
+    assert_eq!(
+        desc.parameters(),
+        [
+            table([DescriptorRange::srv(6, 0)], ShaderVisibility::All),
+            table([DescriptorRange::srv(1, 8)], ShaderVisibility::Pixel),
+        ]
+    );
+}
+
+#[test]
+fn unbounded_ranges_absorb_later_bindings() {
+    let vs = shader(
+        ShaderStage::Vertex,
+        vec![resource("textures", ResourceKind::Texture, 2, UNBOUNDED)],
+    );
+    let ps = shader(
+        ShaderStage::Pixel,
+        vec![resource("albedo", ResourceKind::Texture, 100, 1)],
+    );
+
+    let desc = root_signature(&[&vs, &ps]).unwrap();
+
+    assert_eq!(
+        desc.parameters(),
+        [table(
+            [DescriptorRange::srv(UNBOUNDED, 2)],
+            ShaderVisibility::All
+        )]
+    );
+}
+
+#[test]
+fn root_signature_limits_still_apply() {
+    let resources = (0..65)
+        .map(|register| resource("cb", ResourceKind::ConstantBuffer, register, 1))
+        .collect();
+    let vs = shader(ShaderStage::Vertex, resources);
+
+    assert_eq!(
+        root_signature(&[&vs]).unwrap_err(),
+        ReflectionError::RootSignature(RootSignatureError::TooLarge { dwords: 130 })
+    );
+}
+
+#[test]
+fn resource_kinds_map_to_descriptor_types() {
+    use common::gfx::root_signature::DescriptorType::{Cbv, Sampler, Srv, Uav};
+
+    let expected = [
+        (ResourceKind::ConstantBuffer, Cbv),
+        (ResourceKind::TextureBuffer, Srv),
+        (ResourceKind::Texture, Srv),
+        (ResourceKind::Sampler, Sampler),
+        (ResourceKind::StructuredBuffer, Srv),
+        (ResourceKind::ByteAddressBuffer, Srv),
+        (ResourceKind::AccelerationStructure, Srv),
+        (ResourceKind::RwTexture, Uav),
+        (ResourceKind::RwStructuredBuffer, Uav),
+        (ResourceKind::RwByteAddressBuffer, Uav),
+        (ResourceKind::AppendStructuredBuffer, Uav),
+        (ResourceKind::ConsumeStructuredBuffer, Uav),
+        (ResourceKind::RwStructuredBufferWithCounter, Uav),
+        (ResourceKind::FeedbackTexture, Uav),
+    ];
+    for (kind, descriptor_type) in expected {
+        assert_eq!(kind.descriptor_type(), descriptor_type, "{kind:?}");
+    }
+}
@@ -0,0 +1,185 @@
+mod support;
+
+use std::collections::VecDeque;
+
+use common::gfx::{
+    RingAllocation, RingAllocator, CONSTANT_BUFFER_ALIGNMENT, TEXTURE_PLACEMENT_ALIGNMENT,
+};
+use support::Rng;
+
+#[test]
+fn allocations_are_aligned() {
+    let mut ring = RingAllocator::new(4096);
+
+    let a = ring.allocate(10, 1).unwrap();
+    let b = ring.allocate(100, CONSTANT_BUFFER_ALIGNMENT).unwrap();
+    let c = ring.allocate(1, TEXTURE_PLACEMENT_ALIGNMENT).unwrap();
+
+    assert_eq!(
+        a,
+        RingAllocation {
+            offset: 0,
+            size: 10
+        }
+    );
+    assert_eq!(
+        b,
+        RingAllocation {
+            offset: 256,
+            size: 100
+        }
+    );
+    assert_eq!(
+        c,
+        RingAllocation {
+            offset: 512,
+            size: 1
+        }
+    );
+    assert_eq!(ring.used(), 513);
+}
+
+#[test]
+fn allocation_that_does_not_fit_before_the_end_wraps() {
+    let mut ring = RingAllocator::new(1024);
+
+    ring.allocate(768, 256).unwrap();
+    ring.finish_frame(1);
+    ring.reclaim(1);
+
+    let wrapped = ring.allocate(512, 256).unwrap();
+    assert_eq!(wrapped.offset, 0);
+    // The 256 bytes skipped at the end stay in use until the frame retires.
+    assert_eq!(ring.used(), 768);
+}
+
+#[test]
+fn full_ring_refuses_until_frame_completes() {
+    let mut ring = RingAllocator::new(1024);
+
+    ring.allocate(1024, 256).unwrap();
+    assert_eq!(ring.allocate(1, 1), None);
+
+    ring.finish_frame(1);
+    ring.reclaim(0);
+    assert_eq!(ring.allocate(1, 1), None);
+
+    ring.reclaim(1);
+    assert_eq!(ring.used(), 0);
+    assert_eq!(
+        ring.allocate(1, 1),
+        Some(RingAllocation { offset: 0, size: 1 })
+    );
+}
+
+#[test]
+fn oversized_allocation_fails() {
+    let mut ring = RingAllocator::new(512);
+    assert_eq!(ring.allocate(513, 1), None);
+    assert_eq!(ring.used(), 0);
+}
+
+#[test]
+fn reclaim_only_releases_completed_frames() {
+    let mut ring = RingAllocator::new(4096);
+
+    ring.allocate(256, 256).unwrap();
+    ring.finish_frame(1);
+    ring.allocate(512, 256).unwrap();
+    ring.finish_frame(2);
+    ring.allocate(1024, 256).unwrap();
+
+    ring.reclaim(1);
+    assert_eq!(ring.used(), 1536);
+    assert_eq!(ring.frames_in_flight(), 1);
+
+    ring.reclaim(5);
+    // The current frame has not been submitted, so it cannot be reclaimed.
+    assert_eq!(ring.used(), 1024);
+    assert_eq!(ring.frames_in_flight(), 0);
+}
+
+fn overlaps(a: &RingAllocation, b: &RingAllocation) -> bool {
+    a.offset < b.offset + b.size && b.offset < a.offset + a.size
+}
+
+#[test]
+fn random_frames_never_overlap_live_allocations() {
+    const ALIGNMENTS: [u64; 4] = [1, 4, CONSTANT_BUFFER_ALIGNMENT, TEXTURE_PLACEMENT_ALIGNMENT];
+
+    for seed in 1..=64 {
+        let mut rng = Rng::new(seed);
+        let capacity = rng.range(1, 32) * TEXTURE_PLACEMENT_ALIGNMENT;
+        let mut ring = RingAllocator::new(capacity);
+
+        // Frames submitted to the "GPU" together with their allocations.
+        let mut in_flight: VecDeque<(u64, Vec<RingAllocation>)> = VecDeque::new();
+        let mut current = Vec::new();
+        let mut fence_value = 0;
+        let mut completed = 0;
+
+        for _ in 0..2000 {
+            if rng.chance(1, 8) {
+                fence_value += 1;
+                ring.finish_frame(fence_value);
+                in_flight.push_back((fence_value, std::mem::take(&mut current)));
+            }
+
+            if rng.chance(1, 6) && completed < fence_value {
+                completed = rng.range(completed, fence_value + 1);
+                ring.reclaim(completed);
+                while in_flight.front().is_some_and(|(f, _)| *f <= completed) {
+                    in_flight.pop_front();
+                }
+            }
+
+            let size = rng.range(0, capacity / 2);
+            let alignment = *rng.pick(&ALIGNMENTS);
+
+            let live_bytes: u64 = in_flight
+                .iter()
+                .flat_map(|(_, allocations)| allocations)
+                .chain(current.iter())
+                .map(|a| a.size)
+                .sum();
+            assert!(live_bytes <= ring.used());
+            assert!(ring.used() <= capacity);
+
+            let Some(allocation) = ring.allocate(size, alignment) else {
+                continue;
+            };
+
+            assert_eq!(allocation.offset % alignment, 0);
+            assert!(allocation.offset + allocation.size <= capacity);
+
+            for live in in_flight
+                .iter()
+                .flat_map(|(_, allocations)| allocations)
+                .chain(current.iter())
+            {
+                assert!(
+                    allocation.size == 0 || live.size == 0 || !overlaps(&allocation, live),
+                    "seed {seed}: {allocation:?} overlaps {live:?}"
+                );
+            }
+
+            current.push(allocation);
+        }
+    }
+}
+
+#[test]
+fn everything_is_reclaimed_once_all_frames_complete() {
+    let mut rng = Rng::new(7);
+    let mut ring = RingAllocator::new(8192);
+
+    for frame in 1..=500 {
+        while ring.allocate(rng.range(1, 700), 256).is_some() && rng.chance(3, 4) {}
+        ring.finish_frame(frame);
+        ring.reclaim(frame.saturating_sub(2));
+    }
+
+    ring.reclaim(500);
+    assert_eq!(ring.used(), 0);
+    assert_eq!(ring.available(), 8192);
+}
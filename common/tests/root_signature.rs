@@ -0,0 +1,332 @@
+use common::gfx::root_signature::{
+    AddressMode, ComparisonFunc, DescriptorFlags, DescriptorRange, DescriptorType, Filter,
+    RootParameter, RootSignatureBuilder, RootSignatureError, RootSignatureFlags, ShaderVisibility,
+    StaticSampler, MAX_ROOT_SIGNATURE_DWORDS, UNBOUNDED,
+};
+
+#[test]
+fn empty_root_signature() {
+    let desc = RootSignatureBuilder::new().build().unwrap();
+
+    assert_eq!(desc.dword_cost(), 0);
+    assert_eq!(desc.to_hlsl(), "");
+    assert_eq!(desc.to_hlsl_define("RS"), "#define RS \"\"\n");
+}
+
+#[test]
+fn dword_costs() {
+    let desc = RootSignatureBuilder::new()
+        .constants(4, 0, ShaderVisibility::All)
+        .cbv(1, ShaderVisibility::All)
+        .srv(0, ShaderVisibility::All)
+        .uav(0, ShaderVisibility::All)
+        .descriptor_table([DescriptorRange::srv(8, 1)], ShaderVisibility::Pixel)
+        .static_sampler(StaticSampler::new(0))
+        .build()
+        .unwrap();
+
+    // Static samplers are free.
+    assert_eq!(desc.dword_cost(), 4 + 2 + 2 + 2 + 1);
+}
+
+#[test]
+fn exactly_64_dwords_is_allowed() {
+    let desc = RootSignatureBuilder::new()
+        .constants(60, 0, ShaderVisibility::All)
+        .cbv(1, ShaderVisibility::All)
+        .descriptor_table([DescriptorRange::srv(1, 0)], ShaderVisibility::All)
+        .descriptor_table([DescriptorRange::uav(1, 0)], ShaderVisibility::All)
+        .build()
+        .unwrap();
+
+    assert_eq!(desc.dword_cost(), MAX_ROOT_SIGNATURE_DWORDS);
+}
+
+#[test]
+fn more_than_64_dwords_is_rejected() {
+    let result = RootSignatureBuilder::new()
+        .constants(61, 0, ShaderVisibility::All)
+        .cbv(1, ShaderVisibility::All)
+        .srv(0, ShaderVisibility::All)
+        .build();
+
+    assert_eq!(
+        result.unwrap_err(),
+        RootSignatureError::TooLarge { dwords: 65 }
+    );
+}
+
+#[test]
+fn one_root_descriptor_too_many() {
+    let mut builder = RootSignatureBuilder::new();
+    for register in 0..32 {
+        builder = builder.cbv(register, ShaderVisibility::All);
+    }
+    assert!(builder.clone().build().is_ok());
+
+    let result = builder.constants(1, 32, ShaderVisibility::All).build();
+    assert_eq!(
+        result.unwrap_err(),
+        RootSignatureError::TooLarge { dwords: 65 }
+    );
+}
+
+#[test]
+fn empty_tables_and_ranges_are_rejected() {
+    let empty_table = RootSignatureBuilder::new()
+        .cbv(0, ShaderVisibility::All)
+        .descriptor_table([], ShaderVisibility::All)
+        .build();
+    assert_eq!(
+        empty_table.unwrap_err(),
+        RootSignatureError::EmptyDescriptorTable { parameter: 1 }
+    );
+
+    let empty_range = RootSignatureBuilder::new()
+        .descriptor_table(
+            [DescriptorRange::srv(1, 0), DescriptorRange::srv(0, 1)],
+            ShaderVisibility::All,
+        )
+        .build();
+    assert_eq!(
+        empty_range.unwrap_err(),
+        RootSignatureError::EmptyRange {
+            parameter: 0,
+            range: 1
+        }
+    );
+}
+
+#[test]
+fn samplers_cannot_share_a_table_with_other_descriptors() {
+    let result = RootSignatureBuilder::new()
+        .descriptor_table(
+            [DescriptorRange::srv(1, 0), DescriptorRange::sampler(1, 0)],
+            ShaderVisibility::All,
+        )
+        .build();
+
+    assert_eq!(
+        result.unwrap_err(),
+        RootSignatureError::MixedSamplerTable { parameter: 0 }
+    );
+}
+
+#[test]
+fn samplers_cannot_be_root_descriptors() {
+    let result = RootSignatureBuilder::new()
+        .parameter(RootParameter::Descriptor {
+            descriptor_type: DescriptorType::Sampler,
+            register: 0,
+            space: 0,
+            flags: DescriptorFlags::NONE,
+            visibility: ShaderVisibility::All,
+        })
+        .build();
+
+    assert_eq!(
+        result.unwrap_err(),
+        RootSignatureError::SamplerRootDescriptor { parameter: 0 }
+    );
+}
+
+#[test]
+fn invalid_flags_are_rejected() {
+    let volatile_root_descriptor = RootSignatureBuilder::new()
+        .parameter(RootParameter::Descriptor {
+            descriptor_type: DescriptorType::Cbv,
+            register: 0,
+            space: 0,
+            flags: DescriptorFlags::DESCRIPTORS_VOLATILE,
+            visibility: ShaderVisibility::All,
+        })
+        .build();
+    assert_eq!(
+        volatile_root_descriptor.unwrap_err(),
+        RootSignatureError::InvalidFlags { parameter: 0 }
+    );
+
+    let conflicting_data_flags = RootSignatureBuilder::new()
+        .descriptor_table(
+            [DescriptorRange::srv(1, 0)
+                .flags(DescriptorFlags::DATA_STATIC | DescriptorFlags::DATA_VOLATILE)],
+            ShaderVisibility::All,
+        )
+        .build();
+    assert_eq!(
+        conflicting_data_flags.unwrap_err(),
+        RootSignatureError::InvalidFlags { parameter: 0 }
+    );
+
+    let sampler_data_flags = RootSignatureBuilder::new()
+        .descriptor_table(
+            [DescriptorRange::sampler(1, 0).flags(DescriptorFlags::DATA_STATIC)],
+            ShaderVisibility::All,
+        )
+        .build();
+    assert_eq!(
+        sampler_data_flags.unwrap_err(),
+        RootSignatureError::InvalidFlags { parameter: 0 }
+    );
+
+    let volatile_sampler = RootSignatureBuilder::new()
+        .descriptor_table(
+            [DescriptorRange::sampler(1, 0).flags(DescriptorFlags::DESCRIPTORS_VOLATILE)],
+            ShaderVisibility::All,
+        )
+        .build();
+    assert!(volatile_sampler.is_ok());
+}
+
+#[test]
+fn overlapping_registers_are_rejected() {
+    let result = RootSignatureBuilder::new()
+        .srv(3, ShaderVisibility::All)
+        .descriptor_table([DescriptorRange::srv(4, 0)], ShaderVisibility::Pixel)
+        .build();
+
+    assert_eq!(
+        result.unwrap_err(),
+        RootSignatureError::OverlappingRegisters {
+            register: "t3 space0".into()
+        }
+    );
+}
+
+#[test]
+fn unbounded_ranges_cover_every_later_register() {
+    let result = RootSignatureBuilder::new()
+        .descriptor_table([DescriptorRange::srv(UNBOUNDED, 10)], ShaderVisibility::All)
+        .srv(1000, ShaderVisibility::All)
+        .build();
+
+    assert!(matches!(
+        result,
+        Err(RootSignatureError::OverlappingRegisters { .. })
+    ));
+}
+
+#[test]
+fn same_register_is_fine_in_other_spaces_types_or_stages() {
+    let desc = RootSignatureBuilder::new()
+        .cbv(0, ShaderVisibility::Vertex)
+        .cbv(0, ShaderVisibility::Pixel)
+        .srv(0, ShaderVisibility::All)
+        .uav(0, ShaderVisibility::All)
+        .descriptor_table([DescriptorRange::srv(2, 0).space(1)], ShaderVisibility::All)
+        .static_sampler(StaticSampler::new(0))
+        .static_sampler(StaticSampler::new(0).space(1))
+        .build();
+
+    assert!(desc.is_ok());
+}
+
+#[test]
+fn static_samplers_overlapping_sampler_tables_are_rejected() {
+    let result = RootSignatureBuilder::new()
+        .descriptor_table([DescriptorRange::sampler(2, 0)], ShaderVisibility::All)
+        .static_sampler(StaticSampler::new(1))
+        .build();
+
+    assert_eq!(
+        result.unwrap_err(),
+        RootSignatureError::OverlappingRegisters {
+            register: "s1 space0".into()
+        }
+    );
+}
+
+#[test]
+fn version_1_1_is_only_needed_for_flags() {
+    let plain = RootSignatureBuilder::new()
+        .cbv(0, ShaderVisibility::All)
+        .descriptor_table([DescriptorRange::srv(1, 0)], ShaderVisibility::All)
+        .build()
+        .unwrap();
+    assert!(!plain.requires_version_1_1());
+
+    let flagged = RootSignatureBuilder::new()
+        .descriptor_table(
+            [DescriptorRange::srv(1, 0).flags(DescriptorFlags::DATA_STATIC)],
+            ShaderVisibility::All,
+        )
+        .build()
+        .unwrap();
+    assert!(flagged.requires_version_1_1());
+}
+
+#[test]
+fn hlsl_emission() {
+    let desc = RootSignatureBuilder::new()
+        .flags(
+            RootSignatureFlags::ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT
+                | RootSignatureFlags::DENY_HULL_SHADER_ROOT_ACCESS,
+        )
+        .constants(4, 0, ShaderVisibility::Vertex)
+        .parameter(RootParameter::Descriptor {
+            descriptor_type: DescriptorType::Cbv,
+            register: 1,
+            space: 2,
+            flags: DescriptorFlags::DATA_STATIC,
+            visibility: ShaderVisibility::All,
+        })
+        .uav(0, ShaderVisibility::All)
+        .descriptor_table(
+            [
+                DescriptorRange::srv(4, 0),
+                DescriptorRange::cbv(1, 2).offset(8),
+                DescriptorRange::srv(UNBOUNDED, 0)
+                    .space(1)
+                    .flags(DescriptorFlags::DESCRIPTORS_VOLATILE | DescriptorFlags::DATA_VOLATILE),
+            ],
+            ShaderVisibility::Pixel,
+        )
+        .descriptor_table([DescriptorRange::sampler(2, 0)], ShaderVisibility::Pixel)
+        .static_sampler(StaticSampler::new(2))
+        .static_sampler(
+            StaticSampler::new(3)
+                .filter(Filter::ComparisonLinear)
+                .address_mode(AddressMode::Clamp)
+                .comparison(ComparisonFunc::GreaterEqual)
+                .lod_range(0.0, 4.5)
+                .visibility(ShaderVisibility::Pixel),
+        )
+        .build()
+        .unwrap();
+
+    let expected = [
+        "RootFlags(ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT | DENY_HULL_SHADER_ROOT_ACCESS)",
+        "RootConstants(num32BitConstants=4, b0, visibility=SHADER_VISIBILITY_VERTEX)",
+        "CBV(b1, space=2, flags=DATA_STATIC)",
+        "UAV(u0)",
+        "DescriptorTable(SRV(t0, numDescriptors=4), CBV(b2, offset=8), \
+         SRV(t0, numDescriptors=unbounded, space=1, flags=DESCRIPTORS_VOLATILE | DATA_VOLATILE), \
+         visibility=SHADER_VISIBILITY_PIXEL)",
+        "DescriptorTable(Sampler(s0, numDescriptors=2), visibility=SHADER_VISIBILITY_PIXEL)",
+        "StaticSampler(s2)",
+        "StaticSampler(s3, filter=FILTER_COMPARISON_MIN_MAG_MIP_LINEAR, \
+         addressU=TEXTURE_ADDRESS_CLAMP, addressV=TEXTURE_ADDRESS_CLAMP, \
+         addressW=TEXTURE_ADDRESS_CLAMP, comparisonFunc=COMPARISON_GREATER_EQUAL, \
+         maxLOD=4.5f, visibility=SHADER_VISIBILITY_PIXEL)",
+    ];
+
+    assert_eq!(desc.to_hlsl(), expected.join(", "));
+}
+
+#[test]
+fn hlsl_define() {
+    let desc = RootSignatureBuilder::new()
+        .flags(RootSignatureFlags::ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT)
+        .cbv(0, ShaderVisibility::All)
+        .static_sampler(StaticSampler::new(0).filter(Filter::Point))
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        desc.to_hlsl_define("MAIN_RS"),
+        "#define MAIN_RS \\\n    \
+         \"RootFlags(ALLOW_INPUT_ASSEMBLER_INPUT_LAYOUT), \" \\\n    \
+         \"CBV(b0), \" \\\n    \
+         \"StaticSampler(s0, filter=FILTER_MIN_MAG_MIP_POINT)\"\n"
+    );
+}
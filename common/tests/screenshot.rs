@@ -0,0 +1,441 @@
+use std::path::Path;
+
+use common::screenshot::{ImageFileFormat, Screenshot};
+
+fn gradient(width: u32, height: u32) -> Screenshot {
+    let mut pixels = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let checker = if (x / 4 + y / 4) % 2 == 0 { 200 } else { 10 };
+            pixels.extend_from_slice(&[(x * 7) as u8, (y * 3) as u8, checker, 255]);
+        }
+    }
+    Screenshot::new(width, height, pixels)
+}
+
+fn u32_be(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn u32_le(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+/// Reads deflate bits least significant first.
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Bits<'_> {
+    fn bit(&mut self) -> u32 {
+        let bit = (self.data[self.position / 8] >> (self.position % 8)) & 1;
+        self.position += 1;
+        bit as u32
+    }
+
+    fn bits(&mut self, count: u32) -> u32 {
+        (0..count).fold(0, |value, i| value | self.bit() << i)
+    }
+
+    fn code(&mut self, count: u32) -> u32 {
+        (0..count).fold(0, |value, _| value << 1 | self.bit())
+    }
+}
+
+/// Just enough inflate to read back what the encoder writes: one fixed
+/// Huffman block.
+fn inflate_fixed(data: &[u8]) -> Vec<u8> {
+    const LENGTH_BASES: [usize; 29] = [
+        3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+        131, 163, 195, 227, 258,
+    ];
+    const DISTANCE_BASES: [usize; 30] = [
+        1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+        2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+    ];
+    let length_extra = |code: usize| {
+        if (8..28).contains(&code) {
+            code / 4 - 1
+        } else {
+            0
+        }
+    };
+    let distance_extra = |code: usize| if code >= 4 { code / 2 - 1 } else { 0 };
+
+    let mut bits = Bits { data, position: 0 };
+    assert_eq!(bits.bits(3), 0b011, "expected one final fixed block");
+
+    let mut out: Vec<u8> = Vec::new();
+    loop {
+        // Fixed codes are 7, 8 or 9 bits long.
+        let mut code = bits.code(7);
+        let symbol = if code <= 0b0010111 {
+            code + 256
+        } else {
+            code = code << 1 | bits.bit();
+            match code {
+                0x30..=0xbf => code - 0x30,
+                0xc0..=0xc7 => code - 0xc0 + 280,
+                _ => (code << 1 | bits.bit()) - 0x190 + 144,
+            }
+        } as usize;
+
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return out,
+            _ => {
+                let code = symbol - 257;
+                let length = LENGTH_BASES[code] + bits.bits(length_extra(code) as u32) as usize;
+                let code = bits.code(5) as usize;
+                let distance =
+                    DISTANCE_BASES[code] + bits.bits(distance_extra(code) as u32) as usize;
+                for _ in 0..length {
+                    out.push(out[out.len() - distance]);
+                }
+            }
+        }
+    }
+}
+
+/// The chunks of a PNG, checking their CRCs with a bitwise CRC-32.
+fn chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let crc32 = |data: &[u8]| {
+        !data.iter().fold(!0u32, |mut crc, &byte| {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    0xedb8_8320 ^ (crc >> 1)
+                } else {
+                    crc >> 1
+                };
+            }
+            crc
+        })
+    };
+
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    let mut chunks = Vec::new();
+    let mut rest = &png[8..];
+    while !rest.is_empty() {
+        let length = u32_be(rest) as usize;
+        let (kind, data) = (&rest[4..8], &rest[8..8 + length]);
+        assert_eq!(u32_be(&rest[8 + length..]), crc32(&rest[4..8 + length]));
+        chunks.push((kind.try_into().unwrap(), data));
+        rest = &rest[12 + length..];
+    }
+    chunks
+}
+
+#[test]
+fn formats_come_from_the_extension() {
+    let format = |path: &str| ImageFileFormat::from_path(Path::new(path));
+    assert_eq!(format("shot.png"), Some(ImageFileFormat::Png));
+    assert_eq!(format("dir/SHOT.BMP"), Some(ImageFileFormat::Bmp));
+    assert_eq!(format("a.b.ppm"), Some(ImageFileFormat::Ppm));
+    assert_eq!(format("shot.jpg"), None);
+    assert_eq!(format("png"), None);
+}
+
+#[test]
+fn tiny_png_matches_reference() {
+    let image = Screenshot::new(2, 1, vec![255, 0, 0, 255, 0, 255, 0, 128]);
+    #[rustfmt::skip]
+    let expected = [
+        0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n',
+        0, 0, 0, 13, b'I', b'H', b'D', b'R', 0, 0, 0, 2, 0, 0, 0, 1, 8, 6, 0, 0, 0,
+        0xf4, 0x22, 0x7f, 0x8a,
+        0, 0, 0, 15, b'I', b'D', b'A', b'T', 0x78, 0x01, 0x63, 0xf8, 0xcf, 0x00, 0x44, 0xff,
+        0x19, 0x1a, 0x00, 0x10, 0x79, 0x03, 0x7e, 0xcb, 0x4a, 0x93, 0x8c,
+        0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82,
+    ];
+    assert_eq!(image.to_png(), expected);
+}
+
+#[test]
+fn png_round_trips() {
+    let image = gradient(37, 23);
+    let png = image.encode(ImageFileFormat::Png);
+
+    let chunks = chunks(&png);
+    let kinds: Vec<_> = chunks.iter().map(|(kind, _)| kind).collect();
+    assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+    assert_eq!(chunks[0].1, [0, 0, 0, 37, 0, 0, 0, 23, 8, 6, 0, 0, 0]);
+
+    let zlib = chunks[1].1;
+    assert_eq!(zlib[..2], [0x78, 0x01]);
+    let scanlines = inflate_fixed(&zlib[2..zlib.len() - 4]);
+
+    let mut expected = Vec::new();
+    for row in image.pixels.chunks(37 * 4) {
+        expected.push(0);
+        expected.extend_from_slice(row);
+    }
+    assert_eq!(scanlines, expected);
+
+    // Adler-32 of the uncompressed data.
+    let (a, b) = expected.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    assert_eq!(u32_be(&zlib[zlib.len() - 4..]), b << 16 | a);
+}
+
+#[test]
+fn flat_images_compress_well() {
+    let image = Screenshot::new(256, 256, [0, 51, 102, 255].repeat(256 * 256));
+    let png = image.to_png();
+    assert!(png.len() < 4096, "{} bytes", png.len());
+
+    let zlib = chunks(&png)[1].1;
+    let scanlines = inflate_fixed(&zlib[2..zlib.len() - 4]);
+    assert_eq!(scanlines.len(), 256 * (256 * 4 + 1));
+    assert!(scanlines
+        .chunks(256 * 4 + 1)
+        .all(|row| row[0] == 0 && row[1..] == image.pixels[..256 * 4]));
+}
+
+#[test]
+fn bmp_is_bottom_up_bgr_with_padded_rows() {
+    let image = gradient(3, 2);
+    let bmp = image.encode(ImageFileFormat::Bmp);
+
+    // 3 pixels of 3 bytes pad to 12 per row.
+    assert_eq!(&bmp[..2], b"BM");
+    assert_eq!(u32_le(&bmp[2..]), 54 + 24);
+    assert_eq!(bmp.len(), 54 + 24);
+    assert_eq!(u32_le(&bmp[10..]), 54);
+    assert_eq!(u32_le(&bmp[14..]), 40);
+    assert_eq!((u32_le(&bmp[18..]), u32_le(&bmp[22..])), (3, 2));
+    assert_eq!(bmp[28], 24);
+    assert_eq!(u32_le(&bmp[34..]), 24);
+
+    let rows: Vec<_> = bmp[54..].chunks(12).collect();
+    let [r, g, b, _] = image.pixel(1, 1);
+    assert_eq!(rows[0][3..6], [b, g, r]);
+    let [r, g, b, _] = image.pixel(2, 0);
+    assert_eq!(rows[1][6..9], [b, g, r]);
+    assert_eq!(rows[1][9..], [0, 0, 0]);
+}
+
+#[test]
+fn ppm_is_rgb_after_a_text_header() {
+    let image = Screenshot::new(2, 1, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(image.to_ppm(), b"P6\n2 1\n255\n\x01\x02\x03\x05\x06\x07");
+}
+
+#[test]
+fn save_picks_the_format_from_the_path() {
+    let dir = std::env::temp_dir().join(format!("screenshot-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let image = gradient(5, 4);
+
+    for (name, format) in [
+        ("shot.png", ImageFileFormat::Png),
+        ("shot.bmp", ImageFileFormat::Bmp),
+        ("shot.ppm", ImageFileFormat::Ppm),
+    ] {
+        image.save(dir.join(name)).unwrap();
+        assert_eq!(std::fs::read(dir.join(name)).unwrap(), image.encode(format));
+    }
+
+    let error = image.save(dir.join("shot.tga")).unwrap_err();
+    assert!(error.to_string().contains("shot.tga"), "{error}");
+    assert!(!dir.join("shot.tga").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "expected 2x2 RGBA8 pixels")]
+fn pixel_count_must_match_the_size() {
+    Screenshot::new(2, 2, vec![0; 12]);
+}
+
+/// What the fixtures were generated from.
+fn fixture_pixel(x: u32, y: u32) -> [u8; 4] {
+    [
+        (x * 16 + y) as u8,
+        (y * 32 + x) as u8,
+        (x * y * 7) as u8,
+        255u32.wrapping_sub(x * 8 + y * 4) as u8,
+    ]
+}
+
+fn fixture(name: &str) -> Screenshot {
+    Screenshot::load(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name),
+    )
+    .unwrap()
+}
+
+fn assert_pixels(image: &Screenshot, size: (u32, u32), expected: impl Fn([u8; 4]) -> [u8; 4]) {
+    assert_eq!((image.width, image.height), size);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            assert_eq!(
+                image.pixel(x, y),
+                expected(fixture_pixel(x, y)),
+                "at {x}, {y}"
+            );
+        }
+    }
+}
+
+#[test]
+fn png_with_every_filter_and_dynamic_huffman_codes() {
+    assert_pixels(&fixture("rgba_filters.png"), (24, 20), |p| p);
+}
+
+#[test]
+fn png_with_stored_blocks_and_no_alpha() {
+    assert_pixels(&fixture("rgb_stored.png"), (16, 8), |[r, g, b, _]| {
+        [r, g, b, 255]
+    });
+}
+
+#[test]
+fn png_grey_with_alpha() {
+    assert_pixels(&fixture("grey_alpha.png"), (16, 8), |[l, _, _, a]| {
+        [l, l, l, a]
+    });
+}
+
+#[test]
+fn png_with_a_palette_and_transparency() {
+    let palette = [
+        [255, 0, 0],
+        [0, 255, 0],
+        [0, 0, 255],
+        [255, 255, 0],
+        [10, 20, 30],
+    ];
+    let alphas = [0, 128, 255, 255, 255];
+    let image = fixture("palette_4bit.png");
+    assert_eq!((image.width, image.height), (10, 3));
+    for y in 0..3 {
+        for x in 0..10 {
+            let index = ((x + y) % 5) as usize;
+            let [r, g, b] = palette[index];
+            assert_eq!(image.pixel(x, y), [r, g, b, alphas[index]], "at {x}, {y}");
+        }
+    }
+}
+
+#[test]
+fn sixteen_bit_png_keeps_the_high_byte() {
+    let image = fixture("rgb_16bit.png");
+    assert_eq!((image.width, image.height), (4, 2));
+    for y in 0..2 {
+        for x in 0..4 {
+            let channel = |c: u32| ((x * 0x1234 + y * 0x0101 + c * 0x2000) >> 8) as u8;
+            assert_eq!(image.pixel(x, y), [channel(0), channel(1), channel(2), 255]);
+        }
+    }
+}
+
+#[test]
+fn one_bit_grey_png_with_a_transparent_key() {
+    let image = fixture("grey_1bit.png");
+    assert_eq!((image.width, image.height), (11, 2));
+    for y in 0..2 {
+        for x in 0..11 {
+            let expected = if (x + y) % 2 == 1 {
+                [255; 4]
+            } else {
+                [0, 0, 0, 0]
+            };
+            assert_eq!(image.pixel(x, y), expected, "at {x}, {y}");
+        }
+    }
+}
+
+#[test]
+fn top_down_32_bit_bmp() {
+    assert_pixels(&fixture("top_down_32.bmp"), (5, 3), |[r, g, b, _]| {
+        [r, g, b, 255]
+    });
+}
+
+#[test]
+fn ppm_with_a_comment() {
+    assert_pixels(&fixture("comment.ppm"), (5, 3), |[r, g, b, _]| {
+        [r, g, b, 255]
+    });
+}
+
+#[test]
+fn encoded_images_decode_to_the_same_pixels() {
+    let image = gradient(13, 7);
+    assert_eq!(
+        Screenshot::decode(&image.to_png(), ImageFileFormat::Png).unwrap(),
+        image
+    );
+
+    // BMP and PPM drop alpha, which the gradient doesn't use.
+    for format in [ImageFileFormat::Bmp, ImageFileFormat::Ppm] {
+        assert_eq!(
+            Screenshot::decode(&image.encode(format), format).unwrap(),
+            image
+        );
+    }
+}
+
+#[test]
+fn damaged_files_are_rejected() {
+    let png = gradient(4, 4).to_png();
+    let decode = |bytes: &[u8]| {
+        Screenshot::decode(bytes, ImageFileFormat::Png)
+            .unwrap_err()
+            .0
+    };
+
+    assert_eq!(decode(b"GIF89a"), "not a PNG");
+    assert_eq!(decode(&png[..png.len() - 20]), "PNG is truncated");
+
+    let mut corrupt = png.clone();
+    corrupt[40] ^= 0xff;
+    assert_eq!(decode(&corrupt), "PNG chunk checksum doesn't match");
+
+    assert_eq!(
+        Screenshot::decode(b"P6 2 2 65535\n", ImageFileFormat::Ppm)
+            .unwrap_err()
+            .0,
+        "only 8-bit PPMs are supported"
+    );
+    assert_eq!(
+        Screenshot::decode(b"P6 2 2 255\n\0\0\0", ImageFileFormat::Ppm)
+            .unwrap_err()
+            .0,
+        "PPM is truncated"
+    );
+    assert_eq!(
+        Screenshot::decode(b"BM", ImageFileFormat::Bmp)
+            .unwrap_err()
+            .0,
+        "BMP is truncated"
+    );
+}
+
+#[test]
+fn damaged_bmp_sizes_are_rejected() {
+    let bmp = gradient(2, 2).encode(ImageFileFormat::Bmp);
+    let with_size = |width: i32, height: i32| {
+        let mut bytes = bmp.clone();
+        bytes[18..22].copy_from_slice(&width.to_le_bytes());
+        bytes[22..26].copy_from_slice(&height.to_le_bytes());
+        Screenshot::decode(&bytes, ImageFileFormat::Bmp)
+            .unwrap_err()
+            .0
+    };
+
+    assert_eq!(with_size(0, 2), "BMP is empty or too large");
+    assert_eq!(with_size(2, 0), "BMP is empty or too large");
+    assert_eq!(with_size(i32::MAX, 2), "BMP is empty or too large");
+    assert_eq!(with_size(2, i32::MIN), "BMP is empty or too large");
+    assert_eq!(with_size(-2, 2), "BMP has a negative width");
+    // Fits the limit but not the file.
+    assert_eq!(with_size(16384, -16384), "BMP is truncated");
+}
@@ -0,0 +1,666 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use common::{
+    gfx::pipeline_state::{PipelineDesc, PipelineStateBuilder},
+    shader::{
+        cache_key, expand_includes, includes, profile, scan_dependencies, CompiledShader,
+        Debouncer, DiskFileSystem, FileSystem, HotReload, MemoryFileSystem, Rebuilt,
+        ReloadablePipeline, ShaderCache, ShaderDesc, ShaderError, ShaderModel, ShaderStage,
+    },
+};
+
+fn files(entries: &[(&str, &str)]) -> MemoryFileSystem {
+    let mut fs = MemoryFileSystem::new();
+    for (path, source) in entries {
+        fs.insert(path, *source);
+    }
+    fs
+}
+
+fn paths(paths: &[&str]) -> Vec<PathBuf> {
+    paths.iter().map(PathBuf::from).collect()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("common-shader-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn profiles() {
+    let sm51 = ShaderModel::SM_5_1;
+    assert_eq!(profile(ShaderStage::Vertex, sm51).unwrap(), "vs_5_1");
+    assert_eq!(
+        profile(ShaderStage::Compute, ShaderModel::SM_6_6).unwrap(),
+        "cs_6_6"
+    );
+    assert_eq!(
+        profile(ShaderStage::Mesh, ShaderModel::SM_6_5).unwrap(),
+        "ms_6_5"
+    );
+
+    for (stage, model) in [
+        (ShaderStage::Mesh, ShaderModel::SM_6_0),
+        (ShaderStage::Pixel, ShaderModel::new(4, 0)),
+        (ShaderStage::Pixel, ShaderModel::new(5, 2)),
+    ] {
+        assert_eq!(
+            profile(stage, model),
+            Err(ShaderError::UnsupportedProfile { stage, model })
+        );
+    }
+
+    assert!(!sm51.uses_dxc());
+    assert!(ShaderModel::SM_6_0.uses_dxc());
+}
+
+#[test]
+fn includes_are_expanded_with_line_directives() {
+    let fs = files(&[
+        (
+            "shaders/main.hlsl",
+            "#include \"common.hlsli\"\nfloat4 main() { return 0; }",
+        ),
+        ("shaders/common.hlsli", "#define PI 3.14159"),
+    ]);
+
+    let expanded = expand_includes(&fs, Path::new("shaders/main.hlsl"), &[]).unwrap();
+
+    assert_eq!(
+        expanded.source,
+        "#line 1 \"shaders/main.hlsl\"\n\
+         #line 1 \"shaders/common.hlsli\"\n\
+         #define PI 3.14159\n\
+         #line 2 \"shaders/main.hlsl\"\n\
+         float4 main() { return 0; }\n"
+    );
+    assert_eq!(
+        expanded.dependencies,
+        paths(&["shaders/main.hlsl", "shaders/common.hlsli"])
+    );
+}
+
+#[test]
+fn quoted_includes_prefer_the_including_directory() {
+    let fs = files(&[
+        ("shaders/lighting/main.hlsl", "#include \"brdf.hlsli\""),
+        ("shaders/lighting/brdf.hlsli", "// local"),
+        ("shaders/include/brdf.hlsli", "// shared"),
+    ]);
+    let include_dirs = paths(&["shaders/include"]);
+
+    let expanded =
+        expand_includes(&fs, Path::new("shaders/lighting/main.hlsl"), &include_dirs).unwrap();
+
+    assert!(expanded.source.contains("// local"));
+    assert!(!expanded.source.contains("// shared"));
+}
+
+#[test]
+fn angled_includes_only_search_include_dirs() {
+    let fs = files(&[
+        ("shaders/main.hlsl", "#include <brdf.hlsli>"),
+        ("shaders/brdf.hlsli", "// local"),
+        ("shaders/include/brdf.hlsli", "// shared"),
+    ]);
+    let include_dirs = paths(&["shaders/include"]);
+
+    let expanded = expand_includes(&fs, Path::new("shaders/main.hlsl"), &include_dirs).unwrap();
+
+    assert!(expanded.source.contains("// shared"));
+    assert!(!expanded.source.contains("// local"));
+}
+
+#[test]
+fn relative_includes_are_normalized() {
+    let fs = files(&[
+        (
+            "shaders/passes/main.hlsl",
+            "#include \"../common/a.hlsli\"\n#include \"../common/b.hlsli\"",
+        ),
+        ("shaders/common/a.hlsli", "#pragma once\n// a"),
+        ("shaders/common/b.hlsli", "#include \"./a.hlsli\"\n// b"),
+    ]);
+
+    let expanded = expand_includes(&fs, Path::new("shaders/passes/main.hlsl"), &[]).unwrap();
+
+    assert_eq!(expanded.source.matches("// a").count(), 1);
+    assert_eq!(
+        expanded.dependencies,
+        paths(&[
+            "shaders/passes/main.hlsl",
+            "shaders/common/a.hlsli",
+            "shaders/common/b.hlsli",
+        ])
+    );
+}
+
+#[test]
+fn headers_without_pragma_once_are_included_every_time() {
+    let fs = files(&[
+        ("main.hlsl", "#include \"x.hlsli\"\n#include \"x.hlsli\""),
+        ("x.hlsli", "X"),
+    ]);
+
+    let expanded = expand_includes(&fs, Path::new("main.hlsl"), &[]).unwrap();
+
+    assert_eq!(expanded.source.matches("\nX\n").count(), 2);
+    assert_eq!(expanded.dependencies, paths(&["main.hlsl", "x.hlsli"]));
+}
+
+#[test]
+fn directive_spacing_and_other_lines() {
+    let fs = files(&[
+        (
+            "main.hlsl",
+            "  #  include   \"a.hlsli\"  // comment\n#pragma pack_matrix(row_major)\n#included",
+        ),
+        ("a.hlsli", "  # pragma   once\nA"),
+    ]);
+
+    let expanded = expand_includes(&fs, Path::new("main.hlsl"), &[]).unwrap();
+
+    assert!(expanded.source.contains("\nA\n"));
+    assert!(expanded.source.contains("#pragma pack_matrix(row_major)\n"));
+    assert!(expanded.source.contains("#included\n"));
+    assert!(!expanded.source.contains("pragma   once"));
+}
+
+#[test]
+fn includes_in_comments_are_skipped() {
+    let source = "#include \"a.hlsli\"\n\
+                  \t#  include <b.hlsli>\n\
+                  // #include \"commented.hlsli\"\n\
+                  /* #include \"block.hlsli\"\n\
+                  #include \"still_block.hlsli\" */\n\
+                  #include MACRO_PATH\n\
+                  #included \"no.hlsli\"\n\
+                  float4 x; /* inline */\n\
+                  #include \"c.hlsli\"";
+
+    assert_eq!(
+        includes(source),
+        [("a.hlsli", true), ("b.hlsli", false), ("c.hlsli", true)]
+    );
+}
+
+#[test]
+fn includes_in_block_comments_are_not_expanded() {
+    let fs = files(&[
+        (
+            "main.hlsl",
+            "/* old:\n#include \"missing.hlsli\"\n*/ #include \"a.hlsli\"",
+        ),
+        ("a.hlsli", "float a;"),
+    ]);
+
+    let expanded = expand_includes(&fs, Path::new("main.hlsl"), &[]).unwrap();
+    assert_eq!(expanded.dependencies, paths(&["main.hlsl", "a.hlsli"]));
+    assert!(expanded.source.contains("#include \"missing.hlsli\"\n"));
+}
+
+#[test]
+fn dependencies_are_scanned_in_discovery_order() {
+    let fs = files(&[
+        (
+            "shaders/mesh.hlsl",
+            "#include \"common.hlsli\"\n#include <lighting/brdf.hlsli>",
+        ),
+        ("shaders/common.hlsli", "#include \"math.hlsli\""),
+        ("shaders/math.hlsli", ""),
+        (
+            "shaders/lib/lighting/brdf.hlsli",
+            "#include \"../../common.hlsli\"\n#include \"../../math.hlsli\"",
+        ),
+    ]);
+
+    let dependencies = scan_dependencies(
+        &fs,
+        Path::new("shaders/mesh.hlsl"),
+        &paths(&["shaders/lib"]),
+    )
+    .unwrap();
+
+    assert_eq!(
+        dependencies,
+        paths(&[
+            "shaders/mesh.hlsl",
+            "shaders/common.hlsli",
+            "shaders/lib/lighting/brdf.hlsli",
+            "shaders/math.hlsli",
+        ])
+    );
+}
+
+#[test]
+fn scanning_tolerates_cycles_and_a_missing_root() {
+    let fs = files(&[
+        ("a.hlsl", "#include \"b.hlsli\""),
+        ("b.hlsli", "#include \"c.hlsli\""),
+        ("c.hlsli", "#include \"b.hlsli\"\n#include \"a.hlsl\""),
+    ]);
+
+    assert_eq!(
+        scan_dependencies(&fs, Path::new("a.hlsl"), &[]).unwrap(),
+        paths(&["a.hlsl", "b.hlsli", "c.hlsli"])
+    );
+    // A build script still needs to watch for the file to appear.
+    assert_eq!(
+        scan_dependencies(&fs, Path::new("missing.hlsl"), &[]).unwrap(),
+        paths(&["missing.hlsl"])
+    );
+}
+
+#[test]
+fn scanning_reports_missing_includes() {
+    let fs = files(&[
+        ("a.hlsl", "#include \"b.hlsli\""),
+        ("b.hlsli", "#include <missing.hlsli>"),
+    ]);
+
+    assert_eq!(
+        scan_dependencies(&fs, Path::new("a.hlsl"), &[]),
+        Err(ShaderError::NotFound {
+            path: "missing.hlsli".into(),
+            included_from: Some("b.hlsli".into()),
+        })
+    );
+}
+
+#[test]
+fn include_cycles_are_reported() {
+    let fs = files(&[
+        ("main.hlsl", "#include \"a.hlsli\""),
+        ("a.hlsli", "#include \"b.hlsli\""),
+        ("b.hlsli", "#include \"a.hlsli\""),
+    ]);
+
+    assert_eq!(
+        expand_includes(&fs, Path::new("main.hlsl"), &[]),
+        Err(ShaderError::IncludeCycle {
+            path: "a.hlsli".into()
+        })
+    );
+}
+
+#[test]
+fn missing_files_are_reported() {
+    let fs = files(&[("shaders/main.hlsl", "#include \"missing.hlsli\"")]);
+
+    assert_eq!(
+        expand_includes(&fs, Path::new("shaders/other.hlsl"), &[]),
+        Err(ShaderError::NotFound {
+            path: "shaders/other.hlsl".into(),
+            included_from: None,
+        })
+    );
+    assert_eq!(
+        expand_includes(&fs, Path::new("shaders/main.hlsl"), &[]),
+        Err(ShaderError::NotFound {
+            path: "missing.hlsli".into(),
+            included_from: Some("shaders/main.hlsl".into()),
+        })
+    );
+}
+
+#[test]
+fn disk_file_system_reads_relative_to_its_root() {
+    let dir = temp_dir("disk");
+    fs::create_dir_all(dir.join("include")).unwrap();
+    fs::write(dir.join("main.hlsl"), "#include <common.hlsli>").unwrap();
+    fs::write(dir.join("include/common.hlsli"), "// common").unwrap();
+    let disk = DiskFileSystem::new(&dir);
+
+    let expanded = expand_includes(&disk, Path::new("main.hlsl"), &paths(&["include"])).unwrap();
+
+    assert!(expanded.source.contains("// common"));
+    assert_eq!(
+        disk.disk_path(Path::new("include/common.hlsli")),
+        Some(dir.join("include/common.hlsli"))
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+fn desc(path: &str, entry_point: &str, stage: ShaderStage, model: ShaderModel) -> ShaderDesc {
+    ShaderDesc::new(path, entry_point, stage, model).debug(false)
+}
+
+#[test]
+fn cache_keys() {
+    let base_desc = desc("main.hlsl", "main", ShaderStage::Pixel, ShaderModel::SM_5_1);
+    let key = |desc: &ShaderDesc, source: &str, compiler: &str| {
+        cache_key(desc, source, compiler).unwrap()
+    };
+    let base = key(&base_desc, "source", "fxc");
+
+    assert_eq!(base, key(&base_desc.clone(), "source", "fxc"));
+
+    let variants = [
+        key(&base_desc, "source ", "fxc"),
+        key(&base_desc, "source", "dxc"),
+        key(
+            &base_desc.clone().define("USE_SHADOWS", "1"),
+            "source",
+            "fxc",
+        ),
+        key(&base_desc.clone().debug(true), "source", "fxc"),
+        key(
+            &desc(
+                "main.hlsl",
+                "main2",
+                ShaderStage::Pixel,
+                ShaderModel::SM_5_1,
+            ),
+            "source",
+            "fxc",
+        ),
+        key(
+            &desc(
+                "main.hlsl",
+                "main",
+                ShaderStage::Vertex,
+                ShaderModel::SM_5_1,
+            ),
+            "source",
+            "fxc",
+        ),
+        key(
+            &desc("main.hlsl", "main", ShaderStage::Pixel, ShaderModel::SM_6_0),
+            "source",
+            "fxc",
+        ),
+        key(
+            &desc(
+                "other.hlsl",
+                "main",
+                ShaderStage::Pixel,
+                ShaderModel::SM_5_1,
+            ),
+            "source",
+            "fxc",
+        ),
+    ];
+    for variant in variants {
+        assert_ne!(variant, base);
+    }
+
+    // Define order matters to the preprocessor, so it matters to the key.
+    let ab = base_desc.clone().define("A", "1").define("B", "1");
+    let ba = base_desc.clone().define("B", "1").define("A", "1");
+    assert_ne!(key(&ab, "source", "fxc"), key(&ba, "source", "fxc"));
+}
+
+#[test]
+fn included_file_changes_change_the_key() {
+    let desc = ShaderDesc::new("main.hlsl", "main", ShaderStage::Pixel, ShaderModel::SM_5_1);
+    let mut fs = files(&[("main.hlsl", "#include \"a.hlsli\""), ("a.hlsli", "// v1")]);
+    let before = expand_includes(&fs, &desc.path, &[]).unwrap();
+    fs.insert("a.hlsli", "// v2");
+    let after = expand_includes(&fs, &desc.path, &[]).unwrap();
+
+    assert_ne!(
+        cache_key(&desc, &before.source, "fxc").unwrap(),
+        cache_key(&desc, &after.source, "fxc").unwrap()
+    );
+}
+
+#[test]
+fn disk_cache_round_trip() {
+    let dir = temp_dir("cache");
+    let cache = ShaderCache::new(dir.join("nested"));
+
+    assert_eq!(cache.get(1), None);
+    cache.put(1, b"DXBC one").unwrap();
+    cache.put(2, b"DXBC two").unwrap();
+    cache.put(1, b"DXBC uno").unwrap();
+
+    assert_eq!(cache.get(1).as_deref(), Some(b"DXBC uno".as_slice()));
+    assert_eq!(cache.get(2).as_deref(), Some(b"DXBC two".as_slice()));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn concurrent_cache_writes_of_one_key_dont_collide() {
+    let dir = temp_dir("cache-race");
+    let cache = ShaderCache::new(&dir);
+
+    std::thread::scope(|scope| {
+        for i in 0..8u8 {
+            let cache = &cache;
+            scope.spawn(move || {
+                for _ in 0..50 {
+                    cache.put(7, &[i; 64]).unwrap();
+                }
+            });
+        }
+    });
+
+    let bytecode = cache.get(7).unwrap();
+    assert!(bytecode.len() == 64 && bytecode.iter().all(|&b| b == bytecode[0]));
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn debouncer_waits_for_quiet() {
+    let start = Instant::now();
+    let ms = |n| start + Duration::from_millis(n);
+    let mut debouncer = Debouncer::new(Duration::from_millis(100));
+
+    debouncer.notify("a", ms(0));
+    debouncer.notify("b", ms(20));
+    debouncer.notify("a", ms(50));
+    assert!(debouncer.ready(ms(99)).is_empty());
+    assert_eq!(debouncer.ready(ms(120)), ["b"]);
+    assert_eq!(debouncer.ready(ms(149)), Vec::<&str>::new());
+    assert_eq!(debouncer.ready(ms(150)), ["a"]);
+    assert!(debouncer.is_idle());
+}
+
+#[test]
+fn debouncer_reports_in_first_change_order() {
+    let start = Instant::now();
+    let mut debouncer = Debouncer::new(Duration::from_millis(10));
+
+    debouncer.notify(3, start);
+    debouncer.notify(1, start);
+    debouncer.notify(2, start);
+    debouncer.notify(3, start);
+
+    assert_eq!(debouncer.ready(start + Duration::from_secs(1)), [3, 1, 2]);
+}
+
+fn touch(path: &Path, seconds: u64) {
+    let file = fs::File::options().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+        .unwrap();
+}
+
+#[test]
+fn hot_reload_reports_shaders_using_changed_files() {
+    let dir = temp_dir("watch");
+    let [main, shadow, common] = ["main.hlsl", "shadow.hlsl", "common.hlsli"].map(|name| {
+        let path = dir.join(name);
+        fs::write(&path, name).unwrap();
+        touch(&path, 1);
+        path
+    });
+
+    let start = Instant::now();
+    let ms = |n| start + Duration::from_millis(n);
+    let mut reload = HotReload::new(Duration::from_millis(100));
+    reload.register("main", [main.clone(), common.clone()]);
+    reload.register("shadow", [shadow.clone(), common.clone()]);
+    assert!(reload.poll(ms(0)).is_empty());
+
+    touch(&common, 2);
+    assert!(reload.poll(ms(10)).is_empty());
+    touch(&common, 3);
+    assert!(reload.poll(ms(50)).is_empty());
+    assert_eq!(reload.poll(ms(150)), ["main", "shadow"]);
+    assert!(reload.poll(ms(300)).is_empty());
+
+    touch(&shadow, 4);
+    assert!(reload.poll(ms(400)).is_empty());
+    assert_eq!(reload.poll(ms(500)), ["shadow"]);
+
+    // An edit that drops the include stops `common` from triggering `main`.
+    reload.register("main", [main.clone()]);
+    reload.unregister(&"shadow");
+    touch(&common, 5);
+    reload.poll(ms(600));
+    assert!(reload.poll(ms(800)).is_empty());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn hot_reload_swaps_rebuilt_values_in_and_keeps_the_old_one_on_failure() {
+    let dir = temp_dir("reload");
+    let [shader, common, extra] = ["lit.hlsl", "common.hlsli", "extra.hlsli"].map(|name| {
+        let path = dir.join(name);
+        fs::write(&path, name).unwrap();
+        touch(&path, 1);
+        path
+    });
+
+    let start = Instant::now();
+    let ms = |n| start + Duration::from_millis(n);
+    let mut reload = HotReload::new(Duration::from_millis(100));
+    reload.register("lit", [shader.clone(), common.clone()]);
+    let mut live = vec![("lit", 1)];
+    reload.poll(ms(0));
+
+    // A broken edit leaves the old pipeline in place.
+    touch(&shader, 2);
+    reload.poll(ms(10));
+    let results = reload.reload(ms(200), &mut live, |_| {
+        Err::<Rebuilt<i32>, _>("syntax error")
+    });
+    assert_eq!(results, [("lit", Err("syntax error"))]);
+    assert_eq!(live, [("lit", 1)]);
+
+    // Fixing it swaps the new one in, along with its new dependencies.
+    touch(&shader, 3);
+    reload.poll(ms(300));
+    let results = reload.reload(ms(500), &mut live, |_| {
+        Ok::<_, ()>(Rebuilt {
+            value: 2,
+            dependencies: vec![shader.clone(), extra.clone()],
+        })
+    });
+    assert_eq!(results, [("lit", Ok(()))]);
+    assert_eq!(live, [("lit", 2)]);
+
+    touch(&common, 4);
+    reload.poll(ms(600));
+    assert!(reload.poll(ms(800)).is_empty());
+    touch(&extra, 5);
+    reload.poll(ms(900));
+    assert_eq!(reload.poll(ms(1100)), ["lit"]);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+fn fake_compile(shader: &ShaderDesc) -> Result<CompiledShader, ShaderError> {
+    Ok(CompiledShader {
+        bytecode: shader.entry_point.as_bytes().to_vec(),
+        dependencies: vec![shader.path.clone(), PathBuf::from("common.hlsli")],
+        key: 0,
+        from_cache: false,
+    })
+}
+
+#[test]
+fn reloadable_pipelines_put_new_bytecode_in_each_stage() {
+    let desc = PipelineStateBuilder::new()
+        .vertex_shader(b"old vs".to_vec())
+        .pixel_shader(b"old ps".to_vec())
+        .build()
+        .unwrap();
+    let pipeline = ReloadablePipeline::new(
+        desc,
+        [
+            ShaderDesc::new(
+                "mesh.hlsl",
+                "vs_main",
+                ShaderStage::Vertex,
+                ShaderModel::SM_5_1,
+            ),
+            ShaderDesc::new(
+                "lit.hlsl",
+                "ps_main",
+                ShaderStage::Pixel,
+                ShaderModel::SM_5_1,
+            ),
+        ],
+    );
+
+    let rebuilt = pipeline.recompile(fake_compile).unwrap();
+    let PipelineDesc::Graphics(graphics) = &rebuilt.value else {
+        panic!("expected a graphics pipeline");
+    };
+    assert_eq!(graphics.vs, b"vs_main");
+    assert_eq!(graphics.ps, b"ps_main");
+    assert_eq!(
+        rebuilt.dependencies,
+        paths(&["mesh.hlsl", "common.hlsli", "lit.hlsl"])
+    );
+
+    // A failed compile leaves nothing half swapped.
+    let error = ShaderError::Compile {
+        path: "lit.hlsl".into(),
+        message: "oops".into(),
+    };
+    let result = pipeline.recompile(|shader| match shader.stage {
+        ShaderStage::Pixel => Err(error.clone()),
+        _ => fake_compile(shader),
+    });
+    assert_eq!(result.unwrap_err(), error);
+}
+
+#[test]
+fn reloadable_pipelines_report_the_pipeline_they_replace() {
+    let desc = PipelineStateBuilder::new()
+        .compute_shader(b"cs".to_vec())
+        .build()
+        .unwrap();
+    let mut pipeline = ReloadablePipeline::new(desc, []);
+
+    assert_eq!(pipeline.replace_built(1), None);
+    // Bytecode that didn't change gives back the same cached pipeline.
+    assert_eq!(pipeline.replace_built(1), None);
+    assert_eq!(pipeline.replace_built(2), Some(1));
+    assert_eq!(pipeline.replace_built(3), Some(2));
+}
+
+#[test]
+fn reloadable_pipeline_rejects_a_stage_it_has_no_slot_for() {
+    let desc = PipelineStateBuilder::new()
+        .compute_shader(b"cs".to_vec())
+        .build()
+        .unwrap();
+    let pipeline = ReloadablePipeline::new(
+        desc,
+        [ShaderDesc::new(
+            "blur.hlsl",
+            "main",
+            ShaderStage::Pixel,
+            ShaderModel::SM_5_1,
+        )],
+    );
+
+    assert_eq!(
+        pipeline.recompile(fake_compile).unwrap_err(),
+        ShaderError::StageMismatch {
+            path: "blur.hlsl".into(),
+            stage: ShaderStage::Pixel
+        }
+    );
+}
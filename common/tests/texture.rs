@@ -0,0 +1,182 @@
+use common::{
+    assets::{AssetError, Footprint, Texture, TextureDesc, TextureDimension, TextureLayout},
+    gfx::Format,
+};
+
+fn with_mips(mut desc: TextureDesc, mip_levels: u32) -> TextureDesc {
+    desc.mip_levels = mip_levels;
+    desc
+}
+
+#[test]
+fn block_sizes() {
+    assert_eq!(Format::R8G8B8A8_UNORM.block_bytes(), Some(4));
+    assert_eq!(Format::R32G32B32A32_FLOAT.block_bytes(), Some(16));
+    assert_eq!(Format::R16G16B16A16_FLOAT.block_bytes(), Some(8));
+    assert_eq!(Format::B5G6R5_UNORM.block_bytes(), Some(2));
+    assert_eq!(Format::A8_UNORM.block_bytes(), Some(1));
+    assert_eq!(Format::BC1_UNORM_SRGB.block_bytes(), Some(8));
+    assert_eq!(Format::BC4_SNORM.block_bytes(), Some(8));
+    assert_eq!(Format::BC3_UNORM.block_bytes(), Some(16));
+    assert_eq!(Format::BC6H_UF16.block_bytes(), Some(16));
+    assert_eq!(Format::BC7_UNORM.block_bytes(), Some(16));
+    assert_eq!(Format::UNKNOWN.block_bytes(), None);
+
+    assert!(Format::BC5_UNORM.is_block_compressed());
+    assert!(!Format::B8G8R8A8_UNORM.is_block_compressed());
+}
+
+#[test]
+fn mip_chains() {
+    let desc = TextureDesc::texture_2d(Format::R8G8B8A8_UNORM, 300, 20);
+    assert_eq!(desc.full_mip_chain(), 9);
+    assert_eq!(desc.mip_size(3), (37, 2, 1));
+    assert_eq!(desc.mip_size(8), (1, 1, 1));
+
+    let layout = TextureLayout::new(with_mips(desc, 9)).unwrap();
+    let last = layout.subresources.last().unwrap();
+    assert_eq!((last.width, last.height, last.row_pitch), (1, 1, 4));
+    assert_eq!(
+        TextureLayout::new(with_mips(desc, 10)),
+        Err(AssetError::Invalid(
+            "a 300x20x1 texture can't have 10 mips".into()
+        ))
+    );
+}
+
+#[test]
+fn odd_sizes_round_up_to_whole_blocks() {
+    let layout = TextureLayout::new(TextureDesc::texture_2d(Format::BC1_UNORM, 5, 3)).unwrap();
+    let subresource = layout.subresources[0];
+    assert_eq!((subresource.row_pitch, subresource.rows), (16, 1));
+    assert_eq!(layout.size, 16);
+}
+
+#[test]
+fn descriptions_are_validated() {
+    let texture_2d = TextureDesc::texture_2d(Format::R8_UNORM, 4, 4);
+    let check = |desc: TextureDesc| match TextureLayout::new(desc) {
+        Err(AssetError::Invalid(message)) => message,
+        other => panic!("expected an error, got {other:?}"),
+    };
+
+    assert_eq!(
+        check(TextureDesc {
+            width: 0,
+            ..texture_2d
+        }),
+        "a 0x4x1 texture with 1 slices and 1 mips is empty"
+    );
+    assert_eq!(
+        check(TextureDesc {
+            cube: true,
+            ..texture_2d
+        }),
+        "1 array slices don't make whole cubes"
+    );
+    assert_eq!(
+        check(TextureDesc {
+            cube: true,
+            array_size: 6,
+            height: 2,
+            ..texture_2d
+        }),
+        "cube faces have to be square, not 4x2"
+    );
+    assert_eq!(
+        check(TextureDesc {
+            dimension: TextureDimension::Texture1d,
+            ..texture_2d
+        }),
+        "a 1D texture can't be 4x4x1"
+    );
+    assert_eq!(
+        check(TextureDesc {
+            dimension: TextureDimension::Texture3d,
+            array_size: 2,
+            ..texture_2d
+        }),
+        "volume textures can't be arrays or cubes"
+    );
+    assert_eq!(
+        check(TextureDesc {
+            width: 32768,
+            ..texture_2d
+        }),
+        "a 32768x4x1 texture with 1 slices is over D3D12's limits"
+    );
+    assert_eq!(
+        TextureLayout::new(TextureDesc::texture_2d(Format(66), 8, 8)),
+        Err(AssetError::UnsupportedFormat(Format(66)))
+    );
+}
+
+#[test]
+fn footprints_align_rows_and_subresources() {
+    let desc = with_mips(TextureDesc::texture_2d(Format::BC1_UNORM, 8, 8), 4);
+    let layout = TextureLayout::new(desc).unwrap();
+    let (footprints, total) = layout.copyable_footprints(0);
+
+    let footprint = |offset, size, row_size, rows| Footprint {
+        offset,
+        width: size,
+        height: size,
+        depth: 1,
+        row_pitch: 256,
+        rows,
+        row_size,
+    };
+    // The 2x2 and 1x1 mips are still a whole 4x4 block.
+    assert_eq!(
+        footprints,
+        [
+            footprint(0, 8, 16, 2),
+            footprint(512, 4, 8, 1),
+            footprint(1024, 4, 8, 1),
+            footprint(1536, 4, 8, 1),
+        ]
+    );
+    // The last row isn't padded.
+    assert_eq!(total, 1544);
+
+    let (moved, moved_total) = layout.copyable_footprints(4096);
+    assert_eq!(moved[1].offset, 4096 + 512);
+    assert_eq!(moved_total, total);
+}
+
+#[test]
+fn volume_footprints_stack_slices() {
+    let desc = TextureDesc {
+        dimension: TextureDimension::Texture3d,
+        depth: 3,
+        ..TextureDesc::texture_2d(Format::R16G16B16A16_FLOAT, 40, 2)
+    };
+    let layout = TextureLayout::new(desc).unwrap();
+    let (footprints, total) = layout.copyable_footprints(0);
+    // 320 byte rows pad to 512, and all six rows are in one footprint.
+    assert_eq!(
+        (footprints[0].row_pitch, footprints[0].row_size),
+        (512, 320)
+    );
+    assert_eq!(total, 512 * 5 + 320);
+}
+
+#[test]
+fn texture_data_has_to_match_the_layout() {
+    let layout = TextureLayout::new(TextureDesc::texture_2d(Format::R8G8_UNORM, 2, 2)).unwrap();
+    assert_eq!(
+        Texture::new(layout.clone(), vec![0; 7]),
+        Err(AssetError::Truncated {
+            expected: 8,
+            actual: 7
+        })
+    );
+    assert_eq!(
+        Texture::new(layout.clone(), vec![0; 9]),
+        Err(AssetError::Invalid(
+            "expected 8 bytes of texels, got 9".into()
+        ))
+    );
+    let texture = Texture::new(layout, (0..8).collect()).unwrap();
+    assert_eq!(texture.subresource(0), &[0, 1, 2, 3, 4, 5, 6, 7]);
+}
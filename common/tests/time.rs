@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use common::time::{
+    Clock, FakeClock, FixedTimestep, FrameStats, FrameTimer, SystemClock, DEFAULT_MAX_DELTA,
+};
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn fake_clocks_share_time() {
+    let clock = FakeClock::new();
+    let other = clock.clone();
+
+    assert_eq!(clock.now(), Duration::ZERO);
+    other.advance(ms(5));
+    clock.advance(ms(2));
+    assert_eq!(clock.now(), ms(7));
+    assert_eq!(other.now(), ms(7));
+}
+
+#[test]
+fn system_clock_moves_forward() {
+    let clock = SystemClock::new();
+    let before = clock.now();
+    std::thread::sleep(ms(2));
+
+    assert!(clock.now() >= before + ms(2));
+}
+
+#[test]
+fn ticks_measure_frames() {
+    let clock = FakeClock::new();
+    clock.advance(ms(1000));
+    let mut timer = FrameTimer::with_clock(clock.clone());
+
+    clock.advance(ms(16));
+    assert_eq!(timer.tick(), ms(16));
+    clock.advance(ms(17));
+    assert_eq!(timer.tick(), ms(17));
+    assert_eq!(timer.delta_seconds(), 0.017);
+
+    // A frame that took no time is still a frame.
+    assert_eq!(timer.tick(), Duration::ZERO);
+    assert_eq!(timer.frame_count(), 3);
+    assert_eq!(timer.elapsed(), ms(33));
+    assert_eq!(timer.clock().now(), ms(1033));
+}
+
+#[test]
+fn long_frames_are_clamped() {
+    let clock = FakeClock::new();
+    let mut timer = FrameTimer::with_clock(clock.clone());
+
+    clock.advance(ms(5000));
+    assert_eq!(timer.tick(), DEFAULT_MAX_DELTA);
+    assert_eq!(timer.delta(), DEFAULT_MAX_DELTA);
+    assert_eq!(timer.raw_delta(), ms(5000));
+    assert_eq!(timer.elapsed(), DEFAULT_MAX_DELTA);
+
+    let mut timer = FrameTimer::with_clock(clock.clone()).max_delta(ms(50));
+    clock.advance(ms(60));
+    assert_eq!(timer.tick(), ms(50));
+    clock.advance(ms(40));
+    assert_eq!(timer.tick(), ms(40));
+}
+
+#[test]
+fn fixed_steps_carry_the_remainder() {
+    let mut fixed = FixedTimestep::new(ms(10));
+
+    assert_eq!(fixed.advance(ms(25)), 2);
+    assert_eq!(fixed.alpha(), 0.5);
+    assert_eq!(fixed.advance(ms(4)), 0);
+    assert!((fixed.alpha() - 0.9).abs() < 1e-6);
+    assert_eq!(fixed.advance(ms(1)), 1);
+    assert_eq!(fixed.alpha(), 0.0);
+    assert_eq!(fixed.step_seconds(), 0.01);
+}
+
+#[test]
+fn fixed_steps_add_up_to_real_time() {
+    // 60 Hz simulation under a 144 Hz display: 7ms frames.
+    let mut fixed = FixedTimestep::from_hz(60);
+    let frames = 1440;
+    let steps: u32 = (0..frames)
+        .map(|_| fixed.advance(Duration::from_nanos(6_944_444)))
+        .sum();
+
+    assert!((599..=600).contains(&steps), "{steps} steps");
+    assert!((0.0..1.0).contains(&fixed.alpha()));
+}
+
+#[test]
+fn fixed_steps_are_capped() {
+    let mut fixed = FixedTimestep::new(ms(10)).max_steps(3);
+
+    // Half a second behind: run three steps, drop the rest of the whole
+    // steps and keep the fraction.
+    assert_eq!(fixed.advance(ms(505)), 3);
+    assert_eq!(fixed.alpha(), 0.5);
+    assert_eq!(fixed.advance(ms(5)), 1);
+}
+
+#[test]
+fn stats_over_a_window() {
+    let mut stats = FrameStats::new(4);
+    assert!(stats.is_empty());
+    assert_eq!(stats.average(), None);
+    assert_eq!(stats.percentile(50.0), None);
+    assert_eq!(stats.fps(), None);
+
+    for frame in [100, 10, 20, 30, 40] {
+        stats.push(ms(frame));
+    }
+
+    // The 100ms frame has rolled out.
+    assert_eq!(stats.len(), 4);
+    assert_eq!(stats.average(), Some(ms(25)));
+    assert_eq!(stats.min(), Some(ms(10)));
+    assert_eq!(stats.max(), Some(ms(40)));
+    assert_eq!(stats.fps(), Some(40.0));
+
+    stats.clear();
+    assert!(stats.is_empty());
+}
+
+#[test]
+fn nearest_rank_percentiles() {
+    let mut stats = FrameStats::new(100);
+    // 1..=100ms, shuffled.
+    for i in 0..100 {
+        stats.push(ms((i * 37) % 100 + 1));
+    }
+
+    assert_eq!(stats.percentile(0.0), Some(ms(1)));
+    assert_eq!(stats.percentile(1.0), Some(ms(1)));
+    assert_eq!(stats.percentile(50.0), Some(ms(50)));
+    assert_eq!(stats.percentile(50.5), Some(ms(51)));
+    assert_eq!(stats.percentile(99.0), Some(ms(99)));
+    assert_eq!(stats.percentile(100.0), Some(ms(100)));
+    assert_eq!(stats.percentile(150.0), Some(ms(100)));
+
+    let mut stats = FrameStats::new(8);
+    for frame in [15, 20, 35, 40, 50] {
+        stats.push(ms(frame));
+    }
+    assert_eq!(stats.percentile(30.0), Some(ms(20)));
+    assert_eq!(stats.percentile(40.0), Some(ms(20)));
+    assert_eq!(stats.percentile(75.0), Some(ms(40)));
+}
+
+#[test]
+fn title_text() {
+    let mut stats = FrameStats::new(3);
+    assert_eq!(stats.to_string(), "-- fps");
+
+    for frame in [16, 17, 15] {
+        stats.push(ms(frame));
+    }
+    assert_eq!(
+        stats.to_string(),
+        "62.5 fps, 16.00 ms (min 15.00, max 17.00, 99% 17.00)"
+    );
+}
+
+#[test]
+fn timer_drives_the_fixed_loop() {
+    let clock = FakeClock::new();
+    let mut timer = FrameTimer::with_clock(clock.clone());
+    let mut fixed = FixedTimestep::new(ms(20));
+    let mut stats = FrameStats::new(16);
+    let mut simulated = Duration::ZERO;
+
+    for frame in [12, 12, 12, 250, 12] {
+        clock.advance(ms(frame));
+        let delta = timer.tick();
+        stats.push(timer.raw_delta());
+        for _ in 0..fixed.advance(delta) {
+            simulated += fixed.step();
+        }
+    }
+
+    // The 250ms hitch is clamped to 100ms.
+    assert_eq!(timer.elapsed(), ms(148));
+    assert_eq!(simulated, ms(140));
+    assert!((fixed.alpha() - 0.4).abs() < 1e-6);
+    assert_eq!(stats.max(), Some(ms(250)));
+}
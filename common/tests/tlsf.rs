@@ -0,0 +1,229 @@
+mod support;
+
+use common::gfx::{TlsfAllocation, TlsfAllocator};
+use support::Rng;
+
+const KB: u64 = 1024;
+const MB: u64 = 1024 * KB;
+
+#[test]
+fn first_allocation_starts_at_zero() {
+    let mut tlsf = TlsfAllocator::new(MB, 256);
+    let a = tlsf.allocate(1000, 256).unwrap();
+
+    assert_eq!(a.offset, 0);
+    assert_eq!(a.size, 1024);
+    assert_eq!(tlsf.stats().used, 1024);
+    tlsf.validate().unwrap();
+}
+
+#[test]
+fn whole_capacity_can_be_allocated_in_one_go() {
+    let mut tlsf = TlsfAllocator::new(64 * KB, 64 * KB);
+    let a = tlsf.allocate(64 * KB, 64 * KB).unwrap();
+
+    assert_eq!(a.offset, 0);
+    assert_eq!(tlsf.allocate(1, 1), None);
+
+    tlsf.free(a);
+    assert!(tlsf.is_empty());
+    tlsf.validate().unwrap();
+}
+
+#[test]
+fn allocations_respect_alignment_larger_than_granularity() {
+    let mut tlsf = TlsfAllocator::new(16 * MB, 64 * KB);
+
+    tlsf.allocate(64 * KB, 64 * KB).unwrap();
+    let msaa = tlsf.allocate(MB, 4 * MB).unwrap();
+
+    assert_eq!(msaa.offset, 4 * MB);
+    tlsf.validate().unwrap();
+
+    // The padding in front of the aligned allocation is still usable.
+    let small = tlsf.allocate(64 * KB, 64 * KB).unwrap();
+    assert_eq!(small.offset, 64 * KB);
+}
+
+#[test]
+fn freeing_merges_neighbouring_blocks() {
+    let mut tlsf = TlsfAllocator::new(MB, 4 * KB);
+
+    let a = tlsf.allocate(256 * KB, 1).unwrap();
+    let b = tlsf.allocate(256 * KB, 1).unwrap();
+    let c = tlsf.allocate(256 * KB, 1).unwrap();
+
+    tlsf.free(a);
+    tlsf.free(c);
+    assert_eq!(tlsf.stats().free_block_count, 2);
+
+    tlsf.free(b);
+    let stats = tlsf.stats();
+    assert_eq!(stats.free_block_count, 1);
+    assert_eq!(stats.largest_free_block, MB);
+    assert_eq!(stats.fragmentation(), 0.0);
+    tlsf.validate().unwrap();
+}
+
+#[test]
+fn fragmentation_reflects_split_free_space() {
+    let mut tlsf = TlsfAllocator::new(MB, 64 * KB);
+
+    let blocks: Vec<TlsfAllocation> = (0..16)
+        .map(|_| tlsf.allocate(64 * KB, 1).unwrap())
+        .collect();
+    for block in blocks.iter().step_by(2) {
+        tlsf.free(*block);
+    }
+
+    let stats = tlsf.stats();
+    assert_eq!(stats.free(), 512 * KB);
+    assert_eq!(stats.largest_free_block, 64 * KB);
+    assert!((stats.fragmentation() - 0.875).abs() < f32::EPSILON);
+
+    // No single gap is large enough even though half the heap is free.
+    assert_eq!(tlsf.allocate(128 * KB, 1), None);
+}
+
+#[test]
+fn oversized_requests_fail_cleanly() {
+    let mut tlsf = TlsfAllocator::new(MB, 4 * KB);
+
+    assert_eq!(tlsf.allocate(MB + 1, 1), None);
+    assert_eq!(tlsf.allocate(u64::MAX, 1), None);
+
+    // Offset zero satisfies any alignment, but nothing else in the heap does.
+    let a = tlsf.allocate(4 * KB, 1 << 62).unwrap();
+    assert_eq!(a.offset, 0);
+    assert_eq!(tlsf.allocate(4 * KB, 1 << 62), None);
+
+    tlsf.free(a);
+    assert!(tlsf.is_empty());
+    tlsf.validate().unwrap();
+}
+
+#[test]
+#[should_panic(expected = "already freed")]
+fn double_free_panics() {
+    let mut tlsf = TlsfAllocator::new(MB, 4 * KB);
+    let a = tlsf.allocate(4 * KB, 1).unwrap();
+    let _b = tlsf.allocate(4 * KB, 1).unwrap();
+
+    tlsf.free(a);
+    tlsf.free(a);
+}
+
+#[test]
+#[should_panic(expected = "already freed")]
+fn double_free_after_merging_into_a_free_neighbour_panics() {
+    let mut tlsf = TlsfAllocator::new(MB, 4 * KB);
+    let a = tlsf.allocate(4 * KB, 1).unwrap();
+    let b = tlsf.allocate(4 * KB, 1).unwrap();
+    let _c = tlsf.allocate(4 * KB, 1).unwrap();
+
+    tlsf.free(a);
+    // `b` is absorbed into the free block before it.
+    tlsf.free(b);
+    tlsf.free(b);
+}
+
+#[test]
+#[should_panic(expected = "already freed")]
+fn stale_handle_to_a_reallocated_block_panics() {
+    let mut tlsf = TlsfAllocator::new(MB, 4 * KB);
+    let a = tlsf.allocate(4 * KB, 1).unwrap();
+    let _b = tlsf.allocate(4 * KB, 1).unwrap();
+
+    tlsf.free(a);
+    let again = tlsf.allocate(4 * KB, 1).unwrap();
+    assert_eq!((again.offset, again.size), (a.offset, a.size));
+    tlsf.free(a);
+}
+
+#[test]
+fn fuzz_random_allocations_and_frees() {
+    const ALIGNMENTS: [u64; 4] = [1, 4 * KB, 64 * KB, 4 * MB];
+
+    for seed in 1..=32 {
+        let mut rng = Rng::new(seed * 7919);
+        let granularity = *rng.pick(&[256, 4 * KB, 64 * KB]);
+        let capacity = rng.range(1, 256) * 64 * KB;
+        let mut tlsf = TlsfAllocator::new(capacity, granularity);
+        let mut live: Vec<TlsfAllocation> = Vec::new();
+
+        for step in 0..3000 {
+            if !live.is_empty() && rng.chance(2, 5) {
+                let index = rng.range(0, live.len() as u64) as usize;
+                tlsf.free(live.swap_remove(index));
+            } else {
+                // Mostly small requests with the occasional large one.
+                let size = if rng.chance(1, 10) {
+                    rng.range(1, capacity + 1)
+                } else {
+                    rng.range(1, 256 * KB)
+                };
+                let alignment = *rng.pick(&ALIGNMENTS);
+
+                if let Some(a) = tlsf.allocate(size, alignment) {
+                    assert!(a.size >= size);
+                    assert_eq!(a.offset % alignment.max(granularity), 0);
+                    assert_eq!(a.size % granularity, 0);
+                    assert!(a.offset + a.size <= capacity);
+
+                    for other in &live {
+                        assert!(
+                            a.offset + a.size <= other.offset
+                                || other.offset + other.size <= a.offset,
+                            "seed {seed} step {step}: {a:?} overlaps {other:?}"
+                        );
+                    }
+
+                    live.push(a);
+                }
+            }
+
+            if step % 50 == 0 {
+                tlsf.validate()
+                    .unwrap_or_else(|e| panic!("seed {seed} step {step}: {e}"));
+            }
+
+            let stats = tlsf.stats();
+            assert_eq!(stats.allocation_count, live.len());
+            assert_eq!(stats.used, live.iter().map(|a| a.size).sum::<u64>());
+        }
+
+        for a in live.drain(..) {
+            tlsf.free(a);
+        }
+
+        tlsf.validate().unwrap();
+        let stats = tlsf.stats();
+        assert_eq!(stats.free_block_count, 1);
+        assert_eq!(stats.largest_free_block, capacity);
+    }
+}
+
+#[test]
+fn fuzz_allocation_succeeds_whenever_a_free_block_fits() {
+    // TLSF rounds requests up to the next size class, so only require success
+    // when there is a free block at least double the request.
+    for seed in 1..=16 {
+        let mut rng = Rng::new(seed);
+        let mut tlsf = TlsfAllocator::new(32 * MB, 64 * KB);
+        let mut live = Vec::new();
+
+        for _ in 0..2000 {
+            if !live.is_empty() && rng.chance(1, 2) {
+                let index = rng.range(0, live.len() as u64) as usize;
+                tlsf.free(live.swap_remove(index));
+            }
+
+            let size = rng.range(1, 2 * MB);
+            let largest = tlsf.stats().largest_free_block;
+            match tlsf.allocate(size, 64 * KB) {
+                Some(a) => live.push(a),
+                None => assert!(largest < 2 * size.next_multiple_of(64 * KB)),
+            }
+        }
+    }
+}
@@ -0,0 +1,278 @@
+// Compiles a sample's `shaders/` directory from its `build.rs`, so release
+// builds embed bytecode instead of compiling HLSL at startup:
+//
+// ```ignore
+// // build.rs
+// fn main() {
+//     shader_build::Builder::new("shaders").build().unwrap();
+// }
+//
+// // main.rs
+// mod shaders {
+//     include!(concat!(env!("OUT_DIR"), "/shaders.rs"));
+// }
+// ```
+//
+// `shaders/manifest.txt` lists what to compile (see `ShaderEntry`). Shader
+// model 6 profiles go through `dxc`, older ones through `fxc`; set `DXC` or
+// `FXC` to use a compiler that isn't on the `PATH`. Includes are found the
+// same way `common::shader` finds them for hot reload. `tests/fixtures/shaders`
+// is a small example.
+
+mod manifest;
+
+pub use manifest::{parse_manifest, ManifestError, Permutation, ShaderEntry};
+
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
+
+use common::{
+    hash::stable_hash,
+    shader::{scan_dependencies, DiskFileSystem},
+};
+
+pub const MANIFEST: &str = "manifest.txt";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compiler {
+    Fxc,
+    Dxc,
+}
+
+impl Compiler {
+    pub fn for_profile(profile: &str) -> Self {
+        let major = profile.split('_').nth(1).and_then(|m| m.parse::<u8>().ok());
+        if major.is_some_and(|major| major >= 6) {
+            Compiler::Dxc
+        } else {
+            Compiler::Fxc
+        }
+    }
+
+    fn executable(self) -> String {
+        let (variable, default) = match self {
+            Compiler::Fxc => ("FXC", "fxc"),
+            Compiler::Dxc => ("DXC", "dxc"),
+        };
+        println!("cargo:rerun-if-env-changed={variable}");
+        std::env::var(variable).unwrap_or_else(|_| default.into())
+    }
+}
+
+/// The compiler arguments for one permutation, without the executable.
+pub fn command_line(
+    permutation: &Permutation,
+    source: &Path,
+    output: &Path,
+    include_dirs: &[PathBuf],
+    debug: bool,
+) -> Vec<String> {
+    let flag = |name: &str| match Compiler::for_profile(&permutation.profile) {
+        Compiler::Fxc => format!("/{name}"),
+        Compiler::Dxc => format!("-{name}"),
+    };
+
+    let mut args = vec![
+        flag("nologo"),
+        flag("T"),
+        permutation.profile.clone(),
+        flag("E"),
+        permutation.entry_point.clone(),
+    ];
+    for (name, value) in &permutation.defines {
+        args.push(flag("D"));
+        args.push(format!("{name}={value}"));
+    }
+    for dir in include_dirs {
+        args.push(flag("I"));
+        args.push(dir.display().to_string());
+    }
+    if debug {
+        args.extend([flag("Zi"), flag("Od")]);
+    } else {
+        args.push(flag("O3"));
+    }
+    args.extend([flag("Fo"), output.display().to_string()]);
+    args.push(source.display().to_string());
+    args
+}
+
+/// Whether an output must be rebuilt: it's missing, was built with other
+/// arguments, or a dependency has changed since.
+pub fn needs_rebuild(
+    output_modified: Option<SystemTime>,
+    stamp_matches: bool,
+    dependencies_modified: impl IntoIterator<Item = Option<SystemTime>>,
+) -> bool {
+    let Some(output_modified) = output_modified else {
+        return true;
+    };
+    !stamp_matches
+        || dependencies_modified
+            .into_iter()
+            .any(|modified| modified.is_none_or(|modified| modified > output_modified))
+}
+
+/// The Rust source that embeds the compiled permutations.
+pub fn generate_module(entries: &[ShaderEntry], output_dir: &Path) -> String {
+    let mut module = String::from("// Generated by shader_build. Do not edit.\n\n");
+    for entry in entries {
+        let permutations = entry.permutations();
+        for permutation in &permutations {
+            let output = output_dir.join(output_name(permutation));
+            module.push_str(&format!(
+                "pub const {}: &[u8] = include_bytes!({:?});\n",
+                permutation.const_name,
+                output.display().to_string()
+            ));
+        }
+
+        if !entry.axes.is_empty() {
+            module.push_str(&format!(
+                "pub const {}_PERMUTATIONS: &[(&[(&str, &str)], &[u8])] = &[\n",
+                entry.name.to_uppercase()
+            ));
+            for permutation in &permutations {
+                let axes: Vec<String> = permutation.defines[entry.defines.len()..]
+                    .iter()
+                    .map(|(name, value)| format!("({name:?}, {value:?})"))
+                    .collect();
+                module.push_str(&format!(
+                    "    (&[{}], {}),\n",
+                    axes.join(", "),
+                    permutation.const_name
+                ));
+            }
+            module.push_str("];\n");
+        }
+    }
+    module
+}
+
+fn output_name(permutation: &Permutation) -> String {
+    format!("{}.cso", permutation.const_name.to_lowercase())
+}
+
+fn stamp(executable: &str, args: &[String]) -> String {
+    // A stable hash, so the stamp doesn't change between toolchains.
+    format!("{:016x}", stable_hash(&(executable, args)))
+}
+
+pub struct Builder {
+    shader_dir: PathBuf,
+    include_dirs: Vec<PathBuf>,
+    out_dir: Option<PathBuf>,
+    debug: bool,
+}
+
+impl Builder {
+    /// `shader_dir` is relative to the crate being built.
+    pub fn new(shader_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            shader_dir: shader_dir.into(),
+            include_dirs: Vec::new(),
+            out_dir: None,
+            debug: std::env::var("PROFILE").is_ok_and(|profile| profile == "debug"),
+        }
+    }
+
+    pub fn include_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    /// Defaults to `OUT_DIR`.
+    pub fn out_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.out_dir = Some(dir.into());
+        self
+    }
+
+    /// Defaults to whether this is a debug build.
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Compiles everything in the manifest that is out of date and writes
+    /// `shaders.rs` to the output directory.
+    pub fn build(self) -> Result<(), Box<dyn Error>> {
+        let out_dir = match self.out_dir {
+            Some(dir) => dir,
+            None => PathBuf::from(std::env::var("OUT_DIR")?),
+        };
+        let output_dir = out_dir.join("shaders");
+        fs::create_dir_all(&output_dir)?;
+
+        let manifest_path = self.shader_dir.join(MANIFEST);
+        println!("cargo:rerun-if-changed={}", manifest_path.display());
+        let entries = parse_manifest(&fs::read_to_string(&manifest_path)?)
+            .map_err(|e| format!("{}: {e}", manifest_path.display()))?;
+
+        let mut include_dirs = vec![self.shader_dir.clone()];
+        include_dirs.extend(self.include_dirs.iter().cloned());
+        // Paths are used as they are, relative to the crate being built.
+        let files = DiskFileSystem::new("");
+
+        let mut watched = Vec::new();
+        for entry in &entries {
+            let source = self.shader_dir.join(&entry.path);
+            let dependencies = scan_dependencies(&files, &source, &include_dirs)?;
+            for dependency in &dependencies {
+                if !watched.contains(dependency) {
+                    println!("cargo:rerun-if-changed={}", dependency.display());
+                    watched.push(dependency.clone());
+                }
+            }
+
+            for permutation in entry.permutations() {
+                let output = output_dir.join(output_name(&permutation));
+                let stamp_path = output.with_extension("stamp");
+                let executable = Compiler::for_profile(&permutation.profile).executable();
+                let args = command_line(&permutation, &source, &output, &include_dirs, self.debug);
+                let stamp = stamp(&executable, &args);
+
+                let stamp_matches = fs::read_to_string(&stamp_path).is_ok_and(|s| s == stamp);
+                let rebuild = needs_rebuild(
+                    modified(&output),
+                    stamp_matches,
+                    dependencies.iter().map(|path| modified(path)),
+                );
+                if !rebuild {
+                    continue;
+                }
+
+                let result = Command::new(&executable)
+                    .args(&args)
+                    .output()
+                    .map_err(|e| format!("failed to run `{executable}`: {e}"))?;
+                if !result.status.success() {
+                    return Err(format!(
+                        "{} ({}) failed to compile:\n{}{}",
+                        entry.path.display(),
+                        permutation.const_name,
+                        String::from_utf8_lossy(&result.stdout),
+                        String::from_utf8_lossy(&result.stderr)
+                    )
+                    .into());
+                }
+                fs::write(&stamp_path, stamp)?;
+            }
+        }
+
+        let module = generate_module(&entries, &output_dir);
+        let module_path = out_dir.join("shaders.rs");
+        if fs::read_to_string(&module_path).ok().as_deref() != Some(module.as_str()) {
+            fs::write(module_path, module)?;
+        }
+        Ok(())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,200 @@
+use std::{collections::HashSet, fmt, path::PathBuf};
+
+/// One line of the manifest:
+///
+/// ```text
+/// # name   file        entry    profile  defines...
+/// mesh_vs  mesh.hlsl   vs_main  vs_5_1
+/// mesh_ps  mesh.hlsl   ps_main  ps_6_0   ALPHA_TEST=1 SHADOWS={0,1}
+/// ```
+///
+/// A define with a `{a,b,...}` value is a permutation axis: the shader is
+/// compiled once for every combination of axis values.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub entry_point: String,
+    pub profile: String,
+    pub defines: Vec<(String, String)>,
+    pub axes: Vec<(String, Vec<String>)>,
+}
+
+/// One compile of a manifest entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Permutation {
+    /// The constant the bytecode is embedded as, e.g. `MESH_PS_SHADOWS_1`.
+    pub const_name: String,
+    pub path: PathBuf,
+    pub entry_point: String,
+    pub profile: String,
+    /// The entry's fixed defines followed by one value per axis.
+    pub defines: Vec<(String, String)>,
+}
+
+impl ShaderEntry {
+    /// Every combination of axis values, the last axis changing fastest.
+    pub fn permutations(&self) -> Vec<Permutation> {
+        let mut combinations: Vec<Vec<(String, String)>> = vec![Vec::new()];
+        for (name, values) in &self.axes {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((name.clone(), value.clone()));
+                        combination
+                    })
+                })
+                .collect();
+        }
+
+        combinations
+            .into_iter()
+            .map(|axes| {
+                let mut const_name = self.name.to_uppercase();
+                for (name, value) in &axes {
+                    const_name.push('_');
+                    const_name.push_str(&const_case(name));
+                    const_name.push('_');
+                    const_name.push_str(&const_case(value));
+                }
+
+                Permutation {
+                    const_name,
+                    path: self.path.clone(),
+                    entry_point: self.entry_point.clone(),
+                    profile: self.profile.clone(),
+                    defines: self.defines.iter().cloned().chain(axes).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+fn const_case(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "manifest line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+pub fn parse_manifest(text: &str) -> Result<Vec<ShaderEntry>, ManifestError> {
+    let mut entries: Vec<ShaderEntry> = Vec::new();
+    let mut const_names = HashSet::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| ManifestError {
+            line: index + 1,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or("");
+        let mut fields = line.split_whitespace();
+        let Some(name) = fields.next() else {
+            continue;
+        };
+        let (Some(path), Some(entry_point), Some(profile)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(error(
+                "expected `name file entry profile [defines...]`".into(),
+            ));
+        };
+
+        if !is_identifier(name) {
+            return Err(error(format!("`{name}` is not a valid shader name")));
+        }
+        if entries.iter().any(|entry| entry.name == name) {
+            return Err(error(format!("`{name}` is declared twice")));
+        }
+        if !is_identifier(entry_point) {
+            return Err(error(format!("`{entry_point}` is not a valid entry point")));
+        }
+        if !is_profile(profile) {
+            return Err(error(format!("`{profile}` is not a shader profile")));
+        }
+
+        let mut entry = ShaderEntry {
+            name: name.into(),
+            path: path.into(),
+            entry_point: entry_point.into(),
+            profile: profile.into(),
+            defines: Vec::new(),
+            axes: Vec::new(),
+        };
+
+        for define in fields {
+            let (define_name, value) = define.split_once('=').unwrap_or((define, "1"));
+            if !is_identifier(define_name) {
+                return Err(error(format!("`{define_name}` is not a valid define")));
+            }
+            let declared = entry.defines.iter().map(|(n, _)| n);
+            if declared
+                .chain(entry.axes.iter().map(|(n, _)| n))
+                .any(|n| n == define_name)
+            {
+                return Err(error(format!("`{define_name}` is defined twice")));
+            }
+
+            match value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) {
+                Some(values) => {
+                    let values: Vec<String> = values.split(',').map(|v| v.trim().into()).collect();
+                    if values.iter().any(String::is_empty) {
+                        return Err(error(format!("`{define_name}` has an empty value")));
+                    }
+                    entry.axes.push((define_name.into(), values));
+                }
+                None => entry.defines.push((define_name.into(), value.into())),
+            }
+        }
+
+        for permutation in entry.permutations() {
+            if !const_names.insert(permutation.const_name.clone()) {
+                return Err(error(format!(
+                    "`{}` is generated more than once",
+                    permutation.const_name
+                )));
+            }
+        }
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_profile(s: &str) -> bool {
+    let parts: Vec<&str> = s.split('_').collect();
+    matches!(
+        parts[..],
+        ["vs" | "ps" | "gs" | "hs" | "ds" | "cs" | "as" | "ms" | "lib", major, minor]
+            if major.parse::<u8>().is_ok() && minor.parse::<u8>().is_ok()
+    )
+}
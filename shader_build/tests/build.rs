@@ -0,0 +1,247 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use shader_build::{command_line, generate_module, needs_rebuild, parse_manifest, Compiler};
+
+fn time(seconds: u64) -> Option<SystemTime> {
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+#[test]
+fn compiler_by_profile() {
+    assert_eq!(Compiler::for_profile("vs_5_0"), Compiler::Fxc);
+    assert_eq!(Compiler::for_profile("ps_5_1"), Compiler::Fxc);
+    assert_eq!(Compiler::for_profile("ps_6_0"), Compiler::Dxc);
+    assert_eq!(Compiler::for_profile("ms_6_5"), Compiler::Dxc);
+    assert_eq!(Compiler::for_profile("lib_6_3"), Compiler::Dxc);
+}
+
+#[test]
+fn fxc_and_dxc_command_lines() {
+    let entries = parse_manifest(
+        "vs mesh.hlsl vs_main vs_5_1 SKINNED=1\n\
+         ps mesh.hlsl ps_main ps_6_0",
+    )
+    .unwrap();
+    let include_dirs = [PathBuf::from("shaders"), PathBuf::from("common")];
+    let vs = &entries[0].permutations()[0];
+    let ps = &entries[1].permutations()[0];
+
+    assert_eq!(
+        command_line(vs, Path::new("shaders/mesh.hlsl"), Path::new("out/vs.cso"), &include_dirs, false)
+            .join(" "),
+        "/nologo /T vs_5_1 /E vs_main /D SKINNED=1 /I shaders /I common /O3 /Fo out/vs.cso shaders/mesh.hlsl"
+    );
+    assert_eq!(
+        command_line(
+            ps,
+            Path::new("shaders/mesh.hlsl"),
+            Path::new("out/ps.cso"),
+            &[],
+            true
+        )
+        .join(" "),
+        "-nologo -T ps_6_0 -E ps_main -Zi -Od -Fo out/ps.cso shaders/mesh.hlsl"
+    );
+}
+
+#[test]
+fn rebuild_decisions() {
+    let fresh = [time(10), time(20)];
+
+    assert!(needs_rebuild(None, true, fresh));
+    assert!(!needs_rebuild(time(30), true, fresh));
+    assert!(needs_rebuild(time(30), false, fresh));
+    assert!(needs_rebuild(time(30), true, [time(10), time(31)]));
+    // A deleted include has to be noticed by the compiler.
+    assert!(needs_rebuild(time(30), true, [time(10), None]));
+    assert!(!needs_rebuild(time(30), true, [time(30)]));
+}
+
+#[test]
+fn generated_module() {
+    let entries = parse_manifest(
+        "blit blit.hlsl main cs_5_1\n\
+         mesh_ps mesh.hlsl main ps_6_0 A=1 SHADOWS={0,1}",
+    )
+    .unwrap();
+
+    let module = generate_module(&entries, Path::new("/out/shaders"));
+
+    assert_eq!(
+        module,
+        "// Generated by shader_build. Do not edit.\n\
+         \n\
+         pub const BLIT: &[u8] = include_bytes!(\"/out/shaders/blit.cso\");\n\
+         pub const MESH_PS_SHADOWS_0: &[u8] = include_bytes!(\"/out/shaders/mesh_ps_shadows_0.cso\");\n\
+         pub const MESH_PS_SHADOWS_1: &[u8] = include_bytes!(\"/out/shaders/mesh_ps_shadows_1.cso\");\n\
+         pub const MESH_PS_PERMUTATIONS: &[(&[(&str, &str)], &[u8])] = &[\n\
+         \x20   (&[(\"SHADOWS\", \"0\")], MESH_PS_SHADOWS_0),\n\
+         \x20   (&[(\"SHADOWS\", \"1\")], MESH_PS_SHADOWS_1),\n\
+         ];\n"
+    );
+}
+
+/// Points `FXC` at a stand-in compiler that copies the source to the output
+/// and logs each output path. The guard keeps tests that build from racing
+/// over the variable.
+#[cfg(unix)]
+fn fake_fxc(dir: &Path, log: &Path) -> std::sync::MutexGuard<'static, ()> {
+    use std::{fs, os::unix::fs::PermissionsExt, sync::Mutex};
+
+    static FXC: Mutex<()> = Mutex::new(());
+    let guard = FXC.lock().unwrap_or_else(|e| e.into_inner());
+
+    let compiler = dir.join("fake-fxc");
+    fs::write(
+        &compiler,
+        format!(
+            "#!/bin/sh\n\
+             while [ \"$1\" != /Fo ]; do shift; done\n\
+             echo \"$2\" >> {log}\n\
+             cat \"$3\" > \"$2\"\n",
+            log = log.display()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&compiler, fs::Permissions::from_mode(0o755)).unwrap();
+    std::env::set_var("FXC", &compiler);
+    guard
+}
+
+/// Runs the whole build, compiling only what has changed.
+#[cfg(unix)]
+#[test]
+fn incremental_builds() {
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("shader_build-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let shaders = dir.join("shaders");
+    let out = dir.join("out");
+    fs::create_dir_all(&shaders).unwrap();
+
+    let log = dir.join("log");
+    let _fxc = fake_fxc(&dir, &log);
+
+    fs::write(
+        shaders.join("manifest.txt"),
+        "vs mesh.hlsl vs_main vs_5_1\nps mesh.hlsl ps_main ps_5_1 FOG={0,1}\nblit blit.hlsl main cs_5_1",
+    )
+    .unwrap();
+    fs::write(
+        shaders.join("mesh.hlsl"),
+        "#include \"common.hlsli\"\n// mesh",
+    )
+    .unwrap();
+    fs::write(shaders.join("common.hlsli"), "// common").unwrap();
+    fs::write(shaders.join("blit.hlsl"), "// blit").unwrap();
+
+    let build = || {
+        shader_build::Builder::new(&shaders)
+            .out_dir(&out)
+            .debug(false)
+            .build()
+            .unwrap()
+    };
+    let compiled = || {
+        let lines = fs::read_to_string(&log).unwrap_or_default();
+        fs::remove_file(&log).ok();
+        let mut names: Vec<String> = lines
+            .lines()
+            .map(|line| {
+                Path::new(line)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into()
+            })
+            .collect();
+        names.sort();
+        names
+    };
+    let edit = |name: &str, source: &str| fs::write(shaders.join(name), source).unwrap();
+
+    build();
+    assert_eq!(
+        compiled(),
+        ["blit.cso", "ps_fog_0.cso", "ps_fog_1.cso", "vs.cso"]
+    );
+    assert!(fs::read_to_string(out.join("shaders.rs"))
+        .unwrap()
+        .contains("pub const PS_PERMUTATIONS"));
+
+    build();
+    assert!(compiled().is_empty());
+
+    edit("common.hlsli", "// common v2");
+    build();
+    assert_eq!(compiled(), ["ps_fog_0.cso", "ps_fog_1.cso", "vs.cso"]);
+
+    edit("blit.hlsl", "// blit v2");
+    build();
+    assert_eq!(compiled(), ["blit.cso"]);
+    assert_eq!(
+        fs::read_to_string(out.join("shaders/blit.cso")).unwrap(),
+        "// blit v2"
+    );
+
+    // Changing the arguments rebuilds even though nothing is newer.
+    shader_build::Builder::new(&shaders)
+        .out_dir(&out)
+        .debug(true)
+        .build()
+        .unwrap();
+    assert_eq!(compiled().len(), 4);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// Builds the shaders in `tests/fixtures/shaders` the way a sample's
+/// `build.rs` would.
+#[cfg(unix)]
+#[test]
+fn fixture_shaders() {
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("shader_build-fixture-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let log = dir.join("log");
+    let _fxc = fake_fxc(&dir, &log);
+
+    let shaders = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/shaders");
+    let out = dir.join("out");
+    let build = || {
+        shader_build::Builder::new(&shaders)
+            .out_dir(&out)
+            .debug(false)
+            .build()
+            .unwrap()
+    };
+
+    build();
+    assert_eq!(fs::read_to_string(&log).unwrap().lines().count(), 2);
+    let module = fs::read_to_string(out.join("shaders.rs")).unwrap();
+    for name in ["triangle_vs", "triangle_ps"] {
+        let output = out.join("shaders").join(format!("{name}.cso"));
+        assert!(module.contains(&format!(
+            "pub const {}: &[u8] = include_bytes!({:?});",
+            name.to_uppercase(),
+            output.display().to_string()
+        )));
+        assert_eq!(
+            fs::read(&output).unwrap(),
+            fs::read(shaders.join("triangle.hlsl")).unwrap()
+        );
+    }
+
+    // Both permutations are up to date, include and all.
+    fs::remove_file(&log).unwrap();
+    build();
+    assert!(!log.exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
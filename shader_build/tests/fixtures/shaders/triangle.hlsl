@@ -0,0 +1,27 @@
+#include "triangle.hlsli"
+
+// The corners are generated from the vertex index, so no vertex buffer is
+// needed.
+VsOutput vs_main(uint vertex_id : SV_VertexID)
+{
+    const float2 positions[3] = {
+        float2(0.0, 0.5),
+        float2(0.5, -0.5),
+        float2(-0.5, -0.5),
+    };
+    const float4 colors[3] = {
+        float4(1.0, 0.0, 0.0, 1.0),
+        float4(0.0, 1.0, 0.0, 1.0),
+        float4(0.0, 0.0, 1.0, 1.0),
+    };
+
+    VsOutput output;
+    output.position = float4(positions[vertex_id], 0.0, 1.0);
+    output.color = colors[vertex_id];
+    return output;
+}
+
+float4 ps_main(VsOutput input) : SV_Target
+{
+    return input.color;
+}
@@ -0,0 +1,5 @@
+struct VsOutput
+{
+    float4 position : SV_Position;
+    float4 color : COLOR;
+};
@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+
+use shader_build::{parse_manifest, ManifestError, Permutation};
+
+fn error(line: usize, message: &str) -> ManifestError {
+    ManifestError {
+        line,
+        message: message.into(),
+    }
+}
+
+#[test]
+fn entries_and_comments() {
+    let entries = parse_manifest(
+        "# name    file       entry    profile\n\
+         \n\
+         mesh_vs   mesh.hlsl  vs_main  vs_5_1   # vertex\n\
+         mesh_ps   mesh.hlsl  ps_main  ps_6_0   ALPHA_TEST=1 FAST\n",
+    )
+    .unwrap();
+
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].name, "mesh_vs");
+    assert_eq!(entries[0].path, PathBuf::from("mesh.hlsl"));
+    assert_eq!(entries[0].entry_point, "vs_main");
+    assert_eq!(entries[0].profile, "vs_5_1");
+    assert!(entries[0].defines.is_empty());
+    assert_eq!(
+        entries[1].defines,
+        [
+            ("ALPHA_TEST".to_string(), "1".to_string()),
+            ("FAST".to_string(), "1".to_string())
+        ]
+    );
+}
+
+#[test]
+fn entries_without_axes_have_one_permutation() {
+    let entries = parse_manifest("blit cs/blit.hlsl main cs_5_1 MODE=copy").unwrap();
+
+    assert_eq!(
+        entries[0].permutations(),
+        [Permutation {
+            const_name: "BLIT".into(),
+            path: "cs/blit.hlsl".into(),
+            entry_point: "main".into(),
+            profile: "cs_5_1".into(),
+            defines: vec![("MODE".into(), "copy".into())],
+        }]
+    );
+}
+
+#[test]
+fn axes_expand_to_every_combination() {
+    let entries =
+        parse_manifest("mesh_ps mesh.hlsl main ps_6_0 A=1 SHADOWS={0,1} QUALITY={low,high,ultra}")
+            .unwrap();
+    let permutations = entries[0].permutations();
+
+    let names: Vec<&str> = permutations.iter().map(|p| p.const_name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "MESH_PS_SHADOWS_0_QUALITY_LOW",
+            "MESH_PS_SHADOWS_0_QUALITY_HIGH",
+            "MESH_PS_SHADOWS_0_QUALITY_ULTRA",
+            "MESH_PS_SHADOWS_1_QUALITY_LOW",
+            "MESH_PS_SHADOWS_1_QUALITY_HIGH",
+            "MESH_PS_SHADOWS_1_QUALITY_ULTRA",
+        ]
+    );
+
+    let defines = |p: &Permutation| {
+        p.defines
+            .iter()
+            .map(|(n, v)| format!("{n}={v}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    assert_eq!(defines(&permutations[4]), "A=1 SHADOWS=1 QUALITY=high");
+}
+
+#[test]
+fn invalid_lines_are_reported_with_line_numbers() {
+    let cases = [
+        (
+            "vs mesh.hlsl main",
+            "expected `name file entry profile [defines...]`",
+        ),
+        (
+            "2vs mesh.hlsl main vs_5_1",
+            "`2vs` is not a valid shader name",
+        ),
+        (
+            "vs mesh.hlsl main() vs_5_1",
+            "`main()` is not a valid entry point",
+        ),
+        ("vs mesh.hlsl main vs_5", "`vs_5` is not a shader profile"),
+        (
+            "vs mesh.hlsl main xs_5_1",
+            "`xs_5_1` is not a shader profile",
+        ),
+        (
+            "vs mesh.hlsl main vs_5_1 A-B=1",
+            "`A-B` is not a valid define",
+        ),
+        (
+            "vs mesh.hlsl main vs_5_1 A=1 A={0,1}",
+            "`A` is defined twice",
+        ),
+        (
+            "vs mesh.hlsl main vs_5_1 A={0,,1}",
+            "`A` has an empty value",
+        ),
+    ];
+
+    for (line, message) in cases {
+        assert_eq!(
+            parse_manifest(&format!("# header\n{line}")),
+            Err(error(2, message)),
+            "{line}"
+        );
+    }
+}
+
+#[test]
+fn duplicate_names_are_rejected() {
+    assert_eq!(
+        parse_manifest("a x.hlsl main vs_5_1\na y.hlsl main ps_5_1"),
+        Err(error(2, "`a` is declared twice"))
+    );
+
+    // Different entries can still collide once permutations are named.
+    assert_eq!(
+        parse_manifest("a x.hlsl main vs_5_1 B={1}\na_b_1 y.hlsl main ps_5_1"),
+        Err(error(2, "`A_B_1` is generated more than once"))
+    );
+}